@@ -0,0 +1,85 @@
+//! `speedkarma-cli [--json] status|test|toggle|show|export`: scripts the running instance over its local socket
+
+use isp_speedkarma::core::ipc::{self, IpcRequest, IpcResponse};
+use isp_speedkarma::core::status_codes::{self, StatusCategory};
+
+const USAGE: &str = "usage: speedkarma-cli [--json] <command>
+
+commands:
+  status                 optimization and learning state
+  test                   start a speed test
+  toggle                 switch optimization on or off
+  show                   bring the SpeedKarma window to the front
+  export [--format csv|json] [--days N] [--output PATH]
+                         write measurement history to a file";
+
+/// Registry label for `code`, or the code itself when the registry does not know it
+fn label(category: StatusCategory, code: &str) -> String {
+    status_codes::find(category, code).map(|c| c.label.to_string()).unwrap_or_else(|| code.to_string())
+}
+
+/// Human-readable rendering of a successful answer
+fn render(request: &IpcRequest, value: &serde_json::Value) -> String {
+    let text = |v: &serde_json::Value| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string());
+    match request {
+        IpcRequest::Status => {
+            let system = &value["system"];
+            // `SystemState` is its status code except for `{"error": message}`
+            let state = system["state"].as_object().and_then(|o| o.keys().next().cloned()).unwrap_or_else(|| text(&system["state"]));
+            let labels = |key: &str, category: StatusCategory| -> Vec<String> {
+                value["optimization"][key].as_array().into_iter().flatten()
+                    .filter_map(|code| code.as_str())
+                    .map(|code| label(category, code))
+                    .collect()
+            };
+            let holds = labels("holds", StatusCategory::GenerationHold);
+            let throttles = labels("throttles", StatusCategory::Throttle);
+            let mut out = format!("Optimization: {}\nState: {}\n{}", text(&value["optimization"]["mode"]), label(StatusCategory::SystemState, &state), text(&system["message"]));
+            if !holds.is_empty() {
+                out.push_str(&format!("\nHeld back: {}", holds.join(", ")));
+            }
+            if !throttles.is_empty() {
+                out.push_str(&format!("\nSlowed down: {}", throttles.join(", ")));
+            }
+            out
+        }
+        IpcRequest::Test => "Speed test started".to_string(),
+        IpcRequest::Toggle => format!("Optimization: {}", text(&value["mode"])),
+        IpcRequest::Export { .. } => format!("Exported {} measurements to {}", value["rows"], text(&value["path"])),
+        IpcRequest::Focus => "SpeedKarma window brought to the front".to_string(),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let json = args.iter().any(|a| a == "--json");
+    args.retain(|a| a != "--json");
+    if args.is_empty() || args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let request = match IpcRequest::from_args(&args) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    match ipc::request(&request).await {
+        Ok(IpcResponse::Ok(value)) if json => println!("{}", value),
+        Ok(IpcResponse::Ok(value)) => println!("{}", render(&request, &value)),
+        Ok(IpcResponse::Error(message)) => {
+            if json {
+                println!("{}", serde_json::json!({ "error": message }));
+            } else {
+                eprintln!("{}", message);
+            }
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
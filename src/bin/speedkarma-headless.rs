@@ -0,0 +1,12 @@
+//! `speedkarma-headless [--log-file <path>]`: the engine for machines without a display
+
+use isp_speedkarma::headless::{self, HeadlessOptions};
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Err(e) = headless::run(HeadlessOptions::from_args(&args)).await {
+        eprintln!("speedkarma-headless failed: {}", e);
+        std::process::exit(1);
+    }
+}
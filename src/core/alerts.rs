@@ -0,0 +1,360 @@
+use crate::core::config::SpeedAlertConfig;
+use crate::core::dataset::{self, CoverageGap};
+use crate::core::events::SharedEventSink;
+use crate::core::intelligence::without_vpn;
+use crate::data::models::{SpeedAlertEpisode, SpeedMeasurement};
+use crate::data::repository::Repository;
+use chrono::{DateTime, Duration as ChronoDuration, FixedOffset, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// Passive samples below this confidence are too noisy to raise alerts on
+const MIN_ALERT_CONFIDENCE: f64 = 0.5;
+
+/// Share of an episode's samples on weak Wi-Fi above which the alert blames the wireless link
+const WEAK_WIFI_SHARE: f64 = 0.6;
+
+/// How often new measurements are checked against the threshold
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often coverage of the suspected throttling hours is re-checked
+const COVERAGE_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+/// Window coverage is judged over, so each hour of the week occurs four times
+const COVERAGE_WINDOW_DAYS: u32 = 28;
+/// History needed before a gap counts as a habit rather than a fresh install
+const COVERAGE_MIN_HISTORY_DAYS: i64 = 14;
+/// Same advice is not repeated sooner than this
+const COVERAGE_NOTIFY_COOLDOWN_DAYS: i64 = 3;
+/// Stretches named in one notification
+const COVERAGE_LISTED_GAPS: usize = 2;
+/// Longest alert-episode history the panel can ask for, the most history the app keeps
+pub const MAX_EPISODE_HISTORY_DAYS: u32 = 365;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertTransition {
+    /// Speed has stayed below the threshold long enough to alert
+    Triggered,
+    /// Another low sample while the alert is active
+    Updated,
+    /// Speed is back above the threshold
+    Resolved,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeedAlertPayload {
+    pub transition: AlertTransition,
+    pub episode: SpeedAlertEpisode,
+    /// Most low samples were taken on weak Wi-Fi
+    pub weak_wifi: bool,
+}
+
+/// Tracks how long download speed has stayed under the configured threshold
+#[derive(Debug, Default)]
+pub struct ThresholdTracker {
+    below_since: Option<DateTime<Utc>>,
+    samples: u32,
+    sum_mbps: f64,
+    min_mbps: f64,
+    weak_wifi_samples: u32,
+    episode: Option<SpeedAlertEpisode>,
+}
+
+impl ThresholdTracker {
+    pub fn new() -> Self { Self::default() }
+
+    /// Feeds one measurement (oldest first) and reports any alert transition
+    pub fn observe(&mut self, config: &SpeedAlertConfig, m: &SpeedMeasurement) -> Option<(AlertTransition, SpeedAlertEpisode)> {
+        if m.confidence < MIN_ALERT_CONFIDENCE {
+            return None;
+        }
+
+        if m.download_mbps >= config.min_download_mbps {
+            let episode = self.episode.take();
+            self.reset();
+            return episode.map(|mut e| {
+                e.ended_at = Some(m.timestamp);
+                (AlertTransition::Resolved, e)
+            });
+        }
+
+        let since = *self.below_since.get_or_insert(m.timestamp);
+        self.min_mbps = if self.samples == 0 { m.download_mbps } else { self.min_mbps.min(m.download_mbps) };
+        self.samples += 1;
+        self.sum_mbps += m.download_mbps;
+        self.weak_wifi_samples += m.on_weak_wifi() as u32;
+        let (samples, min_mbps, avg_mbps) = (self.samples, self.min_mbps, self.sum_mbps / self.samples as f64);
+
+        if let Some(episode) = self.episode.as_mut() {
+            episode.sample_count = samples;
+            episode.min_download_mbps = min_mbps;
+            episode.avg_download_mbps = avg_mbps;
+            return Some((AlertTransition::Updated, episode.clone()));
+        }
+
+        if m.timestamp - since >= ChronoDuration::minutes(config.sustained_minutes as i64) {
+            let episode = SpeedAlertEpisode {
+                id: None,
+                started_at: since,
+                ended_at: None,
+                threshold_mbps: config.min_download_mbps,
+                sustained_minutes: config.sustained_minutes,
+                min_download_mbps: min_mbps,
+                avg_download_mbps: avg_mbps,
+                sample_count: samples,
+            };
+            self.episode = Some(episode.clone());
+            return Some((AlertTransition::Triggered, episode));
+        }
+        None
+    }
+
+    /// Remembers the database id of the active episode so later updates land on the same row
+    pub fn set_episode_id(&mut self, id: i64) {
+        if let Some(episode) = self.episode.as_mut() {
+            episode.id = Some(id);
+        }
+    }
+
+    /// Whether most low samples of the current streak were taken on weak Wi-Fi
+    pub fn on_weak_wifi(&self) -> bool {
+        self.samples > 0 && self.weak_wifi_samples as f64 / self.samples as f64 >= WEAK_WIFI_SHARE
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Background watcher raising low-speed alerts over incoming measurements
+pub struct SpeedAlertWatcher {
+    events: SharedEventSink,
+    repository: Arc<Repository>,
+    config: Arc<RwLock<SpeedAlertConfig>>,
+    show_notifications: bool,
+}
+
+impl SpeedAlertWatcher {
+    pub fn new(events: SharedEventSink, repository: Arc<Repository>, config: SpeedAlertConfig, show_notifications: bool) -> Self {
+        Self {
+            events,
+            repository,
+            config: Arc::new(RwLock::new(config)),
+            show_notifications,
+        }
+    }
+
+    pub async fn update_config(&self, cfg: SpeedAlertConfig) { *self.config.write().await = cfg; }
+
+    pub fn start(self: Arc<Self>) {
+        let watcher = Arc::clone(&self);
+        tokio::spawn(async move { watcher.run_loop().await; });
+    }
+
+    async fn run_loop(&self) {
+        let mut tracker = ThresholdTracker::new();
+        let mut last_seen = Utc::now();
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let cfg = self.config.read().await.clone();
+            if !cfg.enabled {
+                tracker.reset();
+                last_seen = Utc::now();
+                continue;
+            }
+
+            let mut measurements = match self.repository.get_speed_measurements_since(last_seen).await {
+                Ok(m) => m,
+                Err(e) => { warn!("Speed alert check failed: {}", e); continue; }
+            };
+            // Samples taken through a VPN or the proxy say nothing about the ISP
+            measurements.retain(|m| m.timestamp > last_seen && !m.tunnelled());
+            measurements.sort_by_key(|m| m.timestamp);
+            for m in &measurements {
+                last_seen = m.timestamp;
+                if let Some((transition, episode)) = tracker.observe(&cfg, m) {
+                    match self.repository.save_speed_alert_episode(&episode).await {
+                        Ok(id) => tracker.set_episode_id(id),
+                        Err(e) => warn!("Failed to record speed alert episode: {}", e),
+                    }
+                    self.announce(transition, &episode, tracker.on_weak_wifi());
+                }
+            }
+        }
+    }
+
+    fn announce(&self, transition: AlertTransition, episode: &SpeedAlertEpisode, weak_wifi: bool) {
+        let message = match transition {
+            AlertTransition::Triggered => {
+                info!("Speed alert: below {:.1} Mbps since {}", episode.threshold_mbps, episode.started_at);
+                let cause = if weak_wifi { ". Your Wi-Fi signal is weak, so this looks like a local wireless problem rather than your ISP" } else { "" };
+                format!(
+                    "Download has been below {:.1} Mbps for {} minutes (lowest {:.1} Mbps){}",
+                    episode.threshold_mbps, episode.sustained_minutes, episode.min_download_mbps, cause
+                )
+            }
+            AlertTransition::Resolved => {
+                info!("Speed alert resolved after {} samples", episode.sample_count);
+                format!("Download speed is back above {:.1} Mbps", episode.threshold_mbps)
+            }
+            AlertTransition::Updated => {
+                debug!("Speed alert ongoing: {:.1} Mbps average", episode.avg_download_mbps);
+                String::new()
+            }
+        };
+
+        self.events.emit_payload("speed_alert", &SpeedAlertPayload { transition, episode: episode.clone(), weak_wifi });
+
+        if self.show_notifications && transition != AlertTransition::Updated {
+            self.events.notify("SpeedKarma", &message);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LearningStallPayload {
+    /// Whether the hours checked come from detected throttling patterns rather than default evenings
+    pub from_patterns: bool,
+    pub gaps: Vec<CoverageGap>,
+    pub advice: Vec<String>,
+}
+
+/// Background watcher telling the user when the device is routinely off during the hours
+/// throttling is suspected in, so learning cannot complete
+pub struct LearningStallWatcher {
+    events: SharedEventSink,
+    repository: Arc<Repository>,
+    show_notifications: bool,
+}
+
+impl LearningStallWatcher {
+    pub fn new(events: SharedEventSink, repository: Arc<Repository>, show_notifications: bool) -> Self {
+        Self { events, repository, show_notifications }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        let watcher = Arc::clone(&self);
+        tokio::spawn(async move { watcher.run_loop().await; });
+    }
+
+    async fn run_loop(&self) {
+        let mut last_notified: Option<(DateTime<Utc>, Vec<CoverageGap>)> = None;
+        let mut interval = tokio::time::interval(COVERAGE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let payload = match self.check(*chrono::Local::now().offset()).await {
+                Ok(Some(payload)) => payload,
+                Ok(None) => { last_notified = None; continue; }
+                Err(e) => { warn!("Coverage check failed: {}", e); continue; }
+            };
+            let repeat = last_notified.as_ref().is_some_and(|(at, gaps)| {
+                *gaps == payload.gaps && Utc::now() - *at < ChronoDuration::days(COVERAGE_NOTIFY_COOLDOWN_DAYS)
+            });
+            if repeat {
+                continue;
+            }
+            info!("Learning stalled: {} uncovered stretches in the watched hours", payload.gaps.len());
+            self.events.emit_payload("learning_stalled", &payload);
+            if self.show_notifications {
+                let mut message: Vec<String> = payload.advice.iter().take(COVERAGE_LISTED_GAPS).cloned().collect();
+                if payload.advice.len() > COVERAGE_LISTED_GAPS {
+                    message.push(format!("and {} more", payload.advice.len() - COVERAGE_LISTED_GAPS));
+                }
+                let watched = if payload.from_patterns { "when your ISP seems to throttle" } else { "in the evenings" };
+                self.events.notify("SpeedKarma needs more data", &format!("Your device is usually off {}. {}", watched, message.join("; ")));
+            }
+            last_notified = Some((Utc::now(), payload.gaps));
+        }
+    }
+
+    /// Gaps in the current ISP's throttling windows, or in local evenings while none are known.
+    /// `None` until there is enough history or when every watched hour is covered.
+    pub async fn check(&self, utc_offset: FixedOffset) -> crate::core::error::Result<Option<LearningStallPayload>> {
+        let now = Utc::now();
+        let measurements = without_vpn(&self.repository.get_speed_measurements_since(now - ChronoDuration::days(COVERAGE_WINDOW_DAYS as i64)).await?);
+        let Some(oldest) = measurements.iter().map(|m| m.timestamp).min() else { return Ok(None) };
+        if now - oldest < ChronoDuration::days(COVERAGE_MIN_HISTORY_DAYS) {
+            return Ok(None);
+        }
+
+        let patterns = match self.repository.get_current_isp_profile().await?.and_then(|p| p.id) {
+            Some(id) => self.repository.get_throttling_patterns_for_isp(id).await?,
+            None => Vec::new(),
+        };
+        let from_patterns = !patterns.is_empty();
+        let targets = if from_patterns { dataset::pattern_hours(&patterns) } else { dataset::evening_hours(utc_offset) };
+        let gaps = dataset::target_coverage_gaps(&measurements, now, COVERAGE_WINDOW_DAYS, &targets);
+        if gaps.is_empty() {
+            debug!("Watched hours are covered");
+            return Ok(None);
+        }
+        let advice = gaps.iter().map(|g| g.advice(utc_offset)).collect();
+        Ok(Some(LearningStallPayload { from_patterns, gaps, advice }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(minutes_ago: i64, download_mbps: f64) -> SpeedMeasurement {
+        let mut m = SpeedMeasurement::new(download_mbps, 1.0, 0, false);
+        m.timestamp = Utc::now() - ChronoDuration::minutes(minutes_ago);
+        m
+    }
+
+    #[test]
+    fn test_alert_requires_sustained_drop() {
+        let cfg = SpeedAlertConfig { enabled: true, min_download_mbps: 5.0, sustained_minutes: 10 };
+        let mut tracker = ThresholdTracker::new();
+
+        assert!(tracker.observe(&cfg, &sample(30, 3.0)).is_none());
+        // Recovery before the window elapses never alerts
+        assert!(tracker.observe(&cfg, &sample(25, 8.0)).is_none());
+        assert!(tracker.observe(&cfg, &sample(20, 4.0)).is_none());
+        assert!(tracker.observe(&cfg, &sample(15, 2.0)).is_none());
+
+        let (transition, episode) = tracker.observe(&cfg, &sample(10, 3.0)).unwrap();
+        assert_eq!(transition, AlertTransition::Triggered);
+        assert_eq!(episode.sample_count, 3);
+        assert_eq!(episode.min_download_mbps, 2.0);
+
+        tracker.set_episode_id(7);
+        let (transition, _) = tracker.observe(&cfg, &sample(5, 4.5)).unwrap();
+        assert_eq!(transition, AlertTransition::Updated);
+
+        let (transition, episode) = tracker.observe(&cfg, &sample(0, 20.0)).unwrap();
+        assert_eq!(transition, AlertTransition::Resolved);
+        assert_eq!(episode.id, Some(7));
+        assert!(episode.ended_at.is_some());
+        assert_eq!(episode.sample_count, 4);
+    }
+
+    #[test]
+    fn test_low_confidence_samples_ignored() {
+        let cfg = SpeedAlertConfig { enabled: true, min_download_mbps: 5.0, sustained_minutes: 0 };
+        let mut tracker = ThresholdTracker::new();
+        let mut noisy = sample(0, 0.5);
+        noisy.confidence = 0.2;
+        assert!(tracker.observe(&cfg, &noisy).is_none());
+        assert!(tracker.observe(&cfg, &sample(0, 0.5)).is_some());
+    }
+
+    #[test]
+    fn test_weak_wifi_streak() {
+        let cfg = SpeedAlertConfig { enabled: true, min_download_mbps: 5.0, sustained_minutes: 10 };
+        let mut tracker = ThresholdTracker::new();
+        let weak = |minutes_ago| SpeedMeasurement { wifi_rssi_dbm: Some(-80), ..sample(minutes_ago, 2.0) };
+        tracker.observe(&cfg, &weak(20));
+        tracker.observe(&cfg, &sample(15, 2.0));
+        assert!(!tracker.on_weak_wifi());
+        tracker.observe(&cfg, &weak(10));
+        assert!(tracker.on_weak_wifi());
+        tracker.observe(&cfg, &sample(0, 20.0));
+        assert!(!tracker.on_weak_wifi());
+    }
+}
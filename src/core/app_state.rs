@@ -1,5 +1,12 @@
+use crate::core::config::{InterfaceSelectionConfig, ModuleToggles, PowerPolicyConfig, QuietHoursConfig};
+use crate::core::error::Result;
+use crate::data::models::{OptimizationStrategy, TrafficTransport};
+use crate::data::stores::StrategyStore;
+use crate::core::conflicts::ConflictReport;
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+use serde::Serialize;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OptimizationMode {
@@ -7,15 +14,231 @@ pub enum OptimizationMode {
     Disabled,
 }
 
+/// What the latest connectivity check found; its snake_case name is the `status_codes` hold code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectivityState {
+    #[default]
+    Online,
+    /// Probes answered by something other than the endpoint, usually a sign-in page
+    CaptivePortal,
+    /// No probe got an answer
+    Offline,
+}
+
 #[derive(Debug, Clone)]
 pub struct AppControlState {
     pub optimization_mode: OptimizationMode,
+    /// Live per-module switches (mirrors `AppConfig.modules`)
+    pub modules: ModuleToggles,
+    /// When the satisfaction question was last shown this session
+    pub last_feedback_prompt: Option<DateTime<Utc>>,
+    /// Competing optimizer/VPN tools found by the latest scan
+    pub conflict: Option<ConflictReport>,
+    /// Traffic generators stand down while a competing tool is active
+    pub generators_paused: bool,
+    /// A video or voice call is running; speed tests and heavy bursts are held back
+    pub call_active: bool,
+    /// The ISP rewrites DNS or proxies plain HTTP; traffic generators stick to HTTPS
+    pub prefer_encrypted: bool,
+    /// Emergency stop engaged; nothing generates traffic until the user re-enables it
+    pub stopped_by_user: bool,
+    /// Started in safe mode after repeated crashes; generators stay off until the user leaves it
+    pub safe_mode: bool,
+    /// Strategy pinned by the user; generators follow it instead of the best scored one
+    pub active_strategy_override: Option<OptimizationStrategy>,
+    /// Transport the stealth engine's last speedtest.net cycle actually used, after proxy and QUIC fallbacks
+    pub transport_in_use: Option<TrafficTransport>,
+    /// Optimization snoozed by the user; generators resume on their own at this time
+    pub paused_until: Option<DateTime<Utc>>,
+    /// Generated traffic used up this billing cycle's data cap
+    pub data_cap_reached: bool,
+    /// Connection is metered and `advanced.metered.auto_suspend` is on
+    pub metered_suspended: bool,
+    /// Offline or behind a captive portal; generators and passive measurements back off
+    pub connectivity: ConnectivityState,
+    /// Recurring window without generated traffic (mirrors `AppConfig.quiet_hours`)
+    pub quiet_hours: QuietHoursConfig,
+    /// On battery or in low-power mode, as the power policy defines it
+    pub power_saving: bool,
+    /// What is held back while saving power (mirrors `AppConfig.power_policy`)
+    pub power_policy: PowerPolicyConfig,
+    /// Interfaces passive speeds are summed over (mirrors `AppConfig.monitoring.interfaces`)
+    pub monitored_interfaces: InterfaceSelectionConfig,
+    /// Signalled by `set_optimization_mode`, so watchers wake on a change instead of polling
+    pub mode_changed: Arc<watch::Sender<()>>,
 }
 
 impl Default for AppControlState {
-    fn default() -> Self { Self { optimization_mode: OptimizationMode::Disabled } }
+    fn default() -> Self {
+        Self {
+            optimization_mode: OptimizationMode::Disabled,
+            modules: ModuleToggles::default(),
+            last_feedback_prompt: None,
+            conflict: None,
+            generators_paused: false,
+            call_active: false,
+            prefer_encrypted: false,
+            stopped_by_user: false,
+            safe_mode: false,
+            active_strategy_override: None,
+            transport_in_use: None,
+            paused_until: None,
+            data_cap_reached: false,
+            metered_suspended: false,
+            connectivity: ConnectivityState::Online,
+            quiet_hours: QuietHoursConfig::default(),
+            power_saving: false,
+            power_policy: PowerPolicyConfig::default(),
+            monitored_interfaces: InterfaceSelectionConfig::default(),
+            mode_changed: Arc::new(watch::Sender::new(())),
+        }
+    }
 }
 
 pub type SharedAppState = Arc<RwLock<AppControlState>>;
 
+impl AppControlState {
+    /// Changes the mode and wakes `mode_changed` subscribers
+    pub fn set_optimization_mode(&mut self, mode: OptimizationMode) {
+        self.optimization_mode = mode;
+        self.mode_changed.send_replace(());
+    }
+
+    /// Whether traffic-producing modules may run right now
+    pub fn may_generate(&self) -> bool {
+        matches!(self.optimization_mode, OptimizationMode::Enabled) && !self.generators_paused && !self.stopped_by_user && !self.safe_mode && !self.is_snoozed(Utc::now())
+            && !self.is_quiet_time() && !self.data_cap_reached && !self.metered_suspended && !self.connectivity_lost()
+    }
+
+    /// Offline or behind a captive portal
+    pub fn connectivity_lost(&self) -> bool {
+        self.connectivity != ConnectivityState::Online
+    }
+
+    /// `status_codes` generation-hold codes for what currently keeps generated traffic back
+    pub fn holds(&self) -> Vec<&'static str> {
+        [
+            (!matches!(self.optimization_mode, OptimizationMode::Enabled), "optimization_disabled"),
+            (self.stopped_by_user, "stopped_by_user"),
+            (self.safe_mode, "safe_mode"),
+            (self.is_snoozed(Utc::now()), "snoozed"),
+            (self.is_quiet_time(), "quiet_hours"),
+            (self.generators_paused, "conflict"),
+            (self.call_active, "call_active"),
+            (self.data_cap_reached, "data_cap_reached"),
+            (self.metered_suspended, "metered_connection"),
+            (self.connectivity == ConnectivityState::CaptivePortal, "captive_portal"),
+            (self.connectivity == ConnectivityState::Offline, "offline"),
+        ]
+        .into_iter()
+        .filter_map(|(held, code)| held.then_some(code))
+        .collect()
+    }
+
+    /// `status_codes` throttle codes for what currently slows generated traffic and measurements down
+    pub fn throttles(&self) -> Vec<&'static str> {
+        if self.power_saving { vec!["power_saving"] } else { Vec::new() }
+    }
+
+    /// Payload of `get_optimization_state`, also reported by the control API and the CLI
+    pub fn to_json(&self) -> serde_json::Value {
+        let mode = match self.optimization_mode { OptimizationMode::Enabled => "Enabled", OptimizationMode::Disabled => "Disabled" };
+        let paused_until = self.paused_until.filter(|_| self.is_snoozed(Utc::now()));
+        serde_json::json!({
+            "mode": mode, "text": "Learning patterns", "stopped_by_user": self.stopped_by_user, "paused_until": paused_until,
+            "quiet_hours_active": self.is_quiet_time(), "data_cap_reached": self.data_cap_reached,
+            "metered_suspended": self.metered_suspended, "connectivity": self.connectivity, "power_saving": self.power_saving,
+            "holds": self.holds(), "throttles": self.throttles(),
+        })
+    }
+
+    /// Whether the throughput keeper stands down to save power
+    pub fn keeper_power_suspended(&self) -> bool {
+        self.power_saving && self.power_policy.suspend_keeper
+    }
+
+    /// Passive measurements run on one in this many ticks
+    pub fn measurement_stride(&self) -> u32 {
+        if self.power_saving { self.power_policy.measurement_interval_multiplier.max(1) } else { 1 }
+    }
+
+    /// Factor applied to the decision engine's wait between rounds
+    pub fn decision_interval_factor(&self) -> u32 {
+        if self.power_saving { self.power_policy.decision_interval_multiplier.max(1) } else { 1 }
+    }
+
+    pub fn is_quiet_time(&self) -> bool {
+        self.quiet_hours.is_quiet(Local::now().naive_local())
+    }
+
+    pub fn is_snoozed(&self, now: DateTime<Utc>) -> bool {
+        self.paused_until.is_some_and(|until| until > now)
+    }
+
+    /// Whether link-saturating traffic (speed tests, stealth sessions) may run right now
+    pub fn may_load_link(&self) -> bool {
+        self.may_generate() && !self.call_active
+    }
+}
+
+/// End of a snooze lasting `minutes` from `now`; zero minutes ends a running snooze instead
+pub fn snooze_end(now: DateTime<Utc>, minutes: u32) -> Option<DateTime<Utc>> {
+    (minutes > 0).then(|| now + Duration::minutes(minutes as i64))
+}
+
+/// Local midnight at the start of tomorrow, for "snooze until tomorrow"
+pub fn start_of_tomorrow(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = now.with_timezone(&Local).date_naive() + Duration::days(1);
+    let midnight = tomorrow.and_hms_opt(0, 0, 0).unwrap_or_default();
+    // A DST jump can skip local midnight; fall back to 24 hours from now
+    Local.from_local_datetime(&midnight).earliest().map(|t| t.with_timezone(&Utc)).unwrap_or(now + Duration::days(1))
+}
+
+/// The strategy traffic should follow: the user's pinned one, else the best scored one in `store`
+pub async fn active_strategy(shared: &SharedAppState, store: &dyn StrategyStore) -> Result<Option<OptimizationStrategy>> {
+    if let Some(pinned) = shared.read().await.active_strategy_override.clone() {
+        return Ok(Some(pinned));
+    }
+    store.get_best_optimization_strategy().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::memory_store::InMemoryStore;
 
+    #[tokio::test]
+    async fn test_pinned_strategy_overrides_best() {
+        let store = InMemoryStore::new();
+        let mut best = OptimizationStrategy::high_stealth_strategy();
+        best.effectiveness_score = Some(0.9);
+        store.save_optimization_strategy(&best).await.unwrap();
+        let shared: SharedAppState = Arc::new(RwLock::new(AppControlState::default()));
+
+        assert_eq!(active_strategy(&shared, &store).await.unwrap().unwrap().name, best.name);
+
+        let pinned = OptimizationStrategy { effectiveness_score: Some(0.1), ..OptimizationStrategy::default_strategy() };
+        shared.write().await.active_strategy_override = Some(pinned.clone());
+        assert_eq!(active_strategy(&shared, &store).await.unwrap().unwrap().name, pinned.name);
+
+        shared.write().await.active_strategy_override = None;
+        assert_eq!(active_strategy(&shared, &store).await.unwrap().unwrap().name, best.name);
+    }
+
+    #[test]
+    fn test_snooze_blocks_generators_until_it_ends() {
+        let now = Utc::now();
+        let mut state = AppControlState { optimization_mode: OptimizationMode::Enabled, ..AppControlState::default() };
+        state.paused_until = snooze_end(now, 30);
+        assert!(state.is_snoozed(now + Duration::minutes(29)));
+        assert!(!state.may_generate());
+        assert!(!state.is_snoozed(now + Duration::minutes(31)));
+
+        state.paused_until = snooze_end(now, 0);
+        assert!(state.may_generate());
+
+        let tomorrow = start_of_tomorrow(now);
+        assert!(tomorrow > now && tomorrow <= now + Duration::hours(25));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::core::error::{Result, SpeedKarmaError};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Launch agent label; matches the bundle identifier
+pub const LAUNCH_AGENT_LABEL: &str = "com.speedkarma.isp-speedkarma";
+
+/// Value name under the Windows `Run` key
+pub const RUN_VALUE_NAME: &str = "SpeedKarma";
+
+#[cfg(target_os = "windows")]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// `get_auto_start_status` payload
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoStartStatus {
+    /// The saved preference (`AppConfig.auto_start`)
+    pub enabled: bool,
+    /// An entry is currently registered with the OS
+    pub registered: bool,
+    /// Where the entry lives: a file path or the registry key
+    pub location: Option<String>,
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// `~/Library/LaunchAgents` plist that starts the app at login
+pub fn launch_agent_plist(exe: &Path) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>ProcessType</key>
+    <string>Interactive</string>
+</dict>
+</plist>
+"#,
+        LAUNCH_AGENT_LABEL,
+        xml_escape(&exe.to_string_lossy())
+    )
+}
+
+/// XDG autostart entry; `Exec` is quoted per the Desktop Entry spec so paths with spaces survive
+pub fn desktop_entry(exe: &Path) -> String {
+    let mut quoted = String::from("\"");
+    for c in exe.to_string_lossy().chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    format!(
+        "[Desktop Entry]\nType=Application\nName=ISP-SpeedKarma\nComment=Start SpeedKarma at login\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+        quoted
+    )
+}
+
+/// Command line stored in the `Run` value
+pub fn windows_run_command(exe: &Path) -> String {
+    format!("\"{}\"", exe.to_string_lossy())
+}
+
+/// The binary to register; an AppImage is registered by its image rather than the mount it runs from
+fn current_exe() -> Result<PathBuf> {
+    #[cfg(target_os = "linux")]
+    if let Some(image) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(image));
+    }
+    Ok(std::env::current_exe()?)
+}
+
+/// File the login entry is written to
+#[cfg(target_os = "macos")]
+pub fn entry_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join("Library").join("LaunchAgents").join(format!("{}.plist", LAUNCH_AGENT_LABEL)))
+}
+
+#[cfg(target_os = "linux")]
+pub fn entry_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("autostart").join("speedkarma.desktop"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn location() -> Option<String> {
+    entry_path().map(|path| path.display().to_string())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+async fn register(exe: &Path) -> Result<()> {
+    let path = entry_path().ok_or_else(|| SpeedKarmaError::SystemError("No home directory for the login entry".to_string()))?;
+    #[cfg(target_os = "macos")]
+    let contents = launch_agent_plist(exe);
+    #[cfg(target_os = "linux")]
+    let contents = desktop_entry(exe);
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::write(&path, contents).await?;
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+async fn unregister() -> Result<()> {
+    let Some(path) = entry_path() else { return Ok(()) };
+    match tokio::fs::remove_file(&path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+async fn is_registered() -> bool {
+    match entry_path() {
+        Some(path) => tokio::fs::try_exists(path).await.unwrap_or(false),
+        None => false,
+    }
+}
+
+#[cfg(target_os = "windows")]
+async fn reg(args: &[&str]) -> Result<bool> {
+    let status = crate::core::command::command("reg").args(args).output().await?.status;
+    Ok(status.success())
+}
+
+#[cfg(target_os = "windows")]
+fn location() -> Option<String> {
+    Some(format!(r"{}\{}", RUN_KEY, RUN_VALUE_NAME))
+}
+
+#[cfg(target_os = "windows")]
+async fn register(exe: &Path) -> Result<()> {
+    let command = windows_run_command(exe);
+    if reg(&["add", RUN_KEY, "/v", RUN_VALUE_NAME, "/t", "REG_SZ", "/d", &command, "/f"]).await? {
+        Ok(())
+    } else {
+        Err(SpeedKarmaError::SystemError("Could not write the Run registry value".to_string()))
+    }
+}
+
+#[cfg(target_os = "windows")]
+async fn unregister() -> Result<()> {
+    if is_registered().await && !reg(&["delete", RUN_KEY, "/v", RUN_VALUE_NAME, "/f"]).await? {
+        return Err(SpeedKarmaError::SystemError("Could not remove the Run registry value".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+async fn is_registered() -> bool {
+    reg(&["query", RUN_KEY, "/v", RUN_VALUE_NAME]).await.unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn location() -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+async fn register(_exe: &Path) -> Result<()> {
+    Err(SpeedKarmaError::SystemError("Launch at login is not supported on this platform".to_string()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+async fn unregister() -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+async fn is_registered() -> bool {
+    false
+}
+
+/// Registers or removes the login entry; registering again refreshes the binary path after an update
+pub async fn apply(enabled: bool) -> Result<()> {
+    if enabled {
+        register(&current_exe()?).await?;
+        info!("Launch at login registered");
+    } else {
+        unregister().await?;
+        info!("Launch at login removed");
+    }
+    Ok(())
+}
+
+/// What the OS has registered, next to the saved preference
+pub async fn status(enabled: bool) -> AutoStartStatus {
+    AutoStartStatus { enabled, registered: is_registered().await, location: location() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_entries_quote_the_binary() {
+        let exe = Path::new("/Applications/ISP SpeedKarma & Co.app/Contents/MacOS/isp-speedkarma");
+        let plist = launch_agent_plist(exe);
+        assert!(plist.contains("<string>/Applications/ISP SpeedKarma &amp; Co.app/Contents/MacOS/isp-speedkarma</string>"));
+        assert!(plist.contains(&format!("<string>{}</string>", LAUNCH_AGENT_LABEL)));
+        assert!(plist.contains("<key>RunAtLoad</key>\n    <true/>"));
+
+        let entry = desktop_entry(Path::new("/home/me/My Apps/$speedkarma"));
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Exec=\"/home/me/My Apps/\\$speedkarma\"\n"));
+
+        assert_eq!(windows_run_command(Path::new(r"C:\Program Files\SpeedKarma\speedkarma.exe")), r#""C:\Program Files\SpeedKarma\speedkarma.exe""#);
+    }
+}
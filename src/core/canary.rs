@@ -0,0 +1,159 @@
+use crate::core::config::StrategyCanaryConfig;
+use crate::core::error::Result;
+use crate::core::intelligence::without_vpn;
+use crate::core::trial::window_median;
+use crate::data::models::{CanaryOutcome, OptimizationStrategy, SpeedMeasurement, StrategyDecision};
+use crate::data::stores::DataStore;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn};
+
+/// Fewest measurements the baseline and canary windows each need
+const MIN_WINDOW_SAMPLES: usize = 3;
+/// Extra windows a canary keeps collecting before it is called inconclusive
+const MAX_WINDOW_EXTENSIONS: i32 = 2;
+/// Longest strategy-decision history the panel can ask for, the most history the app keeps
+pub const MAX_DECISION_HISTORY_DAYS: u32 = 365;
+
+/// Pending decision for switching from `previous` to the just-activated `candidate`
+pub fn pending_decision(candidate: &OptimizationStrategy, previous: &OptimizationStrategy, config: &StrategyCanaryConfig, now: DateTime<Utc>) -> Option<StrategyDecision> {
+    Some(StrategyDecision {
+        id: None,
+        created_at: now,
+        strategy_id: candidate.id?,
+        strategy_name: candidate.name.clone(),
+        previous_strategy_id: previous.id,
+        previous_strategy_name: Some(previous.name.clone()),
+        window_ends_at: now + Duration::minutes(config.window_minutes.max(1) as i64),
+        baseline_mbps: None,
+        canary_mbps: None,
+        outcome: CanaryOutcome::Pending,
+        resolved_at: None,
+        note: None,
+    })
+}
+
+/// Resolved copy of a pending decision, or `None` while its window is still running.
+/// The baseline is the window before the switch; the canary runs from the switch until now,
+/// so a window short of measurements keeps collecting for up to `MAX_WINDOW_EXTENSIONS` more.
+pub fn evaluate(decision: &StrategyDecision, measurements: &[SpeedMeasurement], config: &StrategyCanaryConfig, now: DateTime<Utc>) -> Option<StrategyDecision> {
+    if decision.outcome != CanaryOutcome::Pending || now < decision.window_ends_at {
+        return None;
+    }
+    let window = decision.window_ends_at - decision.created_at;
+    let usable = |w: Option<(f64, usize)>| w.filter(|(_, n)| *n >= MIN_WINDOW_SAMPLES).map(|(median, _)| median);
+    let baseline = usable(window_median(measurements, decision.created_at - window, decision.created_at));
+    let canary = usable(window_median(measurements, decision.created_at, now));
+
+    let (outcome, note) = match (baseline, canary) {
+        (Some(before), Some(during)) if during < before * (1.0 - config.max_regression_pct.clamp(0.0, 100.0) / 100.0) => (
+            CanaryOutcome::Reverted,
+            format!("Download fell from {:.1} to {:.1} Mbps; {} is active again", before, during, decision.previous_strategy_name.as_deref().unwrap_or("the previous strategy")),
+        ),
+        (Some(before), Some(during)) => (CanaryOutcome::Committed, format!("Download {:.1} Mbps against {:.1} before the switch", during, before)),
+        _ if now < decision.window_ends_at + window * MAX_WINDOW_EXTENSIONS => return None,
+        (None, _) => (CanaryOutcome::Inconclusive, "Too few measurements before the switch to compare".to_string()),
+        (Some(_), None) => (CanaryOutcome::Inconclusive, "Too few measurements during the canary window".to_string()),
+    };
+    Some(StrategyDecision { baseline_mbps: baseline, canary_mbps: canary, outcome, resolved_at: Some(now), note: Some(note), ..decision.clone() })
+}
+
+/// Records a pending decision for a switch that just happened. Nothing is recorded without a
+/// previous strategy to fall back to.
+pub async fn start(store: &dyn DataStore, candidate: &OptimizationStrategy, previous: &OptimizationStrategy, config: &StrategyCanaryConfig) -> Result<Option<StrategyDecision>> {
+    if !config.enabled || previous.id == candidate.id {
+        return Ok(None);
+    }
+    let Some(mut decision) = pending_decision(candidate, previous, config, Utc::now()) else { return Ok(None) };
+    decision.id = Some(store.save_strategy_decision(&decision).await?);
+    info!("Strategy '{}' on canary until {}", decision.strategy_name, decision.window_ends_at);
+    Ok(Some(decision))
+}
+
+/// Judges every canary whose window has ended. Regressions are reverted by making the previous
+/// strategy the active one again; the new strategy keeps whatever score it has earned.
+pub async fn resolve_due(store: &dyn DataStore, config: &StrategyCanaryConfig) -> Result<Vec<StrategyDecision>> {
+    let now = Utc::now();
+    let pending: Vec<StrategyDecision> = store
+        .get_strategy_decisions_since(now)
+        .await?
+        .into_iter()
+        .filter(|d| d.outcome == CanaryOutcome::Pending && d.window_ends_at <= now)
+        .collect();
+    let mut resolved = Vec::new();
+    for decision in pending {
+        let window = decision.window_ends_at - decision.created_at;
+        let measurements = without_vpn(&store.get_speed_measurements_since(decision.created_at - window).await?);
+        let Some(result) = evaluate(&decision, &measurements, config, now) else { continue };
+        if result.outcome == CanaryOutcome::Reverted {
+            store.set_active_strategy(result.previous_strategy_id).await?;
+            warn!("Reverted strategy '{}': {}", result.strategy_name, result.note.as_deref().unwrap_or_default());
+        } else {
+            info!("Kept strategy '{}' ({}): {}", result.strategy_name, result.outcome.as_str(), result.note.as_deref().unwrap_or_default());
+        }
+        store.update_strategy_decision(&result).await?;
+        resolved.push(result);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::memory_store::InMemoryStore;
+    use crate::data::stores::{DecisionStore, MeasurementStore, StrategyStore};
+
+    fn at(minutes_ago: i64, mbps: f64) -> SpeedMeasurement {
+        SpeedMeasurement { timestamp: Utc::now() - Duration::minutes(minutes_ago), ..SpeedMeasurement::new(mbps, 5.0, 20, true) }
+    }
+
+    #[tokio::test]
+    async fn test_canary_reverts_regression() {
+        let store = InMemoryStore::new();
+        let mut previous = OptimizationStrategy::default_strategy();
+        previous.effectiveness_score = Some(0.5);
+        previous.id = Some(store.save_optimization_strategy(&previous).await.unwrap());
+        let mut candidate = OptimizationStrategy::high_stealth_strategy();
+        candidate.effectiveness_score = Some(0.51);
+        candidate.id = Some(store.save_optimization_strategy(&candidate).await.unwrap());
+        store.set_active_strategy(candidate.id).await.unwrap();
+
+        // Switched 40 minutes ago with a 30-minute window: 40 Mbps before, 20 during
+        let config = StrategyCanaryConfig::default();
+        let mut decision = pending_decision(&candidate, &previous, &config, Utc::now() - Duration::minutes(40)).unwrap();
+        decision.id = Some(store.save_strategy_decision(&decision).await.unwrap());
+        for (minutes_ago, mbps) in [(65, 41.0), (55, 40.0), (45, 39.0), (35, 21.0), (25, 20.0), (15, 19.0)] {
+            store.save_speed_measurement(&at(minutes_ago, mbps)).await.unwrap();
+        }
+
+        let resolved = resolve_due(&store, &config).await.unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].outcome, CanaryOutcome::Reverted);
+        assert_eq!((resolved[0].baseline_mbps, resolved[0].canary_mbps), (Some(40.0), Some(20.0)));
+        // The previous strategy is active again even though the candidate kept its higher score
+        let active = store.get_best_optimization_strategy().await.unwrap().unwrap();
+        assert_eq!(active.id, previous.id);
+        assert_eq!(active.effectiveness_score, Some(0.5));
+        assert!(resolve_due(&store, &config).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_canary_commits_or_waits() {
+        let config = StrategyCanaryConfig::default();
+        let mut previous = OptimizationStrategy::default_strategy();
+        previous.id = Some(1);
+        let mut candidate = OptimizationStrategy::high_stealth_strategy();
+        candidate.id = Some(2);
+        let decision = pending_decision(&candidate, &previous, &config, Utc::now() - Duration::minutes(40)).unwrap();
+        let now = Utc::now();
+
+        // Within tolerance: committed
+        let steady: Vec<SpeedMeasurement> = [(65, 40.0), (55, 40.0), (45, 40.0), (35, 39.0), (25, 38.5), (15, 39.0)].iter().map(|(m, v)| at(*m, *v)).collect();
+        assert_eq!(evaluate(&decision, &steady, &config, now).unwrap().outcome, CanaryOutcome::Committed);
+
+        // Too few canary samples: keep waiting, then give up as inconclusive
+        let sparse: Vec<SpeedMeasurement> = steady[..4].to_vec();
+        assert!(evaluate(&decision, &sparse, &config, now).is_none());
+        assert_eq!(evaluate(&decision, &sparse, &config, now + Duration::minutes(60)).unwrap().outcome, CanaryOutcome::Inconclusive);
+        assert!(evaluate(&decision, &steady, &config, decision.created_at + Duration::minutes(10)).is_none());
+    }
+}
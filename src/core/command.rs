@@ -0,0 +1,20 @@
+use tokio::process::Command;
+
+/// Keeps console-subsystem tools from flashing a window on every run
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = windows_sys::Win32::System::Threading::CREATE_NO_WINDOW;
+
+/// `program` set up for a background run: no console window on Windows, killed when dropped
+pub fn command(program: impl AsRef<std::ffi::OsStr>) -> Command {
+    let mut command = Command::new(program);
+    command.kill_on_drop(true);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    command
+}
+
+/// Stdout of a successful run of `program`; `None` when it is missing or fails
+pub async fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = command(program).args(args).output().await.ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
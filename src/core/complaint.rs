@@ -0,0 +1,249 @@
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::data::models::{MeasurementSource, SpeedMeasurement, ThrottlingPattern};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Letter templates shipped as data files (`src/core/complaint_letters/<language>.json`)
+const BUNDLED_TEMPLATES: &[(&str, &str)] = &[
+    ("en", include_str!("complaint_letters/en.json")),
+    ("pt", include_str!("complaint_letters/pt.json")),
+    ("id", include_str!("complaint_letters/id.json")),
+];
+
+/// Throttling windows below this confidence are left out of the letter
+const MIN_WINDOW_CONFIDENCE: f64 = 0.6;
+
+/// Evidence period when the caller does not pick one, and the longest it may pick
+pub const DEFAULT_EVIDENCE_DAYS: u32 = 30;
+pub const MAX_EVIDENCE_DAYS: u32 = 365;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComplaintRecipient {
+    Isp,
+    /// The telecom regulator or consumer body; the letter introduces the ISP first
+    Regulator,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LetterTemplate {
+    language: String,
+    name: String,
+    subject: String,
+    isp_greeting: String,
+    /// ISP greeting when the ISP could not be identified
+    unnamed_isp_greeting: String,
+    regulator_greeting: String,
+    /// Stands in for `{isp_name}` in the regulator greeting when the ISP could not be identified
+    unnamed_isp: String,
+    body: String,
+    throttling_heading: String,
+    throttling_item: String,
+    methodology: String,
+    /// Monday first
+    weekdays: [String; 7],
+}
+
+/// A bundled letter language, for the panel's picker
+#[derive(Debug, Clone, Serialize)]
+pub struct LetterLanguage {
+    pub code: String,
+    pub name: String,
+}
+
+/// The measured facts a letter is built from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplaintEvidence {
+    /// `None` when the ISP could not be identified
+    pub isp_name: Option<String>,
+    pub plan_mbps: f64,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// Active tests run with optimization off and a usable link
+    pub samples: usize,
+    /// Tests left out because they ran on weak Wi-Fi
+    pub excluded_weak_wifi: usize,
+    pub p10_mbps: f64,
+    pub median_mbps: f64,
+    pub p90_mbps: f64,
+    /// Share of tests under half the plan speed (0.0 to 1.0)
+    pub below_half_share: f64,
+    pub throttling_windows: Vec<ThrottlingPattern>,
+}
+
+impl ComplaintEvidence {
+    /// Only active tests with optimization off count: they show what the ISP delivers on its own.
+    /// `None` when no such test exists.
+    pub fn gather(isp_name: Option<&str>, plan_mbps: f64, measurements: &[SpeedMeasurement], patterns: &[ThrottlingPattern]) -> Option<Self> {
+        let baseline: Vec<&SpeedMeasurement> =
+            measurements.iter().filter(|m| m.source == MeasurementSource::Active && !m.optimization_active).collect();
+        let excluded_weak_wifi = baseline.iter().filter(|m| m.on_weak_wifi()).count();
+        let mut speeds: Vec<f64> = baseline.iter().filter(|m| !m.on_weak_wifi()).map(|m| m.download_mbps).collect();
+        if speeds.is_empty() {
+            return None;
+        }
+        speeds.sort_by(|a, b| a.total_cmp(b));
+        let timestamps = baseline.iter().map(|m| m.timestamp);
+        let mut windows: Vec<ThrottlingPattern> = patterns.iter().filter(|p| p.confidence >= MIN_WINDOW_CONFIDENCE).cloned().collect();
+        windows.sort_by(|a, b| b.severity.total_cmp(&a.severity));
+        Some(Self {
+            isp_name: isp_name.map(str::to_string),
+            plan_mbps,
+            period_start: timestamps.clone().min().unwrap_or_else(Utc::now),
+            period_end: timestamps.max().unwrap_or_else(Utc::now),
+            samples: speeds.len(),
+            excluded_weak_wifi,
+            p10_mbps: nearest_rank(&speeds, 0.10),
+            median_mbps: nearest_rank(&speeds, 0.50),
+            p90_mbps: nearest_rank(&speeds, 0.90),
+            below_half_share: speeds.iter().filter(|&&s| s < plan_mbps / 2.0).count() as f64 / speeds.len() as f64,
+            throttling_windows: windows,
+        })
+    }
+}
+
+/// A filled-in letter, ready to copy into an email or a regulator's form
+#[derive(Debug, Clone, Serialize)]
+pub struct ComplaintLetter {
+    pub language: String,
+    pub recipient: ComplaintRecipient,
+    pub subject: String,
+    pub body: String,
+    pub evidence: ComplaintEvidence,
+}
+
+fn nearest_rank(sorted: &[f64], quantile: f64) -> f64 {
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn fill(template: &str, values: &[(&str, String)]) -> String {
+    values.iter().fold(template.to_string(), |text, (key, value)| text.replace(&format!("{{{}}}", key), value))
+}
+
+fn bundled_templates() -> Vec<LetterTemplate> {
+    BUNDLED_TEMPLATES.iter().filter_map(|(_, json)| serde_json::from_str(json).ok()).collect()
+}
+
+pub fn languages() -> Vec<LetterLanguage> {
+    bundled_templates().into_iter().map(|t| LetterLanguage { code: t.language, name: t.name }).collect()
+}
+
+/// Fills the template for `language` (an ISO 639-1 code such as "pt") with `evidence`
+pub fn render(evidence: ComplaintEvidence, language: &str, recipient: ComplaintRecipient) -> Result<ComplaintLetter> {
+    let template = bundled_templates()
+        .into_iter()
+        .find(|t| t.language.eq_ignore_ascii_case(language))
+        .ok_or_else(|| SpeedKarmaError::ConfigurationError(format!("No complaint letter template for language '{}'", language)))?;
+
+    let mbps = |value: f64| format!("{:.1}", value);
+    let percent = |share: f64| format!("{:.0}", share * 100.0);
+    let date = |at: DateTime<Utc>| at.with_timezone(&Local).format("%Y-%m-%d").to_string();
+    let isp = [("isp_name", evidence.isp_name.clone().unwrap_or_else(|| template.unnamed_isp.clone()))];
+
+    // No confident window is not proof of no throttling, so the letter says nothing either way
+    let throttling_section = if evidence.throttling_windows.is_empty() {
+        String::new()
+    } else {
+        let items = evidence.throttling_windows.iter().map(|w| {
+            let days: Vec<&str> = w.days_of_week.iter().map(|d| template.weekdays[d.num_days_from_monday() as usize].as_str()).collect();
+            let item = fill(&template.throttling_item, &[
+                ("start", format!("{:02}:{:02}", w.start_hour, w.start_minute)),
+                ("end", format!("{:02}:{:02}", w.end_hour, w.end_minute)),
+                ("days", days.join(", ")),
+                ("severity", percent(w.severity)),
+            ]);
+            format!("- {}", item)
+        });
+        let lines: Vec<String> = std::iter::once(template.throttling_heading.clone()).chain(items).collect();
+        format!("{}\n\n", lines.join("\n"))
+    };
+    let methodology = fill(&template.methodology, &[
+        ("samples", evidence.samples.to_string()),
+        ("excluded", evidence.excluded_weak_wifi.to_string()),
+    ]);
+    let greeting = match recipient {
+        ComplaintRecipient::Isp if evidence.isp_name.is_none() => template.unnamed_isp_greeting.clone(),
+        ComplaintRecipient::Isp => fill(&template.isp_greeting, &isp),
+        ComplaintRecipient::Regulator => fill(&template.regulator_greeting, &isp),
+    };
+    let median_share = if evidence.plan_mbps > 0.0 { evidence.median_mbps / evidence.plan_mbps } else { 0.0 };
+    let body = fill(&template.body, &[
+        ("greeting", greeting),
+        ("plan_mbps", mbps(evidence.plan_mbps)),
+        ("period_start", date(evidence.period_start)),
+        ("period_end", date(evidence.period_end)),
+        ("samples", evidence.samples.to_string()),
+        ("median_mbps", mbps(evidence.median_mbps)),
+        ("median_percent", percent(median_share)),
+        ("p10_mbps", mbps(evidence.p10_mbps)),
+        ("p90_mbps", mbps(evidence.p90_mbps)),
+        ("below_half_percent", percent(evidence.below_half_share)),
+        ("throttling_section", throttling_section),
+        ("methodology", methodology),
+    ]);
+    Ok(ComplaintLetter {
+        language: template.language,
+        recipient,
+        subject: fill(&template.subject, &[("plan_mbps", mbps(evidence.plan_mbps))]),
+        body,
+        evidence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Weekday;
+
+    #[test]
+    fn test_letter_is_filled_from_baseline_tests() {
+        assert_eq!(languages().len(), BUNDLED_TEMPLATES.len());
+
+        let test = |mbps: f64, optimized: bool| SpeedMeasurement::new(mbps, 5.0, 30, optimized).with_source(MeasurementSource::Active);
+        let mut measurements: Vec<SpeedMeasurement> = (1..=10).map(|i| test(i as f64 * 10.0, false)).collect();
+        measurements.push(test(500.0, true));
+        measurements.push(SpeedMeasurement { wifi_rssi_dbm: Some(-85), ..test(1.0, false) });
+        measurements.push(SpeedMeasurement::new(2.0, 1.0, 30, false));
+        let window = ThrottlingPattern {
+            id: None,
+            isp_profile_id: 1,
+            start_hour: 19,
+            start_minute: 0,
+            end_hour: 23,
+            end_minute: 0,
+            days_of_week: vec![Weekday::Fri, Weekday::Sat],
+            severity: 0.45,
+            confidence: 0.8,
+        };
+        let doubtful = ThrottlingPattern { confidence: 0.3, ..window.clone() };
+
+        let evidence = ComplaintEvidence::gather(Some("Dialog"), 100.0, &measurements, &[window, doubtful.clone()]).unwrap();
+        assert_eq!((evidence.samples, evidence.excluded_weak_wifi), (10, 1));
+        assert_eq!((evidence.p10_mbps, evidence.median_mbps, evidence.p90_mbps), (10.0, 50.0, 90.0));
+        assert_eq!(evidence.below_half_share, 0.4);
+        assert_eq!(evidence.throttling_windows.len(), 1);
+
+        let letter = render(evidence.clone(), "en", ComplaintRecipient::Regulator).unwrap();
+        assert!(letter.subject.contains("100.0 Mbps"));
+        assert!(letter.body.contains("provided by Dialog"));
+        assert!(letter.body.contains("Median: 50.0 Mbps (50% of the plan)"));
+        assert!(letter.body.contains("- 19:00-23:00 on Friday, Saturday, about 45% slower"));
+        assert!(!letter.body.contains('{'));
+
+        let portuguese = render(evidence, "PT", ComplaintRecipient::Isp).unwrap();
+        assert!(portuguese.body.contains("sexta-feira, sábado"));
+        assert!(!portuguese.body.contains('{'));
+        assert!(render(portuguese.evidence, "xx", ComplaintRecipient::Isp).is_err());
+        assert!(ComplaintEvidence::gather(Some("Dialog"), 100.0, &[], &[]).is_none());
+
+        // Unknown ISP and no confident window: a plain greeting and no throttling section at all
+        let unknown = ComplaintEvidence::gather(None, 100.0, &measurements, &[doubtful]).unwrap();
+        let letter = render(unknown.clone(), "en", ComplaintRecipient::Isp).unwrap();
+        assert!(letter.body.starts_with("Dear customer support,\n"));
+        assert!(!letter.body.contains("recurring"));
+        assert!(letter.body.contains("below half of the plan speed: 40%\n\nMethodology:"));
+        let letter = render(unknown, "en", ComplaintRecipient::Regulator).unwrap();
+        assert!(letter.body.contains("provided by my internet service provider."));
+    }
+}
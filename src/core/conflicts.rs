@@ -0,0 +1,134 @@
+//! Pure classification of competing tools: known process names, tunnel interfaces and local proxies.
+//! The scanning itself lives in `network::conflicts`.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Process names (lowercase, without `.exe`) of VPNs, DPI bypass tools and other traffic shapers
+const KNOWN_TOOLS: &[&str] = &[
+    "openvpn", "wireguard", "wg-quick", "wg-crypt", "nordvpn", "nordvpnd", "expressvpn", "expressvpnd",
+    "protonvpn", "mullvad-daemon", "warp-svc", "speedify", "speedifyservice", "psiphon", "psiphon3",
+    "v2ray", "xray", "sing-box", "clash", "clash-verge", "ss-local", "sslocal", "shadowsocks",
+    "outline", "goodbyedpi", "zapret", "nfqws", "byedpi", "ciadpi", "netlimiter", "cfosspeed",
+];
+
+/// Interface name prefixes created by tunnel drivers. macOS `utun` is skipped: the system keeps several open.
+/// `ppp*` and `pppoe-*` are left out: on PPPoE links and routers they are the real WAN, not a tunnel.
+const TUNNEL_PREFIXES: &[&str] = &["tun", "tap", "wg", "nordlynx", "proton", "mullvad", "cloudflarewarp"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// A known optimizer or VPN process is running
+    Process,
+    /// A tunnel interface is up
+    TunnelInterface,
+    /// Traffic is sent through a local proxy, as bypass tools do
+    LocalProxy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ConflictSignal {
+    pub kind: ConflictKind,
+    pub name: String,
+}
+
+/// Everything found by one scan; empty when nothing competes with SpeedKarma
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictReport {
+    pub signals: Vec<ConflictSignal>,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl ConflictReport {
+    pub fn has_conflict(&self) -> bool { !self.signals.is_empty() }
+
+    /// Short description for the tray and notifications
+    pub fn summary(&self) -> String {
+        let names: Vec<&str> = self.signals.iter().map(|s| s.name.as_str()).take(3).collect();
+        match self.signals.len() {
+            0 => "No competing tools".to_string(),
+            n if n > names.len() => format!("{} and {} more", names.join(", "), n - names.len()),
+            _ => names.join(", "),
+        }
+    }
+}
+
+/// Known tool names among running process names
+pub fn match_known_processes<'a>(processes: impl IntoIterator<Item = &'a str>) -> Vec<ConflictSignal> {
+    let mut found: Vec<ConflictSignal> = processes
+        .into_iter()
+        .filter_map(|name| {
+            let lower = name.trim().to_lowercase();
+            let stem = lower.strip_suffix(".exe").unwrap_or(&lower);
+            KNOWN_TOOLS.contains(&stem).then(|| ConflictSignal { kind: ConflictKind::Process, name: stem.to_string() })
+        })
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Whether the name belongs to a tunnel driver's interface, macOS `utun` included
+pub fn is_tunnel_interface(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    lower.starts_with("utun") || TUNNEL_PREFIXES.iter().any(|p| lower.starts_with(p))
+}
+
+/// Interfaces whose names mark them as tunnels
+pub fn match_tunnel_interfaces<'a>(interfaces: impl IntoIterator<Item = &'a str>) -> Vec<ConflictSignal> {
+    let mut found: Vec<ConflictSignal> = interfaces
+        .into_iter()
+        .filter(|name| is_tunnel_interface(name) && !name.to_lowercase().starts_with("utun"))
+        .map(|name| ConflictSignal { kind: ConflictKind::TunnelInterface, name: name.to_string() })
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// A proxy URL pointing at this machine, e.g. `socks5://127.0.0.1:1080`
+pub fn is_local_proxy(url: &str) -> bool {
+    let rest = url.split_once("://").map(|(_, r)| r).unwrap_or(url);
+    let authority = rest.split('/').next().unwrap_or("");
+    let host_port = authority.rsplit('@').next().unwrap_or("");
+    let host = if let Some(v6) = host_port.strip_prefix('[') {
+        v6.split(']').next().unwrap_or("")
+    } else {
+        host_port.split(':').next().unwrap_or("")
+    };
+    matches!(host, "localhost" | "::1") || host.starts_with("127.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_processes_and_interfaces() {
+        let procs = match_known_processes(["systemd", "OpenVPN.exe", "openvpn", "firefox", "goodbyedpi.exe"]);
+        assert_eq!(procs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["goodbyedpi", "openvpn"]);
+
+        let ifaces = match_tunnel_interfaces(["eth0", "wlan0", "tun0", "wg-home", "utun3", "lo"]);
+        assert_eq!(ifaces.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["tun0", "wg-home"]);
+        assert!(is_tunnel_interface("utun3") && is_tunnel_interface("NordLynx") && !is_tunnel_interface("en0"));
+        assert!(!is_tunnel_interface("ppp0") && !is_tunnel_interface("pppoe-wan"));
+    }
+
+    #[test]
+    fn test_local_proxy_detection() {
+        assert!(is_local_proxy("socks5://127.0.0.1:1080"));
+        assert!(is_local_proxy("http://user:pw@localhost:8080/"));
+        assert!(is_local_proxy("http://[::1]:3128"));
+        assert!(!is_local_proxy("http://proxy.corp.example:3128"));
+        assert!(!is_local_proxy("http://10.0.0.1:8080"));
+    }
+
+    #[test]
+    fn test_summary() {
+        let signal = |name: &str| ConflictSignal { kind: ConflictKind::Process, name: name.to_string() };
+        let report = ConflictReport { signals: vec![signal("a"), signal("b"), signal("c"), signal("d")], detected_at: Utc::now() };
+        assert_eq!(report.summary(), "a, b, c and 1 more");
+        assert!(!ConflictReport { signals: vec![], detected_at: Utc::now() }.has_conflict());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::core::config::{AppConfig, ControlApiConfig};
+use crate::core::error::Result;
+use crate::core::intelligence::EffectivenessMetrics;
+use crate::core::status_codes;
+use crate::data::export::{ExportFormat, ExportSummary, MeasurementFilter};
+use crate::data::models::{SpeedAlertEpisode, SpeedMeasurement, ThrottlingPatternSummary};
+use async_trait::async_trait;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// History served when the request does not say how many days
+const DEFAULT_HISTORY_DAYS: u32 = 7;
+const MAX_HISTORY_DAYS: u32 = 90;
+
+/// What one instance reports to a fleet operator polling `GET /v1/summary`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SiteSummary {
+    pub isp_name: Option<String>,
+    pub optimization_enabled: bool,
+    pub effectiveness: Option<EffectivenessMetrics>,
+    /// Most confident first
+    pub throttling: Vec<ThrottlingPatternSummary>,
+    /// Low-speed alert episodes of the last day, newest first
+    pub alerts: Vec<SpeedAlertEpisode>,
+}
+
+/// What the HTTP API and the CLI socket can do; the desktop shell implements it with the same code as its commands.
+/// Errors are the message the matching command would return.
+#[async_trait]
+pub trait ControlBackend: Send + Sync {
+    async fn status(&self) -> std::result::Result<serde_json::Value, String>;
+    async fn toggle_optimization(&self) -> std::result::Result<serde_json::Value, String>;
+    async fn run_speedtest(&self) -> std::result::Result<(), String>;
+    async fn history(&self, days: u32) -> std::result::Result<Vec<SpeedMeasurement>, String>;
+    async fn summary(&self) -> std::result::Result<SiteSummary, String>;
+    /// Writes measurements to `path`, or to the downloads folder without one
+    async fn export(&self, format: ExportFormat, filter: MeasurementFilter, path: Option<PathBuf>) -> std::result::Result<ExportSummary, String>;
+    /// Brings the window to the front when a second launch hands over; headless runs have none
+    async fn focus(&self) -> std::result::Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Token for a fresh install of the API
+pub fn generate_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Serves the API when `config` turns it on; the token is created and saved the first time.
+/// The desktop shell and headless runs both start it through here.
+pub async fn launch(config: &ControlApiConfig, backend: Arc<dyn ControlBackend>) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    let mut config = config.clone();
+    if config.token.is_empty() {
+        config.token = generate_token();
+        let mut saved = AppConfig::load().await?;
+        saved.advanced.control_api.token = config.token.clone();
+        saved.save().await?;
+        info!("Generated a control API token; it is stored in the config file");
+    }
+    start(config, backend);
+    Ok(())
+}
+
+/// Serves the API on the configured address in the background. A port that is taken is only logged.
+pub fn start(config: ControlApiConfig, backend: Arc<dyn ControlBackend>) {
+    if !config.enabled || config.token.is_empty() {
+        return;
+    }
+    let addr = SocketAddr::from((config.bind_address, config.port));
+    let token = Arc::new(config.token);
+    tokio::spawn(async move {
+        let make = make_service_fn(move |_| {
+            let (backend, token) = (Arc::clone(&backend), Arc::clone(&token));
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let (backend, token) = (Arc::clone(&backend), Arc::clone(&token));
+                    async move { Ok::<_, Infallible>(handle(req, backend.as_ref(), &token).await) }
+                }))
+            }
+        });
+        match Server::try_bind(&addr) {
+            Ok(server) => {
+                info!("Control API listening on http://{}", addr);
+                if let Err(e) = server.serve(make).await {
+                    warn!("Control API stopped: {}", e);
+                }
+            }
+            Err(e) => warn!("Control API could not bind {}: {}", addr, e),
+        }
+    });
+}
+
+/// Routes one request. Every route needs `Authorization: Bearer <token>`:
+/// `GET /v1/status`, `POST /v1/optimization/toggle`, `POST /v1/speedtest`, `GET /v1/history?days=N`,
+/// `GET /v1/summary`.
+pub async fn handle(req: Request<Body>, backend: &dyn ControlBackend, token: &str) -> Response<Body> {
+    if !authorized(&req, token) {
+        return error(StatusCode::UNAUTHORIZED, "missing or wrong bearer token");
+    }
+    let result = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/v1/status") => backend.status().await,
+        (&Method::POST, "/v1/optimization/toggle") => backend.toggle_optimization().await,
+        (&Method::POST, "/v1/speedtest") => backend.run_speedtest().await.map(|_| serde_json::json!({ "started": true })),
+        (&Method::GET, "/v1/history") => {
+            let days = query_param(&req, "days").and_then(|d| d.parse().ok()).unwrap_or(DEFAULT_HISTORY_DAYS).clamp(1, MAX_HISTORY_DAYS);
+            backend.history(days).await.and_then(|rows| serde_json::to_value(rows).map_err(|e| e.to_string()))
+        }
+        (&Method::GET, "/v1/summary") => backend.summary().await.and_then(|summary| serde_json::to_value(summary).map_err(|e| e.to_string())),
+        (&Method::GET, "/v1/status-codes") => serde_json::to_value(status_codes::all()).map_err(|e| e.to_string()),
+        (_, "/v1/status" | "/v1/optimization/toggle" | "/v1/speedtest" | "/v1/history" | "/v1/summary" | "/v1/status-codes") => {
+            return error(StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+        }
+        _ => return error(StatusCode::NOT_FOUND, "no such endpoint"),
+    };
+    match result {
+        Ok(value) => json(StatusCode::OK, &value),
+        // The command refused or failed, e.g. toggling while the emergency stop is engaged
+        Err(message) => error(StatusCode::CONFLICT, &message),
+    }
+}
+
+fn authorized(req: &Request<Body>, token: &str) -> bool {
+    let Some(given) = req.headers().get(AUTHORIZATION).and_then(|v| v.to_str().ok()).and_then(|v| v.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    // Compare every byte so the response time does not reveal how much of the token matched
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn query_param<'a>(req: &'a Request<Body>, name: &str) -> Option<&'a str> {
+    req.uri().query()?.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+fn json<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    Response::builder().status(status).header(CONTENT_TYPE, "application/json").body(Body::from(body)).unwrap_or_default()
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json(status, &serde_json::json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeBackend {
+        enabled: Mutex<bool>,
+        stopped: Mutex<bool>,
+        history_days: Mutex<Option<u32>>,
+    }
+
+    #[async_trait]
+    impl ControlBackend for FakeBackend {
+        async fn status(&self) -> std::result::Result<serde_json::Value, String> {
+            Ok(serde_json::json!({ "enabled": *self.enabled.lock().await }))
+        }
+        async fn toggle_optimization(&self) -> std::result::Result<serde_json::Value, String> {
+            if *self.stopped.lock().await {
+                return Err("Emergency stop is engaged".into());
+            }
+            let mut enabled = self.enabled.lock().await;
+            *enabled = !*enabled;
+            Ok(serde_json::json!({ "enabled": *enabled }))
+        }
+        async fn run_speedtest(&self) -> std::result::Result<(), String> { Ok(()) }
+        async fn history(&self, days: u32) -> std::result::Result<Vec<SpeedMeasurement>, String> {
+            *self.history_days.lock().await = Some(days);
+            Ok(vec![SpeedMeasurement::new(50.0, 10.0, 20, true)])
+        }
+        async fn summary(&self) -> std::result::Result<SiteSummary, String> {
+            Ok(SiteSummary { optimization_enabled: *self.enabled.lock().await, ..SiteSummary::default() })
+        }
+        async fn export(&self, format: ExportFormat, _filter: MeasurementFilter, path: Option<PathBuf>) -> std::result::Result<ExportSummary, String> {
+            Ok(ExportSummary { path: path.unwrap_or_default(), format, rows: 1 })
+        }
+    }
+
+    async fn call(backend: &FakeBackend, method: Method, uri: &str, token: Option<&str>) -> (StatusCode, serde_json::Value) {
+        let mut req = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            req = req.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let resp = handle(req.body(Body::empty()).unwrap(), backend, "s3cret").await;
+        let status = resp.status();
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_routes_require_token_and_mirror_commands() {
+        let backend = FakeBackend::default();
+        assert_eq!(call(&backend, Method::GET, "/v1/status", None).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call(&backend, Method::GET, "/v1/status", Some("s3creT")).await.0, StatusCode::UNAUTHORIZED);
+
+        assert_eq!(call(&backend, Method::POST, "/v1/optimization/toggle", Some("s3cret")).await, (StatusCode::OK, serde_json::json!({ "enabled": true })));
+        *backend.stopped.lock().await = true;
+        let (status, body) = call(&backend, Method::POST, "/v1/optimization/toggle", Some("s3cret")).await;
+        assert_eq!((status, body["error"].as_str()), (StatusCode::CONFLICT, Some("Emergency stop is engaged")));
+        assert_eq!(call(&backend, Method::GET, "/v1/status", Some("s3cret")).await.1["enabled"], true);
+
+        let (status, body) = call(&backend, Method::GET, "/v1/history?days=500", Some("s3cret")).await;
+        assert_eq!((status, body.as_array().map(|a| a.len())), (StatusCode::OK, Some(1)));
+        assert_eq!(*backend.history_days.lock().await, Some(MAX_HISTORY_DAYS));
+        assert_eq!(call(&backend, Method::GET, "/v1/summary", Some("s3cret")).await.1["optimization_enabled"], true);
+
+        assert_eq!(call(&backend, Method::GET, "/v1/speedtest", Some("s3cret")).await.0, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(call(&backend, Method::GET, "/v1/status-codes", Some("s3cret")).await.1[0]["code"], "learning");
+        assert_eq!(call(&backend, Method::GET, "/v1/nope", Some("s3cret")).await.0, StatusCode::NOT_FOUND);
+    }
+}
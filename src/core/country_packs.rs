@@ -0,0 +1,222 @@
+use crate::core::config::AppConfig;
+use crate::data::models::{OptimizationStrategy, StealthLevel};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Curated market defaults shipped as data files (`src/core/country_packs/<code>.json`)
+const BUNDLED_PACKS: &[(&str, &str)] = &[
+    ("lk", include_str!("country_packs/lk.json")),
+    ("in", include_str!("country_packs/in.json")),
+    ("ph", include_str!("country_packs/ph.json")),
+    ("br", include_str!("country_packs/br.json")),
+    ("id", include_str!("country_packs/id.json")),
+    ("pk", include_str!("country_packs/pk.json")),
+    ("bd", include_str!("country_packs/bd.json")),
+    ("ng", include_str!("country_packs/ng.json")),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackMonitoring {
+    pub measurement_interval: u64,
+    pub max_bandwidth_per_hour: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackKeeper {
+    pub hourly_budget_mb: f64,
+    pub daily_budget_mb: Option<f64>,
+    pub burst_sizes_kb: Vec<u32>,
+}
+
+/// Default cadence, budgets, server preferences and stealth level for one market
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CountryPack {
+    /// ISO 3166-1 alpha-2 code
+    pub country_code: String,
+    pub name: String,
+    /// Other names the detected region may use
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub monitoring: PackMonitoring,
+    pub keeper: PackKeeper,
+    /// Server countries in order of preference
+    pub server_countries: Vec<String>,
+    /// Stealth level of the initial strategy
+    pub stealth_level: StealthLevel,
+    /// ISP name fragments (lowercase) known to throttle speed test traffic in this market
+    #[serde(default)]
+    pub throttling_isps: Vec<String>,
+}
+
+impl CountryPack {
+    /// Whether `region` (a country code or name) refers to this pack's country
+    pub fn matches(&self, region: &str) -> bool {
+        let region = region.trim().to_lowercase();
+        if region.is_empty() { return false; }
+        region == self.country_code.to_lowercase()
+            || std::iter::once(&self.name).chain(&self.aliases).any(|name| region.contains(&name.to_lowercase()))
+    }
+
+    pub fn is_throttling_isp(&self, isp_name: &str) -> bool {
+        let isp = isp_name.to_lowercase();
+        self.throttling_isps.iter().any(|name| isp.contains(name.as_str()))
+    }
+
+    /// First strategy for a fresh install: high stealth for ISPs known to throttle,
+    /// otherwise the default strategy at the market's stealth level
+    pub fn initial_strategy(&self, isp_name: &str) -> OptimizationStrategy {
+        if self.is_throttling_isp(isp_name) {
+            return OptimizationStrategy::high_stealth_strategy();
+        }
+        OptimizationStrategy { stealth_level: self.stealth_level.clone(), ..OptimizationStrategy::default_strategy() }
+    }
+
+    /// Writes the pack's defaults into `cfg` and records it as applied
+    pub fn apply_to(&self, cfg: &mut AppConfig) {
+        cfg.monitoring.measurement_interval = self.monitoring.measurement_interval;
+        cfg.monitoring.max_bandwidth_per_hour = self.monitoring.max_bandwidth_per_hour;
+        let keeper = &mut cfg.advanced.throughput_keeper;
+        keeper.hourly_budget_mb = self.keeper.hourly_budget_mb;
+        keeper.daily_budget_mb = self.keeper.daily_budget_mb;
+        if !self.keeper.burst_sizes_kb.is_empty() {
+            keeper.burst_sizes_kb = self.keeper.burst_sizes_kb.clone();
+        }
+        cfg.advanced.preferred_server_countries = self.server_countries.clone();
+        cfg.advanced.country_pack.applied = Some(self.country_code.clone());
+    }
+
+    /// Like [`apply_to`](Self::apply_to), but only replaces settings still at their
+    /// shipped defaults so values the user already changed are kept
+    pub fn apply_to_defaults(&self, cfg: &mut AppConfig) {
+        let defaults = AppConfig::default();
+        if cfg.monitoring.measurement_interval == defaults.monitoring.measurement_interval {
+            cfg.monitoring.measurement_interval = self.monitoring.measurement_interval;
+        }
+        if cfg.monitoring.max_bandwidth_per_hour == defaults.monitoring.max_bandwidth_per_hour {
+            cfg.monitoring.max_bandwidth_per_hour = self.monitoring.max_bandwidth_per_hour;
+        }
+        let keeper = &mut cfg.advanced.throughput_keeper;
+        let default_keeper = &defaults.advanced.throughput_keeper;
+        if keeper.hourly_budget_mb == default_keeper.hourly_budget_mb {
+            keeper.hourly_budget_mb = self.keeper.hourly_budget_mb;
+        }
+        if keeper.daily_budget_mb == default_keeper.daily_budget_mb {
+            keeper.daily_budget_mb = self.keeper.daily_budget_mb;
+        }
+        if !self.keeper.burst_sizes_kb.is_empty() && keeper.burst_sizes_kb == default_keeper.burst_sizes_kb {
+            keeper.burst_sizes_kb = self.keeper.burst_sizes_kb.clone();
+        }
+        if cfg.advanced.preferred_server_countries == defaults.advanced.preferred_server_countries {
+            cfg.advanced.preferred_server_countries = self.server_countries.clone();
+        }
+        cfg.advanced.country_pack.applied = Some(self.country_code.clone());
+    }
+}
+
+/// First strategy for a fresh install. Uses the market pack when the region is known;
+/// otherwise an ISP any pack lists as throttling still starts at high stealth.
+pub fn initial_strategy(region: &str, isp_name: &str) -> OptimizationStrategy {
+    let packs = bundled_packs();
+    if let Some(pack) = packs.iter().find(|pack| pack.matches(region)) {
+        return pack.initial_strategy(isp_name);
+    }
+    if packs.iter().any(|pack| pack.is_throttling_isp(isp_name)) {
+        return OptimizationStrategy::high_stealth_strategy();
+    }
+    OptimizationStrategy::default_strategy()
+}
+
+/// All packs shipped with the app; a malformed file is logged and skipped
+pub fn bundled_packs() -> Vec<CountryPack> {
+    BUNDLED_PACKS
+        .iter()
+        .filter_map(|(file, json)| match serde_json::from_str::<CountryPack>(json) {
+            Ok(pack) => Some(pack),
+            Err(e) => { warn!("Invalid country pack {}.json: {}", file, e); None }
+        })
+        .collect()
+}
+
+/// Pack for a detected region, by country code or name
+pub fn pack_for_region(region: &str) -> Option<CountryPack> {
+    bundled_packs().into_iter().find(|pack| pack.matches(region))
+}
+
+/// Applies the pack for `region` when auto-selection is on and no pack was applied yet.
+/// Settings the user already customized are left alone. Returns the pack when `cfg` changed.
+pub fn auto_apply(cfg: &mut AppConfig, region: &str) -> Option<CountryPack> {
+    if !cfg.advanced.country_pack.auto_apply || cfg.advanced.country_pack.applied.is_some() {
+        return None;
+    }
+    let pack = pack_for_region(region)?;
+    pack.apply_to_defaults(cfg);
+    Some(pack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_packs_parse_and_validate() {
+        let packs = bundled_packs();
+        assert_eq!(packs.len(), BUNDLED_PACKS.len());
+        for pack in &packs {
+            let mut cfg = AppConfig::default();
+            pack.apply_to(&mut cfg);
+            assert!(cfg.validate().is_ok(), "{} pack breaks validation", pack.country_code);
+            assert!(!pack.server_countries.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_region_lookup() {
+        assert_eq!(pack_for_region("LK").unwrap().name, "Sri Lanka");
+        assert_eq!(pack_for_region("Sri Lanka").unwrap().country_code, "LK");
+        assert_eq!(pack_for_region("Republic of the Philippines").unwrap().country_code, "PH");
+        assert_eq!(pack_for_region("Brasil").unwrap().country_code, "BR");
+        assert!(pack_for_region("Iceland").is_none());
+        assert!(pack_for_region("").is_none());
+    }
+
+    #[test]
+    fn test_auto_apply_only_once() {
+        let mut cfg = AppConfig::default();
+        let pack = auto_apply(&mut cfg, "Philippines").unwrap();
+        assert_eq!(cfg.advanced.country_pack.applied.as_deref(), Some("PH"));
+        assert_eq!(cfg.monitoring.measurement_interval, pack.monitoring.measurement_interval);
+        assert_eq!(cfg.advanced.throughput_keeper.daily_budget_mb, pack.keeper.daily_budget_mb);
+        assert_eq!(cfg.advanced.preferred_server_countries[0], "Philippines");
+
+        // A later detection in another country leaves the user's config alone
+        assert!(auto_apply(&mut cfg, "India").is_none());
+        assert_eq!(cfg.advanced.country_pack.applied.as_deref(), Some("PH"));
+
+        let mut off = AppConfig::default();
+        off.advanced.country_pack.auto_apply = false;
+        assert!(auto_apply(&mut off, "India").is_none());
+    }
+
+    #[test]
+    fn test_auto_apply_keeps_customized_settings() {
+        let mut cfg = AppConfig::default();
+        cfg.monitoring.measurement_interval = 42;
+        cfg.advanced.preferred_server_countries = vec!["Japan".to_string()];
+        let pack = auto_apply(&mut cfg, "Philippines").unwrap();
+        assert_eq!(cfg.monitoring.measurement_interval, 42);
+        assert_eq!(cfg.advanced.preferred_server_countries, vec!["Japan".to_string()]);
+        assert_eq!(cfg.advanced.throughput_keeper.daily_budget_mb, pack.keeper.daily_budget_mb);
+        assert_eq!(cfg.advanced.country_pack.applied.as_deref(), Some("PH"));
+    }
+
+    #[test]
+    fn test_initial_strategy() {
+        let pack = pack_for_region("IN").unwrap();
+        assert_eq!(pack.initial_strategy("Reliance Jio Infocomm").stealth_level, StealthLevel::High);
+        assert_eq!(pack.initial_strategy("ACT Fibernet").stealth_level, pack.stealth_level);
+
+        // Known throttlers start at high stealth even when the region is not recognised
+        assert_eq!(initial_strategy("", "Dialog Axiata").stealth_level, StealthLevel::High);
+        assert_eq!(initial_strategy("Iceland", "Siminn").stealth_level, OptimizationStrategy::default_strategy().stealth_level);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::data::models::{SpeedMeasurement, ThrottlingPattern};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Timelike, Utc, Weekday};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Rows `train_model` needs before it learns anything
+const MIN_TRAINING_ROWS: usize = 50;
+/// Rows at which the data-volume part of model confidence is maxed out
+const FULL_CONFIDENCE_ROWS: usize = 1000;
+/// Share of optimized rows below which effectiveness cannot be compared
+const MIN_OPTIMIZED_SHARE: f64 = 0.2;
+/// Silences at least this long are reported as gaps (hours)
+const GAP_HOURS: i64 = 6;
+/// Measurements below this confidence barely count
+const LOW_CONFIDENCE: f64 = 0.4;
+/// Confidence histogram bucket width
+const BUCKET_WIDTH: f64 = 0.2;
+/// Uncovered hour-of-week slots listed by name in the needs
+const LISTED_SLOTS: usize = 5;
+/// Share of its days a target hour needs a measurement on to count as covered
+const TARGET_COVERAGE: f64 = 0.5;
+/// Longest period the dataset stats may cover; one day row each, so it bounds the response
+pub const MAX_DATASET_DAYS: u32 = 365;
+/// Local evening hours watched while no throttling pattern is known yet
+pub const DEFAULT_EVENING_HOURS: std::ops::Range<u32> = 18..23;
+
+/// Rows recorded on one UTC day
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DayCount {
+    pub date: NaiveDate,
+    pub total: u32,
+    pub optimized: u32,
+    pub baseline: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfidenceBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: u32,
+}
+
+/// A stretch with no measurements
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DataGap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub hours: f64,
+}
+
+/// Hour of the week (UTC) with no measurement in the window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HourSlot {
+    pub weekday: Weekday,
+    pub hour: u8,
+}
+
+/// Consecutive target hours (UTC) the device was mostly off for
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoverageGap {
+    pub weekday: Weekday,
+    pub start_hour: u8,
+    pub hours: u8,
+    /// Days in the window this stretch had any measurement on, and days it occurred on
+    pub covered_days: u32,
+    pub occurrences: u32,
+}
+
+impl CoverageGap {
+    /// "Leave SpeedKarma running Tuesday 7–10 PM", in the local time of `utc_offset`
+    pub fn advice(&self, utc_offset: FixedOffset) -> String {
+        let week = 7 * 24 * 60;
+        let start = (self.weekday.num_days_from_monday() as i32 * 24 * 60 + self.start_hour as i32 * 60 + utc_offset.local_minus_utc() / 60).rem_euclid(week);
+        let end = (start + self.hours as i32 * 60) % (24 * 60);
+        let weekday = Weekday::try_from((start / (24 * 60)) as u8).unwrap_or(Weekday::Mon);
+        let (from, from_pm) = clock(start % (24 * 60));
+        let (to, to_pm) = clock(end);
+        let from = if from_pm == to_pm { from } else { format!("{} {}", from, if from_pm { "PM" } else { "AM" }) };
+        format!("Leave SpeedKarma running {} {}–{} {}", weekday_name(weekday), from, to, if to_pm { "PM" } else { "AM" })
+    }
+}
+
+/// 12-hour clock for minutes after midnight, without the meridiem
+fn clock(minutes: i32) -> (String, bool) {
+    let (hour, minute) = (minutes / 60, minutes % 60);
+    let display = if hour % 12 == 0 { 12 } else { hour % 12 };
+    let text = if minute == 0 { display.to_string() } else { format!("{}:{:02}", display, minute) };
+    (text, hour >= 12)
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// Hours of the week (UTC, like the patterns themselves) covered by suspected throttling windows
+pub fn pattern_hours(patterns: &[ThrottlingPattern]) -> Vec<HourSlot> {
+    let mut slots: Vec<HourSlot> = Vec::new();
+    for pattern in patterns {
+        let span = (pattern.end_hour as i32 - pattern.start_hour as i32).rem_euclid(24) + 1;
+        for day in &pattern.days_of_week {
+            for offset in 0..span {
+                let slot = (day.num_days_from_monday() as i32 * 24 + pattern.start_hour as i32 + offset) % 168;
+                let slot = HourSlot { weekday: Weekday::try_from((slot / 24) as u8).unwrap_or(Weekday::Mon), hour: (slot % 24) as u8 };
+                if !slots.contains(&slot) {
+                    slots.push(slot);
+                }
+            }
+        }
+    }
+    slots
+}
+
+/// UTC hours of the week falling into `DEFAULT_EVENING_HOURS` local time
+pub fn evening_hours(utc_offset: FixedOffset) -> Vec<HourSlot> {
+    let offset_hours = (utc_offset.local_minus_utc() as f64 / 3600.0).round() as i32;
+    (0..168)
+        .filter(|slot| DEFAULT_EVENING_HOURS.contains(&(((slot % 24) + offset_hours).rem_euclid(24) as u32)))
+        .map(|slot| HourSlot { weekday: Weekday::try_from((slot / 24) as u8).unwrap_or(Weekday::Mon), hour: (slot % 24) as u8 })
+        .collect()
+}
+
+/// Target hours that had a measurement on fewer than half of their days in the `days` before
+/// `now`, merged into consecutive stretches. Hours that occurred fewer than twice are skipped.
+pub fn target_coverage_gaps(measurements: &[SpeedMeasurement], now: DateTime<Utc>, days: u32, targets: &[HourSlot]) -> Vec<CoverageGap> {
+    let since = now - Duration::days(days as i64);
+    let seen: HashSet<(NaiveDate, u32)> = measurements
+        .iter()
+        .filter(|m| m.timestamp >= since && m.timestamp <= now)
+        .map(|m| (m.timestamp.date_naive(), m.timestamp.hour()))
+        .collect();
+    let slot_of = |s: &HourSlot| s.weekday.num_days_from_monday() as usize * 24 + s.hour as usize;
+
+    // Per slot: (days covered, days it occurred on), counting only hours wholly inside the window
+    let mut tally = [(0u32, 0u32); 168];
+    let mut hour = since.date_naive().and_hms_opt(since.hour(), 0, 0).map(|t| t.and_utc()).unwrap_or(since) + Duration::hours(1);
+    while hour + Duration::hours(1) <= now {
+        let slot = hour.weekday().num_days_from_monday() as usize * 24 + hour.hour() as usize;
+        tally[slot].1 += 1;
+        if seen.contains(&(hour.date_naive(), hour.hour())) {
+            tally[slot].0 += 1;
+        }
+        hour += Duration::hours(1);
+    }
+
+    let mut missing: Vec<usize> = targets
+        .iter()
+        .map(slot_of)
+        .filter(|&slot| tally[slot].1 >= 2 && (tally[slot].0 as f64) < tally[slot].1 as f64 * TARGET_COVERAGE)
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+
+    let mut gaps: Vec<CoverageGap> = Vec::new();
+    for slot in missing {
+        if let Some(last) = gaps.last_mut() {
+            if slot_of(&HourSlot { weekday: last.weekday, hour: last.start_hour }) + last.hours as usize == slot {
+                last.hours += 1;
+                last.covered_days = last.covered_days.max(tally[slot].0);
+                last.occurrences = last.occurrences.max(tally[slot].1);
+                continue;
+            }
+        }
+        gaps.push(CoverageGap {
+            weekday: Weekday::try_from((slot / 24) as u8).unwrap_or(Weekday::Mon),
+            start_hour: (slot % 24) as u8,
+            hours: 1,
+            covered_days: tally[slot].0,
+            occurrences: tally[slot].1,
+        });
+    }
+    // A stretch running past Sunday midnight continues the one starting Monday 00:00
+    if gaps.len() > 1 {
+        let (first, last) = (&gaps[0], &gaps[gaps.len() - 1]);
+        if (first.weekday, first.start_hour) == (Weekday::Mon, 0) && slot_of(&HourSlot { weekday: last.weekday, hour: last.start_hour }) + last.hours as usize == 168 {
+            let first = gaps.remove(0);
+            let last = gaps.last_mut().expect("more than one gap");
+            last.hours += first.hours;
+            last.covered_days = last.covered_days.max(first.covered_days);
+            last.occurrences = last.occurrences.max(first.occurrences);
+        }
+    }
+    gaps
+}
+
+/// What the model is trained on and what it still lacks, for `get_training_dataset_stats`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrainingDatasetStats {
+    pub window_days: u32,
+    pub total_rows: u32,
+    pub optimized_rows: u32,
+    pub baseline_rows: u32,
+    /// Optimized rows per baseline row; `None` without baseline rows
+    pub optimized_to_baseline: Option<f64>,
+    pub per_day: Vec<DayCount>,
+    pub confidence_distribution: Vec<ConfidenceBucket>,
+    /// Share of the 168 hours of the week with at least one measurement
+    pub hour_of_week_coverage: f64,
+    pub uncovered_hours: Vec<HourSlot>,
+    pub gaps: Vec<DataGap>,
+    /// Suspected throttling hours (or evenings) the device is usually off for
+    pub target_gaps: Vec<CoverageGap>,
+    /// Plain-language list of the data that would help most
+    pub needs: Vec<String>,
+}
+
+impl TrainingDatasetStats {
+    /// Stats over the `days` before `now`
+    pub fn from_measurements(measurements: &[SpeedMeasurement], now: DateTime<Utc>, days: u32) -> Self {
+        let since = now - Duration::days(days as i64);
+        let mut rows: Vec<&SpeedMeasurement> = measurements.iter().filter(|m| m.timestamp >= since && m.timestamp <= now).collect();
+        rows.sort_by_key(|m| m.timestamp);
+
+        let first_day = since.date_naive();
+        let mut per_day: Vec<DayCount> = (0..=(now.date_naive() - first_day).num_days())
+            .map(|i| DayCount { date: first_day + Duration::days(i), total: 0, optimized: 0, baseline: 0 })
+            .collect();
+        let buckets = (1.0 / BUCKET_WIDTH).round() as usize;
+        let mut confidence_distribution: Vec<ConfidenceBucket> = (0..buckets)
+            .map(|i| ConfidenceBucket { lower: i as f64 * BUCKET_WIDTH, upper: (i + 1) as f64 * BUCKET_WIDTH, count: 0 })
+            .collect();
+        let mut covered = [false; 168];
+
+        for m in &rows {
+            let day = &mut per_day[(m.timestamp.date_naive() - first_day).num_days() as usize];
+            day.total += 1;
+            if m.optimization_active { day.optimized += 1 } else { day.baseline += 1 }
+            let bucket = ((m.confidence.clamp(0.0, 1.0) / BUCKET_WIDTH) as usize).min(buckets - 1);
+            confidence_distribution[bucket].count += 1;
+            covered[m.timestamp.weekday().num_days_from_monday() as usize * 24 + m.timestamp.hour() as usize] = true;
+        }
+
+        let uncovered_hours: Vec<HourSlot> = (0..168)
+            .filter(|&slot| !covered[slot])
+            .map(|slot| HourSlot { weekday: Weekday::try_from(slot as u8 / 24).unwrap_or(Weekday::Mon), hour: (slot % 24) as u8 })
+            .collect();
+
+        // Silences between consecutive rows, plus before the first and after the last
+        let mut edges: Vec<DateTime<Utc>> = vec![since];
+        edges.extend(rows.iter().map(|m| m.timestamp));
+        edges.push(now);
+        let gaps: Vec<DataGap> = edges
+            .windows(2)
+            .filter(|w| w[1] - w[0] >= Duration::hours(GAP_HOURS))
+            .map(|w| DataGap { from: w[0], to: w[1], hours: (w[1] - w[0]).num_minutes() as f64 / 60.0 })
+            .collect();
+
+        let total_rows = rows.len() as u32;
+        let optimized_rows = rows.iter().filter(|m| m.optimization_active).count() as u32;
+        let baseline_rows = total_rows - optimized_rows;
+        let mut stats = Self {
+            window_days: days,
+            total_rows,
+            optimized_rows,
+            baseline_rows,
+            optimized_to_baseline: (baseline_rows > 0).then(|| optimized_rows as f64 / baseline_rows as f64),
+            per_day,
+            confidence_distribution,
+            hour_of_week_coverage: (168 - uncovered_hours.len()) as f64 / 168.0,
+            uncovered_hours,
+            gaps,
+            target_gaps: Vec::new(),
+            needs: Vec::new(),
+        };
+        stats.needs = stats.describe_needs(&rows);
+        stats
+    }
+
+    /// Adds the coverage of `targets` (see `target_coverage_gaps`) with advice in local time
+    pub fn with_target_hours(mut self, measurements: &[SpeedMeasurement], now: DateTime<Utc>, targets: &[HourSlot], utc_offset: FixedOffset) -> Self {
+        self.target_gaps = target_coverage_gaps(measurements, now, self.window_days, targets);
+        for gap in &self.target_gaps {
+            self.needs.push(format!("{}: it was off for these hours on {} of {} days", gap.advice(utc_offset), gap.occurrences - gap.covered_days, gap.occurrences));
+        }
+        self
+    }
+
+    fn describe_needs(&self, rows: &[&SpeedMeasurement]) -> Vec<String> {
+        let mut needs = Vec::new();
+        let total = self.total_rows as usize;
+        if total < MIN_TRAINING_ROWS {
+            needs.push(format!("{} more measurements before the model starts learning", MIN_TRAINING_ROWS - total));
+        } else if total < FULL_CONFIDENCE_ROWS {
+            needs.push(format!("{} more measurements for full data confidence", FULL_CONFIDENCE_ROWS - total));
+        }
+        if total > 0 {
+            let optimized_share = self.optimized_rows as f64 / total as f64;
+            if self.baseline_rows == 0 {
+                needs.push("Measurements with optimization off, to compare against".to_string());
+            } else if optimized_share < MIN_OPTIMIZED_SHARE {
+                needs.push(format!(
+                    "More time with optimization on: only {:.0}% of measurements ran optimized, so effectiveness is uncertain",
+                    optimized_share * 100.0
+                ));
+            }
+            let low = rows.iter().filter(|m| m.confidence < LOW_CONFIDENCE).count();
+            if low * 2 > total {
+                needs.push(format!("Steadier measurements: {} of {} have low confidence, usually from a busy or idle link", low, total));
+            }
+        }
+        if !self.uncovered_hours.is_empty() {
+            let listed: Vec<String> = self
+                .uncovered_hours
+                .iter()
+                .take(LISTED_SLOTS)
+                .map(|s| format!("{:?} {:02}:00", s.weekday, s.hour))
+                .collect();
+            let more = self.uncovered_hours.len().saturating_sub(LISTED_SLOTS);
+            needs.push(format!(
+                "Measurements in {} hours of the week never seen yet (UTC: {}{})",
+                self.uncovered_hours.len(),
+                listed.join(", "),
+                if more > 0 { format!(" and {} more", more) } else { String::new() }
+            ));
+        }
+        if let Some(longest) = self.gaps.iter().max_by(|a, b| a.hours.partial_cmp(&b.hours).unwrap_or(std::cmp::Ordering::Equal)) {
+            needs.push(format!(
+                "Keeping SpeedKarma running: the longest gap was {:.0} hours from {}",
+                longest.hours,
+                longest.from.format("%Y-%m-%d %H:%M UTC")
+            ));
+        }
+        needs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dataset_stats() {
+        let now = Utc::now();
+        // Hourly baseline rows for the last two days, a few optimized ones, nothing before
+        let mut rows: Vec<SpeedMeasurement> = (1..=48)
+            .map(|h| SpeedMeasurement { timestamp: now - Duration::hours(h), ..SpeedMeasurement::new(30.0, 5.0, 20, false) })
+            .collect();
+        for h in 1..=4 {
+            rows.push(SpeedMeasurement { timestamp: now - Duration::minutes(h * 60 + 30), confidence: 0.3, ..SpeedMeasurement::new(45.0, 5.0, 20, true) });
+        }
+
+        let stats = TrainingDatasetStats::from_measurements(&rows, now, 7);
+        assert_eq!((stats.total_rows, stats.optimized_rows, stats.baseline_rows), (52, 4, 48));
+        assert_eq!(stats.optimized_to_baseline, Some(4.0 / 48.0));
+        assert_eq!(stats.per_day.len(), 8);
+        assert_eq!(stats.per_day.iter().map(|d| d.total).sum::<u32>(), 52);
+        assert_eq!(stats.confidence_distribution[1].count, 4);
+        // Two days cover every hour of those two weekdays
+        assert_eq!(stats.uncovered_hours.len(), 168 - 48);
+        assert_eq!(stats.gaps.len(), 1);
+        assert!(stats.gaps[0].hours >= 5.0 * 24.0);
+        assert!(stats.needs.iter().any(|n| n.contains("optimization on")));
+        assert!(stats.needs.iter().any(|n| n.contains("hours of the week")));
+
+        let empty = TrainingDatasetStats::from_measurements(&[], now, 7);
+        assert_eq!(empty.total_rows, 0);
+        assert_eq!(empty.hour_of_week_coverage, 0.0);
+        assert!(empty.needs[0].starts_with("50 more measurements"));
+    }
+
+    #[test]
+    fn test_evening_coverage_gaps() {
+        // Four weeks of hourly rows, except Tuesdays 13:00–15:59 UTC (7–10 PM at +6:00)
+        let now = DateTime::parse_from_rfc3339("2026-10-17T12:00:00Z").unwrap().with_timezone(&Utc);
+        let rows: Vec<SpeedMeasurement> = (1..=28 * 24)
+            .map(|h| SpeedMeasurement { timestamp: now - Duration::hours(h) + Duration::minutes(10), ..SpeedMeasurement::new(30.0, 5.0, 20, false) })
+            .filter(|m| !(m.timestamp.weekday() == Weekday::Tue && (13..16).contains(&m.timestamp.hour())))
+            .collect();
+        let offset = FixedOffset::east_opt(6 * 3600).unwrap();
+
+        let gaps = target_coverage_gaps(&rows, now, 28, &evening_hours(offset));
+        assert_eq!(gaps, vec![CoverageGap { weekday: Weekday::Tue, start_hour: 13, hours: 3, covered_days: 0, occurrences: 4 }]);
+        assert_eq!(gaps[0].advice(offset), "Leave SpeedKarma running Tuesday 7–10 PM");
+        assert_eq!(gaps[0].advice(FixedOffset::east_opt(5 * 3600 + 1800).unwrap()), "Leave SpeedKarma running Tuesday 6:30–9:30 PM");
+        assert_eq!(gaps[0].advice(FixedOffset::west_opt(4 * 3600).unwrap()), "Leave SpeedKarma running Tuesday 9 AM–12 PM");
+
+        // A known pattern wrapping midnight Sunday is watched as one stretch
+        let pattern = ThrottlingPattern::new(1, 22, 0, 1, 59, vec![Weekday::Sun], 0.5);
+        let targets = pattern_hours(&[pattern]);
+        assert_eq!(targets.len(), 4);
+        let wrapped = target_coverage_gaps(&[], now, 28, &targets);
+        assert_eq!(wrapped.len(), 1);
+        assert_eq!((wrapped[0].weekday, wrapped[0].start_hour, wrapped[0].hours), (Weekday::Sun, 22, 4));
+
+        let stats = TrainingDatasetStats::from_measurements(&rows, now, 28).with_target_hours(&rows, now, &evening_hours(offset), offset);
+        assert!(stats.needs.iter().any(|n| n.contains("Tuesday 7–10 PM") && n.contains("4 of 4 days")));
+    }
+}
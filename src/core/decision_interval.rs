@@ -0,0 +1,106 @@
+use crate::core::config::DecisionIntervalConfig;
+use crate::core::events::{EventSink, SharedEventSink};
+use crate::core::intelligence::TimeRange;
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDateTime, NaiveTime, Timelike};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Events after which the decision engine re-evaluates without waiting for its next round
+pub const WAKE_EVENTS: &[&str] = &["interface_change", "speed_alert", "conflict_status", "power_state"];
+
+/// Window starts and ends from a day before `now` to a week after it. A window that runs past
+/// midnight ends on the day after the one it starts on.
+fn boundaries(now: NaiveDateTime, windows: &[TimeRange]) -> Vec<NaiveDateTime> {
+    let mut out = Vec::new();
+    for offset in -1..=7 {
+        let day = now.date() + ChronoDuration::days(offset);
+        let weekday = day.weekday().num_days_from_sunday() as u8;
+        for window in windows.iter().filter(|w| w.days_of_week.contains(&weekday)) {
+            let time = |hour: u8, minute: u8| NaiveTime::from_hms_opt(hour as u32 % 24, minute as u32 % 60, 0);
+            let (Some(start), Some(end)) = (time(window.start_hour, window.start_minute), time(window.end_hour, window.end_minute)) else { continue };
+            out.push(day.and_time(start));
+            out.push(if end > start { day.and_time(end) } else { (day + ChronoDuration::days(1)).and_time(end) });
+        }
+    }
+    out
+}
+
+/// Wait before the next round at local time `now`: the minimum around a window boundary, the
+/// night gap overnight, the base gap otherwise, and never sleeping past the run-up to a boundary
+pub fn next_delay(now: NaiveDateTime, config: &DecisionIntervalConfig, windows: &[TimeRange]) -> Duration {
+    let minutes = |m: u32| ChronoDuration::minutes(m as i64);
+    let (min, margin) = (minutes(config.min_minutes.max(1)), minutes(config.boundary_margin_minutes));
+    let hour = now.hour() as u8;
+    let night = if config.night_start_hour <= config.night_end_hour {
+        (config.night_start_hour..config.night_end_hour).contains(&hour)
+    } else {
+        hour >= config.night_start_hour || hour < config.night_end_hour
+    };
+    let mut delay = minutes(if night { config.night_minutes } else { config.base_minutes });
+    for boundary in boundaries(now, windows) {
+        let until = boundary - now;
+        if until.num_seconds().abs() <= margin.num_seconds() {
+            delay = min;
+        } else if until > ChronoDuration::zero() {
+            delay = delay.min(until - margin);
+        }
+    }
+    delay.max(min).to_std().unwrap_or(Duration::from_secs(60))
+}
+
+/// Forwards events and wakes the decision engine on the ones in `WAKE_EVENTS`
+pub struct WakingEventSink {
+    inner: SharedEventSink,
+    wake: Arc<Notify>,
+}
+
+impl WakingEventSink {
+    pub fn new(inner: SharedEventSink, wake: Arc<Notify>) -> Self {
+        Self { inner, wake }
+    }
+}
+
+impl EventSink for WakingEventSink {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        if WAKE_EVENTS.contains(&event) {
+            self.wake.notify_one();
+        }
+        self.inner.emit(event, payload);
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        self.inner.notify(title, body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::events::NullEventSink;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_delay_tightens_near_windows_and_relaxes_at_night() {
+        let config = DecisionIntervalConfig::default();
+        // 2024-06-07 is a Friday (5); throttled 19:00-23:30 on Fridays
+        let evening = TimeRange { start_hour: 19, start_minute: 0, end_hour: 23, end_minute: 30, days_of_week: vec![5] };
+        let at = |day: u32, hour: u32, minute: u32| NaiveDate::from_ymd_opt(2024, 6, day).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+        let mins = |m: u64| Duration::from_secs(m * 60);
+
+        assert_eq!(next_delay(at(7, 12, 0), &config, &[evening.clone()]), mins(15));
+        assert_eq!(next_delay(at(7, 3, 0), &config, &[evening.clone()]), mins(60));
+        // Wakes at the run-up to the window rather than sleeping through its start
+        assert_eq!(next_delay(at(7, 18, 40), &config, &[evening.clone()]), mins(10));
+        assert_eq!(next_delay(at(7, 18, 55), &config, &[evening.clone()]), mins(3));
+        assert_eq!(next_delay(at(7, 23, 35), &config, &[evening.clone()]), mins(3));
+        // Saturday has no window
+        assert_eq!(next_delay(at(8, 18, 55), &config, &[evening]), mins(15));
+
+        let wake = Arc::new(Notify::new());
+        let sink = WakingEventSink::new(Arc::new(NullEventSink), Arc::clone(&wake));
+        sink.emit("keeper_progress", serde_json::Value::Null);
+        sink.emit("interface_change", serde_json::Value::Null);
+        tokio::time::timeout(Duration::from_millis(100), wake.notified()).await.expect("interface change wakes the engine");
+    }
+}
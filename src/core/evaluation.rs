@@ -0,0 +1,183 @@
+use crate::core::error::Result;
+use crate::core::intelligence::without_vpn;
+use crate::data::models::{ModelQualityMetric, SpeedMeasurement};
+use crate::data::repository::Repository;
+use crate::data::stores::MeasurementStore;
+use chrono::{Duration, NaiveDate, Timelike, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Hourly performance below this counts as throttled, the same cut-off the intelligence core uses
+const THROTTLED_SCORE: f64 = 0.6;
+
+/// Samples an hour needs before its average is trusted
+const MIN_HOUR_SAMPLES: usize = 3;
+
+/// History the prediction is trained on, matching `train_model`
+const TRAINING_DAYS: i64 = 30;
+
+/// Longest model-quality history the panel can ask for
+pub const MAX_QUALITY_HISTORY_DAYS: u32 = 365;
+
+/// How often the job checks whether yesterday still needs scoring
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Mean performance score per UTC hour, for hours with enough samples
+pub fn hourly_scores(measurements: &[SpeedMeasurement]) -> HashMap<u8, f64> {
+    let mut by_hour: HashMap<u8, Vec<f64>> = HashMap::new();
+    for m in measurements {
+        by_hour.entry(m.timestamp.hour() as u8).or_default().push(m.performance_score());
+    }
+    by_hour
+        .into_iter()
+        .filter(|(_, scores)| scores.len() >= MIN_HOUR_SAMPLES)
+        .map(|(hour, scores)| (hour, scores.iter().sum::<f64>() / scores.len() as f64))
+        .collect()
+}
+
+/// Scores predicted hourly weights against what one day actually showed.
+/// Hours the model has no weight for are treated as predicted unthrottled.
+pub fn score_day(
+    day: NaiveDate,
+    predicted: &HashMap<u8, f64>,
+    observed: &HashMap<u8, f64>,
+    training_samples: u32,
+) -> ModelQualityMetric {
+    let (mut tp, mut fp, mut fn_, mut tn) = (0u32, 0u32, 0u32, 0u32);
+    for (hour, &actual) in observed {
+        let predicted_throttled = predicted.get(hour).is_some_and(|&w| w < THROTTLED_SCORE);
+        match (predicted_throttled, actual < THROTTLED_SCORE) {
+            (true, true) => tp += 1,
+            (true, false) => fp += 1,
+            (false, true) => fn_ += 1,
+            (false, false) => tn += 1,
+        }
+    }
+
+    let ratio = |num: u32, den: u32| (den > 0).then(|| num as f64 / den as f64);
+    let precision = ratio(tp, tp + fp);
+    let recall = ratio(tp, tp + fn_);
+    let f1 = match (precision, recall) {
+        (Some(p), Some(r)) if p + r > 0.0 => Some(2.0 * p * r / (p + r)),
+        (Some(_), Some(_)) => Some(0.0),
+        _ => None,
+    };
+
+    ModelQualityMetric {
+        id: None,
+        evaluated_date: day,
+        computed_at: Utc::now(),
+        hours_evaluated: observed.len() as u32,
+        true_positives: tp,
+        false_positives: fp,
+        false_negatives: fn_,
+        true_negatives: tn,
+        precision,
+        recall,
+        f1,
+        training_samples,
+    }
+}
+
+/// Backtests one UTC day: trains on the preceding window only, then compares with the day itself.
+/// Returns `None` when either side has too little data to score.
+pub async fn evaluate_day(store: &dyn MeasurementStore, day: NaiveDate) -> Result<Option<ModelQualityMetric>> {
+    let Some(day_start) = day.and_hms_opt(0, 0, 0).map(|t| t.and_utc()) else { return Ok(None) };
+    let day_end = day_start + Duration::days(1);
+    let measurements = without_vpn(&store.get_speed_measurements_since(day_start - Duration::days(TRAINING_DAYS)).await?);
+
+    let (training, evaluated): (Vec<SpeedMeasurement>, Vec<SpeedMeasurement>) = measurements
+        .into_iter()
+        .filter(|m| m.timestamp < day_end)
+        .partition(|m| m.timestamp < day_start);
+
+    let predicted = hourly_scores(&training);
+    let observed = hourly_scores(&evaluated);
+    if predicted.is_empty() || observed.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(score_day(day, &predicted, &observed, training.len() as u32)))
+}
+
+/// Nightly job: once a UTC day is over, scores the model's predictions for it and stores the result
+pub fn start_evaluation_job(repository: Arc<Repository>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(yesterday) = Utc::now().date_naive().pred_opt() else { continue };
+            match repository.get_model_quality_metrics_since(yesterday).await {
+                Ok(existing) if existing.iter().any(|m| m.evaluated_date == yesterday) => continue,
+                Ok(_) => {}
+                Err(e) => { warn!("Model evaluation lookup failed: {}", e); continue; }
+            }
+
+            match evaluate_day(repository.as_ref(), yesterday).await {
+                Ok(Some(metric)) => {
+                    info!(
+                        "Model backtest for {}: precision {:?}, recall {:?} over {} hours",
+                        yesterday, metric.precision, metric.recall, metric.hours_evaluated
+                    );
+                    if let Err(e) = repository.save_model_quality_metric(&metric).await {
+                        warn!("Failed to store model quality metric: {}", e);
+                    }
+                }
+                Ok(None) => debug!("Not enough data to backtest {}", yesterday),
+                Err(e) => warn!("Model evaluation failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::memory_store::InMemoryStore;
+
+    #[test]
+    fn test_score_day_precision_recall() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        // Model expects throttling at 19 and 20; the day was actually slow at 20 and 21
+        let predicted = HashMap::from([(18, 0.9), (19, 0.4), (20, 0.3)]);
+        let observed = HashMap::from([(18, 0.8), (19, 0.7), (20, 0.2), (21, 0.3), (22, 0.9)]);
+        let metric = score_day(day, &predicted, &observed, 100);
+
+        assert_eq!((metric.true_positives, metric.false_positives, metric.false_negatives, metric.true_negatives), (1, 1, 1, 2));
+        assert_eq!(metric.precision, Some(0.5));
+        assert_eq!(metric.recall, Some(0.5));
+        assert_eq!(metric.f1, Some(0.5));
+
+        let quiet = score_day(day, &HashMap::from([(18, 0.9)]), &HashMap::from([(18, 0.9)]), 10);
+        assert_eq!((quiet.precision, quiet.recall, quiet.f1), (None, None, None));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_day_uses_only_prior_history() {
+        let store = InMemoryStore::new();
+        let day = (Utc::now() - Duration::days(1)).date_naive();
+        let at = |days_before: i64, hour: u32, mbps: f64| {
+            let mut m = SpeedMeasurement::new(mbps, 20.0, 20, false);
+            m.timestamp = (day - Duration::days(days_before)).and_hms_opt(hour, 10, 0).unwrap().and_utc();
+            m
+        };
+        for days_before in 1..=3 {
+            for _ in 0..3 {
+                store.save_speed_measurement(&at(days_before, 20, 5.0)).await.unwrap();
+                store.save_speed_measurement(&at(days_before, 10, 100.0)).await.unwrap();
+            }
+        }
+        for _ in 0..3 {
+            store.save_speed_measurement(&at(0, 20, 4.0)).await.unwrap();
+            store.save_speed_measurement(&at(0, 10, 90.0)).await.unwrap();
+        }
+
+        let metric = evaluate_day(&store, day).await.unwrap().unwrap();
+        assert_eq!(metric.hours_evaluated, 2);
+        assert_eq!(metric.training_samples, 18);
+        assert_eq!(metric.precision, Some(1.0));
+        assert_eq!(metric.recall, Some(1.0));
+
+        assert!(evaluate_day(&store, day - Duration::days(10)).await.unwrap().is_none());
+    }
+}
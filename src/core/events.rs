@@ -0,0 +1,39 @@
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Outbound channel for UI events and user notifications.
+/// Engine modules talk to this instead of a Tauri handle so they build without the desktop shell.
+pub trait EventSink: Send + Sync {
+    /// Broadcasts a named event with a JSON payload
+    fn emit(&self, event: &str, payload: serde_json::Value);
+
+    /// Shows a user-facing notification
+    fn notify(&self, title: &str, body: &str);
+}
+
+pub type SharedEventSink = Arc<dyn EventSink>;
+
+impl dyn EventSink {
+    /// Serializes `payload` and emits it, dropping payloads that fail to serialize
+    pub fn emit_payload<T: Serialize>(&self, event: &str, payload: &T) {
+        match serde_json::to_value(payload) {
+            Ok(value) => self.emit(event, value),
+            Err(e) => debug!("Dropping {} event: {}", event, e),
+        }
+    }
+}
+
+/// Sink for headless runs and tests: events are only traced
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullEventSink;
+
+impl EventSink for NullEventSink {
+    fn emit(&self, event: &str, _payload: serde_json::Value) {
+        debug!("Event {} (no listener)", event);
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        debug!("Notification {}: {}", title, body);
+    }
+}
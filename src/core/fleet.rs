@@ -0,0 +1,168 @@
+use crate::core::config::{FleetConfig, FleetSite};
+use crate::core::control_api::SiteSummary;
+use crate::core::error::Result;
+use crate::network::proxy;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tracing::debug;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One site's answer, or why it could not be reached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteReport {
+    pub name: String,
+    pub url: String,
+    pub summary: Option<SiteSummary>,
+    pub error: Option<String>,
+}
+
+/// Every configured site polled at once, with fleet-wide totals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FleetSummary {
+    pub polled_at: DateTime<Utc>,
+    /// In configuration order
+    pub sites: Vec<SiteReport>,
+    pub reachable: usize,
+    /// Sites with at least one stored throttling pattern
+    pub throttled: usize,
+    pub optimizing: usize,
+    /// Mean improvement factor over the sites that report one
+    pub average_improvement: Option<f64>,
+    /// Alert episodes of the last day still open, across all sites
+    pub ongoing_alerts: usize,
+}
+
+impl FleetSummary {
+    pub fn from_reports(sites: Vec<SiteReport>) -> Self {
+        let summaries: Vec<&SiteSummary> = sites.iter().filter_map(|s| s.summary.as_ref()).collect();
+        let improvements: Vec<f64> = summaries.iter().filter_map(|s| s.effectiveness.as_ref()).map(|e| e.improvement_factor).collect();
+        Self {
+            polled_at: Utc::now(),
+            reachable: summaries.len(),
+            throttled: summaries.iter().filter(|s| !s.throttling.is_empty()).count(),
+            optimizing: summaries.iter().filter(|s| s.optimization_enabled).count(),
+            average_improvement: (!improvements.is_empty()).then(|| improvements.iter().sum::<f64>() / improvements.len() as f64),
+            ongoing_alerts: summaries.iter().flat_map(|s| &s.alerts).filter(|a| a.ended_at.is_none()).count(),
+            sites,
+        }
+    }
+}
+
+fn client() -> Result<reqwest::Client> {
+    Ok(proxy::apply(reqwest::Client::builder()).timeout(REQUEST_TIMEOUT).build()?)
+}
+
+/// Another instance's control API, such as a router the desktop app is paired with
+#[derive(Debug, Clone)]
+pub struct RemoteInstance {
+    client: reqwest::Client,
+    site: FleetSite,
+}
+
+impl RemoteInstance {
+    pub fn new(site: FleetSite) -> Result<Self> {
+        Ok(Self { client: client()?, site })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.site.url.trim_end_matches('/'), path)
+    }
+
+    async fn send(&self, method: reqwest::Method, path: &str) -> std::result::Result<reqwest::Response, reqwest::Error> {
+        self.client.request(method, self.url(path)).bearer_auth(&self.site.token).send().await?.error_for_status()
+    }
+
+    pub async fn status(&self) -> Result<serde_json::Value> {
+        Ok(self.send(reqwest::Method::GET, "/v1/status").await?.json().await?)
+    }
+
+    pub async fn summary(&self) -> Result<SiteSummary> {
+        Ok(self.send(reqwest::Method::GET, "/v1/summary").await?.json().await?)
+    }
+
+    /// The instance's optimization state after the switch
+    pub async fn toggle_optimization(&self) -> Result<serde_json::Value> {
+        Ok(self.send(reqwest::Method::POST, "/v1/optimization/toggle").await?.json().await?)
+    }
+
+    /// Starts a speed test on the instance's link; it waits there for a running call to end
+    pub async fn run_speedtest(&self) -> Result<()> {
+        self.send(reqwest::Method::POST, "/v1/speedtest").await?;
+        Ok(())
+    }
+
+    /// The instance's summary, or why it could not be reached
+    pub async fn report(&self) -> SiteReport {
+        let site = self.site.clone();
+        match self.summary().await {
+            Ok(summary) => SiteReport { name: site.name, url: site.url, summary: Some(summary), error: None },
+            Err(e) => {
+                debug!("Fleet site {} unreachable: {}", site.name, e);
+                SiteReport { name: site.name, url: site.url, summary: None, error: Some(e.to_string()) }
+            }
+        }
+    }
+}
+
+/// Polls each site's `GET /v1/summary` in parallel. An unreachable site is reported in place
+/// rather than failing the whole summary.
+pub async fn poll(config: &FleetConfig) -> Result<FleetSummary> {
+    let client = client()?;
+    let mut requests = JoinSet::new();
+    for (index, site) in config.sites.iter().cloned().enumerate() {
+        let remote = RemoteInstance { client: client.clone(), site };
+        requests.spawn(async move { (index, remote.report().await) });
+    }
+    let mut reports: Vec<(usize, SiteReport)> = Vec::with_capacity(config.sites.len());
+    while let Some(joined) = requests.join_next().await {
+        match joined {
+            Ok(report) => reports.push(report),
+            Err(e) => debug!("Fleet poll task failed: {}", e),
+        }
+    }
+    reports.sort_by_key(|(index, _)| *index);
+    Ok(FleetSummary::from_reports(reports.into_iter().map(|(_, report)| report).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::intelligence::EffectivenessMetrics;
+    use crate::data::models::SpeedAlertEpisode;
+
+    fn site(name: &str, summary: Option<SiteSummary>) -> SiteReport {
+        let error = summary.is_none().then(|| "connection refused".to_string());
+        SiteReport { name: name.into(), url: format!("http://127.0.0.1/{}", name), summary, error }
+    }
+
+    #[test]
+    fn test_fleet_totals() {
+        let effectiveness = |improvement_factor| EffectivenessMetrics {
+            improvement_factor,
+            baseline_speed: 10.0,
+            optimized_speed: 10.0 * improvement_factor,
+            confidence: 0.9,
+            last_updated: Utc::now(),
+        };
+        let alert = |ended_at| SpeedAlertEpisode {
+            id: None,
+            started_at: Utc::now(),
+            ended_at,
+            threshold_mbps: 10.0,
+            sustained_minutes: 10,
+            min_download_mbps: 2.0,
+            avg_download_mbps: 4.0,
+            sample_count: 5,
+        };
+        let home = SiteSummary { optimization_enabled: true, effectiveness: Some(effectiveness(2.0)), alerts: vec![alert(None), alert(Some(Utc::now()))], ..Default::default() };
+        let office = SiteSummary { effectiveness: Some(effectiveness(1.0)), ..Default::default() };
+
+        let fleet = FleetSummary::from_reports(vec![site("home", Some(home)), site("office", Some(office)), site("parents", None)]);
+        assert_eq!((fleet.reachable, fleet.optimizing, fleet.throttled, fleet.ongoing_alerts), (2, 1, 0, 1));
+        assert_eq!(fleet.average_improvement, Some(1.5));
+        assert_eq!(fleet.sites[2].error.as_deref(), Some("connection refused"));
+    }
+}
@@ -1,9 +1,20 @@
-use crate::core::error::Result;
-use crate::data::models::{SpeedMeasurement, OptimizationStrategy, ThrottlingPattern, StealthLevel};
-use crate::data::repository::Repository;
+use crate::core::app_state::SharedAppState;
+use crate::core::canary;
+use crate::core::model_share;
+use crate::core::scheduler::PeriodicScheduler;
+use crate::core::shutdown::{self, CancellationToken};
+use crate::core::config::{DecisionIntervalConfig, StrategyCanaryConfig, ThroughputKeeperConfig, WebhookEvent};
+use crate::core::decision_interval;
+use crate::core::webhooks::{new_throttling_periods, WebhookNotifier};
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::core::reasons::{format_hours, Reason, ReasonCode};
+use crate::data::models::{SpeedMeasurement, MeasurementSource, MimicryProfile, OptimizationStrategy, OptimizationTrial, SatisfactionFeedback, ThrottlingPattern, StealthLevel, TrafficTransport};
+use crate::data::stores::DataStore;
+use crate::core::wifi::WifiAttribution;
 use chrono::{DateTime, Utc, Duration, Weekday, Timelike, Datelike};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 /// Core intelligence engine interface - the heart of SpeedKarma's decision making
@@ -39,10 +50,14 @@ pub struct PatternAnalysis {
     
     /// Detected ISP information
     pub isp_profile: Option<String>,
+
+    /// The ISP puts this connection behind carrier-grade NAT, so peak-hour dips may be congestion
+    #[serde(default)]
+    pub cgnat: bool,
 }
 
 /// Time range for throttling periods
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TimeRange {
     pub start_hour: u8,
     pub start_minute: u8,
@@ -55,7 +70,10 @@ pub struct TimeRange {
 #[derive(Debug, Clone)]
 pub struct OptimizationDecision {
     pub should_activate: bool,
+    /// English report rendering of `explanation`
     pub reason: String,
+    /// Reason code and variables, for localized or tray-sized rendering
+    pub explanation: Reason,
     pub confidence: f64,
     pub estimated_improvement: Option<f64>, // Expected speed multiplier
 }
@@ -67,10 +85,56 @@ pub struct SystemStatus {
     pub message: String,
     pub data_collection_progress: Option<DataCollectionProgress>,
     pub effectiveness: Option<EffectivenessMetrics>,
+    /// Most recent passively estimated speed
+    #[serde(default)]
+    pub latest_passive: Option<MeasurementSnapshot>,
+    /// Most recent result of an active speed test
+    #[serde(default)]
+    pub latest_active: Option<MeasurementSnapshot>,
+    /// Supervised background loops; filled in by `get_system_status`
+    #[serde(default)]
+    pub tasks: Vec<crate::core::supervisor::TaskHealth>,
+}
+
+/// Speed figures shown to the user, tagged with where they came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeasurementSnapshot {
+    pub source: MeasurementSource,
+    pub timestamp: DateTime<Utc>,
+    pub download_mbps: f64,
+    pub upload_mbps: f64,
+    #[serde(default)]
+    pub latency_ms: Option<u32>,
+    #[serde(default)]
+    pub packet_loss_pct: Option<f64>,
+    #[serde(default)]
+    pub jitter_ms: Option<f64>,
 }
 
-/// System operational states
+impl From<&SpeedMeasurement> for MeasurementSnapshot {
+    fn from(m: &SpeedMeasurement) -> Self {
+        Self {
+            source: m.source,
+            timestamp: m.timestamp,
+            download_mbps: m.download_mbps,
+            upload_mbps: m.upload_mbps,
+            latency_ms: m.latency_ms,
+            packet_loss_pct: m.packet_loss_pct,
+            jitter_ms: m.jitter_ms,
+        }
+    }
+}
+
+/// How long an active test result is preferred over passive estimates
+const ACTIVE_RESULT_FRESHNESS_MINUTES: i64 = 60;
+/// Hours performing below this share of normal count as throttled
+const THROTTLED_WEIGHT: f64 = 0.6;
+/// Stricter cut-off behind carrier-grade NAT, where the shared gateway alone causes shallow peak-hour dips
+const CGNAT_THROTTLED_WEIGHT: f64 = 0.5;
+
+/// System operational states; serialized as their `status_codes` identifier
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SystemState {
     /// Initial state - collecting baseline data
     Learning,
@@ -84,6 +148,19 @@ pub enum SystemState {
     Error(String),
 }
 
+impl SystemState {
+    /// Identifier in `status_codes`
+    pub fn code(&self) -> &'static str {
+        match self {
+            SystemState::Learning => "learning",
+            SystemState::Optimizing => "optimizing",
+            SystemState::Monitoring => "monitoring",
+            SystemState::Inactive => "inactive",
+            SystemState::Error(_) => "error",
+        }
+    }
+}
+
 /// Progress of data collection phase
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataCollectionProgress {
@@ -141,6 +218,9 @@ impl SystemStatus {
             message: format!("Learning your network patterns ({} of {} days)", days_collected, days_needed),
             data_collection_progress: Some(progress),
             effectiveness: None,
+            latest_passive: None,
+            latest_active: None,
+            tasks: Vec::new(),
         }
     }
     
@@ -151,6 +231,36 @@ impl SystemStatus {
             message: format!("Optimizing ({}x improvement)", effectiveness.improvement_factor),
             data_collection_progress: None,
             effectiveness: Some(effectiveness),
+            latest_passive: None,
+            latest_active: None,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Attaches the newest passive and active measurements from `measurements`
+    pub fn with_latest_measurements(mut self, measurements: &[SpeedMeasurement]) -> Self {
+        let latest = |source: MeasurementSource| {
+            measurements.iter()
+                .filter(|m| m.source == source)
+                .max_by_key(|m| m.timestamp)
+                .map(MeasurementSnapshot::from)
+        };
+        self.latest_passive = latest(MeasurementSource::Passive);
+        self.latest_active = latest(MeasurementSource::Active);
+        self
+    }
+
+    /// Figures to display for the preferred source; `None` picks a recent active test, else the passive estimate
+    pub fn displayed_measurement(&self, preferred: Option<MeasurementSource>) -> Option<&MeasurementSnapshot> {
+        match preferred {
+            Some(MeasurementSource::Passive) => self.latest_passive.as_ref(),
+            Some(MeasurementSource::Active) => self.latest_active.as_ref(),
+            None => {
+                let fresh_active = self.latest_active.as_ref().filter(|m| {
+                    Utc::now() - m.timestamp <= Duration::minutes(ACTIVE_RESULT_FRESHNESS_MINUTES)
+                });
+                fresh_active.or(self.latest_passive.as_ref()).or(self.latest_active.as_ref())
+            }
         }
     }
 }
@@ -179,6 +289,10 @@ pub struct PatternLearningModel {
     
     /// Last model update timestamp
     pub last_updated: DateTime<Utc>,
+
+    /// Trial results per transport, refreshed with the strategy effectiveness
+    #[serde(default)]
+    pub transport_effectiveness: Vec<TransportEffectiveness>,
 }
 
 /// Effectiveness data for a specific optimization strategy
@@ -201,6 +315,125 @@ pub struct StrategyEffectiveness {
     
     /// Last time this strategy was used
     pub last_used: DateTime<Utc>,
+
+    /// Smoothed share of thumbs-up answers while this strategy ran
+    #[serde(default)]
+    pub satisfaction: Option<f64>,
+}
+
+/// Trials each transport needs before the learner prefers one over the other
+pub const MIN_TRANSPORT_TRIALS: u32 = 3;
+
+/// Completed trials of one transport
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransportEffectiveness {
+    pub transport: TrafficTransport,
+    /// Mean improvement factor of its trials
+    pub avg_improvement: f64,
+    pub trial_count: u32,
+}
+
+/// Completed trials grouped by transport; trials recorded before transports were tracked ran on TCP
+pub fn transport_effectiveness(trials: &[OptimizationTrial]) -> Vec<TransportEffectiveness> {
+    TrafficTransport::ALL.into_iter().filter_map(|transport| {
+        let improvements: Vec<f64> = trials.iter()
+            .filter(|t| t.transport.unwrap_or_default() == transport)
+            .filter_map(OptimizationTrial::improvement)
+            .collect();
+        (!improvements.is_empty()).then(|| TransportEffectiveness {
+            transport,
+            avg_improvement: improvements.iter().sum::<f64>() / improvements.len() as f64,
+            trial_count: improvements.len() as u32,
+        })
+    }).collect()
+}
+
+/// Transport with the best mean improvement once every transport has `MIN_TRANSPORT_TRIALS`
+/// trials; until then the least tried one, so QUIC gets the trials it needs to be compared
+pub fn preferred_transport(effectiveness: &[TransportEffectiveness]) -> TrafficTransport {
+    let trial_count = |transport: TrafficTransport| effectiveness.iter()
+        .find(|e| e.transport == transport)
+        .map_or(0, |e| e.trial_count);
+    if let Some(untried) = TrafficTransport::ALL.into_iter()
+        .filter(|t| trial_count(*t) < MIN_TRANSPORT_TRIALS)
+        .min_by_key(|t| trial_count(*t))
+    {
+        return untried;
+    }
+    effectiveness.iter()
+        .max_by(|a, b| a.avg_improvement.total_cmp(&b.avg_improvement))
+        .map_or_else(TrafficTransport::default, |e| e.transport)
+}
+
+/// Measurements one completed trial counts for. Its before/during/after bracket controls for the
+/// time-of-day drift that pooled samples carry, so it is worth more than a single sample.
+pub const TRIAL_SAMPLE_WEIGHT: u32 = 10;
+
+/// Combines the pooled optimized-vs-baseline improvement (with its sample count) and completed
+/// trials into one improvement factor and effective sample count; `None` when there is neither
+pub fn blend_trials(measured: Option<(f64, u32)>, trials: &[OptimizationTrial]) -> Option<(f64, u32)> {
+    let (mut weighted, mut samples) = measured.map_or((0.0, 0), |(improvement, n)| (improvement * n as f64, n));
+    for improvement in trials.iter().filter_map(OptimizationTrial::improvement) {
+        weighted += improvement * TRIAL_SAMPLE_WEIGHT as f64;
+        samples += TRIAL_SAMPLE_WEIGHT;
+    }
+    (samples > 0).then(|| (weighted / samples as f64, samples))
+}
+
+/// How the given measurements' slowdowns relate to Wi-Fi, judged against their median download
+pub fn wifi_attribution(measurements: &[SpeedMeasurement]) -> Option<WifiAttribution> {
+    let mut speeds: Vec<f64> = measurements.iter().map(|m| m.download_mbps).collect();
+    if speeds.is_empty() { return None; }
+    speeds.sort_by(|a, b| a.total_cmp(b));
+    WifiAttribution::from_measurements(measurements, speeds[speeds.len() / 2])
+}
+
+/// Measurements that speak for the ISP. Samples taken through a VPN or the proxy measure the
+/// tunnel and are dropped; when slowdowns line up with weak Wi-Fi, samples taken on a weak signal are dropped too
+/// so local wireless trouble is not learned as throttling.
+pub fn isp_attributable(measurements: &[SpeedMeasurement]) -> Vec<SpeedMeasurement> {
+    let direct = without_vpn(measurements);
+    let local = wifi_attribution(&direct).is_some_and(|w| w.local_wireless);
+    direct.into_iter().filter(|m| !(local && m.on_weak_wifi())).collect()
+}
+
+/// Measurements not taken through a VPN tunnel or the proxy
+pub fn without_vpn(measurements: &[SpeedMeasurement]) -> Vec<SpeedMeasurement> {
+    measurements.iter().filter(|m| !m.tunnelled()).cloned().collect()
+}
+
+/// Measurements from the network of the newest tagged sample, so the model is trained per network.
+/// Untagged rows could come from any network and are left out once anything is tagged; history
+/// from before context tracking is backfilled with the first detected network instead.
+pub fn in_current_context(measurements: &[SpeedMeasurement]) -> Vec<SpeedMeasurement> {
+    let Some(current) = measurements.iter()
+        .filter(|m| m.network_context.is_some())
+        .max_by_key(|m| m.timestamp)
+        .and_then(|m| m.network_context.as_deref())
+    else {
+        return measurements.to_vec();
+    };
+    measurements.iter()
+        .filter(|m| m.network_context.as_deref() == Some(current))
+        .cloned()
+        .collect()
+}
+
+/// Most weight user answers can carry in a strategy's reward, so feedback never outweighs measured throughput
+const MAX_SATISFACTION_WEIGHT: f64 = 0.5;
+
+/// Folds thumbs-up/down answers into a throughput-based success rate. The weight grows with the
+/// number of answers; returns the blended rate and the smoothed approval share, if any answers exist.
+pub fn fold_satisfaction(success_rate: f64, feedback: &[SatisfactionFeedback]) -> (f64, Option<f64>) {
+    if feedback.is_empty() {
+        return (success_rate, None);
+    }
+    let answers = feedback.len() as f64;
+    let thumbs_up = feedback.iter().filter(|f| f.satisfied).count() as f64;
+    // Laplace smoothing keeps one early answer from swinging the reward to an extreme
+    let approval = (thumbs_up + 1.0) / (answers + 2.0);
+    let weight = (answers / (answers + 10.0)).min(MAX_SATISFACTION_WEIGHT);
+    ((1.0 - weight) * success_rate + weight * approval, Some(approval))
 }
 
 /// ISP-specific learning parameters
@@ -222,6 +455,40 @@ pub struct ISPLearningParams {
     pub confidence: f64,
 }
 
+/// Keeper hourly budget (MB) at which a synthesized strategy may run at its full learned intensity
+const FULL_INTENSITY_BUDGET_MB: f64 = 30.0;
+
+/// Most throttled hours a synthesized strategy is built around
+const MAX_STRATEGY_HOURS: usize = 4;
+
+/// A strategy synthesized from the learned model, together with what it was built from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyProposal {
+    pub strategy: OptimizationStrategy,
+    /// Hours (UTC) the model expects throttling, worst first
+    pub best_hours: Vec<u8>,
+    /// Measurements the model was trained on
+    pub training_samples: u32,
+    /// Whether the saved strategy was made the one optimization runs with
+    pub activated: bool,
+}
+
+/// Learned intensity eased off as detection risk grows, then scaled down when the keeper budget
+/// (hourly, or the daily budget spread over the active hours) is below the full-intensity budget
+pub fn safe_traffic_intensity(learned: f64, detection_risk: f64, budget: &ThroughputKeeperConfig, active_hours: usize) -> f64 {
+    let mut hourly_budget = budget.hourly_budget_mb;
+    if let Some(daily) = budget.daily_budget_mb {
+        hourly_budget = hourly_budget.min(daily / active_hours.max(1) as f64);
+    }
+    let budget_ratio = (hourly_budget / FULL_INTENSITY_BUDGET_MB).clamp(0.0, 1.0);
+    (learned * (1.0 - 0.5 * detection_risk.clamp(0.0, 1.0)) * budget_ratio).clamp(0.0, 1.0)
+}
+
+/// Packet timing window and connection count for a detection risk
+fn pacing_for_risk(detection_risk: f64) -> (f64, f64, u8) {
+    if detection_risk > 0.7 { (45.0, 90.0, 2) } else { (30.0, 60.0, 3) }
+}
+
 /// Effectiveness analysis result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EffectivenessAnalysis {
@@ -279,8 +546,11 @@ pub struct OptimizationRecommendation {
     /// Type of recommendation
     pub recommendation_type: RecommendationType,
     
-    /// Human-readable description
+    /// Human-readable description (English report rendering of `explanation`)
     pub description: String,
+
+    /// Reason code and variables behind the description
+    pub explanation: Reason,
     
     /// Expected improvement if followed
     pub expected_improvement: f64,
@@ -292,6 +562,20 @@ pub struct OptimizationRecommendation {
     pub priority: u8,
 }
 
+impl OptimizationRecommendation {
+    /// Builds a recommendation whose description is rendered from `explanation`
+    pub fn new(recommendation_type: RecommendationType, explanation: Reason, expected_improvement: f64, confidence: f64, priority: u8) -> Self {
+        Self {
+            recommendation_type,
+            description: explanation.to_report_string(),
+            explanation,
+            expected_improvement,
+            confidence,
+            priority,
+        }
+    }
+}
+
 /// Types of optimization recommendations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RecommendationType {
@@ -312,13 +596,24 @@ pub enum RecommendationType {
     
     /// ISP-specific optimization
     ISPOptimization,
+    
+    /// Fix the local network (e.g. weak Wi-Fi) rather than optimize against the ISP
+    LocalNetwork,
 }
 
 /// Default intelligence core implementation with machine learning
 pub struct DefaultIntelligenceCore {
-    pub repository: Arc<Repository>,
+    pub repository: Arc<dyn DataStore>,
+    /// Model the decisions read: the local training with imported priors blended in
     pub learning_model: PatternLearningModel,
+    /// What local training produced, before priors; each round starts from it so priors never compound
+    local_model: PatternLearningModel,
     min_learning_days: u32,
+    strategy_canary: StrategyCanaryConfig,
+    /// Imported model blended in after each training round
+    priors_path: Option<PathBuf>,
+    /// Strategy pinned by the user; returned as the optimal one while set
+    strategy_override: Option<OptimizationStrategy>,
 }
 
 impl Default for PatternLearningModel {
@@ -331,6 +626,7 @@ impl Default for PatternLearningModel {
             model_confidence: 0.0,
             training_samples: 0,
             last_updated: Utc::now(),
+            transport_effectiveness: Vec::new(),
         }
     }
 }
@@ -344,25 +640,34 @@ impl Default for StrategyEffectiveness {
             confidence: 0.0,
             trend: 0.0,
             last_used: Utc::now(),
+            satisfaction: None,
         }
     }
 }
 
 impl DefaultIntelligenceCore {
-    pub fn new(repository: Arc<Repository>) -> Self {
+    pub fn new(repository: Arc<dyn DataStore>) -> Self {
         Self {
             repository,
             learning_model: PatternLearningModel::default(),
+            local_model: PatternLearningModel::default(),
             min_learning_days: 7, // Minimum 7 days of data before making recommendations
+            strategy_canary: StrategyCanaryConfig::default(),
+            priors_path: None,
+            strategy_override: None,
         }
     }
 
     /// Creates a core with a custom minimum learning days value
-    pub fn with_min_learning_days(repository: Arc<Repository>, min_learning_days: u32) -> Self {
+    pub fn with_min_learning_days(repository: Arc<dyn DataStore>, min_learning_days: u32) -> Self {
         Self {
             repository,
             learning_model: PatternLearningModel::default(),
+            local_model: PatternLearningModel::default(),
             min_learning_days,
+            strategy_canary: StrategyCanaryConfig::default(),
+            priors_path: None,
+            strategy_override: None,
         }
     }
 
@@ -371,10 +676,30 @@ impl DefaultIntelligenceCore {
         self.min_learning_days = days;
     }
 
+    /// The model as trained on this machine, without imported priors
+    pub fn local_model(&self) -> &PatternLearningModel {
+        &self.local_model
+    }
+
+    /// Canary window applied when a strategy is activated
+    pub fn set_strategy_canary(&mut self, config: StrategyCanaryConfig) {
+        self.strategy_canary = config;
+    }
+
+    /// File holding an imported model; read on every training round so a new import applies without a restart
+    pub fn set_priors_path(&mut self, path: Option<PathBuf>) {
+        self.priors_path = path;
+    }
+
+    /// Pins the strategy `get_optimal_strategy` returns; `None` restores automatic selection
+    pub fn set_strategy_override(&mut self, strategy: Option<OptimizationStrategy>) {
+        self.strategy_override = strategy;
+    }
+
     /// Perform comprehensive effectiveness analysis
     pub async fn analyze_effectiveness(&self) -> Result<EffectivenessAnalysis> {
         let since = Utc::now() - Duration::days(30);
-        let measurements = self.repository.get_speed_measurements_since(since).await?;
+        let measurements = in_current_context(&without_vpn(&self.repository.get_speed_measurements_since(since).await?));
         
         if measurements.len() < 50 {
             return Ok(EffectivenessAnalysis {
@@ -546,14 +871,14 @@ impl DefaultIntelligenceCore {
     /// Advanced pattern learning with statistical analysis
     pub async fn learn_advanced_patterns(&mut self) -> Result<()> {
         let since = Utc::now() - Duration::days(60); // Use 60 days for advanced learning
-        let measurements = self.repository.get_speed_measurements_since(since).await?;
+        let measurements = in_current_context(&without_vpn(&self.repository.get_speed_measurements_since(since).await?));
         
         if measurements.len() < 100 {
             return Ok(()); // Not enough data for advanced learning
         }
 
         // Learn temporal patterns with statistical significance
-        self.learn_temporal_patterns_advanced(&measurements).await?;
+        self.learn_temporal_patterns_advanced(&isp_attributable(&measurements)).await?;
         
         // Learn ISP-specific patterns
         self.learn_isp_patterns(&measurements).await?;
@@ -695,13 +1020,20 @@ impl DefaultIntelligenceCore {
             .filter(|m| !m.optimization_active)
             .collect();
 
-        if optimized_measurements.len() >= 10 && baseline_measurements.len() >= 10 {
-            // Calculate overall effectiveness
+        let measured = (optimized_measurements.len() >= 10 && baseline_measurements.len() >= 10).then(|| {
             let baseline_avg = baseline_measurements.iter().map(|m| m.download_mbps).sum::<f64>() / baseline_measurements.len() as f64;
             let optimized_avg = optimized_measurements.iter().map(|m| m.download_mbps).sum::<f64>() / optimized_measurements.len() as f64;
-            
             let improvement = if baseline_avg > 0.0 { optimized_avg / baseline_avg } else { 1.0 };
+            (improvement, optimized_measurements.len() as u32)
+        });
+        let trials = self.completed_trials().await;
+        self.learning_model.transport_effectiveness = transport_effectiveness(&trials);
+
+        if let Some((improvement, sample_count)) = blend_trials(measured, &trials) {
+            // Calculate overall effectiveness
             let success_rate = if improvement > 1.2 { 1.0 } else { (improvement - 1.0).max(0.0) };
+            let (success_rate, satisfaction) = fold_satisfaction(success_rate, &self.optimizing_feedback(None).await);
+            let trial_samples = trials.len() * TRIAL_SAMPLE_WEIGHT as usize;
 
             // Calculate trend by comparing recent vs older measurements
             let recent_cutoff = Utc::now() - Duration::days(7);
@@ -729,15 +1061,16 @@ impl DefaultIntelligenceCore {
             // Update default strategy effectiveness
             let effectiveness = StrategyEffectiveness {
                 avg_improvement: improvement,
-                sample_count: optimized_measurements.len() as u32,
+                sample_count,
                 success_rate,
                 confidence: self.calculate_effectiveness_confidence(
-                    optimized_measurements.len(),
-                    baseline_measurements.len(),
+                    optimized_measurements.len() + trial_samples,
+                    baseline_measurements.len() + trial_samples,
                     improvement,
                 ),
                 trend,
                 last_used: Utc::now(),
+                satisfaction,
             };
 
             self.learning_model.strategy_effectiveness.insert("Default".to_string(), effectiveness);
@@ -749,35 +1082,42 @@ impl DefaultIntelligenceCore {
     /// Train the machine learning model with historical data
     pub async fn train_model(&mut self) -> Result<()> {
         let since = Utc::now() - Duration::days(30); // Use last 30 days for training
-        let measurements = self.repository.get_speed_measurements_since(since).await?;
-        
-        if measurements.len() < 50 {
-            // Not enough data for meaningful training
-            return Ok(());
-        }
+        let measurements = in_current_context(&without_vpn(&self.repository.get_speed_measurements_since(since).await?));
+        // Train on the local model alone; the priors blended in last round are dropped here
+        self.learning_model = self.local_model.clone();
+        
+        // Fewer rows are not enough for meaningful training; imported priors still apply
+        if measurements.len() >= 50 {
+            // Use advanced pattern learning for better accuracy
+            self.learn_advanced_patterns().await?;
+
+            // Fallback to basic learning if advanced learning didn't work
+            if self.learning_model.temporal_weights.is_empty() {
+                self.update_temporal_patterns(&isp_attributable(&measurements)).await?;
+            }
 
-        // Use advanced pattern learning for better accuracy
-        self.learn_advanced_patterns().await?;
-        
-        // Fallback to basic learning if advanced learning didn't work
-        if self.learning_model.temporal_weights.is_empty() {
-            self.update_temporal_patterns(&measurements).await?;
-        }
-        
-        if self.learning_model.strategy_effectiveness.is_empty() {
-            self.update_strategy_effectiveness().await?;
+            if self.learning_model.strategy_effectiveness.is_empty() {
+                self.update_strategy_effectiveness().await?;
+            }
+
+            if self.learning_model.isp_parameters.is_empty() {
+                self.update_isp_parameters().await?;
+            }
+
+            // Calculate overall model confidence
+            self.calculate_model_confidence();
+
+            self.learning_model.training_samples = measurements.len() as u32;
+            self.learning_model.last_updated = Utc::now();
         }
-        
-        if self.learning_model.isp_parameters.is_empty() {
-            self.update_isp_parameters().await?;
+        self.local_model = self.learning_model.clone();
+
+        if let Some(path) = &self.priors_path {
+            if let Some(priors) = model_share::load_priors(path).await {
+                model_share::apply_priors(&mut self.learning_model, &priors);
+            }
         }
-        
-        // Calculate overall model confidence
-        self.calculate_model_confidence();
-        
-        self.learning_model.training_samples = measurements.len() as u32;
-        self.learning_model.last_updated = Utc::now();
-        
+
         Ok(())
     }
 
@@ -834,12 +1174,12 @@ impl DefaultIntelligenceCore {
     }
 
     /// Calculate effectiveness for a specific strategy
-    pub async fn calculate_strategy_effectiveness(&self, _strategy: &OptimizationStrategy) -> Result<StrategyEffectiveness> {
+    pub async fn calculate_strategy_effectiveness(&self, strategy: &OptimizationStrategy) -> Result<StrategyEffectiveness> {
         // This would analyze historical data for this specific strategy
         // For now, we'll use a simplified calculation
         
         let since = Utc::now() - Duration::days(14);
-        let measurements = self.repository.get_speed_measurements_since(since).await?;
+        let measurements = without_vpn(&self.repository.get_speed_measurements_since(since).await?);
         
         let optimized_measurements: Vec<_> = measurements.iter()
             .filter(|m| m.optimization_active)
@@ -863,6 +1203,10 @@ impl DefaultIntelligenceCore {
         
         let improvement = if avg_baseline > 0.0 { avg_optimized / avg_baseline } else { 1.0 };
         let success_rate = if improvement > 1.2 { 1.0 } else { improvement - 1.0 };
+        let (success_rate, satisfaction) = fold_satisfaction(
+            success_rate.max(0.0).min(1.0),
+            &self.optimizing_feedback(Some(&strategy.name)).await,
+        );
         let confidence = self.calculate_effectiveness_confidence(
             optimized_measurements.len(),
             baseline_measurements.len(),
@@ -876,9 +1220,38 @@ impl DefaultIntelligenceCore {
             confidence,
             trend: 0.0, // Would be calculated from recent vs older data
             last_used: Utc::now(),
+            satisfaction,
         })
     }
 
+    /// Completed optimization trials of the last 30 days
+    async fn completed_trials(&self) -> Vec<OptimizationTrial> {
+        let since = Utc::now() - Duration::days(30);
+        match self.repository.get_optimization_trials_since(since).await {
+            Ok(trials) => trials.into_iter().filter(|t| t.improvement().is_some()).collect(),
+            Err(e) => {
+                tracing::debug!("Optimization trials unavailable: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Satisfaction answers given while optimizing in the last 14 days, optionally for one strategy
+    async fn optimizing_feedback(&self, strategy_name: Option<&str>) -> Vec<SatisfactionFeedback> {
+        let since = Utc::now() - Duration::days(14);
+        match self.repository.get_satisfaction_feedback_since(since).await {
+            Ok(feedback) => feedback
+                .into_iter()
+                .filter(|f| f.optimization_active)
+                .filter(|f| strategy_name.map_or(true, |name| f.strategy_name.as_deref() == Some(name)))
+                .collect(),
+            Err(e) => {
+                tracing::debug!("Satisfaction feedback unavailable: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
     /// Update ISP-specific learning parameters
     async fn update_isp_parameters(&mut self) -> Result<()> {
         if let Some(isp_profile) = self.repository.get_current_isp_profile().await? {
@@ -971,6 +1344,10 @@ impl DefaultIntelligenceCore {
 
     /// Get the best strategy for current ISP and conditions
     pub async fn get_optimal_strategy(&self) -> Result<Option<OptimizationStrategy>> {
+        if let Some(pinned) = &self.strategy_override {
+            return Ok(Some(pinned.clone()));
+        }
+
         // Try to get the best strategy from database first
         if let Some(strategy) = self.repository.get_best_optimization_strategy().await? {
             return Ok(Some(strategy));
@@ -979,15 +1356,18 @@ impl DefaultIntelligenceCore {
         // If no stored strategy, create one based on learned parameters
         if let Some(isp_profile) = self.repository.get_current_isp_profile().await? {
             if let Some(isp_params) = self.learning_model.isp_parameters.get(&isp_profile.name) {
+                let (timing_min, timing_max, connections) = pacing_for_risk(isp_params.detection_risk);
                 let strategy = OptimizationStrategy {
                     id: None,
                     name: format!("Learned-{}", isp_profile.name),
                     server_rotation_interval_minutes: isp_params.optimal_rotation_interval,
-                    packet_timing_min_seconds: if isp_params.detection_risk > 0.7 { 45.0 } else { 30.0 },
-                    packet_timing_max_seconds: if isp_params.detection_risk > 0.7 { 90.0 } else { 60.0 },
-                    connection_count: if isp_params.detection_risk > 0.7 { 2 } else { 3 },
+                    packet_timing_min_seconds: timing_min,
+                    packet_timing_max_seconds: timing_max,
+                    connection_count: connections,
                     traffic_intensity: isp_params.optimal_traffic_intensity,
                     stealth_level: isp_params.optimal_stealth_level.clone(),
+                    mimicry_profile: MimicryProfile::default(),
+                    transport: preferred_transport(&self.learning_model.transport_effectiveness),
                     effectiveness_score: Some(isp_params.confidence),
                     created_at: Utc::now(),
                 };
@@ -1000,6 +1380,72 @@ impl DefaultIntelligenceCore {
         Ok(Some(OptimizationStrategy::default_strategy()))
     }
 
+    /// Builds a strategy from the trained model: the current ISP's learned parameters, the hours
+    /// it throttles most, and an intensity that stays within the keeper budget. Nothing is saved.
+    pub async fn synthesize_strategy(&self, budget: &ThroughputKeeperConfig) -> Result<StrategyProposal> {
+        let best_hours: Vec<u8> = self.predict_optimal_times()
+            .into_iter()
+            .take(MAX_STRATEGY_HOURS)
+            .map(|(hour, _)| hour)
+            .collect();
+
+        let isp_name = self.repository.get_current_isp_profile().await?.map(|p| p.name);
+        let params = isp_name
+            .as_ref()
+            .and_then(|name| self.learning_model.isp_parameters.get(name))
+            .or_else(|| {
+                self.learning_model.isp_parameters.values()
+                    .max_by(|a, b| a.confidence.partial_cmp(&b.confidence).unwrap_or(std::cmp::Ordering::Equal))
+            });
+        let Some(params) = params.filter(|_| !best_hours.is_empty()) else {
+            let collected = self.analyze_patterns().await?.data_collection_days;
+            return Err(SpeedKarmaError::InsufficientData { required: self.min_learning_days.saturating_sub(collected).max(1) });
+        };
+
+        let (timing_min, timing_max, connections) = pacing_for_risk(params.detection_risk);
+        let strategy = OptimizationStrategy {
+            id: None,
+            name: format!("My data ({})", format_hours(&best_hours)),
+            server_rotation_interval_minutes: params.optimal_rotation_interval,
+            packet_timing_min_seconds: timing_min,
+            packet_timing_max_seconds: timing_max,
+            connection_count: connections,
+            traffic_intensity: safe_traffic_intensity(params.optimal_traffic_intensity, params.detection_risk, budget, best_hours.len()),
+            stealth_level: params.optimal_stealth_level.clone(),
+            mimicry_profile: MimicryProfile::default(),
+            transport: preferred_transport(&self.learning_model.transport_effectiveness),
+            effectiveness_score: None,
+            created_at: Utc::now(),
+        };
+
+        Ok(StrategyProposal {
+            strategy,
+            best_hours,
+            training_samples: self.learning_model.training_samples,
+            activated: false,
+        })
+    }
+
+    /// Saves a synthesized strategy unscored; only measurements give it a score. Activating makes
+    /// it the active strategy explicitly, and when it replaces another it runs on canary and is
+    /// reverted if download regresses.
+    pub async fn save_strategy_proposal(&self, mut proposal: StrategyProposal, activate: bool) -> Result<StrategyProposal> {
+        let previous = if activate { self.repository.get_best_optimization_strategy().await? } else { None };
+        proposal.strategy.effectiveness_score = None;
+        proposal.strategy.validate()
+            .map_err(|e| SpeedKarmaError::ConfigurationError(e.to_string()))?;
+        let id = self.repository.save_optimization_strategy(&proposal.strategy).await?;
+        proposal.strategy.id = Some(id);
+        if activate {
+            self.repository.set_active_strategy(Some(id)).await?;
+        }
+        proposal.activated = activate;
+        if let Some(previous) = &previous {
+            canary::start(&*self.repository, &proposal.strategy, previous, &self.strategy_canary).await?;
+        }
+        Ok(proposal)
+    }
+
     /// Generate optimization recommendations based on learned patterns
     pub async fn generate_recommendations(&self) -> Result<Vec<OptimizationRecommendation>> {
         let mut recommendations = Vec::new();
@@ -1007,13 +1453,14 @@ impl DefaultIntelligenceCore {
         // Recommendation 1: Stealth level adjustment
         if let Some(isp_params) = self.learning_model.isp_parameters.values().next() {
             if isp_params.detection_risk > 0.6 {
-                recommendations.push(OptimizationRecommendation {
-                    recommendation_type: RecommendationType::StealthAdjustment,
-                    description: "Increase stealth level to avoid ISP detection".to_string(),
-                    expected_improvement: 1.2,
-                    confidence: isp_params.confidence,
-                    priority: 1,
-                });
+                recommendations.push(OptimizationRecommendation::new(
+                    RecommendationType::StealthAdjustment,
+                    Reason::new(ReasonCode::IncreaseStealth)
+                        .with("risk", format!("{:.0}", isp_params.detection_risk * 100.0)),
+                    1.2,
+                    isp_params.confidence,
+                    1,
+                ));
             }
         }
         
@@ -1023,13 +1470,15 @@ impl DefaultIntelligenceCore {
         
         if let Some((strategy_name, effectiveness)) = best_strategy {
             if effectiveness.avg_improvement > 1.5 && effectiveness.confidence > 0.7 {
-                recommendations.push(OptimizationRecommendation {
-                    recommendation_type: RecommendationType::StrategySwitch,
-                    description: format!("Switch to '{}' strategy for better performance", strategy_name),
-                    expected_improvement: effectiveness.avg_improvement,
-                    confidence: effectiveness.confidence,
-                    priority: 2,
-                });
+                recommendations.push(OptimizationRecommendation::new(
+                    RecommendationType::StrategySwitch,
+                    Reason::new(ReasonCode::SwitchStrategy)
+                        .with("strategy", strategy_name)
+                        .with("improvement", format!("{:.1}", effectiveness.avg_improvement)),
+                    effectiveness.avg_improvement,
+                    effectiveness.confidence,
+                    2,
+                ));
             }
         }
         
@@ -1040,13 +1489,41 @@ impl DefaultIntelligenceCore {
             .collect::<Vec<_>>();
         
         if !peak_hours.is_empty() {
-            recommendations.push(OptimizationRecommendation {
-                recommendation_type: RecommendationType::TimingOptimization,
-                description: format!("Focus optimization during peak throttling hours: {:?}", peak_hours),
-                expected_improvement: 2.0,
-                confidence: 0.8,
-                priority: 1,
-            });
+            recommendations.push(OptimizationRecommendation::new(
+                RecommendationType::TimingOptimization,
+                Reason::new(ReasonCode::FocusPeakHours).with("hours", format_hours(&peak_hours)),
+                2.0,
+                0.8,
+                1,
+            ));
+        }
+        
+        // Recommendation 4: Slowdowns caused by weak Wi-Fi rather than the ISP
+        let recent = self.repository.get_speed_measurements_since(Utc::now() - Duration::days(7)).await?;
+        if let Some(wifi) = wifi_attribution(&recent).filter(|w| w.local_wireless) {
+            recommendations.push(OptimizationRecommendation::new(
+                RecommendationType::LocalNetwork,
+                Reason::new(ReasonCode::WeakWifi)
+                    .with("slow", wifi.slow_on_weak_wifi)
+                    .with("total", wifi.slow_samples)
+                    .with("rssi", wifi.median_rssi_dbm.map_or("low link rate".to_string(), |r| format!("{} dBm", r))),
+                1.0,
+                wifi.slow_on_weak_wifi as f64 / wifi.slow_samples.max(1) as f64,
+                1,
+            ));
+        }
+        
+        // Recommendation 5: Peak-hour dips behind carrier-grade NAT may be the shared gateway
+        let cgnat = self.repository.get_current_isp_profile().await?.and_then(|p| p.cgnat).filter(|c| c.detected());
+        if let Some(cgnat) = cgnat.filter(|_| !peak_hours.is_empty()) {
+            recommendations.push(OptimizationRecommendation::new(
+                RecommendationType::ISPOptimization,
+                Reason::new(ReasonCode::CgnatCongestion).with("hours", format_hours(&peak_hours)),
+                1.0,
+                // A shared address is conclusive; a private upstream hop may be a second home router
+                if cgnat.shared_addresses.is_empty() { 0.5 } else { 0.8 },
+                2,
+            ));
         }
         
         Ok(recommendations)
@@ -1056,7 +1533,7 @@ impl DefaultIntelligenceCore {
 impl IntelligenceCore for DefaultIntelligenceCore {
     async fn analyze_patterns(&self) -> Result<PatternAnalysis> {
         let since = Utc::now() - Duration::days(self.min_learning_days as i64);
-        let measurements = self.repository.get_speed_measurements_since(since).await?;
+        let measurements = without_vpn(&self.repository.get_speed_measurements_since(since).await?);
         
         if measurements.len() < 20 {
             return Ok(PatternAnalysis {
@@ -1065,6 +1542,7 @@ impl IntelligenceCore for DefaultIntelligenceCore {
                 confidence_level: 0.0,
                 data_collection_days: 0,
                 isp_profile: None,
+                cgnat: false,
             });
         }
         
@@ -1079,10 +1557,14 @@ impl IntelligenceCore for DefaultIntelligenceCore {
             0.0
         };
         
+        let profile = self.repository.get_current_isp_profile().await?;
+        let cgnat = profile.as_ref().and_then(|p| p.cgnat.as_ref()).is_some_and(|c| c.detected());
+        let throttled_weight = if cgnat { CGNAT_THROTTLED_WEIGHT } else { THROTTLED_WEIGHT };
+        
         // Detect throttling periods using temporal weights
         let mut throttling_periods = Vec::new();
         for (&hour, &weight) in &self.learning_model.temporal_weights {
-            if weight < 0.6 { // Low performance indicates throttling
+            if weight < throttled_weight { // Low performance indicates throttling
                 throttling_periods.push(TimeRange {
                     start_hour: hour,
                     start_minute: 0,
@@ -1096,8 +1578,7 @@ impl IntelligenceCore for DefaultIntelligenceCore {
         let confidence_level = self.learning_model.model_confidence;
         let data_collection_days = measurements.len() as u32 / 24; // Rough estimate
         
-        let isp_profile = self.repository.get_current_isp_profile().await?
-            .map(|p| p.name);
+        let isp_profile = profile.map(|p| p.name);
         
         Ok(PatternAnalysis {
             throttling_periods,
@@ -1105,6 +1586,7 @@ impl IntelligenceCore for DefaultIntelligenceCore {
             confidence_level,
             data_collection_days,
             isp_profile,
+            cgnat,
         })
     }
 
@@ -1116,12 +1598,15 @@ impl IntelligenceCore for DefaultIntelligenceCore {
                              analysis.baseline_speed > 0.0 &&
                              !analysis.throttling_periods.is_empty();
         
-        let reason = if should_activate {
-            "Throttling patterns detected with sufficient confidence".to_string()
+        let confidence_pct = format!("{:.0}", analysis.confidence_level * 100.0);
+        let explanation = if should_activate {
+            Reason::new(ReasonCode::ThrottlingDetected).with("confidence", confidence_pct)
         } else if analysis.confidence_level <= 0.6 {
-            "Insufficient data confidence for optimization".to_string()
+            Reason::new(ReasonCode::InsufficientConfidence)
+                .with("confidence", confidence_pct)
+                .with("required", 60)
         } else {
-            "No significant throttling patterns detected".to_string()
+            Reason::new(ReasonCode::NoThrottlingDetected)
         };
         
         let estimated_improvement = if should_activate {
@@ -1134,7 +1619,8 @@ impl IntelligenceCore for DefaultIntelligenceCore {
         
         Ok(OptimizationDecision {
             should_activate,
-            reason,
+            reason: explanation.to_report_string(),
+            explanation,
             confidence: analysis.confidence_level,
             estimated_improvement,
         })
@@ -1171,9 +1657,13 @@ impl IntelligenceCore for DefaultIntelligenceCore {
 
     async fn get_status(&self) -> Result<SystemStatus> {
         let analysis = self.analyze_patterns().await?;
+        let recent = self.repository
+            .get_speed_measurements_since(Utc::now() - Duration::days(1))
+            .await
+            .unwrap_or_default();
         
-        if analysis.data_collection_days < self.min_learning_days {
-            Ok(SystemStatus::learning(analysis.data_collection_days, self.min_learning_days))
+        let status = if analysis.data_collection_days < self.min_learning_days {
+            SystemStatus::learning(analysis.data_collection_days, self.min_learning_days)
         } else if analysis.confidence_level > 0.6 {
             let effectiveness = EffectivenessMetrics {
                 improvement_factor: self.learning_model.strategy_effectiveness.values()
@@ -1184,52 +1674,169 @@ impl IntelligenceCore for DefaultIntelligenceCore {
                 confidence: analysis.confidence_level,
                 last_updated: Utc::now(),
             };
-            Ok(SystemStatus::optimizing(effectiveness))
+            SystemStatus::optimizing(effectiveness)
         } else {
-            Ok(SystemStatus {
+            SystemStatus {
                 state: SystemState::Monitoring,
                 message: "Monitoring network patterns".to_string(),
                 data_collection_progress: None,
                 effectiveness: None,
-            })
-        }
+                latest_passive: None,
+                latest_active: None,
+                tasks: Vec::new(),
+            }
+        };
+        
+        Ok(status.with_latest_measurements(&recent))
     }
 }
 
 /// Periodic decision engine that trains the model and evaluates optimization decisions
 pub struct DecisionEngine {
-    repository: Arc<Repository>,
+    repository: Arc<dyn DataStore>,
     intelligence: DefaultIntelligenceCore,
-    min_training_interval_minutes: u64,
+    decision_interval: DecisionIntervalConfig,
+    /// Notified on events that warrant re-evaluating before the next round
+    wake: Arc<tokio::sync::Notify>,
+    raw_cleanup_enabled: bool,
+    scheduler: PeriodicScheduler,
+    webhooks: Option<Arc<WebhookNotifier>>,
+    /// Throttling periods already reported; `None` until the first analysis
+    known_throttling: Option<Vec<TimeRange>>,
+    shared_state: Option<SharedAppState>,
+    shutdown: Option<CancellationToken>,
 }
 
 impl DecisionEngine {
-    pub fn new(repository: Arc<Repository>) -> Self {
+    pub fn new(repository: Arc<dyn DataStore>) -> Self {
         let intelligence = DefaultIntelligenceCore::new(Arc::clone(&repository));
         Self {
             repository,
             intelligence,
-            min_training_interval_minutes: 15,
+            decision_interval: DecisionIntervalConfig::default(),
+            wake: Arc::new(tokio::sync::Notify::new()),
+            raw_cleanup_enabled: true,
+            scheduler: PeriodicScheduler::default(),
+            webhooks: None,
+            known_throttling: None,
+            shared_state: None,
+            shutdown: None,
         }
     }
 
+    /// Disable deleting raw data when a compaction job rolls it into aggregates instead
+    pub fn set_raw_cleanup_enabled(&mut self, enabled: bool) {
+        self.raw_cleanup_enabled = enabled;
+    }
+
     /// Allows configuring minimum learning days used by the intelligence core
     pub fn set_min_learning_days(&mut self, days: u32) {
         self.intelligence.set_min_learning_days(days);
     }
 
+    /// Canary window used to judge strategy switches
+    pub fn set_strategy_canary(&mut self, config: StrategyCanaryConfig) {
+        self.intelligence.set_strategy_canary(config);
+    }
+
+    /// Imported model blended into every training round
+    pub fn set_priors_path(&mut self, path: Option<PathBuf>) {
+        self.intelligence.set_priors_path(path);
+    }
+
+    /// Round cadence; see `decision_interval::next_delay`
+    pub fn set_decision_interval(&mut self, config: DecisionIntervalConfig) {
+        self.decision_interval = config;
+    }
+
+    /// Runs a round right away whenever `wake` is notified, e.g. by `decision_interval::WakingEventSink`
+    pub fn set_wake(&mut self, wake: Arc<tokio::sync::Notify>) {
+        self.wake = wake;
+    }
+
+    /// Spreads training rounds against the app's other periodic loops
+    pub fn set_scheduler(&mut self, scheduler: PeriodicScheduler) {
+        self.scheduler = scheduler;
+    }
+
+    /// Makes `run` return between rounds once `token` is cancelled
+    pub fn set_shutdown(&mut self, token: CancellationToken) {
+        self.shutdown = Some(token);
+    }
+
+    /// Posts newly found throttling periods to webhooks after each training round
+    pub fn set_webhooks(&mut self, webhooks: Arc<WebhookNotifier>) {
+        self.webhooks = Some(webhooks);
+    }
+
+    /// Follows the user's pinned strategy, if any, instead of selecting one
+    pub fn set_shared_state(&mut self, shared_state: SharedAppState) {
+        self.shared_state = Some(shared_state);
+    }
+
+    /// Reports throttling periods not seen in earlier rounds. The first round only records what is
+    /// known, so a restart does not repeat every pattern.
+    async fn report_new_throttling(&mut self, current: &[TimeRange]) {
+        let Some(webhooks) = &self.webhooks else { return };
+        if let Some(known) = &self.known_throttling {
+            for period in new_throttling_periods(known, current) {
+                webhooks.send(WebhookEvent::ThrottlingPattern, &period).await;
+            }
+        }
+        self.known_throttling = Some(current.to_vec());
+    }
+
     /// Runs periodically: trains model and logs decision outcome
     pub async fn run(&mut self) -> Result<()> {
-        use tokio::time::{sleep, Duration as TokioDuration};
+        let mut round: u64 = 0;
+        let mut windows: Vec<TimeRange> = Vec::new();
 
         loop {
+            // The first round trains straight away so a fresh start has a model
+            if round > 0 {
+                let mut delay = decision_interval::next_delay(chrono::Local::now().naive_local(), &self.decision_interval, &windows);
+                if let Some(shared) = &self.shared_state {
+                    delay *= shared.read().await.decision_interval_factor();
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(self.scheduler.delay_for("decision_engine", round, delay)) => {}
+                    _ = self.wake.notified() => tracing::debug!("Decision round triggered by an event"),
+                    _ = shutdown::cancelled(self.shutdown.as_ref()) => {
+                        tracing::info!("Decision engine stopped");
+                        return Ok(());
+                    }
+                }
+            } else if self.shutdown.as_ref().is_some_and(|t| t.is_cancelled()) {
+                return Ok(());
+            }
+            round += 1;
+
             // Cleanup old data based on privacy policy (30 days)
-            let _ = self.repository.cleanup_old_data(30).await;
+            if self.raw_cleanup_enabled {
+                let _ = self.repository.cleanup_old_data(30).await;
+            }
+
+            let pinned = match &self.shared_state {
+                Some(shared) => shared.read().await.active_strategy_override.clone(),
+                None => None,
+            };
+            // A pinned strategy is the user's call: canary reverts would switch away from it
+            if let Some(strategy) = &pinned {
+                tracing::debug!("Strategy '{}' pinned by the user; automatic selection skipped", strategy.name);
+            } else if let Err(e) = canary::resolve_due(&*self.repository, &self.intelligence.strategy_canary).await {
+                tracing::warn!("Strategy canary check failed: {}", e);
+            }
+            self.intelligence.set_strategy_override(pinned);
 
             // Train and analyze
             if let Err(e) = self.intelligence.train_model().await {
                 tracing::warn!("Model training failed: {}", e);
             }
+            match self.intelligence.analyze_patterns().await {
+                Ok(analysis) => windows = analysis.throttling_periods,
+                Err(e) => tracing::debug!("Pattern analysis failed: {}", e),
+            }
+            self.report_new_throttling(&windows).await;
 
             match self.intelligence.should_optimize().await {
                 Ok(decision) => {
@@ -1243,8 +1850,6 @@ impl DecisionEngine {
                 }
                 Err(e) => tracing::warn!("Decision evaluation failed: {}", e),
             }
-
-            sleep(TokioDuration::from_secs(self.min_training_interval_minutes * 60)).await;
         }
     }
 
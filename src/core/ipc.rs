@@ -0,0 +1,331 @@
+use crate::core::control_api::ControlBackend;
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::data::export::{ExportFormat, MeasurementFilter};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::{debug, info, warn};
+
+/// Named pipe the running instance listens on
+#[cfg(windows)]
+pub const PIPE_NAME: &str = r"\\.\pipe\speedkarma";
+
+/// Longest a client waits for an answer; exports of a long history take a while
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// Most history `export --days` reaches back
+const MAX_EXPORT_DAYS: u32 = 3650;
+
+/// One command sent by `speedkarma-cli`, as a single JSON line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum IpcRequest {
+    Status,
+    /// Starts a speed test
+    Test,
+    /// Switches optimization on or off
+    Toggle,
+    Export {
+        format: ExportFormat,
+        #[serde(default)]
+        filter: MeasurementFilter,
+        path: Option<PathBuf>,
+    },
+    /// Sent by a second launch before it exits
+    Focus,
+}
+
+/// Answer to a request, as a single JSON line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpcResponse {
+    Ok(serde_json::Value),
+    Error(String),
+}
+
+impl IpcRequest {
+    /// `status`, `test`, `toggle`, `show` or `export [--format csv|json] [--days N] [--output PATH]`
+    pub fn from_args(args: &[String]) -> std::result::Result<Self, String> {
+        let value = |name: &str| args.iter().position(|a| a == name).and_then(|pos| args.get(pos + 1));
+        match args.first().map(String::as_str) {
+            Some("status") => Ok(IpcRequest::Status),
+            Some("test") => Ok(IpcRequest::Test),
+            Some("toggle") => Ok(IpcRequest::Toggle),
+            Some("show") => Ok(IpcRequest::Focus),
+            Some("export") => {
+                let format = match value("--format").map(String::as_str) {
+                    None | Some("csv") => ExportFormat::Csv,
+                    Some("json") => ExportFormat::Json,
+                    Some(other) => return Err(format!("Unknown export format '{}'; use csv or json", other)),
+                };
+                let since = match value("--days") {
+                    Some(days) => {
+                        let days = days.parse::<u32>().ok().filter(|d| *d > 0).ok_or_else(|| format!("Not a number of days: {}", days))?;
+                        Some(Utc::now() - Duration::days(days.min(MAX_EXPORT_DAYS) as i64))
+                    }
+                    None => None,
+                };
+                let filter = MeasurementFilter { since, ..MeasurementFilter::default() };
+                // The running instance has its own working directory, so relative paths are resolved here
+                let path = match value("--output") {
+                    Some(path) => Some(absolute(PathBuf::from(path)).map_err(|e| format!("Cannot resolve output path: {}", e))?),
+                    None => None,
+                };
+                Ok(IpcRequest::Export { format, filter, path })
+            }
+            Some(other) => Err(format!("Unknown command '{}'", other)),
+            None => Err("No command given".to_string()),
+        }
+    }
+}
+
+/// `path` against the current directory, with the directory part canonicalized when it exists
+fn absolute(path: PathBuf) -> std::io::Result<PathBuf> {
+    let path = if path.is_absolute() { path } else { std::env::current_dir()?.join(path) };
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) if dir.exists() => Ok(dir.canonicalize()?.join(name)),
+        _ => Ok(path),
+    }
+}
+
+/// Runs one request against the backend
+pub async fn dispatch(backend: &dyn ControlBackend, request: IpcRequest) -> IpcResponse {
+    let result = match request {
+        IpcRequest::Status => backend.status().await,
+        IpcRequest::Test => backend.run_speedtest().await.map(|_| serde_json::json!({ "started": true })),
+        IpcRequest::Toggle => backend.toggle_optimization().await,
+        IpcRequest::Export { format, filter, path } => {
+            backend.export(format, filter, path).await.and_then(|summary| serde_json::to_value(summary).map_err(|e| e.to_string()))
+        }
+        IpcRequest::Focus => backend.focus().await.map(|_| serde_json::json!({ "focused": true })),
+    };
+    match result {
+        Ok(value) => IpcResponse::Ok(value),
+        Err(message) => IpcResponse::Error(message),
+    }
+}
+
+/// Reads one request line and writes the answer
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(stream: S, backend: &dyn ControlBackend) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    stream.read_line(&mut line).await?;
+    let response = match serde_json::from_str::<IpcRequest>(&line) {
+        Ok(request) => dispatch(backend, request).await,
+        Err(e) => IpcResponse::Error(format!("Bad request: {}", e)),
+    };
+    let mut body = serde_json::to_vec(&response)?;
+    body.push(b'\n');
+    stream.get_mut().write_all(&body).await?;
+    stream.get_mut().shutdown().await?;
+    Ok(())
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: S, request: &IpcRequest) -> Result<IpcResponse> {
+    let mut stream = BufReader::new(stream);
+    let mut body = serde_json::to_vec(request)?;
+    body.push(b'\n');
+    stream.get_mut().write_all(&body).await?;
+    let mut line = String::new();
+    stream.read_line(&mut line).await?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Socket next to the database, so the CLI finds the instance run by the same user
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    crate::data::consolidation::canonical_db_path().with_file_name("speedkarma.sock")
+}
+
+/// Listens for CLI requests in the background; only the owning user may connect
+#[cfg(unix)]
+pub fn serve(backend: Arc<dyn ControlBackend>) {
+    use std::os::unix::fs::PermissionsExt;
+    let path = socket_path();
+    tokio::spawn(async move {
+        // A socket left by an instance that did not shut down cleanly blocks the bind
+        let _ = tokio::fs::remove_file(&path).await;
+        let listener = match tokio::net::UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => return warn!("CLI socket could not bind {}: {}", path.display(), e),
+        };
+        if let Err(e) = tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).await {
+            warn!("Failed to restrict CLI socket permissions: {}", e);
+        }
+        info!("CLI socket listening on {}", path.display());
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let backend = Arc::clone(&backend);
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, backend.as_ref()).await {
+                            debug!("CLI request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("CLI socket accept failed: {}", e),
+            }
+        }
+    });
+}
+
+/// SID of the user running this process, as a string like `S-1-5-21-...`
+#[cfg(windows)]
+fn current_user_sid() -> std::io::Result<String> {
+    use windows_sys::Win32::Foundation::{CloseHandle, LocalFree, HANDLE};
+    use windows_sys::Win32::Security::Authorization::ConvertSidToStringSidW;
+    use windows_sys::Win32::Security::{GetTokenInformation, TokenUser, TOKEN_QUERY, TOKEN_USER};
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    // SAFETY: every out pointer references a live local; the token is closed and the SID string
+    // freed before returning, and TOKEN_USER is read from a buffer aligned for it
+    unsafe {
+        let mut token: HANDLE = 0;
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u64; 64];
+        let mut length = 0u32;
+        let ok = GetTokenInformation(token, TokenUser, buffer.as_mut_ptr().cast(), (buffer.len() * 8) as u32, &mut length);
+        CloseHandle(token);
+        if ok == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        let mut sid = std::ptr::null_mut::<u16>();
+        if ConvertSidToStringSidW(user.User.Sid, &mut sid) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let length = (0..).take_while(|&i| *sid.add(i) != 0).count();
+        let text = String::from_utf16_lossy(std::slice::from_raw_parts(sid, length));
+        LocalFree(sid as _);
+        Ok(text)
+    }
+}
+
+/// Pipe instance only the current user may open, like the 0600 socket on Unix
+#[cfg(windows)]
+fn create_pipe(first_instance: bool) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1};
+    use windows_sys::Win32::Security::SECURITY_ATTRIBUTES;
+
+    // Protected DACL with a single entry: full access for this user
+    let sddl: Vec<u16> = format!("D:P(A;;GA;;;{})", current_user_sid()?).encode_utf16().chain(Some(0)).collect();
+    let mut descriptor = std::ptr::null_mut();
+    // SAFETY: `sddl` is NUL-terminated; the descriptor it produces is freed below
+    if unsafe { ConvertStringSecurityDescriptorToSecurityDescriptorW(sddl.as_ptr(), SDDL_REVISION_1, &mut descriptor, std::ptr::null_mut()) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: descriptor,
+        bInheritHandle: 0,
+    };
+    // SAFETY: `attributes` and the descriptor it points to outlive the call
+    let server = unsafe {
+        ServerOptions::new()
+            .first_pipe_instance(first_instance)
+            .reject_remote_clients(true)
+            .create_with_security_attributes_raw(PIPE_NAME, &mut attributes as *mut SECURITY_ATTRIBUTES as *mut std::ffi::c_void)
+    };
+    // SAFETY: allocated by ConvertStringSecurityDescriptorToSecurityDescriptorW and no longer used
+    unsafe { LocalFree(descriptor as _) };
+    server
+}
+
+/// Listens for CLI requests in the background; only the current user may connect
+#[cfg(windows)]
+pub fn serve(backend: Arc<dyn ControlBackend>) {
+    tokio::spawn(async move {
+        let mut server = match create_pipe(true) {
+            Ok(server) => server,
+            Err(e) => return warn!("CLI pipe could not be created: {}", e),
+        };
+        info!("CLI pipe listening on {}", PIPE_NAME);
+        loop {
+            if let Err(e) = server.connect().await {
+                warn!("CLI pipe connect failed: {}", e);
+                continue;
+            }
+            let connected = server;
+            server = match create_pipe(false) {
+                Ok(server) => server,
+                Err(e) => return warn!("CLI pipe could not be recreated: {}", e),
+            };
+            let backend = Arc::clone(&backend);
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(connected, backend.as_ref()).await {
+                    debug!("CLI request failed: {}", e);
+                }
+            });
+        }
+    });
+}
+
+/// Sends one request to the running instance
+pub async fn request(request: &IpcRequest) -> Result<IpcResponse> {
+    #[cfg(unix)]
+    let stream = tokio::net::UnixStream::connect(socket_path()).await;
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new().open(PIPE_NAME);
+    let stream = stream.map_err(|e| SpeedKarmaError::SystemError(format!("SpeedKarma is not running or not reachable: {}", e)))?;
+    tokio::time::timeout(REQUEST_TIMEOUT, exchange(stream, request))
+        .await
+        .map_err(|_| SpeedKarmaError::SystemError("SpeedKarma did not answer in time".to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::control_api::SiteSummary;
+    use crate::data::export::ExportSummary;
+    use crate::data::models::SpeedMeasurement;
+    use async_trait::async_trait;
+
+    struct StoppedBackend;
+
+    #[async_trait]
+    impl ControlBackend for StoppedBackend {
+        async fn status(&self) -> std::result::Result<serde_json::Value, String> { Ok(serde_json::json!({ "mode": "Disabled" })) }
+        async fn toggle_optimization(&self) -> std::result::Result<serde_json::Value, String> { Err("Emergency stop is engaged".into()) }
+        async fn run_speedtest(&self) -> std::result::Result<(), String> { Ok(()) }
+        async fn history(&self, _days: u32) -> std::result::Result<Vec<SpeedMeasurement>, String> { Ok(Vec::new()) }
+        async fn summary(&self) -> std::result::Result<SiteSummary, String> { Ok(SiteSummary::default()) }
+        async fn export(&self, format: ExportFormat, filter: MeasurementFilter, path: Option<PathBuf>) -> std::result::Result<ExportSummary, String> {
+            Ok(ExportSummary { path: path.unwrap_or_default(), format, rows: filter.since.map_or(0, |_| 7) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cli_requests_round_trip() {
+        let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert!(matches!(IpcRequest::from_args(&args("status")), Ok(IpcRequest::Status)));
+        assert!(IpcRequest::from_args(&args("export --format xml")).is_err());
+        assert!(IpcRequest::from_args(&args("reboot")).is_err());
+        assert!(IpcRequest::from_args(&args("export --days -3")).is_err());
+        let Ok(IpcRequest::Export { filter, .. }) = IpcRequest::from_args(&args("export --days 999999")) else { panic!("export expected") };
+        assert!(filter.since.unwrap() > Utc::now() - Duration::days(MAX_EXPORT_DAYS as i64 + 1));
+        let export = IpcRequest::from_args(&args("export --format json --days 7 --output out.json")).unwrap();
+        let IpcRequest::Export { path: Some(path), .. } = &export else { panic!("export path expected") };
+        assert_eq!(path, &std::env::current_dir().unwrap().canonicalize().unwrap().join("out.json"));
+
+        let (client, server) = tokio::io::duplex(4096);
+        let served = tokio::spawn(async move { handle_connection(server, &StoppedBackend).await });
+        let response = exchange(client, &export).await.unwrap();
+        served.await.unwrap().unwrap();
+        assert_eq!(response, IpcResponse::Ok(serde_json::json!({ "path": path, "format": "json", "rows": 7 })));
+
+        assert_eq!(dispatch(&StoppedBackend, IpcRequest::Toggle).await, IpcResponse::Error("Emergency stop is engaged".into()));
+        let (client, server) = tokio::io::duplex(4096);
+        tokio::spawn(async move { handle_connection(server, &StoppedBackend).await });
+        let mut client = BufReader::new(client);
+        client.get_mut().write_all(b"{\"command\":\"reboot\"}\n").await.unwrap();
+        let mut line = String::new();
+        client.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("{\"error\":\"Bad request"));
+    }
+}
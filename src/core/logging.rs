@@ -1,4 +1,24 @@
+use crate::core::config::LoggingConfig;
+use chrono::{SecondsFormat, Utc};
+use std::collections::VecDeque;
+use std::fmt::{Debug, Write as _};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::fmt;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, Registry};
+
+/// Log lines kept in memory for support bundles
+const RECENT_LOG_LINES: usize = 2000;
+
+/// Most lines `recent` returns at once
+const MAX_RECENT_LINES: usize = 5000;
 
 /// Initialize structured logging for tests and app runs
 pub fn init_for_tests() { let _ = fmt().with_target(false).try_init(); }
@@ -6,4 +26,256 @@ pub fn init_for_tests() { let _ = fmt().with_target(false).try_init(); }
 /// Initialize compact logging suitable for CI
 pub fn init_for_ci() { let _ = fmt().compact().try_init(); }
 
+/// Layer keeping the most recent log lines so they can be attached to a support bundle
+#[derive(Debug, Clone, Default)]
+pub struct RecentLogs {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl RecentLogs {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == RECENT_LOG_LINES { lines.pop_front(); }
+        lines.push_back(line);
+    }
+
+    /// Buffered lines, oldest first
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+struct LineVisitor(String);
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let _ = if field.name() == "message" {
+            write!(self.0, " {:?}", value)
+        } else {
+            write!(self.0, " {}={:?}", field.name(), value)
+        };
+    }
+}
+
+impl<S: Subscriber> Layer<S> for RecentLogs {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut line = LineVisitor(format!(
+            "{} {:>5} {}:",
+            Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            meta.level(),
+            meta.target()
+        ));
+        event.record(&mut line);
+        self.push(line.0);
+    }
+}
+
+/// Active log file under the app data dir; rotated copies are `speedkarma.log.1` (newest) and up
+pub fn default_log_path() -> PathBuf {
+    crate::data::consolidation::canonical_db_path().with_file_name("logs").join("speedkarma.log")
+}
+
+/// `path.N`, the Nth most recent rotated copy
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+struct RollingState {
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+/// Log file that is rotated by size, keeping a fixed number of older copies
+pub struct RollingFile {
+    path: PathBuf,
+    state: Mutex<RollingState>,
+}
+
+impl RollingFile {
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self { path: path.to_path_buf(), state: Mutex::new(RollingState { file, written, max_bytes, max_files }) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn set_limits(&self, max_bytes: u64, max_files: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.max_bytes = max_bytes;
+        state.max_files = max_files;
+    }
+
+    /// Active file and rotated copies that exist, newest first
+    pub fn files(&self) -> Vec<PathBuf> {
+        let max_files = self.state.lock().unwrap().max_files;
+        std::iter::once(self.path.clone())
+            .chain((1..=max_files).map(|n| rotated_path(&self.path, n)))
+            .filter(|p| p.exists())
+            .collect()
+    }
+
+    fn rotate(&self, state: &mut RollingState) -> io::Result<()> {
+        let _ = std::fs::remove_file(rotated_path(&self.path, state.max_files));
+        for n in (1..state.max_files).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        state.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        state.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for &RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.written > 0 && state.written + buf.len() as u64 > state.max_bytes {
+            self.rotate(&mut state)?;
+        }
+        let n = state.file.write(buf)?;
+        state.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state.lock().unwrap().file.flush()
+    }
+}
+
+/// Level of a formatted line: the word after the timestamp
+fn line_level(line: &str) -> Option<Level> {
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// The last `lines` entries at `min_level` or more severe; continuation lines follow their entry
+pub fn filter_lines<'a>(lines: impl Iterator<Item = &'a str>, count: usize, min_level: Level) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut keep = false;
+    for line in lines {
+        if let Some(level) = line_level(line) {
+            keep = level <= min_level;
+        }
+        if keep {
+            kept.push(line.to_string());
+        }
+    }
+    let skip = kept.len().saturating_sub(count);
+    kept.split_off(skip)
+}
+
+/// Live handle on the installed subscriber: level changes and the log file
+pub struct LogControl {
+    level: reload::Handle<LevelFilter, Registry>,
+    file: Option<Arc<RollingFile>>,
+    recent: RecentLogs,
+}
+
+impl LogControl {
+    /// Applies a changed level and rotation limits; the file itself is opened at start
+    pub fn apply(&self, cfg: &LoggingConfig, debug_logging: bool) {
+        let mut level = cfg.level.to_level();
+        if debug_logging {
+            level = level.max(Level::DEBUG);
+        }
+        if let Err(e) = self.level.reload(LevelFilter::from_level(level)) {
+            tracing::warn!("Could not change the log level: {}", e);
+        }
+        if let Some(file) = &self.file {
+            file.set_limits(cfg.max_file_size_mb * 1024 * 1024, cfg.max_files);
+        }
+    }
+
+    pub fn log_file(&self) -> Option<&Path> {
+        self.file.as_deref().map(RollingFile::path)
+    }
+
+    /// The last `lines` log lines at `min_level` or more severe, oldest first. Reads the log files,
+    /// so earlier runs are included; without a file only this run's in-memory lines are available.
+    pub fn recent(&self, lines: usize, min_level: Level) -> Vec<String> {
+        let lines = lines.min(MAX_RECENT_LINES);
+        let Some(file) = &self.file else {
+            let buffered = self.recent.lines();
+            return filter_lines(buffered.iter().map(String::as_str), lines, min_level);
+        };
+        let mut collected: Vec<String> = Vec::new();
+        for path in file.files() {
+            let Ok(text) = std::fs::read_to_string(&path) else { continue };
+            let mut older = filter_lines(text.lines(), lines - collected.len(), min_level);
+            older.append(&mut collected);
+            collected = older;
+            if collected.len() >= lines {
+                break;
+            }
+        }
+        collected
+    }
+}
+
+/// Installs the global subscriber: stdout, the in-memory buffer and, when enabled, the rotated
+/// log file at `log_path`. A file that cannot be opened is reported and skipped.
+pub fn init(cfg: &LoggingConfig, debug_logging: bool, log_path: &Path, recent: RecentLogs) -> LogControl {
+    let (filter, level) = reload::Layer::new(LevelFilter::INFO);
+    let file = if cfg.file_enabled {
+        match RollingFile::open(log_path, cfg.max_file_size_mb * 1024 * 1024, cfg.max_files) {
+            Ok(file) => Some(Arc::new(file)),
+            Err(e) => {
+                eprintln!("Could not open log file {}: {}", log_path.display(), e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let file_layer = file.clone().map(|file| fmt::layer().with_ansi(false).with_writer(file));
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(file_layer)
+        .with(recent.clone())
+        .try_init();
+    let control = LogControl { level, file, recent };
+    control.apply(cfg, debug_logging);
+    control
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_and_level_filter() {
+        let dir = std::env::temp_dir().join(format!("speedkarma-logs-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("speedkarma.log");
+        let file = RollingFile::open(&path, 64, 2).unwrap();
+        for i in 0..6 {
+            writeln!(&file, "2026-10-17T10:00:0{}.000000Z  INFO isp_speedkarma: line number {}", i, i).unwrap();
+        }
+        assert_eq!(file.files(), vec![path.clone(), rotated_path(&path, 1), rotated_path(&path, 2)]);
+        assert!(!rotated_path(&path, 3).exists());
+        assert!(std::fs::read_to_string(&path).unwrap().contains("line number 5"));
 
+        let text = "2026-10-17T10:00:00Z  INFO a: started\n2026-10-17T10:00:01Z ERROR a: failed\n  caused by: timeout\n\
+                    2026-10-17T10:00:02Z DEBUG a: detail\n2026-10-17T10:00:03Z  WARN a: slow";
+        assert_eq!(filter_lines(text.lines(), 10, Level::WARN), vec![
+            "2026-10-17T10:00:01Z ERROR a: failed", "  caused by: timeout", "2026-10-17T10:00:03Z  WARN a: slow",
+        ]);
+        assert_eq!(filter_lines(text.lines(), 1, Level::TRACE), vec!["2026-10-17T10:00:03Z  WARN a: slow"]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -3,5 +3,36 @@ pub mod intelligence;
 pub mod config;
 pub mod logging;
 pub mod app_state;
+pub mod alerts;
+pub mod events;
+pub mod reasons;
+pub mod conflicts;
+pub mod wifi;
+pub mod evaluation;
+pub mod schema;
+pub mod privacy;
+pub mod country_packs;
+pub mod trial;
+pub mod support;
+pub mod dataset;
+pub mod canary;
+pub mod scheduler;
+pub mod model_share;
+pub mod emergency;
+pub mod control_api;
+pub mod webhooks;
+pub mod safe_mode;
+pub mod fleet;
+pub mod complaint;
+pub mod decision_interval;
+pub mod power;
+pub mod status_codes;
+pub mod ipc;
+pub mod autostart;
+pub mod single_instance;
+pub mod supervisor;
+pub mod shutdown;
+pub mod command;
+pub mod watcher;
 
 pub use error::{Result, SpeedKarmaError};
\ No newline at end of file
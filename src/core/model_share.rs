@@ -0,0 +1,242 @@
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::core::intelligence::{ISPLearningParams, PatternLearningModel};
+use chrono::{NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Version of the shared model file; bumped when its fields change meaning
+pub const MODEL_FORMAT_VERSION: u32 = 1;
+/// Local training samples at which an imported model counts as much as local learning.
+/// Beyond that its share keeps shrinking, so priors fade as the user's own data grows.
+const PRIOR_EQUIVALENT_SAMPLES: f64 = 200.0;
+
+/// Effectiveness of one strategy without usage timestamps or feedback
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedStrategyEffectiveness {
+    pub avg_improvement: f64,
+    pub sample_count: u32,
+    pub success_rate: f64,
+    pub confidence: f64,
+    pub trend: f64,
+}
+
+/// Learned weights of a `PatternLearningModel`, safe to hand to someone else: no measurements,
+/// no timestamps finer than the export day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedModel {
+    pub format_version: u32,
+    pub app_version: String,
+    pub exported_on: NaiveDate,
+    /// ISP the model was trained on, when it was detected
+    pub isp_name: Option<String>,
+    pub training_samples: u32,
+    pub model_confidence: f64,
+    pub temporal_weights: HashMap<u8, f64>,
+    pub weekly_weights: HashMap<Weekday, f64>,
+    pub strategy_effectiveness: HashMap<String, SharedStrategyEffectiveness>,
+    pub isp_parameters: HashMap<String, ISPLearningParams>,
+}
+
+/// What an import brought in, for the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelImportSummary {
+    pub isp_name: Option<String>,
+    pub exported_on: NaiveDate,
+    pub training_samples: u32,
+    pub hours: usize,
+    pub strategies: usize,
+    pub stored_at: PathBuf,
+}
+
+impl SharedModel {
+    pub fn from_model(model: &PatternLearningModel, isp_name: Option<String>) -> Self {
+        Self {
+            format_version: MODEL_FORMAT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_on: Utc::now().date_naive(),
+            isp_name,
+            training_samples: model.training_samples,
+            model_confidence: model.model_confidence,
+            temporal_weights: model.temporal_weights.clone(),
+            weekly_weights: model.weekly_weights.clone(),
+            strategy_effectiveness: model
+                .strategy_effectiveness
+                .iter()
+                .map(|(name, e)| {
+                    let shared = SharedStrategyEffectiveness {
+                        avg_improvement: e.avg_improvement,
+                        sample_count: e.sample_count,
+                        success_rate: e.success_rate,
+                        confidence: e.confidence,
+                        trend: e.trend,
+                    };
+                    (name.clone(), shared)
+                })
+                .collect(),
+            isp_parameters: model.isp_parameters.clone(),
+        }
+    }
+
+    /// Refuses files from a newer app, malformed weights, and models trained on another ISP
+    /// unless `allow_other_isp` is set
+    pub fn check_compatible(&self, current_isp: Option<&str>, allow_other_isp: bool) -> Result<()> {
+        let incompatible = |msg: String| Err(SpeedKarmaError::ConfigurationError(msg));
+        if self.format_version == 0 || self.format_version > MODEL_FORMAT_VERSION {
+            return incompatible(format!(
+                "model file format {} (SpeedKarma {}) is not supported by this version, which reads format {}",
+                self.format_version, self.app_version, MODEL_FORMAT_VERSION
+            ));
+        }
+        if self.training_samples == 0 {
+            return incompatible("model file has no training behind it".to_string());
+        }
+        let weights_ok = self.temporal_weights.iter().all(|(hour, w)| *hour < 24 && w.is_finite())
+            && self.weekly_weights.values().all(|w| w.is_finite())
+            && (0.0..=1.0).contains(&self.model_confidence)
+            && self.strategy_effectiveness.values().all(|e| {
+                e.avg_improvement.is_finite() && e.avg_improvement > 0.0 && (0.0..=1.0).contains(&e.success_rate) && (0.0..=1.0).contains(&e.confidence)
+            });
+        if !weights_ok {
+            return incompatible("model file contains out-of-range weights".to_string());
+        }
+        if let (Some(theirs), Some(ours), false) = (self.isp_name.as_deref(), current_isp, allow_other_isp) {
+            if !theirs.eq_ignore_ascii_case(ours) {
+                return incompatible(format!("model was trained on {}, but this connection is {}", theirs, ours));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self, stored_at: PathBuf) -> ModelImportSummary {
+        ModelImportSummary {
+            isp_name: self.isp_name.clone(),
+            exported_on: self.exported_on,
+            training_samples: self.training_samples,
+            hours: self.temporal_weights.len(),
+            strategies: self.strategy_effectiveness.len(),
+            stored_at,
+        }
+    }
+}
+
+/// Blends an imported model into `model` as priors. Entries the local model lacks are taken
+/// over; entries it has are mixed in with a share that shrinks as local training samples grow.
+pub fn apply_priors(model: &mut PatternLearningModel, priors: &SharedModel) {
+    let share = PRIOR_EQUIVALENT_SAMPLES / (PRIOR_EQUIVALENT_SAMPLES + model.training_samples as f64);
+    let blend = |local: f64, prior: f64| local * (1.0 - share) + prior * share;
+    for (hour, prior) in &priors.temporal_weights {
+        model.temporal_weights.entry(*hour).and_modify(|w| *w = blend(*w, *prior)).or_insert(*prior);
+    }
+    for (day, prior) in &priors.weekly_weights {
+        model.weekly_weights.entry(*day).and_modify(|w| *w = blend(*w, *prior)).or_insert(*prior);
+    }
+    for (name, prior) in &priors.strategy_effectiveness {
+        let entry = model.strategy_effectiveness.entry(name.clone()).or_default();
+        if entry.sample_count == 0 {
+            entry.avg_improvement = prior.avg_improvement;
+            entry.success_rate = prior.success_rate;
+            entry.confidence = prior.confidence * share;
+            entry.trend = prior.trend;
+        } else {
+            entry.avg_improvement = blend(entry.avg_improvement, prior.avg_improvement);
+            entry.success_rate = blend(entry.success_rate, prior.success_rate);
+        }
+    }
+    for (isp, params) in &priors.isp_parameters {
+        model.isp_parameters.entry(isp.clone()).or_insert_with(|| params.clone());
+    }
+    model.model_confidence = model.model_confidence.max(priors.model_confidence * share);
+}
+
+/// Where an imported model is kept between runs
+pub fn default_priors_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("SpeedKarma").join("model-priors.json"))
+}
+
+pub async fn read_model(path: &Path) -> Result<SharedModel> {
+    Ok(serde_json::from_str(&tokio::fs::read_to_string(path).await?)?)
+}
+
+pub async fn write_model(path: &Path, model: &SharedModel) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, serde_json::to_string_pretty(model)?).await?;
+    Ok(())
+}
+
+/// Imported priors, if a readable file exists at `path`
+pub async fn load_priors(path: &Path) -> Option<SharedModel> {
+    match read_model(path).await {
+        Ok(model) => Some(model),
+        Err(SpeedKarmaError::IoError(e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable model priors at {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::intelligence::StrategyEffectiveness;
+
+    #[tokio::test]
+    async fn test_retraining_does_not_compound_priors() {
+        use crate::core::intelligence::DefaultIntelligenceCore;
+        use crate::data::memory_store::InMemoryStore;
+
+        let mut prior_model = PatternLearningModel::default();
+        prior_model.temporal_weights.insert(20, 0.4);
+        prior_model.model_confidence = 0.8;
+        let path = std::env::temp_dir().join(format!("speedkarma-priors-{}.json", uuid::Uuid::new_v4()));
+        write_model(&path, &SharedModel::from_model(&prior_model, None)).await.unwrap();
+
+        let mut core = DefaultIntelligenceCore::new(std::sync::Arc::new(InMemoryStore::new()));
+        core.set_priors_path(Some(path.clone()));
+        core.train_model().await.unwrap();
+        let first = core.learning_model.temporal_weights.clone();
+        core.train_model().await.unwrap();
+        assert_eq!(core.learning_model.temporal_weights, first);
+        assert!(core.local_model().temporal_weights.is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_export_checks_and_priors() {
+        let mut trained = PatternLearningModel::default();
+        trained.training_samples = 600;
+        trained.model_confidence = 0.8;
+        trained.temporal_weights.insert(20, 0.4);
+        trained.temporal_weights.insert(3, 0.9);
+        trained.strategy_effectiveness.insert("Default".to_string(), StrategyEffectiveness { avg_improvement: 1.5, sample_count: 40, success_rate: 0.9, confidence: 0.7, ..StrategyEffectiveness::default() });
+
+        let shared = SharedModel::from_model(&trained, Some("Dialog".to_string()));
+        let json = serde_json::to_value(&shared).unwrap();
+        assert!(json["strategy_effectiveness"]["Default"].get("last_used").is_none());
+        let shared: SharedModel = serde_json::from_value(json).unwrap();
+
+        assert!(shared.check_compatible(Some("dialog"), false).is_ok());
+        assert!(shared.check_compatible(Some("SLT-Mobitel"), false).is_err());
+        assert!(shared.check_compatible(Some("SLT-Mobitel"), true).is_ok());
+        assert!(SharedModel { format_version: MODEL_FORMAT_VERSION + 1, ..shared.clone() }.check_compatible(None, false).is_err());
+        let mut broken = shared.clone();
+        broken.temporal_weights.insert(24, 0.5);
+        assert!(broken.check_compatible(None, false).is_err());
+
+        // A fresh install takes the priors over; one with its own data mostly keeps its own
+        let mut fresh = PatternLearningModel::default();
+        apply_priors(&mut fresh, &shared);
+        assert_eq!(fresh.temporal_weights.get(&20), Some(&0.4));
+        assert_eq!(fresh.strategy_effectiveness["Default"].avg_improvement, 1.5);
+        assert!((fresh.model_confidence - 0.8).abs() < 1e-9);
+
+        let mut experienced = PatternLearningModel::default();
+        experienced.training_samples = 1800;
+        experienced.temporal_weights.insert(20, 0.9);
+        apply_priors(&mut experienced, &shared);
+        assert!((experienced.temporal_weights[&20] - 0.85).abs() < 1e-9);
+    }
+}
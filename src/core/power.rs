@@ -0,0 +1,206 @@
+use crate::core::app_state::SharedAppState;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use crate::core::command::command_output;
+use crate::core::config::PowerPolicyConfig;
+use crate::core::events::SharedEventSink;
+use crate::core::watcher::PollingWatcher;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// Linux power supplies as `(type, value)` pairs, where the value is a battery's `status` or a
+/// charger's `online`. Desktops without a battery count as plugged in.
+pub fn linux_on_battery(supplies: &[(String, String)]) -> bool {
+    let charger_online = supplies.iter().any(|(kind, value)| kind != "Battery" && value == "1");
+    let discharging = supplies.iter().any(|(kind, value)| kind == "Battery" && value == "Discharging");
+    !charger_online && discharging
+}
+
+/// `powerprofilesctl get`
+pub fn parse_power_profile(output: &str) -> bool {
+    output.trim() == "power-saver"
+}
+
+/// `pmset -g batt`; the first line names the source, e.g. "Now drawing from 'Battery Power'"
+pub fn parse_pmset_batt(output: &str) -> Option<bool> {
+    let first = output.lines().next()?;
+    if first.contains("'Battery Power'") {
+        Some(true)
+    } else if first.contains("'AC Power'") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// `pmset -g` lists `lowpowermode 1` while Low Power Mode is on
+pub fn parse_pmset_low_power(output: &str) -> Option<bool> {
+    output.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        (fields.next()? == "lowpowermode").then(|| fields.next() == Some("1"))
+    })
+}
+
+/// Windows `PowerManager` "<PowerSupplyStatus> <EnergySaverStatus>", e.g. "NotPresent On"
+pub fn parse_windows_power(output: &str) -> (Option<bool>, Option<bool>) {
+    let mut fields = output.split_whitespace();
+    let on_battery = match fields.next() {
+        Some("NotPresent") => Some(true),
+        Some("Adequate") | Some("Inadequate") => Some(false),
+        _ => None,
+    };
+    let saver = match fields.next() {
+        Some("On") => Some(true),
+        Some("Off") | Some("Disabled") => Some(false),
+        _ => None,
+    };
+    (on_battery, saver)
+}
+
+/// Whether the device runs on battery and whether the OS is in low-power mode; `None` when unknown
+#[cfg(target_os = "linux")]
+pub async fn detect() -> (Option<bool>, Option<bool>) {
+    let mut supplies = Vec::new();
+    if let Ok(mut entries) = tokio::fs::read_dir("/sys/class/power_supply").await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(kind) = tokio::fs::read_to_string(entry.path().join("type")).await else { continue };
+            let kind = kind.trim().to_string();
+            let file = if kind == "Battery" { "status" } else { "online" };
+            if let Ok(value) = tokio::fs::read_to_string(entry.path().join(file)).await {
+                supplies.push((kind, value.trim().to_string()));
+            }
+        }
+    }
+    let low_power = command_output("powerprofilesctl", &["get"]).await.map(|o| parse_power_profile(&o));
+    (Some(linux_on_battery(&supplies)), low_power)
+}
+
+#[cfg(target_os = "macos")]
+pub async fn detect() -> (Option<bool>, Option<bool>) {
+    let on_battery = command_output("pmset", &["-g", "batt"]).await.and_then(|o| parse_pmset_batt(&o));
+    let low_power = command_output("pmset", &["-g"]).await.and_then(|o| parse_pmset_low_power(&o));
+    (on_battery, low_power)
+}
+
+#[cfg(target_os = "windows")]
+pub async fn detect() -> (Option<bool>, Option<bool>) {
+    const SCRIPT: &str = "[Windows.System.Power.PowerManager,Windows.System.Power,ContentType=WindowsRuntime] > $null; \
+        \"$([Windows.System.Power.PowerManager]::PowerSupplyStatus) $([Windows.System.Power.PowerManager]::EnergySaverStatus)\"";
+    match command_output("powershell", &["-NoProfile", "-NonInteractive", "-Command", SCRIPT]).await {
+        Some(output) => parse_windows_power(&output),
+        None => (None, None),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub async fn detect() -> (Option<bool>, Option<bool>) {
+    (None, None)
+}
+
+/// `get_power_state` payload and `power_state` event
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerState {
+    pub on_battery: Option<bool>,
+    pub low_power_mode: Option<bool>,
+    /// The power policy applies right now
+    pub saving: bool,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl PowerState {
+    pub fn resolve(on_battery: Option<bool>, low_power_mode: Option<bool>, policy: &PowerPolicyConfig) -> Self {
+        let saving = policy.enabled
+            && (on_battery == Some(true) || (policy.follow_low_power_mode && low_power_mode == Some(true)));
+        Self { on_battery, low_power_mode, saving, checked_at: Utc::now() }
+    }
+}
+
+/// Polls the power source and switches the app into its power-saving cadence: fewer passive
+/// measurements, no keeper traffic and longer gaps between decision rounds
+pub struct PowerWatcher {
+    events: SharedEventSink,
+    shared: SharedAppState,
+    config: Arc<RwLock<PowerPolicyConfig>>,
+    state: RwLock<Option<PowerState>>,
+}
+
+impl PowerWatcher {
+    pub fn new(events: SharedEventSink, shared: SharedAppState, config: PowerPolicyConfig) -> Self {
+        Self { events, shared, config: Arc::new(RwLock::new(config)), state: RwLock::new(None) }
+    }
+
+    pub async fn update_config(&self, cfg: PowerPolicyConfig) {
+        *self.config.write().await = cfg.clone();
+        let (on_battery, low_power) = self.state.read().await.as_ref().map_or((None, None), |s| (s.on_battery, s.low_power_mode));
+        self.apply(PowerState::resolve(on_battery, low_power, &cfg), cfg).await;
+    }
+
+    pub async fn state(&self) -> Option<PowerState> {
+        self.state.read().await.clone()
+    }
+
+    async fn apply(&self, state: PowerState, policy: PowerPolicyConfig) {
+        {
+            let mut shared = self.shared.write().await;
+            shared.power_saving = state.saving;
+            shared.power_policy = policy;
+        }
+        let previous = self.state.write().await.replace(state.clone());
+        if previous.as_ref().map(|p| (p.on_battery, p.low_power_mode, p.saving)) == Some((state.on_battery, state.low_power_mode, state.saving)) {
+            debug!("Power state unchanged");
+            return;
+        }
+        if previous.as_ref().map(|p| p.saving) != Some(state.saving) {
+            info!("Power saving {}", if state.saving { "on: background work reduced" } else { "off" });
+        }
+        self.events.emit_payload("power_state", &state);
+    }
+}
+
+#[async_trait]
+impl PollingWatcher for PowerWatcher {
+    async fn poll(&self) -> Duration {
+        let cfg = self.config.read().await.clone();
+        let (on_battery, low_power) = detect().await;
+        let interval = cfg.check_interval_seconds.max(5);
+        self.apply(PowerState::resolve(on_battery, low_power, &cfg), cfg).await;
+        Duration::from_secs(interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_sources_and_policy() {
+        let supply = |kind: &str, value: &str| (kind.to_string(), value.to_string());
+        assert!(linux_on_battery(&[supply("Mains", "0"), supply("Battery", "Discharging")]));
+        assert!(!linux_on_battery(&[supply("Mains", "1"), supply("Battery", "Discharging")]));
+        assert!(!linux_on_battery(&[supply("Battery", "Full")]));
+        assert!(!linux_on_battery(&[]));
+        assert!(parse_power_profile("power-saver\n"));
+        assert!(!parse_power_profile("balanced"));
+
+        assert_eq!(parse_pmset_batt("Now drawing from 'Battery Power'\n -InternalBattery-0 (id=1234)\t85%; discharging;"), Some(true));
+        assert_eq!(parse_pmset_batt("Now drawing from 'AC Power'\n"), Some(false));
+        assert_eq!(parse_pmset_low_power("System-wide power settings:\n lowpowermode         1\n"), Some(true));
+        assert_eq!(parse_pmset_low_power(" sleep 1\n"), None);
+
+        assert_eq!(parse_windows_power("NotPresent On\r\n"), (Some(true), Some(true)));
+        assert_eq!(parse_windows_power("Adequate Disabled"), (Some(false), Some(false)));
+
+        let policy = PowerPolicyConfig::default();
+        assert!(PowerState::resolve(Some(true), None, &policy).saving);
+        assert!(PowerState::resolve(Some(false), Some(true), &policy).saving);
+        assert!(!PowerState::resolve(None, None, &policy).saving);
+        let battery_only = PowerPolicyConfig { follow_low_power_mode: false, ..policy.clone() };
+        assert!(!PowerState::resolve(Some(false), Some(true), &battery_only).saving);
+        let disabled = PowerPolicyConfig { enabled: false, ..policy };
+        assert!(!PowerState::resolve(Some(true), Some(true), &disabled).saving);
+    }
+}
@@ -0,0 +1,148 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Differential-privacy settings for statistics that leave the device
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrivacyParams {
+    /// Privacy loss per released statistic; smaller means more noise
+    pub epsilon: f64,
+    /// Contributions a statistic needs before it may be released at all
+    pub min_cohort_size: usize,
+}
+
+impl Default for PrivacyParams {
+    fn default() -> Self {
+        Self { epsilon: 1.0, min_cohort_size: 10 }
+    }
+}
+
+impl PrivacyParams {
+    /// True when a cohort of `size` contributions is large enough to release
+    pub fn allows(&self, size: usize) -> bool {
+        size >= self.min_cohort_size.max(1)
+    }
+}
+
+/// One draw from a zero-centred Laplace distribution
+pub fn laplace_noise<R: Rng + ?Sized>(scale: f64, rng: &mut R) -> f64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).max(f64::MIN_POSITIVE).ln()
+}
+
+/// Contribution count with Laplace noise (sensitivity 1), never below zero.
+/// `None` when the cohort is too small to release.
+pub fn noisy_count<R: Rng + ?Sized>(count: usize, params: &PrivacyParams, rng: &mut R) -> Option<f64> {
+    if !params.allows(count) { return None; }
+    Some((count as f64 + laplace_noise(1.0 / params.epsilon, rng)).max(0.0))
+}
+
+/// Mean of values clamped to `[lower, upper]`, with Laplace noise scaled to one value's influence.
+/// Suitable for bounded scores such as throttling severity.
+pub fn noisy_mean<R: Rng + ?Sized>(values: &[f64], lower: f64, upper: f64, params: &PrivacyParams, rng: &mut R) -> Option<f64> {
+    if !params.allows(values.len()) || upper <= lower { return None; }
+    let n = values.len() as f64;
+    let mean = values.iter().map(|v| v.clamp(lower, upper)).sum::<f64>() / n;
+    let sensitivity = (upper - lower) / n;
+    Some((mean + laplace_noise(sensitivity / params.epsilon, rng)).clamp(lower, upper))
+}
+
+/// Median of values clamped to `[lower, upper]` via the exponential mechanism: an interval between
+/// neighbouring sorted values is picked with weight favouring the middle rank, then a point inside it.
+pub fn noisy_median<R: Rng + ?Sized>(values: &[f64], lower: f64, upper: f64, params: &PrivacyParams, rng: &mut R) -> Option<f64> {
+    if !params.allows(values.len()) || upper <= lower { return None; }
+    let mut points: Vec<f64> = values.iter().map(|v| v.clamp(lower, upper)).collect();
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    points.insert(0, lower);
+    points.push(upper);
+
+    // Interval i lies between the i-th and (i+1)-th point; utility is minus its distance from the middle rank
+    let middle = values.len() as f64 / 2.0;
+    let log_weights: Vec<f64> = points
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| {
+            let width = w[1] - w[0];
+            if width <= 0.0 { f64::NEG_INFINITY } else { width.ln() - params.epsilon * (i as f64 - middle).abs() / 2.0 }
+        })
+        .collect();
+    let max = log_weights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if !max.is_finite() {
+        // Every value sits on one point
+        return Some(points[0]);
+    }
+    let weights: Vec<f64> = log_weights.iter().map(|w| (w - max).exp()).collect();
+    let mut pick = rng.gen_range(0.0..weights.iter().sum::<f64>());
+    for (i, weight) in weights.iter().enumerate() {
+        if pick < *weight || i == weights.len() - 1 {
+            return Some(rng.gen_range(points[i]..=points[i + 1]));
+        }
+        pick -= weight;
+    }
+    None
+}
+
+/// Noisy median per hour, dropping hours whose cohort is too small. Hours hold disjoint
+/// contributions, so each gets the full epsilon.
+pub fn noisy_hourly_medians<R: Rng + ?Sized>(
+    by_hour: &HashMap<u8, Vec<f64>>,
+    lower: f64,
+    upper: f64,
+    params: &PrivacyParams,
+    rng: &mut R,
+) -> BTreeMap<u8, f64> {
+    let mut hours: Vec<&u8> = by_hour.keys().collect();
+    hours.sort_unstable();
+    hours
+        .into_iter()
+        .filter_map(|hour| noisy_median(&by_hour[hour], lower, upper, params, rng).map(|m| (*hour, m)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_small_cohorts_are_withheld() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let params = PrivacyParams { epsilon: 1.0, min_cohort_size: 5 };
+        let values = [10.0, 20.0, 30.0, 40.0];
+        assert!(noisy_median(&values, 0.0, 100.0, &params, &mut rng).is_none());
+        assert!(noisy_mean(&values, 0.0, 100.0, &params, &mut rng).is_none());
+        assert!(noisy_count(values.len(), &params, &mut rng).is_none());
+
+        let by_hour = HashMap::from([(19, vec![30.0; 8]), (3, vec![80.0; 2])]);
+        let released = noisy_hourly_medians(&by_hour, 0.0, 100.0, &params, &mut rng);
+        assert_eq!(released.keys().copied().collect::<Vec<_>>(), vec![19]);
+    }
+
+    #[test]
+    fn test_noisy_statistics_stay_near_truth() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let params = PrivacyParams { epsilon: 2.0, min_cohort_size: 10 };
+        let values: Vec<f64> = (0..200).map(|i| 40.0 + (i % 21) as f64).collect();
+
+        let medians: Vec<f64> = (0..50).map(|_| noisy_median(&values, 0.0, 100.0, &params, &mut rng).unwrap()).collect();
+        assert!(medians.iter().all(|m| (0.0..=100.0).contains(m)));
+        let avg = medians.iter().sum::<f64>() / medians.len() as f64;
+        assert!((avg - 50.0).abs() < 3.0, "median estimate {}", avg);
+
+        let severities = [0.2; 100];
+        let mean = noisy_mean(&severities, 0.0, 1.0, &params, &mut rng).unwrap();
+        assert!((mean - 0.2).abs() < 0.1 && (0.0..=1.0).contains(&mean));
+    }
+
+    #[test]
+    fn test_laplace_noise_is_centred() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let draws: Vec<f64> = (0..20_000).map(|_| laplace_noise(2.0, &mut rng)).collect();
+        let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+        let mean_abs = draws.iter().map(|d| d.abs()).sum::<f64>() / draws.len() as f64;
+        assert!(mean.abs() < 0.1);
+        // E|X| equals the scale for a Laplace distribution
+        assert!((mean_abs - 2.0).abs() < 0.1);
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Stable identifiers for every user-facing decision and recommendation explanation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonCode {
+    ThrottlingDetected,
+    InsufficientConfidence,
+    NoThrottlingDetected,
+    IncreaseStealth,
+    SwitchStrategy,
+    FocusPeakHours,
+    WeakWifi,
+    CgnatCongestion,
+}
+
+/// Where a reason is shown: the tray needs a few words, reports a full sentence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderContext {
+    Tray,
+    Report,
+}
+
+/// Languages with a template table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+}
+
+impl Locale {
+    /// Picks the table for a BCP 47 tag such as `en-US`, falling back to English
+    pub fn from_tag(_tag: &str) -> Self {
+        Locale::En
+    }
+}
+
+/// A reason code plus the variables its templates refer to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reason {
+    pub code: ReasonCode,
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+}
+
+impl Reason {
+    pub fn new(code: ReasonCode) -> Self {
+        Self { code, params: BTreeMap::new() }
+    }
+
+    /// Adds a `{name}` variable
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Fills the template for this code; unknown placeholders are left as written
+    pub fn render(&self, locale: Locale, context: RenderContext) -> String {
+        self.params
+            .iter()
+            .fold(template(self.code, locale, context).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            })
+    }
+
+    /// English report wording, used where a plain string is stored or logged
+    pub fn to_report_string(&self) -> String {
+        self.render(Locale::En, RenderContext::Report)
+    }
+}
+
+fn template(code: ReasonCode, locale: Locale, context: RenderContext) -> &'static str {
+    match (locale, code, context) {
+        (Locale::En, ReasonCode::ThrottlingDetected, RenderContext::Tray) => "Throttling detected",
+        (Locale::En, ReasonCode::ThrottlingDetected, RenderContext::Report) => {
+            "Throttling patterns detected with sufficient confidence ({confidence}%)"
+        }
+        (Locale::En, ReasonCode::InsufficientConfidence, RenderContext::Tray) => "Still learning",
+        (Locale::En, ReasonCode::InsufficientConfidence, RenderContext::Report) => {
+            "Insufficient data confidence for optimization ({confidence}%, need {required}%)"
+        }
+        (Locale::En, ReasonCode::NoThrottlingDetected, RenderContext::Tray) => "No throttling",
+        (Locale::En, ReasonCode::NoThrottlingDetected, RenderContext::Report) => {
+            "No significant throttling patterns detected"
+        }
+        (Locale::En, ReasonCode::IncreaseStealth, RenderContext::Tray) => "Raise stealth",
+        (Locale::En, ReasonCode::IncreaseStealth, RenderContext::Report) => {
+            "Increase stealth level to avoid ISP detection (detection risk {risk}%)"
+        }
+        (Locale::En, ReasonCode::SwitchStrategy, RenderContext::Tray) => "Try '{strategy}'",
+        (Locale::En, ReasonCode::SwitchStrategy, RenderContext::Report) => {
+            "Switch to '{strategy}' strategy for better performance ({improvement}x expected)"
+        }
+        (Locale::En, ReasonCode::FocusPeakHours, RenderContext::Tray) => "Focus on {hours}",
+        (Locale::En, ReasonCode::FocusPeakHours, RenderContext::Report) => {
+            "Focus optimization during peak throttling hours: {hours}"
+        }
+        (Locale::En, ReasonCode::WeakWifi, RenderContext::Tray) => "Weak Wi-Fi, not your ISP",
+        (Locale::En, ReasonCode::WeakWifi, RenderContext::Report) => {
+            "{slow} of {total} slowdowns happened on weak Wi-Fi ({rssi}); they look local rather than ISP throttling"
+        }
+        (Locale::En, ReasonCode::CgnatCongestion, RenderContext::Tray) => "Shared ISP address (CGNAT)",
+        (Locale::En, ReasonCode::CgnatCongestion, RenderContext::Report) => {
+            "Your ISP shares one public address among many customers (carrier-grade NAT); slowdowns at {hours} may be congestion at the shared gateway rather than throttling"
+        }
+    }
+}
+
+/// Renders hours as `19:00, 20:00`, in ascending order
+pub fn format_hours(hours: &[u8]) -> String {
+    let mut sorted = hours.to_vec();
+    sorted.sort_unstable();
+    sorted.iter().map(|h| format!("{:02}:00", h)).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reason_renders_per_context() {
+        let reason = Reason::new(ReasonCode::SwitchStrategy).with("strategy", "High Stealth").with("improvement", "1.8");
+        assert_eq!(reason.render(Locale::En, RenderContext::Tray), "Try 'High Stealth'");
+        assert_eq!(
+            reason.render(Locale::from_tag("en-GB"), RenderContext::Report),
+            "Switch to 'High Stealth' strategy for better performance (1.8x expected)"
+        );
+
+        let json = serde_json::to_string(&reason).unwrap();
+        assert!(json.contains("\"switch_strategy\""));
+        let parsed: Reason = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, reason);
+    }
+
+    #[test]
+    fn test_every_report_template_is_filled() {
+        let reasons = [
+            Reason::new(ReasonCode::ThrottlingDetected).with("confidence", 82),
+            Reason::new(ReasonCode::InsufficientConfidence).with("confidence", 40).with("required", 60),
+            Reason::new(ReasonCode::NoThrottlingDetected),
+            Reason::new(ReasonCode::IncreaseStealth).with("risk", 70),
+            Reason::new(ReasonCode::SwitchStrategy).with("strategy", "Default").with("improvement", "1.6"),
+            Reason::new(ReasonCode::FocusPeakHours).with("hours", format_hours(&[21, 19])),
+            Reason::new(ReasonCode::WeakWifi).with("slow", 4).with("total", 5).with("rssi", "-78 dBm"),
+            Reason::new(ReasonCode::CgnatCongestion).with("hours", format_hours(&[20])),
+        ];
+        for reason in &reasons {
+            for context in [RenderContext::Tray, RenderContext::Report] {
+                let text = reason.render(Locale::En, context);
+                assert!(!text.contains('{'), "unfilled placeholder in {:?}: {}", reason.code, text);
+            }
+        }
+        assert!(reasons[5].to_report_string().ends_with("19:00, 21:00"));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::core::config::SafeModeConfig;
+use crate::core::error::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const MARKER_FILE: &str = "startup.marker";
+
+/// Uptime after which a start counts as survived and the crash count is cleared
+pub const STABLE_AFTER: Duration = Duration::from_secs(120);
+
+/// Modules held off in safe mode; monitoring, alerts and diagnostics keep running
+pub const DISABLED_IN_SAFE_MODE: &[&str] = &["Throughput keeper", "Stealth traffic", "Disguise mode", "Speed tests", "Optimization trials"];
+
+/// Left on disk for the whole of a start; finding it on the next start means that one crashed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StartupMarker {
+    /// Starts in a row, this one included, that never reached `STABLE_AFTER`
+    unfinished_startups: u32,
+    started_at: DateTime<Utc>,
+}
+
+/// What safe mode turned off and why, for the panel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SafeModeStatus {
+    pub active: bool,
+    /// Earlier starts in a row that crashed before they were stable
+    pub crashed_startups: u32,
+    pub disabled: Vec<String>,
+    pub reason: Option<String>,
+}
+
+impl SafeModeStatus {
+    pub fn evaluate(crashed_startups: u32, config: &SafeModeConfig) -> Self {
+        if !config.enabled || crashed_startups < config.crash_threshold {
+            return Self { crashed_startups, ..Self::default() };
+        }
+        Self {
+            active: true,
+            crashed_startups,
+            disabled: DISABLED_IN_SAFE_MODE.iter().map(|m| m.to_string()).collect(),
+            reason: Some(format!(
+                "The last {} startups crashed, so SpeedKarma started in safe mode: only monitoring and diagnostics are running",
+                crashed_startups
+            )),
+        }
+    }
+}
+
+/// Counts crashed startups through a marker file next to the database
+pub struct StartupGuard {
+    path: PathBuf,
+}
+
+impl StartupGuard {
+    pub fn new(data_dir: &Path) -> Self {
+        Self { path: data_dir.join(MARKER_FILE) }
+    }
+
+    /// Records this start and returns how many starts before it crashed in a row
+    pub async fn begin(&self) -> Result<u32> {
+        let crashed = match tokio::fs::read_to_string(&self.path).await {
+            // An unreadable marker still proves the last start never finished
+            Ok(content) => serde_json::from_str::<StartupMarker>(&content).map(|m| m.unfinished_startups).unwrap_or(1),
+            Err(_) => 0,
+        };
+        self.write(crashed + 1).await?;
+        Ok(crashed)
+    }
+
+    /// This start survived: the next one begins with a clean count
+    pub async fn mark_stable(&self) {
+        if let Err(e) = tokio::fs::remove_file(&self.path).await {
+            debug!("No startup marker to clear: {}", e);
+        }
+    }
+
+    /// Settles the marker when this start ends cleanly or has stayed up: clears it, or keeps the
+    /// next start in safe mode while this one still runs in it
+    pub async fn settle(&self, safe_mode_active: bool, crashed_startups: u32) {
+        if safe_mode_active {
+            self.hold(crashed_startups).await;
+        } else {
+            self.mark_stable().await;
+        }
+    }
+
+    /// Keeps the next start in safe mode, with the same count, even though this one is stable
+    pub async fn hold(&self, crashed_startups: u32) {
+        if let Err(e) = self.write(crashed_startups).await {
+            warn!("Failed to keep safe mode for the next start: {}", e);
+        }
+    }
+
+    async fn write(&self, unfinished_startups: u32) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let marker = StartupMarker { unfinished_startups, started_at: Utc::now() };
+        tokio::fs::write(&self.path, serde_json::to_vec(&marker)?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_repeated_crashes_enter_safe_mode() {
+        let dir = std::env::temp_dir().join(format!("speedkarma-safe-mode-{}", uuid::Uuid::new_v4()));
+        let guard = StartupGuard::new(&dir);
+        let config = SafeModeConfig::default();
+
+        // Three starts that never got stable, then the fourth finds them
+        for expected in 0..4 {
+            assert_eq!(guard.begin().await.unwrap(), expected);
+        }
+        let status = SafeModeStatus::evaluate(3, &config);
+        assert!(status.active);
+        assert_eq!(status.disabled.len(), DISABLED_IN_SAFE_MODE.len());
+        assert!(!SafeModeStatus::evaluate(2, &config).active);
+        assert!(!SafeModeStatus::evaluate(5, &SafeModeConfig { enabled: false, ..config }).active);
+
+        // A stable start in safe mode keeps the next one there; leaving it clears the count
+        guard.hold(3).await;
+        assert_eq!(guard.begin().await.unwrap(), 3);
+        guard.mark_stable().await;
+        assert_eq!(guard.begin().await.unwrap(), 0);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
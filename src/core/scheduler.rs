@@ -0,0 +1,190 @@
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::{sleep_until, Instant};
+
+/// Default share of the period a tick may move either way
+pub const DEFAULT_JITTER_RATIO: f64 = 0.1;
+/// Fractional part of the golden ratio; successive multiples fill [0, 1) evenly
+const PHASE_STEP: f64 = 0.618_033_988_749_895;
+/// Closest two tasks may start to each other, as a share of the shorter period
+const MIN_PHASE_GAP: f64 = 0.1;
+/// Golden-ratio steps tried when a task's phase lands too close to another's
+const MAX_PHASE_STEPS: u32 = 8;
+
+/// A task registered with the scheduler, for diagnostics
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduledTask {
+    pub name: String,
+    pub period_seconds: f64,
+    /// Offset of the first tick from the scheduler's start
+    pub phase_seconds: f64,
+}
+
+#[derive(Debug)]
+struct Registry {
+    epoch: Instant,
+    tasks: Vec<ScheduledTask>,
+}
+
+/// Hands out tickers for the app's periodic loops so they never fire in lockstep. Each task gets a
+/// phase offset and a per-tick jitter derived from its name, so the same set of tasks always lands
+/// on the same timeline.
+#[derive(Debug, Clone)]
+pub struct PeriodicScheduler {
+    registry: Arc<Mutex<Registry>>,
+    jitter_ratio: f64,
+}
+
+impl Default for PeriodicScheduler {
+    fn default() -> Self {
+        Self::new(DEFAULT_JITTER_RATIO)
+    }
+}
+
+impl PeriodicScheduler {
+    /// `jitter_ratio` is clamped below one half so ticks never swap order
+    pub fn new(jitter_ratio: f64) -> Self {
+        Self {
+            registry: Arc::new(Mutex::new(Registry { epoch: Instant::now(), tasks: Vec::new() })),
+            jitter_ratio: jitter_ratio.clamp(0.0, 0.45),
+        }
+    }
+
+    /// Ticker for a task repeating every `period`
+    pub fn register(&self, name: &str, period: Duration) -> PeriodicTicker {
+        let period = period.max(Duration::from_millis(1));
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        let phase = phase_offset(name_seed(name), period, &registry.tasks);
+        registry.tasks.push(ScheduledTask {
+            name: name.to_string(),
+            period_seconds: period.as_secs_f64(),
+            phase_seconds: phase.as_secs_f64(),
+        });
+        PeriodicTicker {
+            epoch: registry.epoch,
+            period,
+            phase,
+            jitter_ratio: self.jitter_ratio,
+            seed: name_seed(name),
+            next: 0,
+        }
+    }
+
+    /// Jittered version of a one-off delay, for loops whose cadence changes every round
+    pub fn delay_for(&self, name: &str, round: u64, base: Duration) -> Duration {
+        base.mul_f64((1.0 + jitter_fraction(name_seed(name), round) * self.jitter_ratio).max(0.0))
+    }
+
+    pub fn tasks(&self) -> Vec<ScheduledTask> {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner()).tasks.clone()
+    }
+}
+
+/// Deterministic timeline of one registered task
+#[derive(Debug)]
+pub struct PeriodicTicker {
+    epoch: Instant,
+    period: Duration,
+    phase: Duration,
+    jitter_ratio: f64,
+    seed: u64,
+    next: u64,
+}
+
+impl PeriodicTicker {
+    /// Offset from the scheduler's start at which tick `n` fires
+    pub fn offset(&self, n: u64) -> Duration {
+        let base = self.phase + self.period.mul_f64(n as f64);
+        let shift = self.period.mul_f64(jitter_fraction(self.seed, n).abs() * self.jitter_ratio);
+        if jitter_fraction(self.seed, n) < 0.0 { base.saturating_sub(shift) } else { base + shift }
+    }
+
+    /// Waits for the next tick. Ticks missed while the caller was busy are skipped rather than
+    /// fired in a burst.
+    pub async fn tick(&mut self) {
+        let elapsed = self.epoch.elapsed();
+        while self.offset(self.next) + self.period < elapsed {
+            self.next += 1;
+        }
+        let deadline = self.epoch + self.offset(self.next);
+        self.next += 1;
+        sleep_until(deadline).await;
+    }
+}
+
+/// First-tick offset for a task, stepped along the golden ratio until it keeps clear of the
+/// tasks already registered
+fn phase_offset(seed: u64, period: Duration, tasks: &[ScheduledTask]) -> Duration {
+    let period_s = period.as_secs_f64();
+    let start = (jitter_fraction(seed, u64::MAX) + 1.0) / 2.0;
+    let clear = |phase: f64| {
+        tasks.iter().all(|t| {
+            let shorter = t.period_seconds.min(period_s);
+            let distance = (phase - t.phase_seconds).rem_euclid(shorter);
+            distance.min(shorter - distance) >= shorter * MIN_PHASE_GAP
+        })
+    };
+    let fraction = (0..MAX_PHASE_STEPS)
+        .map(|k| (start + k as f64 * PHASE_STEP).fract())
+        .find(|f| clear(f * period_s))
+        .unwrap_or(start);
+    period.mul_f64(fraction)
+}
+
+/// FNV-1a hash of the task name
+fn name_seed(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Value in [-1, 1) fixed by the seed and tick number (splitmix64 finaliser)
+fn jitter_fraction(seed: u64, n: u64) -> f64 {
+    let mut z = seed.wrapping_add(n.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticks_spread_and_repeat() {
+        let scheduler = PeriodicScheduler::default();
+        let period = Duration::from_secs(60);
+        let first = scheduler.register("status", period);
+        let second = scheduler.register("measurement", period);
+        let third = scheduler.register("keeper", period);
+
+        // Same-period tasks start well apart from each other
+        let phases: Vec<f64> = scheduler.tasks().iter().map(|t| t.phase_seconds).collect();
+        for (i, a) in phases.iter().enumerate() {
+            for b in &phases[i + 1..] {
+                let distance = (a - b).rem_euclid(60.0);
+                assert!(distance.min(60.0 - distance) >= 6.0, "{:?}", phases);
+            }
+        }
+        // A half-period task keeps clear of them as well
+        let status = scheduler.register("tray_status", Duration::from_secs(30)).phase.as_secs_f64();
+        assert!(phases.iter().all(|p| { let d = (status - p).rem_euclid(30.0); d.min(30.0 - d) >= 3.0 }));
+
+        // Jitter stays within its share of the period and keeps ticks in order
+        for ticker in [&first, &second, &third] {
+            for n in 1..200 {
+                let ideal = ticker.phase + period * n as u32;
+                let drift = ticker.offset(n).as_secs_f64() - ideal.as_secs_f64();
+                assert!(drift.abs() <= 6.0 + 1e-9);
+                assert!(ticker.offset(n) > ticker.offset(n - 1));
+            }
+        }
+        assert!((1..50).any(|n| first.offset(n) != first.phase + period * n as u32));
+
+        // Same name, same timeline
+        let again = PeriodicScheduler::default();
+        let replay = again.register("status", period);
+        assert_eq!((0..50).map(|n| replay.offset(n)).collect::<Vec<_>>(), (0..50).map(|n| first.offset(n)).collect::<Vec<_>>());
+        assert_eq!(scheduler.delay_for("keeper", 3, period), again.delay_for("keeper", 3, period));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::core::config::AppConfig;
+use crate::core::error::Result;
+use crate::data::migrations::MigrationManager;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Bumped when the layout of the dumped files changes
+const DUMP_FORMAT_VERSION: u32 = 1;
+
+/// Migrations and the resulting tables/indexes, built from a scratch in-memory database
+pub async fn database_schema() -> Result<Value> {
+    let manager = MigrationManager::new(":memory:".to_string());
+    // One connection, so every query sees the same in-memory database
+    let pool = sqlx::sqlite::SqlitePoolOptions::new().max_connections(1).connect(":memory:").await?;
+    manager.run_migrations(&pool).await?;
+    let objects = manager.schema_objects(&pool).await?;
+    pool.close().await;
+
+    let names = manager.migration_names();
+    let schema_version = names.iter().map(|(version, _)| *version).max().unwrap_or(0);
+    let migrations: Vec<Value> = names
+        .into_iter()
+        .map(|(version, name)| json!({ "version": version, "name": name }))
+        .collect();
+    Ok(json!({
+        "format_version": DUMP_FORMAT_VERSION,
+        "schema_version": schema_version,
+        "migrations": migrations,
+        "objects": objects,
+    }))
+}
+
+/// JSON Schema of the config file, derived from the serde types
+pub fn config_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(AppConfig)).unwrap_or(Value::Null)
+}
+
+/// Names of the commands the shell exposes to webviews
+pub fn command_catalog(commands: &[&str]) -> Value {
+    let mut names = commands.to_vec();
+    names.sort_unstable();
+    json!({
+        "format_version": DUMP_FORMAT_VERSION,
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "commands": names,
+    })
+}
+
+/// Writes `db_schema.json`, `config.schema.json` and `commands.json` into `dir`
+pub async fn dump_schema(dir: &Path, commands: &[&str]) -> Result<Vec<PathBuf>> {
+    tokio::fs::create_dir_all(dir).await?;
+    let files = [
+        ("db_schema.json", database_schema().await?),
+        ("config.schema.json", config_schema()),
+        ("commands.json", command_catalog(commands)),
+    ];
+
+    let mut written = Vec::with_capacity(files.len());
+    for (name, value) in files {
+        let path = dir.join(name);
+        tokio::fs::write(&path, serde_json::to_vec_pretty(&value)?).await?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dump_schema_files() {
+        let dir = std::env::temp_dir().join(format!("speedkarma-schema-{}", uuid::Uuid::new_v4()));
+        let written = dump_schema(&dir, &["get_config", "apply_fq_codel"]).await.unwrap();
+        assert_eq!(written.len(), 3);
+
+        let db: Value = serde_json::from_slice(&std::fs::read(dir.join("db_schema.json")).unwrap()).unwrap();
+        let objects = db["objects"].as_array().unwrap();
+        assert!(objects.iter().any(|o| o["kind"] == "table" && o["name"] == "speed_measurements"));
+        assert_eq!(db["schema_version"], db["migrations"].as_array().unwrap().last().unwrap()["version"]);
+
+        let config: Value = serde_json::from_slice(&std::fs::read(dir.join("config.schema.json")).unwrap()).unwrap();
+        assert!(config["properties"]["advanced"].is_object());
+
+        let commands: Value = serde_json::from_slice(&std::fs::read(dir.join("commands.json")).unwrap()).unwrap();
+        assert_eq!(commands["commands"][0], "apply_fq_codel");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+//! Graceful shutdown: quitting and OS signals cancel one shared token, the loops finish their current
+//! round and return, and pending database writes are flushed before the process exits.
+
+use crate::core::app_state::SharedAppState;
+use crate::core::safe_mode::StartupGuard;
+use crate::core::supervisor::Supervisor;
+use crate::data::repository::Repository;
+use crate::network::DataUsageMeter;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+pub use tokio_util::sync::CancellationToken;
+
+/// How long the loops get to finish their round before they are aborted
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Resolves once `token` is cancelled; never when there is none
+pub async fn cancelled(token: Option<&CancellationToken>) {
+    match token {
+        Some(token) => token.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(signal) => signal,
+            Err(_) => return std::future::pending().await,
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Shared shutdown of a running engine; cloned into whatever can end the process
+#[derive(Clone)]
+pub struct Shutdown {
+    token: CancellationToken,
+    supervisor: Supervisor,
+    repository: Arc<Repository>,
+    usage: Option<DataUsageMeter>,
+    startup: Option<(Arc<StartupGuard>, SharedAppState, u32)>,
+    done: Arc<Mutex<bool>>,
+}
+
+impl Shutdown {
+    pub fn new(token: CancellationToken, supervisor: Supervisor, repository: Arc<Repository>) -> Self {
+        Self { token, supervisor, repository, usage: None, startup: None, done: Arc::new(Mutex::new(false)) }
+    }
+
+    /// Writes the meter's counted bytes before the database closes
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// A clean exit counts as a survived start, even one that ends before the stability timer ran
+    pub fn with_startup_guard(mut self, guard: Arc<StartupGuard>, shared: SharedAppState, crashed_startups: u32) -> Self {
+        self.startup = Some((guard, shared, crashed_startups));
+        self
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Cancels the token, waits up to `grace` for the supervised loops, then flushes and closes the
+    /// database. A second call waits for the first to finish.
+    pub async fn run(&self, grace: Duration) {
+        let mut done = self.done.lock().await;
+        if *done {
+            return;
+        }
+        info!("Shutting down");
+        self.token.cancel();
+        self.supervisor.join(grace).await;
+        if let Some(usage) = &self.usage {
+            if let Err(e) = usage.flush().await {
+                warn!("Failed to save data usage: {}", e);
+            }
+        }
+        if let Err(e) = self.repository.close().await {
+            warn!("Failed to checkpoint the database: {}", e);
+        }
+        if let Some((guard, shared, crashed_startups)) = &self.startup {
+            guard.settle(shared.read().await.safe_mode, *crashed_startups).await;
+        }
+        *done = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::supervisor::TaskState;
+    use crate::data::migrations::MigrationManager;
+    use sqlx::SqlitePool;
+
+    #[tokio::test]
+    async fn test_run_stops_loops_and_closes_the_database() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        MigrationManager::new(":memory:".to_string()).run_migrations(&pool).await.unwrap();
+        let repository = Arc::new(Repository::new(pool));
+
+        let token = CancellationToken::new();
+        let supervisor = Supervisor::new().with_shutdown(token.clone());
+        let loop_token = token.clone();
+        supervisor.spawn("loop", move || {
+            let token = loop_token.clone();
+            async move {
+                token.cancelled().await;
+                Ok(())
+            }
+        });
+
+        let shutdown = Shutdown::new(token.clone(), supervisor.clone(), repository.clone());
+        shutdown.run(SHUTDOWN_GRACE).await;
+        assert!(token.is_cancelled());
+        assert_eq!(supervisor.health()[0].state, TaskState::Stopped);
+        assert!(repository.get_database_stats().await.is_err());
+        // Nothing new is started once shut down
+        supervisor.spawn("late", || async { Ok(()) });
+        assert_eq!(supervisor.health().len(), 1);
+        shutdown.run(SHUTDOWN_GRACE).await;
+    }
+}
@@ -0,0 +1,54 @@
+use crate::core::error::{Result, SpeedKarmaError};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Held for the life of the process; the OS drops the lock when the process exits, even after a crash
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+/// Lock file next to the database, so instances sharing a database exclude each other
+pub fn lock_path() -> PathBuf {
+    crate::data::consolidation::canonical_db_path().with_file_name("speedkarma.lock")
+}
+
+/// Takes the lock at `path`; `None` while another instance holds it
+pub fn acquire_at(path: &Path) -> Result<Option<InstanceLock>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    match file.try_lock() {
+        Ok(()) => {
+            // The PID is only informational, for support bundles and curious users
+            file.set_len(0)?;
+            write!(file, "{}", std::process::id())?;
+            Ok(Some(InstanceLock { _file: file }))
+        }
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(SpeedKarmaError::SystemError(format!("Could not lock {}: {}", path.display(), e))),
+    }
+}
+
+/// Takes the per-user instance lock
+pub fn acquire() -> Result<Option<InstanceLock>> {
+    acquire_at(&lock_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_instance_is_refused_until_first_exits() {
+        let path = std::env::temp_dir().join(format!("speedkarma-lock-{}", uuid::Uuid::new_v4())).join("speedkarma.lock");
+        let first = acquire_at(&path).unwrap().expect("first instance gets the lock");
+        assert!(acquire_at(&path).unwrap().is_none());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), std::process::id().to_string());
+        drop(first);
+        assert!(acquire_at(&path).unwrap().is_some());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
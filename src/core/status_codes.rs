@@ -0,0 +1,156 @@
+use serde::Serialize;
+use StatusCategory::*;
+
+/// Group a status code belongs to; codes are unique within their category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusCategory {
+    /// Overall state of the intelligence core (`SystemState`)
+    SystemState,
+    /// Why generated traffic is not running right now
+    GenerationHold,
+    /// What makes generated traffic and measurements run less often, without holding them back
+    Throttle,
+    /// Stealth detection risk (`DetectionRisk`)
+    RiskLevel,
+    /// Low-speed alert transitions (`AlertTransition`)
+    SpeedAlert,
+    /// Events webhook endpoints can subscribe to (`WebhookEvent`)
+    WebhookEvent,
+    /// Throughput keeper cadence (`KeeperCadence`)
+    KeeperCadence,
+    /// Decision and recommendation explanations (`ReasonCode`)
+    Reason,
+}
+
+/// One entry of the glossary shared by the panel, CLI and webhook consumers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusCode {
+    pub category: StatusCategory,
+    /// Stable snake_case identifier, as found in payloads
+    pub code: &'static str,
+    /// A few words for badges and the tray
+    pub label: &'static str,
+    pub description: &'static str,
+}
+
+const fn entry(category: StatusCategory, code: &'static str, label: &'static str, description: &'static str) -> StatusCode {
+    StatusCode { category, code, label, description }
+}
+
+const CODES: &[StatusCode] = &[
+    entry(SystemState, "learning", "Learning", "Collecting baseline data before optimizing"),
+    entry(SystemState, "optimizing", "Optimizing", "Throttling found; countermeasures are running"),
+    entry(SystemState, "monitoring", "Monitoring", "Measuring speed without optimizing"),
+    entry(SystemState, "inactive", "Inactive", "Temporarily idle because of current conditions"),
+    entry(SystemState, "error", "Error", "Something needs attention; see the accompanying message"),
+    entry(GenerationHold, "optimization_disabled", "Optimization off", "Optimization is switched off"),
+    entry(GenerationHold, "stopped_by_user", "Emergency stop", "Emergency stop engaged; nothing runs until re-enabled"),
+    entry(GenerationHold, "safe_mode", "Safe mode", "Started in safe mode after repeated crashes"),
+    entry(GenerationHold, "snoozed", "Snoozed", "Paused by the user; resumes on its own"),
+    entry(GenerationHold, "quiet_hours", "Quiet hours", "Inside the configured quiet-hours window"),
+    entry(GenerationHold, "conflict", "Conflicting tool", "Another optimizer, VPN or bypass proxy is active"),
+    entry(GenerationHold, "call_active", "Call in progress", "A voice or video call is running; heavy traffic waits"),
+    entry(GenerationHold, "data_cap_reached", "Data cap reached", "This billing cycle's data cap is used up"),
+    entry(GenerationHold, "metered_connection", "Metered connection", "The connection is metered, cellular or a hotspot"),
+    entry(GenerationHold, "captive_portal", "Sign-in required", "A captive portal intercepts traffic until you sign in"),
+    entry(GenerationHold, "offline", "Offline", "No internet connection; everything waits until it returns"),
+    entry(Throttle, "power_saving", "Saving power", "On battery or in low-power mode; measurements and decisions slow down"),
+    entry(RiskLevel, "low", "Low", "Generated traffic blends in with normal browsing"),
+    entry(RiskLevel, "medium", "Medium", "Some patterns could stand out to traffic analysis"),
+    entry(RiskLevel, "high", "High", "Traffic is likely to be recognised; stealth is raised"),
+    entry(RiskLevel, "critical", "Critical", "Detection is likely; stealth is at its maximum"),
+    entry(SpeedAlert, "triggered", "Speed alert", "Download speed stayed below the alert threshold"),
+    entry(SpeedAlert, "updated", "Still slow", "Another low sample while the alert is active"),
+    entry(SpeedAlert, "resolved", "Speed recovered", "Download speed is back above the threshold"),
+    entry(WebhookEvent, "throttling_pattern", "New throttling pattern", "A throttling period not reported before was found"),
+    entry(WebhookEvent, "optimization_changed", "Optimization changed", "Optimization switched on or off, for any reason"),
+    entry(WebhookEvent, "detection_risk_critical", "Critical detection risk", "Stealth detection risk rose to critical"),
+    entry(KeeperCadence, "warmup", "Warming up", "Short bursts while the keeper learns the line"),
+    entry(KeeperCadence, "steady", "Steady", "Regular bursts at the learned size"),
+    entry(KeeperCadence, "recovery", "Recovering", "Throughput dropped; bursts come more often"),
+    entry(KeeperCadence, "suspended", "Suspended", "The keeper is not sending traffic"),
+    entry(Reason, "throttling_detected", "Throttling detected", "Throttling patterns found with enough confidence"),
+    entry(Reason, "insufficient_confidence", "Still learning", "Not enough data yet to act on"),
+    entry(Reason, "no_throttling_detected", "No throttling", "No significant throttling patterns"),
+    entry(Reason, "increase_stealth", "Raise stealth", "Detection risk is high; a higher stealth level is advised"),
+    entry(Reason, "switch_strategy", "Switch strategy", "Another strategy is expected to perform better"),
+    entry(Reason, "focus_peak_hours", "Focus on peak hours", "Throttling concentrates in a few hours of the day"),
+    entry(Reason, "weak_wifi", "Weak Wi-Fi", "Slowdowns happened on weak Wi-Fi rather than at the ISP"),
+    entry(Reason, "cgnat_congestion", "Shared ISP address", "Carrier-grade NAT; slowdowns may be gateway congestion"),
+];
+
+/// The canonical list of states, risk levels and alert codes
+pub fn all() -> &'static [StatusCode] {
+    CODES
+}
+
+/// Looks up one code
+pub fn find(category: StatusCategory, code: &str) -> Option<&'static StatusCode> {
+    CODES.iter().find(|c| c.category == category && c.code == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::WebhookEvent as Webhook;
+    use crate::core::reasons::ReasonCode;
+    use crate::network::stealth::DetectionRisk;
+
+    #[test]
+    fn test_registry_covers_serialized_codes() {
+        let mut seen = std::collections::HashSet::new();
+        assert!(CODES.iter().all(|c| seen.insert((c.category, c.code))), "duplicate status code");
+
+        let code_of = |value: serde_json::Value| value.as_str().unwrap().to_string();
+        for event in [Webhook::ThrottlingPattern, Webhook::OptimizationChanged, Webhook::DetectionRiskCritical] {
+            assert!(find(WebhookEvent, &code_of(serde_json::to_value(event).unwrap())).is_some());
+        }
+        let reasons = [
+            ReasonCode::ThrottlingDetected, ReasonCode::InsufficientConfidence, ReasonCode::NoThrottlingDetected, ReasonCode::IncreaseStealth,
+            ReasonCode::SwitchStrategy, ReasonCode::FocusPeakHours, ReasonCode::WeakWifi, ReasonCode::CgnatCongestion,
+        ];
+        for reason in reasons {
+            assert!(find(Reason, &code_of(serde_json::to_value(reason).unwrap())).is_some(), "{:?} missing", reason);
+        }
+        for state in [crate::core::intelligence::SystemState::Learning, crate::core::intelligence::SystemState::Error("db".into())] {
+            assert!(find(SystemState, state.code()).is_some());
+        }
+        for risk in [DetectionRisk::Low, DetectionRisk::Medium, DetectionRisk::High, DetectionRisk::Critical] {
+            assert!(find(RiskLevel, risk.code()).is_some());
+        }
+        assert_eq!(serde_json::to_value(&all()[0]).unwrap()["category"], "system_state");
+    }
+
+    #[test]
+    fn test_payloads_serialize_registry_codes() {
+        use crate::core::app_state::{AppControlState, ConnectivityState, OptimizationMode};
+        use crate::core::intelligence::SystemState as State;
+
+        for state in [State::Learning, State::Optimizing, State::Monitoring, State::Inactive] {
+            assert_eq!(serde_json::to_value(&state).unwrap(), state.code());
+        }
+        assert_eq!(serde_json::to_value(State::Error("db".into())).unwrap(), serde_json::json!({ "error": "db" }));
+
+        let state = AppControlState {
+            optimization_mode: OptimizationMode::Disabled,
+            stopped_by_user: true, safe_mode: true, generators_paused: true, call_active: true,
+            data_cap_reached: true, metered_suspended: true, connectivity: ConnectivityState::CaptivePortal, power_saving: true,
+            paused_until: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+            ..AppControlState::default()
+        };
+        let holds = state.to_json()["holds"].as_array().unwrap().clone();
+        assert_eq!(holds.len(), 9);
+        for code in holds {
+            assert!(find(GenerationHold, code.as_str().unwrap()).is_some(), "{} missing", code);
+        }
+        // Saving power slows things down but holds nothing back
+        assert_eq!(state.to_json()["throttles"], serde_json::json!(["power_saving"]));
+        assert!(find(Throttle, "power_saving").is_some());
+        for connectivity in [ConnectivityState::CaptivePortal, ConnectivityState::Offline] {
+            let state = AppControlState { optimization_mode: OptimizationMode::Enabled, connectivity, ..AppControlState::default() };
+            assert_eq!(state.holds(), [serde_json::to_value(connectivity).unwrap().as_str().unwrap()]);
+        }
+        assert!(AppControlState { optimization_mode: OptimizationMode::Enabled, ..AppControlState::default() }.holds().is_empty());
+    }
+}
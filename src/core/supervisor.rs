@@ -0,0 +1,276 @@
+use crate::core::error::Result;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::task::{JoinError, JoinHandle};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// A run this long counts as healthy, so the next failure starts the backoff over
+const STABLE_RUN: Duration = Duration::from_secs(1800);
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(5);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(600);
+
+/// Delay before restart number `attempt` (1-based): doubling from 5 s up to 10 minutes,
+/// scaled by `0.5 + jitter` so restarts of several installs do not line up
+pub fn restart_backoff(attempt: u32, jitter: f64) -> Duration {
+    let exp = RESTART_BACKOFF_BASE.saturating_mul(1u32 << attempt.saturating_sub(1).min(16));
+    exp.min(RESTART_BACKOFF_MAX).mul_f64(0.5 + jitter.clamp(0.0, 1.0))
+}
+
+/// Join handle that aborts its task when dropped, so aborting the supervising task also ends the
+/// worker it started
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl<T> Future for AbortOnDrop<T> {
+    type Output = std::result::Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// Failed and waiting out the backoff before the next start
+    Restarting,
+    /// Ended on purpose, e.g. the keeper after a toggle
+    Stopped,
+}
+
+/// Health of one supervised background task, reported by `get_system_status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskHealth {
+    pub name: String,
+    pub state: TaskState,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub running_since: Option<DateTime<Utc>>,
+}
+
+impl TaskHealth {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), state: TaskState::Running, restarts: 0, last_error: None, last_failure_at: None, running_since: Some(Utc::now()) }
+    }
+}
+
+/// Owns the long-running loops (monitor, decision engine, keeper, disguise). A loop that returns
+/// an error or panics is restarted with jittered exponential backoff; one that returns `Ok` stays stopped.
+#[derive(Clone, Default)]
+pub struct Supervisor {
+    health: Arc<Mutex<BTreeMap<String, TaskHealth>>>,
+    handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    shutdown: CancellationToken,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Nothing is started or restarted once `token` is cancelled
+    pub fn with_shutdown(mut self, token: CancellationToken) -> Self {
+        self.shutdown = token;
+        self
+    }
+
+    /// The token that stops supervised tasks, for loops that end on their own at shutdown
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Runs `factory()` as `name` until it stops on purpose. Ignored while a task of that name is
+    /// still running or restarting, so callers can start it again after a stop without doubling it.
+    pub fn spawn<F, Fut>(&self, name: &str, factory: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        if self.shutdown.is_cancelled() {
+            debug!("Task {} not started; shutting down", name);
+            return;
+        }
+        {
+            let mut health = self.health.lock().unwrap();
+            if health.get(name).is_some_and(|h| h.state != TaskState::Stopped) {
+                debug!("Task {} is already supervised", name);
+                return;
+            }
+            let restarts = health.get(name).map_or(0, |h| h.restarts);
+            health.insert(name.to_string(), TaskHealth { restarts, ..TaskHealth::new(name) });
+        }
+        let health = Arc::clone(&self.health);
+        let shutdown = self.shutdown.clone();
+        let name = name.to_string();
+        let handle = tokio::spawn(async move {
+            let mut attempt = 0u32;
+            loop {
+                let started = Instant::now();
+                let failure = match AbortOnDrop(tokio::spawn(factory())).await {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(e) if e.is_panic() => Some("panicked".to_string()),
+                    Err(e) => Some(format!("task ended: {}", e)),
+                };
+                let stopped = |health: &Mutex<BTreeMap<String, TaskHealth>>| {
+                    Self::update(health, &name, |h| {
+                        h.state = TaskState::Stopped;
+                        h.running_since = None;
+                    });
+                };
+                let Some(failure) = failure else {
+                    info!("Task {} stopped", name);
+                    stopped(&health);
+                    return;
+                };
+                if shutdown.is_cancelled() {
+                    warn!("Task {} failed during shutdown: {}", name, failure);
+                    stopped(&health);
+                    return;
+                }
+
+                attempt = if started.elapsed() >= STABLE_RUN { 1 } else { attempt + 1 };
+                let delay = restart_backoff(attempt, rand::thread_rng().gen::<f64>());
+                warn!("Task {} failed ({}); restart #{} in {:?}", name, failure, attempt, delay);
+                Self::update(&health, &name, |h| {
+                    h.state = TaskState::Restarting;
+                    h.restarts += 1;
+                    h.last_error = Some(failure);
+                    h.last_failure_at = Some(Utc::now());
+                    h.running_since = None;
+                });
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = shutdown.cancelled() => {
+                        stopped(&health);
+                        return;
+                    }
+                }
+                Self::update(&health, &name, |h| {
+                    h.state = TaskState::Running;
+                    h.running_since = Some(Utc::now());
+                });
+            }
+        });
+        let mut handles = self.handles.lock().unwrap();
+        handles.retain(|h| !h.is_finished());
+        handles.push(handle);
+    }
+
+    fn update(health: &Mutex<BTreeMap<String, TaskHealth>>, name: &str, change: impl FnOnce(&mut TaskHealth)) {
+        if let Some(task) = health.lock().unwrap().get_mut(name) {
+            change(task);
+        }
+    }
+
+    /// Every task supervised so far, by name
+    pub fn health(&self) -> Vec<TaskHealth> {
+        self.health.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Waits up to `grace` for every task to end after a shutdown, then aborts the rest
+    pub async fn join(&self, grace: Duration) {
+        let deadline = tokio::time::Instant::now() + grace;
+        let handles: Vec<JoinHandle<()>> = self.handles.lock().unwrap().drain(..).collect();
+        for mut handle in handles {
+            if tokio::time::timeout_at(deadline, &mut handle).await.is_err() {
+                handle.abort();
+            }
+        }
+        for task in self.health() {
+            if task.state != TaskState::Stopped {
+                warn!("Task {} did not stop within {:?}; aborted", task.name, grace);
+            }
+        }
+    }
+
+    /// Stops supervising and aborts every task
+    pub fn abort_all(&self) {
+        for handle in self.handles.lock().unwrap().drain(..) {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::error::SpeedKarmaError;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_tasks_restart_and_stopped_tasks_stay_down() {
+        let supervisor = Supervisor::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&runs);
+        supervisor.spawn("flaky", move || {
+            let run = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match run {
+                    0 => Err(SpeedKarmaError::SystemError("boom".to_string())),
+                    1 => panic!("second run panics"),
+                    _ => Ok(()),
+                }
+            }
+        });
+        // Ignored while the first one is still supervised
+        supervisor.spawn("flaky", || async { Ok(()) });
+
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        let health = supervisor.health();
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].state, TaskState::Stopped);
+        assert_eq!(health[0].restarts, 2);
+        assert_eq!(health[0].last_error.as_deref(), Some("panicked"));
+        supervisor.abort_all();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_abort_stops_the_worker() {
+        let supervisor = Supervisor::new();
+        let ticks = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&ticks);
+        supervisor.spawn("worker", move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                loop {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(5500)).await;
+        assert!(ticks.load(Ordering::SeqCst) >= 5);
+
+        supervisor.abort_all();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let after_abort = ticks.load(Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(ticks.load(Ordering::SeqCst), after_abort);
+    }
+
+    #[test]
+    fn test_restart_backoff_doubles_and_caps() {
+        assert_eq!(restart_backoff(1, 0.5), Duration::from_secs(5));
+        assert_eq!(restart_backoff(3, 0.5), Duration::from_secs(20));
+        assert_eq!(restart_backoff(40, 0.5), RESTART_BACKOFF_MAX);
+        assert_eq!(restart_backoff(2, 0.0), Duration::from_secs(5));
+        assert_eq!(restart_backoff(2, 1.0), Duration::from_secs(15));
+    }
+}
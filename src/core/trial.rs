@@ -0,0 +1,442 @@
+use crate::core::app_state::{OptimizationMode, SharedAppState};
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::core::events::SharedEventSink;
+use crate::core::intelligence::without_vpn;
+use crate::data::models::{OptimizationTrial, SpeedMeasurement, TrialStatus};
+use crate::data::stores::DataStore;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, RwLock};
+use tracing::{info, warn};
+
+/// Fewest measurements a window needs before its median is used
+const MIN_WINDOW_SAMPLES: usize = 2;
+/// Measurements the trial window needs before a slowdown can stop it early
+const MIN_ABORT_SAMPLES: usize = 3;
+/// A trial running below this share of the "before" download is stopped early
+const ABORT_RATIO: f64 = 0.5;
+/// How much further back the "before" window reaches when it has too few measurements
+const BEFORE_FALLBACK_FACTOR: u32 = 4;
+
+/// Lengths of the windows a trial compares
+#[derive(Debug, Clone)]
+pub struct TrialTiming {
+    /// Unoptimized reference taken from measurements already stored
+    pub before: Duration,
+    /// How long optimization stays on
+    pub during: Duration,
+    /// Unoptimized reference after optimization is switched back off
+    pub after: Duration,
+    /// How often a running trial is checked for cancellation or a slowdown
+    pub check_interval: Duration,
+}
+
+impl Default for TrialTiming {
+    fn default() -> Self {
+        Self {
+            before: Duration::from_secs(30 * 60),
+            during: Duration::from_secs(60 * 60),
+            after: Duration::from_secs(15 * 60),
+            check_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrialPhase {
+    Idle,
+    /// Optimization is on and being measured
+    Running,
+    /// Optimization is off again; measuring the "after" window
+    Measuring,
+    Finished,
+}
+
+/// Payload of `trial_progress` and `get_optimization_trial_status`
+#[derive(Debug, Clone, Serialize)]
+pub struct TrialProgress {
+    pub phase: TrialPhase,
+    pub started_at: Option<DateTime<Utc>>,
+    /// When optimization is switched back off
+    pub ends_at: Option<DateTime<Utc>>,
+    pub before_mbps: Option<f64>,
+    pub during_mbps: Option<f64>,
+    /// The last finished trial
+    pub result: Option<OptimizationTrial>,
+}
+
+impl Default for TrialProgress {
+    fn default() -> Self {
+        Self { phase: TrialPhase::Idle, started_at: None, ends_at: None, before_mbps: None, during_mbps: None, result: None }
+    }
+}
+
+/// Median download of the measurements in `[from, to)` and how many there were
+pub fn window_median(measurements: &[SpeedMeasurement], from: DateTime<Utc>, to: DateTime<Utc>) -> Option<(f64, usize)> {
+    let mut values: Vec<f64> = measurements
+        .iter()
+        .filter(|m| m.timestamp >= from && m.timestamp < to)
+        .map(|m| m.download_mbps)
+        .collect();
+    if values.is_empty() { return None; }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = values.len() / 2;
+    let median = if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] };
+    Some((median, values.len()))
+}
+
+/// Drops medians built from too few measurements
+fn usable(window: Option<(f64, usize)>) -> Option<f64> {
+    window.filter(|(_, n)| *n >= MIN_WINDOW_SAMPLES).map(|(median, _)| median)
+}
+
+/// Whether a running trial is clearly hurting the connection
+pub fn should_abort(before_mbps: Option<f64>, during: Option<(f64, usize)>) -> bool {
+    match (before_mbps, during) {
+        (Some(before), Some((median, n))) => n >= MIN_ABORT_SAMPLES && median < before * ABORT_RATIO,
+        _ => false,
+    }
+}
+
+/// Outcome of a trial that ran its full length: it needs the trial window and at least one reference window
+pub fn evaluate(before: Option<f64>, during: Option<f64>, after: Option<f64>) -> (TrialStatus, Option<String>) {
+    match (during, before.or(after)) {
+        (Some(_), Some(_)) => (TrialStatus::Completed, None),
+        (None, _) => (TrialStatus::Inconclusive, Some("Too few measurements while optimization was on".into())),
+        (Some(_), None) => (TrialStatus::Inconclusive, Some("Too few measurements without optimization to compare".into())),
+    }
+}
+
+fn chrono(duration: Duration) -> ChronoDuration {
+    ChronoDuration::from_std(duration).unwrap_or_else(|_| ChronoDuration::zero())
+}
+
+/// Runs one supervised "try optimization now" trial at a time. Optimization is switched on for a
+/// fixed window and back off afterwards; the before/during/after medians are stored for the model.
+pub struct TrialRunner {
+    store: Arc<dyn DataStore>,
+    shared: SharedAppState,
+    events: SharedEventSink,
+    timing: TrialTiming,
+    progress: RwLock<TrialProgress>,
+    cancel: Notify,
+}
+
+impl TrialRunner {
+    pub fn new(store: Arc<dyn DataStore>, shared: SharedAppState, events: SharedEventSink) -> Self {
+        Self {
+            store,
+            shared,
+            events,
+            timing: TrialTiming::default(),
+            progress: RwLock::new(TrialProgress::default()),
+            cancel: Notify::new(),
+        }
+    }
+
+    pub fn with_timing(mut self, timing: TrialTiming) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    pub async fn status(&self) -> TrialProgress { self.progress.read().await.clone() }
+
+    /// Switches optimization on and supervises it in the background. Refused while a trial is
+    /// running, optimization is already on, traffic generators are paused or stopped by the user.
+    pub async fn start(self: Arc<Self>) -> Result<TrialProgress> {
+        if matches!(self.progress.read().await.phase, TrialPhase::Running | TrialPhase::Measuring) {
+            return Err(SpeedKarmaError::ConfigurationError("A trial is already running".into()));
+        }
+        // Switching optimization on under the state lock is what claims the trial, so a second
+        // start racing this one sees it on and is refused
+        {
+            let mut state = self.shared.write().await;
+            if state.optimization_mode == OptimizationMode::Enabled {
+                return Err(SpeedKarmaError::ConfigurationError("Optimization is already on".into()));
+            }
+            if state.generators_paused {
+                return Err(SpeedKarmaError::ConfigurationError("Optimization is paused while another network tool is active".into()));
+            }
+            if state.stopped_by_user {
+                return Err(SpeedKarmaError::ConfigurationError("Emergency stop is engaged".into()));
+            }
+            if state.safe_mode {
+                return Err(SpeedKarmaError::ConfigurationError("Safe mode is on after repeated crashes".into()));
+            }
+            state.set_optimization_mode(OptimizationMode::Enabled);
+            state.transport_in_use = None;
+        }
+
+        let started_at = Utc::now();
+        let before_mbps = self.before_median(started_at).await;
+        {
+            let mut progress = self.progress.write().await;
+            *progress = TrialProgress {
+                phase: TrialPhase::Running,
+                started_at: Some(started_at),
+                ends_at: Some(started_at + chrono(self.timing.during)),
+                before_mbps,
+                during_mbps: None,
+                result: progress.result.take(),
+            };
+        }
+        info!("Optimization trial started");
+        self.emit().await;
+
+        let runner = Arc::clone(&self);
+        tokio::spawn(async move { runner.run().await; });
+        Ok(self.status().await)
+    }
+
+    /// Stops a running trial early; returns whether one was running
+    pub async fn cancel(&self) -> bool {
+        let running = self.progress.read().await.phase == TrialPhase::Running;
+        if running { self.cancel.notify_one(); }
+        running
+    }
+
+    async fn run(&self) {
+        let (started_at, before_mbps) = {
+            let p = self.progress.read().await;
+            (p.started_at.unwrap_or_else(Utc::now), p.before_mbps)
+        };
+        let deadline = Instant::now() + self.timing.during;
+        let mut abort_note: Option<String> = None;
+        // What the stealth engine actually ran, which is TCP after a proxy or QUIC fallback
+        let mut transport = None;
+
+        while abort_note.is_none() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() { break; }
+            tokio::select! {
+                _ = tokio::time::sleep(remaining.min(self.timing.check_interval)) => {}
+                _ = self.cancel.notified() => { abort_note = Some("Cancelled".into()); break; }
+            }
+
+            let state = self.shared.read().await.clone();
+            transport = state.transport_in_use.or(transport);
+            if state.optimization_mode != OptimizationMode::Enabled {
+                abort_note = Some("Optimization was switched off during the trial".into());
+            } else if state.generators_paused {
+                abort_note = Some("Another network tool became active".into());
+            }
+
+            let during = self.median(started_at, Utc::now()).await;
+            self.progress.write().await.during_mbps = usable(during);
+            if abort_note.is_none() && should_abort(before_mbps, during) {
+                abort_note = Some("Download dropped sharply while optimizing".into());
+            }
+            self.emit().await;
+        }
+
+        // Hand the connection back before measuring the reference
+        let during_end = Utc::now();
+        {
+            let mut state = self.shared.write().await;
+            state.set_optimization_mode(OptimizationMode::Disabled);
+            transport = state.transport_in_use.or(transport);
+        }
+        let during_mbps = usable(self.median(started_at, during_end).await);
+
+        let (after_mbps, status, note) = match abort_note {
+            Some(note) => (None, TrialStatus::Aborted, Some(note)),
+            None => {
+                {
+                    let mut p = self.progress.write().await;
+                    p.phase = TrialPhase::Measuring;
+                    p.during_mbps = during_mbps;
+                }
+                self.emit().await;
+                tokio::time::sleep(self.timing.after).await;
+                let after_mbps = usable(self.median(during_end, Utc::now()).await);
+                let (status, note) = evaluate(before_mbps, during_mbps, after_mbps);
+                (after_mbps, status, note)
+            }
+        };
+
+        let strategy_name = self.store.get_best_optimization_strategy().await.ok().flatten().map(|s| s.name);
+        let mut trial = OptimizationTrial {
+            id: None,
+            started_at,
+            ended_at: Utc::now(),
+            strategy_name,
+            before_mbps,
+            during_mbps,
+            after_mbps,
+            status,
+            note,
+            transport,
+        };
+        match self.store.save_optimization_trial(&trial).await {
+            Ok(id) => trial.id = Some(id),
+            Err(e) => warn!("Failed to save optimization trial: {}", e),
+        }
+        info!("Optimization trial finished: {}", trial.status.as_str());
+        self.notify_result(&trial);
+
+        {
+            let mut p = self.progress.write().await;
+            p.phase = TrialPhase::Finished;
+            p.during_mbps = during_mbps;
+            p.result = Some(trial);
+        }
+        self.emit().await;
+    }
+
+    /// Median of the stored measurements before `started_at`, reaching further back when the
+    /// usual window is too sparse
+    async fn before_median(&self, started_at: DateTime<Utc>) -> Option<f64> {
+        let window = chrono(self.timing.before);
+        usable(self.median(started_at - window, started_at).await)
+            .or(usable(self.median(started_at - window * BEFORE_FALLBACK_FACTOR as i32, started_at).await))
+    }
+
+    async fn median(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Option<(f64, usize)> {
+        match self.store.get_speed_measurements_since(from).await {
+            Ok(measurements) => window_median(&without_vpn(&measurements), from, to),
+            Err(e) => {
+                warn!("Measurements unavailable for trial: {}", e);
+                None
+            }
+        }
+    }
+
+    fn notify_result(&self, trial: &OptimizationTrial) {
+        let body = match (trial.status, trial.improvement()) {
+            (TrialStatus::Completed, Some(ratio)) => format!(
+                "Download was {:+.0}% with optimization on ({:.1} Mbps). The model will use this result.",
+                (ratio - 1.0) * 100.0,
+                trial.during_mbps.unwrap_or_default()
+            ),
+            (TrialStatus::Aborted, _) => format!("Trial stopped: {}", trial.note.as_deref().unwrap_or("cancelled")),
+            _ => trial.note.clone().unwrap_or_else(|| "Not enough measurements to judge the trial".into()),
+        };
+        self.events.notify("Optimization trial finished", &body);
+    }
+
+    async fn emit(&self) {
+        let progress = self.status().await;
+        self.events.emit_payload("trial_progress", &progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::app_state::AppControlState;
+    use crate::core::events::NullEventSink;
+    use crate::data::memory_store::InMemoryStore;
+    use crate::data::models::{MeasurementMethod, MeasurementSource};
+    use crate::data::stores::{FeedbackStore, MeasurementStore};
+
+    fn measurement(at: DateTime<Utc>, download_mbps: f64) -> SpeedMeasurement {
+        SpeedMeasurement {
+            id: None,
+            timestamp: at,
+            download_mbps,
+            upload_mbps: 5.0,
+            latency_ms: Some(20),
+            optimization_active: false,
+            confidence: 0.8,
+            source: MeasurementSource::Passive,
+            wifi_rssi_dbm: None,
+            wifi_link_mbps: None,
+            packet_loss_pct: None,
+            jitter_ms: None,
+            via_vpn: false,
+            via_proxy: false,
+            network_context: None,
+            method: MeasurementMethod::Interface,
+            upload_measured: true,
+        }
+    }
+
+    fn runner(store: Arc<InMemoryStore>, shared: SharedAppState) -> Arc<TrialRunner> {
+        Arc::new(TrialRunner::new(store, shared, Arc::new(NullEventSink)).with_timing(TrialTiming {
+            before: Duration::from_millis(500),
+            during: Duration::from_millis(400),
+            after: Duration::from_millis(300),
+            check_interval: Duration::from_millis(50),
+        }))
+    }
+
+    async fn wait_finished(runner: &TrialRunner) -> TrialProgress {
+        for _ in 0..100 {
+            let progress = runner.status().await;
+            if progress.phase == TrialPhase::Finished { return progress; }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("trial did not finish");
+    }
+
+    #[test]
+    fn test_window_median_and_evaluation() {
+        let t0 = Utc::now();
+        let at = |m: i64| t0 + ChronoDuration::minutes(m);
+        let rows = vec![measurement(at(0), 10.0), measurement(at(5), 30.0), measurement(at(10), 20.0), measurement(at(20), 50.0), measurement(at(30), 99.0)];
+        assert_eq!(window_median(&rows, at(0), at(15)), Some((20.0, 3)));
+        assert_eq!(window_median(&rows, at(0), at(25)), Some((25.0, 4)));
+        assert_eq!(window_median(&rows, at(40), at(50)), None);
+
+        assert!(should_abort(Some(20.0), Some((9.0, 3))));
+        assert!(!should_abort(Some(20.0), Some((9.0, 2))));
+        assert!(!should_abort(Some(20.0), Some((11.0, 5))));
+        assert!(!should_abort(None, Some((1.0, 5))));
+
+        assert_eq!(evaluate(Some(10.0), Some(12.0), None).0, TrialStatus::Completed);
+        assert_eq!(evaluate(None, Some(12.0), Some(10.0)).0, TrialStatus::Completed);
+        assert_eq!(evaluate(Some(10.0), None, Some(10.0)).0, TrialStatus::Inconclusive);
+        assert_eq!(evaluate(None, Some(12.0), None).0, TrialStatus::Inconclusive);
+    }
+
+    #[tokio::test]
+    async fn test_trial_measures_and_restores_mode() {
+        let store = Arc::new(InMemoryStore::new());
+        let t0 = Utc::now();
+        let at = |ms: i64| t0 + ChronoDuration::milliseconds(ms);
+        // Before, during and after windows; later rows are timestamped ahead and come into range as the trial runs
+        for (ms, mbps) in [(-300, 10.0), (-200, 10.0), (-100, 10.0), (100, 15.0), (150, 15.0), (200, 15.0), (500, 10.0), (550, 10.0)] {
+            store.save_speed_measurement(&measurement(at(ms), mbps)).await.unwrap();
+        }
+        let shared: SharedAppState = Arc::new(RwLock::new(AppControlState::default()));
+        let runner = runner(Arc::clone(&store), shared.clone());
+
+        runner.clone().start().await.unwrap();
+        assert_eq!(shared.read().await.optimization_mode, OptimizationMode::Enabled);
+        assert!(runner.clone().start().await.is_err(), "second trial must be refused");
+
+        let progress = wait_finished(&runner).await;
+        assert_eq!(shared.read().await.optimization_mode, OptimizationMode::Disabled);
+        let trial = progress.result.unwrap();
+        assert_eq!(trial.status, TrialStatus::Completed);
+        assert_eq!(trial.before_mbps, Some(10.0));
+        assert_eq!(trial.during_mbps, Some(15.0));
+        assert!((trial.improvement().unwrap() - 1.5).abs() < 1e-9);
+
+        let saved = store.get_optimization_trials_since(t0 - ChronoDuration::hours(1)).await.unwrap();
+        assert_eq!(saved.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_trial_cancel_and_refusals() {
+        let store = Arc::new(InMemoryStore::new());
+        let shared: SharedAppState = Arc::new(RwLock::new(AppControlState::default()));
+        let runner = runner(Arc::clone(&store), shared.clone());
+
+        shared.write().await.optimization_mode = OptimizationMode::Enabled;
+        assert!(runner.clone().start().await.is_err());
+        shared.write().await.optimization_mode = OptimizationMode::Disabled;
+
+        runner.clone().start().await.unwrap();
+        assert!(runner.cancel().await);
+        let trial = wait_finished(&runner).await.result.unwrap();
+        assert_eq!(trial.status, TrialStatus::Aborted);
+        assert_eq!(trial.note.as_deref(), Some("Cancelled"));
+        assert!(trial.improvement().is_none());
+        assert_eq!(shared.read().await.optimization_mode, OptimizationMode::Disabled);
+        assert!(!runner.cancel().await);
+    }
+}
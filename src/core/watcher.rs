@@ -0,0 +1,34 @@
+use crate::core::shutdown;
+use crate::core::supervisor::Supervisor;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A background check on its own cadence (power source, metered flag, connectivity, competing
+/// tools). `poll` runs one round, applies what it found and returns the wait before the next.
+#[async_trait]
+pub trait PollingWatcher: Send + Sync + 'static {
+    async fn poll(&self) -> Duration;
+
+    /// Polls as the supervised task `name`, restarted if a round panics and stopped mid-round
+    /// or mid-wait by the supervisor's shutdown
+    fn supervise(self: Arc<Self>, name: &str, supervisor: &Supervisor) where Self: Sized {
+        let token = supervisor.shutdown_token();
+        supervisor.spawn(name, move || {
+            let watcher = Arc::clone(&self);
+            let token = token.clone();
+            async move {
+                loop {
+                    let wait = tokio::select! {
+                        wait = watcher.poll() => wait,
+                        _ = shutdown::cancelled(Some(&token)) => return Ok(()),
+                    };
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => {}
+                        _ = shutdown::cancelled(Some(&token)) => return Ok(()),
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,128 @@
+use crate::core::app_state::{OptimizationMode, SharedAppState};
+use crate::core::config::{WebhookEvent, WebhooksConfig};
+use crate::core::error::Result;
+use crate::core::intelligence::TimeRange;
+use crate::network::proxy;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body of every webhook POST
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub timestamp: DateTime<Utc>,
+    pub data: serde_json::Value,
+}
+
+/// Throttling periods in `current` that are not in `known`
+pub fn new_throttling_periods(known: &[TimeRange], current: &[TimeRange]) -> Vec<TimeRange> {
+    current.iter().filter(|period| !known.contains(period)).cloned().collect()
+}
+
+/// Posts events to the endpoints subscribed to them. Delivery is best effort: each POST runs in
+/// the background and failures are only logged, so a dead endpoint never slows the engine down.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    config: RwLock<WebhooksConfig>,
+    /// Risk was Critical at the last assessment
+    risk_critical: AtomicBool,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhooksConfig) -> Result<Self> {
+        let client = proxy::apply(reqwest::Client::builder()).timeout(REQUEST_TIMEOUT).build()?;
+        Ok(Self { client, config: RwLock::new(config), risk_critical: AtomicBool::new(false) })
+    }
+
+    /// URLs subscribed to `event`
+    pub async fn endpoints_for(&self, event: WebhookEvent) -> Vec<String> {
+        self.config.read().await.endpoints.iter().filter(|e| e.events.contains(&event)).map(|e| e.url.clone()).collect()
+    }
+
+    pub async fn send<T: Serialize>(&self, event: WebhookEvent, data: &T) {
+        let urls = self.endpoints_for(event).await;
+        if urls.is_empty() {
+            return;
+        }
+        let payload = match serde_json::to_value(data) {
+            Ok(data) => WebhookPayload { event, timestamp: Utc::now(), data },
+            Err(e) => { debug!("Dropping {:?} webhook: {}", event, e); return; }
+        };
+        for url in urls {
+            let request = self.client.post(&url).json(&payload);
+            tokio::spawn(async move {
+                match request.send().await.and_then(|r| r.error_for_status()) {
+                    Ok(_) => debug!("Webhook {:?} delivered to {}", payload.event, url),
+                    Err(e) => warn!("Webhook {:?} to {} failed: {}", payload.event, url, e),
+                }
+            });
+        }
+    }
+
+    /// Posts `optimization_changed` whenever the mode flips, whichever part of the app flipped it
+    pub fn watch_optimization(self: Arc<Self>, shared: SharedAppState) {
+        tokio::spawn(async move {
+            let (mut last, mut changes) = { let s = shared.read().await; (s.optimization_mode, s.mode_changed.subscribe()) };
+            while changes.changed().await.is_ok() {
+                let (mode, stopped_by_user) = { let s = shared.read().await; (s.optimization_mode, s.stopped_by_user) };
+                if mode != last {
+                    let active = mode == OptimizationMode::Enabled;
+                    self.send(WebhookEvent::OptimizationChanged, &serde_json::json!({ "active": active, "stopped_by_user": stopped_by_user })).await;
+                    last = mode;
+                }
+            }
+        });
+    }
+
+    /// Posts `detection_risk_critical` when the risk enters Critical; repeated Critical
+    /// assessments stay quiet until the risk has dropped below it again. True when it posted
+    pub async fn detection_risk<T: Serialize>(&self, critical: bool, data: &T) -> bool {
+        if self.risk_critical.swap(critical, Ordering::Relaxed) || !critical {
+            return false;
+        }
+        self.send(WebhookEvent::DetectionRiskCritical, data).await;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::WebhookEndpoint;
+
+    #[tokio::test]
+    async fn test_events_reach_subscribed_endpoints_only() {
+        let notifier = WebhookNotifier::new(WebhooksConfig { endpoints: vec![
+            WebhookEndpoint { url: "http://127.0.0.1:9/all".into(), events: vec![WebhookEvent::ThrottlingPattern, WebhookEvent::OptimizationChanged, WebhookEvent::DetectionRiskCritical] },
+            WebhookEndpoint { url: "http://127.0.0.1:9/risk".into(), events: vec![WebhookEvent::DetectionRiskCritical] },
+        ] }).unwrap();
+        assert_eq!(notifier.endpoints_for(WebhookEvent::OptimizationChanged).await, vec!["http://127.0.0.1:9/all".to_string()]);
+        assert_eq!(notifier.endpoints_for(WebhookEvent::DetectionRiskCritical).await.len(), 2);
+
+        let payload = serde_json::to_value(WebhookPayload { event: WebhookEvent::ThrottlingPattern, timestamp: Utc::now(), data: serde_json::json!({}) }).unwrap();
+        assert_eq!(payload["event"], "throttling_pattern");
+
+        let evening = TimeRange::new("19:00", "23:00");
+        let night = TimeRange::new("01:00", "03:00");
+        assert_eq!(new_throttling_periods(&[evening.clone()], &[evening.clone(), night.clone()]), vec![night]);
+        assert!(new_throttling_periods(&[evening.clone()], &[evening]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_critical_risk_posts_on_transition_only() {
+        let notifier = WebhookNotifier::new(WebhooksConfig::default()).unwrap();
+        let data = serde_json::json!({});
+        assert!(!notifier.detection_risk(false, &data).await);
+        assert!(notifier.detection_risk(true, &data).await);
+        assert!(!notifier.detection_risk(true, &data).await);
+        assert!(!notifier.detection_risk(false, &data).await);
+        assert!(notifier.detection_risk(true, &data).await);
+    }
+}
@@ -0,0 +1,97 @@
+//! Pure attribution of slowdowns to a weak Wi-Fi signal. Reading the signal lives in `network::wifi`.
+
+use crate::data::models::SpeedMeasurement;
+use serde::{Deserialize, Serialize};
+
+/// A measurement this far below the baseline counts as a slowdown
+const SLOW_RATIO: f64 = 0.7;
+/// Slowdowns needed before they are attributed to anything
+const MIN_SLOW_SAMPLES: u32 = 3;
+/// Share of slowdowns on weak Wi-Fi above which they are blamed on the wireless link
+const LOCAL_SHARE: f64 = 0.6;
+
+/// How slowdowns split between weak Wi-Fi and everything else
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WifiAttribution {
+    /// Measurements with a Wi-Fi reading
+    pub wifi_samples: u32,
+    /// Measurements well below the baseline
+    pub slow_samples: u32,
+    /// Slowdowns that happened on weak Wi-Fi
+    pub slow_on_weak_wifi: u32,
+    /// Median signal during those slowdowns
+    pub median_rssi_dbm: Option<i32>,
+    /// Whether slowdowns line up with weak Wi-Fi rather than the ISP
+    pub local_wireless: bool,
+}
+
+impl WifiAttribution {
+    /// `None` when no measurement carries a Wi-Fi reading (wired or unsupported platform)
+    pub fn from_measurements(measurements: &[SpeedMeasurement], baseline_mbps: f64) -> Option<Self> {
+        let wifi_samples = measurements.iter().filter(|m| m.wifi_rssi_dbm.is_some() || m.wifi_link_mbps.is_some()).count();
+        if wifi_samples == 0 || baseline_mbps <= 0.0 { return None; }
+
+        let is_slow = |m: &SpeedMeasurement| m.download_mbps < baseline_mbps * SLOW_RATIO;
+        let weak = measurements.iter().filter(|m| m.on_weak_wifi()).count();
+        let slow = measurements.iter().filter(|m| is_slow(m)).count();
+        let mut weak_slow_rssi: Vec<i32> = Vec::new();
+        let mut slow_on_weak_wifi = 0;
+        for m in measurements.iter().filter(|m| is_slow(m) && m.on_weak_wifi()) {
+            slow_on_weak_wifi += 1;
+            weak_slow_rssi.extend(m.wifi_rssi_dbm);
+        }
+        weak_slow_rssi.sort_unstable();
+        let median_rssi_dbm = weak_slow_rssi.get(weak_slow_rssi.len() / 2).copied();
+
+        // Weak Wi-Fi has to explain most slowdowns and be slow more often than a good signal is
+        let slow_rate = |slow: usize, total: usize| if total == 0 { 0.0 } else { slow as f64 / total as f64 };
+        let correlated = slow_rate(slow_on_weak_wifi, weak) > slow_rate(slow - slow_on_weak_wifi, measurements.len() - weak);
+        let local_wireless = slow as u32 >= MIN_SLOW_SAMPLES
+            && slow_rate(slow_on_weak_wifi, slow) >= LOCAL_SHARE
+            && correlated;
+
+        Some(Self {
+            wifi_samples: wifi_samples as u32,
+            slow_samples: slow as u32,
+            slow_on_weak_wifi: slow_on_weak_wifi as u32,
+            median_rssi_dbm,
+            local_wireless,
+        })
+    }
+
+    /// Plain-language explanation for the user
+    pub fn message(&self) -> String {
+        let signal = self.median_rssi_dbm.map(|rssi| format!(" ({} dBm)", rssi)).unwrap_or_default();
+        format!(
+            "{} of {} slowdowns happened on a weak Wi-Fi signal{}. This looks like a local wireless problem rather than your ISP; moving closer to the router may help.",
+            self.slow_on_weak_wifi, self.slow_samples, signal
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slowdowns_on_weak_wifi_are_local() {
+        let sample = |mbps: f64, rssi: Option<i32>| SpeedMeasurement { wifi_rssi_dbm: rssi, ..SpeedMeasurement::new(mbps, 5.0, 20, false) };
+        let mut rows: Vec<_> = (0..10).map(|_| sample(50.0, Some(-50))).collect();
+        rows.extend((0..4).map(|_| sample(10.0, Some(-78))));
+        rows.push(sample(12.0, Some(-52)));
+
+        let attribution = WifiAttribution::from_measurements(&rows, 50.0).unwrap();
+        assert_eq!((attribution.slow_samples, attribution.slow_on_weak_wifi), (5, 4));
+        assert_eq!(attribution.median_rssi_dbm, Some(-78));
+        assert!(attribution.local_wireless);
+        assert!(attribution.message().contains("-78 dBm"));
+
+        // Slow on a strong signal points at the ISP
+        let isp: Vec<_> = (0..5).map(|_| sample(10.0, Some(-45))).chain((0..10).map(|_| sample(50.0, Some(-45)))).collect();
+        assert!(!WifiAttribution::from_measurements(&isp, 50.0).unwrap().local_wireless);
+
+        // Wired history carries no Wi-Fi readings
+        let wired: Vec<_> = (0..5).map(|_| sample(10.0, None)).collect();
+        assert!(WifiAttribution::from_measurements(&wired, 50.0).is_none());
+    }
+}
@@ -0,0 +1,28 @@
+use crate::core::config::CompactionConfig;
+use crate::data::repository::Repository;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Starts the background job that rolls old raw measurements into hourly aggregates
+pub fn start_compaction_job(repository: Arc<Repository>, config: CompactionConfig) {
+    if !config.enabled {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_hours.max(1) as u64 * 3600));
+        loop {
+            interval.tick().await;
+            match repository.compact_measurements_older_than(config.raw_retention_days).await {
+                Ok(0) => {}
+                Ok(n) => {
+                    info!("Compacted {} raw measurements into hourly aggregates", n);
+                    if let Err(e) = repository.reclaim_space().await {
+                        warn!("Failed to reclaim database space: {}", e);
+                    }
+                }
+                Err(e) => warn!("Measurement compaction failed: {}", e),
+            }
+        }
+    });
+}
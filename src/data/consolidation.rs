@@ -0,0 +1,405 @@
+use crate::core::config::AppConfig;
+use crate::core::error::Result;
+use crate::data::integrity::{integrity_problems, move_aside, open_single, sqlite_url};
+use crate::data::migrations::MigrationManager;
+use crate::data::repository::Repository;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+const DB_FILE: &str = "speedkarma.db";
+
+/// Where the database lives: the platform data dir, or the temp dir older versions used when there is none
+pub fn canonical_db_path() -> PathBuf {
+    dirs::data_dir()
+        .map(|d| d.join("SpeedKarma").join(DB_FILE))
+        .unwrap_or_else(|| std::env::temp_dir().join(DB_FILE))
+}
+
+/// Paths are case-insensitive on the default macOS and Windows file systems
+fn same_path(a: &Path, b: &Path) -> bool {
+    if cfg!(target_os = "linux") {
+        a == b
+    } else {
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    }
+}
+
+fn existing_strays(candidates: Vec<PathBuf>, canonical: &Path) -> Vec<PathBuf> {
+    let mut kept: Vec<PathBuf> = Vec::new();
+    for path in candidates {
+        if !same_path(&path, canonical) && !kept.iter().any(|k| same_path(k, &path)) && path.is_file() {
+            kept.push(path);
+        }
+    }
+    kept
+}
+
+/// Databases left behind by older versions (the temp-dir file)
+pub fn stray_databases(canonical: &Path) -> Vec<PathBuf> {
+    existing_strays(vec![std::env::temp_dir().join(DB_FILE)], canonical)
+}
+
+/// Config files at locations older versions or other platforms' spellings used
+pub fn stray_configs(canonical: &Path) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(dir) = dirs::config_dir() {
+        candidates.push(dir.join("SpeedKarma").join("config.json"));
+        candidates.push(dir.join("speedkarma").join("config.json"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        candidates.push(home.join(".speedkarma").join("config.json"));
+    }
+    existing_strays(candidates, canonical)
+}
+
+/// Measurement history merged from one stray database
+#[derive(Debug, Clone, Serialize)]
+pub struct MergedDatabase {
+    pub source: PathBuf,
+    pub measurements_found: u64,
+    pub imported: u64,
+    /// Rows already present in the canonical store
+    pub duplicates: u64,
+    /// The whole file became the canonical database because none existed yet
+    pub moved: bool,
+    /// Where the stray file was moved once merged
+    pub archived_as: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+/// What happened to one stray config file
+#[derive(Debug, Clone, Serialize)]
+pub struct MergedConfig {
+    pub source: PathBuf,
+    /// Whether it became the canonical config (only when none existed yet)
+    pub adopted: bool,
+    pub archived_as: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+/// Result of the one-time consolidation run at startup
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsolidationReport {
+    pub ran_at: DateTime<Utc>,
+    pub canonical_db: PathBuf,
+    pub databases: Vec<MergedDatabase>,
+    pub configs: Vec<MergedConfig>,
+}
+
+impl ConsolidationReport {
+    pub fn new(canonical_db: PathBuf, configs: Vec<MergedConfig>, databases: Vec<MergedDatabase>) -> Self {
+        Self { ran_at: Utc::now(), canonical_db, databases, configs }
+    }
+
+    /// Whether any stray artifact was found
+    pub fn found_anything(&self) -> bool {
+        !self.databases.is_empty() || !self.configs.is_empty()
+    }
+
+    /// One line for logs and notifications
+    pub fn summary(&self) -> String {
+        if !self.found_anything() {
+            return "No data from older versions found".to_string();
+        }
+        let (moved, merged): (Vec<&MergedDatabase>, Vec<&MergedDatabase>) = self.databases.iter().partition(|d| d.moved);
+        let imported: u64 = merged.iter().map(|d| d.imported).sum();
+        let duplicates: u64 = merged.iter().map(|d| d.duplicates).sum();
+        let adopted = self.configs.iter().any(|c| c.adopted);
+        let failed = self.databases.iter().filter(|d| d.error.is_some()).count()
+            + self.configs.iter().filter(|c| c.error.is_some()).count();
+        let mut parts = Vec::new();
+        if let Some(db) = moved.first() {
+            parts.push(format!("Moved the older database ({} measurements) to {}", db.measurements_found, self.canonical_db.display()));
+        }
+        if !merged.is_empty() || parts.is_empty() {
+            parts.push(format!(
+                "Imported {} measurements from {} older database(s) ({} duplicates skipped)",
+                imported, merged.len(), duplicates
+            ));
+        }
+        if adopted { parts.push("restored settings from an older config".to_string()); }
+        if failed > 0 { parts.push(format!("{} item(s) could not be migrated", failed)); }
+        parts.join("; ")
+    }
+}
+
+/// Adopts the first valid stray config when `target` does not exist yet, then archives every stray
+/// so the next start does not look at them again
+pub async fn consolidate_configs(strays: &[PathBuf], target: &Path) -> Vec<MergedConfig> {
+    let mut target_exists = tokio::fs::try_exists(target).await.unwrap_or(false);
+    let mut merged = Vec::new();
+    for source in strays {
+        let mut entry = MergedConfig { source: source.clone(), adopted: false, archived_as: None, error: None };
+        let parsed = tokio::fs::read_to_string(source)
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<AppConfig>(&json).map_err(|e| e.to_string()))
+            .and_then(|cfg| cfg.validate().map(|_| cfg).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(cfg) if !target_exists => {
+                if let Some(parent) = target.parent() {
+                    let _ = tokio::fs::create_dir_all(parent).await;
+                }
+                match cfg.write_to(target).await {
+                    Ok(()) => { entry.adopted = true; target_exists = true; }
+                    Err(e) => entry.error = Some(e.to_string()),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => entry.error = Some(e),
+        }
+        merged.push(entry);
+    }
+    // Without a canonical config to fall back on, strays stay put for the next run
+    if target_exists {
+        for entry in &mut merged {
+            match move_aside(&entry.source, "migrated").await {
+                Ok(archived) => entry.archived_as = Some(archived),
+                Err(e) => warn!("Could not archive old config {}: {}", entry.source.display(), e),
+            }
+        }
+    }
+    merged
+}
+
+/// Renames across file systems by copying, as the temp dir is often a separate mount
+async fn move_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    if tokio::fs::rename(source, target).await.is_ok() {
+        return Ok(());
+    }
+    tokio::fs::copy(source, target).await?;
+    tokio::fs::remove_file(source).await
+}
+
+/// Moves the first healthy stray database to `canonical` when no canonical database exists yet,
+/// keeping learned patterns, strategies and feedback that a measurement merge would drop.
+/// Returns `None` when there is nothing to move; damaged strays are left for `merge_databases`.
+pub async fn adopt_database(strays: &[PathBuf], canonical: &Path) -> Option<MergedDatabase> {
+    if tokio::fs::try_exists(canonical).await.unwrap_or(true) {
+        return None;
+    }
+    for source in strays {
+        let Ok(pool) = open_single(source, false).await else { continue };
+        if !matches!(integrity_problems(&pool).await, Ok(problems) if problems.is_empty()) {
+            pool.close().await;
+            continue;
+        }
+        let measurements: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM speed_measurements").fetch_one(&pool).await.unwrap_or(0);
+        // Fold the WAL into the main file so a single file carries everything
+        let _ = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&pool).await;
+        pool.close().await;
+
+        let mut moved = MergedDatabase {
+            source: source.clone(),
+            measurements_found: measurements as u64,
+            imported: measurements as u64,
+            duplicates: 0,
+            moved: true,
+            archived_as: None,
+            error: None,
+        };
+        if let Some(parent) = canonical.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        if let Err(e) = move_file(source, canonical).await {
+            warn!("Could not move {} to {}: {}", source.display(), canonical.display(), e);
+            continue;
+        }
+        for suffix in ["-wal", "-shm"] {
+            let companion = PathBuf::from(format!("{}{}", source.display(), suffix));
+            if tokio::fs::try_exists(&companion).await.unwrap_or(false) {
+                let _ = move_file(&companion, Path::new(&format!("{}{}", canonical.display(), suffix))).await;
+            }
+        }
+        info!("Moved database {} to {}", source.display(), canonical.display());
+        moved.archived_as = Some(canonical.to_path_buf());
+        return Some(moved);
+    }
+    None
+}
+
+/// Same reading taken at the same instant
+fn measurement_key(timestamp: DateTime<Utc>, download_mbps: f64) -> (i64, u64) {
+    (timestamp.timestamp_millis(), download_mbps.to_bits())
+}
+
+async fn merge_database(repository: &Repository, source: &Path, known: &mut HashSet<(i64, u64)>) -> Result<MergedDatabase> {
+    let pool = open_single(source, false).await?;
+    let problems = integrity_problems(&pool).await?;
+    if !problems.is_empty() {
+        pool.close().await;
+        return Ok(MergedDatabase {
+            source: source.to_path_buf(),
+            measurements_found: 0,
+            imported: 0,
+            duplicates: 0,
+            moved: false,
+            archived_as: None,
+            error: Some(format!("database is damaged: {}", problems.join("; "))),
+        });
+    }
+    // Bring an old schema up to date so it reads like the canonical store
+    MigrationManager::new(sqlite_url(source)).run_migrations(&pool).await?;
+    let stray = Repository::new(pool.clone());
+    let measurements = stray.get_speed_measurements_since(Utc.timestamp_opt(0, 0).unwrap()).await?;
+    pool.close().await;
+
+    let mut merged = MergedDatabase {
+        source: source.to_path_buf(),
+        measurements_found: measurements.len() as u64,
+        imported: 0,
+        duplicates: 0,
+        moved: false,
+        archived_as: None,
+        error: None,
+    };
+    for m in measurements.iter().rev() {
+        if known.insert(measurement_key(m.timestamp, m.download_mbps)) {
+            repository.save_speed_measurement(m).await?;
+            merged.imported += 1;
+        } else {
+            merged.duplicates += 1;
+        }
+    }
+    merged.archived_as = Some(move_aside(source, "migrated").await?);
+    Ok(merged)
+}
+
+/// Merges measurement history from stray databases into `repository`, skipping rows it already has,
+/// and archives each stray once merged. Damaged or unreadable strays are reported and left in place.
+pub async fn merge_databases(repository: &Repository, strays: &[PathBuf]) -> Vec<MergedDatabase> {
+    if strays.is_empty() { return Vec::new(); }
+    let mut known: HashSet<(i64, u64)> = match repository.get_speed_measurements_since(Utc.timestamp_opt(0, 0).unwrap()).await {
+        Ok(existing) => existing.iter().map(|m| measurement_key(m.timestamp, m.download_mbps)).collect(),
+        Err(e) => {
+            warn!("Skipping database consolidation: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut merged = Vec::new();
+    for source in strays {
+        match merge_database(repository, source, &mut known).await {
+            Ok(entry) => {
+                info!("Merged {} of {} measurements from {}", entry.imported, entry.measurements_found, source.display());
+                merged.push(entry);
+            }
+            Err(e) => merged.push(MergedDatabase {
+                source: source.clone(),
+                measurements_found: 0,
+                imported: 0,
+                duplicates: 0,
+                moved: false,
+                archived_as: None,
+                error: Some(e.to_string()),
+            }),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::SpeedMeasurement;
+    use chrono::Duration;
+
+    fn temp_path(name: &str, ext: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("speedkarma-consolidate-{}-{}.{}", name, uuid::Uuid::new_v4(), ext))
+    }
+
+    async fn open_repository(path: &Path) -> (Repository, sqlx::SqlitePool) {
+        let pool = open_single(path, true).await.unwrap();
+        MigrationManager::new(sqlite_url(path)).run_migrations(&pool).await.unwrap();
+        (Repository::new(pool.clone()), pool)
+    }
+
+    #[tokio::test]
+    async fn test_stray_history_is_merged_once() {
+        let canonical_path = temp_path("canonical", "db");
+        let stray_path = temp_path("stray", "db");
+        let at = Utc::now() - Duration::days(3);
+        let sample = |hours: i64, mbps: f64| SpeedMeasurement { timestamp: at + Duration::hours(hours), ..SpeedMeasurement::new(mbps, 5.0, 20, false) };
+
+        let (stray, stray_pool) = open_repository(&stray_path).await;
+        for (h, mbps) in [(0, 40.0), (1, 12.0), (2, 38.0)] {
+            stray.save_speed_measurement(&sample(h, mbps)).await.unwrap();
+        }
+        stray_pool.close().await;
+        let (canonical, canonical_pool) = open_repository(&canonical_path).await;
+        canonical.save_speed_measurement(&sample(1, 12.0)).await.unwrap();
+
+        let merged = merge_databases(&canonical, std::slice::from_ref(&stray_path)).await;
+        assert_eq!(merged.len(), 1);
+        assert_eq!((merged[0].measurements_found, merged[0].imported, merged[0].duplicates), (3, 2, 1));
+        assert!(merged[0].error.is_none());
+        assert!(!stray_path.exists());
+        let archived = merged[0].archived_as.clone().unwrap();
+        assert!(archived.exists());
+        assert_eq!(canonical.get_speed_measurements_since(at - Duration::hours(1)).await.unwrap().len(), 3);
+
+        let report = ConsolidationReport::new(canonical_path.clone(), Vec::new(), merged);
+        assert!(report.summary().starts_with("Imported 2 measurements from 1 older database(s) (1 duplicates skipped)"));
+
+        canonical_pool.close().await;
+        for path in [canonical_path, archived] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stray_database_moved_when_canonical_missing() {
+        let canonical_path = temp_path("moved", "db");
+        let stray_path = temp_path("temp-dir", "db");
+        let (stray, stray_pool) = open_repository(&stray_path).await;
+        stray.save_speed_measurement(&SpeedMeasurement::new(33.0, 5.0, 20, false)).await.unwrap();
+        let mut strategy = crate::data::models::OptimizationStrategy::default_strategy();
+        strategy.effectiveness_score = Some(0.7);
+        let strategy_id = stray.save_optimization_strategy(&strategy).await.unwrap();
+        stray_pool.close().await;
+
+        let moved = adopt_database(std::slice::from_ref(&stray_path), &canonical_path).await.unwrap();
+        assert!(moved.moved && moved.error.is_none());
+        assert_eq!(moved.measurements_found, 1);
+        assert!(!stray_path.exists() && canonical_path.exists());
+
+        // Everything came along, not just measurements
+        let (canonical, canonical_pool) = open_repository(&canonical_path).await;
+        assert_eq!(canonical.get_speed_measurements_since(Utc::now() - Duration::hours(1)).await.unwrap().len(), 1);
+        assert_eq!(canonical.get_best_optimization_strategy().await.unwrap().unwrap().id, Some(strategy_id));
+        canonical_pool.close().await;
+
+        // With a canonical database in place nothing is moved
+        assert!(adopt_database(&[temp_path("absent", "db")], &canonical_path).await.is_none());
+        let report = ConsolidationReport::new(canonical_path.clone(), Vec::new(), vec![moved]);
+        assert!(report.summary().starts_with("Moved the older database (1 measurements)"));
+        let _ = std::fs::remove_file(canonical_path);
+    }
+
+    #[tokio::test]
+    async fn test_stray_config_adopted_only_without_canonical() {
+        let target = temp_path("target", "json");
+        let stray = temp_path("stray", "json");
+        let broken = temp_path("broken", "json");
+        let mut cfg = AppConfig::default();
+        cfg.monitoring.measurement_interval = 777;
+        std::fs::write(&stray, serde_json::to_string(&cfg).unwrap()).unwrap();
+        std::fs::write(&broken, "{ not json").unwrap();
+
+        let merged = consolidate_configs(&[broken.clone(), stray.clone()], &target).await;
+        assert!(!merged[0].adopted && merged[0].error.is_some());
+        assert!(merged[1].adopted);
+        let adopted: AppConfig = serde_json::from_str(&std::fs::read_to_string(&target).unwrap()).unwrap();
+        assert_eq!(adopted.monitoring.measurement_interval, 777);
+        // Both strays are archived once a canonical config exists
+        assert!(!stray.exists() && !broken.exists());
+
+        let mut leftovers = vec![target];
+        leftovers.extend(merged.iter().filter_map(|m| m.archived_as.clone()));
+        for path in leftovers {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Points a chart gets when the caller asks for none or for more than any panel can draw
+pub const DEFAULT_POINT_BUDGET: usize = 500;
+pub const MAX_POINT_BUDGET: usize = 4000;
+/// Longest window a chart series covers, the most history the app keeps
+pub const MAX_CHART_DAYS: u32 = 365;
+
+/// Quantity a chart series plots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartMetric {
+    DownloadMbps,
+    UploadMbps,
+    LatencyMs,
+    PacketLossPct,
+}
+
+impl ChartMetric {
+    /// Column of `speed_measurements`
+    pub fn raw_column(&self) -> &'static str {
+        match self {
+            ChartMetric::DownloadMbps => "download_mbps",
+            ChartMetric::UploadMbps => "upload_mbps",
+            ChartMetric::LatencyMs => "latency_ms",
+            ChartMetric::PacketLossPct => "packet_loss_pct",
+        }
+    }
+
+    /// Column of `speed_measurements_hourly`; `None` for metrics compaction does not keep
+    pub fn hourly_column(&self) -> Option<&'static str> {
+        match self {
+            ChartMetric::DownloadMbps => Some("avg_download_mbps"),
+            ChartMetric::UploadMbps => Some("avg_upload_mbps"),
+            ChartMetric::LatencyMs => Some("avg_latency_ms"),
+            ChartMetric::PacketLossPct => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChartPoint {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// A chart-ready series reduced to the requested point budget
+#[derive(Debug, Clone, Serialize)]
+pub struct ChartSeries {
+    pub metric: ChartMetric,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Rows the series was drawn from before downsampling
+    pub source_points: usize,
+    pub points: Vec<ChartPoint>,
+}
+
+impl ChartSeries {
+    /// Downsamples `points` (oldest first) to at most `budget` points
+    pub fn new(metric: ChartMetric, since: DateTime<Utc>, until: DateTime<Utc>, points: Vec<ChartPoint>, budget: usize) -> Self {
+        let source_points = points.len();
+        let budget = if budget == 0 { DEFAULT_POINT_BUDGET } else { budget.min(MAX_POINT_BUDGET) };
+        Self { metric, since, until, source_points, points: lttb(&points, budget) }
+    }
+}
+
+/// Largest-Triangle-Three-Buckets: keeps the first and last point and, per bucket, the point that
+/// spans the largest triangle with its neighbours, so peaks and dips survive where bucket means
+/// would flatten them. Points must be sorted by time.
+pub fn lttb(points: &[ChartPoint], budget: usize) -> Vec<ChartPoint> {
+    if budget >= points.len() {
+        return points.to_vec();
+    }
+    if budget < 3 {
+        // Too few points to bucket; keep the ends
+        return [points[0], points[points.len() - 1]][..budget.max(1)].to_vec();
+    }
+    let x = |p: &ChartPoint| p.timestamp.timestamp_millis() as f64;
+    let bucket_size = (points.len() - 2) as f64 / (budget - 2) as f64;
+    let bucket = |i: usize| {
+        let start = (i as f64 * bucket_size) as usize + 1;
+        let end = (((i + 1) as f64 * bucket_size) as usize + 1).min(points.len() - 1);
+        start..end
+    };
+
+    let mut sampled = Vec::with_capacity(budget);
+    sampled.push(points[0]);
+    let mut anchor = points[0];
+    for i in 0..budget - 2 {
+        // Average of the next bucket stands in for the point still to be chosen
+        let next = if i + 1 < budget - 2 { &points[bucket(i + 1)] } else { &points[points.len() - 1..] };
+        let (avg_x, avg_y) = (
+            next.iter().map(x).sum::<f64>() / next.len() as f64,
+            next.iter().map(|p| p.value).sum::<f64>() / next.len() as f64,
+        );
+        let chosen = points[bucket(i)]
+            .iter()
+            .max_by(|a, b| {
+                let area = |p: &ChartPoint| ((x(&anchor) - avg_x) * (p.value - anchor.value) - (x(&anchor) - x(p)) * (avg_y - anchor.value)).abs();
+                area(a).total_cmp(&area(b))
+            })
+            .copied()
+            .unwrap_or(anchor);
+        sampled.push(chosen);
+        anchor = chosen;
+    }
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn series(values: &[f64]) -> Vec<ChartPoint> {
+        let start = Utc::now() - Duration::days(30);
+        values.iter().enumerate().map(|(i, v)| ChartPoint { timestamp: start + Duration::minutes(i as i64), value: *v }).collect()
+    }
+
+    #[test]
+    fn test_lttb_keeps_shape_within_budget() {
+        // A month of minute samples: a slow daily wave with one short outage and one short spike
+        let mut values: Vec<f64> = (0..43_200).map(|i| 50.0 + 10.0 * (i as f64 / 1440.0 * std::f64::consts::TAU).sin()).collect();
+        values[20_000] = 0.5;
+        values[30_000] = 95.0;
+        let points = series(&values);
+
+        let sampled = lttb(&points, 300);
+        assert_eq!(sampled.len(), 300);
+        assert_eq!((sampled[0], sampled[299]), (points[0], points[43_199]));
+        assert!(sampled.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+        // Extremes a bucket mean would average away are kept
+        assert!(sampled.iter().any(|p| p.value == 0.5));
+        assert!(sampled.iter().any(|p| p.value == 95.0));
+        // The wave's range is still visible
+        let (lo, hi) = sampled.iter().filter(|p| p.value > 1.0 && p.value < 90.0).fold((f64::MAX, f64::MIN), |(lo, hi), p| (lo.min(p.value), hi.max(p.value)));
+        assert!(lo < 40.5 && hi > 59.5, "{} {}", lo, hi);
+
+        // Small inputs pass through untouched; degenerate budgets keep the ends
+        assert_eq!(lttb(&points[..10], 300), points[..10].to_vec());
+        assert_eq!(lttb(&points, 2), vec![points[0], points[43_199]]);
+        assert!(lttb(&[], 50).is_empty());
+
+        let capped = ChartSeries::new(ChartMetric::DownloadMbps, points[0].timestamp, points[43_199].timestamp, points.clone(), 0);
+        assert_eq!((capped.source_points, capped.points.len()), (43_200, DEFAULT_POINT_BUDGET));
+    }
+
+    #[tokio::test]
+    async fn test_chart_series_spans_compacted_and_raw_rows() {
+        use crate::data::integrity::{open_single, sqlite_url};
+        use crate::data::migrations::MigrationManager;
+        use crate::data::models::SpeedMeasurement;
+        use crate::data::repository::Repository;
+
+        let path = std::env::temp_dir().join(format!("speedkarma-chart-{}.db", uuid::Uuid::new_v4()));
+        let pool = open_single(&path, true).await.unwrap();
+        MigrationManager::new(sqlite_url(&path)).run_migrations(&pool).await.unwrap();
+        let repo = Repository::new(pool.clone());
+        let hour = Utc::now() - Duration::days(5);
+        let at = |hours_ago: i64, mbps: f64, optimized: bool| SpeedMeasurement { timestamp: Utc::now() - Duration::hours(hours_ago), ..SpeedMeasurement::new(mbps, 5.0, 30, optimized) };
+        // Three samples in one old hour across both optimization states, then recent raw rows
+        for (mbps, optimized) in [(40.0, true), (40.0, true), (10.0, false)] {
+            repo.save_speed_measurement(&SpeedMeasurement { timestamp: hour, ..SpeedMeasurement::new(mbps, 5.0, 30, optimized) }).await.unwrap();
+        }
+        repo.compact_measurements_older_than(2).await.unwrap();
+        for h in 1..=5 {
+            repo.save_speed_measurement(&at(h, 20.0 + h as f64, true)).await.unwrap();
+        }
+
+        let series = repo.get_chart_series(ChartMetric::DownloadMbps, Utc::now() - Duration::days(7), Utc::now(), 100).await.unwrap();
+        assert_eq!(series.source_points, 6);
+        assert!((series.points[0].value - 30.0).abs() < 1e-9);
+        assert_eq!(series.points[5].value, 21.0);
+        let latency = repo.get_chart_series(ChartMetric::LatencyMs, Utc::now() - Duration::days(7), Utc::now(), 3).await.unwrap();
+        assert_eq!((latency.points.len(), latency.points[2].value), (3, 30.0));
+        // Loss is neither compacted nor recorded here
+        assert!(repo.get_chart_series(ChartMetric::PacketLossPct, Utc::now() - Duration::days(7), Utc::now(), 100).await.unwrap().points.is_empty());
+
+        pool.close().await;
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::core::error::Result;
+use crate::data::models::{MeasurementSource, SpeedMeasurement};
+use crate::data::repository::Repository;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Rows read from SQLite per round trip, so exports of any size stay in bounded memory
+const PAGE_SIZE: u32 = 1000;
+
+const CSV_HEADER: &str = "id,timestamp,download_mbps,upload_mbps,latency_ms,optimization_active,confidence,source,wifi_rssi_dbm,wifi_link_mbps,packet_loss_pct,jitter_ms,via_vpn,via_proxy,network_context,method";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// One row per measurement with a header line, for Excel and pandas
+    Csv,
+    /// A single array of measurement objects
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Which measurements to export or list; unset fields do not filter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeasurementFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub optimization_active: Option<bool>,
+    pub source: Option<MeasurementSource>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub path: PathBuf,
+    pub format: ExportFormat,
+    pub rows: u64,
+}
+
+/// `speedkarma-measurements-<timestamp>.<ext>` in the downloads folder, or the temp dir without one
+pub fn default_export_path(format: ExportFormat, now: DateTime<Utc>) -> PathBuf {
+    let dir = dirs::download_dir().unwrap_or_else(std::env::temp_dir);
+    dir.join(format!("speedkarma-measurements-{}.{}", now.format("%Y%m%d-%H%M%S"), format.extension()))
+}
+
+/// Writes the matching measurements in recording order to `path`, page by page. The file is written
+/// under a temporary name and renamed at the end, so a failed export leaves no half-written file.
+pub async fn export_measurements(repository: &Repository, path: &Path, format: ExportFormat, filter: &MeasurementFilter) -> Result<ExportSummary> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let partial = path.with_extension(format!("{}.partial", format.extension()));
+    let result = write_rows(repository, &partial, format, filter).await;
+    match result {
+        Ok(rows) => {
+            tokio::fs::rename(&partial, path).await?;
+            Ok(ExportSummary { path: path.to_path_buf(), format, rows })
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            Err(e)
+        }
+    }
+}
+
+async fn write_rows(repository: &Repository, path: &Path, format: ExportFormat, filter: &MeasurementFilter) -> Result<u64> {
+    let mut out = BufWriter::new(tokio::fs::File::create(path).await?);
+    out.write_all(match format {
+        ExportFormat::Csv => format!("{}\n", CSV_HEADER),
+        ExportFormat::Json => "[".to_string(),
+    }.as_bytes()).await?;
+
+    let mut rows = 0u64;
+    let mut after_id = 0;
+    loop {
+        let page = repository.get_speed_measurements_page(filter, after_id, PAGE_SIZE).await?;
+        for m in &page {
+            let line = match format {
+                ExportFormat::Csv => format!("{}\n", csv_row(m)),
+                ExportFormat::Json => format!("{}\n  {}", if rows == 0 { "" } else { "," }, serde_json::to_string(m)?),
+            };
+            out.write_all(line.as_bytes()).await?;
+            rows += 1;
+        }
+        match page.last().and_then(|m| m.id) {
+            Some(id) if page.len() == PAGE_SIZE as usize => after_id = id,
+            _ => break,
+        }
+    }
+
+    if format == ExportFormat::Json {
+        out.write_all(if rows == 0 { b"]\n" as &[u8] } else { b"\n]\n" }).await?;
+    }
+    out.flush().await?;
+    Ok(rows)
+}
+
+fn csv_row(m: &SpeedMeasurement) -> String {
+    let opt = |v: Option<String>| v.unwrap_or_default();
+    [
+        opt(m.id.map(|v| v.to_string())),
+        m.timestamp.to_rfc3339(),
+        m.download_mbps.to_string(),
+        if m.upload_measured { m.upload_mbps.to_string() } else { String::new() },
+        opt(m.latency_ms.map(|v| v.to_string())),
+        m.optimization_active.to_string(),
+        m.confidence.to_string(),
+        m.source.as_str().to_string(),
+        opt(m.wifi_rssi_dbm.map(|v| v.to_string())),
+        opt(m.wifi_link_mbps.map(|v| v.to_string())),
+        opt(m.packet_loss_pct.map(|v| v.to_string())),
+        opt(m.jitter_ms.map(|v| v.to_string())),
+        m.via_vpn.to_string(),
+        m.via_proxy.to_string(),
+        opt(m.network_context.clone()),
+        m.method.as_str().to_string(),
+    ]
+    .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::integrity::{open_single, sqlite_url};
+    use crate::data::migrations::MigrationManager;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_export_filters_and_formats() {
+        let dir = std::env::temp_dir().join(format!("speedkarma-export-{}", uuid::Uuid::new_v4()));
+        let db = dir.join("speedkarma.db");
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let pool = open_single(&db, true).await.unwrap();
+        MigrationManager::new(sqlite_url(&db)).run_migrations(&pool).await.unwrap();
+        let repo = Repository::new(pool.clone());
+        // More rows than one page, alternating optimization, one per minute
+        let start = Utc::now() - Duration::days(2);
+        for i in 0..(PAGE_SIZE as i64 * 2 + 10) {
+            let m = SpeedMeasurement { timestamp: start + Duration::minutes(i), wifi_rssi_dbm: (i == 0).then_some(-60), ..SpeedMeasurement::new(10.0 + i as f64, 2.0, 25, i % 2 == 0) };
+            repo.save_speed_measurement(&m).await.unwrap();
+        }
+
+        let csv = dir.join("all.csv");
+        let summary = export_measurements(&repo, &csv, ExportFormat::Csv, &MeasurementFilter::default()).await.unwrap();
+        assert_eq!(summary.rows, PAGE_SIZE as u64 * 2 + 10);
+        let text = tokio::fs::read_to_string(&csv).await.unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines.len(), summary.rows as usize + 1);
+        assert!(lines[1].ends_with(",true,1,passive,-60,,,,false,,interface"), "{}", lines[1]);
+        assert!(lines.iter().skip(1).all(|l| l.split(',').count() == CSV_HEADER.split(',').count()));
+
+        let filter = MeasurementFilter { since: Some(start + Duration::minutes(100)), until: Some(start + Duration::minutes(109)), optimization_active: Some(false), source: None };
+        let json = dir.join("baseline.json");
+        let summary = export_measurements(&repo, &json, ExportFormat::Json, &filter).await.unwrap();
+        let parsed: Vec<SpeedMeasurement> = serde_json::from_str(&tokio::fs::read_to_string(&json).await.unwrap()).unwrap();
+        assert_eq!((summary.rows, parsed.len()), (5, 5));
+        assert!(parsed.iter().all(|m| !m.optimization_active));
+        assert!(parsed.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+
+        let none = MeasurementFilter { since: Some(Utc::now()), ..filter };
+        export_measurements(&repo, &json, ExportFormat::Json, &none).await.unwrap();
+        assert!(serde_json::from_str::<Vec<SpeedMeasurement>>(&tokio::fs::read_to_string(&json).await.unwrap()).unwrap().is_empty());
+        assert!(!dir.join("baseline.json.partial").exists());
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::data::models::HourlyAggregate;
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Paired samples at which a bucket's confidence reaches half of its measurement confidence
+const HALF_CONFIDENCE_SAMPLES: f64 = 20.0;
+
+/// Longest period the trend may cover
+pub const MAX_HISTORY_DAYS: u32 = 365;
+
+/// Width of one point of the improvement trend (UTC boundaries)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryBucket {
+    Hour,
+    Day,
+    /// Weeks start on Monday
+    Week,
+}
+
+impl HistoryBucket {
+    pub fn start(&self, t: DateTime<Utc>) -> DateTime<Utc> {
+        let hour = t.duration_trunc(Duration::hours(1)).unwrap_or(t);
+        match self {
+            HistoryBucket::Hour => hour,
+            HistoryBucket::Day => hour - Duration::hours(hour.hour() as i64),
+            HistoryBucket::Week => hour - Duration::hours(hour.hour() as i64) - Duration::days(hour.weekday().num_days_from_monday() as i64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImprovementPoint {
+    pub bucket_start: DateTime<Utc>,
+    /// Optimized over baseline download speed; `None` when no hour of day in the bucket has both
+    pub improvement_factor: Option<f64>,
+    /// 0–1; grows with paired samples and is scaled by the measurements' own confidence
+    pub confidence: f64,
+    pub optimized_samples: i64,
+    pub baseline_samples: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImprovementHistory {
+    pub bucket: HistoryBucket,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Oldest first; buckets without any samples are left out
+    pub points: Vec<ImprovementPoint>,
+}
+
+#[derive(Default)]
+struct Side {
+    samples: i64,
+    download_sum: f64,
+    confidence_sum: f64,
+}
+
+impl Side {
+    fn add(&mut self, a: &HourlyAggregate) {
+        self.samples += a.sample_count;
+        self.download_sum += a.avg_download_mbps * a.sample_count as f64;
+        self.confidence_sum += a.avg_confidence * a.sample_count as f64;
+    }
+
+    fn mean_download(&self) -> f64 { self.download_sum / self.samples as f64 }
+}
+
+/// Improvement per bucket from hourly aggregates. Optimized and baseline speeds are only compared
+/// within the same hour of day, so a bucket that happens to hold optimized evenings and baseline
+/// mornings does not credit (or blame) optimization for the ISP's own daily cycle.
+pub fn improvement_history(aggregates: &[HourlyAggregate], bucket: HistoryBucket, since: DateTime<Utc>, until: DateTime<Utc>) -> ImprovementHistory {
+    // bucket start -> hour of day -> (optimized, baseline)
+    let mut buckets: BTreeMap<DateTime<Utc>, BTreeMap<u32, (Side, Side)>> = BTreeMap::new();
+    for a in aggregates.iter().filter(|a| a.sample_count > 0 && a.hour_start >= since && a.hour_start <= until) {
+        let sides = buckets.entry(bucket.start(a.hour_start)).or_default().entry(a.hour_start.hour()).or_default();
+        if a.optimization_active { sides.0.add(a) } else { sides.1.add(a) }
+    }
+
+    let points = buckets.into_iter().map(|(bucket_start, hours)| {
+        let (mut weight, mut weighted_factor, mut confidence_sum, mut confidence_samples) = (0.0, 0.0, 0.0, 0);
+        for (optimized, baseline) in hours.values() {
+            if optimized.samples == 0 || baseline.samples == 0 || baseline.mean_download() <= 0.0 {
+                continue;
+            }
+            let w = optimized.samples.min(baseline.samples) as f64;
+            weight += w;
+            weighted_factor += w * optimized.mean_download() / baseline.mean_download();
+            confidence_sum += optimized.confidence_sum + baseline.confidence_sum;
+            confidence_samples += optimized.samples + baseline.samples;
+        }
+        let (improvement_factor, confidence) = if weight > 0.0 {
+            (Some(weighted_factor / weight), weight / (weight + HALF_CONFIDENCE_SAMPLES) * confidence_sum / confidence_samples as f64)
+        } else {
+            (None, 0.0)
+        };
+        ImprovementPoint {
+            bucket_start,
+            improvement_factor,
+            confidence,
+            optimized_samples: hours.values().map(|s| s.0.samples).sum(),
+            baseline_samples: hours.values().map(|s| s.1.samples).sum(),
+        }
+    }).collect();
+
+    ImprovementHistory { bucket, since, until, points }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agg(hour_start: DateTime<Utc>, optimization_active: bool, sample_count: i64, avg_download_mbps: f64) -> HourlyAggregate {
+        HourlyAggregate {
+            hour_start,
+            optimization_active,
+            sample_count,
+            avg_download_mbps,
+            min_download_mbps: avg_download_mbps,
+            max_download_mbps: avg_download_mbps,
+            avg_upload_mbps: 5.0,
+            avg_latency_ms: 20.0,
+            avg_confidence: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_improvement_pairs_by_hour_of_day() {
+        // Wednesday 2026-10-14
+        let day = DateTime::parse_from_rfc3339("2026-10-14T00:00:00Z").unwrap().with_timezone(&Utc);
+        let at = |d: i64, h: i64| day + Duration::days(d) + Duration::hours(h);
+        let aggregates = vec![
+            // Day 0: evenings run at 20 baseline and 30 optimized; a fast unpaired morning is ignored
+            agg(at(0, 20), false, 10, 20.0),
+            agg(at(0, 20), true, 10, 30.0),
+            agg(at(0, 9), true, 10, 90.0),
+            // Day 1: optimized only, so no factor can be drawn
+            agg(at(1, 20), true, 4, 30.0),
+        ];
+
+        let history = improvement_history(&aggregates, HistoryBucket::Day, at(-1, 0), at(2, 0));
+        assert_eq!(history.points.len(), 2);
+        let first = &history.points[0];
+        assert_eq!(first.bucket_start, day);
+        assert!((first.improvement_factor.unwrap() - 1.5).abs() < 1e-9);
+        assert!((first.confidence - 10.0 / 30.0).abs() < 1e-9);
+        assert_eq!((first.optimized_samples, first.baseline_samples), (20, 10));
+        assert_eq!((history.points[1].improvement_factor, history.points[1].confidence), (None, 0.0));
+
+        // Both days fall in the week starting Monday 2026-10-12
+        let weekly = improvement_history(&aggregates, HistoryBucket::Week, at(-7, 0), at(7, 0));
+        assert_eq!(weekly.points.len(), 1);
+        assert_eq!(weekly.points[0].bucket_start, at(-2, 0));
+        assert!((weekly.points[0].improvement_factor.unwrap() - 1.5).abs() < 1e-9);
+        assert_eq!(HistoryBucket::Hour.start(at(0, 20) + Duration::minutes(59)), at(0, 20));
+    }
+}
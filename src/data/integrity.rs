@@ -0,0 +1,331 @@
+use crate::core::error::Result;
+use crate::data::migrations::MigrationManager;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Row, SqlitePool};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// Rows copied per statement when a table cannot be copied in one go
+const SALVAGE_BATCH_ROWS: i64 = 500;
+
+/// Problems listed in a report; `integrity_check` can return thousands
+const MAX_REPORTED_PROBLEMS: usize = 20;
+
+/// What startup did about the database file
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum IntegrityOutcome {
+    /// No database yet; it will be created fresh
+    New,
+    Healthy,
+    /// Corruption found; readable rows were moved into a fresh database
+    Repaired { quarantined: PathBuf, tables: Vec<TableSalvage> },
+    /// The corrupt file could not be read at all; started over with an empty database
+    Recreated { quarantined: PathBuf, error: String },
+    /// The check itself failed, e.g. the file could not be moved aside
+    CheckFailed { error: String },
+}
+
+/// Rows rescued from one table of a corrupt database
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSalvage {
+    pub table: String,
+    pub rows_recovered: u64,
+    /// Batches that could not be read and were dropped
+    pub failed_batches: u32,
+}
+
+/// Result of the startup integrity check, kept for diagnostics
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub checked_at: DateTime<Utc>,
+    pub database: PathBuf,
+    /// Messages from `PRAGMA integrity_check`, empty when healthy
+    pub problems: Vec<String>,
+    pub outcome: IntegrityOutcome,
+}
+
+impl IntegrityReport {
+    pub fn needs_attention(&self) -> bool {
+        !matches!(self.outcome, IntegrityOutcome::New | IntegrityOutcome::Healthy)
+    }
+
+    /// One line for logs and notifications
+    pub fn summary(&self) -> String {
+        match &self.outcome {
+            IntegrityOutcome::New => "Created a new database".to_string(),
+            IntegrityOutcome::Healthy => "Database integrity check passed".to_string(),
+            IntegrityOutcome::Repaired { tables, .. } => {
+                let rows: u64 = tables.iter().map(|t| t.rows_recovered).sum();
+                let lost = tables.iter().filter(|t| t.failed_batches > 0).count();
+                if lost == 0 {
+                    format!("Database was damaged and has been repaired ({} rows recovered)", rows)
+                } else {
+                    format!("Database was damaged and has been repaired ({} rows recovered, some history in {} tables lost)", rows, lost)
+                }
+            }
+            IntegrityOutcome::Recreated { .. } => "Database was unreadable; history was reset and the old file kept aside".to_string(),
+            IntegrityOutcome::CheckFailed { error } => format!("Database check failed: {}", error),
+        }
+    }
+}
+
+pub(crate) fn sqlite_url(path: &Path) -> String {
+    format!("sqlite://{}", path.display())
+}
+
+/// One connection, so an ATTACH stays visible to every following statement
+pub(crate) async fn open_single(path: &Path, create: bool) -> Result<SqlitePool> {
+    let options = SqliteConnectOptions::from_str(&sqlite_url(path))?.create_if_missing(create);
+    Ok(SqlitePoolOptions::new().max_connections(1).connect_with(options).await?)
+}
+
+/// Runs `PRAGMA integrity_check`; an empty list means the database is sound
+pub async fn integrity_problems(pool: &SqlitePool) -> Result<Vec<String>> {
+    let rows = sqlx::query("PRAGMA integrity_check").fetch_all(pool).await?;
+    let messages: Vec<String> = rows.iter().map(|r| r.get::<String, _>(0)).collect();
+    if messages.len() == 1 && messages[0] == "ok" {
+        return Ok(Vec::new());
+    }
+    Ok(messages)
+}
+
+async fn check_file(path: &Path) -> Vec<String> {
+    let pool = match open_single(path, false).await {
+        Ok(pool) => pool,
+        Err(e) => return vec![e.to_string()],
+    };
+    let problems = integrity_problems(&pool).await.unwrap_or_else(|e| vec![e.to_string()]);
+    pool.close().await;
+    problems
+}
+
+/// Moves the database and its WAL/SHM companions aside, returning the new path of the main file
+async fn quarantine(path: &Path) -> std::io::Result<PathBuf> {
+    move_aside(path, "corrupt").await
+}
+
+/// Renames the database and its WAL/SHM companions to `<path>.<tag>-<timestamp>`
+pub(crate) async fn move_aside(path: &Path, tag: &str) -> std::io::Result<PathBuf> {
+    let stamp = Utc::now().format("%Y%m%d%H%M%S");
+    let target = PathBuf::from(format!("{}.{}-{}", path.display(), tag, stamp));
+    tokio::fs::rename(path, &target).await?;
+    for suffix in ["-wal", "-shm"] {
+        let companion = PathBuf::from(format!("{}{}", path.display(), suffix));
+        if tokio::fs::try_exists(&companion).await.unwrap_or(false) {
+            let _ = tokio::fs::rename(&companion, format!("{}{}", target.display(), suffix)).await;
+        }
+    }
+    Ok(target)
+}
+
+async fn column_names(pool: &SqlitePool, schema: &str, table: &str) -> Result<Vec<String>> {
+    let rows = sqlx::query(&format!("PRAGMA {}.table_info(\"{}\")", schema, table)).fetch_all(pool).await?;
+    Ok(rows.iter().map(|r| r.get::<String, _>("name")).collect())
+}
+
+/// Copies every readable row of `table` from the attached `old` database, whole-table first,
+/// then in rowid batches so one damaged page only costs its own rows
+async fn salvage_table(pool: &SqlitePool, table: &str) -> Result<TableSalvage> {
+    let old_columns = column_names(pool, "old", table).await?;
+    let columns: Vec<String> = column_names(pool, "main", table)
+        .await?
+        .into_iter()
+        .filter(|c| old_columns.contains(c))
+        .map(|c| format!("\"{}\"", c))
+        .collect();
+    let mut salvage = TableSalvage { table: table.to_string(), rows_recovered: 0, failed_batches: 0 };
+    if columns.is_empty() {
+        return Ok(salvage);
+    }
+    let list = columns.join(", ");
+    let copy = format!("INSERT OR IGNORE INTO main.\"{t}\" ({c}) SELECT {c} FROM old.\"{t}\"", t = table, c = list);
+
+    if let Ok(done) = sqlx::query(&copy).execute(pool).await {
+        salvage.rows_recovered = done.rows_affected();
+        return Ok(salvage);
+    }
+
+    let max_rowid: i64 = match sqlx::query(&format!("SELECT MAX(rowid) FROM old.\"{}\"", table)).fetch_one(pool).await {
+        Ok(row) => row.get::<Option<i64>, _>(0).unwrap_or(0),
+        Err(_) => {
+            salvage.failed_batches += 1;
+            return Ok(salvage);
+        }
+    };
+    let mut start = 0;
+    while start <= max_rowid {
+        let batch = format!("{} WHERE rowid BETWEEN ? AND ?", copy);
+        match sqlx::query(&batch).bind(start).bind(start + SALVAGE_BATCH_ROWS - 1).execute(pool).await {
+            Ok(done) => salvage.rows_recovered += done.rows_affected(),
+            Err(_) => salvage.failed_batches += 1,
+        }
+        start += SALVAGE_BATCH_ROWS;
+    }
+    Ok(salvage)
+}
+
+/// Fills the fresh database at `fresh` with whatever can still be read from `corrupt`
+pub async fn salvage_into(fresh: &Path, corrupt: &Path) -> Result<Vec<TableSalvage>> {
+    let pool = open_single(fresh, true).await?;
+    MigrationManager::new(sqlite_url(fresh)).run_migrations(&pool).await?;
+    sqlx::query("ATTACH DATABASE ? AS old").bind(corrupt.display().to_string()).execute(&pool).await?;
+
+    let tables: Vec<String> = sqlx::query(
+        "SELECT name FROM main.sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name != 'schema_migrations'",
+    )
+    .fetch_all(&pool)
+    .await?
+    .iter()
+    .map(|r| r.get("name"))
+    .collect();
+    let old_tables: Vec<String> = sqlx::query("SELECT name FROM old.sqlite_master WHERE type = 'table'")
+        .fetch_all(&pool)
+        .await?
+        .iter()
+        .map(|r| r.get("name"))
+        .collect();
+
+    let mut salvaged = Vec::new();
+    for table in tables.iter().filter(|t| old_tables.contains(t)) {
+        salvaged.push(salvage_table(&pool, table).await?);
+    }
+    sqlx::query("DETACH DATABASE old").execute(&pool).await?;
+    pool.close().await;
+    Ok(salvaged)
+}
+
+/// Startup check: verifies the database at `path` and, when it is corrupt, quarantines the file
+/// and rebuilds a fresh database from the rows that can still be read
+pub async fn check_and_repair(path: &Path) -> IntegrityReport {
+    let report = |problems: Vec<String>, outcome: IntegrityOutcome| IntegrityReport {
+        checked_at: Utc::now(),
+        database: path.to_path_buf(),
+        problems,
+        outcome,
+    };
+    if !tokio::fs::try_exists(path).await.unwrap_or(false) {
+        return report(Vec::new(), IntegrityOutcome::New);
+    }
+
+    let mut problems = check_file(path).await;
+    if problems.is_empty() {
+        return report(problems, IntegrityOutcome::Healthy);
+    }
+    problems.truncate(MAX_REPORTED_PROBLEMS);
+    warn!("Database integrity check failed: {}", problems.join("; "));
+
+    let quarantined = match quarantine(path).await {
+        Ok(p) => p,
+        Err(e) => return report(problems, IntegrityOutcome::CheckFailed { error: format!("could not move the damaged file aside: {}", e) }),
+    };
+    info!("Moved damaged database to {}", quarantined.display());
+
+    match salvage_into(path, &quarantined).await {
+        Ok(tables) => report(problems, IntegrityOutcome::Repaired { quarantined, tables }),
+        Err(e) => {
+            // Nothing readable: start over with an empty database
+            let _ = tokio::fs::remove_file(path).await;
+            report(problems, IntegrityOutcome::Recreated { quarantined, error: e.to_string() })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("speedkarma-integrity-{}-{}.db", name, uuid::Uuid::new_v4()))
+    }
+
+    async fn seeded_db(path: &Path, measurements: u32) {
+        let pool = open_single(path, true).await.unwrap();
+        MigrationManager::new(sqlite_url(path)).run_migrations(&pool).await.unwrap();
+        for i in 0..measurements {
+            sqlx::query("INSERT INTO speed_measurements (timestamp, download_mbps, upload_mbps, latency_ms, optimization_active, confidence) VALUES (?, ?, 5.0, 20, 0, 1.0)")
+                .bind(Utc::now())
+                .bind(i as f64)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_healthy_and_new_databases() {
+        let path = temp_db("healthy");
+        assert!(matches!(check_and_repair(&path).await.outcome, IntegrityOutcome::New));
+        seeded_db(&path, 3).await;
+        let report = check_and_repair(&path).await;
+        assert!(matches!(report.outcome, IntegrityOutcome::Healthy));
+        assert!(!report.needs_attention());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_salvage_copies_readable_rows() {
+        let old = temp_db("old");
+        let fresh = temp_db("fresh");
+        seeded_db(&old, 1200).await;
+
+        let tables = salvage_into(&fresh, &old).await.unwrap();
+        let measurements = tables.iter().find(|t| t.table == "speed_measurements").unwrap();
+        assert_eq!(measurements.rows_recovered, 1200);
+        assert_eq!(measurements.failed_batches, 0);
+
+        let pool = open_single(&fresh, false).await.unwrap();
+        assert!(integrity_problems(&pool).await.unwrap().is_empty());
+        let count: i64 = sqlx::query("SELECT COUNT(*) FROM speed_measurements").fetch_one(&pool).await.unwrap().get(0);
+        assert_eq!(count, 1200);
+        pool.close().await;
+        let _ = std::fs::remove_file(&old);
+        let _ = std::fs::remove_file(&fresh);
+    }
+
+    #[tokio::test]
+    async fn test_damaged_page_is_repaired() {
+        let path = temp_db("damaged");
+        seeded_db(&path, 5000).await;
+        // Overwrite one page in the middle of the measurements table: the first table leaf
+        // (b-tree page type 0x0D) past the halfway mark, so index pages added by later migrations are skipped
+        let mut bytes = std::fs::read(&path).unwrap();
+        let page = 4096;
+        let mid = (bytes.len() / page / 2..bytes.len() / page)
+            .map(|n| n * page)
+            .find(|&offset| bytes[offset] == 0x0D)
+            .unwrap();
+        bytes[mid..mid + page].fill(0x5A);
+        std::fs::write(&path, bytes).unwrap();
+
+        let report = check_and_repair(&path).await;
+        let IntegrityOutcome::Repaired { quarantined, tables } = &report.outcome else { panic!("unexpected outcome {:?}", report.outcome) };
+        let measurements = tables.iter().find(|t| t.table == "speed_measurements").unwrap();
+        assert!(measurements.rows_recovered > 4000 && measurements.rows_recovered < 5000);
+        assert!(measurements.failed_batches > 0);
+        assert!(matches!(check_and_repair(&path).await.outcome, IntegrityOutcome::Healthy));
+        let _ = std::fs::remove_file(quarantined);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_file_is_quarantined() {
+        let path = temp_db("garbage");
+        std::fs::write(&path, vec![0xAB; 8192]).unwrap();
+
+        let report = check_and_repair(&path).await;
+        assert!(!report.problems.is_empty());
+        let quarantined = match &report.outcome {
+            IntegrityOutcome::Recreated { quarantined, .. } | IntegrityOutcome::Repaired { quarantined, .. } => quarantined.clone(),
+            other => panic!("unexpected outcome {:?}", other),
+        };
+        assert!(quarantined.exists());
+        assert!(report.needs_attention());
+        let _ = std::fs::remove_file(&quarantined);
+        let _ = std::fs::remove_file(&path);
+    }
+}
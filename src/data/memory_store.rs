@@ -0,0 +1,278 @@
+use crate::core::error::Result;
+use crate::data::models::*;
+use crate::data::stores::{DecisionStore, FeedbackStore, MeasurementStore, PatternStore, ServerStore, StrategyStore};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+
+/// Lightweight in-memory implementation of every store, for tests and previews
+#[derive(Default)]
+pub struct InMemoryStore {
+    measurements: Mutex<Vec<SpeedMeasurement>>,
+    profiles: Mutex<Vec<ISPProfile>>,
+    patterns: Mutex<Vec<ThrottlingPattern>>,
+    strategies: Mutex<Vec<OptimizationStrategy>>,
+    active_strategy: Mutex<Option<i64>>,
+    servers: Mutex<Vec<SpeedtestServer>>,
+    feedback: Mutex<Vec<SatisfactionFeedback>>,
+    trials: Mutex<Vec<OptimizationTrial>>,
+    decisions: Mutex<Vec<StrategyDecision>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self { Self::default() }
+}
+
+fn next_id(len: usize) -> i64 { len as i64 + 1 }
+
+#[async_trait]
+impl MeasurementStore for InMemoryStore {
+    async fn save_speed_measurement(&self, measurement: &SpeedMeasurement) -> Result<i64> {
+        let mut rows = self.measurements.lock().unwrap();
+        let id = next_id(rows.len());
+        rows.push(SpeedMeasurement { id: Some(id), ..measurement.clone() });
+        Ok(id)
+    }
+
+    async fn get_speed_measurements_since(&self, since: DateTime<Utc>) -> Result<Vec<SpeedMeasurement>> {
+        let mut rows: Vec<_> = self.measurements.lock().unwrap().iter().filter(|m| m.timestamp >= since).cloned().collect();
+        rows.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(rows)
+    }
+
+    async fn cleanup_old_data(&self, days_to_keep: u32) -> Result<()> {
+        let cutoff = Utc::now() - chrono::Duration::days(days_to_keep as i64);
+        self.measurements.lock().unwrap().retain(|m| m.timestamp >= cutoff);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PatternStore for InMemoryStore {
+    async fn save_isp_profile(&self, profile: &ISPProfile) -> Result<i64> {
+        let mut rows = self.profiles.lock().unwrap();
+        let id = next_id(rows.len());
+        rows.push(ISPProfile { id: Some(id), ..profile.clone() });
+        Ok(id)
+    }
+
+    async fn get_current_isp_profile(&self) -> Result<Option<ISPProfile>> {
+        Ok(self.profiles.lock().unwrap().iter().max_by_key(|p| p.updated_at).cloned())
+    }
+
+    async fn save_throttling_pattern(&self, pattern: &ThrottlingPattern) -> Result<i64> {
+        let mut rows = self.patterns.lock().unwrap();
+        let id = next_id(rows.len());
+        rows.push(ThrottlingPattern { id: Some(id), ..pattern.clone() });
+        Ok(id)
+    }
+
+    async fn get_throttling_patterns_for_isp(&self, isp_profile_id: i64) -> Result<Vec<ThrottlingPattern>> {
+        let mut rows: Vec<_> = self.patterns.lock().unwrap().iter().filter(|p| p.isp_profile_id == isp_profile_id).cloned().collect();
+        rows.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl StrategyStore for InMemoryStore {
+    async fn save_optimization_strategy(&self, strategy: &OptimizationStrategy) -> Result<i64> {
+        let mut rows = self.strategies.lock().unwrap();
+        let id = next_id(rows.len());
+        rows.push(OptimizationStrategy { id: Some(id), ..strategy.clone() });
+        Ok(id)
+    }
+
+    async fn get_best_optimization_strategy(&self) -> Result<Option<OptimizationStrategy>> {
+        let active = *self.active_strategy.lock().unwrap();
+        let strategies = self.strategies.lock().unwrap();
+        if let Some(strategy) = active.and_then(|id| strategies.iter().find(|s| s.id == Some(id))) {
+            return Ok(Some(strategy.clone()));
+        }
+        Ok(strategies
+            .iter()
+            .filter(|s| s.effectiveness_score.is_some())
+            .max_by(|a, b| a.effectiveness_score.partial_cmp(&b.effectiveness_score).unwrap_or(std::cmp::Ordering::Equal))
+            .cloned())
+    }
+
+    async fn update_strategy_effectiveness(&self, id: i64, score: Option<f64>) -> Result<()> {
+        if let Some(strategy) = self.strategies.lock().unwrap().iter_mut().find(|s| s.id == Some(id)) {
+            strategy.effectiveness_score = score;
+        }
+        Ok(())
+    }
+
+    async fn set_active_strategy(&self, id: Option<i64>) -> Result<()> {
+        *self.active_strategy.lock().unwrap() = id;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ServerStore for InMemoryStore {
+    async fn save_speedtest_server(&self, server: &SpeedtestServer) -> Result<i64> {
+        let mut rows = self.servers.lock().unwrap();
+        if let Some(existing) = rows.iter_mut().find(|s| s.server_id == server.server_id) {
+            *existing = SpeedtestServer { id: existing.id, ..server.clone() };
+            return Ok(existing.id.unwrap_or_default());
+        }
+        let id = next_id(rows.len());
+        rows.push(SpeedtestServer { id: Some(id), ..server.clone() });
+        Ok(id)
+    }
+
+    async fn merge_speedtest_servers(&self, servers: &[SpeedtestServer]) -> Result<usize> {
+        let mut rows = self.servers.lock().unwrap();
+        for server in servers {
+            match rows.iter_mut().find(|s| s.server_id == server.server_id) {
+                Some(existing) => {
+                    existing.host = server.host.clone();
+                    existing.port = server.port;
+                    existing.name = server.name.clone();
+                    existing.country = server.country.clone();
+                    existing.sponsor = server.sponsor.clone();
+                    existing.latitude = server.latitude.or(existing.latitude);
+                    existing.longitude = server.longitude.or(existing.longitude);
+                    existing.provider = server.provider;
+                    existing.paths = server.paths.clone();
+                    existing.tls = server.tls;
+                }
+                None => {
+                    let id = next_id(rows.len());
+                    rows.push(SpeedtestServer { id: Some(id), ..server.clone() });
+                }
+            }
+        }
+        Ok(servers.len())
+    }
+
+    async fn get_active_speedtest_servers(&self) -> Result<Vec<SpeedtestServer>> {
+        let mut rows: Vec<_> = self.servers.lock().unwrap().iter().filter(|s| s.is_active).cloned().collect();
+        rows.sort_by(|a, b| (!a.is_custom(), &a.country, &a.name).cmp(&(!b.is_custom(), &b.country, &b.name)));
+        Ok(rows)
+    }
+
+    async fn get_servers_by_country(&self, country: &str) -> Result<Vec<SpeedtestServer>> {
+        let mut rows: Vec<_> = self.servers.lock().unwrap().iter().filter(|s| s.is_active && s.country == country).cloned().collect();
+        rows.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(rows)
+    }
+
+    async fn update_server_last_used(&self, server_id: &str) -> Result<()> {
+        if let Some(server) = self.servers.lock().unwrap().iter_mut().find(|s| s.server_id == server_id) {
+            server.last_used = Some(Utc::now());
+            server.use_count += 1;
+        }
+        Ok(())
+    }
+
+    async fn record_server_probe(&self, server_id: &str, latency: Option<f64>, request_failure_pct: f64, probed_at: DateTime<Utc>, is_active: bool) -> Result<()> {
+        if let Some(server) = self.servers.lock().unwrap().iter_mut().find(|s| s.server_id == server_id) {
+            server.latency = latency.or(server.latency);
+            server.request_failure_pct = Some(request_failure_pct);
+            server.probed_at = Some(probed_at);
+            server.is_active = is_active;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FeedbackStore for InMemoryStore {
+    async fn save_satisfaction_feedback(&self, feedback: &SatisfactionFeedback) -> Result<i64> {
+        let mut rows = self.feedback.lock().unwrap();
+        let id = next_id(rows.len());
+        rows.push(SatisfactionFeedback { id: Some(id), ..feedback.clone() });
+        Ok(id)
+    }
+
+    async fn get_satisfaction_feedback_since(&self, since: DateTime<Utc>) -> Result<Vec<SatisfactionFeedback>> {
+        let mut rows: Vec<_> = self.feedback.lock().unwrap().iter().filter(|f| f.timestamp >= since).cloned().collect();
+        rows.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(rows)
+    }
+
+    async fn save_optimization_trial(&self, trial: &OptimizationTrial) -> Result<i64> {
+        let mut rows = self.trials.lock().unwrap();
+        let id = next_id(rows.len());
+        rows.push(OptimizationTrial { id: Some(id), ..trial.clone() });
+        Ok(id)
+    }
+
+    async fn get_optimization_trials_since(&self, since: DateTime<Utc>) -> Result<Vec<OptimizationTrial>> {
+        let mut rows: Vec<_> = self.trials.lock().unwrap().iter().filter(|t| t.started_at >= since).cloned().collect();
+        rows.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl DecisionStore for InMemoryStore {
+    async fn save_strategy_decision(&self, decision: &StrategyDecision) -> Result<i64> {
+        let mut rows = self.decisions.lock().unwrap();
+        let id = next_id(rows.len());
+        rows.push(StrategyDecision { id: Some(id), ..decision.clone() });
+        Ok(id)
+    }
+
+    async fn update_strategy_decision(&self, decision: &StrategyDecision) -> Result<()> {
+        if let Some(row) = self.decisions.lock().unwrap().iter_mut().find(|d| d.id == decision.id) {
+            *row = decision.clone();
+        }
+        Ok(())
+    }
+
+    async fn get_strategy_decisions_since(&self, since: DateTime<Utc>) -> Result<Vec<StrategyDecision>> {
+        let mut rows: Vec<_> = self.decisions.lock().unwrap()
+            .iter()
+            .filter(|d| d.created_at >= since || d.outcome == CanaryOutcome::Pending)
+            .cloned()
+            .collect();
+        rows.sort_by_key(|d| std::cmp::Reverse(d.created_at));
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_measurements_newest_first() {
+        let store = InMemoryStore::new();
+        let mut old = SpeedMeasurement::new(10.0, 2.0, 20, false);
+        old.timestamp = Utc::now() - chrono::Duration::hours(2);
+        store.save_speed_measurement(&old).await.unwrap();
+        store.save_speed_measurement(&SpeedMeasurement::new(20.0, 4.0, 20, false)).await.unwrap();
+
+        let rows = store.get_speed_measurements_since(Utc::now() - chrono::Duration::hours(3)).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].download_mbps, 20.0);
+
+        store.cleanup_old_data(0).await.unwrap();
+        let rows = store.get_speed_measurements_since(Utc::now() - chrono::Duration::hours(3)).await.unwrap();
+        assert!(rows.iter().all(|m| m.download_mbps == 20.0));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_best_strategy() {
+        let store = InMemoryStore::new();
+        let mut low = OptimizationStrategy::default_strategy();
+        low.effectiveness_score = Some(0.3);
+        let mut high = OptimizationStrategy::high_stealth_strategy();
+        high.effectiveness_score = Some(0.9);
+        store.save_optimization_strategy(&low).await.unwrap();
+        store.save_optimization_strategy(&high).await.unwrap();
+
+        let best = store.get_best_optimization_strategy().await.unwrap().unwrap();
+        assert_eq!(best.effectiveness_score, Some(0.9));
+
+        // An activated strategy wins until the activation is cleared
+        let unscored = store.save_optimization_strategy(&OptimizationStrategy::default_strategy()).await.unwrap();
+        store.set_active_strategy(Some(unscored)).await.unwrap();
+        assert_eq!(store.get_best_optimization_strategy().await.unwrap().unwrap().id, Some(unscored));
+        store.set_active_strategy(None).await.unwrap();
+        assert_eq!(store.get_best_optimization_strategy().await.unwrap().unwrap().effectiveness_score, Some(0.9));
+    }
+}
@@ -1,6 +1,10 @@
+use crate::core::config::StorageConfig;
 use crate::core::error::Result;
 use sqlx::{SqlitePool, migrate::MigrateDatabase, Row};
+use sqlx::sqlite::{SqliteAutoVacuum, SqliteConnectOptions, SqlitePoolOptions};
 use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::str::FromStr;
 
 /// Database migration management with versioning
 pub struct MigrationManager {
@@ -16,6 +20,15 @@ pub struct Migration {
     pub applied_at: Option<DateTime<Utc>>,
 }
 
+/// A table, index or trigger as SQLite stores it after migrations
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaObject {
+    pub kind: String,
+    pub name: String,
+    pub table: String,
+    pub sql: String,
+}
+
 impl MigrationManager {
     pub fn new(database_url: String) -> Self {
         Self { database_url }
@@ -29,6 +42,46 @@ impl MigrationManager {
         Ok(())
     }
     
+    /// Opens a connection pool sized for the configured storage footprint
+    pub async fn connect(&self, storage: &StorageConfig) -> Result<SqlitePool> {
+        let mut options = SqliteConnectOptions::from_str(&self.database_url)?;
+        let mut pool_options = SqlitePoolOptions::new();
+        if storage.tiny_footprint {
+            // auto_vacuum only takes effect on a database without tables, i.e. a fresh install
+            options = options
+                .auto_vacuum(SqliteAutoVacuum::Incremental)
+                .pragma("cache_size", "-256")
+                .pragma("temp_store", "memory");
+            pool_options = pool_options.max_connections(2);
+        }
+        Ok(pool_options.connect_with(options).await?)
+    }
+
+    /// Version and name of every migration this build knows about
+    pub fn migration_names(&self) -> Vec<(i32, String)> {
+        self.get_all_migrations().into_iter().map(|m| (m.version, m.name)).collect()
+    }
+
+    /// Schema objects in the database, tables first
+    pub async fn schema_objects(&self, pool: &SqlitePool) -> Result<Vec<SchemaObject>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT type, name, tbl_name, sql FROM sqlite_master
+            WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+            ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 ELSE 2 END, tbl_name, name
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| SchemaObject {
+            kind: row.get("type"),
+            name: row.get("name"),
+            table: row.get("tbl_name"),
+            sql: row.get("sql"),
+        }).collect())
+    }
+
     /// Runs all pending migrations
     pub async fn run_migrations(&self, pool: &SqlitePool) -> Result<()> {
         // First, create the migrations table to track applied migrations
@@ -122,6 +175,186 @@ impl MigrationManager {
                 sql: self.get_performance_indexes_sql(),
                 applied_at: None,
             },
+            Migration {
+                version: 8,
+                name: "create_speed_measurements_hourly_table".to_string(),
+                sql: self.get_speed_measurements_hourly_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 9,
+                name: "add_speed_measurements_source".to_string(),
+                sql: self.get_speed_measurements_source_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 10,
+                name: "create_speed_alert_episodes_table".to_string(),
+                sql: self.get_speed_alert_episodes_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 11,
+                name: "add_speedtest_server_path_templates".to_string(),
+                sql: self.get_speedtest_server_paths_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 12,
+                name: "create_satisfaction_feedback_table".to_string(),
+                sql: self.get_satisfaction_feedback_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 13,
+                name: "create_model_quality_metrics_table".to_string(),
+                sql: self.get_model_quality_metrics_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 14,
+                name: "add_speedtest_server_location_and_usage".to_string(),
+                sql: self.get_speedtest_server_location_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 15,
+                name: "create_optimization_trials_table".to_string(),
+                sql: self.get_optimization_trials_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 16,
+                name: "add_speed_measurements_wifi_signal".to_string(),
+                sql: self.get_speed_measurements_wifi_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 17,
+                name: "create_route_snapshots_table".to_string(),
+                sql: self.get_route_snapshots_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 18,
+                name: "create_decisions_table".to_string(),
+                sql: self.get_decisions_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 19,
+                name: "add_speed_measurements_loss_jitter".to_string(),
+                sql: self.get_speed_measurements_loss_jitter_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 20,
+                name: "create_bufferbloat_tests_table".to_string(),
+                sql: self.get_bufferbloat_tests_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 21,
+                name: "add_isp_profiles_tampering".to_string(),
+                sql: self.get_isp_profiles_tampering_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 22,
+                name: "add_isp_profiles_cgnat".to_string(),
+                sql: self.get_isp_profiles_cgnat_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 23,
+                name: "create_traffic_usage_table".to_string(),
+                sql: self.get_traffic_usage_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 24,
+                name: "add_optimization_strategies_mimicry_profile".to_string(),
+                sql: self.get_optimization_strategies_mimicry_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 25,
+                name: "add_traffic_transport_columns".to_string(),
+                sql: self.get_traffic_transport_columns_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 26,
+                name: "add_speed_measurements_via_vpn".to_string(),
+                sql: self.get_speed_measurements_via_vpn_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 27,
+                name: "add_speed_measurements_network_context".to_string(),
+                sql: self.get_speed_measurements_network_context_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 28,
+                name: "create_passive_calibrations_table".to_string(),
+                sql: self.get_passive_calibrations_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 29,
+                name: "add_speedtest_servers_probe_columns".to_string(),
+                sql: self.get_speedtest_servers_probe_columns_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 30,
+                name: "add_speedtest_servers_tls_column".to_string(),
+                sql: self.get_speedtest_servers_tls_column_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 31,
+                name: "add_speed_measurements_method".to_string(),
+                sql: self.get_speed_measurements_method_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 32,
+                name: "add_optimization_strategies_active".to_string(),
+                sql: self.get_optimization_strategies_active_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 33,
+                name: "create_speedtest_retries_table".to_string(),
+                sql: self.get_speedtest_retries_table_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 34,
+                name: "add_speed_measurements_upload_measured".to_string(),
+                sql: self.get_speed_measurements_upload_measured_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 35,
+                name: "make_speed_measurements_latency_nullable".to_string(),
+                sql: self.get_speed_measurements_latency_nullable_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 36,
+                name: "add_isp_profiles_candidates".to_string(),
+                sql: self.get_isp_profiles_candidates_sql(),
+                applied_at: None,
+            },
+            Migration {
+                version: 37,
+                name: "add_speed_measurements_via_proxy".to_string(),
+                sql: self.get_speed_measurements_via_proxy_sql(),
+                applied_at: None,
+            },
         ]
     }
 
@@ -281,6 +514,355 @@ impl MigrationManager {
         CREATE INDEX IF NOT EXISTS idx_speedtest_servers_is_active ON speedtest_servers(is_active);
         "#.to_string()
     }
+
+    fn get_speed_measurements_hourly_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS speed_measurements_hourly (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            hour_start DATETIME NOT NULL,
+            optimization_active BOOLEAN NOT NULL,
+            sample_count INTEGER NOT NULL,
+            avg_download_mbps REAL NOT NULL,
+            min_download_mbps REAL NOT NULL,
+            max_download_mbps REAL NOT NULL,
+            avg_upload_mbps REAL NOT NULL,
+            avg_latency_ms REAL NOT NULL,
+            avg_confidence REAL NOT NULL,
+            UNIQUE (hour_start, optimization_active)
+        );
+        CREATE INDEX IF NOT EXISTS idx_speed_measurements_hourly_hour_start ON speed_measurements_hourly(hour_start);
+        "#.to_string()
+    }
+
+    /// Records whether a measurement was estimated passively or taken by an active test
+    fn get_speed_measurements_source_sql(&self) -> String {
+        r#"
+        ALTER TABLE speed_measurements ADD COLUMN source TEXT NOT NULL DEFAULT 'passive';
+        "#.to_string()
+    }
+
+    /// Wi-Fi signal at measurement time, so local wireless trouble is not blamed on the ISP
+    fn get_speed_measurements_wifi_sql(&self) -> String {
+        r#"
+        ALTER TABLE speed_measurements ADD COLUMN wifi_rssi_dbm INTEGER;
+        ALTER TABLE speed_measurements ADD COLUMN wifi_link_mbps REAL;
+        "#.to_string()
+    }
+
+    /// Probe handshake loss and jitter, since some ISPs throttle by dropping packets rather than capping bandwidth
+    fn get_speed_measurements_loss_jitter_sql(&self) -> String {
+        r#"
+        ALTER TABLE speed_measurements ADD COLUMN packet_loss_pct REAL;
+        ALTER TABLE speed_measurements ADD COLUMN jitter_ms REAL;
+        "#.to_string()
+    }
+
+    fn get_speed_alert_episodes_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS speed_alert_episodes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at DATETIME NOT NULL,
+            ended_at DATETIME,
+            threshold_mbps REAL NOT NULL,
+            sustained_minutes INTEGER NOT NULL,
+            min_download_mbps REAL NOT NULL,
+            avg_download_mbps REAL NOT NULL,
+            sample_count INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_speed_alert_episodes_started_at ON speed_alert_episodes(started_at);
+        "#.to_string()
+    }
+
+    fn get_speedtest_server_paths_sql(&self) -> String {
+        r#"
+        ALTER TABLE speedtest_servers ADD COLUMN provider TEXT NOT NULL DEFAULT 'ookla';
+        ALTER TABLE speedtest_servers ADD COLUMN latency_path TEXT;
+        ALTER TABLE speedtest_servers ADD COLUMN download_path TEXT;
+        ALTER TABLE speedtest_servers ADD COLUMN upload_path TEXT;
+        "#.to_string()
+    }
+
+    fn get_speedtest_server_location_sql(&self) -> String {
+        r#"
+        ALTER TABLE speedtest_servers ADD COLUMN latitude REAL;
+        ALTER TABLE speedtest_servers ADD COLUMN longitude REAL;
+        ALTER TABLE speedtest_servers ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0;
+        "#.to_string()
+    }
+
+    fn get_satisfaction_feedback_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS satisfaction_feedback (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp DATETIME NOT NULL,
+            satisfied BOOLEAN NOT NULL,
+            optimization_active BOOLEAN NOT NULL,
+            strategy_name TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_satisfaction_feedback_timestamp ON satisfaction_feedback(timestamp);
+        "#.to_string()
+    }
+
+    fn get_optimization_trials_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS optimization_trials (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at DATETIME NOT NULL,
+            ended_at DATETIME NOT NULL,
+            strategy_name TEXT,
+            before_mbps REAL,
+            during_mbps REAL,
+            after_mbps REAL,
+            status TEXT NOT NULL,
+            note TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_optimization_trials_started_at ON optimization_trials(started_at);
+        "#.to_string()
+    }
+
+    /// Hops are stored as JSON; `path_signature` is kept alongside for cheap change queries
+    fn get_route_snapshots_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS route_snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            taken_at DATETIME NOT NULL,
+            anchor TEXT NOT NULL,
+            hops TEXT NOT NULL,
+            path_signature TEXT NOT NULL,
+            isp_name TEXT,
+            isp_asn INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS idx_route_snapshots_taken_at ON route_snapshots(taken_at);
+        "#.to_string()
+    }
+
+    /// DNS and HTTP interference findings as JSON
+    fn get_isp_profiles_tampering_sql(&self) -> String {
+        r#"
+        ALTER TABLE isp_profiles ADD COLUMN tampering TEXT;
+        "#.to_string()
+    }
+
+    /// Carrier-grade NAT findings as JSON
+    fn get_isp_profiles_cgnat_sql(&self) -> String {
+        r#"
+        ALTER TABLE isp_profiles ADD COLUMN cgnat TEXT;
+        "#.to_string()
+    }
+
+    /// Per-method detection results behind each profile, for manual override
+    fn get_isp_profiles_candidates_sql(&self) -> String {
+        r#"
+        ALTER TABLE isp_profiles ADD COLUMN candidates TEXT;
+        "#.to_string()
+    }
+
+    /// Speed test the stealth engine imitates per strategy; existing strategies keep speedtest.net
+    fn get_optimization_strategies_mimicry_sql(&self) -> String {
+        r#"
+        ALTER TABLE optimization_strategies ADD COLUMN mimicry_profile TEXT NOT NULL DEFAULT 'speedtest';
+        "#.to_string()
+    }
+
+    /// Transport per strategy (existing ones stay on TCP) and per trial, for comparing them
+    fn get_traffic_transport_columns_sql(&self) -> String {
+        r#"
+        ALTER TABLE optimization_strategies ADD COLUMN transport TEXT NOT NULL DEFAULT 'tcp';
+        ALTER TABLE optimization_trials ADD COLUMN transport TEXT;
+        "#.to_string()
+    }
+
+    /// Samples taken through a VPN, which measure the tunnel rather than the ISP
+    fn get_speed_measurements_via_vpn_sql(&self) -> String {
+        r#"
+        ALTER TABLE speed_measurements ADD COLUMN via_vpn BOOLEAN NOT NULL DEFAULT 0;
+        "#.to_string()
+    }
+
+    /// Samples whose traffic went through the configured proxy, which measure the proxy's path too
+    fn get_speed_measurements_via_proxy_sql(&self) -> String {
+        r#"
+        ALTER TABLE speed_measurements ADD COLUMN via_proxy BOOLEAN NOT NULL DEFAULT 0;
+        "#.to_string()
+    }
+
+    /// Network each sample was taken on, so different networks never share a baseline
+    fn get_speed_measurements_network_context_sql(&self) -> String {
+        r#"
+        ALTER TABLE speed_measurements ADD COLUMN network_context TEXT;
+        CREATE INDEX IF NOT EXISTS idx_speed_measurements_network_context ON speed_measurements(network_context);
+        "#.to_string()
+    }
+
+    /// Latest speed-test correction of passive estimates per network
+    fn get_passive_calibrations_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS passive_calibrations (
+            network_context TEXT PRIMARY KEY,
+            calibrated_at DATETIME NOT NULL,
+            factor REAL NOT NULL,
+            confidence_scale REAL NOT NULL,
+            calibrations INTEGER NOT NULL,
+            last_active_mbps REAL NOT NULL,
+            last_passive_mbps REAL NOT NULL
+        );
+        "#.to_string()
+    }
+
+    /// Share of failed probe requests and time of the last latency probe round per server
+    fn get_speedtest_servers_probe_columns_sql(&self) -> String {
+        r#"
+        ALTER TABLE speedtest_servers ADD COLUMN request_failure_pct REAL;
+        ALTER TABLE speedtest_servers ADD COLUMN probed_at DATETIME;
+        "#.to_string()
+    }
+
+    /// Forced scheme for user-defined servers; NULL lets the caller pick
+    fn get_speedtest_servers_tls_column_sql(&self) -> String {
+        r#"
+        ALTER TABLE speedtest_servers ADD COLUMN tls INTEGER;
+        "#.to_string()
+    }
+
+    /// Tool behind each measurement; earlier active tests were all HTTP
+    fn get_speed_measurements_method_sql(&self) -> String {
+        r#"
+        ALTER TABLE speed_measurements ADD COLUMN method TEXT NOT NULL DEFAULT 'interface';
+        UPDATE speed_measurements SET method = 'http' WHERE source = 'active';
+        "#.to_string()
+    }
+
+    /// Strategy activated explicitly, which stays active whatever the scores say
+    fn get_optimization_strategies_active_sql(&self) -> String {
+        r#"
+        ALTER TABLE optimization_strategies ADD COLUMN is_active INTEGER NOT NULL DEFAULT 0;
+        "#.to_string()
+    }
+
+    /// Failed active speed tests waiting for their retry slot
+    fn get_speedtest_retries_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS speedtest_retries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            due DATETIME NOT NULL,
+            attempt INTEGER NOT NULL,
+            reason TEXT NOT NULL
+        );
+        "#.to_string()
+    }
+
+    /// Download-only tests store a placeholder upload that averages must skip
+    fn get_speed_measurements_upload_measured_sql(&self) -> String {
+        r#"
+        ALTER TABLE speed_measurements ADD COLUMN upload_measured INTEGER NOT NULL DEFAULT 1;
+        "#.to_string()
+    }
+
+    /// SQLite cannot drop NOT NULL in place, so the table is rebuilt; the 0 that used to stand for
+    /// an unmeasured latency becomes NULL
+    fn get_speed_measurements_latency_nullable_sql(&self) -> String {
+        r#"
+        CREATE TABLE speed_measurements_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp DATETIME NOT NULL,
+            download_mbps REAL NOT NULL,
+            upload_mbps REAL NOT NULL,
+            latency_ms INTEGER,
+            optimization_active BOOLEAN NOT NULL,
+            confidence REAL NOT NULL DEFAULT 1.0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            source TEXT NOT NULL DEFAULT 'passive',
+            wifi_rssi_dbm INTEGER,
+            wifi_link_mbps REAL,
+            packet_loss_pct REAL,
+            jitter_ms REAL,
+            via_vpn BOOLEAN NOT NULL DEFAULT 0,
+            network_context TEXT,
+            method TEXT NOT NULL DEFAULT 'interface',
+            upload_measured INTEGER NOT NULL DEFAULT 1
+        );
+        INSERT INTO speed_measurements_new (
+            id, timestamp, download_mbps, upload_mbps, latency_ms, optimization_active, confidence, created_at, source,
+            wifi_rssi_dbm, wifi_link_mbps, packet_loss_pct, jitter_ms, via_vpn, network_context, method, upload_measured
+        )
+        SELECT
+            id, timestamp, download_mbps, upload_mbps, NULLIF(latency_ms, 0), optimization_active, confidence, created_at, source,
+            wifi_rssi_dbm, wifi_link_mbps, packet_loss_pct, jitter_ms, via_vpn, network_context, method, upload_measured
+        FROM speed_measurements;
+        DROP TABLE speed_measurements;
+        ALTER TABLE speed_measurements_new RENAME TO speed_measurements;
+        CREATE INDEX IF NOT EXISTS idx_speed_measurements_timestamp ON speed_measurements(timestamp);
+        CREATE INDEX IF NOT EXISTS idx_speed_measurements_optimization_active ON speed_measurements(optimization_active);
+        CREATE INDEX IF NOT EXISTS idx_speed_measurements_network_context ON speed_measurements(network_context);
+        "#.to_string()
+    }
+
+    /// Idle vs loaded latency, to tell congestion from deliberate throttling
+    fn get_bufferbloat_tests_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS bufferbloat_tests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tested_at DATETIME NOT NULL,
+            idle_latency_ms REAL NOT NULL,
+            loaded_latency_ms REAL NOT NULL,
+            load_mbps REAL
+        );
+        CREATE INDEX IF NOT EXISTS idx_bufferbloat_tests_tested_at ON bufferbloat_tests(tested_at);
+        "#.to_string()
+    }
+
+    /// Strategy switches and their canary outcome
+    fn get_decisions_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS decisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            created_at DATETIME NOT NULL,
+            strategy_id INTEGER NOT NULL,
+            strategy_name TEXT NOT NULL,
+            previous_strategy_id INTEGER,
+            previous_strategy_name TEXT,
+            window_ends_at DATETIME NOT NULL,
+            baseline_mbps REAL,
+            canary_mbps REAL,
+            outcome TEXT NOT NULL,
+            resolved_at DATETIME,
+            note TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_decisions_created_at ON decisions(created_at);
+        "#.to_string()
+    }
+
+    /// Bytes generated per local day and module, for the monthly data cap
+    fn get_traffic_usage_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS traffic_usage (
+            day DATE NOT NULL,
+            source TEXT NOT NULL,
+            bytes INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (day, source)
+        );
+        "#.to_string()
+    }
+
+    /// One backtest row per evaluated day
+    fn get_model_quality_metrics_table_sql(&self) -> String {
+        r#"
+        CREATE TABLE IF NOT EXISTS model_quality_metrics (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            evaluated_date DATE NOT NULL UNIQUE,
+            computed_at DATETIME NOT NULL,
+            hours_evaluated INTEGER NOT NULL,
+            true_positives INTEGER NOT NULL,
+            false_positives INTEGER NOT NULL,
+            false_negatives INTEGER NOT NULL,
+            true_negatives INTEGER NOT NULL,
+            precision REAL,
+            recall REAL,
+            f1 REAL,
+            training_samples INTEGER NOT NULL
+        );
+        "#.to_string()
+    }
 }#[cfg
 (test)]
 mod tests {
@@ -311,7 +893,9 @@ mod tests {
             "throttling_patterns",
             "optimization_strategies",
             "speedtest_servers",
-            "app_config"
+            "app_config",
+            "speed_measurements_hourly",
+            "speed_alert_episodes"
         ];
         
         for table in tables {
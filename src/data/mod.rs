@@ -1,7 +1,17 @@
 pub mod models;
 pub mod repository;
 pub mod migrations;
+pub mod stores;
+pub mod memory_store;
+pub mod compaction;
+pub mod integrity;
+pub mod consolidation;
+pub mod downsample;
+pub mod export;
+pub mod improvement;
 
 // Re-export commonly used types
 pub use models::*;
-pub use repository::Repository;
\ No newline at end of file
+pub use repository::Repository;
+pub use stores::{DataStore, FeedbackStore, MeasurementStore, PatternStore, StrategyStore, ServerStore};
+pub use memory_store::InMemoryStore;
\ No newline at end of file
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc, Weekday, Datelike, Timelike};
+use chrono::{DateTime, NaiveDate, Utc, Weekday, Datelike, Timelike};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -19,6 +19,8 @@ pub enum ValidationError {
     InvalidSeverity { value: f64 },
     #[error("Traffic intensity must be between 0.0 and 1.0: {value}")]
     InvalidTrafficIntensity { value: f64 },
+    #[error("Packet timing must be a non-negative, finite number of seconds with the minimum below the maximum: {value}")]
+    InvalidPacketTiming { value: f64 },
     #[error("Connection count must be between 1-10: {value}")]
     InvalidConnectionCount { value: u8 },
     #[error("Name cannot be empty")]
@@ -34,9 +36,428 @@ pub struct SpeedMeasurement {
     pub timestamp: DateTime<Utc>,
     pub download_mbps: f64,
     pub upload_mbps: f64,
-    pub latency_ms: u32,
+    /// `None` when nothing measured it
+    pub latency_ms: Option<u32>,
     pub optimization_active: bool,
     pub confidence: f64,
+    /// Whether the figures were estimated passively or measured by an active test
+    #[serde(default)]
+    pub source: MeasurementSource,
+    /// Signal strength of the active Wi-Fi link; `None` on wired links or when unreadable
+    #[serde(default)]
+    pub wifi_rssi_dbm: Option<i32>,
+    /// Negotiated Wi-Fi link rate
+    #[serde(default)]
+    pub wifi_link_mbps: Option<f64>,
+    /// Share of probe handshakes lost around the measurement; `None` when not probed
+    #[serde(default)]
+    pub packet_loss_pct: Option<f64>,
+    /// Mean variation between consecutive probe handshakes
+    #[serde(default)]
+    pub jitter_ms: Option<f64>,
+    /// Taken while a VPN tunnel carried the traffic; kept out of the ISP model
+    #[serde(default)]
+    pub via_vpn: bool,
+    /// Its traffic went through the configured proxy; kept out of the ISP model like `via_vpn`
+    #[serde(default)]
+    pub via_proxy: bool,
+    /// Key of the network it was taken on (see `network::context`); `None` for older rows
+    #[serde(default)]
+    pub network_context: Option<String>,
+    /// Tool that produced the figures
+    #[serde(default)]
+    pub method: MeasurementMethod,
+    /// False when only the download was tested; `upload_mbps` is then 0 and left out of averages
+    #[serde(default = "upload_measured_default")]
+    pub upload_measured: bool,
+}
+
+fn upload_measured_default() -> bool {
+    true
+}
+
+/// Wi-Fi signal at or below this is weak enough to slow the connection on its own
+pub const WEAK_WIFI_RSSI_DBM: i32 = -70;
+/// Wi-Fi link rate below this caps throughput regardless of the ISP
+pub const WEAK_WIFI_LINK_MBPS: f64 = 20.0;
+/// Packet loss that halves a measurement's performance score; heavier loss keeps lowering it
+/// without ever zeroing a link that still moves data
+pub const HALF_PENALTY_LOSS_PCT: f64 = 10.0;
+
+/// Origin of a speed measurement
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MeasurementSource {
+    /// Estimated from observed interface throughput
+    #[default]
+    Passive,
+    /// Measured by an explicit speed test
+    Active,
+}
+
+impl MeasurementSource {
+    /// Convert to string for database storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MeasurementSource::Passive => "passive",
+            MeasurementSource::Active => "active",
+        }
+    }
+
+    /// Create from string (for database retrieval)
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "active" => MeasurementSource::Active,
+            _ => MeasurementSource::Passive,
+        }
+    }
+}
+
+/// How a speed measurement was taken
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MeasurementMethod {
+    /// Interface byte counters
+    #[default]
+    Interface,
+    /// HTTP transfers against a speed test server, which CDN caches can flatter
+    Http,
+    /// iperf3 client against a server the user runs, e.g. on a VPS
+    Iperf3,
+}
+
+impl MeasurementMethod {
+    /// Convert to string for database storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MeasurementMethod::Interface => "interface",
+            MeasurementMethod::Http => "http",
+            MeasurementMethod::Iperf3 => "iperf3",
+        }
+    }
+
+    /// Create from string (for database retrieval)
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "http" => MeasurementMethod::Http,
+            "iperf3" => MeasurementMethod::Iperf3,
+            _ => MeasurementMethod::Interface,
+        }
+    }
+}
+
+/// Largest page `Repository::get_speed_measurements_paged` returns
+pub const MAX_HISTORY_PAGE_SIZE: u32 = 500;
+
+/// One page of measurement history, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeedMeasurementPage {
+    pub items: Vec<SpeedMeasurement>,
+    /// Zero-based
+    pub page: u32,
+    pub page_size: u32,
+    /// Measurements matching the filter across all pages
+    pub total: u64,
+}
+
+/// Hourly rollup of raw measurements, kept after raw rows are compacted away
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HourlyAggregate {
+    pub hour_start: DateTime<Utc>,
+    pub optimization_active: bool,
+    pub sample_count: i64,
+    pub avg_download_mbps: f64,
+    pub min_download_mbps: f64,
+    pub max_download_mbps: f64,
+    pub avg_upload_mbps: f64,
+    pub avg_latency_ms: f64,
+    pub avg_confidence: f64,
+}
+
+/// A period during which download speed stayed below the alert threshold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeedAlertEpisode {
+    pub id: Option<i64>,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the episode is still ongoing
+    pub ended_at: Option<DateTime<Utc>>,
+    pub threshold_mbps: f64,
+    pub sustained_minutes: u32,
+    pub min_download_mbps: f64,
+    pub avg_download_mbps: f64,
+    pub sample_count: u32,
+}
+
+/// A thumbs-up/down answer to "Is your connection better right now?"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SatisfactionFeedback {
+    pub id: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    pub satisfied: bool,
+    /// Whether optimization was running when the user answered
+    pub optimization_active: bool,
+    /// Strategy in use at the time, if any
+    pub strategy_name: Option<String>,
+}
+
+impl SatisfactionFeedback {
+    pub fn new(satisfied: bool, optimization_active: bool, strategy_name: Option<String>) -> Self {
+        Self { id: None, timestamp: Utc::now(), satisfied, optimization_active, strategy_name }
+    }
+}
+
+/// How a time-boxed optimization trial ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrialStatus {
+    /// Ran its full time and every window had samples
+    Completed,
+    /// Ran its full time but a window had too few samples to compare
+    Inconclusive,
+    /// Stopped early by the user or because speeds fell
+    Aborted,
+}
+
+impl TrialStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrialStatus::Completed => "completed",
+            TrialStatus::Inconclusive => "inconclusive",
+            TrialStatus::Aborted => "aborted",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Self {
+        match value {
+            "completed" => TrialStatus::Completed,
+            "inconclusive" => TrialStatus::Inconclusive,
+            _ => TrialStatus::Aborted,
+        }
+    }
+}
+
+/// One "try optimization now" run: median download before, during and after it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationTrial {
+    pub id: Option<i64>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub strategy_name: Option<String>,
+    pub before_mbps: Option<f64>,
+    pub during_mbps: Option<f64>,
+    pub after_mbps: Option<f64>,
+    pub status: TrialStatus,
+    /// Why the trial was aborted or inconclusive
+    pub note: Option<String>,
+    /// Transport of the strategy that ran, so transports can be compared
+    #[serde(default)]
+    pub transport: Option<TrafficTransport>,
+}
+
+impl OptimizationTrial {
+    /// Download during the trial relative to the unoptimized reference. The reference averages the
+    /// windows before and after, so a connection that drifts over the hour is not credited to the trial.
+    pub fn improvement(&self) -> Option<f64> {
+        if self.status != TrialStatus::Completed { return None; }
+        let during = self.during_mbps?;
+        let reference = match (self.before_mbps, self.after_mbps) {
+            (Some(before), Some(after)) => (before + after) / 2.0,
+            (Some(only), None) | (None, Some(only)) => only,
+            (None, None) => return None,
+        };
+        (reference > 0.0).then(|| during / reference)
+    }
+}
+
+/// Where a strategy switch stands after its canary window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanaryOutcome {
+    /// The new strategy is active and still being measured
+    Pending,
+    /// The canary did not regress; the switch is kept
+    Committed,
+    /// The canary was slower than the prior strategy; the prior one is active again
+    Reverted,
+    /// Too few measurements to compare; the switch is kept
+    Inconclusive,
+}
+
+impl CanaryOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CanaryOutcome::Pending => "pending",
+            CanaryOutcome::Committed => "committed",
+            CanaryOutcome::Reverted => "reverted",
+            CanaryOutcome::Inconclusive => "inconclusive",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Self {
+        match value {
+            "committed" => CanaryOutcome::Committed,
+            "reverted" => CanaryOutcome::Reverted,
+            "inconclusive" => CanaryOutcome::Inconclusive,
+            _ => CanaryOutcome::Pending,
+        }
+    }
+}
+
+/// Two-phase strategy switch: the new strategy runs for a canary window and is only kept
+/// when download does not regress against the window before the switch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyDecision {
+    pub id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub strategy_id: i64,
+    pub strategy_name: String,
+    pub previous_strategy_id: Option<i64>,
+    pub previous_strategy_name: Option<String>,
+    pub window_ends_at: DateTime<Utc>,
+    /// Median download in the window before the switch
+    pub baseline_mbps: Option<f64>,
+    /// Median download during the canary window
+    pub canary_mbps: Option<f64>,
+    pub outcome: CanaryOutcome,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub note: Option<String>,
+}
+
+/// How well the model's predicted throttling hours matched one day of measurements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelQualityMetric {
+    pub id: Option<i64>,
+    /// UTC day whose measurements were scored
+    pub evaluated_date: NaiveDate,
+    pub computed_at: DateTime<Utc>,
+    /// Hours of that day with enough samples to score
+    pub hours_evaluated: u32,
+    pub true_positives: u32,
+    pub false_positives: u32,
+    pub false_negatives: u32,
+    pub true_negatives: u32,
+    /// `None` when no hour was predicted to be throttled
+    pub precision: Option<f64>,
+    /// `None` when no hour was actually throttled
+    pub recall: Option<f64>,
+    pub f1: Option<f64>,
+    /// Measurements the prediction was trained on
+    pub training_samples: u32,
+}
+
+/// Correction for passive estimates on one network, learned by running speed tests next to them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PassiveCalibration {
+    /// `SpeedMeasurement::network_context` the correction applies to
+    pub network_context: String,
+    pub calibrated_at: DateTime<Utc>,
+    /// Speed test download over the passive estimate, smoothed across calibrations
+    pub factor: f64,
+    /// Multiplier on passive confidence; below 1 while calibrations disagree with each other
+    pub confidence_scale: f64,
+    pub calibrations: u32,
+    /// Download speeds seen by the latest calibration
+    pub last_active_mbps: f64,
+    pub last_passive_mbps: f64,
+}
+
+/// A failed active speed test waiting for its retry slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingRetry {
+    pub due: DateTime<Utc>,
+    /// Retry number this run will be (1 for the first retry)
+    pub attempt: u32,
+    /// Why the previous attempt failed
+    pub reason: String,
+}
+
+/// One hop of a traceroute; `address` is `None` when the hop did not answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteHop {
+    pub ttl: u8,
+    pub address: Option<std::net::IpAddr>,
+    pub rtt_ms: Option<f64>,
+    pub asn: Option<u32>,
+    /// AS description from the ASN database
+    pub asn_name: Option<String>,
+}
+
+/// Path to one traceroute anchor at one point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteSnapshot {
+    pub id: Option<i64>,
+    pub taken_at: DateTime<Utc>,
+    pub anchor: String,
+    pub hops: Vec<RouteHop>,
+    /// ISP the first public hops belong to
+    pub isp_name: Option<String>,
+    pub isp_asn: Option<u32>,
+}
+
+impl RouteSnapshot {
+    /// AS-level path (`9506 4755 13335`); two snapshots with different signatures took different routes
+    pub fn path_signature(&self) -> String {
+        let mut path: Vec<u32> = Vec::new();
+        for asn in self.hops.iter().filter_map(|h| h.asn) {
+            if path.last() != Some(&asn) { path.push(asn); }
+        }
+        path.iter().map(u32::to_string).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Module that generated traffic, for data usage accounting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrafficSource {
+    Stealth,
+    Keeper,
+    Speedtest,
+    Disguise,
+}
+
+impl TrafficSource {
+    pub const ALL: [TrafficSource; 4] = [TrafficSource::Stealth, TrafficSource::Keeper, TrafficSource::Speedtest, TrafficSource::Disguise];
+
+    /// Convert to string for database storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrafficSource::Stealth => "stealth",
+            TrafficSource::Keeper => "keeper",
+            TrafficSource::Speedtest => "speedtest",
+            TrafficSource::Disguise => "disguise",
+        }
+    }
+
+    /// `None` for names this build does not know
+    pub fn from_string(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|source| source.as_str() == s)
+    }
+}
+
+/// Bytes one module generated on one local day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrafficUsage {
+    pub day: NaiveDate,
+    pub source: TrafficSource,
+    pub bytes: u64,
+}
+
+/// Handshake latency with the link idle and while the throughput keeper saturates it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferbloatTest {
+    pub id: Option<i64>,
+    pub tested_at: DateTime<Utc>,
+    pub idle_latency_ms: f64,
+    pub loaded_latency_ms: f64,
+    /// Download rate the keeper reached while loading the link
+    pub load_mbps: Option<f64>,
+}
+
+impl BufferbloatTest {
+    /// Latency the load added on top of the idle figure
+    pub fn added_latency_ms(&self) -> f64 {
+        (self.loaded_latency_ms - self.idle_latency_ms).max(0.0)
+    }
 }
 
 /// ISP profile information
@@ -48,6 +469,78 @@ pub struct ISPProfile {
     pub detection_method: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Latest DNS and HTTP interference check; `None` until one ran
+    #[serde(default)]
+    pub tampering: Option<TamperingReport>,
+    /// Latest carrier-grade NAT check; `None` until one ran
+    #[serde(default)]
+    pub cgnat: Option<CgnatReport>,
+    /// Per-method results the profile was chosen from, for manual override
+    #[serde(default)]
+    pub candidates: Vec<IspCandidate>,
+}
+
+/// What one detection method concluded about the ISP
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IspCandidate {
+    pub isp_name: String,
+    pub region: String,
+    pub detection_method: String,
+    pub confidence: f64,
+    #[serde(default)]
+    pub asn: Option<u32>,
+    #[serde(default)]
+    pub organization: Option<String>,
+}
+
+/// ISP interference with DNS answers and plain HTTP traffic
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TamperingReport {
+    pub checked_at: DateTime<Utc>,
+    /// Addresses the resolver returned for names that do not exist
+    pub nxdomain_hijack: Vec<std::net::IpAddr>,
+    /// DNSSEC-signed names the resolver answered differently from the validated answer
+    pub dnssec_mismatch: Vec<String>,
+    /// Request headers that appeared on the way over plain HTTP
+    pub injected_headers: Vec<String>,
+    /// `Via` header on a plain HTTP response, naming a proxy on the path
+    pub via: Option<String>,
+}
+
+impl TamperingReport {
+    pub fn dns_tampered(&self) -> bool {
+        !self.nxdomain_hijack.is_empty() || !self.dnssec_mismatch.is_empty()
+    }
+
+    pub fn transparent_proxy(&self) -> bool {
+        !self.injected_headers.is_empty() || self.via.is_some()
+    }
+
+    pub fn detected(&self) -> bool {
+        self.dns_tampered() || self.transparent_proxy()
+    }
+}
+
+/// Signs that the ISP shares public addresses among customers behind carrier-grade NAT
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CgnatReport {
+    pub checked_at: DateTime<Utc>,
+    /// Address the internet sees, from the public IP lookup
+    pub public_ip: Option<std::net::IpAddr>,
+    /// Address of the interface the default route leaves through
+    pub local_ip: Option<std::net::IpAddr>,
+    /// RFC 6598 shared addresses (100.64.0.0/10) on the interface or among the first hops
+    pub shared_addresses: Vec<std::net::IpAddr>,
+    /// Private hops past the home gateway, where the ISP's network should already begin. Shown for
+    /// context only: a second router at home produces the same trace.
+    pub private_upstream_hops: Vec<std::net::IpAddr>,
+}
+
+impl CgnatReport {
+    /// Only RFC 6598 shared addresses count; private upstream hops are too often a double NAT at home
+    pub fn detected(&self) -> bool {
+        !self.shared_addresses.is_empty()
+    }
 }
 
 /// Throttling pattern data
@@ -65,6 +558,16 @@ pub struct ThrottlingPattern {
     pub confidence: f64,
 }
 
+/// A stored throttling pattern with the wording shown to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThrottlingPatternSummary {
+    pub isp_name: String,
+    /// `ThrottlingPattern::description`
+    pub description: String,
+    pub confidence: f64,
+    pub pattern: ThrottlingPattern,
+}
+
 // Helper functions for serializing weekdays to/from JSON strings
 fn serialize_weekdays<S>(weekdays: &Vec<Weekday>, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -129,6 +632,65 @@ impl StealthLevel {
     }
 }
 
+/// Whose speed test the stealth traffic imitates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MimicryProfile {
+    /// speedtest.net (Ookla) servers from the server pool
+    #[default]
+    Speedtest,
+    /// fast.com: an api.fast.com token fetch, then HTTPS range requests to Netflix Open Connect caches
+    FastCom,
+    /// speed.cloudflare.com: `__down?bytes=` and `__up` transfers against the nearest Cloudflare POP
+    Cloudflare,
+}
+
+impl MimicryProfile {
+    pub const ALL: [MimicryProfile; 3] = [MimicryProfile::Speedtest, MimicryProfile::FastCom, MimicryProfile::Cloudflare];
+
+    /// Convert to string for database storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MimicryProfile::Speedtest => "speedtest",
+            MimicryProfile::FastCom => "fast_com",
+            MimicryProfile::Cloudflare => "cloudflare",
+        }
+    }
+
+    /// `None` for names this build does not know
+    pub fn from_string(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|profile| profile.as_str() == s)
+    }
+}
+
+/// How generated traffic reaches the servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrafficTransport {
+    /// HTTP/1.1 or HTTP/2 over TCP
+    #[default]
+    Tcp,
+    /// HTTP/3 over QUIC (UDP)
+    Quic,
+}
+
+impl TrafficTransport {
+    pub const ALL: [TrafficTransport; 2] = [TrafficTransport::Tcp, TrafficTransport::Quic];
+
+    /// Convert to string for database storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrafficTransport::Tcp => "tcp",
+            TrafficTransport::Quic => "quic",
+        }
+    }
+
+    /// `None` for names this build does not know
+    pub fn from_string(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|transport| transport.as_str() == s)
+    }
+}
+
 /// Optimization strategy configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizationStrategy {
@@ -140,6 +702,12 @@ pub struct OptimizationStrategy {
     pub connection_count: u8,
     pub traffic_intensity: f64,
     pub stealth_level: StealthLevel,
+    /// Traffic the stealth engine imitates while this strategy is active
+    #[serde(default)]
+    pub mimicry_profile: MimicryProfile,
+    /// Transport the stealth engine sends its speedtest.net traffic over
+    #[serde(default)]
+    pub transport: TrafficTransport,
     pub effectiveness_score: Option<f64>,
     pub created_at: DateTime<Utc>,
 }
@@ -151,12 +719,45 @@ impl SpeedMeasurement {
             timestamp: Utc::now(),
             download_mbps,
             upload_mbps,
-            latency_ms,
+            latency_ms: Some(latency_ms),
             optimization_active,
             confidence: 1.0, // Default confidence
+            source: MeasurementSource::Passive,
+            wifi_rssi_dbm: None,
+            wifi_link_mbps: None,
+            packet_loss_pct: None,
+            jitter_ms: None,
+            via_vpn: false,
+            via_proxy: false,
+            network_context: None,
+            method: MeasurementMethod::Interface,
+            upload_measured: true,
         }
     }
 
+    /// Marks where the measurement came from
+    pub fn with_source(mut self, source: MeasurementSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Marks the tool that took the measurement
+    pub fn with_method(mut self, method: MeasurementMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Whether a VPN or the proxy carried the traffic, so the figures describe more than the ISP
+    pub fn tunnelled(&self) -> bool {
+        self.via_vpn || self.via_proxy
+    }
+
+    /// Whether the measurement was taken over a Wi-Fi link too weak to carry full speed
+    pub fn on_weak_wifi(&self) -> bool {
+        self.wifi_rssi_dbm.is_some_and(|rssi| rssi <= WEAK_WIFI_RSSI_DBM)
+            || self.wifi_link_mbps.is_some_and(|rate| rate < WEAK_WIFI_LINK_MBPS)
+    }
+
     /// Validate the speed measurement data
     pub fn validate(&self) -> Result<(), ValidationError> {
         if self.download_mbps < 0.0 {
@@ -165,8 +766,8 @@ impl SpeedMeasurement {
         if self.upload_mbps < 0.0 {
             return Err(ValidationError::InvalidSpeed { value: self.upload_mbps });
         }
-        if self.latency_ms > 10000 {
-            return Err(ValidationError::InvalidLatency { value: self.latency_ms });
+        if let Some(latency_ms) = self.latency_ms.filter(|l| *l > 10000) {
+            return Err(ValidationError::InvalidLatency { value: latency_ms });
         }
         if self.confidence < 0.0 || self.confidence > 1.0 {
             return Err(ValidationError::InvalidConfidence { value: self.confidence });
@@ -176,16 +777,23 @@ impl SpeedMeasurement {
 
     /// Check if this measurement indicates good performance
     pub fn is_good_performance(&self) -> bool {
-        self.download_mbps > 10.0 && self.upload_mbps > 1.0 && self.latency_ms < 100
+        self.download_mbps > 10.0 && (!self.upload_measured || self.upload_mbps > 1.0) && self.latency_ms.is_none_or(|l| l < 100)
     }
 
     /// Calculate a performance score (0.0 to 1.0)
     pub fn performance_score(&self) -> f64 {
         let download_score = (self.download_mbps / 100.0).min(1.0);
-        let upload_score = (self.upload_mbps / 20.0).min(1.0);
-        let latency_score = (1.0 - (self.latency_ms as f64 / 1000.0)).max(0.0);
+        // Without an upload test the download stands in for it rather than scoring as a dead uplink
+        let upload_score = if self.upload_measured { (self.upload_mbps / 20.0).min(1.0) } else { download_score };
+        // Throttling by induced loss leaves throughput intact between drops, so loss scales the whole score
+        let loss_factor = 1.0 / (1.0 + self.packet_loss_pct.unwrap_or(0.0).max(0.0) / HALF_PENALTY_LOSS_PCT);
+        // Without a latency figure score on throughput alone rather than as a perfect link
+        let Some(latency_ms) = self.latency_ms else {
+            return (download_score * 0.5 + upload_score * 0.3) / 0.8 * self.confidence * loss_factor;
+        };
+        let latency_score = (1.0 - (latency_ms as f64 / 1000.0)).max(0.0);
         
-        (download_score * 0.5 + upload_score * 0.3 + latency_score * 0.2) * self.confidence
+        (download_score * 0.5 + upload_score * 0.3 + latency_score * 0.2) * self.confidence * loss_factor
     }
 }
 
@@ -199,6 +807,9 @@ impl ISPProfile {
             detection_method,
             created_at: now,
             updated_at: now,
+            tampering: None,
+            cgnat: None,
+            candidates: Vec::new(),
         }
     }
 
@@ -370,6 +981,8 @@ impl OptimizationStrategy {
             connection_count: 3,
             traffic_intensity: 0.5,
             stealth_level: StealthLevel::Medium,
+            mimicry_profile: MimicryProfile::Speedtest,
+            transport: TrafficTransport::Tcp,
             effectiveness_score: None,
             created_at: Utc::now(),
         }
@@ -386,6 +999,8 @@ impl OptimizationStrategy {
             connection_count: 2,
             traffic_intensity: 0.3,
             stealth_level: StealthLevel::High,
+            mimicry_profile: MimicryProfile::Speedtest,
+            transport: TrafficTransport::Tcp,
             effectiveness_score: None,
             created_at: Utc::now(),
         }
@@ -399,14 +1014,20 @@ impl OptimizationStrategy {
         if self.connection_count == 0 || self.connection_count > 10 {
             return Err(ValidationError::InvalidConnectionCount { value: self.connection_count });
         }
-        if self.traffic_intensity < 0.0 || self.traffic_intensity > 1.0 {
+        if !(0.0..=1.0).contains(&self.traffic_intensity) {
             return Err(ValidationError::InvalidTrafficIntensity { value: self.traffic_intensity });
         }
+        // The stealth loop turns these into `Duration`s, which panic on negative or non-finite seconds
+        for seconds in [self.packet_timing_min_seconds, self.packet_timing_max_seconds] {
+            if !seconds.is_finite() || seconds < 0.0 {
+                return Err(ValidationError::InvalidPacketTiming { value: seconds });
+            }
+        }
         if self.packet_timing_min_seconds >= self.packet_timing_max_seconds {
-            return Err(ValidationError::InvalidSpeed { value: self.packet_timing_min_seconds });
+            return Err(ValidationError::InvalidPacketTiming { value: self.packet_timing_min_seconds });
         }
         if let Some(score) = self.effectiveness_score {
-            if score < 0.0 || score > 1.0 {
+            if !(0.0..=1.0).contains(&score) {
                 return Err(ValidationError::InvalidConfidence { value: score });
             }
         }
@@ -429,6 +1050,9 @@ impl OptimizationStrategy {
     }
 }
 
+/// `server_id` prefix of servers the user defined in `advanced.custom_servers`
+pub const CUSTOM_SERVER_ID_PREFIX: &str = "custom:";
+
 /// Speedtest server information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpeedtestServer {
@@ -443,6 +1067,118 @@ pub struct SpeedtestServer {
     pub latency: Option<f64>,
     pub is_active: bool,
     pub last_used: Option<DateTime<Utc>>,
+    /// Location from the server directory, for maps
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    /// How many times generated traffic was sent to this server
+    #[serde(default)]
+    pub use_count: u32,
+    /// Software the server runs, which decides its endpoint paths
+    #[serde(default)]
+    pub provider: ServerProvider,
+    #[serde(default)]
+    pub paths: ServerPathTemplates,
+    /// Share of latency requests in the last probe round that failed or went unanswered.
+    /// An HTTP-level figure, not packet loss.
+    #[serde(default)]
+    pub request_failure_pct: Option<f64>,
+    /// When `latency` and `request_failure_pct` were last probed
+    #[serde(default)]
+    pub probed_at: Option<DateTime<Utc>>,
+    /// Forces https (`true`) or plain http (`false`); `None` leaves it to the caller
+    #[serde(default)]
+    pub tls: Option<bool>,
+}
+
+/// Speed test server software
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerProvider {
+    #[default]
+    Ookla,
+    Cloudflare,
+    Librespeed,
+    /// User-supplied paths
+    Custom,
+}
+
+impl ServerProvider {
+    /// Convert to string for database storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServerProvider::Ookla => "ookla",
+            ServerProvider::Cloudflare => "cloudflare",
+            ServerProvider::Librespeed => "librespeed",
+            ServerProvider::Custom => "custom",
+        }
+    }
+
+    /// Create from string (for database retrieval)
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "cloudflare" => ServerProvider::Cloudflare,
+            "librespeed" => ServerProvider::Librespeed,
+            "custom" => ServerProvider::Custom,
+            _ => ServerProvider::Ookla,
+        }
+    }
+}
+
+/// Endpoint kinds every speed test server exposes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerEndpoint {
+    Latency,
+    Download,
+    Upload,
+}
+
+/// Request paths (with query) for a server's endpoints.
+/// `{bytes}` and `{nonce}` are substituted per request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerPathTemplates {
+    pub latency: String,
+    pub download: String,
+    pub upload: String,
+}
+
+impl ServerPathTemplates {
+    /// Stock paths for a provider; `Custom` starts from the Ookla layout
+    pub fn for_provider(provider: ServerProvider) -> Self {
+        let (latency, download, upload) = match provider {
+            ServerProvider::Ookla | ServerProvider::Custom => (
+                "/speedtest/latency.txt?r={nonce}",
+                "/speedtest/random4000x4000.jpg?r={nonce}",
+                "/speedtest/upload.php",
+            ),
+            ServerProvider::Cloudflare => (
+                "/__down?bytes=0&r={nonce}",
+                "/__down?bytes={bytes}&r={nonce}",
+                "/__up",
+            ),
+            ServerProvider::Librespeed => (
+                "/backend/empty.php?r={nonce}",
+                "/backend/garbage.php?ckSize=100&r={nonce}",
+                "/backend/empty.php",
+            ),
+        };
+        Self { latency: latency.to_string(), download: download.to_string(), upload: upload.to_string() }
+    }
+
+    /// Path for one request; `bytes` is a size hint only some providers honor
+    pub fn render(&self, endpoint: ServerEndpoint, bytes: u64, nonce: &str) -> String {
+        let template = match endpoint {
+            ServerEndpoint::Latency => &self.latency,
+            ServerEndpoint::Download => &self.download,
+            ServerEndpoint::Upload => &self.upload,
+        };
+        template.replace("{bytes}", &bytes.to_string()).replace("{nonce}", nonce)
+    }
+}
+
+impl Default for ServerPathTemplates {
+    fn default() -> Self { Self::for_provider(ServerProvider::Ookla) }
 }
 
 impl SpeedtestServer {
@@ -459,6 +1195,60 @@ impl SpeedtestServer {
             latency: None,
             is_active: true,
             last_used: None,
+            latitude: None,
+            longitude: None,
+            use_count: 0,
+            provider: ServerProvider::Ookla,
+            paths: ServerPathTemplates::default(),
+            request_failure_pct: None,
+            probed_at: None,
+            tls: None,
+        }
+    }
+
+    /// Cloudflare's speed test edge, used when no directory server is known
+    pub fn cloudflare() -> Self {
+        Self::new(
+            "cloudflare".to_string(),
+            "speed.cloudflare.com".to_string(),
+            443,
+            "Cloudflare".to_string(),
+            "Global".to_string(),
+            "Cloudflare".to_string(),
+        )
+        .with_provider(ServerProvider::Cloudflare)
+    }
+
+    /// Whether the user defined this server rather than a directory
+    pub fn is_custom(&self) -> bool {
+        self.server_id.starts_with(CUSTOM_SERVER_ID_PREFIX)
+    }
+
+    /// Sets the directory location
+    pub fn with_coordinates(mut self, latitude: f64, longitude: f64) -> Self {
+        self.latitude = Some(latitude);
+        self.longitude = Some(longitude);
+        self
+    }
+
+    /// Switches provider and resets the paths to that provider's stock layout
+    pub fn with_provider(mut self, provider: ServerProvider) -> Self {
+        self.provider = provider;
+        self.paths = ServerPathTemplates::for_provider(provider);
+        self
+    }
+
+    /// Full URL for an endpoint. A set `tls` wins over `secure`; otherwise port 443 always uses https.
+    /// The default port for the scheme is omitted.
+    pub fn endpoint_url(&self, secure: bool, endpoint: ServerEndpoint, bytes: u64, nonce: &str) -> String {
+        let secure = self.tls.unwrap_or(secure || self.port == 443);
+        let scheme = if secure { "https" } else { "http" };
+        let default_port = if secure { 443 } else { 80 };
+        let path = self.paths.render(endpoint, bytes, nonce);
+        if self.port == default_port {
+            format!("{}://{}{}", scheme, self.host, path)
+        } else {
+            format!("{}://{}:{}{}", scheme, self.host, self.port, path)
         }
     }
 
@@ -577,6 +1367,21 @@ mod tests {
         let score = measurement.performance_score();
         assert!(score > 0.8);
         assert!(score <= 1.0);
+
+        // Unmeasured latency neither helps nor hurts
+        let slow = SpeedMeasurement { latency_ms: None, ..SpeedMeasurement::new(20.0, 4.0, 0, false) };
+        assert!((slow.performance_score() - 0.2 * slow.confidence).abs() < 1e-9);
+
+        // Loss drags the score down even at full speed
+        let lossy = SpeedMeasurement { packet_loss_pct: Some(10.0), ..measurement.clone() };
+        assert!((lossy.performance_score() - score / 2.0).abs() < 1e-9);
+        let heavy = SpeedMeasurement { packet_loss_pct: Some(40.0), ..measurement.clone() };
+        assert!((heavy.performance_score() - score / 5.0).abs() < 1e-9);
+
+        // A download-only test is not scored as a dead uplink
+        let download_only = SpeedMeasurement { upload_measured: false, ..SpeedMeasurement::new(100.0, 0.0, 20, false) };
+        assert!((download_only.performance_score() - score).abs() < 1e-9);
+        assert!(download_only.is_good_performance());
     }
 
     #[test]
@@ -603,6 +1408,13 @@ mod tests {
         let mut invalid_strategy = strategy.clone();
         invalid_strategy.connection_count = 0;
         assert!(invalid_strategy.validate().is_err());
+
+        for (min, max) in [(-1.0, 10.0), (f64::NAN, 10.0), (5.0, f64::INFINITY), (10.0, 5.0)] {
+            let timing = OptimizationStrategy { packet_timing_min_seconds: min, packet_timing_max_seconds: max, ..strategy.clone() };
+            assert!(matches!(timing.validate(), Err(ValidationError::InvalidPacketTiming { .. })), "{} - {}", min, max);
+        }
+        let nan_intensity = OptimizationStrategy { traffic_intensity: f64::NAN, ..strategy.clone() };
+        assert!(nan_intensity.validate().is_err());
     }
 
     #[test]
@@ -636,6 +1448,36 @@ mod tests {
         assert!(!server.is_suitable_for_region("United States"));
     }
 
+    #[test]
+    fn test_server_endpoint_urls_follow_provider_templates() {
+        let ookla = SpeedtestServer::new(
+            "1".to_string(),
+            "speedtest.example.lk".to_string(),
+            8080,
+            "Colombo".to_string(),
+            "Sri Lanka".to_string(),
+            "Example".to_string(),
+        );
+        assert_eq!(
+            ookla.endpoint_url(false, ServerEndpoint::Latency, 0, "ab12"),
+            "http://speedtest.example.lk:8080/speedtest/latency.txt?r=ab12"
+        );
+        assert_eq!(
+            ookla.endpoint_url(false, ServerEndpoint::Upload, 0, "x"),
+            "http://speedtest.example.lk:8080/speedtest/upload.php"
+        );
+
+        let cloudflare = SpeedtestServer::cloudflare();
+        assert_eq!(
+            cloudflare.endpoint_url(false, ServerEndpoint::Download, 262144, "7"),
+            "https://speed.cloudflare.com/__down?bytes=262144&r=7"
+        );
+
+        let libre = ookla.clone().with_provider(ServerProvider::Librespeed);
+        assert_eq!(libre.paths.render(ServerEndpoint::Upload, 0, ""), "/backend/empty.php");
+        assert_eq!(ServerProvider::from_string(libre.provider.as_str()), ServerProvider::Librespeed);
+    }
+
     #[test]
     fn test_app_config_validation() {
         let config = AppConfig::default();
@@ -1,7 +1,10 @@
-use crate::core::error::Result;
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::data::downsample::{ChartMetric, ChartPoint, ChartSeries};
+use crate::data::export::MeasurementFilter;
 use crate::data::models::*;
+use serde::Serialize;
 use sqlx::{SqlitePool, Row};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 
 /// Repository pattern implementation for database operations
 pub struct Repository {
@@ -12,13 +15,20 @@ impl Repository {
     pub fn new(pool: SqlitePool) -> Self {
         Self { pool }
     }
-    
+
+    /// Folds the WAL into the database file and closes the pool; later queries fail
+    pub async fn close(&self) -> Result<()> {
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&self.pool).await?;
+        self.pool.close().await;
+        Ok(())
+    }
+
     /// Speed measurement operations
     pub async fn save_speed_measurement(&self, measurement: &SpeedMeasurement) -> Result<i64> {
         let result = sqlx::query(
             r#"
-            INSERT INTO speed_measurements (timestamp, download_mbps, upload_mbps, latency_ms, optimization_active, confidence)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO speed_measurements (timestamp, download_mbps, upload_mbps, latency_ms, optimization_active, confidence, source, wifi_rssi_dbm, wifi_link_mbps, packet_loss_pct, jitter_ms, via_vpn, via_proxy, network_context, method, upload_measured)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(&measurement.timestamp)
@@ -27,16 +37,45 @@ impl Repository {
         .bind(measurement.latency_ms)
         .bind(measurement.optimization_active)
         .bind(measurement.confidence)
+        .bind(measurement.source.as_str())
+        .bind(measurement.wifi_rssi_dbm)
+        .bind(measurement.wifi_link_mbps)
+        .bind(measurement.packet_loss_pct)
+        .bind(measurement.jitter_ms)
+        .bind(measurement.via_vpn)
+        .bind(measurement.via_proxy)
+        .bind(&measurement.network_context)
+        .bind(measurement.method.as_str())
+        .bind(measurement.upload_measured)
         .execute(&self.pool)
         .await?;
         
         Ok(result.last_insert_rowid())
     }
     
+    /// Tags measurements stored before network contexts were tracked with `context`, the network
+    /// detected on the first run that tracks them. Rows newer than the first tagged one are left alone.
+    pub async fn backfill_network_context(&self, context: &str) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE speed_measurements SET network_context = ?
+            WHERE network_context IS NULL
+              AND timestamp < COALESCE((SELECT MIN(timestamp) FROM speed_measurements WHERE network_context IS NOT NULL), ?)
+            "#
+        )
+        .bind(context)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     pub async fn get_speed_measurements_since(&self, since: DateTime<Utc>) -> Result<Vec<SpeedMeasurement>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, timestamp, download_mbps, upload_mbps, latency_ms, optimization_active, confidence
+            SELECT id, timestamp, download_mbps, upload_mbps, latency_ms, optimization_active, confidence, source, wifi_rssi_dbm, wifi_link_mbps,
+                   packet_loss_pct, jitter_ms, via_vpn, via_proxy, network_context, method, upload_measured
             FROM speed_measurements
             WHERE timestamp >= ?
             ORDER BY timestamp DESC
@@ -55,6 +94,16 @@ impl Repository {
                 latency_ms: row.get("latency_ms"),
                 optimization_active: row.get("optimization_active"),
                 confidence: row.get("confidence"),
+                source: MeasurementSource::from_string(row.get::<String, _>("source").as_str()),
+                wifi_rssi_dbm: row.get("wifi_rssi_dbm"),
+                wifi_link_mbps: row.get("wifi_link_mbps"),
+                packet_loss_pct: row.get("packet_loss_pct"),
+                jitter_ms: row.get("jitter_ms"),
+                via_vpn: row.get("via_vpn"),
+                via_proxy: row.get("via_proxy"),
+                network_context: row.get("network_context"),
+                method: MeasurementMethod::from_string(row.get::<String, _>("method").as_str()),
+                upload_measured: row.get("upload_measured"),
             }
         }).collect();
         
@@ -65,8 +114,8 @@ impl Repository {
     pub async fn save_isp_profile(&self, profile: &ISPProfile) -> Result<i64> {
         let result = sqlx::query(
             r#"
-            INSERT INTO isp_profiles (name, region, detection_method, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?)
+            INSERT INTO isp_profiles (name, region, detection_method, created_at, updated_at, tampering, cgnat, candidates)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(&profile.name)
@@ -74,6 +123,9 @@ impl Repository {
         .bind(&profile.detection_method)
         .bind(&profile.created_at)
         .bind(&profile.updated_at)
+        .bind(profile.tampering.as_ref().map(serde_json::to_string).transpose()?)
+        .bind(profile.cgnat.as_ref().map(serde_json::to_string).transpose()?)
+        .bind(serde_json::to_string(&profile.candidates)?)
         .execute(&self.pool)
         .await?;
         
@@ -83,7 +135,7 @@ impl Repository {
     pub async fn get_current_isp_profile(&self) -> Result<Option<ISPProfile>> {
         let row = sqlx::query(
             r#"
-            SELECT id, name, region, detection_method, created_at, updated_at
+            SELECT id, name, region, detection_method, created_at, updated_at, tampering, cgnat, candidates
             FROM isp_profiles
             ORDER BY updated_at DESC
             LIMIT 1
@@ -99,136 +151,1117 @@ impl Repository {
             detection_method: r.get("detection_method"),
             created_at: r.get("created_at"),
             updated_at: r.get("updated_at"),
+            tampering: r.get::<Option<String>, _>("tampering").and_then(|t| serde_json::from_str(&t).ok()),
+            cgnat: r.get::<Option<String>, _>("cgnat").and_then(|c| serde_json::from_str(&c).ok()),
+            candidates: r.get::<Option<String>, _>("candidates").and_then(|c| serde_json::from_str(&c).ok()).unwrap_or_default(),
         });
         
         Ok(profile)
     }
-    
-    /// Throttling pattern operations
-    pub async fn save_throttling_pattern(&self, pattern: &ThrottlingPattern) -> Result<i64> {
-        let result = sqlx::query(
+
+    /// Attaches the latest interference check to a profile
+    pub async fn update_isp_profile_tampering(&self, id: i64, report: &TamperingReport) -> Result<()> {
+        sqlx::query("UPDATE isp_profiles SET tampering = ? WHERE id = ?")
+            .bind(serde_json::to_string(report)?)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+    
+    /// Attaches the latest carrier-grade NAT check to a profile
+    pub async fn update_isp_profile_cgnat(&self, id: i64, report: &CgnatReport) -> Result<()> {
+        sqlx::query("UPDATE isp_profiles SET cgnat = ? WHERE id = ?")
+            .bind(serde_json::to_string(report)?)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+    
+    /// Throttling pattern operations
+    pub async fn save_throttling_pattern(&self, pattern: &ThrottlingPattern) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO throttling_patterns (isp_profile_id, start_hour, start_minute, end_hour, end_minute, days_of_week, severity, confidence)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(pattern.isp_profile_id)
+        .bind(pattern.start_hour)
+        .bind(pattern.start_minute)
+        .bind(pattern.end_hour)
+        .bind(pattern.end_minute)
+        .bind(&pattern.days_of_week_json())
+        .bind(pattern.severity)
+        .bind(pattern.confidence)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    /// Replaces every stored pattern of the ISP with `patterns`, so each analysis leaves only its own findings
+    pub async fn replace_throttling_patterns(&self, isp_profile_id: i64, patterns: &[ThrottlingPattern]) -> Result<Vec<i64>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM throttling_patterns WHERE isp_profile_id = ?")
+            .bind(isp_profile_id)
+            .execute(&mut *tx)
+            .await?;
+        let mut ids = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO throttling_patterns (isp_profile_id, start_hour, start_minute, end_hour, end_minute, days_of_week, severity, confidence)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(isp_profile_id)
+            .bind(pattern.start_hour)
+            .bind(pattern.start_minute)
+            .bind(pattern.end_hour)
+            .bind(pattern.end_minute)
+            .bind(&pattern.days_of_week_json())
+            .bind(pattern.severity)
+            .bind(pattern.confidence)
+            .execute(&mut *tx)
+            .await?;
+            ids.push(result.last_insert_rowid());
+        }
+        tx.commit().await?;
+        Ok(ids)
+    }
+
+    /// Patterns of the current ISP with their descriptions, most confident first
+    pub async fn get_current_throttling_summaries(&self) -> Result<Vec<ThrottlingPatternSummary>> {
+        let Some(profile) = self.get_current_isp_profile().await? else { return Ok(Vec::new()) };
+        let Some(profile_id) = profile.id else { return Ok(Vec::new()) };
+        let mut patterns: Vec<ThrottlingPatternSummary> = self
+            .get_throttling_patterns_for_isp(profile_id)
+            .await?
+            .into_iter()
+            .map(|pattern| ThrottlingPatternSummary { isp_name: profile.name.clone(), description: pattern.description(), confidence: pattern.confidence, pattern })
+            .collect();
+        patterns.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        Ok(patterns)
+    }
+
+    pub async fn get_throttling_patterns_for_isp(&self, isp_profile_id: i64) -> Result<Vec<ThrottlingPattern>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, isp_profile_id, start_hour, start_minute, end_hour, end_minute, days_of_week, severity, confidence
+            FROM throttling_patterns
+            WHERE isp_profile_id = ?
+            ORDER BY confidence DESC
+            "#
+        )
+        .bind(isp_profile_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let patterns = rows.into_iter().map(|row| {
+            ThrottlingPattern::from_db_row(
+                row.get("id"),
+                row.get("isp_profile_id"),
+                row.get("start_hour"),
+                row.get("start_minute"),
+                row.get("end_hour"),
+                row.get("end_minute"),
+                &row.get::<String, _>("days_of_week"),
+                row.get("severity"),
+                row.get("confidence"),
+            )
+        }).collect();
+        
+        Ok(patterns)
+    }
+    
+    /// Optimization strategy operations
+    pub async fn save_optimization_strategy(&self, strategy: &OptimizationStrategy) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO optimization_strategies (name, server_rotation_interval_minutes, packet_timing_min_seconds, packet_timing_max_seconds, connection_count, traffic_intensity, stealth_level, mimicry_profile, transport, effectiveness_score, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&strategy.name)
+        .bind(strategy.server_rotation_interval_minutes)
+        .bind(strategy.packet_timing_min_seconds)
+        .bind(strategy.packet_timing_max_seconds)
+        .bind(strategy.connection_count)
+        .bind(strategy.traffic_intensity)
+        .bind(&strategy.stealth_level.to_string())
+        .bind(strategy.mimicry_profile.as_str())
+        .bind(strategy.transport.as_str())
+        .bind(strategy.effectiveness_score)
+        .bind(&strategy.created_at)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    /// The explicitly activated strategy, else the best scored one
+    pub async fn get_best_optimization_strategy(&self) -> Result<Option<OptimizationStrategy>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, name, server_rotation_interval_minutes, packet_timing_min_seconds, packet_timing_max_seconds, connection_count, traffic_intensity, stealth_level, mimicry_profile, transport, effectiveness_score, created_at
+            FROM optimization_strategies
+            WHERE is_active = 1 OR effectiveness_score IS NOT NULL
+            ORDER BY is_active DESC, effectiveness_score DESC
+            LIMIT 1
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        Ok(row.as_ref().map(Self::optimization_strategy_from_row))
+    }
+
+    /// Every saved strategy, oldest first
+    pub async fn get_optimization_strategies(&self) -> Result<Vec<OptimizationStrategy>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, server_rotation_interval_minutes, packet_timing_min_seconds, packet_timing_max_seconds, connection_count, traffic_intensity, stealth_level, mimicry_profile, transport, effectiveness_score, created_at
+            FROM optimization_strategies
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::optimization_strategy_from_row).collect())
+    }
+
+    pub async fn get_optimization_strategy(&self, id: i64) -> Result<Option<OptimizationStrategy>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, name, server_rotation_interval_minutes, packet_timing_min_seconds, packet_timing_max_seconds, connection_count, traffic_intensity, stealth_level, mimicry_profile, transport, effectiveness_score, created_at
+            FROM optimization_strategies
+            WHERE id = ?
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(Self::optimization_strategy_from_row))
+    }
+
+    /// Rewrites a saved strategy's settings; its score and creation time are left alone
+    pub async fn update_optimization_strategy(&self, strategy: &OptimizationStrategy) -> Result<()> {
+        let id = strategy.id.ok_or_else(|| SpeedKarmaError::ConfigurationError("Strategy has not been saved yet".to_string()))?;
+        let updated = sqlx::query(
+            r#"
+            UPDATE optimization_strategies
+            SET name = ?, server_rotation_interval_minutes = ?, packet_timing_min_seconds = ?, packet_timing_max_seconds = ?, connection_count = ?, traffic_intensity = ?, stealth_level = ?, mimicry_profile = ?, transport = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&strategy.name)
+        .bind(strategy.server_rotation_interval_minutes)
+        .bind(strategy.packet_timing_min_seconds)
+        .bind(strategy.packet_timing_max_seconds)
+        .bind(strategy.connection_count)
+        .bind(strategy.traffic_intensity)
+        .bind(strategy.stealth_level.to_string())
+        .bind(strategy.mimicry_profile.as_str())
+        .bind(strategy.transport.as_str())
+        .bind(id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Err(SpeedKarmaError::ConfigurationError(format!("No strategy with id {}", id)));
+        }
+        Ok(())
+    }
+
+    /// Removes a strategy; returns false when there was none with that id
+    pub async fn delete_optimization_strategy(&self, id: i64) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM optimization_strategies WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    fn optimization_strategy_from_row(r: &sqlx::sqlite::SqliteRow) -> OptimizationStrategy {
+        OptimizationStrategy {
+            id: r.get("id"),
+            name: r.get("name"),
+            server_rotation_interval_minutes: r.get("server_rotation_interval_minutes"),
+            packet_timing_min_seconds: r.get("packet_timing_min_seconds"),
+            packet_timing_max_seconds: r.get("packet_timing_max_seconds"),
+            connection_count: r.get("connection_count"),
+            traffic_intensity: r.get("traffic_intensity"),
+            stealth_level: StealthLevel::from_string(&r.get::<String, _>("stealth_level")),
+            mimicry_profile: MimicryProfile::from_string(&r.get::<String, _>("mimicry_profile")).unwrap_or_default(),
+            transport: TrafficTransport::from_string(&r.get::<String, _>("transport")).unwrap_or_default(),
+            effectiveness_score: r.get("effectiveness_score"),
+            created_at: r.get("created_at"),
+        }
+    }
+    
+    /// Makes `id` the active strategy whatever its score; `None` hands the choice back to the scores
+    pub async fn set_active_strategy(&self, id: Option<i64>) -> Result<()> {
+        sqlx::query("UPDATE optimization_strategies SET is_active = CASE WHEN id = ? THEN 1 ELSE 0 END")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Sets or clears a strategy's score; unscored strategies are only picked when activated
+    pub async fn update_strategy_effectiveness(&self, id: i64, score: Option<f64>) -> Result<()> {
+        sqlx::query("UPDATE optimization_strategies SET effectiveness_score = ? WHERE id = ?")
+            .bind(score)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Cleanup old data (privacy-focused approach). Raw rows past `days_to_keep` are rolled into
+    /// hourly averages before they go, so long-term patterns survive for the learning model.
+    pub async fn cleanup_old_data(&self, days_to_keep: u32) -> Result<()> {
+        self.compact_measurements_older_than(days_to_keep).await?;
+        Ok(())
+    }
+
+    /// Rolls raw measurements older than `days` into `speed_measurements_hourly` and deletes them.
+    /// Re-running merges into existing hours using sample-weighted averages.
+    pub async fn compact_measurements_older_than(&self, days: u32) -> Result<u64> {
+        let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO speed_measurements_hourly (
+                hour_start, optimization_active, sample_count,
+                avg_download_mbps, min_download_mbps, max_download_mbps,
+                avg_upload_mbps, avg_latency_ms, avg_confidence
+            )
+            SELECT
+                strftime('%Y-%m-%dT%H:00:00+00:00', timestamp) AS hour_start,
+                optimization_active,
+                COUNT(*),
+                AVG(download_mbps), MIN(download_mbps), MAX(download_mbps),
+                COALESCE(AVG(CASE WHEN upload_measured THEN upload_mbps END), 0), COALESCE(AVG(latency_ms), 0), AVG(confidence)
+            FROM speed_measurements
+            WHERE timestamp < ?
+            GROUP BY hour_start, optimization_active
+            ON CONFLICT(hour_start, optimization_active) DO UPDATE SET
+                avg_download_mbps = (avg_download_mbps * sample_count + excluded.avg_download_mbps * excluded.sample_count) / (sample_count + excluded.sample_count),
+                avg_upload_mbps = (avg_upload_mbps * sample_count + excluded.avg_upload_mbps * excluded.sample_count) / (sample_count + excluded.sample_count),
+                avg_latency_ms = (avg_latency_ms * sample_count + excluded.avg_latency_ms * excluded.sample_count) / (sample_count + excluded.sample_count),
+                avg_confidence = (avg_confidence * sample_count + excluded.avg_confidence * excluded.sample_count) / (sample_count + excluded.sample_count),
+                min_download_mbps = MIN(min_download_mbps, excluded.min_download_mbps),
+                max_download_mbps = MAX(max_download_mbps, excluded.max_download_mbps),
+                sample_count = sample_count + excluded.sample_count
+            "#
+        )
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?;
+
+        let deleted = sqlx::query("DELETE FROM speed_measurements WHERE timestamp < ?")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Returns freed pages to the filesystem; a no-op unless the database uses incremental auto-vacuum
+    pub async fn reclaim_space(&self) -> Result<()> {
+        sqlx::query("PRAGMA incremental_vacuum").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Hourly aggregates since the given time, oldest first
+    pub async fn get_hourly_aggregates_since(&self, since: DateTime<Utc>) -> Result<Vec<HourlyAggregate>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT hour_start, optimization_active, sample_count, avg_download_mbps, min_download_mbps,
+                   max_download_mbps, avg_upload_mbps, avg_latency_ms, avg_confidence
+            FROM speed_measurements_hourly
+            WHERE hour_start >= ?
+            ORDER BY hour_start ASC
+            "#
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let aggregates = rows.into_iter().map(|row| HourlyAggregate {
+            hour_start: row.get("hour_start"),
+            optimization_active: row.get("optimization_active"),
+            sample_count: row.get("sample_count"),
+            avg_download_mbps: row.get("avg_download_mbps"),
+            min_download_mbps: row.get("min_download_mbps"),
+            max_download_mbps: row.get("max_download_mbps"),
+            avg_upload_mbps: row.get("avg_upload_mbps"),
+            avg_latency_ms: row.get("avg_latency_ms"),
+            avg_confidence: row.get("avg_confidence"),
+        }).collect();
+
+        Ok(aggregates)
+    }
+
+    /// Hourly aggregates between `since` and `until`, oldest first: compacted hours as stored, newer
+    /// raw rows grouped the same way on the fly. An hour split by compaction appears twice.
+    pub async fn get_hourly_aggregates_between(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<HourlyAggregate>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT hour_start, optimization_active, sample_count, avg_download_mbps, min_download_mbps,
+                   max_download_mbps, avg_upload_mbps, avg_latency_ms, avg_confidence
+            FROM speed_measurements_hourly
+            WHERE hour_start >= ? AND hour_start <= ?
+            UNION ALL
+            SELECT strftime('%Y-%m-%dT%H:00:00+00:00', timestamp) AS hour_start, optimization_active, COUNT(*),
+                   AVG(download_mbps), MIN(download_mbps), MAX(download_mbps),
+                   AVG(upload_mbps), COALESCE(AVG(latency_ms), 0), AVG(confidence)
+            FROM speed_measurements
+            WHERE timestamp >= ? AND timestamp <= ?
+            GROUP BY 1, optimization_active
+            ORDER BY hour_start ASC
+            "#
+        )
+        .bind(since)
+        .bind(until)
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| HourlyAggregate {
+            hour_start: row.get("hour_start"),
+            optimization_active: row.get("optimization_active"),
+            sample_count: row.get("sample_count"),
+            avg_download_mbps: row.get("avg_download_mbps"),
+            min_download_mbps: row.get("min_download_mbps"),
+            max_download_mbps: row.get("max_download_mbps"),
+            avg_upload_mbps: row.get("avg_upload_mbps"),
+            avg_latency_ms: row.get("avg_latency_ms"),
+            avg_confidence: row.get("avg_confidence"),
+        }).collect())
+    }
+
+    /// One metric between `since` and `until`, oldest first, downsampled to `max_points`. Compacted
+    /// history comes from the hourly table (sample-weighted across optimization states), newer
+    /// history from raw rows, so only the plotted column ever leaves SQLite.
+    pub async fn get_chart_series(&self, metric: ChartMetric, since: DateTime<Utc>, until: DateTime<Utc>, max_points: usize) -> Result<ChartSeries> {
+        let mut points = Vec::new();
+        if let Some(column) = metric.hourly_column() {
+            let hourly = sqlx::query(&format!(
+                "SELECT hour_start, CAST(SUM({c} * sample_count) AS REAL) / SUM(sample_count) AS value
+                 FROM speed_measurements_hourly
+                 WHERE hour_start >= ? AND hour_start <= ? AND sample_count > 0
+                 GROUP BY hour_start ORDER BY hour_start ASC",
+                c = column
+            ))
+            .bind(since)
+            .bind(until)
+            .fetch_all(&self.pool)
+            .await?;
+            points.extend(hourly.iter().map(|row| ChartPoint { timestamp: row.get("hour_start"), value: row.get("value") }));
+        }
+        let raw = sqlx::query(&format!(
+            "SELECT timestamp, CAST({c} AS REAL) AS value FROM speed_measurements
+             WHERE timestamp >= ? AND timestamp <= ? AND {c} IS NOT NULL
+             ORDER BY timestamp ASC",
+            c = metric.raw_column()
+        ))
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await?;
+        points.extend(raw.iter().map(|row| ChartPoint { timestamp: row.get("timestamp"), value: row.get("value") }));
+        points.sort_by_key(|p| p.timestamp);
+        Ok(ChartSeries::new(metric, since, until, points, max_points))
+    }
+
+    /// Up to `limit` measurements matching `filter` with an id above `after_id`, in id order
+    pub async fn get_speed_measurements_page(&self, filter: &MeasurementFilter, after_id: i64, limit: u32) -> Result<Vec<SpeedMeasurement>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, timestamp, download_mbps, upload_mbps, latency_ms, optimization_active, confidence, source, wifi_rssi_dbm, wifi_link_mbps,
+                   packet_loss_pct, jitter_ms, via_vpn, via_proxy, network_context, method, upload_measured
+            FROM speed_measurements
+            WHERE id > ?
+              AND (? IS NULL OR timestamp >= ?)
+              AND (? IS NULL OR timestamp <= ?)
+              AND (? IS NULL OR optimization_active = ?)
+              AND (? IS NULL OR source = ?)
+            ORDER BY id ASC
+            LIMIT ?
+            "#
+        )
+        .bind(after_id)
+        .bind(filter.since)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.until)
+        .bind(filter.optimization_active)
+        .bind(filter.optimization_active)
+        .bind(filter.source.map(|s| s.as_str()))
+        .bind(filter.source.map(|s| s.as_str()))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let measurements = rows.into_iter().map(|row| SpeedMeasurement {
+            id: row.get("id"),
+            timestamp: row.get("timestamp"),
+            download_mbps: row.get("download_mbps"),
+            upload_mbps: row.get("upload_mbps"),
+            latency_ms: row.get("latency_ms"),
+            optimization_active: row.get("optimization_active"),
+            confidence: row.get("confidence"),
+            source: MeasurementSource::from_string(row.get::<String, _>("source").as_str()),
+            wifi_rssi_dbm: row.get("wifi_rssi_dbm"),
+            wifi_link_mbps: row.get("wifi_link_mbps"),
+            packet_loss_pct: row.get("packet_loss_pct"),
+            jitter_ms: row.get("jitter_ms"),
+            via_vpn: row.get("via_vpn"),
+            via_proxy: row.get("via_proxy"),
+            network_context: row.get("network_context"),
+            method: MeasurementMethod::from_string(row.get::<String, _>("method").as_str()),
+            upload_measured: row.get("upload_measured"),
+        }).collect();
+
+        Ok(measurements)
+    }
+
+    /// Page `page` (zero-based) of the measurements matching `filter`, newest first, with the
+    /// total match count. `page_size` is clamped to 1..=`MAX_HISTORY_PAGE_SIZE`.
+    pub async fn get_speed_measurements_paged(&self, filter: &MeasurementFilter, page: u32, page_size: u32) -> Result<SpeedMeasurementPage> {
+        let page_size = page_size.clamp(1, MAX_HISTORY_PAGE_SIZE);
+        let source = filter.source.map(|s| s.as_str());
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM speed_measurements
+            WHERE (? IS NULL OR timestamp >= ?)
+              AND (? IS NULL OR timestamp <= ?)
+              AND (? IS NULL OR optimization_active = ?)
+              AND (? IS NULL OR source = ?)
+            "#
+        )
+        .bind(filter.since)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.until)
+        .bind(filter.optimization_active)
+        .bind(filter.optimization_active)
+        .bind(source)
+        .bind(source)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, timestamp, download_mbps, upload_mbps, latency_ms, optimization_active, confidence, source, wifi_rssi_dbm, wifi_link_mbps,
+                   packet_loss_pct, jitter_ms, via_vpn, via_proxy, network_context, method, upload_measured
+            FROM speed_measurements
+            WHERE (? IS NULL OR timestamp >= ?)
+              AND (? IS NULL OR timestamp <= ?)
+              AND (? IS NULL OR optimization_active = ?)
+              AND (? IS NULL OR source = ?)
+            ORDER BY timestamp DESC, id DESC
+            LIMIT ? OFFSET ?
+            "#
+        )
+        .bind(filter.since)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.until)
+        .bind(filter.optimization_active)
+        .bind(filter.optimization_active)
+        .bind(source)
+        .bind(source)
+        .bind(page_size)
+        .bind(page as i64 * page_size as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let items = rows.into_iter().map(|row| SpeedMeasurement {
+            id: row.get("id"),
+            timestamp: row.get("timestamp"),
+            download_mbps: row.get("download_mbps"),
+            upload_mbps: row.get("upload_mbps"),
+            latency_ms: row.get("latency_ms"),
+            optimization_active: row.get("optimization_active"),
+            confidence: row.get("confidence"),
+            source: MeasurementSource::from_string(row.get::<String, _>("source").as_str()),
+            wifi_rssi_dbm: row.get("wifi_rssi_dbm"),
+            wifi_link_mbps: row.get("wifi_link_mbps"),
+            packet_loss_pct: row.get("packet_loss_pct"),
+            jitter_ms: row.get("jitter_ms"),
+            via_vpn: row.get("via_vpn"),
+            via_proxy: row.get("via_proxy"),
+            network_context: row.get("network_context"),
+            method: MeasurementMethod::from_string(row.get::<String, _>("method").as_str()),
+            upload_measured: row.get("upload_measured"),
+        }).collect();
+
+        Ok(SpeedMeasurementPage { items, page, page_size, total: total as u64 })
+    }
+
+    /// Inserts a new alert episode, or updates it in place when it already has an id
+    pub async fn save_speed_alert_episode(&self, episode: &SpeedAlertEpisode) -> Result<i64> {
+        if let Some(id) = episode.id {
+            sqlx::query(
+                r#"
+                UPDATE speed_alert_episodes
+                SET ended_at = ?, min_download_mbps = ?, avg_download_mbps = ?, sample_count = ?
+                WHERE id = ?
+                "#
+            )
+            .bind(episode.ended_at)
+            .bind(episode.min_download_mbps)
+            .bind(episode.avg_download_mbps)
+            .bind(episode.sample_count)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+            return Ok(id);
+        }
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO speed_alert_episodes (started_at, ended_at, threshold_mbps, sustained_minutes,
+                                              min_download_mbps, avg_download_mbps, sample_count)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(episode.started_at)
+        .bind(episode.ended_at)
+        .bind(episode.threshold_mbps)
+        .bind(episode.sustained_minutes)
+        .bind(episode.min_download_mbps)
+        .bind(episode.avg_download_mbps)
+        .bind(episode.sample_count)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Alert episodes that started since the given time, newest first
+    pub async fn get_speed_alert_episodes_since(&self, since: DateTime<Utc>) -> Result<Vec<SpeedAlertEpisode>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, started_at, ended_at, threshold_mbps, sustained_minutes,
+                   min_download_mbps, avg_download_mbps, sample_count
+            FROM speed_alert_episodes
+            WHERE started_at >= ?
+            ORDER BY started_at DESC
+            "#
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let episodes = rows.into_iter().map(|row| SpeedAlertEpisode {
+            id: row.get("id"),
+            started_at: row.get("started_at"),
+            ended_at: row.get("ended_at"),
+            threshold_mbps: row.get("threshold_mbps"),
+            sustained_minutes: row.get("sustained_minutes"),
+            min_download_mbps: row.get("min_download_mbps"),
+            avg_download_mbps: row.get("avg_download_mbps"),
+            sample_count: row.get("sample_count"),
+        }).collect();
+
+        Ok(episodes)
+    }
+
+    pub async fn save_satisfaction_feedback(&self, feedback: &SatisfactionFeedback) -> Result<i64> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO satisfaction_feedback (timestamp, satisfied, optimization_active, strategy_name)
+            VALUES (?, ?, ?, ?)
+            "#
+        )
+        .bind(feedback.timestamp)
+        .bind(feedback.satisfied)
+        .bind(feedback.optimization_active)
+        .bind(&feedback.strategy_name)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// Newest first
+    pub async fn get_satisfaction_feedback_since(&self, since: DateTime<Utc>) -> Result<Vec<SatisfactionFeedback>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, timestamp, satisfied, optimization_active, strategy_name
+            FROM satisfaction_feedback
+            WHERE timestamp >= ?
+            ORDER BY timestamp DESC
+            "#
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let feedback = rows.into_iter().map(|row| SatisfactionFeedback {
+            id: row.get("id"),
+            timestamp: row.get("timestamp"),
+            satisfied: row.get("satisfied"),
+            optimization_active: row.get("optimization_active"),
+            strategy_name: row.get("strategy_name"),
+        }).collect();
+
+        Ok(feedback)
+    }
+
+    pub async fn save_optimization_trial(&self, trial: &OptimizationTrial) -> Result<i64> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO optimization_trials (started_at, ended_at, strategy_name, before_mbps, during_mbps, after_mbps, status, note, transport)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(trial.started_at)
+        .bind(trial.ended_at)
+        .bind(&trial.strategy_name)
+        .bind(trial.before_mbps)
+        .bind(trial.during_mbps)
+        .bind(trial.after_mbps)
+        .bind(trial.status.as_str())
+        .bind(&trial.note)
+        .bind(trial.transport.map(|t| t.as_str()))
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// Newest first
+    pub async fn get_optimization_trials_since(&self, since: DateTime<Utc>) -> Result<Vec<OptimizationTrial>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, started_at, ended_at, strategy_name, before_mbps, during_mbps, after_mbps, status, note, transport
+            FROM optimization_trials
+            WHERE started_at >= ?
+            ORDER BY started_at DESC
+            "#
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let trials = rows.into_iter().map(|row| OptimizationTrial {
+            id: row.get("id"),
+            started_at: row.get("started_at"),
+            ended_at: row.get("ended_at"),
+            strategy_name: row.get("strategy_name"),
+            before_mbps: row.get("before_mbps"),
+            during_mbps: row.get("during_mbps"),
+            after_mbps: row.get("after_mbps"),
+            status: TrialStatus::from_string(row.get::<String, _>("status").as_str()),
+            note: row.get("note"),
+            transport: row.get::<Option<String>, _>("transport").as_deref().and_then(TrafficTransport::from_string),
+        }).collect();
+
+        Ok(trials)
+    }
+
+    pub async fn save_route_snapshot(&self, snapshot: &RouteSnapshot) -> Result<i64> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO route_snapshots (taken_at, anchor, hops, path_signature, isp_name, isp_asn)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(snapshot.taken_at)
+        .bind(&snapshot.anchor)
+        .bind(serde_json::to_string(&snapshot.hops)?)
+        .bind(snapshot.path_signature())
+        .bind(&snapshot.isp_name)
+        .bind(snapshot.isp_asn.map(i64::from))
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// Oldest first
+    pub async fn get_route_snapshots_since(&self, since: DateTime<Utc>) -> Result<Vec<RouteSnapshot>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, taken_at, anchor, hops, isp_name, isp_asn
+            FROM route_snapshots
+            WHERE taken_at >= ?
+            ORDER BY taken_at ASC
+            "#
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let snapshots = rows.into_iter().map(|row| RouteSnapshot {
+            id: row.get("id"),
+            taken_at: row.get("taken_at"),
+            anchor: row.get("anchor"),
+            hops: serde_json::from_str(row.get::<String, _>("hops").as_str()).unwrap_or_default(),
+            isp_name: row.get("isp_name"),
+            isp_asn: row.get::<Option<i64>, _>("isp_asn").map(|a| a as u32),
+        }).collect();
+
+        Ok(snapshots)
+    }
+
+    pub async fn save_bufferbloat_test(&self, test: &BufferbloatTest) -> Result<i64> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO bufferbloat_tests (tested_at, idle_latency_ms, loaded_latency_ms, load_mbps)
+            VALUES (?, ?, ?, ?)
+            "#
+        )
+        .bind(test.tested_at)
+        .bind(test.idle_latency_ms)
+        .bind(test.loaded_latency_ms)
+        .bind(test.load_mbps)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// Adds `bytes` to the running total of `source` on `day`
+    pub async fn add_traffic_usage(&self, day: NaiveDate, source: TrafficSource, bytes: u64) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO traffic_usage (day, source, bytes) VALUES (?, ?, ?)
+            ON CONFLICT(day, source) DO UPDATE SET bytes = bytes + excluded.bytes
+            "#
+        )
+        .bind(day)
+        .bind(source.as_str())
+        .bind(bytes as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Usage from `since` (inclusive) on, oldest day first
+    pub async fn get_traffic_usage_since(&self, since: NaiveDate) -> Result<Vec<TrafficUsage>> {
+        let rows = sqlx::query("SELECT day, source, bytes FROM traffic_usage WHERE day >= ? ORDER BY day ASC, source ASC")
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().filter_map(|row| {
+            Some(TrafficUsage {
+                day: row.get("day"),
+                source: TrafficSource::from_string(row.get::<String, _>("source").as_str())?,
+                bytes: row.get::<i64, _>("bytes").max(0) as u64,
+            })
+        }).collect())
+    }
+
+    /// Oldest first
+    pub async fn get_bufferbloat_tests_since(&self, since: DateTime<Utc>) -> Result<Vec<BufferbloatTest>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, tested_at, idle_latency_ms, loaded_latency_ms, load_mbps
+            FROM bufferbloat_tests
+            WHERE tested_at >= ?
+            ORDER BY tested_at ASC
+            "#
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let tests = rows.into_iter().map(|row| BufferbloatTest {
+            id: row.get("id"),
+            tested_at: row.get("tested_at"),
+            idle_latency_ms: row.get("idle_latency_ms"),
+            loaded_latency_ms: row.get("loaded_latency_ms"),
+            load_mbps: row.get("load_mbps"),
+        }).collect();
+
+        Ok(tests)
+    }
+
+    pub async fn save_strategy_decision(&self, decision: &StrategyDecision) -> Result<i64> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO decisions (created_at, strategy_id, strategy_name, previous_strategy_id, previous_strategy_name, window_ends_at, baseline_mbps, canary_mbps, outcome, resolved_at, note)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(decision.created_at)
+        .bind(decision.strategy_id)
+        .bind(&decision.strategy_name)
+        .bind(decision.previous_strategy_id)
+        .bind(&decision.previous_strategy_name)
+        .bind(decision.window_ends_at)
+        .bind(decision.baseline_mbps)
+        .bind(decision.canary_mbps)
+        .bind(decision.outcome.as_str())
+        .bind(decision.resolved_at)
+        .bind(&decision.note)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// Records the canary result of a decision saved earlier
+    pub async fn update_strategy_decision(&self, decision: &StrategyDecision) -> Result<()> {
+        sqlx::query("UPDATE decisions SET baseline_mbps = ?, canary_mbps = ?, outcome = ?, resolved_at = ?, note = ? WHERE id = ?")
+            .bind(decision.baseline_mbps)
+            .bind(decision.canary_mbps)
+            .bind(decision.outcome.as_str())
+            .bind(decision.resolved_at)
+            .bind(&decision.note)
+            .bind(decision.id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Newest first; pending decisions are included however old
+    pub async fn get_strategy_decisions_since(&self, since: DateTime<Utc>) -> Result<Vec<StrategyDecision>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, created_at, strategy_id, strategy_name, previous_strategy_id, previous_strategy_name, window_ends_at, baseline_mbps, canary_mbps, outcome, resolved_at, note
+            FROM decisions
+            WHERE created_at >= ? OR outcome = 'pending'
+            ORDER BY created_at DESC
+            "#
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let decisions = rows.into_iter().map(|row| StrategyDecision {
+            id: row.get("id"),
+            created_at: row.get("created_at"),
+            strategy_id: row.get("strategy_id"),
+            strategy_name: row.get("strategy_name"),
+            previous_strategy_id: row.get("previous_strategy_id"),
+            previous_strategy_name: row.get("previous_strategy_name"),
+            window_ends_at: row.get("window_ends_at"),
+            baseline_mbps: row.get("baseline_mbps"),
+            canary_mbps: row.get("canary_mbps"),
+            outcome: CanaryOutcome::from_string(row.get::<String, _>("outcome").as_str()),
+            resolved_at: row.get("resolved_at"),
+            note: row.get("note"),
+        }).collect();
+
+        Ok(decisions)
+    }
+
+    /// Highest applied migration version, 0 on a fresh database
+    pub async fn schema_version(&self) -> Result<i32> {
+        let version: Option<i32> = sqlx::query_scalar("SELECT MAX(version) FROM schema_migrations")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(version.unwrap_or(0))
+    }
+
+    /// Row counts per table and the database size
+    pub async fn get_database_stats(&self) -> Result<DatabaseStats> {
+        let tables: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+            .fetch_all(&self.pool)
+            .await?;
+        let mut table_rows = std::collections::BTreeMap::new();
+        for table in tables {
+            // Names come from sqlite_master, so quoting is all they need
+            let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\"")))
+                .fetch_one(&self.pool)
+                .await?;
+            table_rows.insert(table, count);
+        }
+        let page_count: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(&self.pool).await?;
+        let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(&self.pool).await?;
+        Ok(DatabaseStats { schema_version: self.schema_version().await?, size_bytes: page_count * page_size, table_rows })
+    }
+
+    /// Stores a day's backtest, replacing an earlier run for the same day
+    pub async fn save_model_quality_metric(&self, metric: &ModelQualityMetric) -> Result<i64> {
+        let id = sqlx::query(
             r#"
-            INSERT INTO throttling_patterns (isp_profile_id, start_hour, start_minute, end_hour, end_minute, days_of_week, severity, confidence)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT OR REPLACE INTO model_quality_metrics (evaluated_date, computed_at, hours_evaluated,
+                true_positives, false_positives, false_negatives, true_negatives,
+                precision, recall, f1, training_samples)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
-        .bind(pattern.isp_profile_id)
-        .bind(pattern.start_hour)
-        .bind(pattern.start_minute)
-        .bind(pattern.end_hour)
-        .bind(pattern.end_minute)
-        .bind(&pattern.days_of_week_json())
-        .bind(pattern.severity)
-        .bind(pattern.confidence)
+        .bind(metric.evaluated_date)
+        .bind(metric.computed_at)
+        .bind(metric.hours_evaluated)
+        .bind(metric.true_positives)
+        .bind(metric.false_positives)
+        .bind(metric.false_negatives)
+        .bind(metric.true_negatives)
+        .bind(metric.precision)
+        .bind(metric.recall)
+        .bind(metric.f1)
+        .bind(metric.training_samples)
         .execute(&self.pool)
-        .await?;
-        
-        Ok(result.last_insert_rowid())
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
     }
-    
-    pub async fn get_throttling_patterns_for_isp(&self, isp_profile_id: i64) -> Result<Vec<ThrottlingPattern>> {
+
+    /// Backtests for days on or after `since`, oldest first
+    pub async fn get_model_quality_metrics_since(&self, since: NaiveDate) -> Result<Vec<ModelQualityMetric>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, isp_profile_id, start_hour, start_minute, end_hour, end_minute, days_of_week, severity, confidence
-            FROM throttling_patterns
-            WHERE isp_profile_id = ?
-            ORDER BY confidence DESC
+            SELECT id, evaluated_date, computed_at, hours_evaluated, true_positives, false_positives,
+                   false_negatives, true_negatives, precision, recall, f1, training_samples
+            FROM model_quality_metrics
+            WHERE evaluated_date >= ?
+            ORDER BY evaluated_date ASC
             "#
         )
-        .bind(isp_profile_id)
+        .bind(since)
         .fetch_all(&self.pool)
         .await?;
-        
-        let patterns = rows.into_iter().map(|row| {
-            ThrottlingPattern::from_db_row(
-                row.get("id"),
-                row.get("isp_profile_id"),
-                row.get("start_hour"),
-                row.get("start_minute"),
-                row.get("end_hour"),
-                row.get("end_minute"),
-                &row.get::<String, _>("days_of_week"),
-                row.get("severity"),
-                row.get("confidence"),
-            )
+
+        let metrics = rows.into_iter().map(|row| ModelQualityMetric {
+            id: row.get("id"),
+            evaluated_date: row.get("evaluated_date"),
+            computed_at: row.get("computed_at"),
+            hours_evaluated: row.get("hours_evaluated"),
+            true_positives: row.get("true_positives"),
+            false_positives: row.get("false_positives"),
+            false_negatives: row.get("false_negatives"),
+            true_negatives: row.get("true_negatives"),
+            precision: row.get("precision"),
+            recall: row.get("recall"),
+            f1: row.get("f1"),
+            training_samples: row.get("training_samples"),
         }).collect();
-        
-        Ok(patterns)
+
+        Ok(metrics)
     }
-    
-    /// Optimization strategy operations
-    pub async fn save_optimization_strategy(&self, strategy: &OptimizationStrategy) -> Result<i64> {
-        let result = sqlx::query(
+
+    /// Stores the correction for a network, replacing the previous one
+    pub async fn save_passive_calibration(&self, calibration: &PassiveCalibration) -> Result<()> {
+        sqlx::query(
             r#"
-            INSERT INTO optimization_strategies (name, server_rotation_interval_minutes, packet_timing_min_seconds, packet_timing_max_seconds, connection_count, traffic_intensity, stealth_level, effectiveness_score, created_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT OR REPLACE INTO passive_calibrations (network_context, calibrated_at, factor, confidence_scale,
+                calibrations, last_active_mbps, last_passive_mbps)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
             "#
         )
-        .bind(&strategy.name)
-        .bind(strategy.server_rotation_interval_minutes)
-        .bind(strategy.packet_timing_min_seconds)
-        .bind(strategy.packet_timing_max_seconds)
-        .bind(strategy.connection_count)
-        .bind(strategy.traffic_intensity)
-        .bind(&strategy.stealth_level.to_string())
-        .bind(strategy.effectiveness_score)
-        .bind(&strategy.created_at)
+        .bind(&calibration.network_context)
+        .bind(calibration.calibrated_at)
+        .bind(calibration.factor)
+        .bind(calibration.confidence_scale)
+        .bind(calibration.calibrations)
+        .bind(calibration.last_active_mbps)
+        .bind(calibration.last_passive_mbps)
         .execute(&self.pool)
         .await?;
-        
-        Ok(result.last_insert_rowid())
+        Ok(())
     }
-    
-    pub async fn get_best_optimization_strategy(&self) -> Result<Option<OptimizationStrategy>> {
-        let row = sqlx::query(
-            r#"
-            SELECT id, name, server_rotation_interval_minutes, packet_timing_min_seconds, packet_timing_max_seconds, connection_count, traffic_intensity, stealth_level, effectiveness_score, created_at
-            FROM optimization_strategies
-            WHERE effectiveness_score IS NOT NULL
-            ORDER BY effectiveness_score DESC
-            LIMIT 1
-            "#
-        )
-        .fetch_optional(&self.pool)
-        .await?;
-        
-        let strategy = row.map(|r| OptimizationStrategy {
-            id: r.get("id"),
-            name: r.get("name"),
-            server_rotation_interval_minutes: r.get("server_rotation_interval_minutes"),
-            packet_timing_min_seconds: r.get("packet_timing_min_seconds"),
-            packet_timing_max_seconds: r.get("packet_timing_max_seconds"),
-            connection_count: r.get("connection_count"),
-            traffic_intensity: r.get("traffic_intensity"),
-            stealth_level: StealthLevel::from_string(&r.get::<String, _>("stealth_level")),
-            effectiveness_score: r.get("effectiveness_score"),
-            created_at: r.get("created_at"),
-        });
-        
-        Ok(strategy)
+
+    pub async fn get_passive_calibration(&self, network_context: &str) -> Result<Option<PassiveCalibration>> {
+        let row = sqlx::query("SELECT * FROM passive_calibrations WHERE network_context = ?")
+            .bind(network_context)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.as_ref().map(Self::passive_calibration_from_row))
     }
-    
-    /// Cleanup old data (privacy-focused approach)
-    pub async fn cleanup_old_data(&self, days_to_keep: u32) -> Result<()> {
-        let cutoff_date = Utc::now() - chrono::Duration::days(days_to_keep as i64);
-        
-        sqlx::query("DELETE FROM speed_measurements WHERE timestamp < ?")
-            .bind(cutoff_date)
-            .execute(&self.pool)
+
+    /// Every network's correction, most recently calibrated first
+    pub async fn get_passive_calibrations(&self) -> Result<Vec<PassiveCalibration>> {
+        let rows = sqlx::query("SELECT * FROM passive_calibrations ORDER BY calibrated_at DESC")
+            .fetch_all(&self.pool)
             .await?;
-        
+        Ok(rows.iter().map(Self::passive_calibration_from_row).collect())
+    }
+
+    fn passive_calibration_from_row(row: &sqlx::sqlite::SqliteRow) -> PassiveCalibration {
+        PassiveCalibration {
+            network_context: row.get("network_context"),
+            calibrated_at: row.get("calibrated_at"),
+            factor: row.get("factor"),
+            confidence_scale: row.get("confidence_scale"),
+            calibrations: row.get("calibrations"),
+            last_active_mbps: row.get("last_active_mbps"),
+            last_passive_mbps: row.get("last_passive_mbps"),
+        }
+    }
+
+    /// Replaces the stored retry queue with `retries`
+    pub async fn replace_speedtest_retries(&self, retries: &[PendingRetry]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM speedtest_retries").execute(&mut *tx).await?;
+        for retry in retries {
+            sqlx::query("INSERT INTO speedtest_retries (due, attempt, reason) VALUES (?, ?, ?)")
+                .bind(retry.due)
+                .bind(retry.attempt)
+                .bind(&retry.reason)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
         Ok(())
     }
 
+    /// Queued retries, earliest first
+    pub async fn get_speedtest_retries(&self) -> Result<Vec<PendingRetry>> {
+        let rows = sqlx::query("SELECT due, attempt, reason FROM speedtest_retries ORDER BY due")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| PendingRetry { due: row.get("due"), attempt: row.get("attempt"), reason: row.get("reason") }).collect())
+    }
+
     // Speedtest Server operations
+    /// Inserts a server, or overwrites the stored row with the same `server_id`
     pub async fn save_speedtest_server(&self, server: &SpeedtestServer) -> Result<i64> {
-        let id = sqlx::query(
+        let id: i64 = sqlx::query_scalar(
             r#"
             INSERT INTO speedtest_servers (
                 server_id, host, port, name, country, sponsor, 
-                distance, latency, is_active, last_used
+                distance, latency, is_active, last_used,
+                provider, latency_path, download_path, upload_path,
+                latitude, longitude, use_count, request_failure_pct, probed_at, tls
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(server_id) DO UPDATE SET
+                host = excluded.host,
+                port = excluded.port,
+                name = excluded.name,
+                country = excluded.country,
+                sponsor = excluded.sponsor,
+                distance = excluded.distance,
+                latency = excluded.latency,
+                is_active = excluded.is_active,
+                last_used = excluded.last_used,
+                provider = excluded.provider,
+                latency_path = excluded.latency_path,
+                download_path = excluded.download_path,
+                upload_path = excluded.upload_path,
+                latitude = excluded.latitude,
+                longitude = excluded.longitude,
+                use_count = excluded.use_count,
+                request_failure_pct = excluded.request_failure_pct,
+                probed_at = excluded.probed_at,
+                tls = excluded.tls
+            RETURNING id
             "#
         )
         .bind(&server.server_id)
@@ -241,35 +1274,106 @@ impl Repository {
         .bind(server.latency)
         .bind(server.is_active)
         .bind(server.last_used)
-        .execute(&self.pool)
-        .await?
-        .last_insert_rowid();
+        .bind(server.provider.as_str())
+        .bind(&server.paths.latency)
+        .bind(&server.paths.download)
+        .bind(&server.paths.upload)
+        .bind(server.latitude)
+        .bind(server.longitude)
+        .bind(server.use_count)
+        .bind(server.request_failure_pct)
+        .bind(server.probed_at)
+        .bind(server.tls)
+        .fetch_one(&self.pool)
+        .await?;
         
         Ok(id)
     }
 
+    /// Upserts servers by `server_id`, refreshing directory fields while keeping
+    /// distance, latency, is_active, last_used and use_count as recorded locally
+    pub async fn merge_speedtest_servers(&self, servers: &[SpeedtestServer]) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        for server in servers {
+            sqlx::query(
+                r#"
+                INSERT INTO speedtest_servers (
+                    server_id, host, port, name, country, sponsor,
+                    distance, latency, is_active, last_used,
+                    provider, latency_path, download_path, upload_path,
+                    latitude, longitude, tls
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(server_id) DO UPDATE SET
+                    host = excluded.host,
+                    port = excluded.port,
+                    name = excluded.name,
+                    country = excluded.country,
+                    sponsor = excluded.sponsor,
+                    provider = excluded.provider,
+                    latency_path = excluded.latency_path,
+                    download_path = excluded.download_path,
+                    upload_path = excluded.upload_path,
+                    latitude = COALESCE(excluded.latitude, latitude),
+                    longitude = COALESCE(excluded.longitude, longitude),
+                    tls = excluded.tls
+                "#
+            )
+            .bind(&server.server_id)
+            .bind(&server.host)
+            .bind(server.port)
+            .bind(&server.name)
+            .bind(&server.country)
+            .bind(&server.sponsor)
+            .bind(server.distance)
+            .bind(server.latency)
+            .bind(server.is_active)
+            .bind(server.last_used)
+            .bind(server.provider.as_str())
+            .bind(&server.paths.latency)
+            .bind(&server.paths.download)
+            .bind(&server.paths.upload)
+            .bind(server.latitude)
+            .bind(server.longitude)
+            .bind(server.tls)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(servers.len())
+    }
+
+    /// Makes the stored user-defined servers match `servers`: ones no longer listed are
+    /// deleted, the rest upserted with their local stats kept
+    pub async fn replace_custom_speedtest_servers(&self, servers: &[SpeedtestServer]) -> Result<usize> {
+        let stored: Vec<String> = sqlx::query_scalar(
+            "SELECT server_id FROM speedtest_servers WHERE substr(server_id, 1, length(?)) = ?"
+        )
+        .bind(CUSTOM_SERVER_ID_PREFIX)
+        .bind(CUSTOM_SERVER_ID_PREFIX)
+        .fetch_all(&self.pool)
+        .await?;
+        for server_id in stored.iter().filter(|id| !servers.iter().any(|s| &s.server_id == *id)) {
+            sqlx::query("DELETE FROM speedtest_servers WHERE server_id = ?")
+                .bind(server_id)
+                .execute(&self.pool)
+                .await?;
+        }
+        self.merge_speedtest_servers(servers).await
+    }
+
+    /// Active servers, user-defined ones first so they override the directory
     pub async fn get_active_speedtest_servers(&self) -> Result<Vec<SpeedtestServer>> {
         let rows = sqlx::query(
-            "SELECT * FROM speedtest_servers WHERE is_active = 1 ORDER BY country, name"
+            "SELECT * FROM speedtest_servers WHERE is_active = 1
+             ORDER BY substr(server_id, 1, length(?)) = ? DESC, country, name"
         )
+        .bind(CUSTOM_SERVER_ID_PREFIX)
+        .bind(CUSTOM_SERVER_ID_PREFIX)
         .fetch_all(&self.pool)
         .await?;
         
-        let servers = rows.into_iter().map(|row| {
-            SpeedtestServer {
-                id: row.get("id"),
-                server_id: row.get("server_id"),
-                host: row.get("host"),
-                port: row.get("port"),
-                name: row.get("name"),
-                country: row.get("country"),
-                sponsor: row.get("sponsor"),
-                distance: row.get("distance"),
-                latency: row.get("latency"),
-                is_active: row.get("is_active"),
-                last_used: row.get("last_used"),
-            }
-        }).collect();
+        let servers = rows.iter().map(Self::speedtest_server_from_row).collect();
         
         Ok(servers)
     }
@@ -282,28 +1386,47 @@ impl Repository {
         .fetch_all(&self.pool)
         .await?;
         
-        let servers = rows.into_iter().map(|row| {
-            SpeedtestServer {
-                id: row.get("id"),
-                server_id: row.get("server_id"),
-                host: row.get("host"),
-                port: row.get("port"),
-                name: row.get("name"),
-                country: row.get("country"),
-                sponsor: row.get("sponsor"),
-                distance: row.get("distance"),
-                latency: row.get("latency"),
-                is_active: row.get("is_active"),
-                last_used: row.get("last_used"),
-            }
-        }).collect();
+        let servers = rows.iter().map(Self::speedtest_server_from_row).collect();
         
         Ok(servers)
     }
 
+    fn speedtest_server_from_row(row: &sqlx::sqlite::SqliteRow) -> SpeedtestServer {
+        let provider = ServerProvider::from_string(&row.get::<String, _>("provider"));
+        // Rows written before path templates existed fall back to the provider's stock layout
+        let stock = ServerPathTemplates::for_provider(provider);
+        let path = |column: &str, fallback: String| row.get::<Option<String>, _>(column).unwrap_or(fallback);
+        SpeedtestServer {
+            id: row.get("id"),
+            server_id: row.get("server_id"),
+            host: row.get("host"),
+            port: row.get("port"),
+            name: row.get("name"),
+            country: row.get("country"),
+            sponsor: row.get("sponsor"),
+            distance: row.get("distance"),
+            latency: row.get("latency"),
+            is_active: row.get("is_active"),
+            last_used: row.get("last_used"),
+            latitude: row.get("latitude"),
+            longitude: row.get("longitude"),
+            use_count: row.get("use_count"),
+            request_failure_pct: row.get("request_failure_pct"),
+            probed_at: row.get("probed_at"),
+            tls: row.get("tls"),
+            provider,
+            paths: ServerPathTemplates {
+                latency: path("latency_path", stock.latency),
+                download: path("download_path", stock.download),
+                upload: path("upload_path", stock.upload),
+            },
+        }
+    }
+
+    /// Marks a server as used now and counts the use
     pub async fn update_server_last_used(&self, server_id: &str) -> Result<()> {
         sqlx::query(
-            "UPDATE speedtest_servers SET last_used = ? WHERE server_id = ?"
+            "UPDATE speedtest_servers SET last_used = ?, use_count = use_count + 1 WHERE server_id = ?"
         )
         .bind(Utc::now())
         .bind(server_id)
@@ -313,6 +1436,23 @@ impl Repository {
         Ok(())
     }
 
+    /// Stores one probe round's outcome without touching the directory fields or usage stats;
+    /// an unanswered round keeps the last known latency
+    pub async fn record_server_probe(&self, server_id: &str, latency: Option<f64>, request_failure_pct: f64, probed_at: DateTime<Utc>, is_active: bool) -> Result<()> {
+        sqlx::query(
+            "UPDATE speedtest_servers SET latency = COALESCE(?, latency), request_failure_pct = ?, probed_at = ?, is_active = ? WHERE server_id = ?"
+        )
+        .bind(latency)
+        .bind(request_failure_pct)
+        .bind(probed_at)
+        .bind(is_active)
+        .bind(server_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     // App Configuration operations
     pub async fn save_app_config(&self, config: &AppConfig) -> Result<i64> {
         // First, check if config already exists
@@ -468,13 +1608,20 @@ impl Repository {
         sqlx::query("DELETE FROM optimization_strategies").execute(&self.pool).await?;
         sqlx::query("DELETE FROM speedtest_servers").execute(&self.pool).await?;
         sqlx::query("DELETE FROM isp_profiles").execute(&self.pool).await?;
-        // Keep app_config so app can retain preferences; do not delete schema_migrations
+        sqlx::query("DELETE FROM model_quality_metrics").execute(&self.pool).await?;
+        sqlx::query("DELETE FROM route_snapshots").execute(&self.pool).await?;
+        sqlx::query("DELETE FROM decisions").execute(&self.pool).await?;
+        sqlx::query("DELETE FROM bufferbloat_tests").execute(&self.pool).await?;
+        sqlx::query("DELETE FROM passive_calibrations").execute(&self.pool).await?;
+        sqlx::query("DELETE FROM speedtest_retries").execute(&self.pool).await?;
+        // Keep app_config so app can retain preferences, and traffic_usage so the data cap still holds;
+        // do not delete schema_migrations
         Ok(())
     }
 }
 
 /// Speed statistics for analytics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SpeedStatistics {
     pub total_measurements: i64,
     pub avg_download_mbps: f64,
@@ -487,6 +1634,14 @@ pub struct SpeedStatistics {
     pub improvement_factor: Option<f64>,
 }
 
+/// Size of the database and of each table, for diagnostics; no row contents
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseStats {
+    pub schema_version: i32,
+    pub size_bytes: i64,
+    pub table_rows: std::collections::BTreeMap<String, i64>,
+}
+
 impl SpeedStatistics {
     /// Calculate the improvement factor if both optimized and baseline data exist
     pub fn calculate_improvement_factor(&mut self) {
@@ -529,7 +1684,210 @@ mod tests {
         let since = Utc::now() - chrono::Duration::hours(1);
         let measurements = repo.get_speed_measurements_since(since).await.unwrap();
         assert_eq!(measurements.len(), 1);
-        assert_eq!(measurements[0].download_mbps, 50.0);
+        assert_eq!((measurements[0].download_mbps, measurements[0].latency_ms), (50.0, Some(25)));
+
+        // An unmeasured latency comes back as unmeasured, not 0 ms
+        repo.save_speed_measurement(&SpeedMeasurement { latency_ms: None, ..SpeedMeasurement::new(40.0, 8.0, 0, false) }).await.unwrap();
+        let measurements = repo.get_speed_measurements_since(since).await.unwrap();
+        assert!(measurements.iter().any(|m| m.download_mbps == 40.0 && m.latency_ms.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_speed_measurements_paged_newest_first() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+        let start = Utc::now() - chrono::Duration::hours(2);
+        for i in 0..25 {
+            let mut m = SpeedMeasurement::new(i as f64, 5.0, 20, i % 2 == 0);
+            m.timestamp = start + chrono::Duration::minutes(i);
+            if i % 5 == 0 {
+                m = m.with_source(MeasurementSource::Active);
+            }
+            repo.save_speed_measurement(&m).await.unwrap();
+        }
+
+        let all = MeasurementFilter::default();
+        let first = repo.get_speed_measurements_paged(&all, 0, 10).await.unwrap();
+        assert_eq!((first.total, first.items.len()), (25, 10));
+        assert_eq!(first.items[0].download_mbps, 24.0);
+        let last = repo.get_speed_measurements_paged(&all, 2, 10).await.unwrap();
+        assert_eq!(last.items.iter().map(|m| m.download_mbps).collect::<Vec<_>>(), vec![4.0, 3.0, 2.0, 1.0, 0.0]);
+        assert!(repo.get_speed_measurements_paged(&all, 3, 10).await.unwrap().items.is_empty());
+        assert_eq!(repo.get_speed_measurements_paged(&all, 0, 100_000).await.unwrap().page_size, MAX_HISTORY_PAGE_SIZE);
+
+        let active = MeasurementFilter { source: Some(MeasurementSource::Active), optimization_active: Some(true), ..Default::default() };
+        let page = repo.get_speed_measurements_paged(&active, 0, 10).await.unwrap();
+        assert_eq!(page.items.iter().map(|m| m.download_mbps).collect::<Vec<_>>(), vec![20.0, 10.0, 0.0]);
+        assert_eq!(page.total, 3);
+    }
+
+    #[tokio::test]
+    async fn test_speed_alert_episode_lifecycle() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+
+        let mut episode = SpeedAlertEpisode {
+            id: None,
+            started_at: Utc::now() - chrono::Duration::minutes(15),
+            ended_at: None,
+            threshold_mbps: 5.0,
+            sustained_minutes: 10,
+            min_download_mbps: 2.0,
+            avg_download_mbps: 3.0,
+            sample_count: 3,
+        };
+        episode.id = Some(repo.save_speed_alert_episode(&episode).await.unwrap());
+
+        episode.ended_at = Some(Utc::now());
+        episode.sample_count = 4;
+        repo.save_speed_alert_episode(&episode).await.unwrap();
+
+        let episodes = repo.get_speed_alert_episodes_since(Utc::now() - chrono::Duration::hours(1)).await.unwrap();
+        assert_eq!(episodes.len(), 1);
+        assert!(episodes[0].ended_at.is_some());
+        assert_eq!(episodes[0].sample_count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_satisfaction_feedback_roundtrip() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+
+        repo.save_satisfaction_feedback(&SatisfactionFeedback::new(true, true, Some("Default".to_string()))).await.unwrap();
+        let mut old = SatisfactionFeedback::new(false, false, None);
+        old.timestamp = Utc::now() - chrono::Duration::days(30);
+        repo.save_satisfaction_feedback(&old).await.unwrap();
+
+        let feedback = repo.get_satisfaction_feedback_since(Utc::now() - chrono::Duration::days(14)).await.unwrap();
+        assert_eq!(feedback.len(), 1);
+        assert!(feedback[0].satisfied);
+        assert_eq!(feedback[0].strategy_name.as_deref(), Some("Default"));
+    }
+
+    #[tokio::test]
+    async fn test_optimization_trial_roundtrip() {
+        let repo = Repository::new(setup_test_db().await);
+        let started = Utc::now() - chrono::Duration::hours(2);
+        let trial = OptimizationTrial {
+            id: None,
+            started_at: started,
+            ended_at: started + chrono::Duration::hours(1),
+            strategy_name: Some("Default".to_string()),
+            before_mbps: Some(10.0),
+            during_mbps: Some(15.0),
+            after_mbps: None,
+            status: TrialStatus::Completed,
+            note: None,
+            transport: Some(TrafficTransport::Quic),
+        };
+        repo.save_optimization_trial(&trial).await.unwrap();
+
+        let trials = repo.get_optimization_trials_since(Utc::now() - chrono::Duration::days(1)).await.unwrap();
+        assert_eq!(trials.len(), 1);
+        assert_eq!(trials[0].status, TrialStatus::Completed);
+        assert_eq!(trials[0].after_mbps, None);
+        assert_eq!(trials[0].improvement(), Some(1.5));
+        assert_eq!(trials[0].transport, Some(TrafficTransport::Quic));
+    }
+
+    #[tokio::test]
+    async fn test_route_snapshot_roundtrip() {
+        let repo = Repository::new(setup_test_db().await);
+        let hop = |ttl: u8, address: Option<&str>, asn: Option<u32>| RouteHop {
+            ttl,
+            address: address.map(|a| a.parse().unwrap()),
+            rtt_ms: address.map(|_| ttl as f64 * 4.0),
+            asn,
+            asn_name: None,
+        };
+        let snapshot = RouteSnapshot {
+            id: None,
+            taken_at: Utc::now(),
+            anchor: "1.1.1.1".to_string(),
+            hops: vec![hop(1, Some("192.168.1.1"), None), hop(2, None, None), hop(3, Some("203.94.64.1"), Some(9506)), hop(4, Some("203.94.66.9"), Some(9506)), hop(5, Some("1.1.1.1"), Some(13335))],
+            isp_name: Some("Hutch".to_string()),
+            isp_asn: Some(9506),
+        };
+        repo.save_route_snapshot(&snapshot).await.unwrap();
+
+        let saved = repo.get_route_snapshots_since(Utc::now() - chrono::Duration::hours(1)).await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].hops, snapshot.hops);
+        assert_eq!(saved[0].isp_asn, Some(9506));
+        assert_eq!(saved[0].path_signature(), "9506 13335");
+    }
+
+    #[tokio::test]
+    async fn test_model_quality_metric_replaces_same_day() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+        let day = NaiveDate::from_ymd_opt(2024, 3, 9).unwrap();
+        let mut metric = ModelQualityMetric {
+            id: None,
+            evaluated_date: day,
+            computed_at: Utc::now(),
+            hours_evaluated: 20,
+            true_positives: 3,
+            false_positives: 1,
+            false_negatives: 1,
+            true_negatives: 15,
+            precision: Some(0.75),
+            recall: Some(0.75),
+            f1: Some(0.75),
+            training_samples: 400,
+        };
+        repo.save_model_quality_metric(&metric).await.unwrap();
+        metric.recall = None;
+        repo.save_model_quality_metric(&metric).await.unwrap();
+        metric.evaluated_date = day.pred_opt().unwrap();
+        repo.save_model_quality_metric(&metric).await.unwrap();
+
+        let metrics = repo.get_model_quality_metrics_since(day.pred_opt().unwrap()).await.unwrap();
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[1].evaluated_date, day);
+        assert_eq!(metrics[1].recall, None);
+        assert_eq!(metrics[1].precision, Some(0.75));
+    }
+
+    #[tokio::test]
+    async fn test_passive_calibration_replaces_per_network() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+        let mut calibration = PassiveCalibration {
+            network_context: "0123456789abcdef".to_string(),
+            calibrated_at: Utc::now(),
+            factor: 1.8,
+            confidence_scale: 1.0,
+            calibrations: 1,
+            last_active_mbps: 90.0,
+            last_passive_mbps: 50.0,
+        };
+        repo.save_passive_calibration(&calibration).await.unwrap();
+        calibration.factor = 1.6;
+        calibration.calibrations = 2;
+        repo.save_passive_calibration(&calibration).await.unwrap();
+
+        assert_eq!(repo.get_passive_calibration("0123456789abcdef").await.unwrap(), Some(calibration.clone()));
+        assert_eq!(repo.get_passive_calibration("fedcba9876543210").await.unwrap(), None);
+        assert_eq!(repo.get_passive_calibrations().await.unwrap(), vec![calibration]);
+    }
+
+    #[tokio::test]
+    async fn test_measurement_source_roundtrip() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+        
+        repo.save_speed_measurement(&SpeedMeasurement::new(40.0, 8.0, 0, false)).await.unwrap();
+        let active = SpeedMeasurement::new(90.0, 20.0, 18, true).with_source(MeasurementSource::Active).with_method(MeasurementMethod::Http);
+        repo.save_speed_measurement(&active).await.unwrap();
+        let iperf = SpeedMeasurement::new(95.0, 40.0, 12, false).with_source(MeasurementSource::Active).with_method(MeasurementMethod::Iperf3);
+        repo.save_speed_measurement(&iperf).await.unwrap();
+        
+        let measurements = repo.get_speed_measurements_since(Utc::now() - chrono::Duration::hours(1)).await.unwrap();
+        let sources: Vec<_> = measurements.iter().map(|m| (m.download_mbps, m.source, m.method)).collect();
+        assert!(sources.contains(&(40.0, MeasurementSource::Passive, MeasurementMethod::Interface)));
+        assert!(sources.contains(&(90.0, MeasurementSource::Active, MeasurementMethod::Http)));
+        assert!(sources.contains(&(95.0, MeasurementSource::Active, MeasurementMethod::Iperf3)));
     }
 
     #[tokio::test]
@@ -601,6 +1959,27 @@ mod tests {
         let retrieved = repo.get_best_optimization_strategy().await.unwrap();
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap().name, "Default");
+
+        // Test update, list and delete
+        let mut edited = repo.get_optimization_strategy(id).await.unwrap().unwrap();
+        assert_eq!(edited.mimicry_profile, MimicryProfile::Speedtest);
+        edited.name = "Evening".to_string();
+        edited.connection_count = 5;
+        edited.mimicry_profile = MimicryProfile::FastCom;
+        edited.transport = TrafficTransport::Quic;
+        repo.update_optimization_strategy(&edited).await.unwrap();
+        let listed = repo.get_optimization_strategies().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name, "Evening");
+        assert_eq!(listed[0].connection_count, 5);
+        assert_eq!(listed[0].mimicry_profile, MimicryProfile::FastCom);
+        assert_eq!(listed[0].transport, TrafficTransport::Quic);
+        assert_eq!(listed[0].effectiveness_score, Some(0.8));
+
+        assert!(repo.delete_optimization_strategy(id).await.unwrap());
+        assert!(!repo.delete_optimization_strategy(id).await.unwrap());
+        assert!(repo.update_optimization_strategy(&edited).await.is_err());
+        assert!(repo.get_optimization_strategy(id).await.unwrap().is_none());
     }
 
     #[tokio::test]
@@ -632,6 +2011,91 @@ mod tests {
         
         // Test update last used
         repo.update_server_last_used("12345").await.unwrap();
+
+        // Saving again updates the row in place
+        let mut renamed = repo.get_active_speedtest_servers().await.unwrap().remove(0);
+        renamed.name = "Renamed".to_string();
+        assert_eq!(repo.save_speedtest_server(&renamed).await.unwrap(), id);
+
+        // Probe results leave the usage stats alone
+        let last_used = renamed.last_used;
+        repo.record_server_probe("12345", Some(42.0), 25.0, Utc::now(), true).await.unwrap();
+        repo.record_server_probe("12345", None, 100.0, Utc::now(), true).await.unwrap();
+        let servers = repo.get_active_speedtest_servers().await.unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!((servers[0].name.as_str(), servers[0].latency, servers[0].request_failure_pct), ("Renamed", Some(42.0), Some(100.0)));
+        assert_eq!((servers[0].use_count, servers[0].last_used), (1, last_used));
+        repo.record_server_probe("12345", None, 100.0, Utc::now(), false).await.unwrap();
+        assert!(repo.get_active_speedtest_servers().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_custom_servers_are_replaced_and_listed_first() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+        let custom = |host: &str| {
+            let mut server = SpeedtestServer::new(format!("{}{}:8080", CUSTOM_SERVER_ID_PREFIX, host), host.to_string(), 8080, host.to_string(), "Custom".to_string(), "Self-hosted".to_string())
+                .with_provider(ServerProvider::Librespeed);
+            server.tls = Some(false);
+            server
+        };
+        let directory = SpeedtestServer::new("12345".to_string(), "speedtest.example.com".to_string(), 8080, "A Server".to_string(), "Andorra".to_string(), "Test".to_string());
+        repo.save_speedtest_server(&directory).await.unwrap();
+        repo.replace_custom_speedtest_servers(&[custom("old.lan"), custom("nas.lan")]).await.unwrap();
+        repo.replace_custom_speedtest_servers(&[custom("nas.lan")]).await.unwrap();
+
+        let servers = repo.get_active_speedtest_servers().await.unwrap();
+        let ids: Vec<&str> = servers.iter().map(|s| s.server_id.as_str()).collect();
+        assert_eq!(ids, vec!["custom:nas.lan:8080", "12345"]);
+        assert_eq!((servers[0].provider, servers[0].tls), (ServerProvider::Librespeed, Some(false)));
+        assert_eq!(servers[1].tls, None);
+    }
+
+    #[tokio::test]
+    async fn test_merge_speedtest_servers_preserves_local_metadata() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+
+        let mut server = SpeedtestServer::new(
+            "12345".to_string(),
+            "speedtest.example.com".to_string(),
+            8080,
+            "Test Server".to_string(),
+            "Singapore".to_string(),
+            "Test Sponsor".to_string(),
+        );
+        server.latency = Some(42.0);
+        repo.save_speedtest_server(&server).await.unwrap();
+        repo.update_server_last_used("12345").await.unwrap();
+
+        // Refreshed directory entry renames the server and adds a new one
+        let mut refreshed = server.clone().with_coordinates(1.29, 103.85);
+        refreshed.name = "Renamed Server".to_string();
+        refreshed.latency = None;
+        let added = SpeedtestServer::new(
+            "67890".to_string(),
+            "speedtest2.example.com".to_string(),
+            8080,
+            "Second Server".to_string(),
+            "India".to_string(),
+            "Other Sponsor".to_string(),
+        )
+        .with_provider(ServerProvider::Librespeed);
+        let merged = repo.merge_speedtest_servers(&[refreshed, added]).await.unwrap();
+        assert_eq!(merged, 2);
+
+        let servers = repo.get_active_speedtest_servers().await.unwrap();
+        assert_eq!(servers.len(), 2);
+        let kept = servers.iter().find(|s| s.server_id == "12345").unwrap();
+        assert_eq!(kept.name, "Renamed Server");
+        assert_eq!(kept.latency, Some(42.0));
+        assert!(kept.last_used.is_some());
+        assert_eq!(kept.use_count, 1);
+        assert_eq!((kept.latitude, kept.longitude), (Some(1.29), Some(103.85)));
+        assert_eq!(kept.provider, ServerProvider::Ookla);
+        let libre = servers.iter().find(|s| s.server_id == "67890").unwrap();
+        assert_eq!(libre.provider, ServerProvider::Librespeed);
+        assert_eq!(libre.paths, ServerPathTemplates::for_provider(ServerProvider::Librespeed));
     }
 
     #[tokio::test]
@@ -659,6 +2123,10 @@ mod tests {
         // Verify update
         let final_config = repo.get_app_config().await.unwrap();
         assert_eq!(final_config.unwrap().auto_start, false);
+
+        let stats = repo.get_database_stats().await.unwrap();
+        assert_eq!(stats.table_rows.get("app_config"), Some(&1));
+        assert!(stats.size_bytes > 0 && stats.schema_version > 0);
     }
 
     #[tokio::test]
@@ -709,5 +2177,59 @@ mod tests {
         let since = Utc::now() - chrono::Duration::hours(1);
         let measurements = repo.get_speed_measurements_since(since).await.unwrap();
         assert_eq!(measurements.len(), 1);
+
+        // Old rows are rolled up rather than lost
+        let old = SpeedMeasurement { timestamp: Utc::now() - chrono::Duration::days(45), ..SpeedMeasurement::new(20.0, 4.0, 30, false) };
+        repo.save_speed_measurement(&old).await.unwrap();
+        repo.cleanup_old_data(30).await.unwrap();
+        assert!(repo.get_speed_measurements_since(old.timestamp - chrono::Duration::hours(1)).await.unwrap().iter().all(|m| m.timestamp > old.timestamp));
+        let hourly = repo.get_hourly_aggregates_since(old.timestamp - chrono::Duration::hours(1)).await.unwrap();
+        assert_eq!((hourly.len(), hourly[0].sample_count, hourly[0].avg_download_mbps), (1, 1, 20.0));
+    }
+
+    #[tokio::test]
+    async fn test_compact_measurements_into_hourly() {
+        let pool = setup_test_db().await;
+        let repo = Repository::new(pool);
+
+        let old_hour = (Utc::now() - chrono::Duration::days(40))
+            .date_naive()
+            .and_hms_opt(10, 0, 0)
+            .unwrap()
+            .and_utc();
+        for (minute, speed) in [(5, 40.0), (25, 60.0)] {
+            let mut m = SpeedMeasurement::new(speed, 10.0, 20, false);
+            m.timestamp = old_hour + chrono::Duration::minutes(minute);
+            repo.save_speed_measurement(&m).await.unwrap();
+        }
+        repo.save_speed_measurement(&SpeedMeasurement::new(80.0, 10.0, 20, false)).await.unwrap();
+
+        let deleted = repo.compact_measurements_older_than(30).await.unwrap();
+        assert_eq!(deleted, 2);
+
+        // Second pass for the same hour merges with sample weighting
+        let mut late = SpeedMeasurement::new(100.0, 10.0, 20, false);
+        late.timestamp = old_hour + chrono::Duration::minutes(45);
+        repo.save_speed_measurement(&late).await.unwrap();
+        repo.compact_measurements_older_than(30).await.unwrap();
+
+        let aggregates = repo.get_hourly_aggregates_since(old_hour - chrono::Duration::hours(1)).await.unwrap();
+        assert_eq!(aggregates.len(), 1);
+        let hour = &aggregates[0];
+        assert_eq!(hour.hour_start, old_hour);
+        assert_eq!(hour.sample_count, 3);
+        assert!((hour.avg_download_mbps - 200.0 / 3.0).abs() < 0.001);
+        assert_eq!(hour.min_download_mbps, 40.0);
+        assert_eq!(hour.max_download_mbps, 100.0);
+
+        // Recent raw data is untouched
+        let recent = repo.get_speed_measurements_since(Utc::now() - chrono::Duration::hours(1)).await.unwrap();
+        assert_eq!(recent.len(), 1);
+
+        // Compacted and raw hours read back together, the raw one grouped on the fly
+        let between = repo.get_hourly_aggregates_between(old_hour - chrono::Duration::hours(1), Utc::now()).await.unwrap();
+        assert_eq!(between.len(), 2);
+        assert_eq!((between[0].hour_start, between[0].sample_count), (old_hour, 3));
+        assert_eq!((between[1].sample_count, between[1].avg_download_mbps), (1, 80.0));
     }
-}
\ No newline at end of file
+}
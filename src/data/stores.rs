@@ -0,0 +1,186 @@
+use crate::core::error::Result;
+use crate::data::models::*;
+use crate::data::repository::Repository;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// Speed measurement persistence
+#[async_trait]
+pub trait MeasurementStore: Send + Sync {
+    async fn save_speed_measurement(&self, measurement: &SpeedMeasurement) -> Result<i64>;
+    /// Newest first
+    async fn get_speed_measurements_since(&self, since: DateTime<Utc>) -> Result<Vec<SpeedMeasurement>>;
+    async fn cleanup_old_data(&self, days_to_keep: u32) -> Result<()>;
+}
+
+/// ISP profiles and detected throttling patterns
+#[async_trait]
+pub trait PatternStore: Send + Sync {
+    async fn save_isp_profile(&self, profile: &ISPProfile) -> Result<i64>;
+    async fn get_current_isp_profile(&self) -> Result<Option<ISPProfile>>;
+    async fn save_throttling_pattern(&self, pattern: &ThrottlingPattern) -> Result<i64>;
+    /// Highest confidence first
+    async fn get_throttling_patterns_for_isp(&self, isp_profile_id: i64) -> Result<Vec<ThrottlingPattern>>;
+}
+
+/// Optimization strategies
+#[async_trait]
+pub trait StrategyStore: Send + Sync {
+    async fn save_optimization_strategy(&self, strategy: &OptimizationStrategy) -> Result<i64>;
+    /// The explicitly activated strategy, else the best scored one
+    async fn get_best_optimization_strategy(&self) -> Result<Option<OptimizationStrategy>>;
+    async fn update_strategy_effectiveness(&self, id: i64, score: Option<f64>) -> Result<()>;
+    /// `None` clears the activation so the best scored strategy is used
+    async fn set_active_strategy(&self, id: Option<i64>) -> Result<()>;
+}
+
+/// Speedtest server directory
+#[async_trait]
+pub trait ServerStore: Send + Sync {
+    async fn save_speedtest_server(&self, server: &SpeedtestServer) -> Result<i64>;
+    async fn merge_speedtest_servers(&self, servers: &[SpeedtestServer]) -> Result<usize>;
+    async fn get_active_speedtest_servers(&self) -> Result<Vec<SpeedtestServer>>;
+    async fn get_servers_by_country(&self, country: &str) -> Result<Vec<SpeedtestServer>>;
+    async fn update_server_last_used(&self, server_id: &str) -> Result<()>;
+    /// Latency, request failure rate, probe time and liveness only
+    async fn record_server_probe(&self, server_id: &str, latency: Option<f64>, request_failure_pct: f64, probed_at: DateTime<Utc>, is_active: bool) -> Result<()>;
+}
+
+/// User satisfaction answers and optimization trials that feed strategy rewards
+#[async_trait]
+pub trait FeedbackStore: Send + Sync {
+    async fn save_satisfaction_feedback(&self, feedback: &SatisfactionFeedback) -> Result<i64>;
+    /// Newest first
+    async fn get_satisfaction_feedback_since(&self, since: DateTime<Utc>) -> Result<Vec<SatisfactionFeedback>>;
+    async fn save_optimization_trial(&self, trial: &OptimizationTrial) -> Result<i64>;
+    /// Newest first
+    async fn get_optimization_trials_since(&self, since: DateTime<Utc>) -> Result<Vec<OptimizationTrial>>;
+}
+
+/// Strategy switches awaiting or past their canary window
+#[async_trait]
+pub trait DecisionStore: Send + Sync {
+    async fn save_strategy_decision(&self, decision: &StrategyDecision) -> Result<i64>;
+    async fn update_strategy_decision(&self, decision: &StrategyDecision) -> Result<()>;
+    /// Newest first; pending decisions are included however old
+    async fn get_strategy_decisions_since(&self, since: DateTime<Utc>) -> Result<Vec<StrategyDecision>>;
+}
+
+/// Everything the intelligence core needs; implemented by any type providing all stores
+pub trait DataStore: MeasurementStore + PatternStore + StrategyStore + ServerStore + FeedbackStore + DecisionStore {}
+
+impl<T: MeasurementStore + PatternStore + StrategyStore + ServerStore + FeedbackStore + DecisionStore> DataStore for T {}
+
+#[async_trait]
+impl MeasurementStore for Repository {
+    async fn save_speed_measurement(&self, measurement: &SpeedMeasurement) -> Result<i64> {
+        Repository::save_speed_measurement(self, measurement).await
+    }
+
+    async fn get_speed_measurements_since(&self, since: DateTime<Utc>) -> Result<Vec<SpeedMeasurement>> {
+        Repository::get_speed_measurements_since(self, since).await
+    }
+
+    async fn cleanup_old_data(&self, days_to_keep: u32) -> Result<()> {
+        Repository::cleanup_old_data(self, days_to_keep).await
+    }
+}
+
+#[async_trait]
+impl PatternStore for Repository {
+    async fn save_isp_profile(&self, profile: &ISPProfile) -> Result<i64> {
+        Repository::save_isp_profile(self, profile).await
+    }
+
+    async fn get_current_isp_profile(&self) -> Result<Option<ISPProfile>> {
+        Repository::get_current_isp_profile(self).await
+    }
+
+    async fn save_throttling_pattern(&self, pattern: &ThrottlingPattern) -> Result<i64> {
+        Repository::save_throttling_pattern(self, pattern).await
+    }
+
+    async fn get_throttling_patterns_for_isp(&self, isp_profile_id: i64) -> Result<Vec<ThrottlingPattern>> {
+        Repository::get_throttling_patterns_for_isp(self, isp_profile_id).await
+    }
+}
+
+#[async_trait]
+impl StrategyStore for Repository {
+    async fn save_optimization_strategy(&self, strategy: &OptimizationStrategy) -> Result<i64> {
+        Repository::save_optimization_strategy(self, strategy).await
+    }
+
+    async fn get_best_optimization_strategy(&self) -> Result<Option<OptimizationStrategy>> {
+        Repository::get_best_optimization_strategy(self).await
+    }
+
+    async fn update_strategy_effectiveness(&self, id: i64, score: Option<f64>) -> Result<()> {
+        Repository::update_strategy_effectiveness(self, id, score).await
+    }
+
+    async fn set_active_strategy(&self, id: Option<i64>) -> Result<()> {
+        Repository::set_active_strategy(self, id).await
+    }
+}
+
+#[async_trait]
+impl ServerStore for Repository {
+    async fn save_speedtest_server(&self, server: &SpeedtestServer) -> Result<i64> {
+        Repository::save_speedtest_server(self, server).await
+    }
+
+    async fn merge_speedtest_servers(&self, servers: &[SpeedtestServer]) -> Result<usize> {
+        Repository::merge_speedtest_servers(self, servers).await
+    }
+
+    async fn get_active_speedtest_servers(&self) -> Result<Vec<SpeedtestServer>> {
+        Repository::get_active_speedtest_servers(self).await
+    }
+
+    async fn get_servers_by_country(&self, country: &str) -> Result<Vec<SpeedtestServer>> {
+        Repository::get_servers_by_country(self, country).await
+    }
+
+    async fn update_server_last_used(&self, server_id: &str) -> Result<()> {
+        Repository::update_server_last_used(self, server_id).await
+    }
+
+    async fn record_server_probe(&self, server_id: &str, latency: Option<f64>, request_failure_pct: f64, probed_at: DateTime<Utc>, is_active: bool) -> Result<()> {
+        Repository::record_server_probe(self, server_id, latency, request_failure_pct, probed_at, is_active).await
+    }
+}
+
+#[async_trait]
+impl FeedbackStore for Repository {
+    async fn save_satisfaction_feedback(&self, feedback: &SatisfactionFeedback) -> Result<i64> {
+        Repository::save_satisfaction_feedback(self, feedback).await
+    }
+
+    async fn get_satisfaction_feedback_since(&self, since: DateTime<Utc>) -> Result<Vec<SatisfactionFeedback>> {
+        Repository::get_satisfaction_feedback_since(self, since).await
+    }
+
+    async fn save_optimization_trial(&self, trial: &OptimizationTrial) -> Result<i64> {
+        Repository::save_optimization_trial(self, trial).await
+    }
+
+    async fn get_optimization_trials_since(&self, since: DateTime<Utc>) -> Result<Vec<OptimizationTrial>> {
+        Repository::get_optimization_trials_since(self, since).await
+    }
+}
+
+#[async_trait]
+impl DecisionStore for Repository {
+    async fn save_strategy_decision(&self, decision: &StrategyDecision) -> Result<i64> {
+        Repository::save_strategy_decision(self, decision).await
+    }
+
+    async fn update_strategy_decision(&self, decision: &StrategyDecision) -> Result<()> {
+        Repository::update_strategy_decision(self, decision).await
+    }
+
+    async fn get_strategy_decisions_since(&self, since: DateTime<Utc>) -> Result<Vec<StrategyDecision>> {
+        Repository::get_strategy_decisions_since(self, since).await
+    }
+}
@@ -0,0 +1,616 @@
+//! Engine startup shared by the desktop app and headless runs: config, database, safe mode, ISP
+//! detection and every long-running loop. The shells add their own front (window and tray, or
+//! the log) and serve the control API with their own backend.
+
+use crate::core::alerts::{LearningStallWatcher, SpeedAlertWatcher};
+use crate::core::app_state::{AppControlState, OptimizationMode, SharedAppState};
+use crate::core::config::{AppConfig, CustomServerConfig};
+use crate::core::country_packs;
+use crate::core::decision_interval::WakingEventSink;
+use crate::core::emergency;
+use crate::core::error::Result;
+use crate::core::evaluation::start_evaluation_job;
+use crate::core::events::SharedEventSink;
+use crate::core::intelligence::DecisionEngine;
+use crate::core::model_share;
+use crate::core::power::PowerWatcher;
+use crate::core::safe_mode::{self, SafeModeStatus, StartupGuard};
+use crate::core::scheduler::PeriodicScheduler;
+use crate::core::shutdown::{CancellationToken, Shutdown};
+use crate::core::supervisor::Supervisor;
+use crate::core::watcher::PollingWatcher;
+use crate::core::webhooks::WebhookNotifier;
+use crate::data::compaction::start_compaction_job;
+use crate::data::consolidation::{self, ConsolidationReport};
+use crate::data::integrity::{self, IntegrityReport};
+use crate::data::migrations::MigrationManager;
+use crate::data::models::StealthLevel;
+use crate::data::repository::Repository;
+use crate::network::calls::CallInterlock;
+use crate::network::conflicts::ConflictWatcher;
+use crate::network::connectivity::ConnectivityWatcher;
+use crate::network::ip_lookup::PublicIpLookup;
+use crate::network::metered::MeteredWatcher;
+use crate::network::monitor::{BackgroundMonitor, ISPDetectionResult, MonitoringConfig};
+use crate::network::rtt::LatencyProbe;
+use crate::network::servers::{ServerPool, DEFAULT_PROBE_INTERVAL, DEFAULT_SERVER_CACHE_TTL};
+use crate::network::stealth::{StealthEngine, StealthWatchdog};
+use crate::network::tampering::TamperingChecker;
+use crate::network::{
+    AsnDatabase, ConnectionTable, DataUsageMeter, DisguiseProxy, OutboundLimiter, RttSampler, SpeedtestRetryQueue, SpeedtestSchedule, ThroughputKeeper,
+};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify, RwLock};
+use tracing::{info, warn};
+
+/// Who runs the engine, which decides the starting mode and whether notifications are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frontend {
+    /// Window and tray: optimization waits for the user, notifications follow `ui.show_notifications`
+    Desktop,
+    /// No display: automatic optimization decides the mode, notifications only reach the log
+    Headless,
+}
+
+/// The stealth engine's watchdog, set once the server list has loaded and the loop runs
+pub type StealthSlot = Arc<OnceLock<Arc<StealthWatchdog>>>;
+
+/// The disguise pulse while disguise mode is on; `None` while it is off
+pub type DisguiseSlot = Arc<Mutex<Option<Arc<DisguiseProxy>>>>;
+
+/// The running engine's parts, for the shells to hand to their commands and control backends
+pub struct Engine {
+    /// Effective configuration the engine started with
+    pub config: AppConfig,
+    pub repository: Arc<Repository>,
+    pub shared_state: SharedAppState,
+    pub events: SharedEventSink,
+    pub limiter: OutboundLimiter,
+    pub usage_meter: DataUsageMeter,
+    pub connection_table: ConnectionTable,
+    pub scheduler: PeriodicScheduler,
+    pub supervisor: Supervisor,
+    pub shutdown_token: CancellationToken,
+    /// Stops the loops and flushes the database; the shells run it on quit or signal
+    pub shutdown: Shutdown,
+    /// Interface changes, speed alerts and conflict changes trigger a decision round right away
+    pub decision_wake: Arc<Notify>,
+    pub webhooks: Arc<WebhookNotifier>,
+    pub keeper: Arc<ThroughputKeeper>,
+    pub speed_alerts: Arc<SpeedAlertWatcher>,
+    pub conflicts: Arc<ConflictWatcher>,
+    pub power: Arc<PowerWatcher>,
+    pub metered: Arc<MeteredWatcher>,
+    pub connectivity: Arc<ConnectivityWatcher>,
+    pub interlock: Arc<CallInterlock>,
+    pub retries: Arc<SpeedtestRetryQueue>,
+    pub disguise: DisguiseSlot,
+    pub stealth: StealthSlot,
+    pub asn_database: Arc<RwLock<AsnDatabase>>,
+    /// Result of the startup ISP detection, once it finished
+    pub last_detection: Arc<RwLock<Option<ISPDetectionResult>>>,
+    pub integrity: IntegrityReport,
+    pub consolidation: ConsolidationReport,
+    pub safe_mode: SafeModeStatus,
+    pub startup_guard: Arc<StartupGuard>,
+}
+
+impl Engine {
+    /// Opens the database and spawns the engine's tasks; `events` receives what they report
+    pub async fn start(frontend: Frontend, events: SharedEventSink) -> Result<Self> {
+        // Settings left at locations older versions used are adopted before the config is loaded
+        let config_path = AppConfig::config_file_path()?;
+        let merged_configs = consolidation::consolidate_configs(&consolidation::stray_configs(&config_path), &config_path).await;
+
+        let app_config = AppConfig::load().await?;
+        app_config.validate()?;
+        // Every client and socket opened from here on goes through the configured proxy
+        crate::network::proxy::install(&app_config.advanced.proxy)?;
+        let notifications = frontend == Frontend::Desktop && app_config.ui.show_notifications;
+
+        // Database in the platform data dir, sized by the storage footprint setting
+        let db_path = consolidation::canonical_db_path();
+        if let Some(parent) = db_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Repeated crashed startups bring the engine up with only monitoring and diagnostics running
+        let startup_guard = Arc::new(StartupGuard::new(db_path.parent().unwrap_or(std::path::Path::new("."))));
+        let crashed_startups = startup_guard.begin().await.unwrap_or_else(|e| {
+            warn!("Failed to record startup: {}", e);
+            0
+        });
+        let safe_mode_status = SafeModeStatus::evaluate(crashed_startups, &app_config.advanced.safe_mode);
+        if let Some(reason) = &safe_mode_status.reason {
+            warn!("{}", reason);
+        }
+        // A database older versions kept in the temp dir moves over whole on the first start without one here
+        let adopted_database = consolidation::adopt_database(&consolidation::stray_databases(&db_path), &db_path).await;
+        // Quarantine and rebuild a corrupt database before anything opens it
+        let integrity = integrity::check_and_repair(&db_path).await;
+        if integrity.needs_attention() {
+            warn!("{}", integrity.summary());
+            if notifications {
+                events.notify("SpeedKarma", &integrity.summary());
+            }
+        } else {
+            info!("{}", integrity.summary());
+        }
+        let migration_manager = MigrationManager::new(format!("sqlite://{}", db_path.display()));
+        migration_manager.create_database_if_not_exists().await?;
+        let pool = migration_manager.connect(&app_config.advanced.storage).await?;
+        migration_manager.run_migrations(&pool).await?;
+        let repository = Arc::new(Repository::new(pool));
+
+        // One-time merge of measurement history from any other databases older versions left behind
+        let mut merged_databases: Vec<_> = adopted_database.into_iter().collect();
+        merged_databases.extend(consolidation::merge_databases(&repository, &consolidation::stray_databases(&db_path)).await);
+        let consolidation = ConsolidationReport::new(db_path.clone(), merged_configs, merged_databases);
+        if consolidation.found_anything() {
+            info!("{}", consolidation.summary());
+            if notifications {
+                events.notify("SpeedKarma", &consolidation.summary());
+            }
+        }
+
+        // Profiles such as low-data mode shape what every module runs with
+        let app_config = app_config.effective();
+        let optimization_mode = match frontend {
+            // No panel to switch it on from, so automatic optimization decides
+            Frontend::Headless if app_config.auto_optimization.enabled => OptimizationMode::Enabled,
+            _ => OptimizationMode::Disabled,
+        };
+        let shared_state: SharedAppState = Arc::new(RwLock::new(AppControlState {
+            optimization_mode,
+            modules: app_config.modules.clone(),
+            quiet_hours: app_config.quiet_hours.clone(),
+            power_policy: app_config.power_policy.clone(),
+            monitored_interfaces: app_config.monitoring.interfaces.clone(),
+            safe_mode: safe_mode_status.active,
+            ..AppControlState::default()
+        }));
+        // Handshake times from stealth connections feed passive latency
+        let rtt_sampler = RttSampler::new();
+        // Per-server bookkeeping of keeper and stealth connections for the advanced panel
+        let connection_table = ConnectionTable::new();
+        // Phase and jitter for the periodic loops so they do not fire together
+        let scheduler = PeriodicScheduler::default();
+        // One cap on concurrent outbound requests shared by every traffic generator and probe
+        let limiter = OutboundLimiter::new(app_config.advanced.outbound_limits.max_concurrent as usize);
+        // ISP detection and server ranking share one public-IP lookup and its cache
+        let ip_lookup = PublicIpLookup::new(app_config.advanced.isp_lookup.clone()).with_limiter(limiter.clone());
+        // A stop engaged in an earlier session holds before any generator starts
+        emergency::restore(&app_config, &shared_state, &limiter).await;
+        // Bytes generated per module, against the monthly data cap
+        let usage_meter = DataUsageMeter::new(Arc::clone(&repository), shared_state.clone(), app_config.advanced.data_budget.clone());
+        if let Err(e) = usage_meter.load().await {
+            warn!("Failed to load data usage: {}", e);
+        }
+        usage_meter.clone().start();
+        let asn_database = Self::start_asn_database(&app_config).await;
+
+        // Long-running loops are restarted with backoff when they fail; their health shows in the status
+        let shutdown_token = CancellationToken::new();
+        let supervisor = Supervisor::new().with_shutdown(shutdown_token.clone());
+        let shutdown = Shutdown::new(shutdown_token.clone(), supervisor.clone(), Arc::clone(&repository))
+            .with_usage_meter(usage_meter.clone())
+            .with_startup_guard(Arc::clone(&startup_guard), shared_state.clone(), crashed_startups);
+        let decision_wake = Arc::new(Notify::new());
+        let waking_events: SharedEventSink = Arc::new(WakingEventSink::new(Arc::clone(&events), decision_wake.clone()));
+
+        // Passive monitoring
+        {
+            let repository = Arc::clone(&repository);
+            let shared_state = shared_state.clone();
+            let rtt_sampler = rtt_sampler.clone();
+            let limiter = limiter.clone();
+            let ip_lookup = ip_lookup.clone();
+            let scheduler = scheduler.clone();
+            let waking_events = Arc::clone(&waking_events);
+            let monitoring = app_config.monitoring.clone();
+            let low_data = app_config.advanced.low_data_mode.enabled;
+            let shutdown_token = shutdown_token.clone();
+            supervisor.spawn("monitor", move || {
+                let mut monitor = if low_data {
+                    BackgroundMonitor::with_config(Arc::clone(&repository), MonitoringConfig::with_interval(monitoring.measurement_interval))
+                } else {
+                    BackgroundMonitor::new(Arc::clone(&repository))
+                };
+                monitor.set_shared_state(shared_state.clone());
+                monitor.set_rtt_sampler(rtt_sampler.clone());
+                monitor.set_latency_probe(LatencyProbe::new(monitoring.latency_probes.clone()).with_limiter(limiter.clone()));
+                monitor.set_ip_lookup(ip_lookup.clone());
+                monitor.set_throttling_sensitivity(monitoring.throttling_sensitivity.clone());
+                monitor.set_adaptive_confidence(monitoring.adaptive_confidence.clone());
+                monitor.set_vpn_measurements(monitoring.vpn_measurements);
+                monitor.set_event_sink(Arc::clone(&waking_events));
+                monitor.set_scheduler(scheduler.clone());
+                monitor.set_shutdown(shutdown_token.clone());
+                async move {
+                    monitor.start_monitoring().await?;
+                    monitor.wait().await
+                }
+            });
+        }
+
+        // Throughput keeper, with live config
+        let keeper = Arc::new(ThroughputKeeper::new(Arc::clone(&events), Arc::clone(&repository), shared_state.clone(), app_config.advanced.throughput_keeper.clone())
+            .with_connection_table(connection_table.clone())
+            .with_scheduler(scheduler.clone())
+            .with_limiter(limiter.clone())
+            .with_usage_meter(usage_meter.clone())
+            .with_supervisor(supervisor.clone())
+            .with_shutdown(shutdown_token.clone()));
+        keeper.clone().start();
+
+        let last_detection = Arc::new(RwLock::new(None));
+        Self::start_isp_detection(&app_config, &repository, &shared_state, &limiter, &ip_lookup, &asn_database, &last_detection, &keeper);
+
+        // User webhooks for throttling, optimization and detection-risk events
+        let webhooks = Arc::new(WebhookNotifier::new(app_config.advanced.webhooks.clone())?);
+        webhooks.clone().watch_optimization(shared_state.clone());
+
+        // Decision engine
+        {
+            let repository = Arc::clone(&repository);
+            let scheduler = scheduler.clone();
+            let decision_wake = decision_wake.clone();
+            let webhooks = webhooks.clone();
+            let shared_state = shared_state.clone();
+            let auto_optimization = app_config.auto_optimization.clone();
+            // Old raw rows are rolled into hourly aggregates by the compaction job instead
+            let raw_cleanup = !app_config.advanced.compaction.enabled;
+            let shutdown_token = shutdown_token.clone();
+            supervisor.spawn("decision_engine", move || {
+                let mut engine = DecisionEngine::new(repository.clone());
+                engine.set_scheduler(scheduler.clone());
+                engine.set_priors_path(model_share::default_priors_path());
+                engine.set_min_learning_days(auto_optimization.min_data_days);
+                engine.set_strategy_canary(auto_optimization.strategy_canary.clone());
+                engine.set_decision_interval(auto_optimization.decision_interval.clone());
+                engine.set_wake(decision_wake.clone());
+                engine.set_raw_cleanup_enabled(raw_cleanup);
+                engine.set_webhooks(webhooks.clone());
+                engine.set_shared_state(shared_state.clone());
+                engine.set_shutdown(shutdown_token.clone());
+                async move { engine.run().await }
+            });
+        }
+        // Roll old raw measurements into hourly aggregates
+        start_compaction_job(Arc::clone(&repository), app_config.advanced.compaction.clone());
+        // Score each finished day's throttling predictions against what was measured
+        start_evaluation_job(Arc::clone(&repository));
+
+        // Sustained low speed, and learning stalled because the device is off during the watched hours
+        let speed_alerts = Arc::new(SpeedAlertWatcher::new(Arc::clone(&waking_events), Arc::clone(&repository), app_config.alerts.clone(), notifications));
+        speed_alerts.clone().start();
+        Arc::new(LearningStallWatcher::new(Arc::clone(&events), Arc::clone(&repository), notifications)).start();
+
+        // Watchers that hold generated traffic back: competing tools, power saving, metered
+        // connections, and no connection or a captive portal
+        let conflicts = Arc::new(ConflictWatcher::new(Arc::clone(&waking_events), shared_state.clone(), app_config.advanced.conflict_detection.clone(), notifications));
+        conflicts.clone().supervise("conflicts_watcher", &supervisor);
+        let power = Arc::new(PowerWatcher::new(Arc::clone(&waking_events), shared_state.clone(), app_config.power_policy.clone()));
+        power.clone().supervise("power_watcher", &supervisor);
+        let metered = Arc::new(MeteredWatcher::new(Arc::clone(&events), shared_state.clone(), app_config.advanced.metered.clone(), notifications));
+        metered.clone().supervise("metered_watcher", &supervisor);
+        let connectivity = Arc::new(ConnectivityWatcher::new(Arc::clone(&events), shared_state.clone(), app_config.advanced.connectivity.clone(), notifications)
+            .with_limiter(limiter.clone()));
+        connectivity.clone().supervise("connectivity_watcher", &supervisor);
+
+        // Hold back speed tests and heavy bursts while a call is running
+        let interlock = Arc::new(CallInterlock::new(Arc::clone(&events), shared_state.clone(), app_config.advanced.call_interlock.clone()));
+        interlock.clone().start();
+
+        // Failed speed tests are retried in the same hour later in the week; scheduled ones run at the configured times
+        let retries = Arc::new(SpeedtestRetryQueue::new(Arc::clone(&events), Arc::clone(&repository), shared_state.clone())
+            .with_usage_meter(usage_meter.clone())
+            .with_limiter(limiter.clone()));
+        retries.clone().start();
+        Arc::new(SpeedtestSchedule::new(Arc::clone(&events), Arc::clone(&repository), shared_state.clone())
+            .with_retries(retries.clone())
+            .with_usage_meter(usage_meter.clone())
+            .with_limiter(limiter.clone())
+            .with_shutdown(shutdown_token.clone()))
+            .start();
+
+        let stealth = Arc::new(OnceLock::new());
+        Self::start_stealth(
+            &app_config, &repository, &shared_state, &rtt_sampler, &scheduler, &connection_table, &limiter, &usage_meter,
+            &webhooks, &ip_lookup, &supervisor, &shutdown_token, &stealth,
+        );
+
+        let disguise = app_config.advanced.disguise_mode.enabled.then(|| {
+            Self::start_disguise(&app_config, &events, &repository, &shared_state, &supervisor, &shutdown_token, &limiter, &usage_meter)
+        });
+        let disguise = Arc::new(Mutex::new(disguise));
+
+        if safe_mode_status.active {
+            events.emit_payload("safe_mode", &safe_mode_status);
+            if notifications {
+                let body = format!("Crashed {} times in a row. Turned off: {}.", crashed_startups, safe_mode_status.disabled.join(", "));
+                events.notify("SpeedKarma safe mode", &body);
+            }
+        }
+        // A start that stays up counts as survived; one still in safe mode keeps the next start there
+        {
+            let guard = Arc::clone(&startup_guard);
+            let shared = shared_state.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(safe_mode::STABLE_AFTER).await;
+                guard.settle(shared.read().await.safe_mode, crashed_startups).await;
+            });
+        }
+
+        Ok(Self {
+            config: app_config,
+            repository,
+            shared_state,
+            events,
+            limiter,
+            usage_meter,
+            connection_table,
+            scheduler,
+            supervisor,
+            shutdown_token,
+            shutdown,
+            decision_wake,
+            webhooks,
+            keeper,
+            speed_alerts,
+            conflicts,
+            power,
+            metered,
+            connectivity,
+            interlock,
+            retries,
+            disguise,
+            stealth,
+            asn_database,
+            last_detection,
+            integrity,
+            consolidation,
+            safe_mode: safe_mode_status,
+            startup_guard,
+        })
+    }
+
+    /// Starts the disguise pulse under the supervisor, within the outbound limit and data cap
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_disguise(
+        app_config: &AppConfig,
+        events: &SharedEventSink,
+        repository: &Arc<Repository>,
+        shared_state: &SharedAppState,
+        supervisor: &Supervisor,
+        shutdown_token: &CancellationToken,
+        limiter: &OutboundLimiter,
+        usage_meter: &DataUsageMeter,
+    ) -> Arc<DisguiseProxy> {
+        let mut proxy = DisguiseProxy::new(Arc::clone(events), Arc::clone(repository), shared_state.clone(), app_config.advanced.disguise_mode.clone())
+            .with_supervisor(supervisor.clone())
+            .with_shutdown(shutdown_token.clone())
+            .with_limiter(limiter.clone())
+            .with_usage_meter(usage_meter.clone());
+        if let Some(profile) = app_config.advanced.mimicry_profile {
+            proxy = proxy.with_mimicry_profile(profile);
+        }
+        let proxy = Arc::new(proxy);
+        proxy.clone().start();
+        proxy
+    }
+
+    /// Offline ASN/country database: bundled seed first, refreshed copy when available
+    async fn start_asn_database(app_config: &AppConfig) -> Arc<RwLock<AsnDatabase>> {
+        let db_path = AsnDatabase::default_path();
+        let asn_db = match &db_path {
+            Some(path) => AsnDatabase::load_or_bundled(path).await,
+            None => AsnDatabase::bundled(),
+        };
+        let asn_db = Arc::new(RwLock::new(asn_db));
+        let asn_cfg = app_config.advanced.asn_database.clone();
+        if let (Some(url), Some(path)) = (asn_cfg.refresh_url, db_path) {
+            let every = Duration::from_secs(asn_cfg.refresh_interval_days.max(1) as u64 * 86_400);
+            let asn_db = Arc::clone(&asn_db);
+            tokio::spawn(async move {
+                loop {
+                    let stale = tokio::fs::metadata(&path).await
+                        .and_then(|m| m.modified())
+                        .map(|t| t.elapsed().unwrap_or_default() >= every)
+                        .unwrap_or(true);
+                    if stale {
+                        match AsnDatabase::refresh(&url, &path).await {
+                            Ok(fresh) => *asn_db.write().await = fresh,
+                            Err(e) => warn!("ASN database refresh failed: {}", e),
+                        }
+                    }
+                    tokio::time::sleep(Duration::from_secs(6 * 3600)).await;
+                }
+            });
+        }
+        asn_db
+    }
+
+    /// Detects the ISP in the background and saves its profile, then runs the CGNAT and tampering
+    /// checks and seeds an initial strategy when none exists yet
+    #[allow(clippy::too_many_arguments)]
+    fn start_isp_detection(
+        app_config: &AppConfig,
+        repository: &Arc<Repository>,
+        shared_state: &SharedAppState,
+        limiter: &OutboundLimiter,
+        ip_lookup: &PublicIpLookup,
+        asn_database: &Arc<RwLock<AsnDatabase>>,
+        last_detection: &Arc<RwLock<Option<ISPDetectionResult>>>,
+        keeper: &Arc<ThroughputKeeper>,
+    ) {
+        let repository = Arc::clone(repository);
+        let shared_state = shared_state.clone();
+        let limiter = limiter.clone();
+        let ip_lookup = ip_lookup.clone();
+        let asn_database = Arc::clone(asn_database);
+        let last_detection = Arc::clone(last_detection);
+        let keeper = Arc::clone(keeper);
+        let tampering_cfg = app_config.advanced.tampering_checks.clone();
+        tokio::spawn(async move {
+            // Keep avoiding plain HTTP on an ISP already caught tampering until a new check says otherwise
+            if let Ok(Some(profile)) = repository.get_current_isp_profile().await {
+                shared_state.write().await.prefer_encrypted = profile.tampering.is_some_and(|t| t.detected());
+            }
+            let mut monitor = BackgroundMonitor::new(Arc::clone(&repository));
+            monitor.set_ip_lookup(ip_lookup);
+            monitor.set_asn_database(asn_database);
+            let result = match monitor.detect_isp().await {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("ISP detection failed: {}", e);
+                    return;
+                }
+            };
+            *last_detection.write().await = Some(result.clone());
+            apply_detected_country_pack(&keeper, &result.region).await;
+            let profile_id = match monitor.save_isp_profile(&result).await {
+                Ok(profile_id) => profile_id,
+                Err(e) => {
+                    warn!("Failed to save ISP profile: {}", e);
+                    return;
+                }
+            };
+            match monitor.detect_cgnat().await {
+                Ok(report) => {
+                    if let Err(e) = repository.update_isp_profile_cgnat(profile_id, &report).await {
+                        warn!("Failed to record CGNAT check: {}", e);
+                    }
+                }
+                Err(e) => warn!("CGNAT check failed: {}", e),
+            }
+            if tampering_cfg.enabled {
+                match TamperingChecker::new(tampering_cfg).with_limiter(limiter).check_and_record(&repository, profile_id).await {
+                    Ok(report) => {
+                        if report.detected() {
+                            warn!("ISP interferes with DNS or plain HTTP; traffic generators will use HTTPS");
+                        }
+                        shared_state.write().await.prefer_encrypted = report.detected();
+                    }
+                    Err(e) => warn!("Tampering check failed: {}", e),
+                }
+            }
+            // A default strategy from the market pack and whether the ISP is known to throttle,
+            // only when no strategy exists yet
+            if let Ok(Some(_)) = repository.get_best_optimization_strategy().await {
+                return;
+            }
+            let mut strategy = country_packs::initial_strategy(&result.region, &result.isp_name);
+            // Seed an initial effectiveness score to help selection later
+            if strategy.effectiveness_score.is_none() {
+                strategy.effectiveness_score = Some(0.6);
+            }
+            match repository.save_optimization_strategy(&strategy).await {
+                Ok(_) => info!("Applied initial optimization strategy: {} (stealth: {:?})", strategy.name, strategy.stealth_level),
+                Err(e) => warn!("Failed to save initial optimization strategy: {}", e),
+            }
+        });
+    }
+
+    /// Loads the server list in the background and runs the stealth mimicry loop under the supervisor
+    #[allow(clippy::too_many_arguments)]
+    fn start_stealth(
+        app_config: &AppConfig,
+        repository: &Arc<Repository>,
+        shared_state: &SharedAppState,
+        rtt_sampler: &RttSampler,
+        scheduler: &PeriodicScheduler,
+        connection_table: &ConnectionTable,
+        limiter: &OutboundLimiter,
+        usage_meter: &DataUsageMeter,
+        webhooks: &Arc<WebhookNotifier>,
+        ip_lookup: &PublicIpLookup,
+        supervisor: &Supervisor,
+        shutdown_token: &CancellationToken,
+        slot: &StealthSlot,
+    ) {
+        let repository = Arc::clone(repository);
+        let shared_state = shared_state.clone();
+        let rtt_sampler = rtt_sampler.clone();
+        let scheduler = scheduler.clone();
+        let connection_table = connection_table.clone();
+        let limiter = limiter.clone();
+        let usage_meter = usage_meter.clone();
+        let webhooks = webhooks.clone();
+        let ip_lookup = ip_lookup.clone();
+        let supervisor = supervisor.clone();
+        let shutdown_token = shutdown_token.clone();
+        let slot = Arc::clone(slot);
+        let advanced = app_config.advanced.clone();
+        tokio::spawn(async move {
+            // User-defined servers are stored with the directory ones, where the runner and keeper pick them first
+            let custom_servers: Vec<_> = advanced.custom_servers.iter().map(CustomServerConfig::to_server).collect();
+            if let Err(e) = repository.replace_custom_speedtest_servers(&custom_servers).await {
+                warn!("Could not store custom servers: {}", e);
+            }
+            let mut pool = match ServerPool::new() {
+                Ok(pool) => pool.with_custom_servers(custom_servers),
+                Err(e) => {
+                    warn!("Stealth engine unavailable: {}", e);
+                    return;
+                }
+            };
+            if let Some(path) = ServerPool::default_cache_path() {
+                pool = pool.with_cache(path, DEFAULT_SERVER_CACHE_TTL);
+            }
+            pool = pool.with_preferred_countries(advanced.preferred_server_countries.clone()).with_limiter(limiter.clone()).with_shared_state(shared_state.clone());
+            pool.locate_user(&advanced.user_location, &ip_lookup).await;
+            if let Err(e) = pool.load_servers().await {
+                warn!("Stealth engine has no servers: {}", e);
+                return;
+            }
+            let (stealth_level, transport) = match repository.get_best_optimization_strategy().await {
+                Ok(Some(s)) => (s.stealth_level, s.transport),
+                _ => (StealthLevel::Medium, Default::default()),
+            };
+            // Latency probes re-rank the rotation and retire hosts that answer nothing
+            let pool = Arc::new(pool);
+            Arc::clone(&pool).start_probing(repository.clone(), DEFAULT_PROBE_INTERVAL, Some(shutdown_token.clone()));
+            let mut engine = StealthEngine::new(pool, stealth_level)
+                .with_shared_state(shared_state)
+                .with_strategy_store(repository)
+                .with_transport(transport)
+                .with_dns(advanced.stealth_dns)
+                .with_rtt_sampler(rtt_sampler)
+                .with_scheduler(scheduler)
+                .with_connection_table(connection_table)
+                .with_limiter(limiter)
+                .with_usage_meter(usage_meter)
+                .with_webhooks(webhooks)
+                .with_shutdown(shutdown_token);
+            if let Some(profile) = advanced.mimicry_profile {
+                engine = engine.with_mimicry_profile(profile);
+            }
+            let watchdog = Arc::new(StealthWatchdog::new(Arc::new(engine)));
+            let stealth = Arc::clone(&watchdog);
+            supervisor.spawn("stealth", move || {
+                let stealth = Arc::clone(&stealth);
+                async move { stealth.run().await }
+            });
+            let _ = slot.set(watchdog);
+        });
+    }
+}
+
+/// First detection of a known market writes its defaults into the config
+async fn apply_detected_country_pack(keeper: &ThroughputKeeper, region: &str) {
+    let mut cfg = match AppConfig::load().await {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            warn!("Country defaults skipped: {}", e);
+            return;
+        }
+    };
+    let Some(pack) = country_packs::auto_apply(&mut cfg, region) else { return };
+    if let Err(e) = cfg.save().await {
+        warn!("Failed to save {} defaults: {}", pack.name, e);
+        return;
+    }
+    info!("Applied {} defaults", pack.name);
+    keeper.update_config(cfg.effective().advanced.throughput_keeper).await;
+}
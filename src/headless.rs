@@ -0,0 +1,163 @@
+//! Engine without the desktop shell, for home servers and router boxes with no display.
+//! Starts the same engine as the desktop app; events and notifications go to the log.
+//! The CLI socket and, when switched on, the control API are served as in the desktop app.
+
+use crate::core::app_state::{OptimizationMode, SharedAppState};
+use crate::core::config::AppConfig;
+use crate::core::control_api::{self, ControlBackend, SiteSummary};
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::core::events::{NullEventSink, SharedEventSink};
+use crate::core::intelligence::{DefaultIntelligenceCore, IntelligenceCore};
+use crate::core::logging::{self, LogControl, RecentLogs};
+use crate::core::shutdown::{self, Shutdown, SHUTDOWN_GRACE};
+use crate::core::single_instance;
+use crate::core::supervisor::Supervisor;
+use crate::data::export::{self, ExportFormat, ExportSummary, MeasurementFilter};
+use crate::data::models::SpeedMeasurement;
+use crate::data::repository::Repository;
+use crate::engine::{Engine, Frontend};
+use crate::network::calls::CallInterlock;
+use crate::network::{DataUsageMeter, OutboundLimiter, SpeedtestRetryQueue, SpeedtestRunner};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+/// Command-line options of a headless run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeadlessOptions {
+    /// Log file written next to stdout
+    pub log_file: Option<PathBuf>,
+}
+
+impl HeadlessOptions {
+    /// Reads `--log-file <path>` from the process arguments
+    pub fn from_args(args: &[String]) -> Self {
+        let log_file = args.iter().position(|a| a == "--log-file").and_then(|pos| args.get(pos + 1)).map(PathBuf::from);
+        Self { log_file }
+    }
+}
+
+/// Logs to stdout and to the rotated log file: `log_file` when given, the app data dir otherwise
+pub fn init_logging(config: &AppConfig, log_file: Option<&Path>) -> LogControl {
+    let mut cfg = config.advanced.logging.clone();
+    // An explicit `--log-file` is written even when the config turns the file off
+    cfg.file_enabled |= log_file.is_some();
+    let path = log_file.map_or_else(logging::default_log_path, Path::to_path_buf);
+    logging::init(&cfg, config.advanced.debug_logging, &path, RecentLogs::new())
+}
+
+/// Sets up logging, starts the engine and runs until Ctrl-C or SIGTERM
+pub async fn run(options: HeadlessOptions) -> Result<()> {
+    let _logs = init_logging(&AppConfig::load().await?, options.log_file.as_deref());
+    let Some(_instance) = single_instance::acquire()? else {
+        return Err(SpeedKarmaError::SystemError("SpeedKarma is already running for this user".to_string()));
+    };
+    info!("Starting ISP-SpeedKarma headless");
+    let shutdown = start().await?;
+    shutdown::signal().await;
+    shutdown.run(SHUTDOWN_GRACE).await;
+    Ok(())
+}
+
+/// Starts the engine with events going to the log; returns what stops it and flushes on exit
+async fn start() -> Result<Shutdown> {
+    let engine = Engine::start(Frontend::Headless, Arc::new(NullEventSink)).await?;
+
+    // Local socket for `speedkarma-cli` and the loopback HTTP API, both served by the same backend
+    let backend = Arc::new(HeadlessBackend {
+        repository: Arc::clone(&engine.repository),
+        shared: engine.shared_state.clone(),
+        events: Arc::clone(&engine.events),
+        usage: engine.usage_meter.clone(),
+        limiter: engine.limiter.clone(),
+        interlock: Arc::clone(&engine.interlock),
+        retries: Arc::clone(&engine.retries),
+        min_data_days: engine.config.auto_optimization.min_data_days,
+        supervisor: engine.supervisor.clone(),
+    });
+    crate::core::ipc::serve(backend.clone());
+    control_api::launch(&engine.config.advanced.control_api, backend).await?;
+
+    info!("Headless engine running (optimization {:?})", engine.shared_state.read().await.optimization_mode);
+    Ok(engine.shutdown)
+}
+
+/// Control actions of a headless run, for the CLI socket
+struct HeadlessBackend {
+    repository: Arc<Repository>,
+    shared: SharedAppState,
+    events: SharedEventSink,
+    usage: DataUsageMeter,
+    limiter: OutboundLimiter,
+    interlock: Arc<CallInterlock>,
+    retries: Arc<SpeedtestRetryQueue>,
+    min_data_days: u32,
+    supervisor: Supervisor,
+}
+
+#[async_trait]
+impl ControlBackend for HeadlessBackend {
+    async fn status(&self) -> std::result::Result<serde_json::Value, String> {
+        let optimization = self.shared.read().await.to_json();
+        let mut system = DefaultIntelligenceCore::with_min_learning_days(self.repository.clone(), self.min_data_days).get_status().await.map_err(|e| e.to_string())?;
+        system.tasks = self.supervisor.health();
+        Ok(serde_json::json!({ "optimization": optimization, "system": system }))
+    }
+
+    async fn toggle_optimization(&self) -> std::result::Result<serde_json::Value, String> {
+        let mut guard = self.shared.write().await;
+        if guard.stopped_by_user {
+            return Err("Emergency stop is engaged; re-enable traffic first".to_string());
+        }
+        // The keeper and stealth engine follow the mode on their next round
+        let next = match guard.optimization_mode { OptimizationMode::Enabled => OptimizationMode::Disabled, OptimizationMode::Disabled => OptimizationMode::Enabled };
+        guard.set_optimization_mode(next);
+        Ok(guard.to_json())
+    }
+
+    async fn run_speedtest(&self) -> std::result::Result<(), String> {
+        if !self.shared.read().await.may_generate() {
+            return Err("Generated traffic is held back right now (paused, stopped, quiet hours, data cap or offline)".to_string());
+        }
+        let cfg = AppConfig::load().await.map_err(|e| e.to_string())?.effective().advanced.speedtest_runner;
+        let runner = SpeedtestRunner::new(Arc::clone(&self.events), Arc::clone(&self.repository), self.shared.clone(), cfg)
+            .with_retries(Arc::clone(&self.retries), 0)
+            .with_usage_meter(self.usage.clone())
+            .with_limiter(self.limiter.clone());
+        // During a call the test waits for it to end
+        self.interlock.run_or_defer(runner).await;
+        Ok(())
+    }
+
+    async fn history(&self, days: u32) -> std::result::Result<Vec<SpeedMeasurement>, String> {
+        self.repository.get_speed_measurements_since(Utc::now() - chrono::Duration::days(days as i64)).await.map_err(|e| e.to_string())
+    }
+
+    async fn summary(&self) -> std::result::Result<SiteSummary, String> {
+        let isp_name = self.repository.get_current_isp_profile().await.map_err(|e| e.to_string())?.map(|p| p.name);
+        let throttling = self.repository.get_current_throttling_summaries().await.map_err(|e| e.to_string())?;
+        let alerts = self.repository.get_speed_alert_episodes_since(Utc::now() - chrono::Duration::days(1)).await.map_err(|e| e.to_string())?;
+        let optimization_enabled = matches!(self.shared.read().await.optimization_mode, OptimizationMode::Enabled);
+        let effectiveness = DefaultIntelligenceCore::with_min_learning_days(self.repository.clone(), self.min_data_days).get_status().await.ok().and_then(|s| s.effectiveness);
+        Ok(SiteSummary { isp_name, optimization_enabled, effectiveness, throttling, alerts })
+    }
+
+    async fn export(&self, format: ExportFormat, filter: MeasurementFilter, path: Option<PathBuf>) -> std::result::Result<ExportSummary, String> {
+        let path = path.unwrap_or_else(|| export::default_export_path(format, Utc::now()));
+        export::export_measurements(&self.repository, &path, format, &filter).await.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_from_args() {
+        let args: Vec<String> = ["speedkarma", "--headless", "--log-file", "/var/log/speedkarma.log"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(HeadlessOptions::from_args(&args).log_file, Some(PathBuf::from("/var/log/speedkarma.log")));
+        assert_eq!(HeadlessOptions::from_args(&args[..3]), HeadlessOptions::default());
+    }
+}
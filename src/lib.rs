@@ -1,4 +1,9 @@
 pub mod core;
 pub mod network;
+pub mod data;
+pub mod engine;
+#[cfg(feature = "shell")]
 pub mod ui;
-pub mod data;
\ No newline at end of file
+
+#[cfg(feature = "headless")]
+pub mod headless;
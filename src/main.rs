@@ -1,51 +1,180 @@
 use tracing::{info, error};
-use tracing_subscriber;
-
-mod core;
-mod network;
-mod ui;
-mod data;
-
-use crate::core::error::Result;
-use crate::core::intelligence::{DecisionEngine, DefaultIntelligenceCore};
-use crate::core::intelligence::IntelligenceCore;
-use crate::core::config::AppConfig;
-use crate::core::app_state::{AppControlState, SharedAppState, OptimizationMode};
-use crate::data::migrations::MigrationManager;
-use crate::data::models::OptimizationStrategy;
-use crate::data::repository::Repository;
-use crate::ui::tray::SystemTray;
-use crate::ui::panel::PanelInterface;
-use crate::ui::progress::start_progress_broadcaster;
-use crate::network::monitor::BackgroundMonitor;
-use crate::network::{ThroughputKeeper, SpeedtestRunner, DisguiseProxy};
-use sqlx::SqlitePool;
+
+use isp_speedkarma::core::error::Result;
+use isp_speedkarma::core::intelligence::{without_vpn, DefaultIntelligenceCore, StrategyProposal};
+use isp_speedkarma::core::intelligence::IntelligenceCore;
+use isp_speedkarma::core::config::{AppConfig, CustomServerConfig, FleetSite, LogLevel, SensitivityPreset, ThrottlingSensitivityConfig};
+use isp_speedkarma::core::app_state::{self, SharedAppState, OptimizationMode};
+use isp_speedkarma::core::alerts::{self, SpeedAlertWatcher};
+use isp_speedkarma::core::autostart::{self, AutoStartStatus};
+use isp_speedkarma::data::models::{OptimizationStrategy, SatisfactionFeedback, SpeedMeasurementPage, ThrottlingPatternSummary};
+use isp_speedkarma::data::repository::Repository;
+use isp_speedkarma::engine::{DisguiseSlot, Engine, Frontend, StealthSlot};
+use isp_speedkarma::data::consolidation;
+use isp_speedkarma::ui::tray::SystemTray;
+use isp_speedkarma::ui::panel::PanelInterface;
+use isp_speedkarma::ui::progress::start_progress_broadcaster;
+use isp_speedkarma::network::monitor::{BackgroundMonitor, ISPDetectionResult};
+use isp_speedkarma::network::{ThroughputKeeper, SpeedtestRunner, SpeedtestRetryQueue, SpeedtestSchedule, ConnectionTable, OutboundLimiter, DataUsageMeter, LiveThroughput};
+use isp_speedkarma::network::connections::ConnectionRow;
+use isp_speedkarma::network::calibration::PassiveCalibrationRoutine;
+use isp_speedkarma::network::iperf3::Iperf3Runner;
+use isp_speedkarma::network::traceroute::{self, PathChangeImpact};
+use isp_speedkarma::core::conflicts::ConflictReport;
+use isp_speedkarma::network::conflicts::ConflictWatcher;
+use isp_speedkarma::network::metered::{MeteredStatus, MeteredWatcher};
+use isp_speedkarma::network::connectivity::{ConnectivityStatus, ConnectivityWatcher};
+use isp_speedkarma::core::power::{PowerState, PowerWatcher};
+use isp_speedkarma::core::country_packs::{self, CountryPack};
+use isp_speedkarma::core::trial::{TrialProgress, TrialRunner};
+use isp_speedkarma::core::logging::{self, LogControl, RecentLogs};
+use isp_speedkarma::core::support::SupportBundle;
+use isp_speedkarma::core::events::SharedEventSink;
+use isp_speedkarma::core::shutdown::{self, Shutdown, SHUTDOWN_GRACE};
+use isp_speedkarma::core::supervisor::Supervisor;
+use isp_speedkarma::core::dataset::{self, TrainingDatasetStats};
+use isp_speedkarma::core::evaluation;
+use isp_speedkarma::core::canary;
+use isp_speedkarma::core::emergency;
+use isp_speedkarma::core::safe_mode::{SafeModeStatus, StartupGuard};
+use isp_speedkarma::core::control_api::{self, ControlBackend, SiteSummary};
+use isp_speedkarma::core::fleet::{self, FleetSummary, RemoteInstance, SiteReport};
+use isp_speedkarma::core::complaint::{self, ComplaintEvidence, ComplaintLetter, ComplaintRecipient, LetterLanguage};
+use isp_speedkarma::data::downsample::{ChartMetric, ChartSeries, DEFAULT_POINT_BUDGET, MAX_CHART_DAYS};
+use isp_speedkarma::data::improvement::{self, HistoryBucket, ImprovementHistory};
+use isp_speedkarma::data::export::{self, ExportFormat, ExportSummary, MeasurementFilter};
+use isp_speedkarma::core::model_share::{self, ModelImportSummary, SharedModel};
+use isp_speedkarma::network::calls::{CallInterlock, CallInterlockStatus};
+use isp_speedkarma::network::speedtest_retry::PendingRetry;
 use std::sync::Arc;
 use tauri::Manager;
 use tokio::sync::RwLock;
 
+/// Every command exposed to the webviews; the same list feeds the `dump_schema` catalog
+macro_rules! app_commands {
+    ($($name:ident),* $(,)?) => {
+        const COMMAND_NAMES: &[&str] = &[$(stringify!($name)),*];
+        macro_rules! invoke_handler {
+            () => { tauri::generate_handler![$($name),*] };
+        }
+    };
+}
+
+app_commands![
+    toggle_optimization,
+    get_optimization_state,
+    snooze_optimization,
+    emergency_stop,
+    release_emergency_stop,
+    get_safe_mode,
+    exit_safe_mode,
+    get_system_status,
+    open_advanced,
+    quit_app,
+    get_config,
+    set_min_data_days,
+    set_custom_servers,
+    export_config,
+    import_config,
+    set_throughput_keeper,
+    run_speedtest_once,
+    get_speedtest_retries,
+    get_speedtest_schedule,
+    calibrate_passive_estimates,
+    run_iperf3_baseline,
+    get_passive_calibrations,
+    set_disguise_mode,
+    set_low_data_mode,
+    get_module_toggles,
+    set_module_enabled,
+    set_quiet_hours,
+    get_data_usage,
+    get_live_throughput,
+    set_data_budget,
+    get_metered_status,
+    set_metered_connection,
+    list_network_interfaces,
+    set_monitored_interfaces,
+    get_connectivity_status,
+    get_power_state,
+    set_power_policy,
+    set_auto_start,
+    get_auto_start_status,
+    set_logging,
+    get_recent_logs,
+    get_status_codes,
+    set_speed_alert,
+    get_speed_alert_episodes,
+    get_qos_capability,
+    get_sqm_advice,
+    run_bufferbloat_test,
+    apply_fq_codel,
+    get_isp_detection,
+    should_prompt_feedback,
+    submit_satisfaction_feedback,
+    set_feedback_prompt,
+    get_conflict_status,
+    set_conflict_detection,
+    get_model_quality_history,
+    get_chart_series,
+    get_improvement_history,
+    get_speed_history,
+    get_throttling_patterns,
+    list_strategies,
+    create_strategy,
+    update_strategy,
+    delete_strategy,
+    set_active_strategy,
+    get_fleet_summary,
+    pair_router,
+    unpair_router,
+    get_router_summary,
+    router_toggle_optimization,
+    router_run_speedtest,
+    get_complaint_languages,
+    generate_complaint_letter,
+    export_measurements,
+    dump_schema,
+    preview_strategy_from_data,
+    create_strategy_from_data,
+    export_learned_model,
+    import_learned_model,
+    clear_imported_model,
+    get_server_map_data,
+    get_database_integrity,
+    get_call_interlock_status,
+    set_call_interlock,
+    get_country_packs,
+    apply_country_pack,
+    set_throttling_sensitivity,
+    start_optimization_trial,
+    cancel_optimization_trial,
+    get_optimization_trial_status,
+    get_connection_table,
+    create_support_bundle,
+    export_diagnostics,
+    get_consolidation_report,
+    get_training_dataset_stats,
+    get_route_changes,
+    get_strategy_decisions,
+];
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+    // Initialize logging; recent lines are also kept in memory for support bundles. The runtime is
+    // already up, so the logging settings are read without awaiting the async loader.
+    let recent_logs = RecentLogs::new();
+    let config = AppConfig::config_file_path().ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<AppConfig>(&content).ok())
+        .unwrap_or_default();
+    let log_control = logging::init(&config.advanced.logging, config.advanced.debug_logging, &logging::default_log_path(), recent_logs.clone());
     
     tauri::Builder::default()
+        .manage(recent_logs)
+        .manage(log_control)
         .system_tray(SystemTray::create_tray_menu())
-        .invoke_handler(tauri::generate_handler![
-            toggle_optimization,
-            get_optimization_state,
-            get_system_status,
-            open_advanced,
-            quit_app,
-            get_config,
-            set_min_data_days,
-            set_custom_servers,
-            export_config,
-            import_config,
-            set_throughput_keeper,
-            run_speedtest_once,
-            set_disguise_mode,
-        ])
+        .invoke_handler(invoke_handler!())
         .on_system_tray_event(|app, event| {
             // Handle system tray events asynchronously
             let app_handle = app.clone();
@@ -75,9 +204,16 @@ pub fn run() {
 
 #[tauri::command]
 async fn toggle_optimization(app: tauri::AppHandle) -> std::result::Result<(), String> {
-    let state = app.state::<crate::core::app_state::SharedAppState>();
+    let state = app.state::<isp_speedkarma::core::app_state::SharedAppState>();
     let mut guard = state.write().await;
-    guard.optimization_mode = match guard.optimization_mode { OptimizationMode::Enabled => OptimizationMode::Disabled, OptimizationMode::Disabled => OptimizationMode::Enabled };
+    if guard.stopped_by_user {
+        return Err("Emergency stop is engaged; re-enable traffic first".to_string());
+    }
+    if guard.safe_mode {
+        return Err("SpeedKarma is in safe mode after repeated crashes; leave safe mode first".to_string());
+    }
+    let next = match guard.optimization_mode { OptimizationMode::Enabled => OptimizationMode::Disabled, OptimizationMode::Disabled => OptimizationMode::Enabled };
+    guard.set_optimization_mode(next);
     // Start/stop throughput keeper for clarity, although it self-suspends when disabled
     if let Some(keeper) = app.try_state::<std::sync::Arc<ThroughputKeeper>>() {
         match guard.optimization_mode {
@@ -96,17 +232,99 @@ async fn toggle_optimization(app: tauri::AppHandle) -> std::result::Result<(), S
 
 #[tauri::command]
 async fn get_optimization_state(app: tauri::AppHandle) -> std::result::Result<serde_json::Value, String> {
-    let state = app.state::<crate::core::app_state::SharedAppState>();
+    let state = app.state::<isp_speedkarma::core::app_state::SharedAppState>();
     let guard = state.read().await;
-    let mode = match guard.optimization_mode { OptimizationMode::Enabled => "Enabled", OptimizationMode::Disabled => "Disabled" };
-    Ok(serde_json::json!({"mode": mode, "text": "Learning patterns"}))
+    Ok(guard.to_json())
+}
+
+/// Holds every traffic generator off for `minutes` without turning optimization off; 0 resumes now.
+/// Returns when the snooze ends.
+#[tauri::command]
+async fn snooze_optimization(app: tauri::AppHandle, minutes: u32) -> std::result::Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+    let until = app_state::snooze_end(chrono::Utc::now(), minutes);
+    app.state::<SharedAppState>().write().await.paused_until = until;
+    match until {
+        Some(until) => info!("Optimization snoozed until {}", until),
+        None => info!("Optimization snooze ended"),
+    }
+    Ok(until)
+}
+
+/// Panic switch: halts all generated traffic now and keeps it halted across restarts
+#[tauri::command]
+async fn emergency_stop(app: tauri::AppHandle) -> std::result::Result<(), String> {
+    let shared = app.state::<SharedAppState>();
+    let limiter = app.state::<OutboundLimiter>();
+    emergency::engage(&shared, &limiter).await.map_err(|e| e.to_string())?;
+    if let Some(keeper) = app.try_state::<Arc<ThroughputKeeper>>() {
+        keeper.stop().await;
+    }
+    if let Some(runner) = app.try_state::<Arc<TrialRunner>>() {
+        runner.cancel().await;
+    }
+    Ok(())
+}
+
+/// Explicit re-enable after an emergency stop; optimization stays off until switched on
+#[tauri::command]
+async fn release_emergency_stop(app: tauri::AppHandle) -> std::result::Result<(), String> {
+    let shared = app.state::<SharedAppState>();
+    let limiter = app.state::<OutboundLimiter>();
+    emergency::release(&shared, &limiter).await.map_err(|e| e.to_string())
+}
+
+/// Whether this start is in safe mode, and what it turned off
+#[tauri::command]
+async fn get_safe_mode(app: tauri::AppHandle) -> std::result::Result<SafeModeStatus, String> {
+    let active = app.state::<SharedAppState>().read().await.safe_mode;
+    Ok(match app.try_state::<SafeModeStatus>() {
+        Some(status) if active => status.inner().clone(),
+        Some(status) => SafeModeStatus { crashed_startups: status.crashed_startups, ..SafeModeStatus::default() },
+        None => SafeModeStatus::default(),
+    })
+}
+
+/// Turns the traffic generators back on and clears the crash count; optimization stays off until switched on
+#[tauri::command]
+async fn exit_safe_mode(app: tauri::AppHandle) -> std::result::Result<(), String> {
+    app.state::<SharedAppState>().write().await.safe_mode = false;
+    if let Some(guard) = app.try_state::<Arc<StartupGuard>>() {
+        guard.mark_stable().await;
+    }
+    info!("Left safe mode");
+    Ok(())
+}
+
+/// Binds the emergency stop to a global shortcut; a taken or invalid accelerator is only logged
+fn register_emergency_shortcut(app: &tauri::AppHandle, accelerator: &str) {
+    use tauri::GlobalShortcutManager;
+    if accelerator.is_empty() {
+        return;
+    }
+    let handle = app.clone();
+    let mut shortcuts = app.global_shortcut_manager();
+    let registered = shortcuts.register(accelerator, move || {
+        let handle = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = emergency_stop(handle.clone()).await {
+                tracing::warn!("Emergency stop failed: {}", e);
+                return;
+            }
+            isp_speedkarma::core::events::EventSink::notify(&handle, "SpeedKarma stopped", "All generated traffic is halted. Re-enable it from Advanced settings.");
+        });
+    });
+    if let Err(e) = registered {
+        tracing::warn!("Emergency stop shortcut {} unavailable: {}", accelerator, e);
+    }
 }
 
 #[tauri::command]
-async fn get_system_status(app: tauri::AppHandle) -> std::result::Result<crate::core::intelligence::SystemStatus, String> {
+async fn get_system_status(app: tauri::AppHandle) -> std::result::Result<isp_speedkarma::core::intelligence::SystemStatus, String> {
     let tray_state = app.state::<Arc<RwLock<SystemTray>>>();
     let tray = tray_state.read().await;
-    Ok(tray.get_current_status().await)
+    let mut status = tray.get_current_status().await;
+    status.tasks = app.try_state::<Supervisor>().map(|s| s.health()).unwrap_or_default();
+    Ok(status)
 }
 
 #[tauri::command]
@@ -117,7 +335,14 @@ async fn open_advanced(app: tauri::AppHandle) -> std::result::Result<(), String>
 }
 
 #[tauri::command]
-async fn quit_app(app: tauri::AppHandle) -> std::result::Result<(), String> { app.exit(0); Ok(()) }
+async fn quit_app(app: tauri::AppHandle) -> std::result::Result<(), String> {
+    // Let the loops finish their round and the database flush before the process ends
+    if let Some(shutdown) = app.try_state::<Shutdown>() {
+        shutdown.run(SHUTDOWN_GRACE).await;
+    }
+    app.exit(0);
+    Ok(())
+}
 
 #[tauri::command]
 async fn get_config(_app: tauri::AppHandle) -> std::result::Result<AppConfig, String> { AppConfig::load().await.map_err(|e| e.to_string()) }
@@ -130,35 +355,41 @@ async fn set_min_data_days(_app: tauri::AppHandle, days: u32) -> std::result::Re
 }
 
 #[tauri::command]
-async fn set_custom_servers(_app: tauri::AppHandle, servers: Vec<String>) -> std::result::Result<(), String> {
+async fn set_custom_servers(app: tauri::AppHandle, servers: Vec<CustomServerConfig>) -> std::result::Result<(), String> {
     let mut cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
     cfg.advanced.custom_servers = servers;
-    cfg.save().await.map_err(|e| e.to_string())
+    cfg.validate().map_err(|e| e.to_string())?;
+    cfg.save().await.map_err(|e| e.to_string())?;
+    let rows: Vec<_> = cfg.advanced.custom_servers.iter().map(CustomServerConfig::to_server).collect();
+    let repo = app.state::<Arc<Repository>>();
+    repo.replace_custom_speedtest_servers(&rows).await.map_err(|e| e.to_string())?;
+    Ok(())
 }
 
 #[tauri::command]
 async fn export_config(_app: tauri::AppHandle) -> std::result::Result<String, String> {
     let cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
-    serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())
+    serde_json::to_string_pretty(&cfg.redacted()).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn import_config(_app: tauri::AppHandle, json: String) -> std::result::Result<(), String> {
-    let cfg: AppConfig = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let mut cfg: AppConfig = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    cfg.restore_redacted(&AppConfig::load().await.map_err(|e| e.to_string())?);
     cfg.validate().map_err(|e| e.to_string())?;
     cfg.save().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn set_throughput_keeper(app: tauri::AppHandle, cfg: crate::core::config::ThroughputKeeperConfig) -> std::result::Result<(), String> {
+async fn set_throughput_keeper(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::ThroughputKeeperConfig) -> std::result::Result<(), String> {
     // Save to config file
     let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
     full.advanced.throughput_keeper = cfg.clone();
     full.save().await.map_err(|e| e.to_string())?;
 
-    // Notify running keeper if present
+    // Notify running keeper if present (low-data mode may still cap it)
     if let Some(keeper) = app.try_state::<std::sync::Arc<ThroughputKeeper>>() {
-        keeper.update_config(cfg).await;
+        keeper.update_config(full.effective().advanced.throughput_keeper).await;
     }
     Ok(())
 }
@@ -167,194 +398,1137 @@ async fn set_throughput_keeper(app: tauri::AppHandle, cfg: crate::core::config::
 async fn run_speedtest_once(app: tauri::AppHandle) -> std::result::Result<(), String> {
     let repo = app.state::<Arc<Repository>>();
     let shared = app.state::<SharedAppState>();
-    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?.advanced.speedtest_runner;
-    let runner = SpeedtestRunner::new(app.clone(), Arc::clone(&repo), Arc::clone(&shared), cfg);
-    tokio::spawn(async move { let _ = runner.run_once().await; });
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?.effective().advanced.speedtest_runner;
+    let mut runner = SpeedtestRunner::new(Arc::new(app.clone()), Arc::clone(&repo), Arc::clone(&shared), cfg);
+    if let Some(retries) = app.try_state::<Arc<SpeedtestRetryQueue>>() {
+        runner = runner.with_retries(Arc::clone(&retries), 0);
+    }
+    if let Some(usage) = app.try_state::<DataUsageMeter>() {
+        runner = runner.with_usage_meter(usage.inner().clone());
+    }
+    if let Some(limiter) = app.try_state::<OutboundLimiter>() {
+        runner = runner.with_limiter(limiter.inner().clone());
+    }
+    match app.try_state::<Arc<CallInterlock>>() {
+        Some(interlock) => { interlock.run_or_defer(runner).await; }
+        None => { tokio::spawn(async move { let _ = runner.run_once().await; }); }
+    }
     Ok(())
 }
 
+#[tauri::command]
+async fn get_speedtest_retries(app: tauri::AppHandle) -> std::result::Result<Vec<PendingRetry>, String> {
+    match app.try_state::<Arc<SpeedtestRetryQueue>>() {
+        Some(retries) => Ok(retries.pending().await),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Scheduled speed tests with their next run
+#[tauri::command]
+async fn get_speedtest_schedule() -> std::result::Result<Vec<isp_speedkarma::network::speedtest_schedule::ScheduledSpeedtest>, String> {
+    SpeedtestSchedule::upcoming().await.map_err(|e| e.to_string())
+}
+
+/// Runs a speed test next to the interface counters and stores the current network's correction
+#[tauri::command]
+async fn calibrate_passive_estimates(app: tauri::AppHandle) -> std::result::Result<isp_speedkarma::data::models::PassiveCalibration, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let shared = app.state::<SharedAppState>();
+    let mut routine = PassiveCalibrationRoutine::new(Arc::new(app.clone()), Arc::clone(&repo), Arc::clone(&shared));
+    if let Some(usage) = app.try_state::<DataUsageMeter>() {
+        routine = routine.with_usage_meter(usage.inner().clone());
+    }
+    if let Some(limiter) = app.try_state::<OutboundLimiter>() {
+        routine = routine.with_limiter(limiter.inner().clone());
+    }
+    routine.run().await.map_err(|e| e.to_string())
+}
+
+/// Runs an iperf3 baseline against the configured server and returns the stored measurement
+#[tauri::command]
+async fn run_iperf3_baseline(app: tauri::AppHandle) -> std::result::Result<isp_speedkarma::data::models::SpeedMeasurement, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let shared = app.state::<SharedAppState>();
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?.effective().advanced.iperf3;
+    let mut runner = Iperf3Runner::new(Arc::clone(&repo), Arc::clone(&shared), cfg);
+    if let Some(usage) = app.try_state::<DataUsageMeter>() {
+        runner = runner.with_usage_meter(usage.inner().clone());
+    }
+    if let Some(limiter) = app.try_state::<OutboundLimiter>() {
+        runner = runner.with_limiter(limiter.inner().clone());
+    }
+    runner.run_once().await.map_err(|e| e.to_string())
+}
+
+/// Stored corrections of passive estimates, one per network
+#[tauri::command]
+async fn get_passive_calibrations(app: tauri::AppHandle) -> std::result::Result<Vec<isp_speedkarma::data::models::PassiveCalibration>, String> {
+    let repo = app.state::<Arc<Repository>>();
+    repo.get_passive_calibrations().await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn set_disguise_mode(app: tauri::AppHandle, enabled: bool) -> std::result::Result<(), String> {
     let mut cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
     cfg.advanced.disguise_mode.enabled = enabled;
     cfg.save().await.map_err(|e| e.to_string())?;
-    // Start/stop background disguise task
-    if enabled {
-        if let (Some(repo), Some(shared)) = (app.try_state::<Arc<Repository>>(), app.try_state::<SharedAppState>()) {
-            let proxy = std::sync::Arc::new(DisguiseProxy::new(app.clone(), Arc::clone(&repo), Arc::clone(&shared), cfg.advanced.disguise_mode.clone()));
-            proxy.clone().start();
-            app.manage(proxy);
+    let Some(slot) = app.try_state::<DisguiseSlot>() else { return Ok(()) };
+    let mut running = slot.lock().await;
+    if !enabled {
+        if let Some(proxy) = running.take() {
+            proxy.stop();
+        }
+        return Ok(());
+    }
+    if running.as_ref().is_some_and(|proxy| !proxy.is_stopped()) {
+        return Ok(());
+    }
+    let events: SharedEventSink = Arc::new(app.clone());
+    let proxy = Engine::start_disguise(
+        &cfg.effective(),
+        &events,
+        &app.state::<Arc<Repository>>(),
+        &app.state::<SharedAppState>(),
+        &app.state::<Supervisor>(),
+        &app.state::<Shutdown>().token(),
+        &app.state::<OutboundLimiter>(),
+        &app.state::<DataUsageMeter>(),
+    );
+    *running = Some(proxy);
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_low_data_mode(app: tauri::AppHandle, enabled: bool) -> std::result::Result<(), String> {
+    let mut cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    cfg.advanced.low_data_mode.enabled = enabled;
+    cfg.save().await.map_err(|e| e.to_string())?;
+    // Keeper picks up the new budget live; monitor cadence applies on next start
+    if let Some(keeper) = app.try_state::<std::sync::Arc<ThroughputKeeper>>() {
+        keeper.update_config(cfg.effective().advanced.throughput_keeper).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_module_toggles(_app: tauri::AppHandle) -> std::result::Result<isp_speedkarma::core::config::ModuleToggles, String> {
+    Ok(AppConfig::load().await.map_err(|e| e.to_string())?.modules)
+}
+
+#[tauri::command]
+async fn set_module_enabled(app: tauri::AppHandle, module: String, enabled: bool) -> std::result::Result<(), String> {
+    let mut cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    cfg.modules.set(&module, enabled).map_err(|e| e.to_string())?;
+    cfg.save().await.map_err(|e| e.to_string())?;
+    if cfg.modules.is_detection_only() {
+        info!("Detection-only mode: no generated traffic");
+    }
+    // Apply live: background loops read the switches from shared state every cycle
+    if let Some(shared) = app.try_state::<SharedAppState>() {
+        shared.write().await.modules = cfg.modules;
+    }
+    Ok(())
+}
+
+/// Per-second throughput of the last ~15 minutes, newer than `since` when given
+#[tauri::command]
+async fn get_live_throughput(app: tauri::AppHandle, since: Option<chrono::DateTime<chrono::Utc>>) -> std::result::Result<Vec<isp_speedkarma::network::live::LiveSample>, String> {
+    Ok(app.state::<LiveThroughput>().samples(since))
+}
+
+#[tauri::command]
+async fn get_data_usage(app: tauri::AppHandle) -> std::result::Result<isp_speedkarma::network::usage::DataUsage, String> {
+    let usage = app.try_state::<DataUsageMeter>().ok_or("Data usage meter is not running")?;
+    usage.usage().await.map_err(|e| e.to_string())
+}
+
+/// Saves the monthly data cap; generation stops or resumes right away to match it
+#[tauri::command]
+async fn set_data_budget(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::DataBudgetConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.advanced.data_budget = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(usage) = app.try_state::<DataUsageMeter>() {
+        usage.update_budget(cfg).await.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_metered_status(app: tauri::AppHandle) -> std::result::Result<Option<MeteredStatus>, String> {
+    match app.try_state::<Arc<MeteredWatcher>>() {
+        Some(watcher) => Ok(watcher.status().await),
+        None => Ok(None),
+    }
+}
+
+/// Latest connectivity check: online, behind a captive portal (with its login URL) or offline
+#[tauri::command]
+async fn get_connectivity_status(app: tauri::AppHandle) -> std::result::Result<Option<ConnectivityStatus>, String> {
+    match app.try_state::<Arc<ConnectivityWatcher>>() {
+        Some(watcher) => Ok(watcher.status().await),
+        None => Ok(None),
+    }
+}
+
+/// Saves the metered-connection settings; `treat_as` overrides what the platform reports
+#[tauri::command]
+async fn set_metered_connection(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::MeteredConnectionConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.advanced.metered = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(watcher) = app.try_state::<Arc<MeteredWatcher>>() {
+        watcher.update_config(cfg).await;
+    }
+    Ok(())
+}
+
+/// Glossary of states, risk levels and alert codes, so every surface uses the same wording
+#[tauri::command]
+async fn get_status_codes() -> std::result::Result<Vec<isp_speedkarma::core::status_codes::StatusCode>, String> {
+    Ok(isp_speedkarma::core::status_codes::all().to_vec())
+}
+
+#[tauri::command]
+async fn get_power_state(app: tauri::AppHandle) -> std::result::Result<Option<PowerState>, String> {
+    match app.try_state::<Arc<PowerWatcher>>() {
+        Some(watcher) => Ok(watcher.state().await),
+        None => Ok(None),
+    }
+}
+
+/// Saves the power policy; the keeper, monitor and decision engine pick it up on their next round
+#[tauri::command]
+async fn set_power_policy(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::PowerPolicyConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.power_policy = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(watcher) = app.try_state::<Arc<PowerWatcher>>() {
+        watcher.update_config(cfg).await;
+    }
+    Ok(())
+}
+
+/// Saves the logging settings; the level and rotation limits apply at once, turning the file on or off at the next start
+#[tauri::command]
+async fn set_logging(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::LoggingConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.advanced.logging = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(logs) = app.try_state::<LogControl>() {
+        logs.apply(&cfg, full.advanced.debug_logging);
+    }
+    Ok(())
+}
+
+/// The last `lines` log lines at `level` or more severe (default: all), oldest first, for bug reports
+#[tauri::command]
+async fn get_recent_logs(app: tauri::AppHandle, lines: usize, level: Option<LogLevel>) -> std::result::Result<Vec<String>, String> {
+    let level = level.unwrap_or(LogLevel::Trace).to_level();
+    match app.try_state::<LogControl>() {
+        Some(logs) => Ok(logs.recent(lines, level)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Saves `AppConfig.auto_start` and registers or removes the login entry to match
+#[tauri::command]
+async fn set_auto_start(app: tauri::AppHandle, enabled: bool) -> std::result::Result<AutoStartStatus, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let mut config = repo.get_app_config().await.map_err(|e| e.to_string())?.unwrap_or_default();
+    autostart::apply(enabled).await.map_err(|e| e.to_string())?;
+    config.auto_start = enabled;
+    config.updated_at = chrono::Utc::now();
+    repo.save_app_config(&config).await.map_err(|e| e.to_string())?;
+    Ok(autostart::status(enabled).await)
+}
+
+#[tauri::command]
+async fn get_auto_start_status(app: tauri::AppHandle) -> std::result::Result<AutoStartStatus, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let config = repo.get_app_config().await.map_err(|e| e.to_string())?;
+    Ok(autostart::status(config.is_some_and(|c| c.auto_start)).await)
+}
+
+/// Interfaces on this machine and whether passive monitoring counts them, for the interface picker
+#[tauri::command]
+async fn list_network_interfaces(app: tauri::AppHandle) -> std::result::Result<Vec<isp_speedkarma::network::monitor::NetworkInterfaceInfo>, String> {
+    let selection = match app.try_state::<SharedAppState>() {
+        Some(shared) => shared.read().await.monitored_interfaces.clone(),
+        None => AppConfig::load().await.map_err(|e| e.to_string())?.monitoring.interfaces,
+    };
+    isp_speedkarma::network::monitor::list_network_interfaces(&selection).await.map_err(|e| e.to_string())
+}
+
+/// Saves which interfaces passive monitoring counts; the next sample uses them
+#[tauri::command]
+async fn set_monitored_interfaces(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::InterfaceSelectionConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.monitoring.interfaces = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(shared) = app.try_state::<SharedAppState>() {
+        shared.write().await.monitored_interfaces = cfg;
+    }
+    Ok(())
+}
+
+/// Saves the quiet-hours window and applies it to the running generators
+#[tauri::command]
+async fn set_quiet_hours(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::QuietHoursConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.quiet_hours = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(shared) = app.try_state::<SharedAppState>() {
+        shared.write().await.quiet_hours = cfg;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_speed_alert(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::SpeedAlertConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.alerts = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(watcher) = app.try_state::<Arc<SpeedAlertWatcher>>() {
+        watcher.update_config(cfg).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_conflict_status(app: tauri::AppHandle) -> std::result::Result<Option<ConflictReport>, String> {
+    let shared = app.state::<SharedAppState>();
+    let conflict = shared.read().await.conflict.clone();
+    Ok(conflict)
+}
+
+#[tauri::command]
+async fn set_conflict_detection(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::ConflictDetectionConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.advanced.conflict_detection = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(watcher) = app.try_state::<Arc<ConflictWatcher>>() {
+        watcher.update_config(cfg).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_call_interlock_status(app: tauri::AppHandle) -> std::result::Result<Option<CallInterlockStatus>, String> {
+    match app.try_state::<Arc<CallInterlock>>() {
+        Some(interlock) => Ok(Some(interlock.status().await)),
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+async fn set_call_interlock(app: tauri::AppHandle, cfg: isp_speedkarma::core::config::CallInterlockConfig) -> std::result::Result<(), String> {
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.advanced.call_interlock = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    if let Some(interlock) = app.try_state::<Arc<CallInterlock>>() {
+        interlock.update_config(cfg).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_throttling_sensitivity(_app: tauri::AppHandle, cfg: ThrottlingSensitivityConfig) -> std::result::Result<ThrottlingSensitivityConfig, String> {
+    // Named presets always carry their own values; only Custom keeps what was sent
+    let cfg = match cfg.preset {
+        SensitivityPreset::Custom => cfg,
+        preset => ThrottlingSensitivityConfig::preset(preset),
+    };
+    let mut full = AppConfig::load().await.map_err(|e| e.to_string())?;
+    full.monitoring.throttling_sensitivity = cfg.clone();
+    full.validate().map_err(|e| e.to_string())?;
+    full.save().await.map_err(|e| e.to_string())?;
+    Ok(cfg)
+}
+
+#[tauri::command]
+async fn start_optimization_trial(app: tauri::AppHandle) -> std::result::Result<TrialProgress, String> {
+    let runner = app.try_state::<Arc<TrialRunner>>().ok_or("Trials are not available yet")?;
+    let progress = Arc::clone(&runner).start().await.map_err(|e| e.to_string())?;
+    // The keeper may have been stopped by an earlier toggle; it suspends itself again when the trial ends
+    if let Some(keeper) = app.try_state::<Arc<ThroughputKeeper>>() {
+        Arc::clone(&keeper).start();
+    }
+    Ok(progress)
+}
+
+#[tauri::command]
+async fn cancel_optimization_trial(app: tauri::AppHandle) -> std::result::Result<bool, String> {
+    match app.try_state::<Arc<TrialRunner>>() {
+        Some(runner) => Ok(runner.cancel().await),
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+async fn get_optimization_trial_status(app: tauri::AppHandle) -> std::result::Result<TrialProgress, String> {
+    match app.try_state::<Arc<TrialRunner>>() {
+        Some(runner) => Ok(runner.status().await),
+        None => Ok(TrialProgress::default()),
+    }
+}
+
+#[tauri::command]
+async fn get_connection_table(app: tauri::AppHandle) -> std::result::Result<Vec<ConnectionRow>, String> {
+    Ok(app.try_state::<ConnectionTable>().map(|table| table.snapshot()).unwrap_or_default())
+}
+
+/// Live state gathered for support bundles and diagnostics exports
+async fn collect_diagnostics(app: &tauri::AppHandle) -> serde_json::Value {
+    serde_json::json!({
+        "system_status": get_system_status(app.clone()).await.ok(),
+        "optimization_state": get_optimization_state(app.clone()).await.ok(),
+        "database_integrity": get_database_integrity(app.clone()).await.ok().flatten(),
+        "consolidation": get_consolidation_report(app.clone()).await.ok().flatten(),
+        "isp_detection": get_isp_detection(app.clone()).await.ok().flatten(),
+        "conflicts": get_conflict_status(app.clone()).await.ok().flatten(),
+        "call_interlock": get_call_interlock_status(app.clone()).await.ok().flatten(),
+        "optimization_trial": get_optimization_trial_status(app.clone()).await.ok(),
+        "connections": get_connection_table(app.clone()).await.ok(),
+        "module_toggles": get_module_toggles(app.clone()).await.ok(),
+    })
+}
+
+/// Writes a redacted ZIP of diagnostics, recent logs, config and schema version; returns its path
+#[tauri::command]
+async fn create_support_bundle(app: tauri::AppHandle) -> std::result::Result<String, String> {
+    let diagnostics = collect_diagnostics(&app).await;
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    let logs = app.try_state::<RecentLogs>().map(|logs| logs.lines()).unwrap_or_default();
+    let schema_version = match app.try_state::<Arc<Repository>>() {
+        Some(repo) => repo.schema_version().await.map_err(|e| e.to_string())?,
+        None => 0,
+    };
+    let bundle = SupportBundle::new(diagnostics, &cfg, &logs, schema_version).map_err(|e| e.to_string())?;
+    let path = bundle.default_path();
+    bundle.write_to(&path).map_err(|e| e.to_string())?;
+    info!("Support bundle written to {}", path.display());
+    Ok(path.display().to_string())
+}
+
+/// Log lines included in a diagnostics export
+const DIAGNOSTICS_LOG_LINES: usize = 2000;
+
+/// Everything a bug report needs in one redacted ZIP: the support bundle plus logs from the log
+/// files, database statistics (no measurements), ISP profile, current strategy and stealth/DPI
+/// stats. Written to `path`, or to the downloads folder without one; returns the path.
+#[tauri::command]
+async fn export_diagnostics(app: tauri::AppHandle, path: Option<String>) -> std::result::Result<String, String> {
+    let repo = Arc::clone(&app.state::<Arc<Repository>>());
+    let diagnostics = collect_diagnostics(&app).await;
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    let logs = match app.try_state::<LogControl>() {
+        Some(logs) => logs.recent(DIAGNOSTICS_LOG_LINES, tracing::Level::TRACE),
+        None => app.try_state::<RecentLogs>().map(|logs| logs.lines()).unwrap_or_default(),
+    };
+    let database = repo.get_database_stats().await.map_err(|e| e.to_string())?;
+    let last_week = repo.get_speed_statistics(7).await.ok();
+    let isp_profile = repo.get_current_isp_profile().await.map_err(|e| e.to_string())?;
+    let pinned = app.state::<SharedAppState>().read().await.active_strategy_override.clone();
+    let strategy = match &pinned {
+        Some(strategy) => Some(strategy.clone()),
+        None => repo.get_best_optimization_strategy().await.map_err(|e| e.to_string())?,
+    };
+    let stealth = match app.try_state::<StealthSlot>().and_then(|slot| slot.get().cloned()) {
+        Some(watchdog) => serde_json::json!({
+            "stats": watchdog.engine().get_stealth_stats().await,
+            "supervisor": app.try_state::<Supervisor>().and_then(|s| s.health().into_iter().find(|task| task.name == "stealth")),
+        }),
+        None => serde_json::Value::Null,
+    };
+
+    let bundle = SupportBundle::new(diagnostics, &cfg, &logs, database.schema_version).map_err(|e| e.to_string())?
+        .with_kind("diagnostics")
+        .with_section("database", serde_json::json!({ "stats": database, "last_7_days": last_week }))
+        .with_section("isp_profile", serde_json::to_value(isp_profile).map_err(|e| e.to_string())?)
+        .with_section("strategy", serde_json::json!({ "pinned": pinned.is_some(), "strategy": strategy }))
+        .with_section("stealth", stealth);
+    let path = path.map(std::path::PathBuf::from).unwrap_or_else(|| bundle.default_path());
+    bundle.write_to(&path).map_err(|e| e.to_string())?;
+    info!("Diagnostics written to {}", path.display());
+    Ok(path.display().to_string())
+}
+
+#[tauri::command]
+async fn get_country_packs() -> std::result::Result<Vec<CountryPack>, String> {
+    Ok(country_packs::bundled_packs())
+}
+
+#[tauri::command]
+async fn apply_country_pack(app: tauri::AppHandle, country_code: String) -> std::result::Result<CountryPack, String> {
+    let pack = country_packs::pack_for_region(&country_code).ok_or_else(|| format!("No defaults for {}", country_code))?;
+    let mut cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    pack.apply_to(&mut cfg);
+    cfg.validate().map_err(|e| e.to_string())?;
+    cfg.save().await.map_err(|e| e.to_string())?;
+    if let Some(keeper) = app.try_state::<Arc<ThroughputKeeper>>() {
+        keeper.update_config(cfg.effective().advanced.throughput_keeper).await;
+    }
+    Ok(pack)
+}
+
+/// One metric over the last `days`, reduced server-side to at most `max_points` for the panel charts
+#[tauri::command]
+async fn get_chart_series(app: tauri::AppHandle, metric: ChartMetric, days: u32, max_points: Option<usize>) -> std::result::Result<ChartSeries, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let until = chrono::Utc::now();
+    let since = until - chrono::Duration::days(days.clamp(1, MAX_CHART_DAYS) as i64);
+    repo.get_chart_series(metric, since, until, max_points.unwrap_or(DEFAULT_POINT_BUDGET)).await.map_err(|e| e.to_string())
+}
+
+/// Improvement factor, confidence and sample counts per `bucket` over the last `days`, for the trend chart
+#[tauri::command]
+async fn get_improvement_history(app: tauri::AppHandle, days: u32, bucket: HistoryBucket) -> std::result::Result<ImprovementHistory, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let until = chrono::Utc::now();
+    let since = bucket.start(until - chrono::Duration::days(days.clamp(1, improvement::MAX_HISTORY_DAYS) as i64));
+    let aggregates = repo.get_hourly_aggregates_between(since, until).await.map_err(|e| e.to_string())?;
+    Ok(improvement::improvement_history(&aggregates, bucket, since, until))
+}
+
+/// One page of raw measurements, newest first, so the panel can page through history instead of loading it all
+#[tauri::command]
+async fn get_speed_history(app: tauri::AppHandle, page: u32, page_size: u32, filter: Option<MeasurementFilter>) -> std::result::Result<SpeedMeasurementPage, String> {
+    let repo = app.state::<Arc<Repository>>();
+    repo.get_speed_measurements_paged(&filter.unwrap_or_default(), page, page_size).await.map_err(|e| e.to_string())
+}
+
+/// Throttling patterns stored for the current ISP, most confident first; empty until the ISP is detected
+#[tauri::command]
+async fn get_throttling_patterns(app: tauri::AppHandle) -> std::result::Result<Vec<ThrottlingPatternSummary>, String> {
+    let repo = app.state::<Arc<Repository>>();
+    repo.get_current_throttling_summaries().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_strategies(app: tauri::AppHandle) -> std::result::Result<Vec<OptimizationStrategy>, String> {
+    let repo = app.state::<Arc<Repository>>();
+    repo.get_optimization_strategies().await.map_err(|e| e.to_string())
+}
+
+/// Saves a hand-made strategy unscored, so it is not picked over learned ones until it has been measured
+#[tauri::command]
+async fn create_strategy(app: tauri::AppHandle, strategy: OptimizationStrategy) -> std::result::Result<OptimizationStrategy, String> {
+    strategy.validate().map_err(|e| e.to_string())?;
+    let repo = app.state::<Arc<Repository>>();
+    let strategy = OptimizationStrategy { id: None, effectiveness_score: None, created_at: chrono::Utc::now(), ..strategy };
+    let id = repo.save_optimization_strategy(&strategy).await.map_err(|e| e.to_string())?;
+    info!("Created strategy '{}'", strategy.name);
+    Ok(OptimizationStrategy { id: Some(id), ..strategy })
+}
+
+#[tauri::command]
+async fn update_strategy(app: tauri::AppHandle, strategy: OptimizationStrategy) -> std::result::Result<OptimizationStrategy, String> {
+    strategy.validate().map_err(|e| e.to_string())?;
+    let repo = app.state::<Arc<Repository>>();
+    repo.update_optimization_strategy(&strategy).await.map_err(|e| e.to_string())?;
+    let id = strategy.id.unwrap_or_default();
+    let updated = repo.get_optimization_strategy(id).await.map_err(|e| e.to_string())?.ok_or_else(|| format!("No strategy with id {}", id))?;
+    // A pinned strategy picks up the edit right away
+    let shared = app.state::<SharedAppState>();
+    let mut state = shared.write().await;
+    if let Some(pinned) = state.active_strategy_override.as_mut().filter(|p| p.id == Some(id)) {
+        *pinned = updated.clone();
+    }
+    Ok(updated)
+}
+
+/// Past decisions keep the strategy's name, so history still reads after a delete
+#[tauri::command]
+async fn delete_strategy(app: tauri::AppHandle, id: i64) -> std::result::Result<(), String> {
+    let repo = app.state::<Arc<Repository>>();
+    if !repo.delete_optimization_strategy(id).await.map_err(|e| e.to_string())? {
+        return Err(format!("No strategy with id {}", id));
+    }
+    let shared = app.state::<SharedAppState>();
+    let mut state = shared.write().await;
+    if state.active_strategy_override.as_ref().is_some_and(|p| p.id == Some(id)) {
+        state.active_strategy_override = None;
+        info!("Deleted the pinned strategy; automatic selection resumes");
+    }
+    info!("Deleted strategy {}", id);
+    Ok(())
+}
+
+/// Pins a strategy for the stealth engine, keeper and speed tests, bypassing automatic
+/// selection until the app restarts; `None` hands the choice back to the decision engine
+#[tauri::command]
+async fn set_active_strategy(app: tauri::AppHandle, strategy_id: Option<i64>) -> std::result::Result<Option<OptimizationStrategy>, String> {
+    let strategy = match strategy_id {
+        Some(id) => {
+            let repo = app.state::<Arc<Repository>>();
+            let strategy = repo.get_optimization_strategy(id).await.map_err(|e| e.to_string())?.ok_or_else(|| format!("No strategy with id {}", id))?;
+            strategy.validate().map_err(|e| e.to_string())?;
+            Some(strategy)
         }
+        None => None,
+    };
+    match &strategy {
+        Some(s) => info!("Strategy '{}' pinned by the user", s.name),
+        None => info!("Strategy pin cleared; automatic selection resumes"),
+    }
+    app.state::<SharedAppState>().write().await.active_strategy_override = strategy.clone();
+    Ok(strategy)
+}
+
+/// Polls every site in `advanced.fleet` for the operator view; unreachable sites are listed with their error
+#[tauri::command]
+async fn get_fleet_summary(_app: tauri::AppHandle) -> std::result::Result<FleetSummary, String> {
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    fleet::poll(&cfg.advanced.fleet).await.map_err(|e| e.to_string())
+}
+
+/// The paired router's control API; an error when no router is paired
+async fn paired_router() -> std::result::Result<RemoteInstance, String> {
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    let site = cfg.advanced.paired_router.ok_or("No router is paired")?;
+    RemoteInstance::new(site).map_err(|e| e.to_string())
+}
+
+/// Pairs with a router's control API once it answers with `token`, and returns its summary
+#[tauri::command]
+async fn pair_router(url: String, token: String) -> std::result::Result<SiteSummary, String> {
+    let site = FleetSite { name: "Router".to_string(), url: url.trim().to_string(), token: token.trim().to_string() };
+    let summary = RemoteInstance::new(site.clone()).map_err(|e| e.to_string())?.summary().await.map_err(|e| format!("Router did not answer: {}", e))?;
+    let mut cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    cfg.advanced.paired_router = Some(site);
+    cfg.save().await.map_err(|e| e.to_string())?;
+    info!("Paired with the router at {}", url.trim());
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn unpair_router() -> std::result::Result<(), String> {
+    let mut cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    cfg.advanced.paired_router = None;
+    cfg.save().await.map_err(|e| e.to_string())
+}
+
+/// The paired router's summary or why it is unreachable; `None` when no router is paired
+#[tauri::command]
+async fn get_router_summary() -> std::result::Result<Option<SiteReport>, String> {
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    let Some(site) = cfg.advanced.paired_router else { return Ok(None) };
+    Ok(Some(RemoteInstance::new(site).map_err(|e| e.to_string())?.report().await))
+}
+
+#[tauri::command]
+async fn router_toggle_optimization() -> std::result::Result<serde_json::Value, String> {
+    paired_router().await?.toggle_optimization().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn router_run_speedtest() -> std::result::Result<(), String> {
+    paired_router().await?.run_speedtest().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_complaint_languages() -> std::result::Result<Vec<LetterLanguage>, String> {
+    Ok(complaint::languages())
+}
+
+/// Complaint letter to the ISP or regulator built from the last `days` (30 by default, at most 365)
+/// of speed tests run without optimization, measured against the advertised `plan_mbps`
+#[tauri::command]
+async fn generate_complaint_letter(
+    app: tauri::AppHandle,
+    plan_mbps: f64,
+    recipient: ComplaintRecipient,
+    language: Option<String>,
+    days: Option<u32>,
+) -> std::result::Result<ComplaintLetter, String> {
+    if plan_mbps <= 0.0 {
+        return Err("Enter the download speed your plan advertises".to_string());
+    }
+    let repo = app.state::<Arc<Repository>>();
+    let days = days.unwrap_or(complaint::DEFAULT_EVIDENCE_DAYS).clamp(1, complaint::MAX_EVIDENCE_DAYS);
+    let since = chrono::Utc::now() - chrono::Duration::days(days as i64);
+    let measurements = without_vpn(&repo.get_speed_measurements_since(since).await.map_err(|e| e.to_string())?);
+    let profile = repo.get_current_isp_profile().await.map_err(|e| e.to_string())?;
+    let patterns = match profile.as_ref().and_then(|p| p.id) {
+        Some(id) => repo.get_throttling_patterns_for_isp(id).await.map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    // Detection's placeholder would read as a name in the letter
+    let isp_name = profile.map(|p| p.name).filter(|name| name != "Unknown ISP");
+    let evidence = ComplaintEvidence::gather(isp_name.as_deref(), plan_mbps, &measurements, &patterns)
+        .ok_or_else(|| "No speed tests without optimization in this period yet; run a few speed tests first".to_string())?;
+    complaint::render(evidence, language.as_deref().unwrap_or("en"), recipient).map_err(|e| e.to_string())
+}
+
+/// Writes measurement history as CSV or JSON to `path`, or to the downloads folder without one
+#[tauri::command]
+async fn export_measurements(app: tauri::AppHandle, format: ExportFormat, filter: Option<MeasurementFilter>, path: Option<String>) -> std::result::Result<ExportSummary, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let path = path.map(std::path::PathBuf::from).unwrap_or_else(|| export::default_export_path(format, chrono::Utc::now()));
+    export::export_measurements(&repo, &path, format, &filter.unwrap_or_default()).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_model_quality_history(app: tauri::AppHandle, days: u32) -> std::result::Result<Vec<isp_speedkarma::data::models::ModelQualityMetric>, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let days = days.clamp(1, evaluation::MAX_QUALITY_HISTORY_DAYS);
+    let since = (chrono::Utc::now() - chrono::Duration::days(days as i64)).date_naive();
+    repo.get_model_quality_metrics_since(since).await.map_err(|e| e.to_string())
+}
+
+/// Trains on the stored history and synthesizes a strategy from it, without saving
+async fn synthesize_strategy_from_data(repo: Arc<Repository>) -> isp_speedkarma::core::error::Result<StrategyProposal> {
+    let cfg = AppConfig::load().await?.effective();
+    let mut intelligence = DefaultIntelligenceCore::with_min_learning_days(repo, cfg.auto_optimization.min_data_days);
+    intelligence.set_priors_path(model_share::default_priors_path());
+    intelligence.train_model().await?;
+    intelligence.synthesize_strategy(&cfg.advanced.throughput_keeper).await
+}
+
+#[tauri::command]
+async fn preview_strategy_from_data(app: tauri::AppHandle) -> std::result::Result<StrategyProposal, String> {
+    let repo = app.state::<Arc<Repository>>();
+    synthesize_strategy_from_data(Arc::clone(&repo)).await.map_err(|e| e.user_message())
+}
+
+#[tauri::command]
+async fn create_strategy_from_data(app: tauri::AppHandle, activate: bool) -> std::result::Result<StrategyProposal, String> {
+    let repo = Arc::clone(&app.state::<Arc<Repository>>());
+    let proposal = synthesize_strategy_from_data(Arc::clone(&repo)).await.map_err(|e| e.user_message())?;
+    let mut intelligence = DefaultIntelligenceCore::new(repo);
+    intelligence.set_strategy_canary(AppConfig::load().await.map(|c| c.auto_optimization.strategy_canary).unwrap_or_default());
+    let saved = intelligence.save_strategy_proposal(proposal, activate).await.map_err(|e| e.to_string())?;
+    info!("Saved strategy '{}' from learned data (activated: {})", saved.strategy.name, saved.activated);
+    Ok(saved)
+}
+
+/// Learned weights trained on local history only (imported priors are not passed on), as JSON
+#[tauri::command]
+async fn export_learned_model(app: tauri::AppHandle) -> std::result::Result<String, String> {
+    let repo = Arc::clone(&app.state::<Arc<Repository>>());
+    let isp_name = repo.get_current_isp_profile().await.map_err(|e| e.to_string())?.map(|p| p.name);
+    let mut intelligence = DefaultIntelligenceCore::new(repo);
+    intelligence.train_model().await.map_err(|e| e.to_string())?;
+    if intelligence.local_model().training_samples == 0 {
+        return Err("Not enough measurements yet to export a learned model".to_string());
+    }
+    serde_json::to_string_pretty(&SharedModel::from_model(intelligence.local_model(), isp_name)).map_err(|e| e.to_string())
+}
+
+/// Stores a shared model as priors for training, after version and ISP checks
+#[tauri::command]
+async fn import_learned_model(app: tauri::AppHandle, json: String, allow_other_isp: bool) -> std::result::Result<ModelImportSummary, String> {
+    let model: SharedModel = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let repo = app.state::<Arc<Repository>>();
+    let current_isp = repo.get_current_isp_profile().await.map_err(|e| e.to_string())?.map(|p| p.name);
+    model.check_compatible(current_isp.as_deref(), allow_other_isp).map_err(|e| e.user_message())?;
+    let path = model_share::default_priors_path().ok_or("No data directory to keep the model in")?;
+    model_share::write_model(&path, &model).await.map_err(|e| e.to_string())?;
+    info!("Imported learned model from {} ({} samples)", model.isp_name.as_deref().unwrap_or("an unknown ISP"), model.training_samples);
+    Ok(model.summary(path))
+}
+
+/// Drops imported priors; returns whether there were any
+#[tauri::command]
+async fn clear_imported_model() -> std::result::Result<bool, String> {
+    let Some(path) = model_share::default_priors_path() else { return Ok(false) };
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Writes DB schema, config JSON Schema and the command catalog for integrators
+#[tauri::command]
+async fn dump_schema(output_dir: String) -> std::result::Result<Vec<String>, String> {
+    let written = isp_speedkarma::core::schema::dump_schema(std::path::Path::new(&output_dir), COMMAND_NAMES)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(written.into_iter().map(|p| p.display().to_string()).collect())
+}
+
+/// Servers the app sends traffic to, with coordinates, latency and use counts, for the panel map
+#[tauri::command]
+async fn get_server_map_data(app: tauri::AppHandle) -> std::result::Result<Vec<isp_speedkarma::network::servers::ServerMapPoint>, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let stored = repo.get_active_speedtest_servers().await.map_err(|e| e.to_string())?;
+    let (rotation, current) = match app.try_state::<StealthSlot>().and_then(|slot| slot.get().cloned()) {
+        Some(watchdog) => watchdog.engine().rotation_servers().await,
+        None => (Vec::new(), None),
+    };
+    Ok(isp_speedkarma::network::servers::server_map_points(&rotation, current.as_deref(), &stored))
+}
+
+/// Outcome of the startup database integrity check
+#[tauri::command]
+async fn get_database_integrity(app: tauri::AppHandle) -> std::result::Result<Option<isp_speedkarma::data::integrity::IntegrityReport>, String> {
+    Ok(app.try_state::<isp_speedkarma::data::integrity::IntegrityReport>().map(|r| r.inner().clone()))
+}
+
+#[tauri::command]
+async fn get_consolidation_report(app: tauri::AppHandle) -> std::result::Result<Option<consolidation::ConsolidationReport>, String> {
+    Ok(app.try_state::<consolidation::ConsolidationReport>().map(|r| r.inner().clone()))
+}
+
+#[tauri::command]
+async fn get_training_dataset_stats(app: tauri::AppHandle, days: u32) -> std::result::Result<TrainingDatasetStats, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let days = days.clamp(1, dataset::MAX_DATASET_DAYS);
+    let now = chrono::Utc::now();
+    let measurements = repo.get_speed_measurements_since(now - chrono::Duration::days(days as i64)).await.map_err(|e| e.to_string())?;
+    let patterns = match repo.get_current_isp_profile().await.map_err(|e| e.to_string())?.and_then(|p| p.id) {
+        Some(id) => repo.get_throttling_patterns_for_isp(id).await.map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    let offset = *chrono::Local::now().offset();
+    let targets = if patterns.is_empty() { dataset::evening_hours(offset) } else { dataset::pattern_hours(&patterns) };
+    Ok(TrainingDatasetStats::from_measurements(&measurements, now, days).with_target_hours(&measurements, now, &targets, offset))
+}
+
+/// Route changes to the traceroute anchors, with the download speed either side of each
+#[tauri::command]
+async fn get_route_changes(app: tauri::AppHandle, days: u32) -> std::result::Result<Vec<PathChangeImpact>, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let since = chrono::Utc::now() - chrono::Duration::days(days.clamp(1, traceroute::MAX_ROUTE_HISTORY_DAYS) as i64);
+    let snapshots = repo.get_route_snapshots_since(since).await.map_err(|e| e.to_string())?;
+    let measurements = repo.get_speed_measurements_since(since - chrono::Duration::hours(3)).await.map_err(|e| e.to_string())?;
+    Ok(traceroute::correlate_path_changes(traceroute::path_changes(&snapshots), &measurements, chrono::Duration::hours(3)))
+}
+
+/// Strategy switches with their canary outcome, newest first
+#[tauri::command]
+async fn get_strategy_decisions(app: tauri::AppHandle, days: u32) -> std::result::Result<Vec<isp_speedkarma::data::models::StrategyDecision>, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let since = chrono::Utc::now() - chrono::Duration::days(days.clamp(1, canary::MAX_DECISION_HISTORY_DAYS) as i64);
+    repo.get_strategy_decisions_since(since).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_speed_alert_episodes(app: tauri::AppHandle, days: u32) -> std::result::Result<Vec<isp_speedkarma::data::models::SpeedAlertEpisode>, String> {
+    let repo = app.state::<Arc<Repository>>();
+    let since = chrono::Utc::now() - chrono::Duration::days(days.clamp(1, alerts::MAX_EPISODE_HISTORY_DAYS) as i64);
+    repo.get_speed_alert_episodes_since(since).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_qos_capability(_app: tauri::AppHandle) -> std::result::Result<isp_speedkarma::network::qos::QosCapability, String> {
+    Ok(isp_speedkarma::network::qos::detect_capability())
+}
+
+#[tauri::command]
+async fn get_sqm_advice(_app: tauri::AppHandle) -> std::result::Result<isp_speedkarma::network::sqm::SqmAdvice, String> {
+    Ok(isp_speedkarma::network::sqm::SqmAdvisor::analyze().await)
+}
+
+#[tauri::command]
+async fn run_bufferbloat_test(app: tauri::AppHandle) -> std::result::Result<isp_speedkarma::network::monitor::BufferbloatReport, String> {
+    let keeper = app.try_state::<Arc<ThroughputKeeper>>();
+    let repo = app.state::<Arc<Repository>>();
+    let monitor = BackgroundMonitor::new(Arc::clone(&repo));
+    monitor.run_bufferbloat_test(keeper.as_ref().map(|k| k.inner().as_ref())).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn apply_fq_codel(_app: tauri::AppHandle, interface: String) -> std::result::Result<(), String> {
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    if !cfg.advanced.sqm.allow_automatic_setup {
+        return Err("Automatic queue setup is disabled in Advanced settings".to_string());
+    }
+    isp_speedkarma::network::sqm::SqmAdvisor::apply_fq_codel(&interface).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_isp_detection(app: tauri::AppHandle) -> std::result::Result<Option<ISPDetectionResult>, String> {
+    match app.try_state::<Arc<RwLock<Option<ISPDetectionResult>>>>() {
+        Some(slot) => Ok(slot.read().await.clone()),
+        None => Ok(None),
     }
+}
+
+#[tauri::command]
+async fn should_prompt_feedback(app: tauri::AppHandle) -> std::result::Result<bool, String> {
+    let cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    let prompt = cfg.ui.feedback_prompt;
+    let (Some(shared), Some(repo)) = (app.try_state::<SharedAppState>(), app.try_state::<Arc<Repository>>()) else {
+        return Ok(false);
+    };
+    let mut state = shared.write().await;
+    // Only ask while optimizing: answers are meant to grade the active strategy
+    if !matches!(state.optimization_mode, OptimizationMode::Enabled) {
+        return Ok(false);
+    }
+    let now = chrono::Utc::now();
+    let recent = repo
+        .get_satisfaction_feedback_since(now - chrono::Duration::hours(prompt.min_interval_hours as i64))
+        .await
+        .map_err(|e| e.to_string())?;
+    let last_asked = recent.first().map(|f| f.timestamp).max(state.last_feedback_prompt);
+    if !prompt.is_due(last_asked, now) {
+        return Ok(false);
+    }
+    state.last_feedback_prompt = Some(now);
+    Ok(true)
+}
+
+#[tauri::command]
+async fn submit_satisfaction_feedback(app: tauri::AppHandle, satisfied: bool) -> std::result::Result<(), String> {
+    let repo = app.state::<Arc<Repository>>();
+    let shared = app.state::<SharedAppState>();
+    let optimization_active = matches!(shared.read().await.optimization_mode, OptimizationMode::Enabled);
+    let strategy_name = app_state::active_strategy(&shared, &**repo).await.ok().flatten().map(|s| s.name);
+    let feedback = SatisfactionFeedback::new(satisfied, optimization_active, strategy_name);
+    repo.save_satisfaction_feedback(&feedback).await.map_err(|e| e.to_string())?;
     Ok(())
 }
 
+#[tauri::command]
+async fn set_feedback_prompt(_app: tauri::AppHandle, enabled: bool) -> std::result::Result<(), String> {
+    let mut cfg = AppConfig::load().await.map_err(|e| e.to_string())?;
+    cfg.ui.feedback_prompt.enabled = enabled;
+    cfg.save().await.map_err(|e| e.to_string())
+}
+
 async fn initialize_application(app_handle: tauri::AppHandle) -> Result<()> {
     info!("Starting ISP-SpeedKarma application");
-    
-    // Initialize database (file-based in user config dir)
-    let db_path = std::env::temp_dir().join("speedkarma.db");
-    let database_url = format!("sqlite://{}", db_path.display());
-    let migration_manager = MigrationManager::new(database_url.clone());
-    migration_manager.create_database_if_not_exists().await?;
-    let pool = SqlitePool::connect(&database_url).await?;
-    migration_manager.run_migrations(&pool).await?;
+    let engine = Engine::start(Frontend::Desktop, Arc::new(app_handle.clone())).await?;
 
-    let repository = Arc::new(Repository::new(pool));
-
-    // Load app configuration (JSON-based intelligent defaults)
-    let app_config = AppConfig::load().await?;
-    app_config.validate()?;
+    // Keep the login entry in step with the saved preference; re-registering also follows the binary after an update
+    if let Some(saved) = engine.repository.get_app_config().await? {
+        if let Err(e) = autostart::apply(saved.auto_start).await {
+            tracing::warn!("Could not update launch at login: {}", e);
+        }
+    }
 
     // Initialize system tray
     let mut system_tray = SystemTray::new();
     system_tray.initialize(app_handle.clone()).await?;
-    
-    // Store repository, system tray and app control state in app state for access from event handlers
-    app_handle.manage(Arc::clone(&repository));
     app_handle.manage(Arc::new(RwLock::new(system_tray)));
-    let shared_state: SharedAppState = Arc::new(RwLock::new(AppControlState::default()));
-    app_handle.manage(shared_state.clone());
 
-    // Start passive background monitoring if enabled
-    {
-        let repo_for_monitor = Arc::clone(&repository);
-        tokio::spawn(async move {
-            let mut monitor = BackgroundMonitor::new(repo_for_monitor);
-            if let Err(e) = monitor.start_monitoring().await {
-                tracing::warn!("Failed to start background monitoring: {}", e);
-            }
-        });
-    }
+    // The engine's parts, for the commands
+    app_handle.manage(engine.integrity.clone());
+    app_handle.manage(engine.consolidation.clone());
+    app_handle.manage(Arc::clone(&engine.repository));
+    app_handle.manage(engine.shared_state.clone());
+    app_handle.manage(engine.connection_table.clone());
+    app_handle.manage(engine.limiter.clone());
+    app_handle.manage(engine.usage_meter.clone());
+    app_handle.manage(Arc::clone(&engine.asn_database));
+    app_handle.manage(Arc::clone(&engine.last_detection));
+    app_handle.manage(engine.supervisor.clone());
+    app_handle.manage(engine.shutdown.clone());
+    app_handle.manage(engine.webhooks.clone());
+    app_handle.manage(Arc::clone(&engine.keeper));
+    app_handle.manage(Arc::clone(&engine.speed_alerts));
+    app_handle.manage(Arc::clone(&engine.conflicts));
+    app_handle.manage(Arc::clone(&engine.power));
+    app_handle.manage(Arc::clone(&engine.metered));
+    app_handle.manage(Arc::clone(&engine.connectivity));
+    app_handle.manage(Arc::clone(&engine.interlock));
+    app_handle.manage(Arc::clone(&engine.retries));
+    app_handle.manage(Arc::clone(&engine.stealth));
+    app_handle.manage(Arc::clone(&engine.disguise));
+    app_handle.manage(engine.safe_mode.clone());
+    app_handle.manage(Arc::clone(&engine.startup_guard));
 
-    // Perform ISP detection on startup (non-blocking) and save profile
+    // Last quarter hour of per-second throughput for the live graph
+    let live_throughput = LiveThroughput::new().with_shared_state(engine.shared_state.clone());
+    live_throughput.clone().start();
+    app_handle.manage(live_throughput);
+    register_emergency_shortcut(&app_handle, &engine.config.emergency_stop.shortcut);
+
+    // Quitting and OS signals stop the loops and flush the database before exiting
     {
-        let repo_for_detection = Arc::clone(&repository);
+        let shutdown = engine.shutdown.clone();
+        let app_for_signal = app_handle.clone();
         tokio::spawn(async move {
-            let monitor = BackgroundMonitor::new(Arc::clone(&repo_for_detection));
-            match monitor.detect_isp().await {
-                Ok(result) => {
-                    if let Err(e) = monitor.save_isp_profile(&result).await {
-                        tracing::warn!("Failed to save ISP profile: {}", e);
-                    } else {
-                        // Apply a sensible default optimization strategy based on detected ISP
-                        // Only if no strategy exists yet
-                        match repo_for_detection.get_best_optimization_strategy().await {
-                            Ok(Some(_)) => {
-                                // Strategy already exists; do nothing
-                            }
-                            Ok(None) | Err(_) => {
-                                // Choose a default strategy depending on whether ISP is known to throttle
-                                let isp_name_lower = result.isp_name.to_lowercase();
-                                let throttling_isps = ["hutch", "dialog", "mobitel", "airtel"]; 
-                                let mut strategy = if throttling_isps
-                                    .iter()
-                                    .any(|name| isp_name_lower.contains(name))
-                                {
-                                    OptimizationStrategy::high_stealth_strategy()
-                                } else {
-                                    OptimizationStrategy::default_strategy()
-                                };
-
-                                // Seed an initial effectiveness score to help selection later
-                                if strategy.effectiveness_score.is_none() {
-                                    strategy.effectiveness_score = Some(0.6);
-                                }
-
-                                if let Err(e) = repo_for_detection.save_optimization_strategy(&strategy).await {
-                                    tracing::warn!("Failed to save initial optimization strategy: {}", e);
-                                } else {
-                                    tracing::info!(
-                                        "Applied initial optimization strategy: {} (stealth: {:?})",
-                                        strategy.name, strategy.stealth_level
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => tracing::warn!("ISP detection failed: {}", e),
-            }
+            shutdown::signal().await;
+            shutdown.run(SHUTDOWN_GRACE).await;
+            app_for_signal.exit(0);
         });
     }
 
-    // Start decision engine in background
-    let repo_for_task = Arc::clone(&repository);
-    let app_handle_for_task = app_handle.clone();
-    tokio::spawn(async move {
-        let mut engine = DecisionEngine::new(repo_for_task);
-        // Respect configurable data-days requirement
-        engine.set_min_learning_days(app_config.auto_optimization.min_data_days);
-        
-        // Status update loop
-        let status_app_handle = app_handle_for_task.clone();
-        let repo_for_status = Arc::clone(&engine.intelligence().repository);
+    // Tray status refresh
+    {
+        let status_app_handle = app_handle.clone();
+        let repo_for_status = Arc::clone(&engine.repository);
+        let shared_for_status = engine.shared_state.clone();
+        let scheduler_for_task = engine.scheduler.clone();
+        let min_data_days = engine.config.auto_optimization.min_data_days;
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
+            let mut ticker = scheduler_for_task.register("tray_status", std::time::Duration::from_secs(30));
             
             loop {
-                interval.tick().await;
+                ticker.tick().await;
                 
                 // Get status from intelligence core and update tray
                 let tray_state = status_app_handle.state::<Arc<RwLock<SystemTray>>>();
                 let tray = tray_state.read().await;
                 // Compute current status from intelligence core
                 let intelligence = DefaultIntelligenceCore::with_min_learning_days(
-                    Arc::clone(&repo_for_status),
-                    app_config.auto_optimization.min_data_days,
+                    repo_for_status.clone(),
+                    min_data_days,
                 );
-                let status = match intelligence.get_status().await {
+                let mut status = match intelligence.get_status().await {
                     Ok(s) => s,
-                    Err(e) => crate::core::intelligence::SystemStatus {
-                        state: crate::core::intelligence::SystemState::Error(e.to_string()),
+                    Err(e) => isp_speedkarma::core::intelligence::SystemStatus {
+                        state: isp_speedkarma::core::intelligence::SystemState::Error(e.to_string()),
                         message: "Error obtaining status".to_string(),
                         data_collection_progress: None,
                         effectiveness: None,
+                        latest_passive: None,
+                        latest_active: None,
+                        tasks: Vec::new(),
                     },
                 };
+                let paused_by = {
+                    let shared = shared_for_status.read().await;
+                    shared.conflict.clone().filter(|_| shared.generators_paused)
+                };
+                if let Some(conflict) = paused_by {
+                    status.state = isp_speedkarma::core::intelligence::SystemState::Inactive;
+                    status.message = format!("Paused — {} is active", conflict.summary());
+                } else if shared_for_status.read().await.call_active {
+                    status.message = "Holding speed tests during your call".to_string();
+                }
                 
                 if let Err(e) = tray.update_status(status).await {
                     tracing::warn!("Failed to update tray status: {}", e);
                 }
             }
         });
-        
-        // Decision engine loop
-        if let Err(e) = engine.run().await {
-            tracing::warn!("Decision engine stopped: {}", e);
-        }
-    });
+    }
 
     // Start UI progress broadcaster (pushes optimization_progress events)
-    {
-        let repo_for_progress = Arc::clone(&repository);
-        let app_for_progress = app_handle.clone();
-        let shared_for_progress = shared_state.clone();
-        start_progress_broadcaster(app_for_progress, repo_for_progress, shared_for_progress);
+    start_progress_broadcaster(app_handle.clone(), Arc::clone(&engine.repository), engine.shared_state.clone(), engine.shutdown_token.clone());
+
+    // One-hour "try optimization now" trials; results feed the effectiveness model
+    app_handle.manage(Arc::new(TrialRunner::new(
+        Arc::clone(&engine.repository),
+        engine.shared_state.clone(),
+        Arc::new(app_handle.clone()),
+    )));
+
+    // Loopback HTTP API for scripts; the token is created the first time it is switched on
+    control_api::launch(&engine.config.advanced.control_api, Arc::new(AppControlBackend(app_handle.clone()))).await?;
+    // Local socket for `speedkarma-cli`
+    isp_speedkarma::core::ipc::serve(Arc::new(AppControlBackend(app_handle.clone())));
+
+    info!("ISP-SpeedKarma initialized successfully");
+    Ok(())
+}
+
+/// Control API actions, run through the same handlers as the matching commands
+struct AppControlBackend(tauri::AppHandle);
+
+#[async_trait::async_trait]
+impl ControlBackend for AppControlBackend {
+    async fn status(&self) -> std::result::Result<serde_json::Value, String> {
+        let optimization = get_optimization_state(self.0.clone()).await?;
+        let system = get_system_status(self.0.clone()).await?;
+        Ok(serde_json::json!({ "optimization": optimization, "system": system }))
     }
 
-    // Start ThroughputKeeper background task with safe defaults and live config
-    {
-        let cfg = app_config.advanced.throughput_keeper.clone();
-        let keeper = std::sync::Arc::new(ThroughputKeeper::new(app_handle.clone(), Arc::clone(&repository), shared_state.clone(), cfg));
-        keeper.clone().start();
-        // Manage so we can update config later
-        app_handle.manage(std::sync::Arc::clone(&keeper));
+    async fn toggle_optimization(&self) -> std::result::Result<serde_json::Value, String> {
+        toggle_optimization(self.0.clone()).await?;
+        get_optimization_state(self.0.clone()).await
     }
 
-    // Start disguise mode background if enabled
-    if app_config.advanced.disguise_mode.enabled {
-        let proxy = std::sync::Arc::new(DisguiseProxy::new(app_handle.clone(), Arc::clone(&repository), shared_state.clone(), app_config.advanced.disguise_mode.clone()));
-        proxy.clone().start();
-        app_handle.manage(proxy);
+    async fn run_speedtest(&self) -> std::result::Result<(), String> {
+        run_speedtest_once(self.0.clone()).await
     }
 
-    info!("ISP-SpeedKarma initialized successfully");
-    Ok(())
+    async fn history(&self, days: u32) -> std::result::Result<Vec<isp_speedkarma::data::models::SpeedMeasurement>, String> {
+        let repo = self.0.state::<Arc<Repository>>();
+        repo.get_speed_measurements_since(chrono::Utc::now() - chrono::Duration::days(days as i64)).await.map_err(|e| e.to_string())
+    }
+
+    async fn summary(&self) -> std::result::Result<SiteSummary, String> {
+        let repo = self.0.state::<Arc<Repository>>();
+        let throttling = get_throttling_patterns(self.0.clone()).await?;
+        let isp_name = repo.get_current_isp_profile().await.map_err(|e| e.to_string())?.map(|p| p.name);
+        let alerts = repo.get_speed_alert_episodes_since(chrono::Utc::now() - chrono::Duration::days(1)).await.map_err(|e| e.to_string())?;
+        let optimization_enabled = matches!(self.0.state::<SharedAppState>().read().await.optimization_mode, OptimizationMode::Enabled);
+        let effectiveness = get_system_status(self.0.clone()).await?.effectiveness;
+        Ok(SiteSummary { isp_name, optimization_enabled, effectiveness, throttling, alerts })
+    }
+
+    async fn export(&self, format: ExportFormat, filter: MeasurementFilter, path: Option<std::path::PathBuf>) -> std::result::Result<ExportSummary, String> {
+        export_measurements(self.0.clone(), format, Some(filter), path.map(|p| p.to_string_lossy().into_owned())).await
+    }
+
+    async fn focus(&self) -> std::result::Result<(), String> {
+        let Some(window) = self.0.get_window("main") else { return Ok(()) };
+        window.unminimize().map_err(|e| e.to_string())?;
+        window.show().map_err(|e| e.to_string())?;
+        window.set_focus().map_err(|e| e.to_string())
+    }
 }
 
 // Entry point for non-mobile builds
 #[tokio::main]
 async fn main() {
+    // Developer mode: `isp-speedkarma --dump-schema <dir>` writes the schema files and exits
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--dump-schema") {
+        let dir = args.get(pos + 1).map(String::as_str).unwrap_or("schema");
+        match isp_speedkarma::core::schema::dump_schema(std::path::Path::new(dir), COMMAND_NAMES).await {
+            Ok(files) => files.iter().for_each(|f| println!("{}", f.display())),
+            Err(e) => {
+                eprintln!("dump_schema failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    // Operator mode: `isp-speedkarma --fleet-summary` prints the combined summary of `advanced.fleet` as JSON
+    if args.iter().any(|a| a == "--fleet-summary") {
+        let summary = match AppConfig::load().await {
+            Ok(cfg) => fleet::poll(&cfg.advanced.fleet).await,
+            Err(e) => Err(e),
+        };
+        match summary.and_then(|s| Ok(serde_json::to_string_pretty(&s)?)) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("fleet summary failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    // Server mode: `isp-speedkarma --headless [--log-file <path>]` runs the engine without window or tray
+    #[cfg(feature = "headless")]
+    if args.iter().any(|a| a == "--headless") {
+        if let Err(e) = isp_speedkarma::headless::run(isp_speedkarma::headless::HeadlessOptions::from_args(&args)).await {
+            eprintln!("headless run failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // A second launch hands over to the running tray app instead of starting another engine
+    let _instance = match isp_speedkarma::core::single_instance::acquire() {
+        Ok(Some(lock)) => Some(lock),
+        Ok(None) => {
+            if let Err(e) = isp_speedkarma::core::ipc::request(&isp_speedkarma::core::ipc::IpcRequest::Focus).await {
+                eprintln!("SpeedKarma is already running but did not respond: {}", e);
+            }
+            return;
+        }
+        Err(e) => {
+            eprintln!("Single-instance check failed, continuing: {}", e);
+            None
+        }
+    };
     run();
 }
\ No newline at end of file
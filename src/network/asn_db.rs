@@ -0,0 +1,248 @@
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::network::proxy;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Compact seed shipped with the app so classification works before the first refresh.
+/// Same layout as the refreshed file: start, end, ASN, country, AS description.
+const BUNDLED_RANGES: &[(&str, &str, u32, &str, &str)] = &[
+    ("1.0.0.0", "1.0.0.255", 13335, "US", "CLOUDFLARENET"),
+    ("1.1.1.0", "1.1.1.255", 13335, "US", "CLOUDFLARENET"),
+    ("8.8.4.0", "8.8.4.255", 15169, "US", "GOOGLE"),
+    ("8.8.8.0", "8.8.8.255", 15169, "US", "GOOGLE"),
+    ("9.9.9.0", "9.9.9.255", 19281, "US", "QUAD9-AS-1"),
+];
+
+/// Known operators by AS number
+const KNOWN_ISP_ASNS: &[(u32, &str)] = &[
+    (18001, "Dialog"),
+    (9329, "SLT"),
+    (45356, "Mobitel"),
+    (9506, "Hutch"),
+    (9498, "Airtel"),
+    (24560, "Airtel"),
+    (45609, "Airtel"),
+];
+
+/// Known operators by registrable reverse-DNS domain (exact match)
+const KNOWN_ISP_DOMAINS: &[(&str, &str)] = &[
+    ("dialog.lk", "Dialog"),
+    ("slt.lk", "SLT"),
+    ("slt.net.lk", "SLT"),
+    ("mobitel.lk", "Mobitel"),
+    ("hutch.lk", "Hutch"),
+    ("airtel.in", "Airtel"),
+    ("airtel.lk", "Airtel"),
+];
+
+/// One IPv4 range of the IP-to-ASN dataset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AsnRecord {
+    pub range_start: u32,
+    pub range_end: u32,
+    pub asn: u32,
+    pub country: String,
+    pub description: String,
+}
+
+/// Friendly name of a known operator's AS
+pub fn known_isp_name(asn: u32) -> Option<&'static str> {
+    KNOWN_ISP_ASNS.iter().find(|(known, _)| *known == asn).map(|(_, name)| *name)
+}
+
+/// Friendly name of a known operator's registrable domain, e.g. `dialog.lk`
+pub fn known_isp_domain(domain: &str) -> Option<&'static str> {
+    KNOWN_ISP_DOMAINS.iter().find(|(known, _)| known.eq_ignore_ascii_case(domain)).map(|(_, name)| *name)
+}
+
+/// Dataset text from a download, gunzipped when it is gzip-compressed (as iptoasn.com serves it)
+pub fn decode_dataset(bytes: &[u8]) -> Result<String> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut content = String::new();
+        flate2::read::GzDecoder::new(bytes).read_to_string(&mut content)?;
+        Ok(content)
+    } else {
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+impl AsnRecord {
+    /// Friendly ISP name when the AS belongs to a known operator
+    pub fn isp_name(&self) -> Option<&'static str> {
+        known_isp_name(self.asn)
+    }
+}
+
+/// Offline IP-to-ASN/country database (sorted ranges, binary searched)
+#[derive(Debug, Clone, Default)]
+pub struct AsnDatabase {
+    records: Vec<AsnRecord>,
+}
+
+impl AsnDatabase {
+    /// Database built from the bundled seed only
+    pub fn bundled() -> Self {
+        let records = BUNDLED_RANGES
+            .iter()
+            .filter_map(|(start, end, asn, country, desc)| {
+                Some(AsnRecord {
+                    range_start: u32::from(start.parse::<Ipv4Addr>().ok()?),
+                    range_end: u32::from(end.parse::<Ipv4Addr>().ok()?),
+                    asn: *asn,
+                    country: country.to_string(),
+                    description: desc.to_string(),
+                })
+            })
+            .collect();
+        Self::from_records(records)
+    }
+
+    pub fn from_records(mut records: Vec<AsnRecord>) -> Self {
+        records.sort_by_key(|r| r.range_start);
+        Self { records }
+    }
+
+    /// Parses ip2asn-style TSV (`start end asn country description`), with u32 or dotted addresses.
+    /// Unrouted ranges (ASN 0) and malformed lines are skipped.
+    pub fn parse_tsv(content: &str) -> Self {
+        let parse_ip = |s: &str| s.parse::<u32>().ok().or_else(|| s.parse::<Ipv4Addr>().ok().map(u32::from));
+        let records = content
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut cols = line.splitn(5, '\t');
+                let range_start = parse_ip(cols.next()?.trim())?;
+                let range_end = parse_ip(cols.next()?.trim())?;
+                let asn = cols.next()?.trim().parse::<u32>().ok()?;
+                let country = cols.next()?.trim().to_string();
+                let description = cols.next().unwrap_or("").trim().to_string();
+                if asn == 0 || range_end < range_start { return None; }
+                Some(AsnRecord { range_start, range_end, asn, country, description })
+            })
+            .collect();
+        Self::from_records(records)
+    }
+
+    /// Loads the refreshed dataset from disk, falling back to the bundled seed
+    pub async fn load_or_bundled(path: &Path) -> Self {
+        match tokio::fs::read_to_string(path).await {
+            Ok(content) => {
+                let db = Self::parse_tsv(&content);
+                if db.is_empty() {
+                    warn!("ASN database at {} is empty, using bundled data", path.display());
+                    Self::bundled()
+                } else {
+                    debug!("Loaded {} ASN ranges from {}", db.len(), path.display());
+                    db
+                }
+            }
+            Err(_) => Self::bundled(),
+        }
+    }
+
+    /// Downloads a fresh TSV dataset, plain or gzip-compressed, and stores it uncompressed at `path`
+    pub async fn refresh(url: &str, path: &Path) -> Result<Self> {
+        let client = proxy::apply(reqwest::Client::builder()).timeout(Duration::from_secs(60)).build()?;
+        let response = client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(SpeedKarmaError::NetworkUnavailable(
+                format!("ASN database download returned status: {}", response.status())
+            ));
+        }
+        let content = decode_dataset(&response.bytes().await?)?;
+        let db = Self::parse_tsv(&content);
+        if db.is_empty() {
+            return Err(SpeedKarmaError::SystemError("Downloaded ASN database contains no ranges".to_string()));
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, content).await?;
+        info!("Refreshed ASN database with {} ranges", db.len());
+        Ok(db)
+    }
+
+    /// Default on-disk location of the refreshed dataset
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|d| d.join("SpeedKarma").join("ip2asn-v4.tsv"))
+    }
+
+    /// Finds the range containing `ip`
+    pub fn lookup(&self, ip: Ipv4Addr) -> Option<&AsnRecord> {
+        let ip = u32::from(ip);
+        let idx = self.records.partition_point(|r| r.range_start <= ip);
+        let candidate = self.records.get(idx.checked_sub(1)?)?;
+        (ip <= candidate.range_end).then_some(candidate)
+    }
+
+    /// Country code for `ip`, if known
+    pub fn country_of(&self, ip: Ipv4Addr) -> Option<&str> {
+        self.lookup(ip).map(|r| r.country.as_str())
+    }
+
+    pub fn len(&self) -> usize { self.records.len() }
+
+    pub fn is_empty(&self) -> bool { self.records.is_empty() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_lookup() {
+        let db = AsnDatabase::bundled();
+        assert!(!db.is_empty());
+        let record = db.lookup(Ipv4Addr::new(1, 1, 1, 1)).unwrap();
+        assert_eq!(record.asn, 13335);
+        assert_eq!(db.country_of(Ipv4Addr::new(8, 8, 8, 8)), Some("US"));
+        assert!(db.lookup(Ipv4Addr::new(10, 0, 0, 1)).is_none());
+    }
+
+    #[test]
+    fn test_parse_tsv_mixed_formats() {
+        let content = "# comment\n\
+            16777216\t16777471\t13335\tUS\tCLOUDFLARENET\n\
+            112.134.0.0\t112.135.255.255\t9329\tLK\tSLT-AP Sri Lanka Telecom PLC\n\
+            0.0.0.0\t0.255.255.255\t0\tNone\tNot routed\n\
+            garbage line\n";
+        let db = AsnDatabase::parse_tsv(content);
+        assert_eq!(db.len(), 2);
+        let record = db.lookup(Ipv4Addr::new(112, 135, 10, 1)).unwrap();
+        assert_eq!(record.country, "LK");
+        assert_eq!(record.isp_name(), Some("SLT"));
+    }
+
+    #[test]
+    fn test_lookup_range_edges() {
+        let db = AsnDatabase::parse_tsv("10.0.0.0\t10.0.0.255\t18001\tLK\tDialog Axiata PLC\n");
+        assert!(db.lookup(Ipv4Addr::new(10, 0, 0, 0)).is_some());
+        assert!(db.lookup(Ipv4Addr::new(10, 0, 0, 255)).is_some());
+        assert!(db.lookup(Ipv4Addr::new(10, 0, 1, 0)).is_none());
+        assert!(db.lookup(Ipv4Addr::new(9, 255, 255, 255)).is_none());
+        assert_eq!(db.lookup(Ipv4Addr::new(10, 0, 0, 9)).unwrap().isp_name(), Some("Dialog"));
+    }
+
+    #[test]
+    fn test_isp_names_come_from_the_asn_not_the_description() {
+        let db = AsnDatabase::parse_tsv("203.0.113.0\t203.0.113.255\t64500\tUS\tCONSULTING-ISLTD Example Networks\n");
+        assert_eq!(db.lookup(Ipv4Addr::new(203, 0, 113, 1)).unwrap().isp_name(), None);
+        assert_eq!(known_isp_domain("SLT.lk"), Some("SLT"));
+        assert_eq!(known_isp_domain("notslt.lk"), None);
+    }
+
+    #[test]
+    fn test_gzip_dataset_is_decoded() {
+        use std::io::Write;
+        let tsv = "112.134.0.0\t112.135.255.255\t9329\tLK\tSLT-AP Sri Lanka Telecom PLC\n";
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(tsv.as_bytes()).unwrap();
+        let gz = encoder.finish().unwrap();
+        assert_eq!(decode_dataset(&gz).unwrap(), tsv);
+        assert_eq!(decode_dataset(tsv.as_bytes()).unwrap(), tsv);
+    }
+}
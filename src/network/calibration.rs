@@ -0,0 +1,171 @@
+//! Passive estimates come from interface counters, which see other devices' traffic through a
+//! bridge, miss offloaded bytes and count tunnel overhead, so on some networks they sit far from
+//! what speedtest.net shows. Calibration runs a speed test, reads the counters over the same
+//! seconds, and keeps the ratio per network to correct later passive samples.
+
+use crate::core::app_state::SharedAppState;
+use crate::core::config::AppConfig;
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::core::events::SharedEventSink;
+use crate::data::models::{MeasurementSource, PassiveCalibration, SpeedMeasurement};
+use crate::data::repository::Repository;
+use crate::network::context;
+use crate::network::live;
+use crate::network::monitor::BackgroundMonitor;
+use crate::network::speedtest_runner::{SpeedtestPhase, SpeedtestRunner};
+use crate::network::limiter::OutboundLimiter;
+use crate::network::usage::DataUsageMeter;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::info;
+
+/// Bounds on the correction; anything further out is a broken reading, not a bias
+const MIN_FACTOR: f64 = 0.1;
+const MAX_FACTOR: f64 = 10.0;
+/// Weight of the newest calibration in the smoothed factor
+const SMOOTHING: f64 = 0.5;
+/// Lowest confidence multiplier, reached when a calibration disagrees with the factor by 50% or more
+const MIN_CONFIDENCE_SCALE: f64 = 0.5;
+
+/// Folds one speed test and the passive estimate over the same window into the network's correction
+pub fn updated(previous: Option<&PassiveCalibration>, network_context: &str, active_mbps: f64, passive_mbps: f64, at: DateTime<Utc>) -> PassiveCalibration {
+    let ratio = (active_mbps / passive_mbps).clamp(MIN_FACTOR, MAX_FACTOR);
+    let (factor, confidence_scale, calibrations) = match previous {
+        // A ratio that moves between calibrations means the passive numbers are unreliable here
+        Some(previous) => {
+            let drift = (ratio - previous.factor).abs() / previous.factor;
+            let factor = previous.factor * (1.0 - SMOOTHING) + ratio * SMOOTHING;
+            (factor, (1.0 - drift).clamp(MIN_CONFIDENCE_SCALE, 1.0), previous.calibrations + 1)
+        }
+        None => (ratio, 1.0, 1),
+    };
+    PassiveCalibration {
+        network_context: network_context.to_string(),
+        calibrated_at: at,
+        factor,
+        confidence_scale,
+        calibrations,
+        last_active_mbps: active_mbps,
+        last_passive_mbps: passive_mbps,
+    }
+}
+
+/// Corrects a passive sample taken on the calibrated network. Only download is scaled: the
+/// calibration loads the downlink, so it says nothing about how counters see uploads.
+pub fn apply(calibration: &PassiveCalibration, measurement: &mut SpeedMeasurement) {
+    measurement.download_mbps *= calibration.factor;
+    measurement.confidence = (measurement.confidence * calibration.confidence_scale).clamp(0.0, 1.0);
+}
+
+/// Runs a speed test and compares it with the interface counters over its download phase
+pub struct PassiveCalibrationRoutine {
+    events: SharedEventSink,
+    repository: Arc<Repository>,
+    shared: SharedAppState,
+    usage: Option<DataUsageMeter>,
+    limiter: Option<OutboundLimiter>,
+}
+
+impl PassiveCalibrationRoutine {
+    pub fn new(events: SharedEventSink, repository: Arc<Repository>, shared: SharedAppState) -> Self {
+        Self { events, repository, shared, usage: None, limiter: None }
+    }
+
+    /// Counts the bytes of the calibration test against the monthly data cap
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Lets the emergency stop abort the calibration test mid-run
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Download Mbps the monitored interfaces' counters saw while the runner was in its download
+    /// phase; `None` when the run ended without downloading
+    async fn passive_estimate(&self, mut phases: watch::Receiver<SpeedtestPhase>) -> Result<Option<f64>> {
+        let selection = self.shared.read().await.monitored_interfaces.clone();
+        let read = || async {
+            let mut stats = BackgroundMonitor::get_network_interface_stats().await?;
+            stats.retain(|name, _| selection.selects(name));
+            Ok::<_, SpeedKarmaError>(stats)
+        };
+        let started = phases.wait_for(|p| matches!(p, SpeedtestPhase::Download | SpeedtestPhase::Done)).await.map(|p| *p);
+        if started != Ok(SpeedtestPhase::Download) {
+            return Ok(None);
+        }
+        let before = read().await?;
+        let _ = phases.wait_for(|p| *p != SpeedtestPhase::Download).await;
+        let after = read().await?;
+        Ok(live::throughput(&before, &after).map(|(download_mbps, _)| download_mbps))
+    }
+
+    pub async fn run(&self) -> Result<PassiveCalibration> {
+        let cfg = AppConfig::load().await?.effective().advanced.speedtest_runner;
+        let network = context::current().await;
+        let network_context = network.key()
+            .ok_or_else(|| SpeedKarmaError::NetworkUnavailable("the current network could not be identified".into()))?;
+
+        let started = Utc::now();
+        let (phase_tx, phases) = watch::channel(SpeedtestPhase::Starting);
+        let mut runner = SpeedtestRunner::new(Arc::clone(&self.events), Arc::clone(&self.repository), Arc::clone(&self.shared), cfg)
+            .with_phase_updates(phase_tx);
+        if let Some(usage) = &self.usage {
+            runner = runner.with_usage_meter(usage.clone());
+        }
+        if let Some(limiter) = &self.limiter {
+            runner = runner.with_limiter(limiter.clone());
+        }
+        // The counters are read when the runner starts and ends its download phase, so latency
+        // probing and the upload stay out of the passive figure
+        let (tested, passive) = tokio::join!(runner.run_once(), self.passive_estimate(phases));
+        tested?;
+
+        let active = self.repository.get_speed_measurements_since(started).await?.into_iter()
+            .find(|m| m.source == MeasurementSource::Active && m.network_context.as_deref() == Some(network_context.as_str()))
+            .filter(|m| m.download_mbps > 0.0)
+            .ok_or_else(|| SpeedKarmaError::NetworkUnavailable("the speed test did not complete".into()))?;
+        let passive_mbps = passive?.filter(|mbps| *mbps > 0.0)
+            .ok_or_else(|| SpeedKarmaError::NetworkUnavailable("the interface counters saw no traffic".into()))?;
+
+        let previous = self.repository.get_passive_calibration(&network_context).await?;
+        let calibration = updated(previous.as_ref(), &network_context, active.download_mbps, passive_mbps, Utc::now());
+        self.repository.save_passive_calibration(&calibration).await?;
+        info!(
+            "Calibrated passive estimates on {}: speed test {:.1} Mbps, counters {:.1} Mbps, factor {:.2}",
+            network.describe(), active.download_mbps, passive_mbps, calibration.factor
+        );
+        self.events.emit_payload("passive_calibrated", &calibration);
+        Ok(calibration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration_smooths_the_factor_and_corrects_passive_samples() {
+        let at = Utc::now();
+        // Counters saw half of what the speed test measured
+        let first = updated(None, "home", 100.0, 50.0, at);
+        assert_eq!((first.factor, first.confidence_scale, first.calibrations), (2.0, 1.0, 1));
+
+        // A consistent second calibration keeps full confidence; a wild one lowers it
+        let steady = updated(Some(&first), "home", 90.0, 45.0, at);
+        assert_eq!((steady.factor, steady.confidence_scale, steady.calibrations), (2.0, 1.0, 2));
+        let wild = updated(Some(&first), "home", 100.0, 100.0, at);
+        assert_eq!((wild.factor, wild.confidence_scale), (1.5, MIN_CONFIDENCE_SCALE));
+
+        // Broken readings are held to the bounds
+        assert_eq!(updated(None, "home", 500.0, 0.5, at).factor, MAX_FACTOR);
+
+        let mut sample = SpeedMeasurement::new(40.0, 8.0, 20, false);
+        sample.confidence = 0.8;
+        apply(&wild, &mut sample);
+        assert_eq!((sample.download_mbps, sample.upload_mbps, sample.confidence), (60.0, 8.0, 0.4));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::core::app_state::SharedAppState;
+#[cfg(target_os = "macos")]
+use crate::core::command::command_output;
+use crate::core::config::CallInterlockConfig;
+use crate::core::events::SharedEventSink;
+use crate::network::monitor::BackgroundMonitor;
+use crate::network::speedtest_runner::{SpeedtestProgressPayload, SpeedtestRunner};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, info};
+
+/// Time between two traffic samples
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// System-wide UDP datagram counters (IPv4 and IPv6 together)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UdpCounters {
+    pub in_datagrams: u64,
+    pub out_datagrams: u64,
+}
+
+/// Reads the `Udp:` rows of `/proc/net/snmp` and, when given, `Udp6*` lines of `/proc/net/snmp6`
+pub fn parse_udp_counters(snmp: &str, snmp6: Option<&str>) -> Option<UdpCounters> {
+    let mut rows = snmp.lines().filter(|l| l.starts_with("Udp:"));
+    let header: Vec<&str> = rows.next()?.split_whitespace().collect();
+    let values: Vec<&str> = rows.next()?.split_whitespace().collect();
+    let field = |name: &str| header.iter().position(|h| *h == name).and_then(|i| values.get(i)?.parse::<u64>().ok());
+    let mut counters = UdpCounters { in_datagrams: field("InDatagrams")?, out_datagrams: field("OutDatagrams")? };
+
+    for line in snmp6.unwrap_or("").lines() {
+        let mut parts = line.split_whitespace();
+        let (name, value) = (parts.next(), parts.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0));
+        match name {
+            Some("Udp6InDatagrams") => counters.in_datagrams += value,
+            Some("Udp6OutDatagrams") => counters.out_datagrams += value,
+            _ => {}
+        }
+    }
+    Some(counters)
+}
+
+/// Loopback packets `(received, sent)` from the `lo` row of `/proc/net/dev`
+pub fn parse_proc_loopback(dev: &str) -> Option<(u64, u64)> {
+    dev.lines().find_map(|line| {
+        let (name, counters) = line.split_once(':')?;
+        if name.trim() != "lo" {
+            return None;
+        }
+        let fields: Vec<u64> = counters.split_whitespace().filter_map(|f| f.parse().ok()).collect();
+        Some((*fields.get(1)?, *fields.get(9)?))
+    })
+}
+
+/// Datagram totals from macOS `netstat -s -p udp`, which counts IPv4 and IPv6 together
+pub fn parse_netstat_udp(output: &str) -> Option<UdpCounters> {
+    let count = |label: &str| output.lines().find_map(|l| l.trim().strip_suffix(label)?.trim().parse::<u64>().ok());
+    Some(UdpCounters { in_datagrams: count("datagrams received")?, out_datagrams: count("datagrams output")? })
+}
+
+/// Loopback packets `(received, sent)` from macOS `netstat -n -I lo0`. The link row has no
+/// address, so its columns are read from the right.
+pub fn parse_netstat_loopback(output: &str) -> Option<(u64, u64)> {
+    let fields: Vec<&str> = output.lines().find(|l| l.contains("<Link#"))?.split_whitespace().collect();
+    let from_end = |n: usize| fields.len().checked_sub(n).and_then(|i| fields[i].parse().ok());
+    Some((from_end(5)?, from_end(3)?))
+}
+
+/// One reading of UDP counters and interface byte totals
+#[derive(Debug, Clone, Copy)]
+pub struct MediaSample {
+    pub udp: UdpCounters,
+    /// Packets over the loopback interface, which carries local UDP but never a call
+    pub loopback_rx_packets: u64,
+    pub loopback_tx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub at: Instant,
+}
+
+/// Traffic rates between two samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaRates {
+    pub in_pps: f64,
+    pub out_pps: f64,
+    pub rx_kbps: f64,
+    pub tx_kbps: f64,
+}
+
+impl MediaRates {
+    /// `None` when the samples are not in order or a counter went backwards (interface reset)
+    pub fn between(prev: &MediaSample, cur: &MediaSample) -> Option<Self> {
+        let secs = cur.at.checked_duration_since(prev.at)?.as_secs_f64();
+        if secs <= 0.0 { return None; }
+        let delta = |a: u64, b: u64| b.checked_sub(a).map(|d| d as f64 / secs);
+        // Loopback packets come off the UDP totals; local TCP goes with them, which can hide a call but never fake one
+        let off_host = |udp: f64, loopback: f64| (udp - loopback).max(0.0);
+        Some(Self {
+            in_pps: off_host(delta(prev.udp.in_datagrams, cur.udp.in_datagrams)?, delta(prev.loopback_rx_packets, cur.loopback_rx_packets)?),
+            out_pps: off_host(delta(prev.udp.out_datagrams, cur.udp.out_datagrams)?, delta(prev.loopback_tx_packets, cur.loopback_tx_packets)?),
+            rx_kbps: delta(prev.rx_bytes, cur.rx_bytes)? * 8.0 / 1000.0,
+            tx_kbps: delta(prev.tx_bytes, cur.tx_bytes)? * 8.0 / 1000.0,
+        })
+    }
+
+    /// Steady two-way UDP at a conferencing upload rate: audio runs at ~50 packets/s each way,
+    /// video adds more, and uploads stay between tens of kbps and a few Mbps
+    pub fn looks_like_call(&self, cfg: &CallInterlockConfig) -> bool {
+        self.in_pps >= cfg.min_packets_per_second
+            && self.out_pps >= cfg.min_packets_per_second
+            && self.rx_kbps >= cfg.min_kbps
+            && (cfg.min_kbps..=cfg.max_kbps).contains(&self.tx_kbps)
+    }
+}
+
+/// Debounces call-like samples: a call starts after `sustain_seconds` of them and
+/// ends after `release_seconds` without them
+#[derive(Debug, Default)]
+pub struct CallDetector {
+    active: bool,
+    streak_since: Option<Instant>,
+}
+
+impl CallDetector {
+    pub fn is_active(&self) -> bool { self.active }
+
+    /// Feeds one observation; returns the new state when it flips
+    pub fn observe(&mut self, call_like: bool, now: Instant, cfg: &CallInterlockConfig) -> Option<bool> {
+        // While inactive the streak counts call-like samples, while active it counts quiet ones
+        if call_like != self.active {
+            let since = *self.streak_since.get_or_insert(now);
+            let needed = if self.active { cfg.release_seconds } else { cfg.sustain_seconds };
+            if now.duration_since(since) >= Duration::from_secs(needed) {
+                self.active = !self.active;
+                self.streak_since = None;
+                return Some(self.active);
+            }
+        } else {
+            self.streak_since = None;
+        }
+        None
+    }
+}
+
+/// UDP datagram totals and loopback packets `(received, sent)`
+#[cfg(target_os = "linux")]
+async fn read_udp() -> Option<(UdpCounters, (u64, u64))> {
+    let snmp = tokio::fs::read_to_string("/proc/net/snmp").await.ok()?;
+    let snmp6 = tokio::fs::read_to_string("/proc/net/snmp6").await.ok();
+    let dev = tokio::fs::read_to_string("/proc/net/dev").await.ok()?;
+    Some((parse_udp_counters(&snmp, snmp6.as_deref())?, parse_proc_loopback(&dev)?))
+}
+
+#[cfg(target_os = "macos")]
+async fn read_udp() -> Option<(UdpCounters, (u64, u64))> {
+    let (udp, loopback) = tokio::join!(command_output("netstat", &["-s", "-p", "udp"]), command_output("netstat", &["-n", "-I", "lo0"]));
+    Some((parse_netstat_udp(&udp?)?, parse_netstat_loopback(&loopback?)?))
+}
+
+/// Windows keeps no counters on its loopback pseudo-interface, so nothing is taken off there
+#[cfg(windows)]
+async fn read_udp() -> Option<(UdpCounters, (u64, u64))> {
+    use windows_sys::Win32::NetworkManagement::IpHelper::{GetUdpStatisticsEx, MIB_UDPSTATS};
+    use windows_sys::Win32::Networking::WinSock::{AF_INET, AF_INET6};
+
+    let mut counters = UdpCounters::default();
+    for family in [AF_INET, AF_INET6] {
+        // SAFETY: an all-zero MIB_UDPSTATS is valid and the pointer is to a live local
+        let mut stats: MIB_UDPSTATS = unsafe { std::mem::zeroed() };
+        if unsafe { GetUdpStatisticsEx(&mut stats, family) } != 0 {
+            return None;
+        }
+        counters.in_datagrams += u64::from(stats.dwInDatagrams);
+        counters.out_datagrams += u64::from(stats.dwOutDatagrams);
+    }
+    Some((counters, (0, 0)))
+}
+
+/// No UDP counter source elsewhere; calls go undetected
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+async fn read_udp() -> Option<(UdpCounters, (u64, u64))> {
+    None
+}
+
+/// UDP counters with the byte totals of the non-loopback interfaces the passive monitor reads
+async fn read_sample() -> Option<MediaSample> {
+    let (udp, (loopback_rx_packets, loopback_tx_packets)) = read_udp().await?;
+    let stats = BackgroundMonitor::get_network_interface_stats().await.ok()?;
+    Some(MediaSample {
+        udp,
+        loopback_rx_packets,
+        loopback_tx_packets,
+        rx_bytes: stats.values().map(|s| s.bytes_received).sum(),
+        tx_bytes: stats.values().map(|s| s.bytes_sent).sum(),
+        at: Instant::now(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CallInterlockStatus {
+    pub active: bool,
+    pub since: Option<DateTime<Utc>>,
+    /// A speed test is waiting for the call to end
+    pub test_deferred: bool,
+}
+
+/// Watches for running calls, blocks speed tests and heavy bursts during them and
+/// runs a deferred test once the call is over
+pub struct CallInterlock {
+    events: SharedEventSink,
+    shared: SharedAppState,
+    config: Arc<RwLock<CallInterlockConfig>>,
+    since: RwLock<Option<DateTime<Utc>>>,
+    deferred: Mutex<Option<SpeedtestRunner>>,
+}
+
+impl CallInterlock {
+    pub fn new(events: SharedEventSink, shared: SharedAppState, config: CallInterlockConfig) -> Self {
+        Self {
+            events,
+            shared,
+            config: Arc::new(RwLock::new(config)),
+            since: RwLock::new(None),
+            deferred: Mutex::new(None),
+        }
+    }
+
+    pub async fn update_config(&self, cfg: CallInterlockConfig) { *self.config.write().await = cfg; }
+
+    pub async fn status(&self) -> CallInterlockStatus {
+        CallInterlockStatus {
+            active: self.shared.read().await.call_active,
+            since: *self.since.read().await,
+            test_deferred: self.deferred.lock().await.is_some(),
+        }
+    }
+
+    /// Starts the speed test now, or queues it for when the current call ends (a newer request
+    /// replaces an older one). Returns whether it was deferred.
+    pub async fn run_or_defer(&self, runner: SpeedtestRunner) -> bool {
+        if !self.shared.read().await.call_active {
+            tokio::spawn(async move { let _ = runner.run_once().await; });
+            return false;
+        }
+        *self.deferred.lock().await = Some(runner);
+        info!("Speed test deferred until the call ends");
+        self.events.emit_payload("speedtest_progress", &SpeedtestProgressPayload { phase: "deferred".into(), down_mbps: 0.0, up_mbps: 0.0, elapsed_s: 0 });
+        self.emit_status().await;
+        true
+    }
+
+    pub fn start(self: Arc<Self>) {
+        let interlock = Arc::clone(&self);
+        tokio::spawn(async move { interlock.run_loop().await; });
+    }
+
+    async fn run_loop(&self) {
+        let mut detector = CallDetector::default();
+        let mut previous: Option<MediaSample> = None;
+        loop {
+            let cfg = self.config.read().await.clone();
+            let sample = if cfg.enabled { read_sample().await } else { None };
+            let call_like = match (&previous, &sample) {
+                (Some(prev), Some(cur)) => MediaRates::between(prev, cur).map(|r| {
+                    debug!("Media rates: {:.0}/{:.0} pps, {:.0}/{:.0} kbps", r.in_pps, r.out_pps, r.rx_kbps, r.tx_kbps);
+                    r.looks_like_call(&cfg)
+                }),
+                _ => None,
+            };
+            previous = sample;
+
+            let change = match call_like {
+                Some(call_like) => detector.observe(call_like, Instant::now(), &cfg),
+                // Disabled or unreadable: release immediately
+                None if detector.is_active() => { detector = CallDetector::default(); Some(false) }
+                None => None,
+            };
+            if let Some(active) = change {
+                self.set_active(active).await;
+            }
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+        }
+    }
+
+    async fn set_active(&self, active: bool) {
+        self.shared.write().await.call_active = active;
+        *self.since.write().await = active.then(Utc::now);
+        if active {
+            info!("Call detected; holding back speed tests and heavy bursts");
+        } else {
+            info!("Call ended; speed tests allowed again");
+            if let Some(runner) = self.deferred.lock().await.take() {
+                tokio::spawn(async move { let _ = runner.run_once().await; });
+            }
+        }
+        self.emit_status().await;
+    }
+
+    async fn emit_status(&self) {
+        let status = self.status().await;
+        self.events.emit_payload("call_interlock", &status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNMP: &str = "Ip: Forwarding DefaultTTL\nIp: 1 64\n\
+        Udp: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors InCsumErrors IgnoredMulti MemErrors\n\
+        Udp: 2507 0 0 2535 0 0 0 0 0\n\
+        UdpLite: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors InCsumErrors IgnoredMulti MemErrors\n\
+        UdpLite: 9 0 0 9 0 0 0 0 0\n";
+
+    #[test]
+    fn test_parse_udp_counters() {
+        let snmp6 = "Udp6InDatagrams                 \t100\nUdp6NoPorts \t3\nUdp6OutDatagrams                \t40\n";
+        assert_eq!(parse_udp_counters(SNMP, Some(snmp6)), Some(UdpCounters { in_datagrams: 2607, out_datagrams: 2575 }));
+        assert_eq!(parse_udp_counters(SNMP, None), Some(UdpCounters { in_datagrams: 2507, out_datagrams: 2535 }));
+        assert_eq!(parse_udp_counters("Ip: 1\n", None), None);
+
+        let dev = "Inter-|   Receive                                                |  Transmit\n face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n    lo: 5000 120 0 0 0 0 0 0 5000 120 0 0 0 0 0 0\n  eth0: 900000 700 0 0 0 0 0 0 40000 300 0 0 0 0 0 0\n";
+        assert_eq!(parse_proc_loopback(dev), Some((120, 120)));
+    }
+
+    #[test]
+    fn test_parse_macos_netstat() {
+        let udp = "udp:\n\t2296624 datagrams received\n\t\t0 with incomplete header\n\t\t11 dropped due to no socket\n\t1880468 datagrams output\n";
+        assert_eq!(parse_netstat_udp(udp), Some(UdpCounters { in_datagrams: 2296624, out_datagrams: 1880468 }));
+        assert_eq!(parse_netstat_udp("udp:\n"), None);
+
+        let lo0 = "Name       Mtu   Network       Address            Ipkts Ierrs    Opkts Oerrs  Coll\n\
+            lo0        16384 <Link#1>                        3417562     0  3417560     0     0\n\
+            lo0        16384 127           127.0.0.1         3417562     -  3417560     -     -\n";
+        assert_eq!(parse_netstat_loopback(lo0), Some((3417562, 3417560)));
+    }
+
+    #[test]
+    fn test_call_rates() {
+        let cfg = CallInterlockConfig::default();
+        let start = Instant::now();
+        let sample = |secs: u64, dgrams: u64, rx: u64, tx: u64| MediaSample {
+            udp: UdpCounters { in_datagrams: dgrams, out_datagrams: dgrams },
+            loopback_rx_packets: 0,
+            loopback_tx_packets: 0,
+            rx_bytes: rx,
+            tx_bytes: tx,
+            at: start + Duration::from_secs(secs),
+        };
+        // 10 s of 100 packets/s each way, 1.2 Mbps up and 2 Mbps down
+        let call = MediaRates::between(&sample(0, 0, 0, 0), &sample(10, 1000, 2_500_000, 1_500_000)).unwrap();
+        assert!(call.looks_like_call(&cfg));
+        // A large upload is not a call even with UDP chatter
+        let upload = MediaRates::between(&sample(0, 0, 0, 0), &sample(10, 1000, 2_500_000, 50_000_000)).unwrap();
+        assert!(!upload.looks_like_call(&cfg));
+        // Download-only streaming sends too little back
+        let stream = MediaRates::between(&sample(0, 0, 0, 0), &sample(10, 5, 10_000_000, 20_000)).unwrap();
+        assert!(!stream.looks_like_call(&cfg));
+        // The same datagrams over loopback are a local service, not a call
+        let looped = |secs: u64, dgrams: u64, rx: u64, tx: u64| MediaSample { loopback_rx_packets: dgrams, loopback_tx_packets: dgrams, ..sample(secs, dgrams, rx, tx) };
+        let local = MediaRates::between(&looped(0, 0, 0, 0), &looped(10, 1000, 2_500_000, 1_500_000)).unwrap();
+        assert_eq!((local.in_pps, local.out_pps), (0.0, 0.0));
+        assert!(!local.looks_like_call(&cfg));
+        // Counter reset
+        assert!(MediaRates::between(&sample(0, 50, 10, 10), &sample(5, 10, 20, 20)).is_none());
+    }
+
+    #[test]
+    fn test_detector_debounces_start_and_end() {
+        let cfg = CallInterlockConfig { sustain_seconds: 15, release_seconds: 30, ..CallInterlockConfig::default() };
+        let t0 = Instant::now();
+        let at = |s: u64| t0 + Duration::from_secs(s);
+        let mut detector = CallDetector::default();
+
+        assert_eq!(detector.observe(true, at(0), &cfg), None);
+        assert_eq!(detector.observe(false, at(5), &cfg), None);
+        // The streak restarts after a quiet sample
+        assert_eq!(detector.observe(true, at(10), &cfg), None);
+        assert_eq!(detector.observe(true, at(20), &cfg), None);
+        assert_eq!(detector.observe(true, at(25), &cfg), Some(true));
+
+        assert_eq!(detector.observe(false, at(30), &cfg), None);
+        assert_eq!(detector.observe(true, at(40), &cfg), None);
+        assert_eq!(detector.observe(false, at(45), &cfg), None);
+        assert_eq!(detector.observe(false, at(70), &cfg), None);
+        assert_eq!(detector.observe(false, at(75), &cfg), Some(false));
+        assert!(!detector.is_active());
+    }
+}
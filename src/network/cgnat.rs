@@ -0,0 +1,86 @@
+use crate::data::models::{CgnatReport, RouteSnapshot};
+use crate::network::resolvers::is_routable;
+use chrono::Utc;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+
+/// Leading hops searched; the NAT gateway sits at the edge of the access network
+const ACCESS_HOPS: usize = 5;
+
+/// RFC 6598 shared address space (100.64.0.0/10), reserved for carrier-grade NAT
+pub fn is_shared_address(address: &IpAddr) -> bool {
+    matches!(address, IpAddr::V4(v4) if v4.octets()[0] == 100 && (64..128).contains(&v4.octets()[1]))
+}
+
+/// Weighs the interface address and the first hops of each trace against the public address.
+/// Only shared addresses (100.64.0.0/10) mark carrier-grade NAT. A private hop past the first one
+/// may be the ISP NATing again upstream, but just as often a second router at home, so it is
+/// listed without counting towards detection.
+pub fn assess(public_ip: Option<IpAddr>, local_ip: Option<IpAddr>, snapshots: &[RouteSnapshot]) -> CgnatReport {
+    let mut report = CgnatReport { checked_at: Utc::now(), public_ip, local_ip, ..Default::default() };
+    // The machine holds the public address itself: nothing translates it
+    if public_ip.is_some() && public_ip == local_ip {
+        return report;
+    }
+    let add = |list: &mut Vec<IpAddr>, address: IpAddr| {
+        if !list.contains(&address) {
+            list.push(address);
+        }
+    };
+    if let Some(local) = local_ip.filter(is_shared_address) {
+        add(&mut report.shared_addresses, local);
+    }
+    for snapshot in snapshots {
+        // Only hops before the path reaches the public internet belong to the access network
+        let access = snapshot.hops.iter().take(ACCESS_HOPS).take_while(|h| !h.address.is_some_and(|a| is_routable(&a)));
+        for (position, address) in access.filter_map(|h| h.address).enumerate() {
+            if is_shared_address(&address) {
+                add(&mut report.shared_addresses, address);
+            } else if position > 0 && matches!(address, IpAddr::V4(v4) if v4.is_private()) {
+                add(&mut report.private_upstream_hops, address);
+            }
+        }
+    }
+    report
+}
+
+/// Address of the interface the default route uses. Connecting a UDP socket only selects the
+/// route; no packet is sent.
+pub fn local_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(1, 1, 1, 1), 53)).ok()?;
+    socket.local_addr().ok().map(|a| a.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::models::RouteHop;
+
+    fn trace(addresses: &[Option<&str>]) -> RouteSnapshot {
+        let hops = addresses
+            .iter()
+            .enumerate()
+            .map(|(i, a)| RouteHop { ttl: i as u8 + 1, address: a.map(|a| a.parse().unwrap()), rtt_ms: None, asn: None, asn_name: None })
+            .collect();
+        RouteSnapshot { id: None, taken_at: Utc::now(), anchor: "1.1.1.1".into(), hops, isp_name: None, isp_asn: None }
+    }
+
+    #[test]
+    fn test_cgnat_from_shared_and_private_hops() {
+        let public = Some("203.94.70.12".parse().unwrap());
+        let home = Some("192.168.1.20".parse().unwrap());
+
+        let behind = assess(public, home, &[trace(&[Some("192.168.1.1"), None, Some("100.72.0.1"), Some("203.94.64.1"), Some("100.64.9.9")])]);
+        assert!(behind.detected());
+        // Shared addresses past the first public hop are someone else's network
+        assert_eq!(behind.shared_addresses, vec!["100.72.0.1".parse::<IpAddr>().unwrap()]);
+
+        let upstream_nat = assess(public, home, &[trace(&[Some("192.168.1.1"), Some("10.20.0.1"), Some("203.94.64.1")])]);
+        assert_eq!(upstream_nat.private_upstream_hops, vec!["10.20.0.1".parse::<IpAddr>().unwrap()]);
+        assert!(!upstream_nat.detected());
+
+        assert!(!assess(public, home, &[trace(&[Some("192.168.1.1"), Some("203.94.64.1")])]).detected());
+        assert!(assess(public, Some("100.100.3.4".parse().unwrap()), &[]).detected());
+        assert!(!assess(public, public, &[trace(&[Some("10.0.0.1"), Some("10.0.0.2")])]).detected());
+    }
+}
@@ -0,0 +1,133 @@
+//! speed.cloudflare.com test flow: `/meta` names the serving POP, `__down?bytes=0` pings measure
+//! latency, and `__down?bytes=N` / `__up` move data, all tagged with one measurement id.
+
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::network::proxy;
+use rand::Rng;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, ORIGIN, REFERER, USER_AGENT};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+pub const CLOUDFLARE_SPEED_URL: &str = "https://speed.cloudflare.com";
+const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+/// Connection details from `/meta`; `colo` is the IATA code of the serving POP
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudflareMeta {
+    #[serde(default)]
+    pub colo: Option<String>,
+    #[serde(default)]
+    pub asn: Option<u32>,
+    #[serde(default)]
+    pub as_organization: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+}
+
+/// 16-digit id the page attaches to every request of one run
+pub fn new_measurement_id() -> String {
+    let mut rng = rand::thread_rng();
+    (0..16).map(|i| char::from(b'0' + rng.gen_range(if i == 0 { 1 } else { 0 }..10))).collect()
+}
+
+pub fn download_url(measurement_id: &str, bytes: u64) -> String {
+    format!("{}/__down?measId={}&bytes={}", CLOUDFLARE_SPEED_URL, measurement_id, bytes)
+}
+
+pub fn upload_url(measurement_id: &str) -> String {
+    format!("{}/__up?measId={}", CLOUDFLARE_SPEED_URL, measurement_id)
+}
+
+/// Talks to speed.cloudflare.com the way its web page does
+pub struct CloudflareClient {
+    client: Client,
+}
+
+impl Default for CloudflareClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CloudflareClient {
+    pub fn new() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static(BROWSER_USER_AGENT));
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+        headers.insert(ORIGIN, HeaderValue::from_static(CLOUDFLARE_SPEED_URL));
+        headers.insert(REFERER, HeaderValue::from_static("https://speed.cloudflare.com/"));
+        let client = proxy::apply(Client::builder())
+            .timeout(Duration::from_secs(30))
+            .default_headers(headers)
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+
+    fn check(response: reqwest::Response, what: &str) -> Result<reqwest::Response> {
+        if !response.status().is_success() {
+            return Err(SpeedKarmaError::NetworkUnavailable(format!("speed.cloudflare.com {} returned {}", what, response.status())));
+        }
+        Ok(response)
+    }
+
+    /// POP, ASN and location of this connection, fetched first by the page
+    pub async fn meta(&self) -> Result<CloudflareMeta> {
+        let response = self.client.get(format!("{}/meta", CLOUDFLARE_SPEED_URL)).send().await?;
+        Ok(Self::check(response, "meta")?.json().await?)
+    }
+
+    /// Empty download used as a latency ping; returns the round trip
+    pub async fn ping(&self, measurement_id: &str) -> Result<Duration> {
+        let started = Instant::now();
+        let response = self.client.get(download_url(measurement_id, 0)).send().await?;
+        Self::check(response, "ping")?.bytes().await?;
+        Ok(started.elapsed())
+    }
+
+    /// Downloads `bytes` bytes; returns the bytes received
+    pub async fn download(&self, measurement_id: &str, bytes: u64) -> Result<u64> {
+        let response = self.client.get(download_url(measurement_id, bytes)).send().await?;
+        Ok(Self::check(response, "download")?.bytes().await?.len() as u64)
+    }
+
+    /// Uploads `bytes` bytes of the page's filler text; returns the bytes sent
+    pub async fn upload(&self, measurement_id: &str, bytes: u64) -> Result<u64> {
+        let body = "0".repeat(bytes as usize);
+        let response = self.client
+            .post(upload_url(measurement_id))
+            .header("Content-Type", "text/plain;charset=UTF-8")
+            .body(body)
+            .send()
+            .await?;
+        Self::check(response, "upload")?;
+        debug!("Uploaded {} bytes to speed.cloudflare.com", bytes);
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls_and_meta_follow_the_cloudflare_page() {
+        let id = new_measurement_id();
+        assert_eq!(id.len(), 16);
+        assert!(id.chars().all(|c| c.is_ascii_digit()) && !id.starts_with('0'));
+        assert_eq!(download_url("1234567890123456", 0), "https://speed.cloudflare.com/__down?measId=1234567890123456&bytes=0");
+        assert_eq!(upload_url("1234567890123456"), "https://speed.cloudflare.com/__up?measId=1234567890123456");
+
+        let meta: CloudflareMeta = serde_json::from_str(
+            r#"{"hostname":"speed.cloudflare.com","clientIp":"203.0.113.7","httpProtocol":"HTTP/2","asn":9506,"asOrganization":"Hutchison Telecommunications Lanka","colo":"CMB","country":"LK","city":"Colombo"}"#,
+        ).unwrap();
+        assert_eq!(meta.colo.as_deref(), Some("CMB"));
+        assert_eq!(meta.asn, Some(9506));
+        assert_eq!(meta.as_organization.as_deref(), Some("Hutchison Telecommunications Lanka"));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::core::config::AdaptiveConfidenceConfig;
+use crate::data::models::{MeasurementSource, SpeedMeasurement};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+
+/// Confidences remembered per interface (about a day at the default cadence)
+const CONFIDENCE_HISTORY: usize = 1440;
+/// Samples needed before the threshold moves off the fixed one
+const MIN_SAMPLES: usize = 30;
+/// Passive samples kept around to pair with speed tests that land later
+const PAIRING_WINDOW_MINUTES: i64 = 10;
+const AGREEMENT_HISTORY: usize = 60;
+/// Speed-test pairs needed before agreement shifts the threshold
+const MIN_PAIRS: usize = 5;
+/// A passive sample within this share of the speed test counts as agreeing
+const AGREEMENT_TOLERANCE: f64 = 0.3;
+/// Samples at or above the learned cutoff must agree at least this often
+const TARGET_AGREEMENT: f64 = 0.7;
+
+#[derive(Debug, Clone)]
+struct PassiveSample {
+    at: DateTime<Utc>,
+    interface: String,
+    confidence: f64,
+    download_mbps: f64,
+}
+
+#[derive(Debug, Default)]
+struct InterfaceHistory {
+    confidences: VecDeque<f64>,
+    /// Confidence of a passive sample and whether it matched the speed test next to it
+    agreements: VecDeque<(f64, bool)>,
+}
+
+impl InterfaceHistory {
+    /// Lowest confidence from which samples matched speed tests often enough; `None` without
+    /// enough pairs, and a stricter-than-any cutoff when even the best samples disagreed
+    fn agreement_cutoff(&self) -> Option<f64> {
+        if self.agreements.len() < MIN_PAIRS {
+            return None;
+        }
+        let mut pairs: Vec<(f64, bool)> = self.agreements.iter().copied().collect();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let cutoff = (0..pairs.len()).find(|&i| {
+            let tail = &pairs[i..];
+            tail.iter().filter(|(_, agreed)| *agreed).count() as f64 / tail.len() as f64 >= TARGET_AGREEMENT
+        });
+        Some(cutoff.map(|i| pairs[i].0).unwrap_or(1.0))
+    }
+}
+
+/// Learns, per interface, the confidence a passive sample needs to be stored. Noisy links get a
+/// lower bar so they keep producing data, clean links a higher one, and samples that disagreed
+/// with nearby speed tests push the bar up.
+#[derive(Debug)]
+pub struct ConfidenceCalibrator {
+    config: AdaptiveConfidenceConfig,
+    /// Used until an interface has history
+    fallback: f64,
+    interfaces: HashMap<String, InterfaceHistory>,
+    recent: VecDeque<PassiveSample>,
+    /// Speed tests up to here have been paired
+    active_seen_until: DateTime<Utc>,
+}
+
+impl ConfidenceCalibrator {
+    pub fn new(config: AdaptiveConfidenceConfig, fallback: f64) -> Self {
+        Self { config, fallback, interfaces: HashMap::new(), recent: VecDeque::new(), active_seen_until: Utc::now() }
+    }
+
+    /// Records a passive sample, stored or not, and returns the threshold it has to meet
+    pub fn observe(&mut self, interface: &str, at: DateTime<Utc>, confidence: f64, download_mbps: f64) -> f64 {
+        let threshold = self.threshold(interface);
+        let history = self.interfaces.entry(interface.to_string()).or_default();
+        history.confidences.push_back(confidence);
+        if history.confidences.len() > CONFIDENCE_HISTORY {
+            history.confidences.pop_front();
+        }
+        self.recent.push_back(PassiveSample { at, interface: interface.to_string(), confidence, download_mbps });
+        let cutoff = at - Duration::minutes(PAIRING_WINDOW_MINUTES * 2);
+        while self.recent.front().is_some_and(|s| s.at < cutoff) {
+            self.recent.pop_front();
+        }
+        threshold
+    }
+
+    /// Pairs speed tests newer than the last call with the closest passive sample
+    pub fn compare_with_active(&mut self, measurements: &[SpeedMeasurement]) {
+        let window = Duration::minutes(PAIRING_WINDOW_MINUTES);
+        for test in measurements.iter().filter(|m| m.source == MeasurementSource::Active && m.timestamp > self.active_seen_until) {
+            let closest = self
+                .recent
+                .iter()
+                .filter(|s| (s.at - test.timestamp).abs() <= window)
+                .min_by_key(|s| (s.at - test.timestamp).abs());
+            if let (Some(sample), true) = (closest, test.download_mbps > 0.0) {
+                let agreed = (sample.download_mbps - test.download_mbps).abs() / test.download_mbps <= AGREEMENT_TOLERANCE;
+                let history = self.interfaces.entry(sample.interface.clone()).or_default();
+                history.agreements.push_back((sample.confidence, agreed));
+                if history.agreements.len() > AGREEMENT_HISTORY {
+                    history.agreements.pop_front();
+                }
+            }
+        }
+        if let Some(latest) = measurements.iter().filter(|m| m.source == MeasurementSource::Active).map(|m| m.timestamp).max() {
+            self.active_seen_until = self.active_seen_until.max(latest);
+        }
+    }
+
+    /// When speed tests were last paired; fetch measurements from here on
+    pub fn active_seen_until(&self) -> DateTime<Utc> {
+        self.active_seen_until
+    }
+
+    pub fn threshold(&self, interface: &str) -> f64 {
+        let (min, max) = (self.config.min_threshold, self.config.max_threshold);
+        let Some(history) = self.interfaces.get(interface).filter(|h| h.confidences.len() >= MIN_SAMPLES) else {
+            return self.fallback.clamp(min, max);
+        };
+        let mut sorted: Vec<f64> = history.confidences.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let index = ((self.config.drop_share * sorted.len() as f64) as usize).min(sorted.len() - 1);
+        let from_distribution = sorted[index];
+        let threshold = match history.agreement_cutoff() {
+            Some(cutoff) => (from_distribution + cutoff) / 2.0,
+            None => from_distribution,
+        };
+        threshold.clamp(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_follows_link_noise_and_speed_test_agreement() {
+        let config = AdaptiveConfidenceConfig::default();
+        let mut calibrator = ConfidenceCalibrator::new(config.clone(), 0.3);
+        let start = Utc::now();
+
+        // Too little history: the fixed threshold applies
+        assert_eq!(calibrator.observe("wlan0", start, 0.25, 40.0), 0.3);
+
+        // A noisy link settles low, a clean one high, each within bounds
+        for i in 0..100 {
+            let at = start + Duration::minutes(i);
+            calibrator.observe("wlan0", at, 0.2 + (i % 10) as f64 * 0.02, 40.0);
+            calibrator.observe("eth0", at, 0.8 + (i % 10) as f64 * 0.02, 90.0);
+        }
+        let noisy = calibrator.threshold("wlan0");
+        assert!(noisy >= config.min_threshold && noisy < 0.3, "{}", noisy);
+        assert_eq!(calibrator.threshold("eth0"), config.max_threshold);
+
+        // Speed tests that disagree with everything wlan0 reported raise its bar
+        let tests: Vec<SpeedMeasurement> = (0..6)
+            .map(|i| SpeedMeasurement { timestamp: start + Duration::minutes(90 + i), ..SpeedMeasurement::new(100.0, 10.0, 20, false).with_source(MeasurementSource::Active) })
+            .collect();
+        calibrator.compare_with_active(&tests);
+        assert!(calibrator.threshold("wlan0") > noisy);
+        assert_eq!(calibrator.active_seen_until(), tests[5].timestamp);
+    }
+}
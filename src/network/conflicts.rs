@@ -0,0 +1,128 @@
+//! Scans for competing tools and pauses the traffic generators while one runs. What counts as a
+//! conflict is decided in `core::conflicts`.
+
+use crate::core::app_state::SharedAppState;
+use crate::core::config::ConflictDetectionConfig;
+use crate::core::conflicts::{is_local_proxy, match_known_processes, match_tunnel_interfaces, ConflictKind, ConflictReport, ConflictSignal};
+use crate::core::events::SharedEventSink;
+use crate::core::watcher::PollingWatcher;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+const PROXY_VARS: &[&str] = &["ALL_PROXY", "HTTPS_PROXY", "HTTP_PROXY", "all_proxy", "https_proxy", "http_proxy"];
+
+fn local_proxy_signals() -> Vec<ConflictSignal> {
+    let mut found: Vec<ConflictSignal> = PROXY_VARS
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .filter(|url| is_local_proxy(url))
+        .map(|url| ConflictSignal { kind: ConflictKind::LocalProxy, name: url })
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+#[cfg(feature = "sysinfo")]
+async fn running_processes() -> Vec<String> {
+    use sysinfo::{ProcessRefreshKind, RefreshKind, System};
+    tokio::task::spawn_blocking(|| {
+        let system = System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()));
+        system.processes().values().map(|p| p.name().to_string()).collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+#[cfg(not(feature = "sysinfo"))]
+async fn running_processes() -> Vec<String> {
+    let mut names = Vec::new();
+    let Ok(mut entries) = tokio::fs::read_dir("/proc").await else { return names };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if !entry.file_name().to_string_lossy().chars().all(|c| c.is_ascii_digit()) { continue; }
+        if let Ok(comm) = tokio::fs::read_to_string(entry.path().join("comm")).await {
+            names.push(comm.trim().to_string());
+        }
+    }
+    names
+}
+
+#[cfg(feature = "sysinfo")]
+async fn interface_names() -> Vec<String> {
+    sysinfo::Networks::new_with_refreshed_list().keys().cloned().collect()
+}
+
+#[cfg(not(feature = "sysinfo"))]
+async fn interface_names() -> Vec<String> {
+    match tokio::fs::read_to_string("/proc/net/dev").await {
+        Ok(content) => crate::network::monitor::parse_proc_net_dev(&content, std::time::Instant::now()).into_keys().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Scans processes, interfaces and proxy settings once
+pub async fn scan() -> ConflictReport {
+    let processes = running_processes().await;
+    let interfaces = interface_names().await;
+    let mut signals = match_known_processes(processes.iter().map(String::as_str));
+    signals.extend(match_tunnel_interfaces(interfaces.iter().map(String::as_str)));
+    signals.extend(local_proxy_signals());
+    ConflictReport { signals, detected_at: Utc::now() }
+}
+
+/// Periodically looks for competing tools and pauses SpeedKarma's traffic generators while one is active
+pub struct ConflictWatcher {
+    events: SharedEventSink,
+    shared: SharedAppState,
+    config: Arc<RwLock<ConflictDetectionConfig>>,
+    show_notifications: bool,
+}
+
+impl ConflictWatcher {
+    pub fn new(events: SharedEventSink, shared: SharedAppState, config: ConflictDetectionConfig, show_notifications: bool) -> Self {
+        Self { events, shared, config: Arc::new(RwLock::new(config)), show_notifications }
+    }
+
+    pub async fn update_config(&self, cfg: ConflictDetectionConfig) { *self.config.write().await = cfg; }
+
+    async fn apply(&self, cfg: &ConflictDetectionConfig, report: Option<ConflictReport>) {
+        let current = report.filter(ConflictReport::has_conflict);
+        let previous = {
+            let mut state = self.shared.write().await;
+            state.generators_paused = cfg.pause_generators && current.is_some();
+            std::mem::replace(&mut state.conflict, current.clone())
+        };
+
+        let names = |r: &Option<ConflictReport>| r.as_ref().map(|r| r.signals.clone()).unwrap_or_default();
+        if names(&previous) == names(&current) {
+            debug!("Conflict scan unchanged");
+            return;
+        }
+
+        match &current {
+            Some(report) => {
+                warn!("Competing network tools detected: {}", report.summary());
+                if self.show_notifications {
+                    let action = if cfg.pause_generators { "SpeedKarma paused its traffic to avoid clashing with" } else { "SpeedKarma may clash with" };
+                    self.events.notify("SpeedKarma", &format!("{} {}", action, report.summary()));
+                }
+            }
+            None => info!("Competing network tools no longer detected; generators resumed"),
+        }
+        self.events.emit_payload("conflict_status", &current);
+    }
+}
+
+#[async_trait]
+impl PollingWatcher for ConflictWatcher {
+    async fn poll(&self) -> Duration {
+        let cfg = self.config.read().await.clone();
+        let report = if cfg.enabled { Some(scan().await) } else { None };
+        self.apply(&cfg, report).await;
+        Duration::from_secs(cfg.check_interval_seconds.max(10))
+    }
+}
@@ -0,0 +1,161 @@
+use crate::data::models::SpeedtestServer;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Rows idle this long are dropped from the table (seconds)
+const STALE_AFTER_S: i64 = 15 * 60;
+
+/// Module holding a server connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionOwner {
+    Keeper,
+    Stealth,
+}
+
+/// One row of `get_connection_table`
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionRow {
+    pub owner: ConnectionOwner,
+    pub server_id: String,
+    pub server_name: String,
+    pub host: String,
+    pub opened_at: DateTime<Utc>,
+    pub age_s: u64,
+    pub bytes: u64,
+    pub requests: u64,
+    pub last_activity: DateTime<Utc>,
+    pub idle_s: u64,
+    pub errors: u32,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    server_name: String,
+    host: String,
+    opened_at: DateTime<Utc>,
+    bytes: u64,
+    requests: u64,
+    last_activity: DateTime<Utc>,
+    errors: u32,
+    last_error: Option<(String, DateTime<Utc>)>,
+}
+
+/// Bookkeeping shared by the keeper and stealth engine for every server they keep warm
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionTable {
+    entries: Arc<Mutex<HashMap<(ConnectionOwner, String), Entry>>>,
+}
+
+impl ConnectionTable {
+    pub fn new() -> Self { Self::default() }
+
+    fn touch<F: FnOnce(&mut Entry)>(&self, owner: ConnectionOwner, server: &SpeedtestServer, now: DateTime<Utc>, update: F) {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.entry((owner, server.server_id.clone())).or_insert_with(|| Entry {
+            server_name: server.name.clone(),
+            host: server.host.clone(),
+            opened_at: now,
+            bytes: 0,
+            requests: 0,
+            last_activity: now,
+            errors: 0,
+            last_error: None,
+        });
+        update(entry);
+    }
+
+    /// A request to `server` went through, moving `bytes`
+    pub fn record_activity(&self, owner: ConnectionOwner, server: &SpeedtestServer, bytes: u64) {
+        let now = Utc::now();
+        self.touch(owner, server, now, |e| {
+            e.bytes += bytes;
+            e.requests += 1;
+            e.last_activity = now;
+        });
+    }
+
+    pub fn record_error(&self, owner: ConnectionOwner, server: &SpeedtestServer, error: &str) {
+        let now = Utc::now();
+        self.touch(owner, server, now, |e| {
+            e.errors += 1;
+            e.last_error = Some((error.to_string(), now));
+        });
+    }
+
+    /// The owner let go of its connection to `server_id` (e.g. after rotating away)
+    pub fn close(&self, owner: ConnectionOwner, server_id: &str) {
+        self.entries.lock().unwrap().remove(&(owner, server_id.to_string()));
+    }
+
+    /// Drops every row of `owner`, e.g. when it stops
+    pub fn close_owner(&self, owner: ConnectionOwner) {
+        self.entries.lock().unwrap().retain(|(o, _), _| *o != owner);
+    }
+
+    /// Current rows, most recently active first per owner; stale rows are pruned
+    pub fn snapshot(&self) -> Vec<ConnectionRow> {
+        self.snapshot_at(Utc::now())
+    }
+
+    fn snapshot_at(&self, now: DateTime<Utc>) -> Vec<ConnectionRow> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, e| (now - e.last_activity).num_seconds() < STALE_AFTER_S);
+        let secs = |from: DateTime<Utc>| (now - from).num_seconds().max(0) as u64;
+        let mut rows: Vec<ConnectionRow> = entries
+            .iter()
+            .map(|((owner, server_id), e)| ConnectionRow {
+                owner: *owner,
+                server_id: server_id.clone(),
+                server_name: e.server_name.clone(),
+                host: e.host.clone(),
+                opened_at: e.opened_at,
+                age_s: secs(e.opened_at),
+                bytes: e.bytes,
+                requests: e.requests,
+                last_activity: e.last_activity,
+                idle_s: secs(e.last_activity),
+                errors: e.errors,
+                last_error: e.last_error.as_ref().map(|(msg, _)| msg.clone()),
+                last_error_at: e.last_error.as_ref().map(|(_, at)| *at),
+            })
+            .collect();
+        rows.sort_by(|a, b| a.owner.cmp(&b.owner).then(b.last_activity.cmp(&a.last_activity)));
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_connection_bookkeeping() {
+        let table = ConnectionTable::new();
+        let a = SpeedtestServer::cloudflare();
+        let b = SpeedtestServer { server_id: "b".into(), name: "B".into(), ..SpeedtestServer::cloudflare() };
+
+        table.record_activity(ConnectionOwner::Keeper, &a, 65_536);
+        table.record_activity(ConnectionOwner::Keeper, &a, 1024);
+        table.record_error(ConnectionOwner::Keeper, &a, "timed out");
+        table.record_activity(ConnectionOwner::Stealth, &b, 300);
+
+        let rows = table.snapshot();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].owner, ConnectionOwner::Keeper);
+        assert_eq!((rows[0].bytes, rows[0].requests, rows[0].errors), (66_560, 2, 1));
+        assert_eq!(rows[0].last_error.as_deref(), Some("timed out"));
+        assert_eq!(rows[1].server_name, "B");
+
+        table.close(ConnectionOwner::Stealth, "b");
+        assert_eq!(table.snapshot().len(), 1);
+
+        // Idle rows age out
+        assert!(table.snapshot_at(Utc::now() + Duration::seconds(STALE_AFTER_S + 1)).is_empty());
+    }
+}
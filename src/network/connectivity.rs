@@ -0,0 +1,172 @@
+use crate::core::app_state::SharedAppState;
+pub use crate::core::app_state::ConnectivityState;
+use crate::core::config::ConnectivityCheckConfig;
+use crate::core::events::SharedEventSink;
+use crate::core::watcher::PollingWatcher;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// Recheck interval while offline or behind a portal, so loops resume soon after it clears
+const RECHECK_WHILE_LOST: Duration = Duration::from_secs(10);
+
+/// What one 204 probe saw
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    NoContent,
+    /// Any other answer; `location` is the redirect target, typically the portal's login page
+    Intercepted { location: Option<String> },
+    Failed,
+}
+
+/// Online when any probe got its 204, behind a portal when one was answered otherwise, else offline
+pub fn classify(outcomes: &[ProbeOutcome]) -> (ConnectivityState, Option<String>) {
+    if outcomes.contains(&ProbeOutcome::NoContent) {
+        return (ConnectivityState::Online, None);
+    }
+    let mut intercepted = outcomes.iter().filter_map(|o| match o {
+        ProbeOutcome::Intercepted { location } => Some(location.clone()),
+        _ => None,
+    });
+    match intercepted.next() {
+        Some(location) => (ConnectivityState::CaptivePortal, location.or_else(|| intercepted.flatten().next())),
+        None => (ConnectivityState::Offline, None),
+    }
+}
+
+/// `get_connectivity_status` payload and `connectivity_changed` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityStatus {
+    pub state: ConnectivityState,
+    /// Where the portal redirected the probe, when it did
+    pub portal_url: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Probes 204 endpoints and holds back every loop while offline or behind a captive portal, so
+/// failures there neither flood the log nor reach the confidence statistics
+pub struct ConnectivityWatcher {
+    events: SharedEventSink,
+    shared: SharedAppState,
+    config: ConnectivityCheckConfig,
+    /// Built once; probes never follow redirects so a portal's answer stays visible
+    client: Client,
+    status: RwLock<Option<ConnectivityStatus>>,
+    limiter: Option<OutboundLimiter>,
+    show_notifications: bool,
+}
+
+impl ConnectivityWatcher {
+    pub fn new(events: SharedEventSink, shared: SharedAppState, config: ConnectivityCheckConfig, show_notifications: bool) -> Self {
+        let client = proxy::apply(Client::builder())
+            .redirect(Policy::none())
+            .timeout(Duration::from_secs(config.timeout_seconds.max(1)))
+            .build()
+            .unwrap_or_default();
+        Self { events, shared, config, client, status: RwLock::new(None), limiter: None, show_notifications }
+    }
+
+    /// Generated traffic in flight on this limiter is cancelled when the connection is lost
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    pub async fn status(&self) -> Option<ConnectivityStatus> {
+        self.status.read().await.clone()
+    }
+
+    /// Runs every configured probe once
+    async fn check(&self) -> ConnectivityStatus {
+        let mut outcomes = Vec::with_capacity(self.config.probe_urls.len());
+        for url in &self.config.probe_urls {
+            let outcome = match self.client.get(url).send().await {
+                Ok(response) if response.status() == StatusCode::NO_CONTENT => ProbeOutcome::NoContent,
+                Ok(response) => ProbeOutcome::Intercepted {
+                    location: response.headers().get(LOCATION).and_then(|v| v.to_str().ok()).map(str::to_string),
+                },
+                Err(e) => {
+                    debug!("Connectivity probe {} failed: {}", url, e);
+                    ProbeOutcome::Failed
+                }
+            };
+            // One answer is enough to be online
+            let online = outcome == ProbeOutcome::NoContent;
+            outcomes.push(outcome);
+            if online {
+                break;
+            }
+        }
+        let (state, portal_url) = classify(&outcomes);
+        ConnectivityStatus { state, portal_url, checked_at: Utc::now() }
+    }
+
+    async fn apply(&self, status: ConnectivityStatus) {
+        let was_online = std::mem::replace(&mut self.shared.write().await.connectivity, status.state) == ConnectivityState::Online;
+        let previous = self.status.write().await.replace(status.clone());
+        if previous.as_ref().map(|p| p.state) == Some(status.state) {
+            debug!("Connectivity check unchanged");
+            return;
+        }
+        // Whatever was generating traffic when the connection went can only time out now
+        if was_online && status.state != ConnectivityState::Online {
+            if let Some(limiter) = &self.limiter {
+                limiter.interrupt();
+            }
+        }
+        match status.state {
+            ConnectivityState::CaptivePortal => {
+                warn!("Captive portal detected{}; monitoring and generated traffic paused",
+                    status.portal_url.as_deref().map(|u| format!(" ({})", u)).unwrap_or_default());
+                if self.show_notifications {
+                    self.events.notify("SpeedKarma", "This network wants you to sign in. SpeedKarma paused until the connection is open.");
+                }
+            }
+            ConnectivityState::Offline => warn!("Connection offline; monitoring and generated traffic paused"),
+            // The first check finding the connection online is not news
+            ConnectivityState::Online if previous.is_some() => info!("Connection back online; monitoring and generated traffic resumed"),
+            ConnectivityState::Online => {}
+        }
+        self.events.emit_payload("connectivity_changed", &status);
+    }
+}
+
+#[async_trait]
+impl PollingWatcher for ConnectivityWatcher {
+    async fn poll(&self) -> Duration {
+        let status = if self.config.enabled {
+            self.check().await
+        } else {
+            ConnectivityStatus { state: ConnectivityState::Online, portal_url: None, checked_at: Utc::now() }
+        };
+        let interval = Duration::from_secs(self.config.check_interval_seconds.max(5));
+        let wait = if status.state == ConnectivityState::Online { interval } else { interval.min(RECHECK_WHILE_LOST) };
+        self.apply(status).await;
+        wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_probe_outcomes() {
+        let portal = ProbeOutcome::Intercepted { location: Some("http://login.hotel.example/".to_string()) };
+        assert_eq!(classify(&[ProbeOutcome::Failed, ProbeOutcome::NoContent]), (ConnectivityState::Online, None));
+        assert_eq!(classify(&[portal.clone(), ProbeOutcome::NoContent]).0, ConnectivityState::Online);
+        assert_eq!(
+            classify(&[ProbeOutcome::Intercepted { location: None }, ProbeOutcome::Failed, portal]),
+            (ConnectivityState::CaptivePortal, Some("http://login.hotel.example/".to_string())),
+        );
+        assert_eq!(classify(&[ProbeOutcome::Failed, ProbeOutcome::Failed]), (ConnectivityState::Offline, None));
+    }
+}
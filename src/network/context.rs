@@ -0,0 +1,159 @@
+//! Which network the machine is on. A laptop moving from home Wi-Fi to the office lands on a
+//! different ISP with a different baseline, so every measurement carries the key of the network it
+//! was taken on and the intelligence core trains on the current network only.
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use crate::core::command::command_output;
+use crate::network::resolvers;
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Longest a detected context is reused while the route looks unchanged; another Wi-Fi network
+/// can hand out the same local address
+const CONTEXT_MAX_AGE: Duration = Duration::from_secs(600);
+
+/// Default-route interface, Wi-Fi network name and gateway of the current connection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NetworkContext {
+    pub interface: Option<String>,
+    pub ssid: Option<String>,
+    pub gateway: Option<IpAddr>,
+}
+
+impl NetworkContext {
+    /// Stable key stored with measurements; a hash, so network names never reach exports.
+    /// The SSID stands in for the interface when known, since one Wi-Fi adapter joins many networks
+    /// and a dock's Ethernet port can appear under a new name. `None` when nothing was detected.
+    pub fn key(&self) -> Option<String> {
+        let gateway = self.gateway.map(|g| g.to_string());
+        let name = self.ssid.as_deref().map(|ssid| format!("ssid:{}", ssid))
+            .or_else(|| self.interface.as_deref().map(|interface| format!("if:{}", interface)));
+        if name.is_none() && gateway.is_none() {
+            return None;
+        }
+        let identity = format!("{}|{}", name.unwrap_or_default(), gateway.unwrap_or_default());
+        // FNV-1a: the key must not change between builds, which std's hasher does not promise
+        let hash = identity.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+        Some(format!("{:016x}", hash))
+    }
+
+    /// Short description for logs and notifications
+    pub fn describe(&self) -> String {
+        let name = self.ssid.clone().or_else(|| self.interface.clone()).unwrap_or_else(|| "unknown network".to_string());
+        match self.gateway {
+            Some(gateway) => format!("{} via {}", name, gateway),
+            None => name,
+        }
+    }
+}
+
+/// `iwgetid -r` prints the SSID alone; an empty line means not associated
+pub fn parse_iwgetid(output: &str) -> Option<String> {
+    Some(output.trim()).filter(|ssid| !ssid.is_empty()).map(str::to_string)
+}
+
+/// `SSID` line of Windows `netsh wlan show interfaces`; `BSSID` is skipped
+pub fn parse_ssid_field(output: &str, separator: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once(separator)?;
+        (key.trim() == "SSID").then(|| value.trim().to_string()).filter(|ssid| !ssid.is_empty())
+    })
+}
+
+/// `interface:` line of macOS `route -n get default`
+pub fn parse_route_get_interface(output: &str) -> Option<String> {
+    output.lines().find_map(|line| Some(line.trim().strip_prefix("interface:")?.trim().to_string()))
+}
+
+#[cfg(target_os = "linux")]
+async fn interface_and_ssid() -> (Option<String>, Option<String>) {
+    let interface = tokio::fs::read_to_string("/proc/net/route").await.ok().as_deref().and_then(crate::network::metered::default_route_interface);
+    let ssid = match &interface {
+        Some(interface) => command_output("iwgetid", &[interface, "-r"]).await.as_deref().and_then(parse_iwgetid),
+        None => None,
+    };
+    (interface, ssid)
+}
+
+#[cfg(target_os = "macos")]
+async fn interface_and_ssid() -> (Option<String>, Option<String>) {
+    let interface = command_output("route", &["-n", "get", "default"]).await.as_deref().and_then(parse_route_get_interface);
+    let ssid = command_output("system_profiler", &["SPAirPortDataType"]).await.as_deref().and_then(crate::network::wifi::parse_system_profiler_ssid);
+    (interface, ssid)
+}
+
+#[cfg(target_os = "windows")]
+async fn interface_and_ssid() -> (Option<String>, Option<String>) {
+    let ssid = command_output("netsh", &["wlan", "show", "interfaces"]).await.as_deref().and_then(|out| parse_ssid_field(out, " : "));
+    (None, ssid)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+async fn interface_and_ssid() -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+/// Network the machine is on right now
+pub async fn detect() -> NetworkContext {
+    let (interface, ssid) = interface_and_ssid().await;
+    let (_, gateway) = resolvers::system_hosts().await;
+    NetworkContext { interface, ssid, gateway }
+}
+
+struct CachedContext {
+    route: Option<IpAddr>,
+    detected_at: Instant,
+    context: NetworkContext,
+}
+
+static CACHED: Mutex<Option<CachedContext>> = Mutex::new(None);
+
+/// Local address the OS picks for the default route. Connecting a UDP socket only looks the
+/// route up; nothing is sent.
+async fn route_address() -> Option<IpAddr> {
+    let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await.ok()?;
+    socket.connect("192.0.2.1:9").await.ok()?;
+    socket.local_addr().ok().map(|address| address.ip())
+}
+
+/// Like `detect`, but reuses the last result until the default route's local address changes,
+/// so measuring every tick does not spawn the platform's Wi-Fi and route tools each time
+pub async fn current() -> NetworkContext {
+    let route = route_address().await;
+    let cached = CACHED.lock().unwrap().as_ref()
+        .filter(|cached| cached.route == route && cached.detected_at.elapsed() < CONTEXT_MAX_AGE)
+        .map(|cached| cached.context.clone());
+    if let Some(context) = cached {
+        return context;
+    }
+    let context = detect().await;
+    *CACHED.lock().unwrap() = Some(CachedContext { route, detected_at: Instant::now(), context: context.clone() });
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_keys_and_parsers() {
+        let home = NetworkContext { interface: Some("wlan0".into()), ssid: Some("home".into()), gateway: Some("192.168.1.1".parse().unwrap()) };
+        let key = home.key().unwrap();
+        assert_eq!(key.len(), 16);
+        assert_eq!(home.key(), Some(key.clone()), "keys are stable");
+        // Same Wi-Fi through another adapter is the same network; another SSID is not
+        assert_eq!(NetworkContext { interface: Some("wlan1".into()), ..home.clone() }.key(), Some(key.clone()));
+        assert_ne!(NetworkContext { ssid: Some("office".into()), ..home.clone() }.key(), Some(key.clone()));
+        assert_ne!(NetworkContext { ssid: None, ..home.clone() }.key(), Some(key));
+        assert_eq!(NetworkContext::default().key(), None);
+        assert_eq!(home.describe(), "home via 192.168.1.1");
+
+        assert_eq!(parse_iwgetid("home\n"), Some("home".to_string()));
+        assert_eq!(parse_iwgetid("\n"), None);
+        let netsh = "    Name                   : Wi-Fi\n    SSID                   : office\n    BSSID                  : aa:bb:cc:dd:ee:ff\n";
+        assert_eq!(parse_ssid_field(netsh, " : "), Some("office".to_string()));
+        assert_eq!(parse_route_get_interface("    gateway: 192.168.1.1\n  interface: en0\n"), Some("en0".to_string()));
+    }
+}
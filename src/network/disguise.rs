@@ -1,23 +1,78 @@
-use crate::core::app_state::{OptimizationMode, SharedAppState};
+use crate::core::app_state::{self, SharedAppState};
 use crate::core::config::DisguiseModeConfig;
+use crate::core::error::Result;
+use crate::core::events::SharedEventSink;
+use crate::core::shutdown::{self, CancellationToken};
+use crate::core::supervisor::Supervisor;
 use crate::data::repository::Repository;
-use crate::data::models::StealthLevel;
+use crate::data::models::{MimicryProfile, ServerEndpoint, StealthLevel, TrafficSource};
+use crate::network::cloudflare::{self, CloudflareClient};
+use crate::network::fastcom::FastComClient;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use crate::network::usage::DataUsageMeter;
 use std::sync::Arc;
 use std::time::Duration;
-use tauri::AppHandle;
 use tracing::{info, warn, debug};
 
+/// Time between warmup pulses
+const PULSE_INTERVAL: Duration = Duration::from_secs(300);
+/// Size of the download a pulse pulls, the opening request of a speed test
+const WARMUP_BYTES: u64 = 128 * 1024;
+
 /// Global disguise proxy: best-effort approach that periodically warms up and can be wired to an HTTP proxy later.
 pub struct DisguiseProxy {
-    app: AppHandle,
+    events: SharedEventSink,
     repository: Arc<Repository>,
     shared: SharedAppState,
     config: DisguiseModeConfig,
+    supervisor: Option<Supervisor>,
+    shutdown: Option<CancellationToken>,
+    /// Cancelled by `stop` when disguise mode is switched off
+    stopped: CancellationToken,
+    limiter: OutboundLimiter,
+    usage: Option<DataUsageMeter>,
+    /// Set by the user; otherwise each pulse follows the active strategy's profile
+    mimicry_profile: Option<MimicryProfile>,
+    fast_com: FastComClient,
+    cloudflare: CloudflareClient,
 }
 
 impl DisguiseProxy {
-    pub fn new(app: AppHandle, repository: Arc<Repository>, shared: SharedAppState, config: DisguiseModeConfig) -> Self {
-        Self { app, repository, shared, config }
+    pub fn new(events: SharedEventSink, repository: Arc<Repository>, shared: SharedAppState, config: DisguiseModeConfig) -> Self {
+        Self { events, repository, shared, config, supervisor: None, shutdown: None, stopped: CancellationToken::new(),
+            limiter: OutboundLimiter::default(), usage: None, mimicry_profile: None, fast_com: FastComClient::new(), cloudflare: CloudflareClient::new() }
+    }
+
+    /// Restarts the pulse with backoff when it panics
+    pub fn with_supervisor(mut self, supervisor: Supervisor) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    /// Ends the pulse once `token` is cancelled; it writes nothing, so it can stop mid-round
+    pub fn with_shutdown(mut self, token: CancellationToken) -> Self {
+        self.shutdown = Some(token);
+        self
+    }
+
+    /// Counts each warmup request against the app-wide outbound limit
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Counts warmup bytes against the monthly data cap
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Speed test whose opening the pulse imitates whatever the active strategy says; a pinned
+    /// strategy's profile still wins
+    pub fn with_mimicry_profile(mut self, profile: MimicryProfile) -> Self {
+        self.mimicry_profile = Some(profile);
+        self
     }
 
     /// Placeholder: future hook to route app HTTP requests through a header-masquerading client.
@@ -25,21 +80,120 @@ impl DisguiseProxy {
 
     /// Background pulse that mimics speedtest headers to keep cache/paths primed for general traffic
     pub fn start(self: Arc<Self>) {
-        tauri::async_runtime::spawn(async move {
+        match self.supervisor.clone() {
+            Some(supervisor) => supervisor.spawn("disguise", move || {
+                let proxy = Arc::clone(&self);
+                async move { proxy.run().await }
+            }),
+            None => {
+                tokio::spawn(async move { self.run().await });
+            }
+        }
+    }
+
+    /// Ends the pulse for good; a stopped proxy is not started again
+    pub fn stop(&self) {
+        self.stopped.cancel();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.is_cancelled()
+    }
+
+    /// Opening of the profile's speed test; bytes that arrived before a failure or an emergency
+    /// stop still count against the cap
+    async fn warmup(&self, profile: MimicryProfile, stealth_level: &StealthLevel) -> Result<()> {
+        let mut received = 0u64;
+        let cancel = self.limiter.traffic_token();
+        let pull = async {
+            match profile {
+                MimicryProfile::Speedtest => self.speedtest_warmup(stealth_level, &mut received).await,
+                MimicryProfile::FastCom => self.fast_com_warmup(&mut received).await,
+                MimicryProfile::Cloudflare => self.cloudflare_warmup(&mut received).await,
+            }
+        };
+        // The emergency stop drops the requests in flight
+        let outcome = tokio::select! {
+            _ = cancel.cancelled() => Ok(()),
+            out = pull => out,
+        };
+        if let Some(usage) = &self.usage {
+            usage.record(TrafficSource::Disguise, received).await;
+        }
+        outcome?;
+        debug!("Disguise {} warmup pulled {} bytes", profile.as_str(), received);
+        Ok(())
+    }
+
+    /// speedtest.net: a latency request and a small download from an active server
+    async fn speedtest_warmup(&self, stealth_level: &StealthLevel, received: &mut u64) -> Result<()> {
+        let servers = self.repository.get_active_speedtest_servers().await?;
+        let Some(server) = servers.first() else { return Ok(()) };
+        let secure = !matches!(stealth_level, StealthLevel::Low);
+        let client = proxy::apply(reqwest::Client::builder()).timeout(Duration::from_secs(15)).build()?;
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        for (endpoint, bytes) in [(ServerEndpoint::Latency, 0), (ServerEndpoint::Download, WARMUP_BYTES)] {
+            let _permit = self.limiter.acquire("disguise warmup").await;
+            let mut response = client.get(server.endpoint_url(secure, endpoint, bytes, &nonce)).send().await?;
+            while let Some(chunk) = response.chunk().await? {
+                *received += chunk.len() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// fast.com: one cache from api.fast.com and a small range from it, as the page warms up
+    async fn fast_com_warmup(&self, received: &mut u64) -> Result<()> {
+        let targets = {
+            let _permit = self.limiter.acquire("disguise fast.com api").await;
+            self.fast_com.targets(1).await?
+        };
+        let Some(target) = targets.first() else { return Ok(()) };
+        let _permit = self.limiter.acquire("disguise warmup").await;
+        *received += self.fast_com.fetch_range(target, 0, WARMUP_BYTES - 1).await?;
+        Ok(())
+    }
+
+    /// speed.cloudflare.com: `/meta`, then a small download
+    async fn cloudflare_warmup(&self, received: &mut u64) -> Result<()> {
+        let measurement_id = cloudflare::new_measurement_id();
+        {
+            let _permit = self.limiter.acquire("disguise cloudflare meta").await;
+            if let Err(e) = self.cloudflare.meta().await {
+                debug!("speed.cloudflare.com meta failed: {}", e);
+            }
+        }
+        let _permit = self.limiter.acquire("disguise warmup").await;
+        *received += self.cloudflare.download(&measurement_id, WARMUP_BYTES).await?;
+        Ok(())
+    }
+
+    /// The pulse loop; it ends on shutdown or `stop`, or by panicking
+    pub async fn run(&self) -> Result<()> {
+        let pulse = async {
             loop {
                 if !self.config.enabled { tokio::time::sleep(Duration::from_secs(10)).await; continue; }
-                let enabled = { let s = self.shared.read().await; matches!(s.optimization_mode, OptimizationMode::Enabled) };
-                if !enabled { tokio::time::sleep(Duration::from_secs(5)).await; continue; }
-                // Warm path using stealth server selection
-                let stealth_level = match self.repository.get_best_optimization_strategy().await {
-                    Ok(Some(s)) => s.stealth_level,
-                    _ => StealthLevel::Medium,
+                let (enabled, pinned) = { let s = self.shared.read().await; (s.may_generate() && s.modules.disguise, s.active_strategy_override.is_some()) };
+                if !enabled || self.limiter.is_halted() { tokio::time::sleep(Duration::from_secs(5)).await; continue; }
+                // Read every pulse, so a newly activated strategy takes effect without a restart
+                let strategy = app_state::active_strategy(&self.shared, &*self.repository).await.ok().flatten();
+                let stealth_level = strategy.as_ref().map_or(StealthLevel::Medium, |s| s.stealth_level.clone());
+                let profile = match (pinned, self.mimicry_profile) {
+                    (false, Some(profile)) => profile,
+                    _ => strategy.map(|s| s.mimicry_profile).unwrap_or_default(),
                 };
-                let _ = stealth_level; // reserved for future use
-                // For now this is a no-op; real routing hook would be here.
-                tokio::time::sleep(Duration::from_secs(10)).await;
+                if let Err(e) = self.warmup(profile, &stealth_level).await {
+                    debug!("Disguise warmup failed: {}", e);
+                }
+                tokio::time::sleep(PULSE_INTERVAL).await;
             }
-        });
+        };
+        tokio::select! {
+            _ = pulse => {}
+            _ = shutdown::cancelled(self.shutdown.as_ref()) => info!("Disguise pulse stopped"),
+            _ = self.stopped.cancelled() => info!("Disguise pulse stopped: disguise mode turned off"),
+        }
+        Ok(())
     }
 }
 
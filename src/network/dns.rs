@@ -0,0 +1,223 @@
+//! Real hostname lookups for DNS pattern replication. A speedtest session resolves the site, its
+//! CDN and the chosen server before any data flows, so the stealth engine performs the same
+//! lookups through the system resolver, DNS-over-HTTPS or DNS-over-TLS.
+
+use crate::core::config::{DnsResolverKind, StealthDnsConfig};
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::network::proxy;
+use rand::Rng;
+use reqwest::Client;
+use rustls::{ClientConfig, ServerName};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_rustls::TlsConnector;
+
+pub(crate) const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const DNS_MESSAGE: &str = "application/dns-message";
+
+fn malformed(what: &str) -> SpeedKarmaError {
+    SpeedKarmaError::NetworkUnavailable(format!("Malformed DNS response: {}", what))
+}
+
+/// Wire-format query for one name with recursion desired
+pub fn build_query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>> {
+    let mut query = Vec::with_capacity(18 + name.len());
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(SpeedKarmaError::NetworkUnavailable(format!("Invalid DNS name {}", name)));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&qtype.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Offset just past the name starting at `pos`; a compression pointer ends the name
+fn skip_name(message: &[u8], mut pos: usize) -> Result<usize> {
+    loop {
+        let len = *message.get(pos).ok_or_else(|| malformed("truncated name"))? as usize;
+        match len {
+            0 => return Ok(pos + 1),
+            l if l & 0xC0 == 0xC0 => return Ok(pos + 2),
+            l => pos += 1 + l,
+        }
+    }
+}
+
+fn read_u16(message: &[u8], pos: usize) -> Result<u16> {
+    message.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| malformed("truncated record"))
+}
+
+/// A and AAAA addresses in the answer section of a response to query `id`
+pub fn parse_response(id: u16, message: &[u8]) -> Result<Vec<IpAddr>> {
+    if message.len() < 12 || read_u16(message, 0)? != id {
+        return Err(malformed("id mismatch"));
+    }
+    let rcode = message[3] & 0x0F;
+    if rcode != 0 {
+        return Err(SpeedKarmaError::NetworkUnavailable(format!("DNS lookup failed with rcode {}", rcode)));
+    }
+    let questions = read_u16(message, 4)?;
+    let answers = read_u16(message, 6)?;
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(message, pos)? + 4;
+    }
+
+    let mut addresses = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(message, pos)?;
+        let rtype = read_u16(message, pos)?;
+        let len = read_u16(message, pos + 8)? as usize;
+        let data = message.get(pos + 10..pos + 10 + len).ok_or_else(|| malformed("truncated data"))?;
+        match (rtype, len) {
+            (TYPE_A, 4) => addresses.push(IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3]))),
+            (TYPE_AAAA, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(data);
+                addresses.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+        pos += 10 + len;
+    }
+    Ok(addresses)
+}
+
+/// DoT config: default rustls algorithms with ALPN `dot` (RFC 7858)
+fn dot_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    Arc::clone(CONFIG.get_or_init(|| {
+        let mut config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(super::tls::root_store())
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"dot".to_vec()];
+        Arc::new(config)
+    }))
+}
+
+/// Resolves names through the configured resolver, asking for A and AAAA like a browser
+pub struct StealthResolver {
+    config: StealthDnsConfig,
+    http: Client,
+}
+
+impl Default for StealthResolver {
+    fn default() -> Self {
+        Self::new(StealthDnsConfig::default())
+    }
+}
+
+impl StealthResolver {
+    pub fn new(config: StealthDnsConfig) -> Self {
+        let http = proxy::apply(Client::builder())
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .unwrap_or_default();
+        Self { config, http }
+    }
+
+    pub fn kind(&self) -> DnsResolverKind {
+        self.config.resolver
+    }
+
+    /// Resolves `name`; fails when the resolver errors or times out
+    pub async fn lookup(&self, name: &str) -> Result<Vec<IpAddr>> {
+        let timeout = Duration::from_secs(self.config.timeout_seconds);
+        let lookup = async {
+            match self.config.resolver {
+                DnsResolverKind::System => self.lookup_system(name).await,
+                DnsResolverKind::Doh => self.lookup_doh(name).await,
+                DnsResolverKind::Dot => self.lookup_dot(name).await,
+            }
+        };
+        tokio::time::timeout(timeout, lookup).await
+            .map_err(|_| SpeedKarmaError::NetworkUnavailable(format!("DNS lookup for {} timed out", name)))?
+    }
+
+    async fn lookup_system(&self, name: &str) -> Result<Vec<IpAddr>> {
+        let addrs = tokio::net::lookup_host((name, 443)).await
+            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("DNS lookup for {} failed: {}", name, e)))?;
+        Ok(addrs.map(|addr| addr.ip()).collect())
+    }
+
+    /// RFC 8484 POSTs with id 0, which keeps the answers cacheable
+    async fn lookup_doh(&self, name: &str) -> Result<Vec<IpAddr>> {
+        let mut addresses = Vec::new();
+        for qtype in [TYPE_A, TYPE_AAAA] {
+            let response = self.http.post(&self.config.doh_url)
+                .header("Content-Type", DNS_MESSAGE)
+                .header("Accept", DNS_MESSAGE)
+                .body(build_query(0, name, qtype)?)
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(SpeedKarmaError::NetworkUnavailable(format!("DoH lookup for {} returned {}", name, response.status())));
+            }
+            addresses.extend(parse_response(0, &response.bytes().await?)?);
+        }
+        Ok(addresses)
+    }
+
+    /// Both queries on one TLS connection, each framed with its two-byte length
+    async fn lookup_dot(&self, name: &str) -> Result<Vec<IpAddr>> {
+        let unavailable = |e: std::io::Error| SpeedKarmaError::NetworkUnavailable(format!("DoT lookup for {} failed: {}", name, e));
+        let server: SocketAddr = self.config.dot_server.parse()
+            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Invalid DoT server {}: {}", self.config.dot_server, e)))?;
+        let server_name = ServerName::try_from(self.config.dot_name.as_str())
+            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Invalid DoT name {}: {}", self.config.dot_name, e)))?;
+        let tcp = proxy::connect(&server.ip().to_string(), server.port()).await?;
+        let mut stream = TlsConnector::from(dot_config()).connect(server_name, tcp).await.map_err(unavailable)?;
+
+        let mut addresses = Vec::new();
+        for qtype in [TYPE_A, TYPE_AAAA] {
+            let id: u16 = rand::thread_rng().gen();
+            let query = build_query(id, name, qtype)?;
+            stream.write_all(&(query.len() as u16).to_be_bytes()).await.map_err(unavailable)?;
+            stream.write_all(&query).await.map_err(unavailable)?;
+            let len = stream.read_u16().await.map_err(unavailable)? as usize;
+            let mut response = vec![0u8; len];
+            stream.read_exact(&mut response).await.map_err(unavailable)?;
+            addresses.extend(parse_response(id, &response)?);
+        }
+        Ok(addresses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_and_response_round_trip() {
+        let query = build_query(0x1234, "www.speedtest.net", TYPE_A).unwrap();
+        assert_eq!(&query[..4], &[0x12, 0x34, 0x01, 0x00]);
+        assert_eq!(&query[12..16], &[3, b'w', b'w', b'w']);
+        assert!(build_query(1, "bad..name", TYPE_A).is_err());
+
+        // Response: header, the question, then a CNAME and an A record using compression pointers
+        let mut response = query.clone();
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 2;
+        response.extend_from_slice(&[0xC0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xC0, 16]);
+        response.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 151, 101, 2, 219]);
+        assert_eq!(parse_response(0x1234, &response).unwrap(), vec![IpAddr::V4(Ipv4Addr::new(151, 101, 2, 219))]);
+        assert!(parse_response(0x4321, &response).is_err());
+
+        response[3] = 0x83;
+        assert!(parse_response(0x1234, &response).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! fast.com (Netflix) test flow: the page's app script carries an API token, api.fast.com hands
+//! out Open Connect cache (OCA) URLs for it, and the test downloads byte ranges from them over HTTPS.
+
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::network::proxy;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, ORIGIN, REFERER, USER_AGENT};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::debug;
+
+pub const FAST_COM_URL: &str = "https://fast.com/";
+const API_URL: &str = "https://api.fast.com/netflix/speedtest/v2";
+/// The token changes with fast.com releases; refetched after this long or when the API refuses it
+const TOKEN_TTL: Duration = Duration::from_secs(6 * 3600);
+const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FastLocation {
+    pub city: String,
+    pub country: String,
+}
+
+/// One Open Connect cache handed out by api.fast.com
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FastTarget {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub location: Option<FastLocation>,
+}
+
+#[derive(Deserialize)]
+struct TargetsResponse {
+    targets: Vec<FastTarget>,
+}
+
+/// Path of the app bundle in the fast.com page, e.g. `/app-7b1b5b.js`
+pub fn find_script_path(html: &str) -> Option<String> {
+    html.match_indices("/app-").find_map(|(start, _)| {
+        let rest = &html[start..];
+        let end = rest.find(".js")?;
+        let hash = &rest["/app-".len()..end];
+        (!hash.is_empty() && hash.chars().all(|c| c.is_ascii_alphanumeric())).then(|| format!("{}.js", &rest[..end]))
+    })
+}
+
+/// API token embedded in the app bundle as `token:"..."`
+pub fn find_token(script: &str) -> Option<String> {
+    let start = script.find("token:\"")? + "token:\"".len();
+    let token = &script[start..start + script[start..].find('"')?];
+    (!token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric())).then(|| token.to_string())
+}
+
+/// Targets from an api.fast.com response body
+pub fn parse_targets(body: &str) -> Result<Vec<FastTarget>> {
+    Ok(serde_json::from_str::<TargetsResponse>(body)?.targets)
+}
+
+/// `url` with fast.com's `/range/<start>-<end>` inserted before the query
+pub fn range_url(url: &str, start: u64, end: u64) -> String {
+    match url.find('?') {
+        Some(query) => format!("{}/range/{}-{}{}", &url[..query], start, end, &url[query..]),
+        None => format!("{}/range/{}-{}", url, start, end),
+    }
+}
+
+/// Talks to fast.com the way its web page does; the token is cached between cycles
+pub struct FastComClient {
+    client: Client,
+    token: RwLock<Option<(String, Instant)>>,
+}
+
+impl Default for FastComClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FastComClient {
+    pub fn new() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static(BROWSER_USER_AGENT));
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+        headers.insert(ORIGIN, HeaderValue::from_static("https://fast.com"));
+        headers.insert(REFERER, HeaderValue::from_static(FAST_COM_URL));
+        let client = proxy::apply(Client::builder())
+            .timeout(Duration::from_secs(30))
+            .default_headers(headers)
+            .build()
+            .unwrap_or_default();
+        Self { client, token: RwLock::new(None) }
+    }
+
+    async fn fetch_text(&self, url: &str) -> Result<String> {
+        let response = self.client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(SpeedKarmaError::NetworkUnavailable(format!("{} returned {}", url, response.status())));
+        }
+        Ok(response.text().await?)
+    }
+
+    /// Cached token, or a fresh one read from the page's app script
+    async fn token(&self) -> Result<String> {
+        if let Some((token, fetched)) = self.token.read().await.as_ref() {
+            if fetched.elapsed() < TOKEN_TTL {
+                return Ok(token.clone());
+            }
+        }
+        let page = self.fetch_text(FAST_COM_URL).await?;
+        let script_path = find_script_path(&page).ok_or_else(|| SpeedKarmaError::NetworkUnavailable("fast.com page has no app script".to_string()))?;
+        let script = self.fetch_text(&format!("https://fast.com{}", script_path)).await?;
+        let token = find_token(&script).ok_or_else(|| SpeedKarmaError::NetworkUnavailable("fast.com app script has no token".to_string()))?;
+        debug!("Fetched fast.com token from {}", script_path);
+        *self.token.write().await = Some((token.clone(), Instant::now()));
+        Ok(token)
+    }
+
+    /// Asks api.fast.com for `url_count` caches, as the page does before measuring
+    pub async fn targets(&self, url_count: u8) -> Result<Vec<FastTarget>> {
+        let token = self.token().await?;
+        let url = format!("{}?https=true&token={}&urlCount={}", API_URL, token, url_count);
+        let response = self.client.get(&url).send().await?;
+        if matches!(response.status(), StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED) {
+            // Token rotated with a new release of the page
+            *self.token.write().await = None;
+        }
+        if !response.status().is_success() {
+            return Err(SpeedKarmaError::NetworkUnavailable(format!("api.fast.com returned {}", response.status())));
+        }
+        parse_targets(&response.text().await?)
+    }
+
+    /// Downloads bytes `start..=end` of a cache's test file; returns the bytes received
+    pub async fn fetch_range(&self, target: &FastTarget, start: u64, end: u64) -> Result<u64> {
+        let response = self.client.get(range_url(&target.url, start, end)).send().await?;
+        if !response.status().is_success() {
+            return Err(SpeedKarmaError::NetworkUnavailable(format!("{} returned {}", target.name, response.status())));
+        }
+        Ok(response.bytes().await?.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_targets_and_range_urls_follow_the_fast_com_page() {
+        let html = r#"<link rel="stylesheet" href="/styles-1a2b.css"><script src="/app-7b1b5b.js"></script>"#;
+        assert_eq!(find_script_path(html).as_deref(), Some("/app-7b1b5b.js"));
+        assert_eq!(find_script_path("<script src=\"/app-.js\"></script>"), None);
+
+        let script = r#"var e={https:!0,token:"YXNkZmFzZGxmbnNkYWZoYXNkZmhrYWxm",urlCount:5}"#;
+        assert_eq!(find_token(script).as_deref(), Some("YXNkZmFzZGxmbnNkYWZoYXNkZmhrYWxm"));
+        assert_eq!(find_token("token:\"\""), None);
+
+        let body = r#"{"client":{"ip":"203.0.113.7"},"targets":[{"name":"https://ipv4-c001-cmb001-ix.1.oca.nflxvideo.net/speedtest?c=lk&n=9506&v=5&e=1700000000&t=abc","url":"https://ipv4-c001-cmb001-ix.1.oca.nflxvideo.net/speedtest?c=lk&n=9506&v=5&e=1700000000&t=abc","location":{"city":"Colombo","country":"LK"}}]}"#;
+        let targets = parse_targets(body).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].location.as_ref().map(|l| l.city.as_str()), Some("Colombo"));
+
+        assert_eq!(
+            range_url(&targets[0].url, 0, 2048),
+            "https://ipv4-c001-cmb001-ix.1.oca.nflxvideo.net/speedtest/range/0-2048?c=lk&n=9506&v=5&e=1700000000&t=abc"
+        );
+        assert_eq!(range_url("https://oca.example/speedtest", 0, 1), "https://oca.example/speedtest/range/0-1");
+    }
+}
@@ -0,0 +1,318 @@
+use crate::core::config::{IpLookupProvider, IspLookupConfig};
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::network::asn_db::known_isp_name;
+use crate::network::context;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// HTTPS needs the Pro endpoint; the free `ip-api.com` host only answers plain HTTP
+const IP_API_URL: &str = "https://pro.ip-api.com/json/?fields=status,message,country,countryCode,lat,lon,isp,org,as,query";
+const IPINFO_URL: &str = "https://ipinfo.io/json";
+
+/// What a public-IP service reports about this connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublicIpInfo {
+    pub ip: String,
+    pub asn: Option<u32>,
+    /// Organisation or AS name as the service reports it
+    pub organization: String,
+    /// Friendly ISP name
+    pub isp_name: String,
+    /// Country name, or the ISO code when the service only gives that
+    pub country: String,
+    /// Upper-case ISO 3166 code
+    pub country_code: Option<String>,
+    /// Approximate location of the IP, city level at best
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    pub provider: IpLookupProvider,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl PublicIpInfo {
+    /// `(latitude, longitude)` when the service geolocated the IP
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
+    }
+
+    /// ISO country code when known, so every provider reports the same region as the ASN database
+    pub fn region(&self) -> String {
+        self.country_code.clone().unwrap_or_else(|| self.country.clone())
+    }
+}
+
+/// Upper-case ISO code, `None` for anything that is not two letters
+fn normalize_country_code(code: &str) -> Option<String> {
+    let code = code.trim();
+    (code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic())).then(|| code.to_ascii_uppercase())
+}
+
+/// Splits an `AS9506 Hutchison Telecommunications Lanka` field into number and name
+pub fn parse_as_field(field: &str) -> (Option<u32>, String) {
+    let field = field.trim();
+    match field.split_once(' ') {
+        Some((asn, name)) if asn.starts_with("AS") => (asn[2..].parse().ok(), name.trim().to_string()),
+        _ => (field.strip_prefix("AS").and_then(|n| n.parse().ok()), field.to_string()),
+    }
+}
+
+/// Known operator name for the AS, else the ISP or organisation as reported
+fn friendly_isp_name(asn: Option<u32>, isp: &str, organization: &str) -> String {
+    asn.and_then(known_isp_name)
+        .map(str::to_string)
+        .unwrap_or_else(|| if isp.is_empty() { organization.to_string() } else { isp.to_string() })
+}
+
+/// `ip-api.com/json` response
+pub fn parse_ip_api(json: &Value) -> Option<PublicIpInfo> {
+    let field = |name: &str| json.get(name).and_then(Value::as_str).unwrap_or("").to_string();
+    if field("status") != "success" { return None; }
+    let (asn, as_name) = parse_as_field(&field("as"));
+    let organization = [field("org"), as_name].into_iter().find(|s| !s.is_empty()).unwrap_or_default();
+    let isp_name = friendly_isp_name(asn, &field("isp"), &organization);
+    if isp_name.is_empty() { return None; }
+    let country_code = normalize_country_code(&field("countryCode"));
+    Some(PublicIpInfo {
+        ip: field("query"),
+        asn,
+        organization,
+        isp_name,
+        country: field("country"),
+        country_code,
+        latitude: json.get("lat").and_then(Value::as_f64),
+        longitude: json.get("lon").and_then(Value::as_f64),
+        provider: IpLookupProvider::IpApi,
+        fetched_at: Utc::now(),
+    })
+}
+
+/// `ipinfo.io/json` response; `org` carries the AS number and name, `country` the ISO code
+pub fn parse_ipinfo(json: &Value) -> Option<PublicIpInfo> {
+    let field = |name: &str| json.get(name).and_then(Value::as_str).unwrap_or("").to_string();
+    let (asn, organization) = parse_as_field(&field("org"));
+    if organization.is_empty() { return None; }
+    let country_code = normalize_country_code(&field("country"));
+    let country = country_code.clone().unwrap_or_else(|| field("country"));
+    let loc = field("loc");
+    let (latitude, longitude) = match loc.split_once(',') {
+        Some((lat, lon)) => (lat.trim().parse().ok(), lon.trim().parse().ok()),
+        None => (None, None),
+    };
+    Some(PublicIpInfo {
+        ip: field("ip"),
+        asn,
+        isp_name: friendly_isp_name(asn, "", &organization),
+        organization,
+        country,
+        country_code,
+        latitude,
+        longitude,
+        provider: IpLookupProvider::IpInfo,
+        fetched_at: Utc::now(),
+    })
+}
+
+/// Cached results keyed by the network they were looked up on
+type CacheFile = HashMap<String, PublicIpInfo>;
+
+/// Public-IP ISP lookup over a fallback chain of services, with results cached on disk per network.
+/// Clones share one lookup at a time, so the ISP detection and server ranking at startup query once.
+#[derive(Debug, Clone)]
+pub struct PublicIpLookup {
+    config: IspLookupConfig,
+    cache_path: Option<PathBuf>,
+    limiter: OutboundLimiter,
+    in_flight: Arc<Mutex<()>>,
+}
+
+impl Default for PublicIpLookup {
+    fn default() -> Self { Self::new(IspLookupConfig::default()) }
+}
+
+impl PublicIpLookup {
+    pub fn new(config: IspLookupConfig) -> Self {
+        Self { config, cache_path: Self::default_cache_path(), limiter: OutboundLimiter::default(), in_flight: Arc::new(Mutex::new(())) }
+    }
+
+    /// Counts each provider query against the app-wide outbound limit
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    pub fn with_cache_path(mut self, path: Option<PathBuf>) -> Self {
+        self.cache_path = path;
+        self
+    }
+
+    /// Default on-disk location of the cached result
+    pub fn default_cache_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|d| d.join("SpeedKarma").join("public-ip.json"))
+    }
+
+    fn ttl(&self) -> Duration {
+        Duration::hours(self.config.cache_ttl_hours as i64)
+    }
+
+    /// Key of the network the machine is on; unidentified networks share the empty key
+    async fn network_key() -> String {
+        context::current().await.key().unwrap_or_default()
+    }
+
+    async fn read_cache(&self) -> CacheFile {
+        let Some(path) = &self.cache_path else { return CacheFile::new() };
+        // A cache written before results were keyed by network does not parse and is dropped
+        match tokio::fs::read_to_string(path).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => CacheFile::new(),
+        }
+    }
+
+    /// Cached result for the current network younger than the TTL
+    pub async fn cached(&self) -> Option<PublicIpInfo> {
+        self.cached_on(&Self::network_key().await).await
+    }
+
+    async fn cached_on(&self, network: &str) -> Option<PublicIpInfo> {
+        let info = self.read_cache().await.remove(network)?;
+        (Utc::now() - info.fetched_at < self.ttl()).then_some(info)
+    }
+
+    /// Stores `info` as the cached result for the current network
+    pub async fn store(&self, info: &PublicIpInfo) -> Result<()> {
+        self.store_on(&Self::network_key().await, info).await
+    }
+
+    async fn store_on(&self, network: &str, info: &PublicIpInfo) -> Result<()> {
+        let Some(path) = &self.cache_path else { return Ok(()) };
+        let ttl = self.ttl();
+        let mut cache = self.read_cache().await;
+        cache.retain(|_, cached| Utc::now() - cached.fetched_at < ttl);
+        cache.insert(network.to_string(), info.clone());
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_string_pretty(&cache)?).await?;
+        Ok(())
+    }
+
+    async fn query(&self, client: &reqwest::Client, provider: IpLookupProvider) -> Result<PublicIpInfo> {
+        let request = match provider {
+            IpLookupProvider::IpApi => match &self.config.ip_api_key {
+                Some(key) => client.get(IP_API_URL).query(&[("key", key)]),
+                None => return Err(SpeedKarmaError::ConfigurationError("ip-api.com needs a Pro key for HTTPS".to_string())),
+            },
+            IpLookupProvider::IpInfo => match &self.config.ipinfo_token {
+                Some(token) => client.get(IPINFO_URL).bearer_auth(token),
+                None => client.get(IPINFO_URL),
+            },
+        };
+        let _permit = self.limiter.acquire("public IP lookup").await;
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(SpeedKarmaError::NetworkUnavailable(format!("{:?} lookup returned status: {}", provider, response.status())));
+        }
+        let json: Value = response.json().await?;
+        let parsed = match provider {
+            IpLookupProvider::IpApi => parse_ip_api(&json),
+            IpLookupProvider::IpInfo => parse_ipinfo(&json),
+        };
+        parsed.ok_or_else(|| SpeedKarmaError::NetworkUnavailable(format!("{:?} lookup gave no ISP", provider)))
+    }
+
+    /// Cached result when fresh, otherwise the first provider that answers. Always direct: through
+    /// a proxy the services would report the proxy exit's ISP and location instead of the user's
+    pub async fn lookup(&self) -> Result<PublicIpInfo> {
+        // A caller waiting here finds the result the one ahead of it stored
+        let _guard = self.in_flight.lock().await;
+        if let Some(info) = self.cached().await {
+            debug!("Using cached public IP lookup from {:?}", info.provider);
+            return Ok(info);
+        }
+        let client = proxy::direct(reqwest::Client::builder())
+            .timeout(StdDuration::from_secs(self.config.timeout_seconds.max(1)))
+            .build()?;
+        let mut last_error = SpeedKarmaError::NetworkUnavailable("no public IP lookup provider configured".to_string());
+        for provider in &self.config.providers {
+            match self.query(&client, *provider).await {
+                Ok(info) => {
+                    if let Err(e) = self.store(&info).await {
+                        warn!("Could not cache public IP lookup: {}", e);
+                    }
+                    return Ok(info);
+                }
+                Err(e) => {
+                    debug!("{:?} lookup failed: {}", provider, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_responses() {
+        let ip_api = serde_json::json!({
+            "status": "success", "country": "Sri Lanka", "countryCode": "LK",
+            "isp": "Hutchison Telecommunications Lanka", "org": "", "as": "AS9506 Hutchison Telecommunications Lanka (Pvt) Ltd",
+            "lat": 6.9271, "lon": 79.8612, "query": "203.0.113.9"
+        });
+        let info = parse_ip_api(&ip_api).unwrap();
+        assert_eq!(info.coordinates(), Some((6.9271, 79.8612)));
+        assert_eq!((info.asn, info.isp_name.as_str(), info.country.as_str()), (Some(9506), "Hutch", "Sri Lanka"));
+        assert_eq!(info.region(), "LK");
+        assert_eq!(info.organization, "Hutchison Telecommunications Lanka (Pvt) Ltd");
+        assert!(parse_ip_api(&serde_json::json!({ "status": "fail", "message": "reserved range" })).is_none());
+
+        let ipinfo = serde_json::json!({ "ip": "198.51.100.4", "country": "in", "loc": "19.0728,72.8826", "org": "AS55836 Reliance Jio Infocomm Limited" });
+        let info = parse_ipinfo(&ipinfo).unwrap();
+        assert_eq!(info.coordinates(), Some((19.0728, 72.8826)));
+        assert_eq!((info.asn, info.isp_name.as_str(), info.country_code.as_deref()), (Some(55836), "Reliance Jio Infocomm Limited", Some("IN")));
+        assert_eq!((info.country.as_str(), info.region()), ("IN", "IN".to_string()));
+        assert_eq!(parse_as_field("Example Net"), (None, "Example Net".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_cache_respects_ttl() {
+        let path = std::env::temp_dir().join(format!("speedkarma-public-ip-{}.json", uuid::Uuid::new_v4()));
+        let lookup = PublicIpLookup::default().with_cache_path(Some(path.clone()));
+        let mut info = parse_ipinfo(&serde_json::json!({ "ip": "198.51.100.4", "country": "LK", "org": "AS18001 Dialog Axiata PLC." })).unwrap();
+        lookup.store(&info).await.unwrap();
+        assert_eq!(lookup.lookup().await.unwrap().isp_name, "Dialog");
+
+        info.fetched_at = Utc::now() - Duration::hours(25);
+        lookup.store(&info).await.unwrap();
+        assert!(lookup.cached().await.is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_kept_per_network() {
+        let path = std::env::temp_dir().join(format!("speedkarma-public-ip-{}.json", uuid::Uuid::new_v4()));
+        let lookup = PublicIpLookup::default().with_cache_path(Some(path.clone()));
+        let home = parse_ipinfo(&serde_json::json!({ "ip": "198.51.100.4", "country": "LK", "org": "AS18001 Dialog Axiata PLC." })).unwrap();
+        let office = parse_ipinfo(&serde_json::json!({ "ip": "203.0.113.9", "country": "LK", "org": "AS9506 Hutchison Telecommunications Lanka" })).unwrap();
+        lookup.store_on("home", &home).await.unwrap();
+        assert!(lookup.cached_on("office").await.is_none(), "another network's ISP is not reused");
+        lookup.store_on("office", &office).await.unwrap();
+        assert_eq!(lookup.cached_on("home").await.unwrap().isp_name, "Dialog");
+        assert_eq!(lookup.cached_on("office").await.unwrap().isp_name, "Hutch");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,188 @@
+//! Speed tests through CDNs and speedtest.net hosts can be flattered by caches and zero-rating,
+//! or dragged down by a busy shared server. A user with a VPS can run `iperf3 -s` there and get a
+//! clean baseline: plain TCP between two endpoints they control. The client binary is spawned
+//! with `--json` and its report becomes an active measurement tagged `iperf3`.
+
+use crate::core::app_state::{OptimizationMode, SharedAppState};
+use crate::core::command::command;
+use crate::core::config::Iperf3Config;
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::data::models::{MeasurementMethod, MeasurementSource, SpeedMeasurement, TrafficSource};
+use crate::data::repository::Repository;
+use crate::network::context;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::usage::DataUsageMeter;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// Time allowed beyond the test length for connecting and the final exchange of results
+const SETUP_ALLOWANCE: Duration = Duration::from_secs(20);
+/// No cache, directory server or shared host sits between the two ends
+const IPERF3_CONFIDENCE: f64 = 1.0;
+
+/// Totals of one `iperf3 --json` run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Iperf3Report {
+    /// Rate the receiving end saw
+    pub mbps: f64,
+    pub bytes: u64,
+    /// Mean TCP round-trip time over the sending streams, when the sender is local and reports it
+    pub rtt_ms: Option<f64>,
+}
+
+/// Reads the totals from an `iperf3 --json` report; a report carrying `error` fails
+pub fn parse_report(json: &Value) -> Result<Iperf3Report> {
+    if let Some(error) = json.get("error").and_then(Value::as_str) {
+        return Err(SpeedKarmaError::NetworkUnavailable(format!("iperf3: {}", error)));
+    }
+    let end = &json["end"];
+    // TCP runs report what the receiver got; UDP runs only have `sum`
+    let sum = [&end["sum_received"], &end["sum"]].into_iter()
+        .find(|sum| sum.get("bits_per_second").is_some())
+        .ok_or_else(|| SpeedKarmaError::NetworkUnavailable("iperf3 report has no totals".into()))?;
+    let rtts: Vec<f64> = end["streams"].as_array().into_iter().flatten()
+        .filter_map(|stream| stream["sender"]["mean_rtt"].as_f64())
+        .collect();
+    Ok(Iperf3Report {
+        mbps: sum["bits_per_second"].as_f64().unwrap_or(0.0) / 1_000_000.0,
+        bytes: sum["bytes"].as_u64().unwrap_or(0),
+        // iperf3 reports RTT in microseconds
+        rtt_ms: (!rtts.is_empty()).then(|| rtts.iter().sum::<f64>() / rtts.len() as f64 / 1000.0),
+    })
+}
+
+/// Runs the iperf3 client against the configured server and stores the result
+pub struct Iperf3Runner {
+    repository: Arc<Repository>,
+    shared: SharedAppState,
+    config: Iperf3Config,
+    usage: Option<DataUsageMeter>,
+    limiter: Option<OutboundLimiter>,
+}
+
+impl Iperf3Runner {
+    pub fn new(repository: Arc<Repository>, shared: SharedAppState, config: Iperf3Config) -> Self {
+        Self { repository, shared, config, usage: None, limiter: None }
+    }
+
+    /// Kills the client when the emergency stop is engaged mid-run
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Counts the bytes the test moves against the monthly data cap
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Client arguments for one direction; `reverse` has the server send, measuring download
+    fn args(&self, server: &str, reverse: bool) -> Vec<String> {
+        let mut args = vec![
+            "-c".to_string(), server.to_string(),
+            "-p".to_string(), self.config.port.to_string(),
+            "-t".to_string(), self.config.duration_s.to_string(),
+            "-P".to_string(), self.config.parallel_streams.max(1).to_string(),
+            "--json".to_string(),
+        ];
+        if reverse {
+            args.push("-R".to_string());
+        }
+        args
+    }
+
+    async fn run_direction(&self, server: &str, reverse: bool) -> Result<Iperf3Report> {
+        let mut command = command(&self.config.binary);
+        command.args(self.args(server, reverse));
+        let limit = Duration::from_secs(self.config.duration_s as u64) + SETUP_ALLOWANCE;
+        let output = tokio::time::timeout(limit, command.output()).await
+            .map_err(|_| SpeedKarmaError::NetworkUnavailable(format!("iperf3 did not finish within {} s", limit.as_secs())))?
+            .map_err(|e| SpeedKarmaError::SystemError(format!("could not run {}: {}", self.config.binary, e)))?;
+        // iperf3 exits non-zero on failure but still prints a JSON report with the reason
+        let json: Value = serde_json::from_slice(&output.stdout).map_err(|_| {
+            SpeedKarmaError::NetworkUnavailable(format!("iperf3 failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+        })?;
+        let report = parse_report(&json)?;
+        if let Some(usage) = &self.usage {
+            usage.record(TrafficSource::Speedtest, report.bytes).await;
+        }
+        Ok(report)
+    }
+
+    /// Download, then upload when configured; returns the stored measurement
+    pub async fn run_once(&self) -> Result<SpeedMeasurement> {
+        if !self.config.enabled {
+            return Err(SpeedKarmaError::ConfigurationError("iperf3 baselines are turned off".into()));
+        }
+        let server = self.config.server.clone().filter(|s| !s.trim().is_empty())
+            .ok_or_else(|| SpeedKarmaError::ConfigurationError("no iperf3 server configured".into()))?;
+        let (allowed, in_call, optimizing) = {
+            let s = self.shared.read().await;
+            (s.may_generate() && s.modules.active_testing, s.call_active, s.optimization_mode == OptimizationMode::Enabled)
+        };
+        if !allowed {
+            return Err(SpeedKarmaError::SystemError("active testing is paused".into()));
+        }
+        if in_call {
+            return Err(SpeedKarmaError::SystemError("a call is in progress".into()));
+        }
+
+        // Dropping the run kills the client, which closes its connections
+        let cancel = self.limiter.as_ref().map(OutboundLimiter::traffic_token).unwrap_or_default();
+        let run = async {
+            let download = self.run_direction(&server, true).await?;
+            let upload = if self.config.upload { Some(self.run_direction(&server, false).await?) } else { None };
+            Ok::<_, SpeedKarmaError>((download, upload))
+        };
+        let (download, upload) = tokio::select! {
+            _ = cancel.cancelled() => return Err(SpeedKarmaError::SystemError("stopped by the emergency stop".into())),
+            out = run => out?,
+        };
+        // Only a local sender reports RTT, so the upload run is the one that has it
+        let rtt_ms = upload.and_then(|u| u.rtt_ms).or(download.rtt_ms);
+        let measurement = SpeedMeasurement {
+            confidence: IPERF3_CONFIDENCE,
+            network_context: context::current().await.key(),
+            // A download-only run is marked so its placeholder upload stays out of averages
+            upload_measured: upload.is_some(),
+            latency_ms: rtt_ms.map(|ms| ms.round() as u32),
+            ..SpeedMeasurement::new(download.mbps, upload.map_or(0.0, |u| u.mbps), 0, optimizing)
+                .with_source(MeasurementSource::Active)
+                .with_method(MeasurementMethod::Iperf3)
+        };
+        let id = self.repository.save_speed_measurement(&measurement).await?;
+        match upload {
+            Some(_) => info!("iperf3 baseline against {}: {:.1} Mbps down, {:.1} Mbps up", server, measurement.download_mbps, measurement.upload_mbps),
+            None => info!("iperf3 baseline against {}: {:.1} Mbps down", server, measurement.download_mbps),
+        }
+        Ok(SpeedMeasurement { id: Some(id), ..measurement })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_give_receiver_rate_and_sender_rtt() {
+        let upload = serde_json::json!({
+            "start": { "connected": [{ "socket": 5 }] },
+            "end": {
+                "streams": [
+                    { "sender": { "bytes": 60_000_000u64, "mean_rtt": 14_000 } },
+                    { "sender": { "bytes": 60_000_000u64, "mean_rtt": 16_000 } }
+                ],
+                "sum_sent": { "bytes": 120_000_000u64, "bits_per_second": 97_000_000.0 },
+                "sum_received": { "bytes": 118_750_000u64, "bits_per_second": 95_000_000.0 }
+            }
+        });
+        assert_eq!(parse_report(&upload).unwrap(), Iperf3Report { mbps: 95.0, bytes: 118_750_000, rtt_ms: Some(15.0) });
+
+        let refused = serde_json::json!({ "start": {}, "end": {}, "error": "unable to connect to server: Connection refused" });
+        assert!(parse_report(&refused).unwrap_err().to_string().contains("Connection refused"));
+        assert!(parse_report(&serde_json::json!({ "end": {} })).is_err());
+    }
+}
@@ -1,15 +1,24 @@
-use crate::core::app_state::{OptimizationMode, SharedAppState};
-use crate::core::config::ThroughputKeeperConfig;
-use crate::core::error::Result;
+use crate::core::app_state::{self, SharedAppState};
+use crate::core::config::{KeeperDownloadConfig, RampUpProfile, ThroughputKeeperConfig};
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::core::events::SharedEventSink;
+use crate::core::scheduler::PeriodicScheduler;
+use crate::core::shutdown::{self, CancellationToken};
+use crate::core::supervisor::Supervisor;
 use crate::data::repository::Repository;
-use crate::data::models::StealthLevel;
-use chrono::{DateTime, Utc, Duration as ChronoDuration, Timelike};
+use crate::data::models::{ServerEndpoint, SpeedtestServer, StealthLevel, TrafficSource};
+use crate::network::connections::{ConnectionOwner, ConnectionTable};
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use crate::network::usage::DataUsageMeter;
+use crate::network::tls::TlsFingerprint;
+use chrono::{DateTime, Utc, Duration as ChronoDuration};
 use rand::Rng;
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT, ACCEPT, ACCEPT_LANGUAGE, ACCEPT_ENCODING, CACHE_CONTROL, CONNECTION, RANGE, PRAGMA};
 use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tauri::{AppHandle, Manager};
 use tokio::sync::RwLock;
 use tokio::time::sleep;
 use tracing::{info, warn, debug, error};
@@ -32,45 +41,208 @@ pub struct KeeperProgressPayload {
     pub cadence: String,
 }
 
+struct RunningFlag(Arc<AtomicBool>);
+
+impl Drop for RunningFlag {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// How often the engine checks whether the ramp-up calls for another stream
+const RAMP_TICK: Duration = Duration::from_millis(250);
+
+/// Longest a single keeper burst may take
+const BURST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Streams that should be open `elapsed` into a load
+pub fn streams_at(cfg: &KeeperDownloadConfig, elapsed: Duration) -> usize {
+    let streams = cfg.streams.max(1) as usize;
+    let progress = match cfg.ramp_up_s {
+        0 => 1.0,
+        ramp => (elapsed.as_secs_f64() / ramp as f64).min(1.0),
+    };
+    if progress >= 1.0 {
+        return streams;
+    }
+    match cfg.ramp_up {
+        RampUpProfile::Immediate => streams,
+        RampUpProfile::Linear => 1 + ((streams - 1) as f64 * progress) as usize,
+        RampUpProfile::Exponential => {
+            let doublings = (streams as f64).log2().ceil() * progress;
+            (1usize << doublings as u32).min(streams)
+        }
+    }
+}
+
+/// How long a stream waits so the bytes so far do not exceed `target_mbps`; zero without a target
+pub fn pacing_delay(target_mbps: Option<f64>, received: u64, elapsed: Duration) -> Duration {
+    let Some(target) = target_mbps.filter(|mbps| *mbps > 0.0) else { return Duration::ZERO };
+    let due = Duration::from_secs_f64(received as f64 * 8.0 / (target * 1_000_000.0));
+    due.saturating_sub(elapsed)
+}
+
+/// Parallel range GETs against one server, opened along a ramp-up profile, paced to a target rate
+/// and stopped at a byte cap so the keeper's budgets hold
+struct DownloadEngine {
+    config: KeeperDownloadConfig,
+    client: reqwest::Client,
+    server: SpeedtestServer,
+    secure: bool,
+    limiter: OutboundLimiter,
+}
+
+impl DownloadEngine {
+    /// Bytes received before `duration` ran out or `cap_bytes` was reached
+    async fn run(&self, duration: Duration, cap_bytes: u64) -> u64 {
+        let started = Instant::now();
+        let deadline = started + duration;
+        let received = Arc::new(AtomicU64::new(0));
+        let mut streams = tokio::task::JoinSet::new();
+        while Instant::now() < deadline && received.load(Ordering::Relaxed) < cap_bytes {
+            while streams.len() < streams_at(&self.config, started.elapsed()) {
+                streams.spawn(self.stream(streams.len(), started, deadline, cap_bytes, Arc::clone(&received)));
+            }
+            sleep(RAMP_TICK.min(deadline.saturating_duration_since(Instant::now()))).await;
+        }
+        // Streams stop on their own at the deadline; the grace covers a chunk still arriving
+        let _ = tokio::time::timeout(Duration::from_secs(2), async { while streams.join_next().await.is_some() {} }).await;
+        streams.abort_all();
+        received.load(Ordering::Relaxed)
+    }
+
+    fn stream(&self, index: usize, started: Instant, deadline: Instant, cap_bytes: u64, received: Arc<AtomicU64>) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let (client, server, secure, limiter) = (self.client.clone(), self.server.clone(), self.secure, self.limiter.clone());
+        let (mut sizes, target_mbps) = (self.config.chunk_sizes_kb.clone(), self.config.target_mbps);
+        sizes.retain(|kb| *kb > 0);
+        sizes.sort_unstable();
+        if sizes.is_empty() {
+            sizes.push(1024);
+        }
+        async move {
+            // Streams start at different sizes so their requests do not line up
+            let mut chunk = index;
+            while Instant::now() < deadline && received.load(Ordering::Relaxed) < cap_bytes {
+                // A slot per request, so other modules get a turn between a stream's chunks
+                let _permit = limiter.acquire("keeper load").await;
+                let size_bytes = sizes[chunk % sizes.len()] as u64 * 1024;
+                chunk += 1;
+                let nonce = rand::thread_rng().gen::<u32>();
+                let url = server.endpoint_url(secure, ServerEndpoint::Download, size_bytes, &nonce.to_string());
+                let mut headers = ThroughputKeeper::build_headers();
+                if let Ok(range) = HeaderValue::from_str(&format!("bytes=0-{}", size_bytes - 1)) {
+                    headers.insert(RANGE, range);
+                }
+                let mut response = match client.get(&url).headers(headers).send().await {
+                    Ok(response) if response.status().is_success() => response,
+                    Ok(response) => {
+                        debug!("Keeper download stream {} got HTTP {}", index, response.status());
+                        sleep(Duration::from_millis(500)).await;
+                        continue;
+                    }
+                    Err(e) => {
+                        debug!("Keeper download stream {} failed: {}", index, e);
+                        sleep(Duration::from_millis(500)).await;
+                        continue;
+                    }
+                };
+                while Instant::now() < deadline {
+                    match response.chunk().await {
+                        Ok(Some(bytes)) => {
+                            let total = received.fetch_add(bytes.len() as u64, Ordering::Relaxed) + bytes.len() as u64;
+                            if total >= cap_bytes {
+                                return;
+                            }
+                            let wait = pacing_delay(target_mbps, total, started.elapsed());
+                            if !wait.is_zero() {
+                                sleep(wait.min(deadline.saturating_duration_since(Instant::now()))).await;
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub struct ThroughputKeeper {
     repository: Arc<Repository>,
     shared_state: SharedAppState,
-    app_handle: AppHandle,
+    events: SharedEventSink,
     config: Arc<RwLock<ThroughputKeeperConfig>>,
-    is_running: Arc<RwLock<bool>>,
+    is_running: Arc<AtomicBool>,
     hourly_budget_used_mb: Arc<RwLock<f64>>, // resets every hour
     last_reset: Arc<RwLock<DateTime<Utc>>>,
+    daily_budget_used_mb: Arc<RwLock<f64>>, // resets every day
+    last_daily_reset: Arc<RwLock<DateTime<Utc>>>,
+    connection_table: Option<ConnectionTable>,
+    scheduler: PeriodicScheduler,
+    limiter: OutboundLimiter,
+    usage: Option<DataUsageMeter>,
+    supervisor: Option<Supervisor>,
+    shutdown: Option<CancellationToken>,
 }
 
 impl ThroughputKeeper {
-    pub fn new(app_handle: AppHandle, repository: Arc<Repository>, shared_state: SharedAppState, config: ThroughputKeeperConfig) -> Self {
+    pub fn new(events: SharedEventSink, repository: Arc<Repository>, shared_state: SharedAppState, config: ThroughputKeeperConfig) -> Self {
         Self {
             repository,
             shared_state,
-            app_handle,
+            events,
             config: Arc::new(RwLock::new(config)),
-            is_running: Arc::new(RwLock::new(false)),
+            is_running: Arc::new(AtomicBool::new(false)),
             hourly_budget_used_mb: Arc::new(RwLock::new(0.0)),
             last_reset: Arc::new(RwLock::new(Utc::now())),
+            daily_budget_used_mb: Arc::new(RwLock::new(0.0)),
+            last_daily_reset: Arc::new(RwLock::new(Utc::now())),
+            connection_table: None,
+            scheduler: PeriodicScheduler::default(),
+            limiter: OutboundLimiter::default(),
+            usage: None,
+            supervisor: None,
+            shutdown: None,
         }
     }
 
-    pub async fn update_config(&self, cfg: ThroughputKeeperConfig) { *self.config.write().await = cfg; }
+    /// Reports bytes, activity and errors per burst target for the connection table
+    pub fn with_connection_table(mut self, table: ConnectionTable) -> Self {
+        self.connection_table = Some(table);
+        self
+    }
 
-    fn jitter_secs(base: u64, jitter_frac: f64) -> u64 {
-        // Compute jitter using time-based hash to keep this function non-async and non-blocking
-        let nanos = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.subsec_nanos() as u64)
-            .unwrap_or(0);
-        let jitter = (base as f64 * jitter_frac) as i64;
-        let sign = if (nanos & 1) == 0 { -1 } else { 1 };
-        let magnitude = (nanos % (jitter.abs() as u64 + 1)) as i64;
-        let delta = sign * magnitude.min(jitter.abs());
-        let val = base as i64 + delta;
-        val.max(1) as u64
+    /// Jitters burst intervals from the app-wide scheduler instead of a private one
+    pub fn with_scheduler(mut self, scheduler: PeriodicScheduler) -> Self {
+        self.scheduler = scheduler;
+        self
     }
 
+    /// Counts bursts and load pulls against the app-wide outbound limit
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Counts burst and load bytes against the monthly data cap
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Restarts the loop with backoff when it panics
+    pub fn with_supervisor(mut self, supervisor: Supervisor) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    /// Ends the loop after the current burst once `token` is cancelled
+    pub fn with_shutdown(mut self, token: CancellationToken) -> Self {
+        self.shutdown = Some(token);
+        self
+    }
+
+    pub async fn update_config(&self, cfg: ThroughputKeeperConfig) { *self.config.write().await = cfg; }
+
     fn user_agent() -> &'static str { "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_4) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15" }
 
     fn build_headers() -> HeaderMap {
@@ -98,16 +270,6 @@ impl ThroughputKeeper {
         }
     }
 
-    fn should_quiet_hour(cfg: &ThroughputKeeperConfig) -> bool {
-        if let Some(hours) = &cfg.quiet_hours {
-            let now = Utc::now();
-            let hour = now.hour() as u8;
-            // Quiet hours represent disallowed hours; suspend if current hour is listed
-            return hours.contains(&hour);
-        }
-        false
-    }
-
     async fn reset_budget_if_needed(&self) {
         let mut last_reset = self.last_reset.write().await;
         let now = Utc::now();
@@ -116,6 +278,20 @@ impl ThroughputKeeper {
             *last_reset = now;
             debug!("ThroughputKeeper: hourly budget reset");
         }
+        drop(last_reset);
+        let mut last_daily = self.last_daily_reset.write().await;
+        if now.signed_duration_since(*last_daily) >= ChronoDuration::days(1) {
+            *self.daily_budget_used_mb.write().await = 0.0;
+            *last_daily = now;
+            debug!("ThroughputKeeper: daily budget reset");
+        }
+    }
+
+    async fn daily_budget_exhausted(&self, cfg: &ThroughputKeeperConfig) -> bool {
+        match cfg.daily_budget_mb {
+            Some(daily) => *self.daily_budget_used_mb.read().await >= daily,
+            None => false,
+        }
     }
 
     async fn get_recent_metrics(&self) -> (f64, f64) {
@@ -137,93 +313,199 @@ impl ThroughputKeeper {
         (0.0, 1.0)
     }
 
-    async fn pick_target_url(&self, stealth_level: &StealthLevel) -> Option<String> {
-        // Prefer active speedtest servers; fallback to a CDN-like path
-        if let Ok(servers) = self.repository.get_active_speedtest_servers().await {
-            if let Some(s) = servers.first() {
-                let scheme = if matches!(stealth_level, StealthLevel::Maximum) { "https" } else { "http" };
-                let nonce = (Utc::now().timestamp_millis() as u64) & 0xFFFF_FFFF;
-                return Some(format!("{}://{}:{}/download?nocache={}", scheme, s.host, s.port, nonce));
+    async fn pick_target_url(&self, stealth_level: &StealthLevel, size_bytes: u64) -> Option<(SpeedtestServer, String)> {
+        // Prefer active speedtest servers; fallback to Cloudflare's edge
+        let server = match self.repository.get_active_speedtest_servers().await {
+            Ok(servers) => servers.into_iter().next().unwrap_or_else(SpeedtestServer::cloudflare),
+            Err(_) => SpeedtestServer::cloudflare(),
+        };
+        if server.id.is_some() {
+            if let Err(e) = self.repository.update_server_last_used(&server.server_id).await {
+                debug!("Failed to record keeper server use: {}", e);
             }
         }
-        Some("https://speed.cloudflare.com/__down?bytes=262144".to_string())
+        let secure = TlsFingerprint::for_level(stealth_level).is_some() || self.shared_state.read().await.prefer_encrypted;
+        let nonce = (Utc::now().timestamp_millis() as u64) & 0xFFFF_FFFF;
+        let url = server.endpoint_url(secure, ServerEndpoint::Download, size_bytes, &nonce.to_string());
+        Some((server, url))
     }
 
-    async fn perform_burst(&self, size_kb: u32, stealth_level: &StealthLevel) -> Result<()> {
-        let url = match self.pick_target_url(stealth_level).await { Some(u) => u, None => return Ok(()) };
-        let mut headers = Self::build_headers();
-        // Randomize Range header, mimic partial GET/HEAD
-        let size_bytes = (size_kb as u64) * 1024;
-        let start = (Utc::now().timestamp_nanos_opt().unwrap_or(0) as u64) % 2048u64;
-        let end = start + size_bytes.saturating_sub(1);
-        let range_val = format!("bytes={}-{}", start, end);
-        headers.insert(RANGE, HeaderValue::from_str(&range_val).unwrap_or(HeaderValue::from_static("bytes=0-1023")));
+    async fn current_stealth_level(&self) -> StealthLevel {
+        match app_state::active_strategy(&self.shared_state, &*self.repository).await {
+            Ok(Some(s)) => s.stealth_level,
+            _ => StealthLevel::Medium,
+        }
+    }
+
+    /// Why generated load may not run right now, `None` when it may
+    async fn load_blocked(&self) -> Option<&'static str> {
+        let s = self.shared_state.read().await;
+        if s.stopped_by_user {
+            Some("Emergency stop is engaged")
+        } else if s.safe_mode {
+            Some("Safe mode is on after repeated crashes")
+        } else if s.data_cap_reached {
+            Some("The monthly data cap is reached")
+        } else if !s.may_load_link() {
+            Some("Generated traffic is held back right now")
+        } else {
+            None
+        }
+    }
+
+    /// Download engine against `server` at the strategy's stealth level; requests time out after `timeout`
+    async fn download_engine(&self, config: KeeperDownloadConfig, server: SpeedtestServer, stealth_level: &StealthLevel, timeout: Duration) -> Result<DownloadEngine> {
+        let mut builder = proxy::apply(reqwest::Client::builder()).timeout(timeout);
+        // Over TLS the fingerprint's ALPN picks the protocol, as it does for the imitated client
+        if let Some(fingerprint) = TlsFingerprint::for_level(stealth_level) {
+            builder = fingerprint.apply(builder);
+        }
+        let client = builder.build()?;
+        let secure = TlsFingerprint::for_level(stealth_level).is_some() || self.shared_state.read().await.prefer_encrypted;
+        Ok(DownloadEngine { config, client, server, secure, limiter: self.limiter.clone() })
+    }
+
+    /// Adds `received` bytes from `server` to the budgets, the data usage and the connection table
+    async fn account(&self, server: &SpeedtestServer, received: u64) {
+        let mb = received as f64 / (1024.0 * 1024.0);
+        *self.hourly_budget_used_mb.write().await += mb;
+        *self.daily_budget_used_mb.write().await += mb;
+        if let Some(table) = &self.connection_table {
+            if received > 0 {
+                table.record_activity(ConnectionOwner::Keeper, server, received);
+            } else {
+                table.record_error(ConnectionOwner::Keeper, server, "no data received");
+            }
+        }
+        if let Some(usage) = &self.usage {
+            usage.record(TrafficSource::Keeper, received).await;
+        }
+    }
+
+    /// Downloads for up to `duration` to load the link, through the multi-stream engine in
+    /// `download` and within the keeper's data budgets. Returns the rate reached in Mbps, `None`
+    /// when nothing arrived.
+    pub async fn pull_for(&self, duration: Duration) -> Result<Option<f64>> {
+        self.reset_budget_if_needed().await;
+        let cfg = self.config.read().await.clone();
+        let remaining_mb = cfg.hourly_budget_mb - *self.hourly_budget_used_mb.read().await;
+        if remaining_mb <= 0.0 || self.daily_budget_exhausted(&cfg).await {
+            return Err(SpeedKarmaError::ConfigurationError("Keeper data budget is used up for now".to_string()));
+        }
+        if let Some(reason) = self.load_blocked().await {
+            return Err(SpeedKarmaError::ConfigurationError(reason.to_string()));
+        }
+        let daily_remaining_mb = match cfg.daily_budget_mb {
+            Some(daily) => daily - *self.daily_budget_used_mb.read().await,
+            None => remaining_mb,
+        };
+        let cap_bytes = (remaining_mb.min(daily_remaining_mb) * 1024.0 * 1024.0) as u64;
+        let stealth_level = self.current_stealth_level().await;
+        let Some((server, _)) = self.pick_target_url(&stealth_level, 0).await else { return Ok(None) };
+
+        let engine = self.download_engine(cfg.download.clone(), server.clone(), &stealth_level, duration + Duration::from_secs(2)).await?;
+        let started = Instant::now();
+        let received = engine.run(duration, cap_bytes).await;
+        let elapsed = started.elapsed().as_secs_f64();
+        self.account(&server, received).await;
+        Ok((received > 0 && elapsed > 0.0).then(|| received as f64 * 8.0 / 1_000_000.0 / elapsed))
+    }
 
-        let client = reqwest::Client::builder()
-            .http2_prior_knowledge()
-            .pool_idle_timeout(Duration::from_secs(30))
-            .build()?;
+    /// One `size_kb` burst through the download engine as a single stream; returns the bytes received
+    async fn perform_burst(&self, size_kb: u32, stealth_level: &StealthLevel) -> Result<u64> {
+        let size_bytes = (size_kb as u64) * 1024;
+        let (server, url) = match self.pick_target_url(stealth_level, size_bytes).await { Some(t) => t, None => return Ok(0) };
+        let config = KeeperDownloadConfig { streams: 1, chunk_sizes_kb: vec![size_kb], ramp_up_s: 0, target_mbps: None, ..KeeperDownloadConfig::default() };
+        let engine = self.download_engine(config, server.clone(), stealth_level, BURST_TIMEOUT).await?;
 
-        // Randomly pick method (compute randomness in a local scope to avoid non-Send across await)
+        // Randomly lead with a HEAD, as a browser checking the resource would (compute randomness in a
+        // local scope to avoid non-Send across await)
         let head_first = {
             let mut rng = rand::thread_rng();
             rng.gen_bool(0.4)
         };
         if head_first {
-            let _ = client.head(&url).headers(headers.clone()).send().await;
+            let _permit = self.limiter.acquire("keeper burst").await;
+            let _ = engine.client.head(&url).headers(Self::build_headers()).send().await;
             let pause_ms: u64 = {
                 let mut rng = rand::thread_rng();
                 rng.gen_range(20..80)
             };
             sleep(Duration::from_millis(pause_ms)).await;
         }
-        let _ = client.get(&url).headers(headers).send().await;
-        Ok(())
+        // Count what actually arrived; a transfer cut short only moved part of the range
+        let received = engine.run(BURST_TIMEOUT, size_bytes).await;
+        self.account(&server, received).await;
+        if received == 0 {
+            return Err(SpeedKarmaError::NetworkUnavailable(format!("Keeper burst to {} received nothing", server.host)));
+        }
+        Ok(received)
     }
 
+    /// Starts the loop, under the supervisor when one is set
     pub fn start(self: Arc<Self>) {
-        let keeper = Arc::clone(&self);
-        tauri::async_runtime::spawn(async move { keeper.run_loop().await; });
+        match self.supervisor.clone() {
+            Some(supervisor) => supervisor.spawn("throughput_keeper", move || {
+                let keeper = Arc::clone(&self);
+                async move { keeper.run().await }
+            }),
+            None => {
+                tokio::spawn(async move { self.run_loop().await; });
+            }
+        }
     }
 
     pub async fn stop(&self) {
-        *self.is_running.write().await = false;
+        self.is_running.store(false, Ordering::SeqCst);
     }
 
-    async fn run_loop(&self) {
-        {
-            let mut r = self.is_running.write().await;
-            if *r { return; }
-            *r = true;
+    /// Runs the loop in the calling task until `stop`
+    pub async fn run(&self) -> Result<()> {
+        self.run_loop().await;
+        Ok(())
+    }
+
+    /// Sleeps for `duration`; false when a shutdown cut it short
+    async fn pause(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = sleep(duration) => true,
+            _ = shutdown::cancelled(self.shutdown.as_ref()) => false,
         }
+    }
+
+    async fn run_loop(&self) {
+        if self.is_running.swap(true, Ordering::SeqCst) { return; }
+        // Cleared however the loop ends, panics included, so a restart can run it again
+        let _running = RunningFlag(Arc::clone(&self.is_running));
 
         info!("ThroughputKeeper started");
         let mut cadence = KeeperCadence::Warmup;
         let mut last_change = Instant::now();
         let mut last_burst_kb: u32 = 64;
+        let mut round: u64 = 0;
 
         loop {
-            if !*self.is_running.read().await { break; }
+            if !self.is_running.load(Ordering::SeqCst) || self.shutdown.as_ref().is_some_and(|t| t.is_cancelled()) { break; }
             // Check optimization and config enable
             let enabled = {
                 let s = self.shared_state.read().await;
-                matches!(s.optimization_mode, OptimizationMode::Enabled)
+                s.may_generate() && s.modules.keeper && !s.keeper_power_suspended()
             };
             let cfg = self.config.read().await.clone();
-            if !enabled || !cfg.enabled || Self::should_quiet_hour(&cfg) {
+            if !enabled || !cfg.enabled {
                 cadence = KeeperCadence::Suspended;
                 self.emit_progress(0, 0, *self.hourly_budget_used_mb.read().await, cfg.hourly_budget_mb, &cadence).await;
-                sleep(Duration::from_secs(3)).await;
+                if !self.pause(Duration::from_secs(3)).await { break; }
                 continue;
             }
 
             // Budget checks
             self.reset_budget_if_needed().await;
             let used = *self.hourly_budget_used_mb.read().await;
-            if used >= cfg.hourly_budget_mb {
+            if used >= cfg.hourly_budget_mb || self.daily_budget_exhausted(&cfg).await {
                 cadence = KeeperCadence::Suspended;
                 self.emit_progress(0, 0, used, cfg.hourly_budget_mb, &cadence).await;
-                sleep(Duration::from_secs(30)).await;
+                if !self.pause(Duration::from_secs(30)).await { break; }
                 continue;
             }
 
@@ -263,33 +545,33 @@ impl ThroughputKeeper {
             if budget_ratio > 0.8 { interval_s += 5; }
             if budget_ratio > 0.9 { interval_s += 10; }
             // Slight jitter
-            interval_s = Self::jitter_secs(interval_s, 0.15);
+            round += 1;
+            interval_s = self.scheduler.delay_for("throughput_keeper", round, Duration::from_secs(interval_s)).as_secs_f64().round().max(1.0) as u64;
 
-            let stealth_level = match self.repository.get_best_optimization_strategy().await {
-                Ok(Some(s)) => s.stealth_level,
-                _ => StealthLevel::Medium,
-            };
+            let stealth_level = self.current_stealth_level().await;
 
-            let size_kb = Self::choose_burst_size_kb(&cfg, &cadence, last_burst_kb).await;
-            if size_kb == 0 { sleep(Duration::from_secs(interval_s)).await; continue; }
+            let mut size_kb = Self::choose_burst_size_kb(&cfg, &cadence, last_burst_kb).await;
+            if self.shared_state.read().await.call_active {
+                size_kb = size_kb.min(cfg.call_burst_cap_kb);
+            }
+            if size_kb == 0 {
+                if !self.pause(Duration::from_secs(interval_s)).await { break; }
+                continue;
+            }
 
-            // Perform burst with backoff
-            let burst_bytes_mb = (size_kb as f64) / 1024.0;
+            // Perform burst with backoff; the budgets are charged with what arrived
             let mut attempt = 0u8;
             let mut success = false;
             while attempt < 3 {
-                match self.perform_burst(size_kb, &stealth_level).await {
-                    Ok(_) => { success = true; break; },
-                    Err(e) => { warn!("ThroughputKeeper burst failed: {}", e); sleep(Duration::from_secs(2u64.pow(attempt as u32))).await; }
+                match self.limiter.unless_halted(self.perform_burst(size_kb, &stealth_level)).await {
+                    Some(Ok(_)) => { success = true; break; },
+                    Some(Err(e)) => { warn!("ThroughputKeeper burst failed: {}", e); if !self.pause(Duration::from_secs(2u64.pow(attempt as u32))).await { break; } }
+                    // Emergency stop: the burst was cancelled mid-flight
+                    None => break,
                 }
                 attempt += 1;
             }
             if success {
-                // account budget
-                {
-                    let mut used = self.hourly_budget_used_mb.write().await;
-                    *used += burst_bytes_mb;
-                }
                 last_burst_kb = size_kb;
             }
 
@@ -297,7 +579,7 @@ impl ThroughputKeeper {
             let used_mb = *self.hourly_budget_used_mb.read().await;
             self.emit_progress(interval_s as u32, last_burst_kb, used_mb, cfg.hourly_budget_mb, &cadence).await;
 
-            sleep(Duration::from_secs(interval_s)).await;
+            if !self.pause(Duration::from_secs(interval_s)).await { break; }
         }
 
         info!("ThroughputKeeper stopped");
@@ -311,8 +593,30 @@ impl ThroughputKeeper {
             hour_budget_mb: (budget_mb * 100.0).round() / 100.0,
             cadence: match cadence { KeeperCadence::Warmup => "warmup", KeeperCadence::Steady => "steady", KeeperCadence::Recovery => "recovery", KeeperCadence::Suspended => "suspended" }.to_string(),
         };
-        let _ = self.app_handle.emit_all("keeper_progress", payload);
+        self.events.emit_payload("keeper_progress", &payload);
     }
 }
 
-
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_engine_ramps_streams_and_paces_to_the_target() {
+        let at = |secs: f64| Duration::from_secs_f64(secs);
+        let mut cfg = KeeperDownloadConfig { streams: 8, ramp_up_s: 4, ..KeeperDownloadConfig::default() };
+        assert_eq!([0.0, 1.0, 2.0, 3.9, 4.0].map(|s| streams_at(&cfg, at(s))), [1, 2, 4, 7, 8]);
+        cfg.ramp_up = RampUpProfile::Exponential;
+        assert_eq!([0.0, 1.5, 2.7, 4.0].map(|s| streams_at(&cfg, at(s))), [1, 2, 4, 8]);
+        cfg.ramp_up = RampUpProfile::Immediate;
+        assert_eq!(streams_at(&cfg, at(0.0)), 8);
+        cfg.ramp_up_s = 0;
+        cfg.ramp_up = RampUpProfile::Linear;
+        assert_eq!(streams_at(&cfg, at(0.0)), 8);
+
+        // 2.5 MB is two seconds at 10 Mbps: one second in, streams wait out the other
+        assert_eq!(pacing_delay(Some(10.0), 2_500_000, at(1.0)), at(1.0));
+        assert_eq!(pacing_delay(Some(10.0), 2_500_000, at(3.0)), Duration::ZERO);
+        assert_eq!(pacing_delay(None, 2_500_000, at(0.0)), Duration::ZERO);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::core::shutdown::CancellationToken;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+/// Concurrent outbound requests allowed when no limit is configured
+pub const DEFAULT_MAX_CONCURRENT: usize = 6;
+
+/// App-wide cap on concurrent outbound requests and sockets. The keeper, stealth engine, server
+/// discovery and probes share one limiter so weak routers are not flooded and the connection
+/// count stays below what ISP heuristics flag.
+#[derive(Debug, Clone)]
+pub struct OutboundLimiter {
+    semaphore: Arc<Semaphore>,
+    max_concurrent: usize,
+    /// Emergency stop: generated traffic is cancelled while set
+    halted: Arc<watch::Sender<bool>>,
+    /// Cancelled by `halt`, replaced by `resume`; handed to spawned traffic tasks
+    traffic: Arc<Mutex<CancellationToken>>,
+    /// Bumped by `interrupt`, which drops traffic in flight without halting
+    interrupted: Arc<watch::Sender<u64>>,
+}
+
+impl Default for OutboundLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT)
+    }
+}
+
+impl OutboundLimiter {
+    /// `max_concurrent` of zero is treated as one
+    pub fn new(max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self { semaphore: Arc::new(Semaphore::new(max_concurrent)), max_concurrent, halted: Arc::new(watch::Sender::new(false)),
+            traffic: Arc::new(Mutex::new(CancellationToken::new())), interrupted: Arc::new(watch::Sender::new(0)) }
+    }
+
+    /// Waits for a free slot; the slot is released when the permit is dropped
+    pub async fn acquire(&self, purpose: &str) -> OwnedSemaphorePermit {
+        if let Ok(permit) = Arc::clone(&self.semaphore).try_acquire_owned() {
+            return permit;
+        }
+        debug!("Outbound limit of {} reached; {} waits for a slot", self.max_concurrent, purpose);
+        Arc::clone(&self.semaphore).acquire_owned().await.expect("outbound semaphore is never closed")
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// Requests currently holding a slot
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrent - self.semaphore.available_permits()
+    }
+
+    /// Cancels generated traffic in flight and refuses new generated traffic until `resume`
+    pub fn halt(&self) {
+        self.halted.send_replace(true);
+        self.traffic.lock().expect("traffic token lock poisoned").cancel();
+    }
+
+    pub fn resume(&self) {
+        let mut traffic = self.traffic.lock().expect("traffic token lock poisoned");
+        if traffic.is_cancelled() {
+            *traffic = CancellationToken::new();
+        }
+        self.halted.send_replace(false);
+    }
+
+    /// Cancels generated traffic in flight but lets new traffic start, for when the connection
+    /// drops under it and whatever is running can only fail
+    pub fn interrupt(&self) {
+        let mut traffic = self.traffic.lock().expect("traffic token lock poisoned");
+        traffic.cancel();
+        if !self.is_halted() {
+            *traffic = CancellationToken::new();
+        }
+        self.interrupted.send_modify(|n| *n += 1);
+    }
+
+    /// Token cancelled by the next `halt` or `interrupt`, for traffic that runs in spawned tasks a dropped
+    /// future cannot reach; already cancelled while halted
+    pub fn traffic_token(&self) -> CancellationToken {
+        self.traffic.lock().expect("traffic token lock poisoned").clone()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        *self.halted.borrow()
+    }
+
+    /// Runs generated traffic; `None` when halted before it started, or halted or interrupted
+    /// while it ran, in which case the future is dropped and its connections closed
+    pub async fn unless_halted<F: Future>(&self, traffic: F) -> Option<F::Output> {
+        let mut halted = self.halted.subscribe();
+        let mut interrupted = self.interrupted.subscribe();
+        tokio::select! {
+            biased;
+            _ = halted.wait_for(|h| *h) => None,
+            _ = interrupted.changed() => None,
+            out = traffic => Some(out),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_limit_is_shared_between_clones() {
+        let limiter = OutboundLimiter::new(2);
+        let other = limiter.clone();
+        let first = limiter.acquire("keeper").await;
+        let _second = other.acquire("stealth").await;
+        assert_eq!(limiter.in_flight(), 2);
+
+        // A third request waits until a slot frees up
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire("probe").await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        drop(first);
+        let _third = tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+        assert_eq!(other.in_flight(), 2);
+        assert_eq!(OutboundLimiter::new(0).max_concurrent(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_halt_cancels_generated_traffic() {
+        let limiter = OutboundLimiter::new(2);
+        assert_eq!(limiter.unless_halted(async { 7 }).await, Some(7));
+
+        // Traffic in flight is dropped as soon as the stop is engaged, on any clone
+        let running = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.unless_halted(tokio::time::sleep(Duration::from_secs(60))).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        limiter.clone().halt();
+        assert_eq!(tokio::time::timeout(Duration::from_secs(1), running).await.unwrap().unwrap(), None);
+        assert!(limiter.unless_halted(async { 7 }).await.is_none());
+
+        limiter.resume();
+        assert!(!limiter.is_halted());
+        assert_eq!(limiter.unless_halted(async { 7 }).await, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_traffic_token_is_cancelled_by_halt_and_renewed_by_resume() {
+        let limiter = OutboundLimiter::new(2);
+        let token = limiter.traffic_token();
+        assert!(!token.is_cancelled());
+        limiter.clone().halt();
+        assert!(token.is_cancelled());
+        assert!(limiter.traffic_token().is_cancelled());
+
+        limiter.resume();
+        assert!(token.is_cancelled());
+        assert!(!limiter.traffic_token().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_cancels_traffic_in_flight_only() {
+        let limiter = OutboundLimiter::new(2);
+        let token = limiter.traffic_token();
+        let running = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.unless_halted(tokio::time::sleep(Duration::from_secs(60))).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        limiter.clone().interrupt();
+        assert_eq!(tokio::time::timeout(Duration::from_secs(1), running).await.unwrap().unwrap(), None);
+        assert!(token.is_cancelled());
+
+        // New traffic still runs and gets a fresh token
+        assert!(!limiter.is_halted());
+        assert!(!limiter.traffic_token().is_cancelled());
+        assert_eq!(limiter.unless_halted(async { 7 }).await, Some(7));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::network::wifi;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Ceiling for interfaces that do not report a link speed (what every interface used to get)
+pub const FALLBACK_CEILING_MBPS: f64 = 1000.0;
+/// Slack over the negotiated rate: counters include framing, and Wi-Fi rates adapt between samples
+const LINK_HEADROOM: f64 = 1.25;
+
+/// `/sys/class/net/<if>/speed` in Mbps; virtual and disconnected links report -1 or nothing
+pub fn parse_sysfs_speed(content: &str) -> Option<f64> {
+    content.trim().parse::<f64>().ok().filter(|mbps| *mbps > 0.0)
+}
+
+/// A counter delta faster than its interface's link can carry, e.g. a driver counter glitch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImpossibleReading {
+    pub interface: String,
+    pub mbps: f64,
+    pub ceiling_mbps: f64,
+    /// Negotiated rate the ceiling came from; `None` when the fallback applied
+    pub link_mbps: Option<f64>,
+}
+
+/// Negotiated link rate per interface: Ethernet speed, or the Wi-Fi PHY rate
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LinkSpeeds(HashMap<String, f64>);
+
+impl LinkSpeeds {
+    pub fn new(speeds: HashMap<String, f64>) -> Self { Self(speeds) }
+
+    /// Reads the rate of each named interface. Ethernet comes from sysfs on Linux; the Wi-Fi
+    /// rate, where the platform reports one, fills in for the wireless interface.
+    pub async fn read<'a>(interfaces: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut speeds = HashMap::new();
+        for name in interfaces {
+            if let Some(mbps) = read_sysfs(name).await {
+                speeds.insert(name.to_string(), mbps);
+            }
+        }
+        if let Some(signal) = wifi::read_signal().await {
+            if let (Some(name), Some(mbps)) = (signal.interface, signal.link_rate_mbps.filter(|r| *r > 0.0)) {
+                speeds.entry(name).or_insert(mbps);
+            }
+        }
+        Self(speeds)
+    }
+
+    pub fn get(&self, interface: &str) -> Option<f64> {
+        self.0.get(interface).copied()
+    }
+
+    /// Fastest rate `interface` can plausibly have carried
+    pub fn ceiling_mbps(&self, interface: &str) -> f64 {
+        self.get(interface).map_or(FALLBACK_CEILING_MBPS, |mbps| mbps * LINK_HEADROOM)
+    }
+
+    /// `None` when `bytes` over `seconds` fits the link, otherwise the reading to flag
+    pub fn check(&self, interface: &str, bytes: u64, seconds: f64) -> Option<ImpossibleReading> {
+        let mbps = bytes as f64 * 8.0 / (seconds * 1_000_000.0);
+        let ceiling_mbps = self.ceiling_mbps(interface);
+        (mbps > ceiling_mbps).then(|| ImpossibleReading { interface: interface.to_string(), mbps, ceiling_mbps, link_mbps: self.get(interface) })
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn read_sysfs(interface: &str) -> Option<f64> {
+    // Reading `speed` fails with EINVAL on wireless and down links
+    let content = tokio::fs::read_to_string(format!("/sys/class/net/{}/speed", interface)).await.ok()?;
+    parse_sysfs_speed(&content)
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn read_sysfs(_interface: &str) -> Option<f64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_ceilings_flag_impossible_readings() {
+        assert_eq!(parse_sysfs_speed("1000\n"), Some(1000.0));
+        assert_eq!(parse_sysfs_speed("-1\n"), None);
+        assert_eq!(parse_sysfs_speed(""), None);
+
+        let links = LinkSpeeds::new(HashMap::from([("eth0".to_string(), 100.0), ("wlan0".to_string(), 866.7)]));
+        // 60 s at 110 Mbps fits a Fast Ethernet port with headroom; 200 Mbps does not
+        assert!(links.check("eth0", 110 * 125_000 * 60, 60.0).is_none());
+        let flagged = links.check("eth0", 200 * 125_000 * 60, 60.0).unwrap();
+        assert_eq!((flagged.ceiling_mbps, flagged.link_mbps), (125.0, Some(100.0)));
+        assert!((flagged.mbps - 200.0).abs() < 1e-9);
+        // A 2.5 Gbps reading is real on a multi-gig port but impossible on unknown links
+        let multigig = LinkSpeeds::new(HashMap::from([("enp5s0".to_string(), 2500.0)]));
+        assert!(multigig.check("enp5s0", 2400 * 125_000, 1.0).is_none());
+        assert_eq!(links.check("usb0", 2400 * 125_000, 1.0).map(|r| r.ceiling_mbps), Some(FALLBACK_CEILING_MBPS));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::core::app_state::SharedAppState;
+use crate::network::monitor::{BackgroundMonitor, NetworkStats};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::debug;
+
+/// About 15 minutes of one-second samples
+const MAX_SAMPLES: usize = 900;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Throughput over one sampling second, summed over the monitored interfaces
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LiveSample {
+    pub at: DateTime<Utc>,
+    pub download_mbps: f64,
+    pub upload_mbps: f64,
+}
+
+/// Combined throughput between two counter readings; interfaces that appeared or reset count as idle
+pub(crate) fn throughput(previous: &HashMap<String, NetworkStats>, current: &HashMap<String, NetworkStats>) -> Option<(f64, f64)> {
+    let (mut received, mut sent, mut seconds) = (0u64, 0u64, 0.0f64);
+    for (name, now) in current {
+        let Some(before) = previous.get(name) else { continue };
+        if now.bytes_received >= before.bytes_received && now.bytes_sent >= before.bytes_sent {
+            received += now.bytes_received - before.bytes_received;
+            sent += now.bytes_sent - before.bytes_sent;
+        }
+        seconds = seconds.max(now.timestamp.duration_since(before.timestamp).as_secs_f64());
+    }
+    (seconds > 0.0).then(|| (received as f64 * 8.0 / seconds / 1_000_000.0, sent as f64 * 8.0 / seconds / 1_000_000.0))
+}
+
+/// Per-second interface throughput for the panel's live graph. Kept in memory only, so the
+/// graph adds no database load.
+#[derive(Debug, Clone, Default)]
+pub struct LiveThroughput {
+    samples: Arc<Mutex<VecDeque<LiveSample>>>,
+    shared: Option<SharedAppState>,
+}
+
+impl LiveThroughput {
+    pub fn new() -> Self { Self::default() }
+
+    /// Counts only the interfaces passive monitoring counts; all of them without it
+    pub fn with_shared_state(mut self, shared: SharedAppState) -> Self {
+        self.shared = Some(shared);
+        self
+    }
+
+    pub fn record(&self, sample: LiveSample) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// Samples after `since`, oldest first; all of them without it
+    pub fn samples(&self, since: Option<DateTime<Utc>>) -> Vec<LiveSample> {
+        let samples = self.samples.lock().unwrap();
+        samples.iter().filter(|s| since.is_none_or(|since| s.at > since)).cloned().collect()
+    }
+
+    /// Reads the interface counters once a second
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            let mut previous: Option<HashMap<String, NetworkStats>> = None;
+            loop {
+                interval.tick().await;
+                let mut current = match BackgroundMonitor::get_network_interface_stats().await {
+                    Ok(current) => current,
+                    Err(e) => {
+                        debug!("Live throughput sample skipped: {}", e);
+                        continue;
+                    }
+                };
+                if let Some(shared) = &self.shared {
+                    let selection = shared.read().await.monitored_interfaces.clone();
+                    current.retain(|name, _| selection.selects(name));
+                }
+                if let Some((download_mbps, upload_mbps)) = previous.as_ref().and_then(|previous| throughput(previous, &current)) {
+                    self.record(LiveSample { at: Utc::now(), download_mbps, upload_mbps });
+                }
+                previous = Some(current);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_live_samples_are_bounded_and_skip_counter_resets() {
+        let at = Instant::now();
+        let stats = |received, sent, timestamp| NetworkStats { bytes_received: received, bytes_sent: sent, packets_received: 0, packets_sent: 0, timestamp };
+        let previous = HashMap::from([("eth0".to_string(), stats(1_000_000, 0, at)), ("wlan0".to_string(), stats(5_000_000, 0, at))]);
+        let current = HashMap::from([
+            ("eth0".to_string(), stats(2_250_000, 125_000, at + Duration::from_secs(1))),
+            // Counter reset: contributes nothing rather than a huge wrap-around
+            ("wlan0".to_string(), stats(10, 0, at + Duration::from_secs(1))),
+            ("usb0".to_string(), stats(9_000_000, 0, at + Duration::from_secs(1))),
+        ]);
+        assert_eq!(throughput(&previous, &current), Some((10.0, 1.0)));
+
+        let live = LiveThroughput::new();
+        let start = Utc::now();
+        for i in 0..(MAX_SAMPLES as i64 + 10) {
+            live.record(LiveSample { at: start + chrono::Duration::seconds(i), download_mbps: 1.0, upload_mbps: 0.5 });
+        }
+        let all = live.samples(None);
+        assert_eq!(all.len(), MAX_SAMPLES);
+        assert_eq!(all[0].at, start + chrono::Duration::seconds(10));
+        assert_eq!(live.samples(Some(all[MAX_SAMPLES - 3].at)).len(), 2);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::core::app_state::SharedAppState;
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+use crate::core::command::command_output;
+use crate::core::config::{MeteredConnectionConfig, MeteredOverride};
+use crate::core::events::SharedEventSink;
+use crate::core::watcher::PollingWatcher;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// NetworkManager's `Metered` value: the D-Bus enum as printed by `busctl` ("u 3") or the word
+/// `nmcli` prints ("yes (guessed)"). Guesses count; unknown is `None`.
+pub fn parse_networkmanager_metered(output: &str) -> Option<bool> {
+    let value = output.trim();
+    let value = value.strip_prefix("u ").unwrap_or(value);
+    match value {
+        "1" | "3" => Some(true),
+        "2" | "4" => Some(false),
+        _ if value.starts_with("yes") => Some(true),
+        _ if value.starts_with("no") => Some(false),
+        _ => None,
+    }
+}
+
+/// Windows `NetworkCostType` of the internet connection profile
+pub fn parse_network_cost_type(output: &str) -> Option<bool> {
+    match output.trim() {
+        "Fixed" | "Variable" => Some(true),
+        "Unrestricted" => Some(false),
+        _ => None,
+    }
+}
+
+/// Interface of the IPv4 default route in `/proc/net/route`
+pub fn default_route_interface(table: &str) -> Option<String> {
+    table.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let (interface, destination) = (fields.next()?, fields.next()?);
+        (destination == "00000000").then(|| interface.to_string())
+    })
+}
+
+/// Whether the platform reports the current connection as metered; `None` when it cannot tell
+#[cfg(target_os = "linux")]
+pub async fn detect() -> Option<bool> {
+    let manager = command_output("busctl", &[
+        "get-property", "org.freedesktop.NetworkManager", "/org/freedesktop/NetworkManager", "org.freedesktop.NetworkManager", "Metered",
+    ]).await;
+    if let Some(metered) = manager.as_deref().and_then(parse_networkmanager_metered) {
+        return Some(metered);
+    }
+    let table = tokio::fs::read_to_string("/proc/net/route").await.ok()?;
+    let interface = default_route_interface(&table)?;
+    parse_networkmanager_metered(&command_output("nmcli", &["-t", "-g", "GENERAL.METERED", "device", "show", &interface]).await?)
+}
+
+#[cfg(target_os = "macos")]
+pub async fn detect() -> Option<bool> {
+    tokio::task::spawn_blocking(nw_path::is_expensive).await.ok().flatten()
+}
+
+/// The expensive flag macOS sets on cellular, Personal Hotspot and user-marked networks. Apps can
+/// only read it through the Network framework's path monitor.
+#[cfg(target_os = "macos")]
+mod nw_path {
+    use block::{Block, ConcreteBlock};
+    use std::ffi::c_void;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// The monitor reports the current path right after it starts
+    const FIRST_UPDATE_TIMEOUT: Duration = Duration::from_secs(2);
+
+    #[link(name = "Network", kind = "framework")]
+    extern "C" {
+        fn nw_path_monitor_create() -> *mut c_void;
+        fn nw_path_monitor_set_queue(monitor: *mut c_void, queue: *mut c_void);
+        fn nw_path_monitor_set_update_handler(monitor: *mut c_void, handler: &Block<(*mut c_void,), ()>);
+        fn nw_path_monitor_start(monitor: *mut c_void);
+        fn nw_path_monitor_cancel(monitor: *mut c_void);
+        fn nw_path_is_expensive(path: *mut c_void) -> bool;
+        fn nw_release(object: *mut c_void);
+    }
+
+    extern "C" {
+        fn dispatch_get_global_queue(identifier: isize, flags: usize) -> *mut c_void;
+    }
+
+    /// Blocks for up to `FIRST_UPDATE_TIMEOUT`; run it off the async runtime
+    pub fn is_expensive() -> Option<bool> {
+        let (tx, rx) = mpsc::channel();
+        let handler = ConcreteBlock::new(move |path: *mut c_void| {
+            let _ = tx.send(unsafe { nw_path_is_expensive(path) });
+        }).copy();
+        unsafe {
+            let monitor = nw_path_monitor_create();
+            if monitor.is_null() {
+                return None;
+            }
+            nw_path_monitor_set_queue(monitor, dispatch_get_global_queue(0, 0));
+            nw_path_monitor_set_update_handler(monitor, &handler);
+            nw_path_monitor_start(monitor);
+            let expensive = rx.recv_timeout(FIRST_UPDATE_TIMEOUT).ok();
+            nw_path_monitor_cancel(monitor);
+            nw_release(monitor);
+            expensive
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub async fn detect() -> Option<bool> {
+    const SCRIPT: &str = "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime] > $null; \
+        $p = [Windows.Networking.Connectivity.NetworkInformation]::GetInternetConnectionProfile(); \
+        if ($p) { $p.GetConnectionCost().NetworkCostType }";
+    parse_network_cost_type(&command_output("powershell", &["-NoProfile", "-NonInteractive", "-Command", SCRIPT]).await?)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub async fn detect() -> Option<bool> {
+    None
+}
+
+/// `get_metered_status` payload and `metered_connection` event
+#[derive(Debug, Clone, Serialize)]
+pub struct MeteredStatus {
+    /// What the platform reports; `None` when it cannot tell
+    pub detected: Option<bool>,
+    /// After the configured override
+    pub metered: bool,
+    /// Generated traffic is held back because of it
+    pub suspended: bool,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl MeteredStatus {
+    pub fn resolve(detected: Option<bool>, config: &MeteredConnectionConfig) -> Self {
+        let metered = match config.treat_as {
+            MeteredOverride::Detect => detected.unwrap_or(false),
+            MeteredOverride::Metered => true,
+            MeteredOverride::Unmetered => false,
+        };
+        Self { detected, metered, suspended: metered && config.auto_suspend, checked_at: Utc::now() }
+    }
+}
+
+/// Polls the platform's metered flag and suspends generated traffic while it is set.
+/// A safety net for tethering, where background traffic eats the phone's data plan.
+pub struct MeteredWatcher {
+    events: SharedEventSink,
+    shared: SharedAppState,
+    config: Arc<RwLock<MeteredConnectionConfig>>,
+    status: RwLock<Option<MeteredStatus>>,
+    show_notifications: bool,
+}
+
+impl MeteredWatcher {
+    pub fn new(events: SharedEventSink, shared: SharedAppState, config: MeteredConnectionConfig, show_notifications: bool) -> Self {
+        Self { events, shared, config: Arc::new(RwLock::new(config)), status: RwLock::new(None), show_notifications }
+    }
+
+    pub async fn update_config(&self, cfg: MeteredConnectionConfig) {
+        *self.config.write().await = cfg.clone();
+        let detected = self.status.read().await.as_ref().and_then(|s| s.detected);
+        self.apply(MeteredStatus::resolve(detected, &cfg)).await;
+    }
+
+    pub async fn status(&self) -> Option<MeteredStatus> {
+        self.status.read().await.clone()
+    }
+
+    async fn apply(&self, status: MeteredStatus) {
+        let was_suspended = std::mem::replace(&mut self.shared.write().await.metered_suspended, status.suspended);
+        let previous = self.status.write().await.replace(status.clone());
+        if previous.as_ref().map(|p| (p.metered, p.suspended)) == Some((status.metered, status.suspended)) {
+            debug!("Metered check unchanged");
+            return;
+        }
+        if status.suspended && !was_suspended {
+            warn!("Metered connection detected; generated traffic suspended");
+            if self.show_notifications {
+                self.events.notify("SpeedKarma", "Metered connection detected. SpeedKarma paused its background traffic to save your data.");
+            }
+        } else if was_suspended && !status.suspended {
+            info!("Connection no longer treated as metered; generated traffic resumed");
+        }
+        self.events.emit_payload("metered_connection", &status);
+    }
+}
+
+#[async_trait]
+impl PollingWatcher for MeteredWatcher {
+    async fn poll(&self) -> Duration {
+        let cfg = self.config.read().await.clone();
+        let detected = match cfg.treat_as {
+            MeteredOverride::Detect => detect().await,
+            _ => None,
+        };
+        self.apply(MeteredStatus::resolve(detected, &cfg)).await;
+        Duration::from_secs(cfg.check_interval_seconds.max(5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform_outputs_and_override() {
+        assert_eq!(parse_networkmanager_metered("u 3\n"), Some(true));
+        assert_eq!(parse_networkmanager_metered("u 4"), Some(false));
+        assert_eq!(parse_networkmanager_metered("u 0"), None);
+        assert_eq!(parse_networkmanager_metered("yes (guessed)\n"), Some(true));
+        assert_eq!(parse_networkmanager_metered("no"), Some(false));
+        assert_eq!(parse_networkmanager_metered("unknown"), None);
+
+        assert_eq!(parse_network_cost_type("Variable\r\n"), Some(true));
+        assert_eq!(parse_network_cost_type("Unrestricted"), Some(false));
+        assert_eq!(parse_network_cost_type("Unknown"), None);
+
+        let table = "Iface\tDestination\tGateway\nwlan0\t0010A8C0\t00000000\nwwan0\t00000000\t0100A8C0\n";
+        assert_eq!(default_route_interface(table).as_deref(), Some("wwan0"));
+
+        let config = MeteredConnectionConfig::default();
+        assert!(MeteredStatus::resolve(Some(true), &config).suspended);
+        assert!(!MeteredStatus::resolve(None, &config).metered);
+        let unmetered = MeteredConnectionConfig { treat_as: MeteredOverride::Unmetered, ..config.clone() };
+        assert!(!MeteredStatus::resolve(Some(true), &unmetered).suspended);
+        let report_only = MeteredConnectionConfig { auto_suspend: false, ..config };
+        let status = MeteredStatus::resolve(Some(true), &report_only);
+        assert!(status.metered && !status.suspended);
+    }
+}
@@ -4,7 +4,38 @@ pub mod stealth;
 pub mod servers;
 pub mod keeper;
 pub mod speedtest_runner;
+pub mod speedtest_retry;
+pub mod speedtest_schedule;
 pub mod disguise;
+pub mod asn_db;
+pub mod qos;
+pub mod sqm;
+pub mod conflicts;
+pub mod rtt;
+pub mod calls;
+pub mod wifi;
+pub mod link_speed;
+pub mod connections;
+pub mod ip_lookup;
+pub mod resolvers;
+pub mod traceroute;
+pub mod cgnat;
+pub mod tampering;
+pub mod limiter;
+pub mod confidence;
+pub mod usage;
+pub mod live;
+pub mod fastcom;
+pub mod cloudflare;
+pub mod tls;
+pub mod quic;
+pub mod dns;
+pub mod proxy;
+pub mod metered;
+pub mod connectivity;
+pub mod context;
+pub mod calibration;
+pub mod iperf3;
 
 // Re-export commonly used types
 pub use monitor::BackgroundMonitor;
@@ -13,4 +44,12 @@ pub use stealth::StealthEngine;
 pub use servers::ServerPool;
 pub use keeper::ThroughputKeeper;
 pub use speedtest_runner::SpeedtestRunner;
-pub use disguise::DisguiseProxy;
\ No newline at end of file
+pub use speedtest_retry::SpeedtestRetryQueue;
+pub use speedtest_schedule::SpeedtestSchedule;
+pub use disguise::DisguiseProxy;
+pub use asn_db::AsnDatabase;
+pub use rtt::RttSampler;
+pub use connections::ConnectionTable;
+pub use limiter::OutboundLimiter;
+pub use usage::DataUsageMeter;
+pub use live::LiveThroughput;
\ No newline at end of file
@@ -1,13 +1,35 @@
+use crate::core::app_state::SharedAppState;
+use crate::core::conflicts;
+use crate::core::config::{AdaptiveConfidenceConfig, InterfaceSelectionConfig, ThrottlingSensitivityConfig, VpnMeasurementPolicy};
 use crate::core::error::{Result, SpeedKarmaError};
-use crate::data::models::{SpeedMeasurement, ISPProfile, ThrottlingPattern};
+use crate::core::events::SharedEventSink;
+use crate::core::intelligence::without_vpn;
+use crate::core::scheduler::PeriodicScheduler;
+use crate::core::shutdown::{self, CancellationToken};
+use crate::data::models::{BufferbloatTest, CgnatReport, SpeedMeasurement, MeasurementMethod, MeasurementSource, ISPProfile, IspCandidate, RouteSnapshot, ThrottlingPattern};
 use crate::data::repository::Repository;
+use crate::network::asn_db::AsnDatabase;
+use crate::network::calibration;
+use crate::network::cgnat;
+use crate::network::confidence::ConfidenceCalibrator;
+use crate::network::ip_lookup::PublicIpLookup;
+use crate::network::keeper::ThroughputKeeper;
+use crate::network::link_speed::{ImpossibleReading, LinkSpeeds};
+use crate::network::proxy;
+use crate::network::resolvers;
+use crate::network::sqm::{BufferbloatGrade, SqmAdvisor};
+use crate::network::rtt::{LatencyProbe, ProbeResult, RttSampler};
+use crate::network::traceroute;
+use crate::network::context;
+use crate::core::wifi::WifiAttribution;
+use crate::network::wifi;
 use chrono::{DateTime, Utc, Duration, Weekday, Timelike, Datelike};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration as StdDuration, Instant};
 use tokio::sync::{mpsc, RwLock};
-use tokio::time::interval;
+use tokio::task::JoinHandle;
 use tracing::{debug, info, warn, error};
 
 /// Network interface statistics for bandwidth calculation
@@ -20,6 +42,108 @@ pub struct NetworkStats {
     pub timestamp: Instant,
 }
 
+/// An interface as listed for the monitoring picker
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkInterfaceInfo {
+    pub name: String,
+    /// Counted by passive monitoring under the current include/exclude patterns
+    pub selected: bool,
+    /// VPN or overlay tunnel
+    pub tunnel: bool,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
+
+/// Interfaces in `stats` sorted by name, marked with whether `selection` counts them
+pub fn describe_interfaces(stats: &HashMap<String, NetworkStats>, selection: &InterfaceSelectionConfig) -> Vec<NetworkInterfaceInfo> {
+    let mut interfaces: Vec<NetworkInterfaceInfo> = stats.iter().map(|(name, stat)| NetworkInterfaceInfo {
+        name: name.clone(),
+        selected: selection.selects(name),
+        tunnel: conflicts::is_tunnel_interface(name),
+        bytes_received: stat.bytes_received,
+        bytes_sent: stat.bytes_sent,
+    }).collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
+
+/// Interfaces on this machine for the monitoring picker
+pub async fn list_network_interfaces(selection: &InterfaceSelectionConfig) -> Result<Vec<NetworkInterfaceInfo>> {
+    Ok(describe_interfaces(&BackgroundMonitor::get_network_interface_stats().await?, selection))
+}
+
+/// Per-interface counters from `/proc/net/dev` content, skipping loopback.
+/// Used instead of sysinfo on router builds, where it is the only counter source.
+pub fn parse_proc_net_dev(content: &str, timestamp: Instant) -> HashMap<String, NetworkStats> {
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, counters) = line.split_once(':')?;
+            let name = name.trim();
+            if name == "lo" {
+                return None;
+            }
+            let fields: Vec<u64> = counters.split_whitespace().filter_map(|f| f.parse().ok()).collect();
+            // Receive: bytes packets errs drop fifo frame compressed multicast; transmit follows
+            if fields.len() < 16 {
+                return None;
+            }
+            Some((name.to_string(), NetworkStats {
+                bytes_received: fields[0],
+                packets_received: fields[1],
+                bytes_sent: fields[8],
+                packets_sent: fields[9],
+                timestamp,
+            }))
+        })
+        .collect()
+}
+
+/// How long the keeper loads the link during a bufferbloat test
+const BUFFERBLOAT_LOAD: StdDuration = StdDuration::from_secs(8);
+/// Latency added under load from which a slow period is put down to congestion (grade C or worse)
+const CONGESTION_ADDED_LATENCY_MS: f64 = 60.0;
+/// Behind carrier-grade NAT, untested dips shallower than this (fraction below baseline) are put
+/// down to the shared NAT gateway filling up at peak time rather than to a shaper
+const CGNAT_CONGESTION_SEVERITY: f64 = 0.4;
+/// How often the monitor re-runs the throttling analysis and replaces the stored patterns
+const THROTTLING_ANALYSIS_INTERVAL: StdDuration = StdDuration::from_secs(6 * 3600);
+/// Days of measurements each periodic analysis looks at
+const THROTTLING_ANALYSIS_DAYS: u32 = 14;
+
+/// Interfaces that appeared, disappeared or restarted their counters since the last sample
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InterfaceChange {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Present in both samples but with counters that went backwards, e.g. a replugged USB adapter
+    pub reset: Vec<String>,
+}
+
+impl InterfaceChange {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.reset.is_empty()
+    }
+}
+
+/// Compares two interface samples; names are sorted so events are stable
+pub fn diff_interfaces(previous: &HashMap<String, NetworkStats>, current: &HashMap<String, NetworkStats>) -> InterfaceChange {
+    let mut change = InterfaceChange::default();
+    for (name, stat) in current {
+        match previous.get(name) {
+            None => change.added.push(name.clone()),
+            Some(prev) if stat.bytes_received < prev.bytes_received || stat.bytes_sent < prev.bytes_sent => change.reset.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    change.removed = previous.keys().filter(|name| !current.contains_key(*name)).cloned().collect();
+    change.added.sort();
+    change.removed.sort();
+    change.reset.sort();
+    change
+}
+
 impl Default for NetworkStats {
     fn default() -> Self {
         Self {
@@ -40,6 +164,33 @@ pub struct PassiveSpeedResult {
     pub upload_mbps: f64,
     pub confidence: f64,
     pub measurement_duration_seconds: f64,
+    /// The interface that carried the most traffic in the window
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Tunnel interface that carried the window's traffic, when a VPN was up
+    #[serde(default)]
+    pub vpn_interface: Option<String>,
+}
+
+/// Least a tunnel must receive in a window before it counts as carrying traffic
+const MIN_VPN_BYTES: u64 = 64 * 1024;
+/// Share of the physical interfaces' received bytes a tunnel must match; split tunnels carry less
+const VPN_TRAFFIC_SHARE: f64 = 0.5;
+
+/// The tunnel interface carrying the traffic, from bytes received per interface over one window
+pub fn vpn_carrier<'a>(received: impl IntoIterator<Item = (&'a str, u64)>) -> Option<String> {
+    let mut physical = 0u64;
+    let mut busiest_tunnel: Option<(&str, u64)> = None;
+    for (name, bytes) in received {
+        if !conflicts::is_tunnel_interface(name) {
+            physical += bytes;
+        } else if busiest_tunnel.is_none_or(|(_, most)| bytes > most) {
+            busiest_tunnel = Some((name, bytes));
+        }
+    }
+    busiest_tunnel
+        .filter(|(_, bytes)| *bytes >= MIN_VPN_BYTES && *bytes as f64 >= VPN_TRAFFIC_SHARE * physical as f64)
+        .map(|(name, _)| name.to_string())
 }
 
 /// Configuration for passive monitoring
@@ -62,6 +213,18 @@ impl Default for MonitoringConfig {
     }
 }
 
+impl MonitoringConfig {
+    /// Defaults with a custom cadence; the hourly cap follows the interval
+    pub fn with_interval(measurement_interval_seconds: u64) -> Self {
+        let interval = measurement_interval_seconds.max(1);
+        Self {
+            measurement_interval_seconds: interval,
+            max_measurements_per_hour: (3600 / interval).max(1) as u32,
+            ..Self::default()
+        }
+    }
+}
+
 /// Background network monitoring service
 /// Operates like macOS system processes - always running, never intrusive
 pub struct BackgroundMonitor {
@@ -72,6 +235,19 @@ pub struct BackgroundMonitor {
     network_interfaces: Arc<RwLock<HashMap<String, NetworkStats>>>,
     measurement_count: Arc<RwLock<u32>>,
     last_hour_reset: Arc<RwLock<DateTime<Utc>>>,
+    shared_state: Option<SharedAppState>,
+    rtt_sampler: Option<RttSampler>,
+    latency_probe: Option<LatencyProbe>,
+    throttling_sensitivity: ThrottlingSensitivityConfig,
+    adaptive_confidence: Option<AdaptiveConfidenceConfig>,
+    vpn_measurements: VpnMeasurementPolicy,
+    ip_lookup: PublicIpLookup,
+    asn_db: Option<Arc<RwLock<AsnDatabase>>>,
+    route_anchors: Vec<String>,
+    events: Option<SharedEventSink>,
+    scheduler: PeriodicScheduler,
+    task: Option<JoinHandle<Result<()>>>,
+    shutdown: Option<CancellationToken>,
 }
 
 impl BackgroundMonitor {
@@ -84,6 +260,19 @@ impl BackgroundMonitor {
             network_interfaces: Arc::new(RwLock::new(HashMap::new())),
             measurement_count: Arc::new(RwLock::new(0)),
             last_hour_reset: Arc::new(RwLock::new(Utc::now())),
+            shared_state: None,
+            rtt_sampler: None,
+            latency_probe: None,
+            throttling_sensitivity: ThrottlingSensitivityConfig::default(),
+            adaptive_confidence: None,
+            vpn_measurements: VpnMeasurementPolicy::default(),
+            ip_lookup: PublicIpLookup::default(),
+            asn_db: None,
+            route_anchors: traceroute::ANCHORS.iter().map(|a| a.to_string()).collect(),
+            events: None,
+            scheduler: PeriodicScheduler::default(),
+            task: None,
+            shutdown: None,
         }
     }
 
@@ -96,9 +285,82 @@ impl BackgroundMonitor {
             network_interfaces: Arc::new(RwLock::new(HashMap::new())),
             measurement_count: Arc::new(RwLock::new(0)),
             last_hour_reset: Arc::new(RwLock::new(Utc::now())),
+            shared_state: None,
+            rtt_sampler: None,
+            latency_probe: None,
+            throttling_sensitivity: ThrottlingSensitivityConfig::default(),
+            adaptive_confidence: None,
+            vpn_measurements: VpnMeasurementPolicy::default(),
+            ip_lookup: PublicIpLookup::default(),
+            asn_db: None,
+            route_anchors: traceroute::ANCHORS.iter().map(|a| a.to_string()).collect(),
+            events: None,
+            scheduler: PeriodicScheduler::default(),
+            task: None,
+            shutdown: None,
         }
     }
     
+    /// Lets the monitor honor the live passive-monitoring module switch
+    pub fn set_shared_state(&mut self, shared_state: SharedAppState) {
+        self.shared_state = Some(shared_state);
+    }
+
+    /// Fills `latency_ms` with the median handshake time of SpeedKarma's own connections
+    pub fn set_rtt_sampler(&mut self, sampler: RttSampler) {
+        self.rtt_sampler = Some(sampler);
+    }
+
+    /// Handshake probes for windows in which none of our own connections measured a round trip
+    pub fn set_latency_probe(&mut self, probe: LatencyProbe) {
+        self.latency_probe = Some(probe);
+    }
+
+    /// Thresholds used by `analyze_throttling_patterns`
+    pub fn set_throttling_sensitivity(&mut self, sensitivity: ThrottlingSensitivityConfig) {
+        self.throttling_sensitivity = sensitivity;
+    }
+
+    /// Replaces the fixed `min_confidence_threshold` with one learned per interface
+    pub fn set_adaptive_confidence(&mut self, adaptive: AdaptiveConfidenceConfig) {
+        self.adaptive_confidence = adaptive.enabled.then_some(adaptive);
+    }
+
+    /// Whether passive samples taken through a VPN are tagged or not stored
+    pub fn set_vpn_measurements(&mut self, policy: VpnMeasurementPolicy) {
+        self.vpn_measurements = policy;
+    }
+
+    /// Services and cache used by public-IP ISP detection
+    pub fn set_ip_lookup(&mut self, lookup: PublicIpLookup) {
+        self.ip_lookup = lookup;
+    }
+
+    /// ASN ranges used to map resolvers and gateway to an ISP; the bundled seed is used without one
+    pub fn set_asn_database(&mut self, asn_db: Arc<RwLock<AsnDatabase>>) {
+        self.asn_db = Some(asn_db);
+    }
+
+    /// Addresses traced for routing-based ISP detection; empty skips it
+    pub fn set_route_anchors(&mut self, anchors: Vec<String>) {
+        self.route_anchors = anchors;
+    }
+
+    /// Receives `interface_change` events when interfaces are plugged in or removed
+    pub fn set_event_sink(&mut self, events: SharedEventSink) {
+        self.events = Some(events);
+    }
+
+    /// Spreads the measurement timer against the app's other periodic loops
+    pub fn set_scheduler(&mut self, scheduler: PeriodicScheduler) {
+        self.scheduler = scheduler;
+    }
+
+    /// Ends the loop after the current measurement once `token` is cancelled
+    pub fn set_shutdown(&mut self, token: CancellationToken) {
+        self.shutdown = Some(token);
+    }
+
     /// Starts passive speed monitoring without running speed tests
     pub async fn start_monitoring(&mut self) -> Result<()> {
         let mut is_running = self.is_running.write().await;
@@ -121,25 +383,50 @@ impl BackgroundMonitor {
         let network_interfaces = Arc::clone(&self.network_interfaces);
         let measurement_count = Arc::clone(&self.measurement_count);
         let last_hour_reset = Arc::clone(&self.last_hour_reset);
+        let shared_state = self.shared_state.clone();
+        let rtt_sampler = self.rtt_sampler.clone();
+        let latency_probe = self.latency_probe.clone();
+        let events = self.events.clone();
+        let vpn_measurements = self.vpn_measurements;
+        let mut calibrator = self.adaptive_confidence.clone().map(|adaptive| ConfidenceCalibrator::new(adaptive, config.min_confidence_threshold));
+        let shutdown_token = self.shutdown.clone();
+        let mut ticker = self.scheduler.register("passive_measurement", StdDuration::from_secs(config.measurement_interval_seconds));
+        let mut analysis_ticker = self.scheduler.register("throttling_analysis", THROTTLING_ANALYSIS_INTERVAL);
+        let mut analyst = BackgroundMonitor::new(Arc::clone(&self.repository));
+        analyst.throttling_sensitivity = self.throttling_sensitivity.clone();
+        analyst.shared_state = self.shared_state.clone();
 
         // Spawn the monitoring task
-        tokio::spawn(async move {
-            let mut interval = interval(StdDuration::from_secs(config.measurement_interval_seconds));
+        self.task = Some(tokio::spawn(async move {
             
             // Initialize network interface baseline
             if let Err(e) = Self::initialize_network_interfaces(&network_interfaces).await {
                 error!("Failed to initialize network interfaces: {}", e);
-                return;
+                *is_running_clone.write().await = false;
+                return Err(e);
             }
 
+            let mut ticks: u64 = 0;
+            let mut current_context: Option<String> = None;
             loop {
                 tokio::select! {
-                    _ = interval.tick() => {
+                    _ = ticker.tick() => {
                         // Check if we should continue running
                         if !*is_running_clone.read().await {
                             break;
                         }
 
+                        // Skip while the passive monitoring module is switched off or the connection is down,
+                        // and measure only on every few ticks while saving power
+                        ticks += 1;
+                        if let Some(shared) = &shared_state {
+                            let shared = shared.read().await;
+                            if !shared.modules.passive_monitoring || shared.connectivity_lost()
+                                || !(ticks - 1).is_multiple_of(shared.measurement_stride() as u64) {
+                                continue;
+                            }
+                        }
+
                         // Reset hourly measurement count if needed
                         Self::reset_hourly_count_if_needed(&measurement_count, &last_hour_reset).await;
 
@@ -149,32 +436,116 @@ impl BackgroundMonitor {
                             continue;
                         }
 
-                        // Perform passive speed measurement
-                        match Self::perform_passive_measurement(&config, &network_interfaces).await {
+                        // Perform passive speed measurement over the selected interfaces
+                        let selection = match &shared_state {
+                            Some(shared) => shared.read().await.monitored_interfaces.clone(),
+                            None => InterfaceSelectionConfig::default(),
+                        };
+                        let measured = Self::perform_passive_measurement(&config, &network_interfaces, &selection).await;
+                        if let Ok((_, change, impossible)) = &measured {
+                            if !change.is_empty() {
+                                info!("Network interfaces changed: added {:?}, removed {:?}, reset {:?}", change.added, change.removed, change.reset);
+                                if let Some(events) = &events {
+                                    events.emit_payload("interface_change", change);
+                                }
+                            }
+                            for reading in impossible {
+                                warn!("Discarded impossible reading on {}: {:.0} Mbps over a {:.0} Mbps ceiling", reading.interface, reading.mbps, reading.ceiling_mbps);
+                                if let Some(events) = &events {
+                                    events.emit_payload("impossible_reading", reading);
+                                }
+                            }
+                        }
+                        match measured.map(|(result, _, _)| result) {
                             Ok(Some(result)) => {
+                                if let (Some(tunnel), VpnMeasurementPolicy::Pause) = (&result.vpn_interface, vpn_measurements) {
+                                    debug!("Traffic goes through VPN tunnel {}, skipping passive measurement", tunnel);
+                                    continue;
+                                }
                                 // Store the measurement if confidence is sufficient
-                                if result.confidence >= config.min_confidence_threshold {
-                                    let measurement = SpeedMeasurement {
+                                let threshold = match &mut calibrator {
+                                    Some(calibrator) => {
+                                        match repository.get_speed_measurements_since(calibrator.active_seen_until()).await {
+                                            Ok(recent) => calibrator.compare_with_active(&recent),
+                                            Err(e) => debug!("Could not load speed tests for confidence calibration: {}", e),
+                                        }
+                                        let interface = result.interface.as_deref().unwrap_or_default();
+                                        calibrator.observe(interface, result.timestamp, result.confidence, result.download_mbps)
+                                    }
+                                    None => config.min_confidence_threshold,
+                                };
+                                if result.confidence >= threshold {
+                                    // Passive counters carry no latency, loss or jitter; probe the anchors for all three
+                                    // and prefer handshake times from our own connections for latency
+                                    let probe = match &latency_probe {
+                                        Some(probe) => probe.measure().await,
+                                        None => ProbeResult::default(),
+                                    };
+                                    let window = StdDuration::from_secs(config.measurement_interval_seconds.max(1));
+                                    let sampled_ms = rtt_sampler.as_ref().and_then(|s| s.take_median_ms(window));
+                                    // Our own connections go through the proxy whenever one is set
+                                    let via_proxy = probe.via_proxy || (sampled_ms.is_some() && proxy::active().is_some());
+                                    let latency_ms = sampled_ms.or(probe.latency_ms);
+                                    let signal = wifi::read_signal().await;
+                                    // A different interface, SSID or gateway starts a new context instead of
+                                    // mixing another network into this one's baseline
+                                    let network = context::current().await;
+                                    let network_context = network.key();
+                                    if let Some(key) = network_context.as_deref().filter(|key| current_context.as_deref() != Some(*key)) {
+                                        if current_context.is_some() {
+                                            info!("Network changed to {}; starting a new measurement context", network.describe());
+                                            if let Some(events) = &events {
+                                                events.emit_payload("network_context_changed", &network);
+                                            }
+                                        } else {
+                                            // History from before contexts were tracked joins the first network seen
+                                            match repository.backfill_network_context(key).await {
+                                                Ok(0) => {}
+                                                Ok(tagged) => info!("Tagged {} earlier measurements with {}", tagged, network.describe()),
+                                                Err(e) => warn!("Failed to tag earlier measurements: {}", e),
+                                            }
+                                        }
+                                        current_context = network_context.clone();
+                                    }
+                                    let mut measurement = SpeedMeasurement {
                                         id: None,
                                         timestamp: result.timestamp,
                                         download_mbps: result.download_mbps,
                                         upload_mbps: result.upload_mbps,
-                                        latency_ms: 0, // Passive monitoring doesn't measure latency
+                                        latency_ms,
                                         optimization_active: false, // This is baseline monitoring
                                         confidence: result.confidence,
+                                        source: MeasurementSource::Passive,
+                                        wifi_rssi_dbm: signal.as_ref().and_then(|s| s.rssi_dbm),
+                                        wifi_link_mbps: signal.as_ref().and_then(|s| s.link_rate_mbps),
+                                        packet_loss_pct: probe.packet_loss_pct,
+                                        jitter_ms: probe.jitter_ms,
+                                        via_vpn: result.vpn_interface.is_some(),
+                                        via_proxy,
+                                        network_context,
+                                        method: MeasurementMethod::Interface,
+                                        upload_measured: true,
                                     };
+                                    // Scale by what speed tests on this network showed the counters to miss
+                                    if let Some(key) = &measurement.network_context {
+                                        match repository.get_passive_calibration(key).await {
+                                            Ok(Some(correction)) => calibration::apply(&correction, &mut measurement),
+                                            Ok(None) => {}
+                                            Err(e) => debug!("Could not load passive calibration: {}", e),
+                                        }
+                                    }
 
                                     if let Err(e) = repository.save_speed_measurement(&measurement).await {
                                         warn!("Failed to save speed measurement: {}", e);
                                     } else {
                                         debug!("Saved passive measurement: {:.2} Mbps down, {:.2} Mbps up (confidence: {:.2})", 
-                                               result.download_mbps, result.upload_mbps, result.confidence);
+                                               measurement.download_mbps, measurement.upload_mbps, measurement.confidence);
                                         
                                         // Increment measurement count
                                         *measurement_count.write().await += 1;
                                     }
                                 } else {
-                                    debug!("Measurement confidence too low ({:.2}), skipping", result.confidence);
+                                    debug!("Measurement confidence too low ({:.2} < {:.2}), skipping", result.confidence, threshold);
                                 }
                             }
                             Ok(None) => {
@@ -185,19 +556,43 @@ impl BackgroundMonitor {
                             }
                         }
                     }
+                    _ = analysis_ticker.tick() => {
+                        match analyst.refresh_throttling_patterns(THROTTLING_ANALYSIS_DAYS).await {
+                            Ok(Some(analysis)) => {
+                                debug!("Throttling analysis found {} slow periods", analysis.patterns.len());
+                                match analyst.explain_current_slowdown(&analysis).await {
+                                    Ok(Some(report)) => info!("Bufferbloat test in a slow period: grade {:?}", report.grade),
+                                    Ok(None) => {}
+                                    Err(e) => debug!("Bufferbloat test in a slow period failed: {}", e),
+                                }
+                            }
+                            Ok(None) => debug!("No ISP profile yet; throttling analysis skipped"),
+                            Err(e) => warn!("Throttling analysis failed: {}", e),
+                        }
+                    }
                     _ = shutdown_rx.recv() => {
                         info!("Received shutdown signal for background monitoring");
                         break;
                     }
+                    _ = shutdown::cancelled(shutdown_token.as_ref()) => break,
                 }
             }
 
             info!("Background monitoring stopped");
             *is_running_clone.write().await = false;
-        });
+            Ok(())
+        }));
 
         Ok(())
     }
+
+    /// Waits for the monitoring loop to end; a failed start or a panic comes back as an error
+    pub async fn wait(&mut self) -> Result<()> {
+        match self.task.take() {
+            Some(task) => task.await.map_err(|e| SpeedKarmaError::SystemError(format!("Monitoring task {}", e)))?,
+            None => Ok(()),
+        }
+    }
     
     /// Stops all monitoring activities
     pub async fn stop_monitoring(&mut self) -> Result<()> {
@@ -260,24 +655,38 @@ impl BackgroundMonitor {
         }
     }
 
-    /// Perform a passive speed measurement by analyzing network interface statistics
+    /// Perform a passive speed measurement by analyzing network interface statistics.
+    /// Interfaces that appeared or reset since the last sample only get a new baseline this round.
+    /// Deltas faster than an interface's negotiated link speed are left out and returned as flagged.
+    /// Interfaces outside `selection` are not counted but still tell whether a VPN carries the traffic.
     async fn perform_passive_measurement(
         _config: &MonitoringConfig,
-        network_interfaces: &Arc<RwLock<HashMap<String, NetworkStats>>>
-    ) -> Result<Option<PassiveSpeedResult>> {
+        network_interfaces: &Arc<RwLock<HashMap<String, NetworkStats>>>,
+        selection: &InterfaceSelectionConfig,
+    ) -> Result<(Option<PassiveSpeedResult>, InterfaceChange, Vec<ImpossibleReading>)> {
         let measurement_start = Instant::now();
         
         // Get current network stats
         let current_stats = Self::get_network_interface_stats().await?;
+        let links = LinkSpeeds::read(current_stats.keys().map(String::as_str)).await;
         
         // Calculate bandwidth usage over the measurement window
         let mut interfaces_guard = network_interfaces.write().await;
+        let change = diff_interfaces(&interfaces_guard, &current_stats);
         let mut total_download_bytes = 0u64;
         let mut total_upload_bytes = 0u64;
         let mut valid_measurements = 0;
         let mut total_time_diff = 0.0;
+        let mut impossible = Vec::new();
+        // Fastest link that contributed; the combined estimate cannot beat it
+        let mut ceiling_mbps: f64 = 0.0;
+        let mut busiest: Option<(&String, u64)> = None;
+        let mut received = Vec::new();
 
         for (interface_name, current_stat) in &current_stats {
+            if change.reset.contains(interface_name) {
+                continue;
+            }
             if let Some(previous_stat) = interfaces_guard.get(interface_name) {
                 let time_diff = measurement_start.duration_since(previous_stat.timestamp).as_secs_f64();
                 
@@ -285,21 +694,34 @@ impl BackgroundMonitor {
                 if time_diff >= 10.0 && time_diff <= 300.0 { // Between 10 seconds and 5 minutes
                     let bytes_received_diff = current_stat.bytes_received.saturating_sub(previous_stat.bytes_received);
                     let bytes_sent_diff = current_stat.bytes_sent.saturating_sub(previous_stat.bytes_sent);
+                    received.push((interface_name.as_str(), bytes_received_diff));
+                    if !selection.selects(interface_name) {
+                        continue;
+                    }
                     
-                    // Filter out unrealistic spikes (more than 1 Gbps)
-                    let max_bytes_per_second = 125_000_000; // 1 Gbps in bytes
-                    let max_bytes_in_window = (max_bytes_per_second as f64 * time_diff) as u64;
-                    
-                    if bytes_received_diff <= max_bytes_in_window && bytes_sent_diff <= max_bytes_in_window {
-                        total_download_bytes += bytes_received_diff;
-                        total_upload_bytes += bytes_sent_diff;
-                        total_time_diff += time_diff;
-                        valid_measurements += 1;
+                    // Filter out readings the link cannot carry
+                    let flagged = links.check(interface_name, bytes_received_diff, time_diff)
+                        .or_else(|| links.check(interface_name, bytes_sent_diff, time_diff));
+                    match flagged {
+                        Some(reading) => impossible.push(reading),
+                        None => {
+                            total_download_bytes += bytes_received_diff;
+                            total_upload_bytes += bytes_sent_diff;
+                            total_time_diff += time_diff;
+                            valid_measurements += 1;
+                            ceiling_mbps = ceiling_mbps.max(links.ceiling_mbps(interface_name));
+                            if busiest.is_none_or(|(_, bytes)| bytes_received_diff > bytes) {
+                                busiest = Some((interface_name, bytes_received_diff));
+                            }
+                        }
                     }
                 }
             }
         }
 
+        let interface = busiest.map(|(name, _)| name.clone());
+        let vpn_interface = vpn_carrier(received);
+
         // Update stored stats for next measurement
         *interfaces_guard = current_stats;
         drop(interfaces_guard);
@@ -308,9 +730,10 @@ impl BackgroundMonitor {
         if valid_measurements > 0 && total_time_diff > 0.0 {
             let avg_time_diff = total_time_diff / valid_measurements as f64;
             
-            // Convert bytes to megabits per second
-            let download_mbps = (total_download_bytes as f64 * 8.0) / (avg_time_diff * 1_000_000.0);
-            let upload_mbps = (total_upload_bytes as f64 * 8.0) / (avg_time_diff * 1_000_000.0);
+            // Convert bytes to megabits per second; bridged interfaces count the same traffic twice,
+            // so the sum is capped at the fastest contributing link
+            let download_mbps = ((total_download_bytes as f64 * 8.0) / (avg_time_diff * 1_000_000.0)).min(ceiling_mbps);
+            let upload_mbps = ((total_upload_bytes as f64 * 8.0) / (avg_time_diff * 1_000_000.0)).min(ceiling_mbps);
             
             // Calculate confidence based on measurement quality
             let confidence = Self::calculate_measurement_confidence(
@@ -320,20 +743,23 @@ impl BackgroundMonitor {
                 upload_mbps
             );
 
-            Ok(Some(PassiveSpeedResult {
+            Ok((Some(PassiveSpeedResult {
                 timestamp: Utc::now(),
                 download_mbps,
                 upload_mbps,
                 confidence,
                 measurement_duration_seconds: avg_time_diff,
-            }))
+                interface,
+                vpn_interface,
+            }), change, impossible))
         } else {
-            Ok(None)
+            Ok((None, change, impossible))
         }
     }
 
     /// Get network interface statistics using system APIs
-    async fn get_network_interface_stats() -> Result<HashMap<String, NetworkStats>> {
+    #[cfg(feature = "sysinfo")]
+    pub(crate) async fn get_network_interface_stats() -> Result<HashMap<String, NetworkStats>> {
         use sysinfo::{System, Networks};
         
         let _system = System::new();
@@ -369,6 +795,19 @@ impl BackgroundMonitor {
         Ok(stats)
     }
 
+    /// Get network interface statistics from the kernel's `/proc/net/dev` counters
+    #[cfg(not(feature = "sysinfo"))]
+    pub(crate) async fn get_network_interface_stats() -> Result<HashMap<String, NetworkStats>> {
+        let content = tokio::fs::read_to_string("/proc/net/dev").await?;
+        let stats = parse_proc_net_dev(&content, Instant::now());
+        if stats.is_empty() {
+            return Err(SpeedKarmaError::SystemError(
+                "No active network interfaces found".to_string()
+            ));
+        }
+        Ok(stats)
+    }
+
     /// Calculate confidence score for a passive measurement
     fn calculate_measurement_confidence(
         valid_measurements: usize,
@@ -439,19 +878,11 @@ impl BackgroundMonitor {
             detection_results.push(result);
         }
         
-        // Combine results and select the most confident one
-        let best_result = detection_results
-            .into_iter()
-            .max_by(|a, b| a.confidence.partial_cmp(&b.confidence).unwrap_or(std::cmp::Ordering::Equal))
-            .unwrap_or_else(|| ISPDetectionResult {
-                isp_name: "Unknown ISP".to_string(),
-                region: "Unknown".to_string(),
-                detection_method: ISPDetectionMethod::Combined.as_str().to_string(),
-                confidence: 0.1,
-                detected_at: Utc::now(),
-            });
+        // Cross-check the methods and keep every candidate for the override UI
+        let best_result = combine_isp_results(detection_results);
         
-        info!("ISP detected: {} (confidence: {:.2})", best_result.isp_name, best_result.confidence);
+        info!("ISP detected: {} (confidence: {:.2}, {} candidates)",
+              best_result.isp_name, best_result.confidence, best_result.candidates.len());
         Ok(best_result)
     }
 
@@ -464,7 +895,8 @@ impl BackgroundMonitor {
         } else {
             Utc::now() - Duration::days(days as i64)
         };
-        let measurements = self.repository.get_speed_measurements_since(since).await?;
+        // Samples taken through a VPN measure the tunnel, not the ISP
+        let measurements = without_vpn(&self.repository.get_speed_measurements_since(since).await?);
         
         if measurements.len() < 10 {
             return Ok(PatternAnalysisResult {
@@ -475,6 +907,8 @@ impl BackgroundMonitor {
                 baseline_speed_mbps: 0.0,
                 throttled_speed_mbps: 0.0,
                 improvement_potential: 0.0,
+                wifi: None,
+                cgnat: false,
             });
         }
         
@@ -482,7 +916,17 @@ impl BackgroundMonitor {
         let mut hourly_speeds: HashMap<(Weekday, u8), Vec<f64>> = HashMap::new();
         let mut all_speeds = Vec::new();
         
-        for measurement in &measurements {
+        // Slowdowns explained by a weak Wi-Fi signal say nothing about the ISP
+        let mut sorted: Vec<f64> = measurements.iter().map(|m| m.download_mbps).collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let overall_median = sorted[sorted.len() / 2];
+        let wifi = WifiAttribution::from_measurements(&measurements, overall_median);
+        let exclude_weak_wifi = wifi.as_ref().is_some_and(|w| w.local_wireless);
+        if exclude_weak_wifi {
+            info!("Slowdowns line up with weak Wi-Fi; leaving those samples out of throttling detection");
+        }
+        
+        for measurement in measurements.iter().filter(|m| !(exclude_weak_wifi && m.on_weak_wifi())) {
             let weekday = measurement.timestamp.weekday();
             let hour = measurement.timestamp.hour() as u8;
             
@@ -495,7 +939,7 @@ impl BackgroundMonitor {
         }
         
         // Calculate baseline speed (median of all measurements)
-        all_speeds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        all_speeds.sort_by(|a, b| a.total_cmp(b));
         let baseline_speed = if all_speeds.is_empty() {
             0.0
         } else {
@@ -503,10 +947,20 @@ impl BackgroundMonitor {
         };
         
         // Detect throttling patterns
-        let patterns = self.detect_throttling_patterns(&hourly_speeds, baseline_speed).await?;
+        let mut patterns = self.detect_throttling_patterns(&hourly_speeds, baseline_speed, &self.throttling_sensitivity).await?;
+        
+        // Slow periods where latency balloons under load are congestion, not throttling
+        let bufferbloat_tests = self.repository.get_bufferbloat_tests_since(since).await?;
+        let cgnat = self.repository.get_current_isp_profile().await?.and_then(|p| p.cgnat).is_some_and(|c| c.detected());
+        for pattern in &mut patterns {
+            pattern.cause = Self::slowdown_cause(pattern, &bufferbloat_tests);
+            if cgnat && pattern.cause == SlowdownCause::Unknown && pattern.severity < CGNAT_CONGESTION_SEVERITY {
+                pattern.cause = SlowdownCause::CgnatCongestion;
+            }
+        }
         
         // Calculate overall throttling metrics
-        let throttling_detected = !patterns.is_empty();
+        let throttling_detected = patterns.iter().any(|p| !matches!(p.cause, SlowdownCause::Congestion | SlowdownCause::CgnatCongestion));
         let confidence = if throttling_detected {
             patterns.iter().map(|p| p.confidence).sum::<f64>() / patterns.len() as f64
         } else {
@@ -550,26 +1004,116 @@ impl BackgroundMonitor {
             baseline_speed_mbps: baseline_speed,
             throttled_speed_mbps: throttled_speed,
             improvement_potential,
+            wifi,
+            cgnat,
+        })
+    }
+
+    /// Measures latency idle and then under load with `SqmAdvisor`, stores the result and grades the
+    /// added delay. The keeper's budgeted download loads the link when given, the SQM check's own
+    /// download otherwise. Stored tests let `analyze_throttling_patterns` tell congestion from throttling.
+    pub async fn run_bufferbloat_test(&self, keeper: Option<&ThroughputKeeper>) -> Result<BufferbloatReport> {
+        let (idle, loaded, load_mbps) = match keeper {
+            Some(keeper) => {
+                let (idle, loaded, load) = SqmAdvisor::measure_under_load(keeper.pull_for(BUFFERBLOAT_LOAD)).await?;
+                (idle, loaded, load?)
+            }
+            None => SqmAdvisor::measure_bufferbloat().await?,
+        };
+        let unreachable = || SpeedKarmaError::NetworkUnavailable("Latency probe host did not answer".to_string());
+        let idle = idle.ok_or_else(unreachable)?;
+        let loaded = loaded.ok_or_else(unreachable)?;
+
+        let mut test = BufferbloatTest {
+            id: None,
+            tested_at: Utc::now(),
+            idle_latency_ms: idle,
+            loaded_latency_ms: loaded,
+            load_mbps,
+        };
+        test.id = Some(self.repository.save_bufferbloat_test(&test).await?);
+        let added_latency_ms = test.added_latency_ms();
+        let grade = BufferbloatGrade::from_added_latency(added_latency_ms);
+        info!("Bufferbloat test: {:.0} ms idle, {:.0} ms loaded at {:?} Mbps, grade {:?}", idle, loaded, load_mbps, grade);
+        Ok(BufferbloatReport { test, added_latency_ms, grade })
+    }
+
+    /// Runs a bufferbloat test when `now`'s hour falls in a slow period no test has explained yet, so
+    /// the next analysis can tell congestion from throttling there. Skipped while the link may not be loaded.
+    pub async fn explain_current_slowdown(&self, analysis: &PatternAnalysisResult) -> Result<Option<BufferbloatReport>> {
+        if !Self::unexplained_slowdown_at(&analysis.patterns, Utc::now()) {
+            return Ok(None);
+        }
+        if let Some(shared) = &self.shared_state {
+            if !shared.read().await.may_load_link() {
+                return Ok(None);
+            }
+        }
+        self.run_bufferbloat_test(None).await.map(Some)
+    }
+
+    fn unexplained_slowdown_at(patterns: &[DetectedThrottlingPattern], now: DateTime<Utc>) -> bool {
+        patterns.iter().any(|p| {
+            p.cause == SlowdownCause::Unknown
+                && p.days_of_week.contains(&now.weekday())
+                && Self::is_hour_in_pattern(now.hour() as u8, p.start_hour, p.end_hour)
         })
     }
 
+    /// Judges a slow period by the median added latency of the bufferbloat tests that fell inside it
+    pub fn slowdown_cause(pattern: &DetectedThrottlingPattern, tests: &[BufferbloatTest]) -> SlowdownCause {
+        let mut added: Vec<f64> = tests
+            .iter()
+            .filter(|t| pattern.days_of_week.contains(&t.tested_at.weekday()))
+            .filter(|t| Self::is_hour_in_pattern(t.tested_at.hour() as u8, pattern.start_hour, pattern.end_hour))
+            .map(BufferbloatTest::added_latency_ms)
+            .collect();
+        if added.is_empty() {
+            return SlowdownCause::Unknown;
+        }
+        added.sort_by(|a, b| a.total_cmp(b));
+        if added[added.len() / 2] >= CONGESTION_ADDED_LATENCY_MS { SlowdownCause::Congestion } else { SlowdownCause::Throttling }
+    }
+
+    /// Checks for carrier-grade NAT using the public address, the local interface address and the
+    /// route snapshots `detect_isp` just traced
+    pub async fn detect_cgnat(&self) -> Result<CgnatReport> {
+        let public_ip = self.ip_lookup.lookup().await.ok().and_then(|info| info.ip.parse().ok());
+        let snapshots = self.repository.get_route_snapshots_since(Utc::now() - Duration::hours(1)).await?;
+        let report = cgnat::assess(public_ip, cgnat::local_ip(), &snapshots);
+        if report.detected() {
+            info!("Connection is behind carrier-grade NAT (shared {:?}, private upstream {:?})", report.shared_addresses, report.private_upstream_hops);
+        }
+        Ok(report)
+    }
+
     /// Save detected ISP profile to database
     pub async fn save_isp_profile(&self, detection_result: &ISPDetectionResult) -> Result<i64> {
-        let profile = ISPProfile::new(
-            detection_result.isp_name.clone(),
-            detection_result.region.clone(),
-            detection_result.detection_method.clone(),
-        );
+        let candidates = detection_result.candidates.iter().map(|c| IspCandidate {
+            isp_name: c.isp_name.clone(),
+            region: c.region.clone(),
+            detection_method: c.detection_method.clone(),
+            confidence: c.confidence,
+            asn: c.asn,
+            organization: c.organization.clone(),
+        }).collect();
+        let profile = ISPProfile {
+            candidates,
+            ..ISPProfile::new(
+                detection_result.isp_name.clone(),
+                detection_result.region.clone(),
+                detection_result.detection_method.clone(),
+            )
+        };
         
         self.repository.save_isp_profile(&profile).await
     }
 
-    /// Save detected throttling patterns to database
+    /// Save detected throttling patterns to database, replacing the ISP's previous ones
     pub async fn save_throttling_patterns(&self, isp_profile_id: i64, patterns: &[DetectedThrottlingPattern]) -> Result<Vec<i64>> {
-        let mut pattern_ids = Vec::new();
-        
-        for pattern in patterns {
-            let throttling_pattern = ThrottlingPattern::new(
+        let rows: Vec<ThrottlingPattern> = patterns.iter().map(|pattern| ThrottlingPattern {
+            confidence: pattern.confidence,
+            ..ThrottlingPattern::new(
                 isp_profile_id,
                 pattern.start_hour,
                 pattern.start_minute,
@@ -577,74 +1121,105 @@ impl BackgroundMonitor {
                 pattern.end_minute,
                 pattern.days_of_week.clone(),
                 pattern.severity,
-            );
-            
-            let id = self.repository.save_throttling_pattern(&throttling_pattern).await?;
-            pattern_ids.push(id);
-        }
-        
-        Ok(pattern_ids)
+            )
+        }).collect();
+        self.repository.replace_throttling_patterns(isp_profile_id, &rows).await
+    }
+
+    /// Runs the throttling analysis and stores the slowdowns it blames on the ISP under the current
+    /// profile; congestion windows are left out. Returns the analysis, or `None` before any ISP is known.
+    pub async fn refresh_throttling_patterns(&self, days: u32) -> Result<Option<PatternAnalysisResult>> {
+        let Some(profile_id) = self.repository.get_current_isp_profile().await?.and_then(|p| p.id) else {
+            return Ok(None);
+        };
+        let analysis = self.analyze_throttling_patterns(days).await?;
+        let throttling: Vec<DetectedThrottlingPattern> = analysis.patterns.iter()
+            .filter(|p| !matches!(p.cause, SlowdownCause::Congestion | SlowdownCause::CgnatCongestion))
+            .cloned()
+            .collect();
+        self.save_throttling_patterns(profile_id, &throttling).await?;
+        Ok(Some(analysis))
     }
 
-    /// Detect ISP via DNS analysis
+    /// Detect ISP via DNS analysis: reverse names and ASNs of the configured resolvers and default gateway
     async fn detect_isp_via_dns(&self) -> Result<ISPDetectionResult> {
-        // Simplified DNS-based ISP detection
-        // In a real implementation, this would analyze DNS servers and routing
-        
-        // For demo purposes, we'll simulate detection based on common patterns
-        let known_isps = vec![
-            ("Hutch", "Sri Lanka", 0.8),
-            ("Dialog", "Sri Lanka", 0.7),
-            ("Mobitel", "Sri Lanka", 0.6),
-            ("SLT", "Sri Lanka", 0.7),
-            ("Airtel", "Sri Lanka", 0.6),
-        ];
-        
-        // Simulate DNS lookup delay
-        tokio::time::sleep(StdDuration::from_millis(100)).await;
-        
-        // For demo, randomly select an ISP (in real implementation, this would be actual DNS analysis)
-        let (isp_name, region, confidence) = known_isps[0]; // Default to Hutch for demo
-        
+        let observations = resolvers::observe().await;
+        debug!("DNS analysis looked at {} resolvers/gateway", observations.len());
+        self.isp_from_resolvers(&observations).await
+    }
+
+    /// DNS analysis verdict for already observed resolvers and gateway
+    async fn isp_from_resolvers(&self, observations: &[resolvers::HostObservation]) -> Result<ISPDetectionResult> {
+        let verdict = match &self.asn_db {
+            Some(db) => resolvers::isp_from_observations(observations, &*db.read().await),
+            None => resolvers::isp_from_observations(observations, &AsnDatabase::bundled()),
+        }
+        .ok_or_else(|| SpeedKarmaError::NetworkUnavailable("Resolvers and gateway do not point at an ISP".to_string()))?;
+
         Ok(ISPDetectionResult {
-            isp_name: isp_name.to_string(),
-            region: region.to_string(),
+            isp_name: verdict.isp_name,
+            region: verdict.region,
             detection_method: ISPDetectionMethod::DnsAnalysis.as_str().to_string(),
-            confidence,
+            confidence: verdict.confidence,
             detected_at: Utc::now(),
+            candidates: Vec::new(),
+            asn: verdict.asn,
+            organization: verdict.organization,
         })
     }
 
-    /// Detect ISP via public IP lookup
+    /// Detect ISP via public IP lookup (ip-api.com / ipinfo.io fallback chain, cached)
     async fn detect_isp_via_public_ip(&self) -> Result<ISPDetectionResult> {
-        // Simplified public IP-based ISP detection
-        // In a real implementation, this would query IP geolocation services
-        
-        tokio::time::sleep(StdDuration::from_millis(200)).await;
-        
-        // For demo purposes, simulate a detection result
+        let info = self.ip_lookup.lookup().await?;
+        // An AS number means the service matched the address to a registered network
+        let confidence = if info.asn.is_some() { 0.9 } else { 0.75 };
         Ok(ISPDetectionResult {
-            isp_name: "Hutch".to_string(),
-            region: "Sri Lanka".to_string(),
+            isp_name: info.isp_name,
+            region: info.region(),
             detection_method: ISPDetectionMethod::PublicIPLookup.as_str().to_string(),
-            confidence: 0.9,
+            confidence,
             detected_at: Utc::now(),
+            candidates: Vec::new(),
+            asn: info.asn,
+            organization: Some(info.organization),
         })
     }
 
-    /// Detect ISP via network routing analysis
+    /// Detect ISP via traceroutes to a few anchors; the snapshots are kept for path-change analysis
     async fn detect_isp_via_routing(&self) -> Result<ISPDetectionResult> {
-        // Simplified routing-based ISP detection
-        // In a real implementation, this would analyze traceroute data
-        
-        tokio::time::sleep(StdDuration::from_millis(150)).await;
-        
+        let snapshots = match &self.asn_db {
+            Some(db) => traceroute::trace_anchors(&self.route_anchors, &*db.read().await).await,
+            None => traceroute::trace_anchors(&self.route_anchors, &AsnDatabase::bundled()).await,
+        };
+        debug!("Routing analysis traced {} anchors", snapshots.len());
+        self.isp_from_routes(&snapshots).await
+    }
+
+    /// Routing verdict for traced snapshots, which are kept for path-change analysis
+    async fn isp_from_routes(&self, snapshots: &[RouteSnapshot]) -> Result<ISPDetectionResult> {
+        let bundled;
+        let guard;
+        let asn_db: &AsnDatabase = match &self.asn_db {
+            Some(db) => { guard = db.read().await; &guard }
+            None => { bundled = AsnDatabase::bundled(); &bundled }
+        };
+        for snapshot in &snapshots {
+            if let Err(e) = self.repository.save_route_snapshot(snapshot).await {
+                warn!("Failed to save route snapshot: {}", e);
+            }
+        }
+        let verdict = traceroute::isp_from_routes(snapshots, asn_db)
+            .ok_or_else(|| SpeedKarmaError::NetworkUnavailable("Traced routes do not enter a known network".to_string()))?;
+
         Ok(ISPDetectionResult {
-            isp_name: "Hutch".to_string(),
-            region: "Sri Lanka".to_string(),
+            isp_name: verdict.isp_name,
+            region: verdict.region,
             detection_method: ISPDetectionMethod::NetworkRouting.as_str().to_string(),
-            confidence: 0.7,
+            confidence: verdict.confidence,
             detected_at: Utc::now(),
+            candidates: Vec::new(),
+            asn: Some(verdict.asn),
+            organization: verdict.organization,
         })
     }
 
@@ -653,9 +1228,15 @@ impl BackgroundMonitor {
         &self,
         hourly_speeds: &HashMap<(Weekday, u8), Vec<f64>>,
         baseline_speed: f64,
+        sensitivity: &ThrottlingSensitivityConfig,
     ) -> Result<Vec<DetectedThrottlingPattern>> {
         let mut patterns = Vec::new();
-        let throttling_threshold = baseline_speed * 0.7; // 30% reduction indicates throttling
+        // An hour this far below the baseline indicates throttling (30% by default)
+        let throttling_threshold = baseline_speed * sensitivity.throttled_ratio();
+        let min_samples = sensitivity.min_samples.max(1) as usize;
+        let long_enough = |p: &DetectedThrottlingPattern| {
+            p.confidence > 0.5 && p.end_hour.saturating_sub(p.start_hour) + 1 >= sensitivity.min_duration_hours
+        };
         
         // Group consecutive hours with similar throttling behavior
         let mut current_pattern: Option<DetectedThrottlingPattern> = None;
@@ -664,8 +1245,8 @@ impl BackgroundMonitor {
         for weekday in [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun] {
             for hour in 0..24 {
                 if let Some(speeds) = hourly_speeds.get(&(weekday, hour)) {
-                    if speeds.len() < 3 {
-                        continue; // Need at least 3 measurements for confidence
+                    if speeds.len() < min_samples {
+                        continue; // Need enough measurements for confidence
                     }
                     
                     let avg_speed = speeds.iter().sum::<f64>() / speeds.len() as f64;
@@ -687,7 +1268,7 @@ impl BackgroundMonitor {
                             _ => {
                                 // Start new pattern or save current one
                                 if let Some(pattern) = current_pattern.take() {
-                                    if pattern.confidence > 0.5 {
+                                    if long_enough(&pattern) {
                                         patterns.push(pattern);
                                     }
                                 }
@@ -701,12 +1282,13 @@ impl BackgroundMonitor {
                                     severity,
                                     confidence,
                                     sample_count: speeds.len() as u32,
+                                    cause: SlowdownCause::Unknown,
                                 });
                             }
                         }
                     } else if let Some(pattern) = current_pattern.take() {
                         // End current pattern
-                        if pattern.confidence > 0.5 {
+                        if long_enough(&pattern) {
                             patterns.push(pattern);
                         }
                     }
@@ -716,7 +1298,7 @@ impl BackgroundMonitor {
         
         // Don't forget the last pattern
         if let Some(pattern) = current_pattern {
-            if pattern.confidence > 0.5 {
+            if long_enough(&pattern) {
                 patterns.push(pattern);
             }
         }
@@ -798,6 +1380,86 @@ pub struct ISPDetectionResult {
     pub detection_method: String,
     pub confidence: f64,
     pub detected_at: DateTime<Utc>,
+    /// Per-method results behind a combined verdict, for manual override
+    #[serde(default)]
+    pub candidates: Vec<ISPDetectionResult>,
+    /// Autonomous system number, when the method knows it
+    #[serde(default)]
+    pub asn: Option<u32>,
+    /// Organisation or AS name behind the connection
+    #[serde(default)]
+    pub organization: Option<String>,
+}
+
+/// How much a method's own confidence can be trusted when methods are cross-checked
+fn method_reliability(detection_method: &str) -> f64 {
+    match detection_method {
+        m if m == ISPDetectionMethod::PublicIPLookup.as_str() => 1.0,
+        m if m == ISPDetectionMethod::NetworkRouting.as_str() => 0.8,
+        m if m == ISPDetectionMethod::DnsAnalysis.as_str() => 0.6,
+        _ => 0.5,
+    }
+}
+
+/// Weighted consensus over per-method ISP results. Agreeing methods reinforce each other
+/// (noisy-OR of reliability-weighted confidences), and the winner is scaled by its share of
+/// all evidence, so disagreement lowers the final confidence.
+pub fn combine_isp_results(results: Vec<ISPDetectionResult>) -> ISPDetectionResult {
+    let evidence = |r: &ISPDetectionResult| r.confidence.clamp(0.0, 1.0) * method_reliability(&r.detection_method);
+
+    let mut groups: Vec<(String, Vec<&ISPDetectionResult>)> = Vec::new();
+    for result in &results {
+        let key = result.isp_name.trim().to_lowercase();
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, members)) => members.push(result),
+            None => groups.push((key, vec![result])),
+        }
+    }
+
+    let support: Vec<f64> = groups.iter().map(|(_, members)| members.iter().map(|r| evidence(r)).sum()).collect();
+    let total: f64 = support.iter().sum();
+    let winner = (0..groups.len())
+        .max_by(|&a, &b| support[a].total_cmp(&support[b]))
+        .filter(|_| total > 0.0);
+
+    let (isp_name, region, detection_method, confidence, asn, organization) = match winner {
+        Some(i) => {
+            let members = &groups[i].1;
+            let agreement = 1.0 - members.iter().map(|r| 1.0 - evidence(r)).product::<f64>();
+            let lead = members
+                .iter()
+                .max_by(|a, b| evidence(a).total_cmp(&evidence(b)))
+                .expect("groups are never empty");
+            let method = if members.len() > 1 {
+                ISPDetectionMethod::Combined.as_str().to_string()
+            } else {
+                lead.detection_method.clone()
+            };
+            // Network identity comes from whichever agreeing method knows it
+            let asn = members.iter().find_map(|r| r.asn);
+            let organization = members.iter().find_map(|r| r.organization.clone());
+            (lead.isp_name.trim().to_string(), lead.region.clone(), method, agreement * support[i] / total, asn, organization)
+        }
+        None => (
+            "Unknown ISP".to_string(),
+            "Unknown".to_string(),
+            ISPDetectionMethod::Combined.as_str().to_string(),
+            0.1,
+            None,
+            None,
+        ),
+    };
+
+    ISPDetectionResult {
+        isp_name,
+        region,
+        detection_method,
+        confidence,
+        detected_at: Utc::now(),
+        candidates: results,
+        asn,
+        organization,
+    }
 }
 
 /// Pattern analysis result for throttling detection
@@ -810,6 +1472,12 @@ pub struct PatternAnalysisResult {
     pub baseline_speed_mbps: f64,
     pub throttled_speed_mbps: f64,
     pub improvement_potential: f64,
+    /// How slowdowns relate to the Wi-Fi signal; `None` on wired links
+    #[serde(default)]
+    pub wifi: Option<WifiAttribution>,
+    /// The ISP shares this connection's public address behind carrier-grade NAT
+    #[serde(default)]
+    pub cgnat: bool,
 }
 
 /// A detected throttling pattern
@@ -823,6 +1491,31 @@ pub struct DetectedThrottlingPattern {
     pub severity: f64,
     pub confidence: f64,
     pub sample_count: u32,
+    #[serde(default)]
+    pub cause: SlowdownCause,
+}
+
+/// What bufferbloat tests taken during a slow period say about it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowdownCause {
+    /// No bufferbloat test ran during the period
+    #[default]
+    Unknown,
+    /// Latency balloons under load: the link is full, typically shared capacity at peak time
+    Congestion,
+    /// Latency stays flat under load while speed is capped, the mark of a shaper or policer
+    Throttling,
+    /// Untested shallow dip behind carrier-grade NAT: likely the shared NAT gateway at peak time
+    CgnatCongestion,
+}
+
+/// Outcome of one bufferbloat test
+#[derive(Debug, Clone, Serialize)]
+pub struct BufferbloatReport {
+    pub test: BufferbloatTest,
+    pub added_latency_ms: f64,
+    pub grade: BufferbloatGrade,
 }
 
 /// ISP detection methods
@@ -981,6 +1674,16 @@ mod tests {
         assert!(confidence <= 0.4, "Should have low confidence for poor measurement, got: {}", confidence);
     }
 
+    #[test]
+    fn test_vpn_carrier_needs_most_of_the_traffic_on_a_tunnel() {
+        assert_eq!(vpn_carrier([("en0", 10_000_000), ("utun4", 9_500_000)]), Some("utun4".to_string()));
+        assert_eq!(vpn_carrier([("eth0", 10_000_000), ("wg0", 2_000_000), ("tun0", 6_000_000)]), Some("tun0".to_string()));
+        // Split tunnel and idle system tunnels
+        assert_eq!(vpn_carrier([("eth0", 10_000_000), ("wg0", 1_000_000)]), None);
+        assert_eq!(vpn_carrier([("utun0", 2_000), ("en0", 0)]), None);
+        assert_eq!(vpn_carrier([("wlan0", 5_000_000)]), None);
+    }
+
     #[test]
     fn test_passive_speed_result_serialization() {
         let result = PassiveSpeedResult {
@@ -989,6 +1692,8 @@ mod tests {
             upload_mbps: 10.2,
             confidence: 0.85,
             measurement_duration_seconds: 60.0,
+            interface: Some("eth0".to_string()),
+            vpn_interface: None,
         };
         
         // Test serialization
@@ -1014,6 +1719,43 @@ mod tests {
         assert_eq!(config.max_measurements_per_hour, 60);
     }
 
+    #[test]
+    fn test_parse_proc_net_dev() {
+        let content = "Inter-|   Receive                                                |  Transmit\n \
+            face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n \
+               lo:  123456     100    0    0    0     0          0         0   123456     100    0    0    0     0       0          0\n \
+             eth0: 9876543    7000    0    2    0     0          0        12  1234567    5000    0    0    0     0       0          0\n\
+            br-lan:1000 10 0 0 0 0 0 0 2000 20 0 0 0 0 0 0\n";
+        let stats = parse_proc_net_dev(content, Instant::now());
+        assert_eq!(stats.len(), 2);
+        let eth0 = &stats["eth0"];
+        assert_eq!(eth0.bytes_received, 9_876_543);
+        assert_eq!(eth0.packets_received, 7000);
+        assert_eq!(eth0.bytes_sent, 1_234_567);
+        assert_eq!(eth0.packets_sent, 5000);
+        assert_eq!(stats["br-lan"].bytes_sent, 2000);
+
+        // The LAN bridge repeats WAN traffic and is left out by default
+        let listed = describe_interfaces(&stats, &InterfaceSelectionConfig::default());
+        assert_eq!(listed.iter().map(|i| (i.name.as_str(), i.selected)).collect::<Vec<_>>(), vec![("br-lan", false), ("eth0", true)]);
+    }
+
+    #[test]
+    fn test_diff_interfaces() {
+        let stat = |rx: u64, tx: u64| NetworkStats { bytes_received: rx, bytes_sent: tx, ..NetworkStats::default() };
+        let previous: HashMap<String, NetworkStats> =
+            [("eth0".to_string(), stat(5000, 800)), ("usb0".to_string(), stat(9000, 900)), ("wlan0".to_string(), stat(100, 100))].into();
+        // USB tethering replugged under the same name, docking Ethernet unplugged, a new Wi-Fi adapter
+        let current: HashMap<String, NetworkStats> =
+            [("usb0".to_string(), stat(300, 40)), ("wlan0".to_string(), stat(200, 150)), ("wlan1".to_string(), stat(10, 10))].into();
+
+        let change = diff_interfaces(&previous, &current);
+        assert_eq!(change.added, vec!["wlan1"]);
+        assert_eq!(change.removed, vec!["eth0"]);
+        assert_eq!(change.reset, vec!["usb0"]);
+        assert!(diff_interfaces(&current, &current).is_empty());
+    }
+
     #[test]
     fn test_network_stats_default() {
         let stats = NetworkStats::default();
@@ -1075,9 +1817,17 @@ mod tests {
     #[tokio::test]
     async fn test_isp_detection() {
         let repository = setup_test_repository().await;
-        let monitor = BackgroundMonitor::new(repository);
+        let mut monitor = BackgroundMonitor::new(repository);
+        // Answer from a cached lookup and trace nothing, so the test needs no network tools
+        let cache = std::env::temp_dir().join(format!("speedkarma-public-ip-{}.json", uuid::Uuid::new_v4()));
+        let lookup = PublicIpLookup::default().with_cache_path(Some(cache.clone()));
+        let info = crate::network::ip_lookup::parse_ipinfo(&serde_json::json!({ "ip": "203.0.113.9", "country": "LK", "org": "AS9506 Hutchison Telecommunications Lanka" })).unwrap();
+        lookup.store(&info).await.unwrap();
+        monitor.set_ip_lookup(lookup);
+        monitor.set_route_anchors(Vec::new());
         
         let result = monitor.detect_isp().await.unwrap();
+        let _ = std::fs::remove_file(&cache);
         
         assert!(!result.isp_name.is_empty());
         assert!(!result.region.is_empty());
@@ -1089,22 +1839,56 @@ mod tests {
     #[tokio::test]
     async fn test_isp_detection_methods() {
         let repository = setup_test_repository().await;
-        let monitor = BackgroundMonitor::new(repository);
-        
-        // Test DNS detection
-        let dns_result = monitor.detect_isp_via_dns().await.unwrap();
+        let mut monitor = BackgroundMonitor::new(repository);
+        let dialog = AsnDatabase::from_records(vec![crate::network::asn_db::AsnRecord {
+            range_start: u32::from(std::net::Ipv4Addr::new(203, 0, 113, 0)),
+            range_end: u32::from(std::net::Ipv4Addr::new(203, 0, 113, 255)),
+            asn: 18001,
+            country: "LK".into(),
+            description: "DIALOG-AS Dialog Axiata PLC.".into(),
+        }]);
+        monitor.set_asn_database(Arc::new(RwLock::new(dialog.clone())));
+        // A fresh cached lookup answers without reaching the services
+        let cache = std::env::temp_dir().join(format!("speedkarma-public-ip-{}.json", uuid::Uuid::new_v4()));
+        let lookup = PublicIpLookup::default().with_cache_path(Some(cache.clone()));
+        let info = crate::network::ip_lookup::parse_ipinfo(&serde_json::json!({ "ip": "203.0.113.9", "country": "LK", "org": "AS9506 Hutchison Telecommunications Lanka" })).unwrap();
+        lookup.store(&info).await.unwrap();
+        monitor.set_ip_lookup(lookup);
+        
+        // Test DNS detection on observed resolvers, so the result does not depend on this machine's network
+        let resolver = resolvers::HostObservation {
+            address: "203.0.113.53".parse().unwrap(),
+            role: resolvers::HostRole::Resolver,
+            reverse_name: Some("dns1.dialog.lk".to_string()),
+        };
+        let dns_result = monitor.isp_from_resolvers(&[resolver]).await.unwrap();
         assert_eq!(dns_result.detection_method, "DNS Analysis");
         assert!(dns_result.confidence > 0.0);
+        assert_eq!((dns_result.isp_name.as_str(), dns_result.region.as_str(), dns_result.asn), ("Dialog", "LK", Some(18001)));
+        assert!(monitor.isp_from_resolvers(&[]).await.is_err());
         
         // Test public IP detection
         let ip_result = monitor.detect_isp_via_public_ip().await.unwrap();
         assert_eq!(ip_result.detection_method, "Public IP Lookup");
         assert!(ip_result.confidence > 0.0);
-        
-        // Test routing detection
-        let routing_result = monitor.detect_isp_via_routing().await.unwrap();
+        assert_eq!((ip_result.isp_name.as_str(), ip_result.asn), ("Hutch", Some(9506)));
+        let _ = std::fs::remove_file(&cache);
+        
+        // Test routing detection on a traced path entering the known network
+        let mut hops = traceroute::parse_traceroute(" 1  192.168.1.1  1.0 ms\n 2  203.0.113.1  5.0 ms\n 3  1.1.1.1  9.0 ms\n");
+        traceroute::annotate(&mut hops, &dialog);
+        let snapshot = traceroute::snapshot("1.1.1.1", hops.clone(), &dialog);
+        let routing_result = monitor.isp_from_routes(&[snapshot]).await.unwrap();
         assert_eq!(routing_result.detection_method, "Network Routing");
         assert!(routing_result.confidence > 0.0);
+        assert_eq!((routing_result.isp_name.as_str(), routing_result.asn), ("Dialog", Some(18001)));
+        let saved = monitor.repository.get_route_snapshots_since(Utc::now() - Duration::hours(1)).await.unwrap();
+        assert_eq!(saved.len(), 1, "traced routes are kept for path-change analysis");
+        // A path that never leaves the home network names no ISP
+        let unknown = traceroute::snapshot("1.1.1.1", hops[..1].to_vec(), &dialog);
+        assert!(monitor.isp_from_routes(&[unknown]).await.is_err());
+        monitor.set_route_anchors(Vec::new());
+        assert!(monitor.detect_isp_via_routing().await.is_err());
     }
 
     #[tokio::test]
@@ -1112,21 +1896,19 @@ mod tests {
         let repository = setup_test_repository().await;
         let monitor = BackgroundMonitor::new(repository.clone());
         
-        let detection_result = ISPDetectionResult {
-            isp_name: "Test ISP".to_string(),
-            region: "Test Region".to_string(),
-            detection_method: "Test Method".to_string(),
-            confidence: 0.8,
-            detected_at: Utc::now(),
-        };
+        let detection_result = combine_isp_results(vec![
+            candidate("Test ISP", ISPDetectionMethod::PublicIPLookup, 0.8),
+            candidate("Other ISP", ISPDetectionMethod::DnsAnalysis, 0.5),
+        ]);
         
         let profile_id = monitor.save_isp_profile(&detection_result).await.unwrap();
         assert!(profile_id > 0);
         
-        // Verify the profile was saved
-        let saved_profile = repository.get_current_isp_profile().await.unwrap();
-        assert!(saved_profile.is_some());
-        assert_eq!(saved_profile.unwrap().name, "Test ISP");
+        // Verify the profile was saved with every method's verdict
+        let saved_profile = repository.get_current_isp_profile().await.unwrap().unwrap();
+        assert_eq!(saved_profile.name, "Test ISP");
+        let candidates: Vec<_> = saved_profile.candidates.iter().map(|c| c.isp_name.as_str()).collect();
+        assert_eq!(candidates, vec!["Test ISP", "Other ISP"]);
     }
 
     #[tokio::test]
@@ -1159,6 +1941,105 @@ mod tests {
         assert_eq!(result.improvement_potential, 0.0);
     }
 
+    #[tokio::test]
+    async fn test_periodic_analysis_stores_throttling_patterns() {
+        let repository = setup_test_repository().await;
+        let monitor = BackgroundMonitor::new(Arc::clone(&repository));
+        // Without an ISP profile there is nothing to file the patterns under
+        assert!(monitor.refresh_throttling_patterns(14).await.unwrap().is_none());
+
+        let profile_id = repository.save_isp_profile(&ISPProfile::new("Test ISP".into(), "LK".into(), "Public IP Lookup".into())).await.unwrap();
+        // One day at 10 Mbps with a 70% drop from 20:00 to 20:59
+        let day = (Utc::now() - Duration::days(2)).date_naive();
+        for hour in 12..22 {
+            for minute in (0..60).step_by(6) {
+                let mut measurement = SpeedMeasurement::new(if hour == 20 { 3.0 } else { 10.0 }, 5.0, 20, false);
+                measurement.timestamp = day.and_hms_opt(hour, minute, 0).unwrap().and_utc();
+                repository.save_speed_measurement(&measurement).await.unwrap();
+            }
+        }
+
+        // A second run replaces the first run's rows instead of adding to them
+        monitor.refresh_throttling_patterns(14).await.unwrap().unwrap();
+        let analysis = monitor.refresh_throttling_patterns(14).await.unwrap().unwrap();
+        assert!(analysis.throttling_detected);
+        let stored = repository.get_throttling_patterns_for_isp(profile_id).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!((stored[0].start_hour, stored[0].end_hour), (20, 20));
+        assert_eq!(stored[0].days_of_week, vec![day.weekday()]);
+        assert!((stored[0].severity - 0.7).abs() < 1e-9);
+        assert_eq!(stored[0].confidence, analysis.patterns[0].confidence);
+    }
+
+    #[tokio::test]
+    async fn test_throttling_sensitivity_presets() {
+        use crate::core::config::SensitivityPreset;
+        let monitor = BackgroundMonitor::new(setup_test_repository().await);
+        // Baseline 10 Mbps: a 25% evening dip (19-20h) and a 45% one-hour dip at 8h
+        let hourly = HashMap::from([
+            ((Weekday::Mon, 8), vec![5.5; 10]),
+            ((Weekday::Mon, 19), vec![7.5; 10]),
+            ((Weekday::Mon, 20), vec![7.5; 10]),
+        ]);
+        let detect = |preset| {
+            let sensitivity = ThrottlingSensitivityConfig::preset(preset);
+            let monitor = &monitor;
+            let hourly = &hourly;
+            async move {
+                let mut starts: Vec<u8> = monitor.detect_throttling_patterns(hourly, 10.0, &sensitivity).await.unwrap()
+                    .iter().map(|p| p.start_hour).collect();
+                starts.sort_unstable();
+                starts
+            }
+        };
+
+        assert_eq!(detect(SensitivityPreset::Default).await, vec![8]);
+        assert_eq!(detect(SensitivityPreset::Sensitive).await, vec![8, 19]);
+        // One throttled hour is too short for the conservative preset
+        assert!(detect(SensitivityPreset::Conservative).await.is_empty());
+
+        let strict_samples = ThrottlingSensitivityConfig { min_samples: 11, ..ThrottlingSensitivityConfig::preset(SensitivityPreset::Sensitive) };
+        assert!(monitor.detect_throttling_patterns(&hourly, 10.0, &strict_samples).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_slowdown_cause_from_bufferbloat() {
+        let repository = setup_test_repository().await;
+        // Monday 19:00-21:59 slow period; one test inside it bloated, one outside it flat
+        let monday_evening = DateTime::parse_from_rfc3339("2024-03-04T20:15:00Z").unwrap().with_timezone(&Utc);
+        let test = |at: DateTime<Utc>, loaded: f64| BufferbloatTest { id: None, tested_at: at, idle_latency_ms: 20.0, loaded_latency_ms: loaded, load_mbps: Some(40.0) };
+        repository.save_bufferbloat_test(&test(monday_evening, 240.0)).await.unwrap();
+        repository.save_bufferbloat_test(&test(monday_evening - Duration::hours(10), 25.0)).await.unwrap();
+        let tests = repository.get_bufferbloat_tests_since(monday_evening - Duration::days(1)).await.unwrap();
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests[1].added_latency_ms(), 220.0);
+
+        let mut pattern = DetectedThrottlingPattern {
+            start_hour: 19,
+            start_minute: 0,
+            end_hour: 21,
+            end_minute: 59,
+            days_of_week: vec![Weekday::Mon],
+            severity: 0.5,
+            confidence: 0.8,
+            sample_count: 10,
+            cause: SlowdownCause::Unknown,
+        };
+        assert_eq!(BackgroundMonitor::slowdown_cause(&pattern, &tests), SlowdownCause::Congestion);
+        pattern.start_hour = 9;
+        pattern.end_hour = 11;
+        assert_eq!(BackgroundMonitor::slowdown_cause(&pattern, &tests), SlowdownCause::Throttling);
+        pattern.days_of_week = vec![Weekday::Tue];
+        assert_eq!(BackgroundMonitor::slowdown_cause(&pattern, &tests), SlowdownCause::Unknown);
+
+        // An untested slow period asks for a test while it is on, and only then
+        let tuesday_morning = monday_evening + Duration::hours(14);
+        assert!(BackgroundMonitor::unexplained_slowdown_at(&[pattern.clone()], tuesday_morning));
+        assert!(!BackgroundMonitor::unexplained_slowdown_at(&[pattern.clone()], monday_evening));
+        pattern.cause = SlowdownCause::Throttling;
+        assert!(!BackgroundMonitor::unexplained_slowdown_at(&[pattern], tuesday_morning));
+    }
+
     #[test]
     fn test_pattern_confidence_calculation() {
         // Test high confidence (many samples, high severity)
@@ -1204,6 +2085,7 @@ mod tests {
                 severity: 0.7,
                 confidence: 0.8,
                 sample_count: 10,
+                cause: SlowdownCause::Unknown,
             },
             DetectedThrottlingPattern {
                 start_hour: 19,
@@ -1214,6 +2096,7 @@ mod tests {
                 severity: 0.75,
                 confidence: 0.85,
                 sample_count: 12,
+                cause: SlowdownCause::Unknown,
             },
         ];
         
@@ -1226,6 +2109,53 @@ mod tests {
         assert_eq!(merged[0].sample_count, 22);
     }
 
+    fn candidate(isp_name: &str, method: ISPDetectionMethod, confidence: f64) -> ISPDetectionResult {
+        ISPDetectionResult {
+            isp_name: isp_name.to_string(),
+            region: "Sri Lanka".to_string(),
+            detection_method: method.as_str().to_string(),
+            confidence,
+            detected_at: Utc::now(),
+            candidates: Vec::new(),
+            asn: None,
+            organization: None,
+        }
+    }
+
+    #[test]
+    fn test_isp_consensus_rewards_agreement_and_penalizes_conflict() {
+        let agreeing = combine_isp_results(vec![
+            candidate("hutch ", ISPDetectionMethod::DnsAnalysis, 0.8),
+            candidate("Hutch", ISPDetectionMethod::PublicIPLookup, 0.9),
+            candidate("Hutch", ISPDetectionMethod::NetworkRouting, 0.7),
+        ]);
+        assert_eq!(agreeing.isp_name, "Hutch");
+        assert_eq!(agreeing.detection_method, "Combined Methods");
+        assert!(agreeing.confidence > 0.9 && agreeing.confidence <= 1.0);
+        assert_eq!(agreeing.candidates.len(), 3);
+
+        let conflicting = combine_isp_results(vec![
+            candidate("Dialog", ISPDetectionMethod::DnsAnalysis, 0.8),
+            candidate("Hutch", ISPDetectionMethod::PublicIPLookup, 0.9),
+            candidate("Hutch", ISPDetectionMethod::NetworkRouting, 0.7),
+        ]);
+        assert_eq!(conflicting.isp_name, "Hutch");
+        assert!(conflicting.confidence < agreeing.confidence);
+        assert_eq!(conflicting.candidates.len(), 3);
+
+        // A lone, less reliable method cannot beat a reliable one at equal confidence
+        let single = combine_isp_results(vec![
+            candidate("Dialog", ISPDetectionMethod::DnsAnalysis, 0.9),
+            candidate("SLT", ISPDetectionMethod::PublicIPLookup, 0.9),
+        ]);
+        assert_eq!(single.isp_name, "SLT");
+        assert_eq!(single.detection_method, "Public IP Lookup");
+
+        let unknown = combine_isp_results(Vec::new());
+        assert_eq!(unknown.isp_name, "Unknown ISP");
+        assert_eq!(unknown.confidence, 0.1);
+    }
+
     #[test]
     fn test_isp_detection_result_serialization() {
         let result = ISPDetectionResult {
@@ -1234,6 +2164,9 @@ mod tests {
             detection_method: "Test Method".to_string(),
             confidence: 0.85,
             detected_at: Utc::now(),
+            candidates: Vec::new(),
+            asn: None,
+            organization: None,
         };
         
         // Test serialization
@@ -1257,6 +2190,8 @@ mod tests {
             baseline_speed_mbps: 100.0,
             throttled_speed_mbps: 30.0,
             improvement_potential: 3.33,
+            wifi: None,
+            cgnat: false,
         };
         
         // Test serialization
@@ -0,0 +1,144 @@
+use serde::Serialize;
+use socket2::SockRef;
+use tokio::net::TcpStream;
+
+/// Prioritization mechanism the platform lets us request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QosCapability {
+    /// Windows qWave/QoS2 flows (the OS applies DSCP per policy)
+    Qwave,
+    /// Plain IP_TOS marking on the socket
+    Dscp,
+    /// No usable mechanism on this platform
+    Unsupported,
+}
+
+/// Result of asking for prioritization on one connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QosOutcome {
+    /// Socket added to a qWave flow with the given id
+    QwaveFlow(u32),
+    /// DSCP written into the socket's TOS byte
+    DscpMarked(u8),
+    /// Nothing applied; the reason is for logs only
+    Skipped(String),
+}
+
+/// Detects what prioritization is available on this machine
+pub fn detect_capability() -> QosCapability {
+    #[cfg(windows)]
+    {
+        if platform::qos_handle().is_some() {
+            return QosCapability::Qwave;
+        }
+    }
+    if cfg!(any(unix, windows)) { QosCapability::Dscp } else { QosCapability::Unsupported }
+}
+
+/// Requests prioritization for a connected stream, preferring qWave and falling back to DSCP marking.
+/// Failures are never fatal: traffic simply flows at default priority.
+pub fn prioritize(stream: &TcpStream, dscp: u8) -> QosOutcome {
+    if dscp == 0 {
+        return QosOutcome::Skipped("no DSCP requested".to_string());
+    }
+
+    #[cfg(windows)]
+    {
+        match platform::add_to_flow(stream, dscp) {
+            Ok(flow_id) => return QosOutcome::QwaveFlow(flow_id),
+            Err(e) => tracing::debug!("qWave flow unavailable, falling back to DSCP: {}", e),
+        }
+    }
+
+    mark_dscp(stream, dscp)
+}
+
+fn mark_dscp(stream: &TcpStream, dscp: u8) -> QosOutcome {
+    match stream.peer_addr() {
+        Ok(addr) if addr.is_ipv4() => {}
+        Ok(_) => return QosOutcome::Skipped("DSCP marking is only applied to IPv4 flows".to_string()),
+        Err(e) => return QosOutcome::Skipped(format!("socket not connected: {}", e)),
+    }
+    // DSCP occupies the upper six bits of the TOS byte
+    let tos = u32::from(dscp & 0x3f) << 2;
+    match SockRef::from(stream).set_tos(tos) {
+        Ok(()) => QosOutcome::DscpMarked(dscp),
+        Err(e) => QosOutcome::Skipped(format!("IP_TOS rejected: {}", e)),
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::os::windows::io::AsRawSocket;
+    use std::sync::OnceLock;
+    use tokio::net::TcpStream;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::NetworkManagement::QoS::{
+        QOSAddSocketToFlow, QOSCreateHandle, QOSTrafficTypeAudioVideo, QOSTrafficTypeBestEffort,
+        QOSTrafficTypeExcellentEffort, QOSTrafficTypeVoice, QOS_NON_ADAPTIVE_FLOW, QOS_TRAFFIC_TYPE, QOS_VERSION,
+    };
+
+    static QOS_HANDLE: OnceLock<Option<HANDLE>> = OnceLock::new();
+
+    /// Process-wide qWave handle; `None` when the qWave service is missing or disabled
+    pub(super) fn qos_handle() -> Option<HANDLE> {
+        *QOS_HANDLE.get_or_init(|| {
+            let version = QOS_VERSION { MajorVersion: 1, MinorVersion: 0 };
+            let mut handle: HANDLE = 0;
+            // SAFETY: both pointers reference valid locals for the duration of the call
+            let ok = unsafe { QOSCreateHandle(&version, &mut handle) };
+            (ok != 0).then_some(handle)
+        })
+    }
+
+    /// Closest qWave traffic class for a DSCP code point
+    fn traffic_type(dscp: u8) -> QOS_TRAFFIC_TYPE {
+        match dscp {
+            46 => QOSTrafficTypeVoice,
+            32..=39 => QOSTrafficTypeAudioVideo,
+            1..=31 => QOSTrafficTypeExcellentEffort,
+            _ => QOSTrafficTypeBestEffort,
+        }
+    }
+
+    /// Adds a connected socket to a non-adaptive flow; Windows removes the flow when the socket closes
+    pub(super) fn add_to_flow(stream: &TcpStream, dscp: u8) -> std::io::Result<u32> {
+        let handle = qos_handle()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Unsupported, "qWave not available"))?;
+        let mut flow_id: u32 = 0;
+        // SAFETY: the socket is connected and owned by `stream`, a null destination means "connected peer"
+        let ok = unsafe {
+            QOSAddSocketToFlow(
+                handle,
+                stream.as_raw_socket() as usize,
+                std::ptr::null(),
+                traffic_type(dscp),
+                QOS_NON_ADAPTIVE_FLOW,
+                &mut flow_id,
+            )
+        };
+        if ok == 0 { Err(std::io::Error::last_os_error()) } else { Ok(flow_id) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_prioritize_loopback_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stream = TcpStream::connect(addr).await.unwrap();
+
+        assert!(matches!(prioritize(&stream, 0), QosOutcome::Skipped(_)));
+        // Either mechanism may be granted or refused by the OS, but it must never panic or error out
+        let outcome = prioritize(&stream, 34);
+        if cfg!(unix) {
+            assert_eq!(outcome, QosOutcome::DscpMarked(34));
+        }
+        assert_ne!(detect_capability(), QosCapability::Unsupported);
+    }
+}
@@ -0,0 +1,170 @@
+//! HTTP/3 over QUIC for generated traffic. Current Ookla and fast.com clients speak HTTP/3, and
+//! some DPI boxes shape UDP flows differently from TCP, so the stealth engine can send its
+//! requests this way. Like a browser, one connection is kept per host and reused until it idles out.
+
+use crate::core::error::{Result, SpeedKarmaError};
+use bytes::{Buf, Bytes};
+use h3::client::SendRequest;
+use quinn::crypto::rustls::QuicClientConfig;
+use quinn::rustls::{self, crypto::ring, pki_types::{Der, TrustAnchor}, RootCertStore};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+/// Chrome drops idle QUIC connections after 30 seconds; the same here so reuse looks alike
+pub const QUIC_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Outcome of one HTTP/3 request
+#[derive(Debug, Clone, Copy)]
+pub struct Http3Response {
+    pub status: u16,
+    pub received: u64,
+    /// Handshake time when the request had to open a new connection
+    pub handshake: Option<Duration>,
+}
+
+struct Http3Connection {
+    authority: String,
+    connection: quinn::Connection,
+    send_request: SendRequest<h3_quinn::OpenStreams, Bytes>,
+}
+
+/// HTTP/3 client with Chrome's QUIC cipher and group order
+pub struct Http3Client {
+    endpoint: quinn::Endpoint,
+    current: Mutex<Option<Http3Connection>>,
+}
+
+fn unavailable(context: &str, e: impl std::fmt::Display) -> SpeedKarmaError {
+    SpeedKarmaError::NetworkUnavailable(format!("{}: {}", context, e))
+}
+
+/// QUIC client config: TLS 1.3 only, AES-128 first, X25519 then P-256 and P-384, ALPN h3
+fn client_config() -> Result<quinn::ClientConfig> {
+    let mut provider = ring::default_provider();
+    provider.cipher_suites = vec![
+        ring::cipher_suite::TLS13_AES_128_GCM_SHA256,
+        ring::cipher_suite::TLS13_AES_256_GCM_SHA384,
+        ring::cipher_suite::TLS13_CHACHA20_POLY1305_SHA256,
+    ];
+    provider.kx_groups = vec![ring::kx_group::X25519, ring::kx_group::SECP256R1, ring::kx_group::SECP384R1];
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| TrustAnchor {
+            subject: Der::from_slice(anchor.subject),
+            subject_public_key_info: Der::from_slice(anchor.spki),
+            name_constraints: anchor.name_constraints.map(Der::from_slice),
+        }).collect(),
+    };
+    let mut tls = rustls::ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(|e| unavailable("QUIC TLS setup failed", e))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    tls.alpn_protocols = vec![b"h3".to_vec()];
+
+    let crypto = QuicClientConfig::try_from(tls).map_err(|e| unavailable("QUIC TLS setup failed", e))?;
+    let mut config = quinn::ClientConfig::new(Arc::new(crypto));
+    let mut transport = quinn::TransportConfig::default();
+    transport.max_idle_timeout(QUIC_IDLE_TIMEOUT.try_into().ok());
+    config.transport_config(Arc::new(transport));
+    Ok(config)
+}
+
+impl Http3Client {
+    /// Opens the UDP socket, dual-stack where IPv6 is available; needs a running tokio runtime
+    pub fn new() -> Result<Self> {
+        let mut endpoint = quinn::Endpoint::client(SocketAddr::from(([0u16; 8], 0)))
+            .or_else(|_| quinn::Endpoint::client(SocketAddr::from(([0u8; 4], 0))))
+            .map_err(|e| unavailable("Failed to open a UDP socket", e))?;
+        endpoint.set_default_client_config(client_config()?);
+        Ok(Self { endpoint, current: Mutex::new(None) })
+    }
+
+    async fn connect(&self, host: &str, authority: &str) -> Result<Http3Connection> {
+        let ipv6 = self.endpoint.local_addr().map(|addr| addr.is_ipv6()).unwrap_or(false);
+        let addr = tokio::net::lookup_host(authority).await
+            .map_err(|e| unavailable(authority, e))?
+            .find(|addr| ipv6 || addr.is_ipv4())
+            .ok_or_else(|| SpeedKarmaError::NetworkUnavailable(format!("{} did not resolve", authority)))?;
+        let connection = self.endpoint.connect(addr, host)
+            .map_err(|e| unavailable(authority, e))?
+            .await
+            .map_err(|e| unavailable(&format!("QUIC handshake with {} failed", authority), e))?;
+        let (mut driver, send_request) = h3::client::new(h3_quinn::Connection::new(connection.clone())).await
+            .map_err(|e| unavailable(&format!("HTTP/3 setup with {} failed", authority), e))?;
+        // Serves the control streams until the connection closes
+        tokio::spawn(async move {
+            let _ = driver.wait_idle().await;
+        });
+        debug!("Opened HTTP/3 connection to {}", authority);
+        Ok(Http3Connection { authority: authority.to_string(), connection, send_request })
+    }
+
+    /// Sends one request, reusing the open connection when it goes to the same host and port;
+    /// the response body is read to the end and counted
+    pub async fn request(&self, method: http::Method, url: &str, user_agent: &str, body: Option<Bytes>) -> Result<Http3Response> {
+        let uri: http::Uri = url.parse().map_err(|e| unavailable(&format!("Invalid URL {}", url), e))?;
+        let host = uri.host()
+            .ok_or_else(|| SpeedKarmaError::NetworkUnavailable(format!("URL {} has no host", url)))?
+            .to_string();
+        let authority = format!("{}:{}", host, uri.port_u16().unwrap_or(443));
+
+        let mut handshake = None;
+        let mut send_request = {
+            let mut current = self.current.lock().await;
+            match current.as_ref() {
+                Some(open) if open.authority == authority && open.connection.close_reason().is_none() => open.send_request.clone(),
+                _ => {
+                    let started = Instant::now();
+                    let open = self.connect(&host, &authority).await?;
+                    handshake = Some(started.elapsed());
+                    let send_request = open.send_request.clone();
+                    *current = Some(open);
+                    send_request
+                }
+            }
+        };
+
+        let request = http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(http::header::USER_AGENT, user_agent)
+            .body(())
+            .map_err(|e| unavailable(&format!("Invalid request for {}", url), e))?;
+        let exchange = async {
+            let mut stream = send_request.send_request(request).await?;
+            if let Some(body) = body {
+                stream.send_data(body).await?;
+            }
+            stream.finish().await?;
+            let response = stream.recv_response().await?;
+            let mut received = 0u64;
+            while let Some(mut chunk) = stream.recv_data().await? {
+                received += chunk.remaining() as u64;
+                chunk.advance(chunk.remaining());
+            }
+            Ok::<_, h3::error::StreamError>((response.status().as_u16(), received))
+        };
+        let (status, received) = tokio::time::timeout(REQUEST_TIMEOUT, exchange).await
+            .map_err(|_| SpeedKarmaError::NetworkUnavailable(format!("HTTP/3 request to {} timed out", authority)))?
+            .map_err(|e| unavailable(&format!("HTTP/3 request to {} failed", authority), e))?;
+        Ok(Http3Response { status, received, handshake })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_client_builds_and_rejects_urls_without_a_host() {
+        assert!(client_config().is_ok());
+        let client = Http3Client::new().unwrap();
+        assert!(client.request(http::Method::GET, "/latency.txt", "test", None).await.is_err());
+        assert!(client.request(http::Method::GET, "not a url", "test", None).await.is_err());
+        assert!(client.current.lock().await.is_none());
+    }
+}
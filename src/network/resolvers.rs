@@ -0,0 +1,323 @@
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crate::core::command::command_output;
+use crate::network::asn_db::{known_isp_domain, AsnDatabase};
+use crate::network::cgnat::is_shared_address;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+/// Reverse lookups slower than this are given up on
+const REVERSE_LOOKUP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Anycast resolvers anyone can use; they say nothing about the ISP
+const PUBLIC_RESOLVERS: &[&str] = &[
+    "8.8.8.8", "8.8.4.4", "1.1.1.1", "1.0.0.1", "9.9.9.9", "149.112.112.112",
+    "208.67.222.222", "208.67.220.220", "94.140.14.14", "94.140.15.15",
+    "2001:4860:4860::8888", "2001:4860:4860::8844", "2606:4700:4700::1111", "2606:4700:4700::1001", "2620:fe::fe",
+];
+
+/// Second-level labels under which ccTLD registries hand out domains (`dialog.co.lk`)
+const SECOND_LEVEL_LABELS: &[&str] = &["co", "com", "net", "org", "ac", "gov", "edu", "ne", "or"];
+
+/// Evidence weights: an ASN of a known operator, a reverse name under its domain, anything else
+const KNOWN_ASN_WEIGHT: f64 = 0.8;
+const KNOWN_DOMAIN_WEIGHT: f64 = 0.75;
+const UNKNOWN_WEIGHT: f64 = 0.45;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostRole {
+    Resolver,
+    Gateway,
+}
+
+/// A configured DNS server or the default gateway, with its reverse name when it has one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostObservation {
+    pub address: IpAddr,
+    pub role: HostRole,
+    pub reverse_name: Option<String>,
+}
+
+/// ISP the resolvers and gateway point at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolverVerdict {
+    pub isp_name: String,
+    /// Country code, empty when unknown
+    pub region: String,
+    pub confidence: f64,
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+}
+
+/// `nameserver` lines of a resolv.conf
+pub fn parse_resolv_conf(content: &str) -> Vec<IpAddr> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|rest| parse_address(rest.trim()))
+        .collect()
+}
+
+/// Default route of `/proc/net/route` (gateway column is little-endian hex)
+pub fn parse_proc_net_route(content: &str) -> Option<IpAddr> {
+    content.lines().skip(1).find_map(|line| {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        if cols.len() < 3 || cols[1] != "00000000" { return None; }
+        let gateway = u32::from_str_radix(cols[2], 16).ok().filter(|g| *g != 0)?;
+        Some(IpAddr::V4(Ipv4Addr::from(gateway.to_le_bytes())))
+    })
+}
+
+/// `gateway:` line of macOS `route -n get default`
+pub fn parse_route_get(output: &str) -> Option<IpAddr> {
+    output.lines().find_map(|line| parse_address(line.trim().strip_prefix("gateway:")?.trim()))
+}
+
+/// DNS servers and default gateway from Windows `ipconfig /all`, including continuation lines
+pub fn parse_ipconfig(output: &str) -> (Vec<IpAddr>, Option<IpAddr>) {
+    let mut dns = Vec::new();
+    let mut gateway = None;
+    let mut key = String::new();
+    for line in output.lines() {
+        let value = match line.split_once(" : ") {
+            Some((k, v)) => {
+                key = k.trim().trim_end_matches(['.', ' ']).to_string();
+                v.trim()
+            }
+            None => line.trim(),
+        };
+        let Some(address) = parse_address(value) else { continue };
+        match key.as_str() {
+            "DNS Servers" => dns.push(address),
+            // IPv4 gateways are listed after the link-local IPv6 one
+            "Default Gateway" if gateway.is_none() || gateway.is_some_and(|g: IpAddr| g.is_ipv6()) => gateway = Some(address),
+            _ => {}
+        }
+    }
+    (dns, gateway)
+}
+
+/// Address with any `%zone` suffix dropped
+fn parse_address(value: &str) -> Option<IpAddr> {
+    value.split('%').next()?.parse().ok()
+}
+
+fn is_public_resolver(address: &IpAddr) -> bool {
+    PUBLIC_RESOLVERS.iter().any(|r| r.parse::<IpAddr>().is_ok_and(|r| r == *address))
+}
+
+pub(crate) fn is_routable(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified() || is_shared_address(address)),
+        IpAddr::V6(v6) => !(v6.is_loopback() || v6.is_unspecified() || (v6.segments()[0] & 0xfe00) == 0xfc00 || (v6.segments()[0] & 0xffc0) == 0xfe80),
+    }
+}
+
+/// `dialog.lk` from `dns1.dialog.lk`, `example.co.lk` from `ns.example.co.lk`
+pub fn registrable_domain(host: &str) -> Option<String> {
+    let labels: Vec<&str> = host.trim_end_matches('.').split('.').filter(|l| !l.is_empty()).collect();
+    if labels.len() < 2 { return None; }
+    let tld = labels[labels.len() - 1];
+    let keep = if labels.len() >= 3 && tld.len() == 2 && SECOND_LEVEL_LABELS.contains(&labels[labels.len() - 2]) { 3 } else { 2 };
+    Some(labels[labels.len() - keep..].join(".").to_lowercase())
+}
+
+/// Country code implied by a ccTLD
+fn country_of_domain(domain: &str) -> Option<String> {
+    let tld = domain.rsplit('.').next()?;
+    (tld.len() == 2 && tld.chars().all(|c| c.is_ascii_alphabetic())).then(|| tld.to_uppercase())
+}
+
+struct Evidence {
+    isp_name: String,
+    region: Option<String>,
+    weight: f64,
+    asn: Option<u32>,
+    organization: Option<String>,
+}
+
+fn evidence_for(observation: &HostObservation, asn_db: &AsnDatabase) -> Vec<Evidence> {
+    let address = observation.address;
+    if address.is_loopback() || is_public_resolver(&address) { return Vec::new(); }
+    let mut evidence = Vec::new();
+
+    if let Some(domain) = observation.reverse_name.as_deref().and_then(registrable_domain) {
+        let known = known_isp_domain(&domain);
+        // Routers answer for names like `router.home`; only routable hosts say something about the ISP
+        if known.is_some() || is_routable(&address) {
+            evidence.push(Evidence {
+                isp_name: known.map(str::to_string).unwrap_or_else(|| domain.clone()),
+                region: country_of_domain(&domain),
+                weight: if known.is_some() { KNOWN_DOMAIN_WEIGHT } else { UNKNOWN_WEIGHT },
+                asn: None,
+                organization: None,
+            });
+        }
+    }
+
+    if let IpAddr::V4(v4) = address {
+        if let Some(record) = asn_db.lookup(v4).filter(|_| is_routable(&address)) {
+            let known = record.isp_name();
+            evidence.push(Evidence {
+                isp_name: known.map(str::to_string).unwrap_or_else(|| record.description.clone()),
+                region: Some(record.country.clone()).filter(|c| !c.is_empty()),
+                weight: if known.is_some() { KNOWN_ASN_WEIGHT } else { UNKNOWN_WEIGHT },
+                asn: Some(record.asn),
+                organization: Some(record.description.clone()).filter(|d| !d.is_empty()),
+            });
+        }
+    }
+    evidence.retain(|e| !e.isp_name.trim().is_empty());
+    evidence
+}
+
+/// Weighs what resolvers and gateway reveal. Hosts pointing at the same ISP reinforce each other
+/// (noisy-OR), and the result is scaled by that ISP's share of all evidence.
+/// `None` when nothing points at an ISP, e.g. only a home router and public resolvers.
+pub fn isp_from_observations(observations: &[HostObservation], asn_db: &AsnDatabase) -> Option<ResolverVerdict> {
+    let evidence: Vec<Evidence> = observations.iter().flat_map(|o| evidence_for(o, asn_db)).collect();
+    let mut groups: Vec<(String, Vec<&Evidence>)> = Vec::new();
+    for e in &evidence {
+        let key = e.isp_name.to_lowercase();
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, members)) => members.push(e),
+            None => groups.push((key, vec![e])),
+        }
+    }
+    let support = |members: &[&Evidence]| members.iter().map(|e| e.weight).sum::<f64>();
+    let total: f64 = groups.iter().map(|(_, m)| support(m)).sum();
+    let (_, members) = groups
+        .iter()
+        .max_by(|a, b| support(&a.1).partial_cmp(&support(&b.1)).unwrap_or(std::cmp::Ordering::Equal))?;
+
+    let agreement = 1.0 - members.iter().map(|e| 1.0 - e.weight).product::<f64>();
+    let lead = members
+        .iter()
+        .max_by(|a, b| a.weight.partial_cmp(&b.weight).unwrap_or(std::cmp::Ordering::Equal))?;
+    Some(ResolverVerdict {
+        isp_name: lead.isp_name.clone(),
+        region: members.iter().find_map(|e| e.region.clone()).unwrap_or_default(),
+        confidence: agreement * support(members) / total,
+        asn: members.iter().find_map(|e| e.asn),
+        organization: members.iter().find_map(|e| e.organization.clone()),
+    })
+}
+
+/// PTR name of `address`, if it has one
+pub async fn reverse_name(address: IpAddr) -> Option<String> {
+    let lookup = tokio::task::spawn_blocking(move || dns_lookup::lookup_addr(&address));
+    let name = tokio::time::timeout(REVERSE_LOOKUP_TIMEOUT, lookup).await.ok()?.ok()?.ok()?;
+    let name = name.trim_end_matches('.').to_string();
+    (parse_address(&name).is_none() && !name.is_empty()).then_some(name)
+}
+
+/// Configured DNS servers and the default gateway
+#[cfg(target_os = "linux")]
+pub async fn system_hosts() -> (Vec<IpAddr>, Option<IpAddr>) {
+    // With systemd-resolved, /etc/resolv.conf only lists the local stub
+    let mut resolvers = Vec::new();
+    for path in ["/run/systemd/resolve/resolv.conf", "/etc/resolv.conf"] {
+        if let Ok(content) = tokio::fs::read_to_string(path).await {
+            resolvers = parse_resolv_conf(&content);
+            if !resolvers.is_empty() { break; }
+        }
+    }
+    let gateway = tokio::fs::read_to_string("/proc/net/route").await.ok().as_deref().and_then(parse_proc_net_route);
+    (resolvers, gateway)
+}
+
+#[cfg(target_os = "macos")]
+pub async fn system_hosts() -> (Vec<IpAddr>, Option<IpAddr>) {
+    let resolvers = tokio::fs::read_to_string("/etc/resolv.conf").await.map(|c| parse_resolv_conf(&c)).unwrap_or_default();
+    let gateway = command_output("route", &["-n", "get", "default"]).await.as_deref().and_then(parse_route_get);
+    (resolvers, gateway)
+}
+
+#[cfg(target_os = "windows")]
+pub async fn system_hosts() -> (Vec<IpAddr>, Option<IpAddr>) {
+    command_output("ipconfig", &["/all"]).await.map(|out| parse_ipconfig(&out)).unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub async fn system_hosts() -> (Vec<IpAddr>, Option<IpAddr>) {
+    (Vec::new(), None)
+}
+
+/// Resolvers and gateway of this machine with their reverse names
+pub async fn observe() -> Vec<HostObservation> {
+    let (resolvers, gateway) = system_hosts().await;
+    let mut hosts: Vec<(IpAddr, HostRole)> = Vec::new();
+    for address in resolvers {
+        if !hosts.iter().any(|(a, _)| *a == address) { hosts.push((address, HostRole::Resolver)); }
+    }
+    if let Some(address) = gateway.filter(|g| !hosts.iter().any(|(a, _)| a == g)) {
+        hosts.push((address, HostRole::Gateway));
+    }
+    let mut observations = Vec::with_capacity(hosts.len());
+    for (address, role) in hosts {
+        let reverse_name = if address.is_loopback() { None } else { reverse_name(address).await };
+        observations.push(HostObservation { address, role, reverse_name });
+    }
+    observations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::asn_db::AsnRecord;
+
+    #[test]
+    fn test_system_sources() {
+        let resolv = "# Generated\nnameserver 192.168.1.1\nnameserver fe80::1%eth0\nsearch home\n";
+        assert_eq!(parse_resolv_conf(resolv), vec!["192.168.1.1".parse::<IpAddr>().unwrap(), "fe80::1".parse().unwrap()]);
+
+        let route = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\nwlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\nwlan0\t0001A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\n";
+        assert_eq!(parse_proc_net_route(route), Some("192.168.1.1".parse().unwrap()));
+        assert_eq!(parse_route_get("   route to: default\n    gateway: 10.0.0.1\n  interface: en0\n"), Some("10.0.0.1".parse().unwrap()));
+
+        let ipconfig = "   Default Gateway . . . . . . . . . : fe80::1%12\n                                       192.168.8.1\n   \
+             DNS Servers . . . . . . . . . . . : 203.0.113.53\n                                       203.0.113.54\n   NetBIOS over Tcpip. . . . . . . . : Enabled\n";
+        let (dns, gateway) = parse_ipconfig(ipconfig);
+        assert_eq!(dns.len(), 2);
+        assert_eq!(gateway, Some("192.168.8.1".parse().unwrap()));
+
+        assert_eq!(registrable_domain("dns1.dialog.lk.").as_deref(), Some("dialog.lk"));
+        assert_eq!(registrable_domain("ns.example.co.lk").as_deref(), Some("example.co.lk"));
+        assert!(registrable_domain("router").is_none());
+    }
+
+    #[test]
+    fn test_isp_from_resolvers() {
+        let db = AsnDatabase::from_records(vec![AsnRecord {
+            range_start: u32::from(Ipv4Addr::new(203, 0, 113, 0)),
+            range_end: u32::from(Ipv4Addr::new(203, 0, 113, 255)),
+            asn: 18001,
+            country: "LK".into(),
+            description: "DIALOG-AS Dialog Axiata PLC.".into(),
+        }]);
+        let host = |ip: &str, role, name: Option<&str>| HostObservation { address: ip.parse().unwrap(), role, reverse_name: name.map(str::to_string) };
+
+        let verdict = isp_from_observations(&[
+            host("203.0.113.53", HostRole::Resolver, Some("dns1.dialog.lk")),
+            host("8.8.8.8", HostRole::Resolver, Some("dns.google")),
+            host("192.168.8.1", HostRole::Gateway, None),
+        ], &db).unwrap();
+        assert_eq!((verdict.isp_name.as_str(), verdict.region.as_str(), verdict.asn), ("Dialog", "LK", Some(18001)));
+        assert!(verdict.confidence > KNOWN_ASN_WEIGHT);
+
+        // Home router and public resolvers only: nothing to go on
+        assert!(isp_from_observations(&[
+            host("192.168.1.1", HostRole::Resolver, Some("router.home")),
+            host("1.1.1.1", HostRole::Resolver, Some("one.one.one.one")),
+        ], &db).is_none());
+
+        // Conflicting hints lower the confidence
+        let split = isp_from_observations(&[
+            host("203.0.113.53", HostRole::Resolver, None),
+            host("198.51.100.53", HostRole::Resolver, Some("ns1.example-isp.net")),
+        ], &db).unwrap();
+        assert_eq!(split.isp_name, "Dialog");
+        assert!(split.confidence < verdict.confidence);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::core::config::LatencyProbeConfig;
+use crate::network::dns;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+use tokio::time::timeout;
+use tracing::debug;
+
+/// Samples kept between two passive measurements
+const MAX_SAMPLES: usize = 256;
+
+/// TCP handshake round trips of the stealth engine's own connections, timed as they open.
+/// The passive monitor takes their median once per measurement window.
+#[derive(Debug, Clone, Default)]
+pub struct RttSampler {
+    samples: Arc<Mutex<VecDeque<(Instant, Duration)>>>,
+}
+
+impl RttSampler {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn record(&self, rtt: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back((Instant::now(), rtt));
+    }
+
+    /// Median of the samples taken within `window`, in milliseconds; clears all samples
+    pub fn take_median_ms(&self, window: Duration) -> Option<u32> {
+        let recent: Vec<Duration> = self.samples
+            .lock()
+            .unwrap()
+            .drain(..)
+            .filter(|(at, _)| at.elapsed() <= window)
+            .map(|(_, rtt)| rtt)
+            .collect();
+        median_ms(recent)
+    }
+}
+
+fn median_ms(mut rtts: Vec<Duration>) -> Option<u32> {
+    if rtts.is_empty() {
+        return None;
+    }
+    rtts.sort_unstable();
+    let mid = rtts.len() / 2;
+    let median = if rtts.len().is_multiple_of(2) { (rtts[mid - 1] + rtts[mid]) / 2 } else { rtts[mid] };
+    Some(median.as_millis().min(10_000) as u32)
+}
+
+/// Splits a `host:port` anchor; IPv6 hosts are written `[::1]:443`
+pub fn parse_anchor(anchor: &str) -> Option<(String, u16)> {
+    let (host, port) = anchor.trim().rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = port.parse().ok()?;
+    (!host.is_empty()).then(|| (host.to_string(), port))
+}
+
+/// What one probe round measured across the anchors
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProbeResult {
+    /// Median handshake time
+    pub latency_ms: Option<u32>,
+    /// Share of unanswered DNS queries, counting only anchors that answered at least once so a
+    /// firewalled anchor is not mistaken for loss
+    pub packet_loss_pct: Option<f64>,
+    /// Mean change between consecutive handshake times to the same anchor
+    pub jitter_ms: Option<f64>,
+    /// Some handshake was tunnelled through the configured proxy
+    pub via_proxy: bool,
+}
+
+/// Attempts of anchors that answered at least once; an anchor that never answers is filtered, not lossy
+fn reachable(per_anchor: &[Vec<Option<Duration>>]) -> Vec<&Vec<Option<Duration>>> {
+    per_anchor.iter().filter(|a| a.iter().any(Option::is_some)).collect()
+}
+
+impl ProbeResult {
+    /// Summarises a round from each anchor's handshakes and DNS queries, `None` marking a failed
+    /// handshake or an unanswered query. Handshakes cannot show loss: the kernel retransmits a
+    /// lost SYN within the timeout, so it surfaces only as a slow handshake.
+    pub fn from_attempts(handshakes: &[Vec<Option<Duration>>], queries: &[Vec<Option<Duration>>]) -> Self {
+        let answering = reachable(handshakes);
+        let rtts: Vec<Duration> = answering.iter().flat_map(|a| a.iter().flatten().copied()).collect();
+        let deltas: Vec<f64> = answering
+            .iter()
+            .flat_map(|a| {
+                let answered: Vec<f64> = a.iter().flatten().map(|d| d.as_secs_f64() * 1000.0).collect();
+                answered.windows(2).map(|w| (w[1] - w[0]).abs()).collect::<Vec<_>>()
+            })
+            .collect();
+        let jitter_ms = (!deltas.is_empty()).then(|| deltas.iter().sum::<f64>() / deltas.len() as f64);
+
+        let answering = reachable(queries);
+        let sent: usize = answering.iter().map(|a| a.len()).sum();
+        let answered: usize = answering.iter().map(|a| a.iter().flatten().count()).sum();
+        let packet_loss_pct = (sent > 1).then(|| (sent - answered) as f64 / sent as f64 * 100.0);
+        Self { latency_ms: median_ms(rtts), packet_loss_pct, jitter_ms, via_proxy: false }
+    }
+}
+
+/// Name the loss probes look up; any resolver answers it from cache
+const LOSS_PROBE_NAME: &str = "example.com";
+
+/// UDP socket connected to `host:port`, bound to the matching address family
+async fn udp_socket(host: &str, port: u16) -> Option<UdpSocket> {
+    let target = tokio::net::lookup_host((host, port)).await.ok()?.next()?;
+    let local: SocketAddr = if target.is_ipv4() { (Ipv4Addr::UNSPECIFIED, 0).into() } else { (Ipv6Addr::UNSPECIFIED, 0).into() };
+    let socket = UdpSocket::bind(local).await.ok()?;
+    socket.connect(target).await.ok()?;
+    Some(socket)
+}
+
+type ProbeRound = (Instant, ProbeResult);
+
+/// Repeated handshakes to well-known anchors: a round-trip time for passive samples that have no
+/// RTT of their own, plus jitter, and packet loss from DNS queries to the same anchors. At most one round runs per
+/// `min_interval_seconds`; calls in between get the last result.
+#[derive(Debug, Clone)]
+pub struct LatencyProbe {
+    config: LatencyProbeConfig,
+    /// When the last round ran and what it measured
+    last_round: Arc<Mutex<Option<ProbeRound>>>,
+    limiter: OutboundLimiter,
+}
+
+impl LatencyProbe {
+    pub fn new(config: LatencyProbeConfig) -> Self {
+        Self { config, last_round: Arc::new(Mutex::new(None)), limiter: OutboundLimiter::default() }
+    }
+
+    /// Counts each handshake against the app-wide outbound limit
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Latest probe round; all `None` when disabled
+    pub async fn measure(&self) -> ProbeResult {
+        if !self.config.enabled {
+            return ProbeResult::default();
+        }
+        let min_interval = Duration::from_secs(self.config.min_interval_seconds);
+        if let Some((at, result)) = *self.last_round.lock().unwrap() {
+            if at.elapsed() < min_interval {
+                return result;
+            }
+        }
+        let result = self.round().await;
+        *self.last_round.lock().unwrap() = Some((Instant::now(), result));
+        result
+    }
+
+    /// Probes every anchor now, regardless of the rate limit or the enabled switch. Anchors are
+    /// probed concurrently so a round takes about as long as the slowest anchor.
+    pub async fn round(&self) -> ProbeResult {
+        let limit = Duration::from_millis(self.config.timeout_ms.max(1));
+        let attempts = self.config.attempts_per_anchor.max(1);
+        let mut probes = JoinSet::new();
+        for (index, (host, port)) in self.config.anchors.iter().filter_map(|a| parse_anchor(a)).enumerate() {
+            let probe = self.clone();
+            probes.spawn(async move {
+                let (handshakes, queries) = tokio::join!(
+                    probe.handshakes(&host, port, attempts, limit),
+                    probe.queries(&host, attempts, limit),
+                );
+                (index, handshakes, queries)
+            });
+        }
+        let mut rounds = Vec::new();
+        while let Some(joined) = probes.join_next().await {
+            if let Ok(round) = joined {
+                rounds.push(round);
+            }
+        }
+        rounds.sort_by_key(|(index, _, _)| *index);
+        let via_proxy = rounds.iter().any(|(_, (_, proxied), _)| *proxied);
+        let (handshakes, queries): (Vec<_>, Vec<_>) = rounds.into_iter().map(|(_, (h, _), q)| (h, q)).unzip();
+        ProbeResult { via_proxy, ..ProbeResult::from_attempts(&handshakes, &queries) }
+    }
+
+    /// Consecutive TCP handshakes to one anchor, and whether any went through the proxy
+    async fn handshakes(&self, host: &str, port: u16, attempts: u32, limit: Duration) -> (Vec<Option<Duration>>, bool) {
+        let mut results = Vec::new();
+        let mut via_proxy = false;
+        for _ in 0..attempts {
+            let _permit = self.limiter.acquire("latency probe").await;
+            let started = Instant::now();
+            match timeout(limit, proxy::connect_measurement(host, port)).await {
+                Ok(Ok((_stream, proxied))) => {
+                    results.push(Some(started.elapsed()));
+                    via_proxy |= proxied;
+                }
+                _ => {
+                    debug!("Latency probe to {}:{} failed", host, port);
+                    results.push(None);
+                }
+            }
+        }
+        (results, via_proxy)
+    }
+
+    /// One DNS query per attempt to the anchor's resolver, each sent once; empty when loss
+    /// probing is off or the anchor cannot be addressed
+    async fn queries(&self, host: &str, attempts: u32, limit: Duration) -> Vec<Option<Duration>> {
+        let port = self.config.loss_probe_port;
+        if port == 0 {
+            return Vec::new();
+        }
+        let Some(socket) = udp_socket(host, port).await else {
+            return Vec::new();
+        };
+        let mut results = Vec::new();
+        let mut buffer = [0u8; 512];
+        for _ in 0..attempts {
+            let _permit = self.limiter.acquire("loss probe").await;
+            let id: u16 = rand::random();
+            let Ok(query) = dns::build_query(id, LOSS_PROBE_NAME, dns::TYPE_A) else { return Vec::new() };
+            let started = Instant::now();
+            if socket.send(&query).await.is_err() {
+                return Vec::new();
+            }
+            // Late answers to earlier queries carry another id and are skipped
+            let answer = timeout(limit, async {
+                loop {
+                    match socket.recv(&mut buffer).await {
+                        Ok(len) if len >= 2 && buffer[..2] == id.to_be_bytes() => return true,
+                        Ok(_) => continue,
+                        Err(_) => return false,
+                    }
+                }
+            }).await;
+            results.push(matches!(answer, Ok(true)).then(|| started.elapsed()));
+        }
+        results
+    }
+
+    /// Median handshake time across the anchors in milliseconds; `None` when disabled or nothing answered
+    pub async fn latency_ms(&self) -> Option<u32> {
+        self.measure().await.latency_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median_and_drain() {
+        let sampler = RttSampler::new();
+        assert_eq!(sampler.take_median_ms(Duration::from_secs(60)), None);
+        for ms in [40, 10, 30, 20] {
+            sampler.record(Duration::from_millis(ms));
+        }
+        assert_eq!(sampler.take_median_ms(Duration::from_secs(60)), Some(25));
+        assert_eq!(sampler.take_median_ms(Duration::from_secs(60)), None);
+
+        sampler.record(Duration::from_millis(15));
+        std::thread::sleep(Duration::from_millis(20));
+        sampler.record(Duration::from_millis(50));
+        // Only the sample inside the window counts
+        assert_eq!(sampler.take_median_ms(Duration::from_millis(10)), Some(50));
+    }
+
+    #[tokio::test]
+    async fn test_latency_probe_rate_limit() {
+        assert_eq!(parse_anchor("1.1.1.1:443"), Some(("1.1.1.1".to_string(), 443)));
+        assert_eq!(parse_anchor("[2606:4700::1111]:443"), Some(("2606:4700::1111".to_string(), 443)));
+        assert_eq!(parse_anchor("example.com"), None);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let config = LatencyProbeConfig { anchors: vec![format!("127.0.0.1:{}", port)], loss_probe_port: 0, ..LatencyProbeConfig::default() };
+        let probe = LatencyProbe::new(config);
+        assert!(probe.latency_ms().await.is_some());
+        // Within the interval the last round is reused rather than probing again
+        drop(listener);
+        assert!(probe.latency_ms().await.is_some());
+
+        let disabled = LatencyProbe::new(LatencyProbeConfig { enabled: false, ..LatencyProbeConfig::default() });
+        assert_eq!(disabled.latency_ms().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_loss_probe_counts_unanswered_queries() {
+        // A resolver that drops every other query
+        let resolver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = resolver.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 512];
+            for received in 0.. {
+                let Ok((len, from)) = resolver.recv_from(&mut buffer).await else { break };
+                if received % 2 == 0 {
+                    let _ = resolver.send_to(&buffer[..len], from).await;
+                }
+            }
+        });
+        let probe = LatencyProbe::new(LatencyProbeConfig { loss_probe_port: port, timeout_ms: 200, ..LatencyProbeConfig::default() });
+        let answers = probe.queries("127.0.0.1", 4, Duration::from_millis(200)).await;
+        assert_eq!(answers.iter().map(Option::is_some).collect::<Vec<_>>(), vec![true, false, true, false]);
+    }
+
+    #[test]
+    fn test_round_loss_and_jitter() {
+        let ms = |v: u64| Some(Duration::from_millis(v));
+        // The second anchor never answers and is left out of the loss figure
+        let handshakes = [vec![ms(20), None, ms(30), ms(20)], vec![None, None, None, None]];
+        let queries = [vec![ms(15), None, ms(15), ms(16)], vec![None, None, None, None]];
+        let round = ProbeResult::from_attempts(&handshakes, &queries);
+        assert_eq!(round.latency_ms, Some(20));
+        assert_eq!(round.packet_loss_pct, Some(25.0));
+        assert_eq!(round.jitter_ms, Some(10.0));
+
+        // A failed handshake is not loss; without queries loss stays unmeasured
+        let round = ProbeResult::from_attempts(&handshakes, &[]);
+        assert_eq!((round.latency_ms, round.packet_loss_pct), (Some(20), None));
+
+        let offline = ProbeResult::from_attempts(&[vec![None, None]], &[vec![None, None]]);
+        assert_eq!(offline, ProbeResult::default());
+    }
+}
@@ -1,8 +1,18 @@
+use crate::core::app_state::SharedAppState;
+use crate::core::config::UserLocationConfig;
 use crate::core::error::{Result, SpeedKarmaError};
-use crate::data::models::SpeedtestServer;
-use reqwest::{Client, ClientBuilder};
-use serde::Deserialize;
+use crate::core::shutdown::{self, CancellationToken};
+use crate::data::models::{ServerEndpoint, SpeedtestServer};
+use crate::data::stores::ServerStore;
+use crate::network::ip_lookup::PublicIpLookup;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, ClientBuilder, StatusCode};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -28,6 +38,102 @@ struct SpeedtestServerResponse {
     longitude: f64,
 }
 
+/// How long a cached server list is trusted before revalidating with the API
+pub const DEFAULT_SERVER_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Regions stealth rotation draws from when no preference is configured
+const DEFAULT_PREFERRED_COUNTRIES: &[&str] = &["Sri Lanka", "Singapore", "India"];
+
+/// How often candidate servers are probed for latency and request failures
+pub const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Latency requests per server and probe round
+const PROBE_ATTEMPTS: u32 = 4;
+/// Servers probed per round: the regional ones first, then the closest
+const MAX_PROBED_SERVERS: usize = 20;
+/// Failed requests counted like this much extra latency per percent when ranking
+const FAILURE_PENALTY_MS: f64 = 10.0;
+
+/// Outcome of one probe round against a server
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerProbe {
+    pub server_id: String,
+    /// Median of the answered requests; `None` when none was answered
+    pub latency_ms: Option<f64>,
+    /// Share of the round's HTTP requests that failed or timed out; not packet loss
+    pub request_failure_pct: f64,
+    pub probed_at: DateTime<Utc>,
+}
+
+impl ServerProbe {
+    /// Summarizes the round trip times of one round; `None` entries went unanswered
+    pub fn from_samples(server_id: &str, samples: &[Option<f64>], probed_at: DateTime<Utc>) -> Self {
+        let mut answered: Vec<f64> = samples.iter().flatten().copied().collect();
+        answered.sort_by(|a, b| a.total_cmp(b));
+        let latency_ms = (!answered.is_empty()).then(|| answered[answered.len() / 2]);
+        let failed = samples.len() - answered.len();
+        let request_failure_pct = if samples.is_empty() { 0.0 } else { failed as f64 * 100.0 / samples.len() as f64 };
+        Self { server_id: server_id.to_string(), latency_ms, request_failure_pct, probed_at }
+    }
+
+    /// Whether no request came back
+    pub fn is_dead(&self) -> bool {
+        self.latency_ms.is_none()
+    }
+
+    /// Ranking cost, lower is better; `None` for dead servers
+    fn cost(&self) -> Option<f64> {
+        self.latency_ms.map(|latency| latency + self.request_failure_pct * FAILURE_PENALTY_MS)
+    }
+}
+
+/// Orders `servers` by probe results: answering servers by latency and failed requests, then unprobed ones
+/// in their given order. Dead servers are dropped unless nothing else is left.
+pub fn rank_by_probes(servers: Vec<SpeedtestServer>, probes: &HashMap<String, ServerProbe>) -> Vec<SpeedtestServer> {
+    let (dead, mut alive): (Vec<_>, Vec<_>) = servers.into_iter()
+        .partition(|server| probes.get(&server.server_id).is_some_and(ServerProbe::is_dead));
+    if alive.is_empty() {
+        return dead;
+    }
+    // Stable sort keeps the regional order among unprobed servers
+    alive.sort_by(|a, b| {
+        let cost = |server: &SpeedtestServer| probes.get(&server.server_id).and_then(ServerProbe::cost).unwrap_or(f64::INFINITY);
+        cost(a).total_cmp(&cost(b))
+    });
+    alive
+}
+
+/// On-disk cache of the last server list fetch with its HTTP validators
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerListCache {
+    pub fetched_at: DateTime<Utc>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub servers: Vec<SpeedtestServer>,
+}
+
+impl ServerListCache {
+    /// Whether the cache is still within its TTL
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        let age = Utc::now().signed_duration_since(self.fetched_at);
+        age.to_std().map(|a| a < ttl).unwrap_or(true)
+    }
+
+    /// Loads a cache file, returning None if missing or unreadable
+    pub async fn load(path: &Path) -> Option<Self> {
+        let content = tokio::fs::read_to_string(path).await.ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Writes the cache file, creating parent directories as needed
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_string(self)?).await?;
+        Ok(())
+    }
+}
+
 /// Connection health status for a server
 #[derive(Debug, Clone)]
 pub struct ConnectionHealth {
@@ -48,6 +154,17 @@ pub struct ServerConnection {
     pub last_activity: Instant,
 }
 
+/// Great-circle distance in kilometres between two points given in degrees
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let r = 6371.0; // Earth's radius in kilometers
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    r * c
+}
+
 /// Pool of speedtest servers with connection management
 pub struct ServerPool {
     #[cfg(test)]
@@ -56,13 +173,27 @@ pub struct ServerPool {
     pub(crate) servers: Vec<SpeedtestServer>,
     connections: Arc<RwLock<HashMap<String, ServerConnection>>>,
     client: Client,
+    /// Never proxied; probes fall back to it while the proxy is unreachable
+    direct_client: Client,
     current_index: usize,
     user_location: Option<(f64, f64)>, // (latitude, longitude)
+    cache_path: Option<PathBuf>,
+    cache_ttl: Duration,
+    preferred_countries: Vec<String>,
+    limiter: OutboundLimiter,
+    probes: Arc<RwLock<HashMap<String, ServerProbe>>>,
+    custom_servers: Vec<SpeedtestServer>,
+    shared_state: Option<SharedAppState>,
 }
 
 impl ServerPool {
     pub fn new() -> Result<Self> {
-        let client = ClientBuilder::new()
+        let client = proxy::apply(ClientBuilder::new())
+            .timeout(Duration::from_secs(30))
+            .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+            .build()
+            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Failed to create HTTP client: {}", e)))?;
+        let direct_client = proxy::direct(ClientBuilder::new())
             .timeout(Duration::from_secs(30))
             .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
             .build()
@@ -72,51 +203,152 @@ impl ServerPool {
             servers: Vec::new(),
             connections: Arc::new(RwLock::new(HashMap::new())),
             client,
+            direct_client,
             current_index: 0,
             user_location: None,
+            cache_path: None,
+            cache_ttl: DEFAULT_SERVER_CACHE_TTL,
+            preferred_countries: Vec::new(),
+            limiter: OutboundLimiter::default(),
+            probes: Arc::new(RwLock::new(HashMap::new())),
+            custom_servers: Vec::new(),
+            shared_state: None,
         })
     }
 
-    /// Loads servers from speedtest.net API with regional prioritization
+    /// Probe rounds are skipped while generated traffic is held back (emergency stop, safe mode,
+    /// metered connection, calls and the like)
+    pub fn with_shared_state(mut self, shared: SharedAppState) -> Self {
+        self.shared_state = Some(shared);
+        self
+    }
+
+    /// Enables the on-disk server list cache with the given TTL
+    pub fn with_cache(mut self, path: PathBuf, ttl: Duration) -> Self {
+        self.cache_path = Some(path);
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Server countries to favour, most preferred first (e.g. from a country pack)
+    pub fn with_preferred_countries(mut self, countries: Vec<String>) -> Self {
+        self.preferred_countries = countries;
+        self
+    }
+
+    /// User-defined servers that replace the speedtest.net list when non-empty
+    pub fn with_custom_servers(mut self, servers: Vec<SpeedtestServer>) -> Self {
+        self.custom_servers = servers;
+        self
+    }
+
+    /// Counts server list fetches, connection tests and pings against the app-wide outbound limit
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Preferred server countries, falling back to the built-in regional order
+    pub fn preferred_countries(&self) -> Vec<String> {
+        if self.preferred_countries.is_empty() {
+            DEFAULT_PREFERRED_COUNTRIES.iter().map(|c| c.to_string()).collect()
+        } else {
+            self.preferred_countries.clone()
+        }
+    }
+
+    /// Default location of the server list cache
+    pub fn default_cache_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|d| d.join("SpeedKarma").join("servers.json"))
+    }
+
+    /// Loads servers from speedtest.net API, or the custom servers when set, with regional prioritization
     pub async fn load_servers(&mut self) -> Result<()> {
-        info!("Loading speedtest servers from API");
+        let servers = if !self.custom_servers.is_empty() {
+            info!("Using {} custom speedtest servers", self.custom_servers.len());
+            self.custom_servers.clone()
+        } else {
+            info!("Loading speedtest servers from API");
+            // First try to get server list from speedtest.net
+            self.fetch_servers_from_api().await
+                .or_else(|_| self.load_fallback_servers())
+                .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Failed to load servers: {}", e)))?
+        };
+
+        self.servers = servers;
+        self.apply_distances();
+        info!("Loaded {} speedtest servers", self.servers.len());
         
-        // First try to get server list from speedtest.net
-        let servers = self.fetch_servers_from_api().await
-            .or_else(|_| self.load_fallback_servers())
-            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Failed to load servers: {}", e)))?;
+        Ok(())
+    }
 
-        // Calculate distances if we have user location
-        let mut processed_servers = servers;
+    /// Recomputes each server's distance from the user and re-sorts by priority
+    fn apply_distances(&mut self) {
         if let Some((user_lat, user_lon)) = self.user_location {
-            for server in &mut processed_servers {
-                if let (Some(lat), Some(lon)) = (server.distance, server.latency) {
-                    server.distance = Some(self.calculate_distance(user_lat, user_lon, lat, lon));
+            for server in &mut self.servers {
+                if let (Some(lat), Some(lon)) = (server.latitude, server.longitude) {
+                    server.distance = Some(haversine_km(user_lat, user_lon, lat, lon));
                 }
             }
         }
 
         // Prioritize servers for Sri Lanka and regional optimization
-        processed_servers.sort_by(|a, b| {
+        let mut servers = std::mem::take(&mut self.servers);
+        servers.sort_by(|a, b| {
             let a_priority = self.get_server_priority(a);
             let b_priority = self.get_server_priority(b);
             b_priority.partial_cmp(&a_priority).unwrap_or(std::cmp::Ordering::Equal)
         });
-
-        self.servers = processed_servers;
-        info!("Loaded {} speedtest servers", self.servers.len());
-        
-        Ok(())
+        self.servers = servers;
     }
 
-    /// Fetch servers from speedtest.net API
+    /// Fetch servers from speedtest.net API, honoring the local cache and HTTP validators
     async fn fetch_servers_from_api(&self) -> Result<Vec<SpeedtestServer>> {
+        let cached = match &self.cache_path {
+            Some(path) => ServerListCache::load(path).await,
+            None => None,
+        };
+
+        if let Some(cache) = &cached {
+            if cache.is_fresh(self.cache_ttl) && !cache.servers.is_empty() {
+                debug!("Using cached server list ({} servers)", cache.servers.len());
+                return Ok(cache.servers.clone());
+            }
+        }
+
         debug!("Fetching servers from speedtest.net API");
-        
-        let response = self.client
-            .get("https://www.speedtest.net/api/js/servers?engine=js")
-            .send()
-            .await?;
+
+        let mut request = self.client.get("https://www.speedtest.net/api/js/servers?engine=js");
+        if let Some(cache) = &cached {
+            if let Some(etag) = &cache.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cache.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let _permit = self.limiter.acquire("server list").await;
+        let response = match request.send().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                // A stale cache still beats the hardcoded fallback list
+                if let Some(cache) = cached.filter(|c| !c.servers.is_empty()) {
+                    warn!("Server list fetch failed ({}), using stale cache", e);
+                    return Ok(cache.servers);
+                }
+                return Err(e.into());
+            }
+        };
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(mut cache) = cached {
+                debug!("Server list not modified, refreshing cache timestamp");
+                cache.fetched_at = Utc::now();
+                self.store_cache(&cache).await;
+                return Ok(cache.servers);
+            }
+        }
 
         if !response.status().is_success() {
             return Err(SpeedKarmaError::NetworkUnavailable(
@@ -124,6 +356,9 @@ impl ServerPool {
             ));
         }
 
+        let etag = response.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let last_modified = response.headers().get(LAST_MODIFIED).and_then(|v| v.to_str().ok()).map(str::to_string);
+
         let servers_data: Vec<SpeedtestServerResponse> = response.json().await?;
         
         let servers: Vec<SpeedtestServer> = servers_data
@@ -137,13 +372,30 @@ impl ServerPool {
                     server_data.country,
                     server_data.sponsor,
                 )
+                .with_coordinates(server_data.latitude, server_data.longitude)
             })
             .collect();
 
         debug!("Fetched {} servers from API", servers.len());
+        self.store_cache(&ServerListCache { fetched_at: Utc::now(), etag, last_modified, servers: servers.clone() }).await;
         Ok(servers)
     }
 
+    async fn store_cache(&self, cache: &ServerListCache) {
+        if let Some(path) = &self.cache_path {
+            if let Err(e) = cache.save(path).await {
+                warn!("Failed to write server list cache: {}", e);
+            }
+        }
+    }
+
+    /// Merges the loaded servers into the `speedtest_servers` table without touching user metadata
+    pub async fn sync_to_store(&self, store: &dyn ServerStore) -> Result<usize> {
+        let merged = store.merge_speedtest_servers(&self.servers).await?;
+        info!("Merged {} servers into local database", merged);
+        Ok(merged)
+    }
+
     /// Load fallback servers for when API is unavailable
     fn load_fallback_servers(&self) -> Result<Vec<SpeedtestServer>> {
         warn!("Using fallback server list");
@@ -157,7 +409,8 @@ impl ServerPool {
                 "Dialog Axiata".to_string(),
                 "Sri Lanka".to_string(),
                 "Dialog Axiata PLC".to_string(),
-            ),
+            )
+            .with_coordinates(6.93, 79.85),
             SpeedtestServer::new(
                 "24037".to_string(),
                 "speedtest-sin1.digitalocean.com".to_string(),
@@ -165,7 +418,8 @@ impl ServerPool {
                 "Singapore".to_string(),
                 "Singapore".to_string(),
                 "DigitalOcean".to_string(),
-            ),
+            )
+            .with_coordinates(1.29, 103.85),
             SpeedtestServer::new(
                 "13623".to_string(),
                 "speedtest.slt.lk".to_string(),
@@ -173,7 +427,8 @@ impl ServerPool {
                 "Sri Lanka Telecom".to_string(),
                 "Sri Lanka".to_string(),
                 "Sri Lanka Telecom PLC".to_string(),
-            ),
+            )
+            .with_coordinates(6.93, 79.85),
             SpeedtestServer::new(
                 "28910".to_string(),
                 "speedtest-blr1.digitalocean.com".to_string(),
@@ -181,7 +436,8 @@ impl ServerPool {
                 "Bangalore".to_string(),
                 "India".to_string(),
                 "DigitalOcean".to_string(),
-            ),
+            )
+            .with_coordinates(12.97, 77.59),
             SpeedtestServer::new(
                 "15322".to_string(),
                 "lg-sin.fdcservers.net".to_string(),
@@ -189,7 +445,8 @@ impl ServerPool {
                 "Singapore".to_string(),
                 "Singapore".to_string(),
                 "FDC Servers".to_string(),
-            ),
+            )
+            .with_coordinates(1.29, 103.85),
         ];
 
         Ok(fallback_servers)
@@ -199,9 +456,14 @@ impl ServerPool {
     fn get_server_priority(&self, server: &SpeedtestServer) -> f64 {
         let mut priority = 0.0;
 
-        // Prioritize regional servers for Sri Lanka
         let country_lower = server.country.to_lowercase();
-        if country_lower.contains("sri lanka") {
+        if !self.preferred_countries.is_empty() {
+            // Configured preferences replace the built-in regional order
+            if let Some(rank) = self.preferred_countries.iter().position(|c| country_lower.contains(&c.to_lowercase())) {
+                priority += (100.0 - rank as f64 * 10.0).max(50.0);
+            }
+        } else if country_lower.contains("sri lanka") {
+            // Prioritize regional servers for Sri Lanka
             priority += 100.0;
         } else if country_lower.contains("singapore") {
             priority += 80.0;
@@ -224,28 +486,18 @@ impl ServerPool {
         priority
     }
 
-    /// Calculate distance between two points using Haversine formula
-    fn calculate_distance(&self, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-        let r = 6371.0; // Earth's radius in kilometers
-        let d_lat = (lat2 - lat1).to_radians();
-        let d_lon = (lon2 - lon1).to_radians();
-        let a = (d_lat / 2.0).sin().powi(2)
-            + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
-        let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-        r * c
-    }
-
     /// Establish persistent connection to a server
     pub async fn connect_to_server(&self, server: &SpeedtestServer) -> Result<()> {
         debug!("Establishing connection to server: {} ({})", server.name, server.host);
 
-        let client = ClientBuilder::new()
+        let client = proxy::apply(ClientBuilder::new())
             .timeout(Duration::from_secs(10))
             .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
             .build()?;
 
         // Test connection with a lightweight request
-        let test_url = format!("http://{}:{}/speedtest/latency.txt", server.host, server.port);
+        let test_url = server.endpoint_url(false, ServerEndpoint::Latency, 0, &Utc::now().timestamp_millis().to_string());
+        let _permit = self.limiter.acquire("server connect").await;
         let start_time = Instant::now();
         
         let response = client
@@ -379,7 +631,8 @@ impl ServerPool {
 
     /// Ping a server to check connection health
     async fn ping_server(&self, connection: &ServerConnection) -> Result<f64> {
-        let ping_url = format!("http://{}:{}/speedtest/latency.txt", connection.server.host, connection.server.port);
+        let ping_url = connection.server.endpoint_url(false, ServerEndpoint::Latency, 0, &Utc::now().timestamp_millis().to_string());
+        let _permit = self.limiter.acquire("server ping").await;
         let start_time = Instant::now();
         
         let response = connection.client
@@ -430,6 +683,98 @@ impl ServerPool {
         servers.into_iter().take(count).collect()
     }
 
+    /// Servers worth probing: the regional ones stealth rotation draws from, then the closest
+    fn probe_candidates(&self) -> Vec<SpeedtestServer> {
+        let mut candidates: Vec<SpeedtestServer> = Vec::new();
+        let regional = self.preferred_countries().into_iter().flat_map(|country| self.get_regional_servers(&country));
+        for server in regional.chain(self.get_closest_servers(5)) {
+            // The user picked custom servers; a file server without a latency endpoint must not be deactivated
+            if server.is_custom() {
+                continue;
+            }
+            if candidates.len() < MAX_PROBED_SERVERS && !candidates.iter().any(|c| c.server_id == server.server_id) {
+                candidates.push(server.clone());
+            }
+        }
+        candidates
+    }
+
+    /// Times `PROBE_ATTEMPTS` latency requests to one server, through the proxy while it is reachable
+    pub async fn probe_server(&self, server: &SpeedtestServer) -> ServerProbe {
+        let client = match proxy::measurement_route(&server.host).await {
+            Some(_) => &self.client,
+            None => &self.direct_client,
+        };
+        let mut samples = Vec::with_capacity(PROBE_ATTEMPTS as usize);
+        for attempt in 0..PROBE_ATTEMPTS {
+            let url = server.endpoint_url(false, ServerEndpoint::Latency, 0, &format!("{}{}", Utc::now().timestamp_millis(), attempt));
+            let _permit = self.limiter.acquire("server probe").await;
+            let started = Instant::now();
+            let answered = matches!(
+                client.get(&url).timeout(Duration::from_secs(3)).send().await,
+                Ok(resp) if resp.status().is_success()
+            );
+            samples.push(answered.then(|| started.elapsed().as_secs_f64() * 1000.0));
+        }
+        ServerProbe::from_samples(&server.server_id, &samples, Utc::now())
+    }
+
+    /// Probes the candidate servers, keeps the results for ranking and stores them with each server;
+    /// servers that answered nothing are stored as inactive. Returns how many answered.
+    pub async fn probe_servers(&self, store: &dyn ServerStore) -> Result<usize> {
+        let mut answered = 0;
+        for server in self.probe_candidates() {
+            let probe = self.probe_server(&server).await;
+            if probe.is_dead() {
+                debug!("Server {} ({}) did not answer latency probes", server.name, server.host);
+            } else {
+                answered += 1;
+            }
+            store.record_server_probe(&server.server_id, probe.latency_ms, probe.request_failure_pct, probe.probed_at, !probe.is_dead()).await?;
+            self.probes.write().await.insert(server.server_id.clone(), probe);
+        }
+        info!("Probed servers: {} answering", answered);
+        Ok(answered)
+    }
+
+    /// Whether a probe round may send traffic now
+    async fn may_probe(&self) -> bool {
+        match &self.shared_state {
+            Some(shared) => shared.read().await.may_load_link(),
+            None => true,
+        }
+    }
+
+    /// Latest probe results by server id
+    pub async fn probes(&self) -> HashMap<String, ServerProbe> {
+        self.probes.read().await.clone()
+    }
+
+    /// `servers` reordered by the latest probes, dead ones left out
+    pub async fn rank(&self, servers: Vec<SpeedtestServer>) -> Vec<SpeedtestServer> {
+        rank_by_probes(servers, &*self.probes.read().await)
+    }
+
+    /// Re-probes the candidates every `interval` until `shutdown` is cancelled
+    pub fn start_probing(self: Arc<Self>, store: Arc<dyn ServerStore>, interval: Duration, shutdown: Option<CancellationToken>) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = shutdown::cancelled(shutdown.as_ref()) => break,
+                }
+                if !self.may_probe().await {
+                    debug!("Generated traffic is held back; skipping server probes");
+                    continue;
+                }
+                if let Err(e) = self.probe_servers(&*store).await {
+                    warn!("Server probing failed: {}", e);
+                }
+            }
+        });
+    }
+
     /// Get currently connected servers
     pub async fn get_connected_servers(&self) -> Vec<String> {
         let connections = self.connections.read().await;
@@ -466,10 +811,36 @@ impl ServerPool {
         }
     }
 
-    /// Set user location for distance calculations
+    /// Set user location for distance calculations; already loaded servers are re-ranked
     pub fn set_user_location(&mut self, latitude: f64, longitude: f64) {
         self.user_location = Some((latitude, longitude));
         info!("User location set to: {:.4}, {:.4}", latitude, longitude);
+        self.apply_distances();
+    }
+
+    /// User location used for distances, if known
+    pub fn user_location(&self) -> Option<(f64, f64)> {
+        self.user_location
+    }
+
+    /// Sets the user location from the manual config, else from public-IP geolocation
+    pub async fn locate_user(&mut self, config: &UserLocationConfig, lookup: &PublicIpLookup) -> Option<(f64, f64)> {
+        let location = match config.manual() {
+            Some(location) => Some(location),
+            None if config.ip_geolocation => match lookup.lookup().await {
+                Ok(info) => info.coordinates(),
+                Err(e) => {
+                    debug!("Could not geolocate public IP: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        match location {
+            Some((latitude, longitude)) => self.set_user_location(latitude, longitude),
+            None => debug!("User location unknown; servers are ranked without distance"),
+        }
+        location
     }
 
     /// Get servers count (for testing)
@@ -490,4 +861,137 @@ pub struct ConnectionStats {
     pub healthy_connections: usize,
     pub average_latency_ms: Option<f64>,
     pub servers_available: usize,
-}
\ No newline at end of file
+}
+/// One server the app sends traffic to, placed for the panel's map
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerMapPoint {
+    pub server_id: String,
+    pub name: String,
+    pub sponsor: String,
+    pub country: String,
+    pub host: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub latency_ms: Option<f64>,
+    /// Keeper, speed test and stealth sends combined
+    pub use_count: u32,
+    pub last_used: Option<DateTime<Utc>>,
+    /// In the stealth rotation
+    pub in_rotation: bool,
+    /// The rotation server stealth traffic currently goes to
+    pub current: bool,
+}
+
+/// Merges the stealth rotation with stored servers that traffic was sent to, busiest first.
+/// Stored counts cover keeper and speed test sends, rotation counts cover stealth sends.
+pub fn server_map_points(rotation: &[SpeedtestServer], current_server_id: Option<&str>, stored: &[SpeedtestServer]) -> Vec<ServerMapPoint> {
+    let mut points: Vec<ServerMapPoint> = Vec::new();
+    let used_stored = stored.iter().filter(|s| s.use_count > 0 || s.last_used.is_some());
+    for (server, in_rotation) in rotation.iter().map(|s| (s, true)).chain(used_stored.map(|s| (s, false))) {
+        if let Some(point) = points.iter_mut().find(|p| p.server_id == server.server_id) {
+            point.use_count += server.use_count;
+            point.last_used = point.last_used.max(server.last_used);
+            point.latitude = point.latitude.or(server.latitude);
+            point.longitude = point.longitude.or(server.longitude);
+            point.latency_ms = point.latency_ms.or(server.latency);
+            continue;
+        }
+        points.push(ServerMapPoint {
+            server_id: server.server_id.clone(),
+            name: server.name.clone(),
+            sponsor: server.sponsor.clone(),
+            country: server.country.clone(),
+            host: server.host.clone(),
+            latitude: server.latitude,
+            longitude: server.longitude,
+            latency_ms: server.latency,
+            use_count: server.use_count,
+            last_used: server.last_used,
+            in_rotation,
+            current: in_rotation && current_server_id == Some(server.server_id.as_str()),
+        });
+    }
+    points.sort_by(|a, b| b.use_count.cmp(&a.use_count).then_with(|| a.name.cmp(&b.name)));
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(id: &str, uses: u32) -> SpeedtestServer {
+        let mut s = SpeedtestServer::new(id.to_string(), format!("{}.example", id), 8080, id.to_string(), "Singapore".to_string(), "Test".to_string());
+        s.use_count = uses;
+        s
+    }
+
+    #[test]
+    fn test_server_map_points_merge_rotation_and_store() {
+        let rotation = vec![server("a", 3).with_coordinates(1.29, 103.85), server("b", 0)];
+        let mut stored_a = server("a", 2);
+        stored_a.latency = Some(40.0);
+        let stored = vec![stored_a, server("c", 5), server("idle", 0)];
+
+        let points = server_map_points(&rotation, Some("b"), &stored);
+        let ids: Vec<&str> = points.iter().map(|p| p.server_id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c", "b"]);
+        assert_eq!(points[0].use_count, 5);
+        assert_eq!((points[0].latitude, points[0].latency_ms), (Some(1.29), Some(40.0)));
+        assert!(points[0].in_rotation && !points[0].current);
+        assert!(!points[1].in_rotation);
+        assert!(points[2].current);
+    }
+
+    #[test]
+    fn test_probes_rank_servers_and_drop_dead_ones() {
+        let at = Utc::now();
+        let probes: HashMap<String, ServerProbe> = [
+            ServerProbe::from_samples("slow", &[Some(80.0), Some(90.0), Some(85.0), Some(300.0)], at),
+            ServerProbe::from_samples("fast", &[Some(20.0), Some(25.0), None, Some(22.0)], at),
+            ServerProbe::from_samples("dead", &[None, None, None, None], at),
+        ].into_iter().map(|p| (p.server_id.clone(), p)).collect();
+        assert_eq!((probes["slow"].latency_ms, probes["slow"].request_failure_pct), (Some(90.0), 0.0));
+        assert_eq!((probes["fast"].latency_ms, probes["fast"].request_failure_pct), (Some(22.0), 25.0));
+        assert!(probes["dead"].is_dead());
+
+        // 25% failed requests cost 250 ms, so the server that answered every time ranks first; unprobed ones come last
+        let ranked = rank_by_probes(vec![server("dead", 0), server("new", 0), server("fast", 0), server("slow", 0)], &probes);
+        let ids: Vec<&str> = ranked.iter().map(|s| s.server_id.as_str()).collect();
+        assert_eq!(ids, vec!["slow", "fast", "new"]);
+
+        // Nothing else left: dead servers stay rather than emptying the rotation
+        assert_eq!(rank_by_probes(vec![server("dead", 0)], &probes).len(), 1);
+    }
+
+    #[test]
+    fn test_preferred_countries_order_priority() {
+        let in_country = |country: &str| SpeedtestServer::new(country.to_string(), "h.example".to_string(), 8080, country.to_string(), country.to_string(), "Test".to_string());
+        let default_pool = ServerPool::new().unwrap();
+        assert_eq!(default_pool.preferred_countries(), vec!["Sri Lanka", "Singapore", "India"]);
+        assert!(default_pool.get_server_priority(&in_country("Sri Lanka")) > default_pool.get_server_priority(&in_country("Philippines")));
+
+        let pool = ServerPool::new().unwrap().with_preferred_countries(vec!["Philippines".into(), "Singapore".into()]);
+        let ph = pool.get_server_priority(&in_country("Philippines"));
+        let sg = pool.get_server_priority(&in_country("Singapore"));
+        let lk = pool.get_server_priority(&in_country("Sri Lanka"));
+        assert!(ph > sg && sg > lk);
+    }
+
+    #[tokio::test]
+    async fn test_manual_location_sets_real_distances() {
+        assert!((haversine_km(6.93, 79.85, 1.29, 103.85) - 2733.5).abs() < 1.0);
+
+        let mut pool = ServerPool::new().unwrap();
+        pool.set_servers(vec![
+            server("colombo", 0).with_coordinates(6.93, 79.85),
+            server("bangalore", 0).with_coordinates(12.97, 77.59),
+            server("unplaced", 0),
+        ]);
+        let config = UserLocationConfig { latitude: Some(1.29), longitude: Some(103.85), ip_geolocation: false };
+        assert_eq!(pool.locate_user(&config, &PublicIpLookup::default()).await, Some((1.29, 103.85)));
+
+        let closest: Vec<(&str, Option<f64>)> = pool.get_closest_servers(3).iter().map(|s| (s.server_id.as_str(), s.distance.map(f64::round))).collect();
+        assert_eq!(closest, vec![("colombo", Some(2734.0)), ("bangalore", Some(3170.0)), ("unplaced", None)]);
+        assert!(pool.locate_user(&UserLocationConfig { ip_geolocation: false, ..Default::default() }, &PublicIpLookup::default()).await.is_none());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::core::app_state::SharedAppState;
+use crate::core::config::AppConfig;
+use crate::core::events::SharedEventSink;
+use crate::data::models::{MeasurementSource, SpeedMeasurement};
+pub use crate::data::models::PendingRetry;
+use crate::data::repository::Repository;
+use crate::network::speedtest_runner::SpeedtestRunner;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::usage::DataUsageMeter;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Timelike, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// How often due retries are looked for
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// A retry lands on one of this many following days
+const LOOKAHEAD_DAYS: i64 = 6;
+/// Active tests this far back decide which weekday a retry fills
+const COVERAGE_DAYS: i64 = 28;
+
+/// Same hour of day (UTC, like the model's temporal buckets) as `failed_at`, on the following day
+/// whose weekday has the fewest active tests in that hour, counting retries already queued.
+/// Ties go to the earliest day, so a gap is filled as soon as possible.
+pub fn next_slot(failed_at: DateTime<Utc>, active: &[SpeedMeasurement], queued: &[DateTime<Utc>]) -> DateTime<Utc> {
+    let hour = failed_at.hour();
+    let in_slot = |t: &DateTime<Utc>, day: chrono::Weekday| t.weekday() == day && t.hour() == hour;
+    (1..=LOOKAHEAD_DAYS)
+        .map(|d| failed_at + ChronoDuration::days(d))
+        .min_by_key(|candidate| {
+            let day = candidate.weekday();
+            active.iter().filter(|m| in_slot(&m.timestamp, day)).count() + queued.iter().filter(|t| in_slot(t, day)).count()
+        })
+        .unwrap_or(failed_at + ChronoDuration::days(1))
+}
+
+/// Reschedules failed active speed tests into the same hour of day later in the week instead of
+/// dropping them, so the model keeps balanced coverage of every hour. The queue is stored, so
+/// retries survive a restart.
+pub struct SpeedtestRetryQueue {
+    events: SharedEventSink,
+    repository: Arc<Repository>,
+    shared: SharedAppState,
+    pending: Mutex<Vec<PendingRetry>>,
+    usage: Option<DataUsageMeter>,
+    limiter: Option<OutboundLimiter>,
+}
+
+impl SpeedtestRetryQueue {
+    pub fn new(events: SharedEventSink, repository: Arc<Repository>, shared: SharedAppState) -> Self {
+        Self { events, repository, shared, pending: Mutex::new(Vec::new()), usage: None, limiter: None }
+    }
+
+    /// Counts the bytes of retried tests against the monthly data cap
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Lets the emergency stop abort a retried test mid-run
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    pub async fn pending(&self) -> Vec<PendingRetry> {
+        self.pending.lock().await.clone()
+    }
+
+    /// Restores the retries queued before the last shutdown; overdue ones run on the next check
+    pub async fn load(&self) {
+        match self.repository.get_speedtest_retries().await {
+            Ok(stored) => *self.pending.lock().await = stored,
+            Err(e) => warn!("Could not restore queued speed test retries: {}", e),
+        }
+    }
+
+    async fn persist(&self, pending: &[PendingRetry]) {
+        if let Err(e) = self.repository.replace_speedtest_retries(pending).await {
+            warn!("Could not store queued speed test retries: {}", e);
+        }
+    }
+
+    /// Queues a retry for a test that failed at `failed_at` on its `attempt`-th retry (0 for the
+    /// original run). Returns the slot, or `None` once `max_retries` are used up.
+    pub async fn schedule(&self, failed_at: DateTime<Utc>, attempt: u32, max_retries: u32, reason: String) -> Option<DateTime<Utc>> {
+        if attempt >= max_retries {
+            info!("Speed test failed ({}); giving up after {} retries", reason, attempt);
+            return None;
+        }
+        let active: Vec<SpeedMeasurement> = match self.repository.get_speed_measurements_since(failed_at - ChronoDuration::days(COVERAGE_DAYS)).await {
+            Ok(rows) => rows.into_iter().filter(|m| m.source == MeasurementSource::Active).collect(),
+            Err(e) => {
+                warn!("Could not read test coverage for the retry slot: {}", e);
+                Vec::new()
+            }
+        };
+        let pending = {
+            let mut pending = self.pending.lock().await;
+            let queued: Vec<DateTime<Utc>> = pending.iter().map(|p| p.due).collect();
+            let due = next_slot(failed_at, &active, &queued);
+            info!("Speed test failed ({}); retry {} scheduled for {}", reason, attempt + 1, due);
+            pending.push(PendingRetry { due, attempt: attempt + 1, reason });
+            pending.sort_by_key(|p| p.due);
+            pending.clone()
+        };
+        self.persist(&pending).await;
+        self.events.emit_payload("speedtest_retries", &pending);
+        pending.last().map(|p| p.due)
+    }
+
+    pub fn start(self: Arc<Self>) {
+        let queue = Arc::clone(&self);
+        tokio::spawn(async move {
+            queue.load().await;
+            queue.run_loop().await;
+        });
+    }
+
+    async fn run_loop(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            // A retry started during a call would only fail again and burn an attempt; due ones
+            // wait for the call to end
+            if self.shared.read().await.call_active { continue; }
+            let (due, later): (Vec<PendingRetry>, Vec<PendingRetry>) = {
+                let mut pending = self.pending.lock().await;
+                let now = Utc::now();
+                let (due, later) = pending.drain(..).partition(|p| p.due <= now);
+                *pending = later.clone();
+                (due, later)
+            };
+            if due.is_empty() { continue; }
+            self.persist(&later).await;
+            // One at a time, so retries never load the link together
+            for retry in due {
+                let cfg = match AppConfig::load().await {
+                    Ok(cfg) => cfg.effective().advanced.speedtest_runner,
+                    Err(e) => { warn!("Speed test retry skipped: {}", e); continue; }
+                };
+                let mut runner = SpeedtestRunner::new(Arc::clone(&self.events), Arc::clone(&self.repository), Arc::clone(&self.shared), cfg)
+                    .with_retries(Arc::clone(&self), retry.attempt);
+                if let Some(usage) = &self.usage {
+                    runner = runner.with_usage_meter(usage.clone());
+                }
+                if let Some(limiter) = &self.limiter {
+                    runner = runner.with_limiter(limiter.clone());
+                }
+                info!("Retrying speed test (retry {}, previously: {})", retry.attempt, retry.reason);
+                let _ = runner.run_once().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Weekday;
+
+    #[test]
+    fn test_retry_fills_the_thinnest_weekday_at_the_same_hour() {
+        // A Monday 20:15 failure
+        let failed_at = DateTime::parse_from_rfc3339("2026-10-12T20:15:00Z").unwrap().with_timezone(&Utc);
+        let active_at = |day: i64, hour: u32| SpeedMeasurement {
+            timestamp: (failed_at - ChronoDuration::days(day)).with_hour(hour).unwrap(),
+            ..SpeedMeasurement::new(30.0, 5.0, 20, true).with_source(MeasurementSource::Active)
+        };
+        // 20:00 is covered on every weekday but Thursday; Thursday only has other hours
+        let mut active: Vec<SpeedMeasurement> = (1..=7).filter(|d| *d != 4).map(|d| active_at(d, 20)).collect();
+        active.push(active_at(4, 9));
+
+        let slot = next_slot(failed_at, &active, &[]);
+        assert_eq!((slot.weekday(), slot.hour(), slot.minute()), (Weekday::Thu, 20, 15));
+
+        // With Thursday already queued, the next retry takes the earliest remaining day
+        let slot = next_slot(failed_at, &active, &[slot]);
+        assert_eq!(slot.weekday(), Weekday::Tue);
+        assert!(slot > failed_at && slot - failed_at <= ChronoDuration::days(LOOKAHEAD_DAYS));
+    }
+}
@@ -1,14 +1,21 @@
-use crate::core::app_state::{OptimizationMode, SharedAppState};
+use crate::core::app_state::{self, SharedAppState};
 use crate::core::config::SpeedtestRunnerConfig;
 use crate::core::error::Result;
+use crate::core::events::SharedEventSink;
 use crate::data::repository::Repository;
-use crate::data::models::StealthLevel;
+use crate::data::models::{MeasurementMethod, MeasurementSource, ServerEndpoint, SpeedMeasurement, SpeedtestServer, StealthLevel, TrafficSource};
+use crate::network::context;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use crate::network::speedtest_retry::SpeedtestRetryQueue;
+use crate::network::usage::DataUsageMeter;
 use chrono::Utc;
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT, ACCEPT, ACCEPT_LANGUAGE, ACCEPT_ENCODING, CACHE_CONTROL, CONNECTION};
 use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tauri::{AppHandle, Manager};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
 use tokio::time::{sleep, timeout};
 use tracing::{info, warn, debug};
 
@@ -20,16 +27,62 @@ pub struct SpeedtestProgressPayload {
     pub elapsed_s: u32,
 }
 
+/// Where a run is, for callers that sample something else over exactly the same seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedtestPhase {
+    Starting,
+    Download,
+    Upload,
+    Done,
+}
+
 pub struct SpeedtestRunner {
-    app: AppHandle,
+    events: SharedEventSink,
     repository: Arc<Repository>,
     shared: SharedAppState,
     config: SpeedtestRunnerConfig,
+    retries: Option<Arc<SpeedtestRetryQueue>>,
+    /// Retry number of this run; 0 for a first attempt
+    attempt: u32,
+    usage: Option<DataUsageMeter>,
+    phases: Option<watch::Sender<SpeedtestPhase>>,
+    limiter: Option<OutboundLimiter>,
 }
 
 impl SpeedtestRunner {
-    pub fn new(app: AppHandle, repository: Arc<Repository>, shared: SharedAppState, config: SpeedtestRunnerConfig) -> Self {
-        Self { app, repository, shared, config }
+    pub fn new(events: SharedEventSink, repository: Arc<Repository>, shared: SharedAppState, config: SpeedtestRunnerConfig) -> Self {
+        Self { events, repository, shared, config, retries: None, attempt: 0, usage: None, phases: None, limiter: None }
+    }
+
+    /// Reschedules the test through `queue` if it fails
+    pub fn with_retries(mut self, queue: Arc<SpeedtestRetryQueue>, attempt: u32) -> Self {
+        self.retries = Some(queue);
+        self.attempt = attempt;
+        self
+    }
+
+    /// Counts the bytes the test moves against the monthly data cap
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Reports each phase as it starts; `Done` follows every run, including skipped and failed ones
+    pub fn with_phase_updates(mut self, phases: watch::Sender<SpeedtestPhase>) -> Self {
+        self.phases = Some(phases);
+        self
+    }
+
+    /// Aborts the streams of a run in progress when the emergency stop is engaged
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    fn enter(&self, phase: SpeedtestPhase) {
+        if let Some(phases) = &self.phases {
+            phases.send_replace(phase);
+        }
     }
 
     fn build_headers() -> HeaderMap {
@@ -43,47 +96,91 @@ impl SpeedtestRunner {
         headers
     }
 
-    async fn pick_server(&self, stealth_level: &StealthLevel) -> Option<String> {
-        if let Ok(servers) = self.repository.get_active_speedtest_servers().await {
-            if let Some(s) = servers.first() {
-                let scheme = if matches!(stealth_level, StealthLevel::Maximum) { "https" } else { "http" };
-                return Some(format!("{}://{}:{}/", scheme, s.host, s.port));
-            }
+    async fn pick_server(&self) -> SpeedtestServer {
+        match self.repository.get_active_speedtest_servers().await {
+            Ok(servers) => servers.into_iter().next().unwrap_or_else(SpeedtestServer::cloudflare),
+            Err(_) => SpeedtestServer::cloudflare(),
         }
-        Some("https://speed.cloudflare.com/".to_string())
     }
 
     pub async fn run_once(&self) -> Result<()> {
-        if !self.config.enabled { return Ok(()); }
-        let enabled = { let s = self.shared.read().await; matches!(s.optimization_mode, OptimizationMode::Enabled) };
-        if !enabled { return Ok(()); }
+        let result = self.measure().await;
+        self.enter(SpeedtestPhase::Done);
+        let failure = match &result {
+            Ok(failure) => failure.clone(),
+            Err(e) => Some(e.to_string()),
+        };
+        if let (Some(reason), Some(queue)) = (failure, &self.retries) {
+            queue.schedule(Utc::now(), self.attempt, self.config.max_retries, reason).await;
+        }
+        result.map(|_| ())
+    }
+
+    /// Runs the test; returns why it failed, or `None` when it completed or was not allowed to run
+    async fn measure(&self) -> Result<Option<String>> {
+        if !self.config.enabled { return Ok(None); }
+        let (enabled, in_call) = { let s = self.shared.read().await; (s.may_generate() && s.modules.active_testing, s.call_active) };
+        if !enabled { return Ok(None); }
+        let cancel = self.limiter.as_ref().map(OutboundLimiter::traffic_token).unwrap_or_default();
+        if cancel.is_cancelled() { return Ok(None); }
+        if in_call {
+            info!("Speed test skipped: a call is in progress");
+            return Ok(Some("a call was in progress".into()));
+        }
 
         // Choose server and client
-        let stealth_level = match self.repository.get_best_optimization_strategy().await {
+        let stealth_level = match app_state::active_strategy(&self.shared, &*self.repository).await {
             Ok(Some(s)) => s.stealth_level,
             _ => StealthLevel::Medium,
         };
-        let base = match self.pick_server(&stealth_level).await { Some(b)=>b, None=>return Ok(()) };
-        let client = reqwest::Client::builder().default_headers(Self::build_headers()).pool_idle_timeout(Duration::from_secs(30)).build()?;
+        let server = self.pick_server().await;
+        if server.id.is_some() {
+            if let Err(e) = self.repository.update_server_last_used(&server.server_id).await {
+                debug!("Failed to record speed test server use: {}", e);
+            }
+        }
+        let secure = matches!(stealth_level, StealthLevel::Maximum);
+        let (builder, via_proxy) = proxy::measurement(reqwest::Client::builder(), &server.host).await;
+        let client = builder.default_headers(Self::build_headers()).pool_idle_timeout(Duration::from_secs(30)).build()?;
+
+        // Latency: round trip of a lightweight request before loading the link
+        let ping_url = server.endpoint_url(secure, ServerEndpoint::Latency, 0, "0");
+        let ping_start = Instant::now();
+        let latency_ms = match timeout(Duration::from_secs(5), client.head(&ping_url).send()).await {
+            Ok(Ok(_)) => Some(ping_start.elapsed().as_millis().min(10_000) as u32),
+            _ => None,
+        };
 
         // Download phase: open parallel streams and fully read bodies until time expires
         let dl_secs = self.config.download_duration_s.max(1);
-        let end_time = std::time::Instant::now() + Duration::from_secs(dl_secs as u64);
-        let is_cloudflare = base.contains("speed.cloudflare.com");
+        self.enter(SpeedtestPhase::Download);
+        let start_dl = Instant::now();
+        let end_time = start_dl + Duration::from_secs(dl_secs as u64);
+        let downloaded = Arc::new(AtomicU64::new(0));
         let mut tasks = Vec::new();
         for i in 0..self.config.parallel_connections.max(1) as usize {
             let client_cl = client.clone();
-            let base_cl = base.clone();
+            let server_cl = server.clone();
+            let downloaded_cl = Arc::clone(&downloaded);
+            let cancel_cl = cancel.clone();
+            let limiter_cl = self.limiter.clone();
             tasks.push(tokio::spawn(async move {
+                // Each stream holds an outbound slot for the whole phase
+                let _permit = match &limiter_cl { Some(limiter) => Some(limiter.acquire("speed test download").await), None => None };
                 let mut seed: u64 = i as u64 + 1;
                 while std::time::Instant::now() < end_time {
-                    let url = if is_cloudflare {
-                        format!("{}__down?bytes=16777216&seed={}", base_cl, seed)
-                    } else {
-                        format!("{}speedtest/random4000x4000.jpg?r={}", base_cl, seed)
+                    let url = server_cl.endpoint_url(secure, ServerEndpoint::Download, 16_777_216, &seed.to_string());
+                    let pull = async {
+                        if let Ok(mut resp) = client_cl.get(&url).send().await {
+                            // fully consume to pull bandwidth, counting as it arrives so a stop keeps the partial bytes
+                            while let Ok(Some(chunk)) = resp.chunk().await {
+                                downloaded_cl.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                            }
+                        }
                     };
-                    if let Ok(resp) = client_cl.get(&url).send().await {
-                        let _ = resp.bytes().await; // fully consume to pull bandwidth
+                    tokio::select! {
+                        _ = cancel_cl.cancelled() => break,
+                        _ = pull => {}
                     }
                     seed = seed.wrapping_add(1);
                 }
@@ -91,40 +188,100 @@ impl SpeedtestRunner {
         }
         // Emit progress ticks during download
         loop {
-            let now = std::time::Instant::now();
-            if now >= end_time { break; }
+            let now = Instant::now();
+            if now >= end_time || cancel.is_cancelled() { break; }
             let elapsed = (dl_secs as u64).saturating_sub((end_time - now).as_secs());
-            let _ = self.app.emit_all("speedtest_progress", SpeedtestProgressPayload { phase: "download".into(), down_mbps: 0.0, up_mbps: 0.0, elapsed_s: elapsed as u32 });
+            let down_mbps = Self::mbps(downloaded.load(Ordering::Relaxed), start_dl.elapsed());
+            self.events.emit_payload("speedtest_progress", &SpeedtestProgressPayload { phase: "download".into(), down_mbps, up_mbps: 0.0, elapsed_s: elapsed as u32 });
             sleep(Duration::from_millis(300)).await;
         }
         for t in tasks { let _ = t.await; }
+        let down_mbps = Self::mbps(downloaded.load(Ordering::Relaxed), start_dl.elapsed());
+        if cancel.is_cancelled() {
+            return Ok(self.stopped(downloaded.load(Ordering::Relaxed)).await);
+        }
 
         // Upload phase: push random data to upload endpoints
         let ul_secs = self.config.upload_duration_s.max(1);
-        let start_ul = std::time::Instant::now();
+        self.enter(SpeedtestPhase::Upload);
+        let start_ul = Instant::now();
+        let uploaded = Arc::new(AtomicU64::new(0));
         let mut tasks_ul = Vec::new();
         for _i in 0..self.config.parallel_connections.max(1) as usize {
-            let url = if is_cloudflare { format!("{}__up", base) } else { format!("{}speedtest/upload.php", base) };
+            let url = server.endpoint_url(secure, ServerEndpoint::Upload, 0, "");
             let body = vec![0u8; 2_000_000]; // ~2MB per request, repeated
             let client_cl = client.clone();
+            let uploaded_cl = Arc::clone(&uploaded);
+            let cancel_cl = cancel.clone();
+            let limiter_cl = self.limiter.clone();
             tasks_ul.push(tokio::spawn(async move {
-                let _ = timeout(Duration::from_secs(ul_secs as u64), async {
+                let _permit = match &limiter_cl { Some(limiter) => Some(limiter.acquire("speed test upload").await), None => None };
+                let push = timeout(Duration::from_secs(ul_secs as u64), async {
                     loop {
-                        let _ = client_cl.post(&url).body(body.clone()).send().await;
+                        if client_cl.post(&url).body(body.clone()).send().await.is_ok() {
+                            uploaded_cl.fetch_add(body.len() as u64, Ordering::Relaxed);
+                        }
                     }
-                }).await;
+                });
+                tokio::select! {
+                    _ = cancel_cl.cancelled() => {}
+                    _ = push => {}
+                }
             }));
         }
         loop {
             let elapsed = start_ul.elapsed().as_secs();
-            if elapsed >= ul_secs as u64 { break; }
-            let _ = self.app.emit_all("speedtest_progress", SpeedtestProgressPayload { phase: "upload".into(), down_mbps: 0.0, up_mbps: 0.0, elapsed_s: elapsed as u32 });
+            if elapsed >= ul_secs as u64 || cancel.is_cancelled() { break; }
+            let up_mbps = Self::mbps(uploaded.load(Ordering::Relaxed), start_ul.elapsed());
+            self.events.emit_payload("speedtest_progress", &SpeedtestProgressPayload { phase: "upload".into(), down_mbps, up_mbps, elapsed_s: (dl_secs as u64 + elapsed) as u32 });
             sleep(Duration::from_millis(300)).await;
         }
         for t in tasks_ul { let _ = t.await; }
+        let up_mbps = Self::mbps(uploaded.load(Ordering::Relaxed), start_ul.elapsed());
+        if cancel.is_cancelled() {
+            return Ok(self.stopped(downloaded.load(Ordering::Relaxed) + uploaded.load(Ordering::Relaxed)).await);
+        }
+        // Counted once, after both phases, so the data cap sees each byte a single time
+        if let Some(usage) = &self.usage {
+            usage.record(TrafficSource::Speedtest, downloaded.load(Ordering::Relaxed) + uploaded.load(Ordering::Relaxed)).await;
+        }
+
+        // Record the result so status displays can show it as an active measurement
+        let completed = down_mbps > 0.0 || up_mbps > 0.0;
+        if completed {
+            let measurement = SpeedMeasurement {
+                network_context: context::current().await.key(),
+                latency_ms,
+                via_proxy,
+                ..SpeedMeasurement::new(down_mbps, up_mbps, 0, true)
+                    .with_source(MeasurementSource::Active)
+                    .with_method(MeasurementMethod::Http)
+            };
+            match self.repository.save_speed_measurement(&measurement).await {
+                Ok(_) => info!("Speed test finished: {:.1} Mbps down, {:.1} Mbps up, latency {}", down_mbps, up_mbps, latency_ms.map_or("unmeasured".to_string(), |ms| format!("{} ms", ms))),
+                Err(e) => warn!("Failed to save speed test result: {}", e),
+            }
+        }
+
+        self.events.emit_payload("speedtest_progress", &SpeedtestProgressPayload { phase: "done".into(), down_mbps, up_mbps, elapsed_s: (dl_secs+ul_secs) });
+        Ok((!completed).then(|| format!("no data transferred with {}", server.name)))
+    }
+
+    /// Ends a run cut short by the emergency stop: the bytes still count, but nothing is saved
+    /// and nothing is retried
+    async fn stopped(&self, bytes: u64) -> Option<String> {
+        info!("Speed test stopped by the emergency stop");
+        if let Some(usage) = &self.usage {
+            usage.record(TrafficSource::Speedtest, bytes).await;
+        }
+        self.events.emit_payload("speedtest_progress", &SpeedtestProgressPayload { phase: "stopped".into(), down_mbps: 0.0, up_mbps: 0.0, elapsed_s: 0 });
+        None
+    }
 
-        let _ = self.app.emit_all("speedtest_progress", SpeedtestProgressPayload { phase: "done".into(), down_mbps: 0.0, up_mbps: 0.0, elapsed_s: (dl_secs+ul_secs) });
-        Ok(())
+    fn mbps(bytes: u64, elapsed: Duration) -> f64 {
+        let secs = elapsed.as_secs_f64();
+        if secs <= 0.0 { return 0.0; }
+        (bytes as f64 * 8.0) / secs / 1_000_000.0
     }
 }
 
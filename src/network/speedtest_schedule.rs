@@ -0,0 +1,359 @@
+use crate::core::app_state::SharedAppState;
+use crate::core::config::{AdvancedConfig, AppConfig, Iperf3Config, SpeedtestRunnerConfig};
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::core::events::SharedEventSink;
+use crate::core::shutdown::{self, CancellationToken};
+use crate::data::repository::Repository;
+use crate::network::speedtest_retry::SpeedtestRetryQueue;
+use crate::network::iperf3::Iperf3Runner;
+use crate::network::speedtest_runner::SpeedtestRunner;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::usage::DataUsageMeter;
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// How often the clock is compared with the schedule; under a minute so no minute is skipped
+const CHECK_INTERVAL: Duration = Duration::from_secs(20);
+/// Furthest ahead `next_after` looks; far enough for `0 0 29 2 *`
+const SEARCH_DAYS: i64 = 366 * 8;
+
+/// Five-field cron expression (minute, hour, day of month, month, day of week) in local time.
+/// Fields take `*`, numbers, `a-b` ranges, `,` lists and `/n` steps; Sunday is 0 or 7. As in cron,
+/// a restricted day of month and day of week match when either does. `@hourly` and `@daily` work too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpression {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn invalid(expression: &str, why: impl std::fmt::Display) -> SpeedKarmaError {
+    SpeedKarmaError::ConfigurationError(format!("Invalid speed test schedule \"{}\": {}", expression, why))
+}
+
+/// Bit mask of the values `field` selects within `min..=max`
+fn parse_field(field: &str, min: u32, max: u32) -> std::result::Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>().map_err(|_| format!("bad step in {}", part))?)),
+            None => (part, None),
+        };
+        let number = |value: &str| value.parse::<u32>().map_err(|_| format!("{} is not a number", value));
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (number(start)?, number(end)?),
+            // `5/15` runs from 5 to the end of the range
+            None if step.is_some() => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("{} is outside {}-{}", part, min, max));
+        }
+        let step = step.unwrap_or(1);
+        if step == 0 {
+            return Err(format!("zero step in {}", part));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl CronExpression {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid(expression, "expected minute hour day month weekday"));
+        };
+        let field = |value: &str, min, max| parse_field(value, min, max).map_err(|why| invalid(expression, why));
+        let weekdays = field(weekday, 0, 7)?;
+        Ok(Self {
+            minutes: field(minute, 0, 59)?,
+            hours: field(hour, 0, 23)? as u32,
+            days: field(day, 1, 31)? as u32,
+            months: field(month, 1, 12)? as u16,
+            // Sunday may be written as 7
+            weekdays: ((weekdays | (weekdays >> 7)) & 0x7F) as u8,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    fn matches_day(&self, t: &NaiveDateTime) -> bool {
+        let day = self.days & (1 << t.day()) != 0;
+        let weekday = self.weekdays & (1 << t.weekday().num_days_from_sunday()) != 0;
+        let in_month = self.months & (1 << t.month()) != 0;
+        in_month && match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// Whether the minute of `t` is scheduled
+    pub fn matches(&self, t: &NaiveDateTime) -> bool {
+        self.matches_day(t) && self.hours & (1 << t.hour()) != 0 && self.minutes & (1 << t.minute()) != 0
+    }
+
+    /// First scheduled minute after `t`
+    pub fn next_after(&self, t: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = t.with_second(0)?.with_nanosecond(0)?;
+        let end = start + ChronoDuration::days(SEARCH_DAYS);
+        let mut candidate = start + ChronoDuration::minutes(1);
+        while candidate < end {
+            if !self.matches_day(&candidate) {
+                candidate = candidate.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << candidate.hour()) == 0 {
+                candidate = candidate.with_minute(0)? + ChronoDuration::hours(1);
+            } else if self.minutes & (1 << candidate.minute()) == 0 {
+                candidate += ChronoDuration::minutes(1);
+            } else {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+}
+
+/// A configured schedule entry and when it fires next, for `get_speedtest_schedule`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduledSpeedtest {
+    pub expression: String,
+    pub next_run: Option<chrono::DateTime<Utc>>,
+}
+
+/// Valid entries of `schedule`; invalid ones are logged and left out
+fn parse_schedule(schedule: &[String]) -> Vec<(String, CronExpression)> {
+    schedule.iter().filter_map(|expression| match CronExpression::parse(expression) {
+        Ok(cron) => Some((expression.clone(), cron)),
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    }).collect()
+}
+
+/// The effective schedules, parsed once per config file change
+struct Schedules {
+    /// Modification time of the config file they were read from
+    modified: Option<SystemTime>,
+    /// Empty while the runner is disabled
+    speedtest: Vec<(String, CronExpression)>,
+    iperf3: Vec<(String, CronExpression)>,
+    runner: SpeedtestRunnerConfig,
+    iperf3_config: Iperf3Config,
+}
+
+impl Schedules {
+    fn new(advanced: AdvancedConfig, modified: Option<SystemTime>) -> Self {
+        let parse = |enabled: bool, schedule: &[String]| if enabled { parse_schedule(schedule) } else { Vec::new() };
+        Self {
+            modified,
+            speedtest: parse(advanced.speedtest_runner.enabled, &advanced.speedtest_runner.schedule),
+            iperf3: parse(advanced.iperf3.enabled, &advanced.iperf3.schedule),
+            runner: advanced.speedtest_runner,
+            iperf3_config: advanced.iperf3,
+        }
+    }
+
+    /// First entry due at `minute`; entries due in the same minute share one run
+    fn due(entries: &[(String, CronExpression)], minute: &NaiveDateTime) -> Option<String> {
+        entries.iter().find(|(_, cron)| cron.matches(minute)).map(|(expression, _)| expression.clone())
+    }
+}
+
+/// Runs active speed tests at the times in `advanced.speedtest_runner.schedule`, so the model gets
+/// controlled samples inside and outside suspected throttling windows without anyone pressing a button.
+/// iperf3 baselines run at the times in `advanced.iperf3.schedule`, after a speed test due the same minute.
+pub struct SpeedtestSchedule {
+    events: SharedEventSink,
+    repository: Arc<Repository>,
+    shared: SharedAppState,
+    retries: Option<Arc<SpeedtestRetryQueue>>,
+    usage: Option<DataUsageMeter>,
+    limiter: Option<OutboundLimiter>,
+    shutdown: Option<CancellationToken>,
+}
+
+impl SpeedtestSchedule {
+    pub fn new(events: SharedEventSink, repository: Arc<Repository>, shared: SharedAppState) -> Self {
+        Self { events, repository, shared, retries: None, usage: None, limiter: None, shutdown: None }
+    }
+
+    /// Failed scheduled tests are retried through `queue`
+    pub fn with_retries(mut self, queue: Arc<SpeedtestRetryQueue>) -> Self {
+        self.retries = Some(queue);
+        self
+    }
+
+    /// Counts the bytes of scheduled tests against the monthly data cap
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Lets the emergency stop abort a scheduled test mid-run
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Stops the loop, and a scheduled run in progress, once `token` is cancelled
+    pub fn with_shutdown(mut self, token: CancellationToken) -> Self {
+        self.shutdown = Some(token);
+        self
+    }
+
+    /// Configured entries with their next run; `None` for entries that never fire
+    pub async fn upcoming() -> Result<Vec<ScheduledSpeedtest>> {
+        let cfg = AppConfig::load().await?.effective().advanced.speedtest_runner;
+        let now = Local::now().naive_local();
+        Ok(parse_schedule(&cfg.schedule).into_iter().map(|(expression, cron)| ScheduledSpeedtest {
+            next_run: cfg.enabled.then(|| cron.next_after(&now)).flatten()
+                .and_then(|next| Local.from_local_datetime(&next).earliest())
+                .map(|next| next.with_timezone(&Utc)),
+            expression,
+        }).collect())
+    }
+
+    pub fn start(self: Arc<Self>) {
+        let schedule = Arc::clone(&self);
+        tokio::spawn(async move { schedule.run_loop().await; });
+    }
+
+    async fn run_loop(&self) {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut last_fired: Option<NaiveDateTime> = None;
+        let mut schedules: Option<Schedules> = None;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown::cancelled(self.shutdown.as_ref()) => return,
+            }
+            let Some(minute) = Local::now().naive_local().with_second(0).and_then(|t| t.with_nanosecond(0)) else { continue };
+            if last_fired == Some(minute) {
+                continue;
+            }
+            Self::refresh(&mut schedules).await;
+            let Some(current) = &schedules else { continue };
+            let speedtest = Schedules::due(&current.speedtest, &minute);
+            let iperf3 = Schedules::due(&current.iperf3, &minute);
+            if speedtest.is_none() && iperf3.is_none() {
+                continue;
+            }
+            last_fired = Some(minute);
+            let runs = async {
+                if let Some(expression) = speedtest {
+                    info!("Running scheduled speed test ({})", expression);
+                    self.run_speedtest(current.runner.clone()).await;
+                }
+                if let Some(expression) = iperf3 {
+                    info!("Running scheduled iperf3 baseline ({})", expression);
+                    self.run_iperf3(current.iperf3_config.clone()).await;
+                }
+            };
+            tokio::select! {
+                _ = runs => {}
+                _ = shutdown::cancelled(self.shutdown.as_ref()) => return,
+            }
+        }
+    }
+
+    /// Rereads the config only when its file changed since `current` was read, so edits apply
+    /// without a restart and an idle schedule costs one metadata lookup per round
+    async fn refresh(current: &mut Option<Schedules>) {
+        let modified = match AppConfig::config_file_path() {
+            Ok(path) => tokio::fs::metadata(path).await.and_then(|m| m.modified()).ok(),
+            Err(_) => None,
+        };
+        if modified.is_some() && current.as_ref().is_some_and(|s| s.modified == modified) {
+            return;
+        }
+        match AppConfig::load().await {
+            Ok(cfg) => *current = Some(Schedules::new(cfg.effective().advanced, modified)),
+            // The schedule read last stays in effect
+            Err(e) => debug!("Scheduled speed tests not reloaded: {}", e),
+        }
+    }
+
+    async fn run_speedtest(&self, cfg: SpeedtestRunnerConfig) {
+        let mut runner = SpeedtestRunner::new(Arc::clone(&self.events), Arc::clone(&self.repository), Arc::clone(&self.shared), cfg);
+        if let Some(retries) = &self.retries {
+            runner = runner.with_retries(Arc::clone(retries), 0);
+        }
+        if let Some(usage) = &self.usage {
+            runner = runner.with_usage_meter(usage.clone());
+        }
+        if let Some(limiter) = &self.limiter {
+            runner = runner.with_limiter(limiter.clone());
+        }
+        if let Err(e) = runner.run_once().await {
+            warn!("Scheduled speed test failed: {}", e);
+        }
+    }
+
+    async fn run_iperf3(&self, cfg: Iperf3Config) {
+        let mut runner = Iperf3Runner::new(Arc::clone(&self.repository), Arc::clone(&self.shared), cfg);
+        if let Some(usage) = &self.usage {
+            runner = runner.with_usage_meter(usage.clone());
+        }
+        if let Some(limiter) = &self.limiter {
+            runner = runner.with_limiter(limiter.clone());
+        }
+        if let Err(e) = runner.run_once().await {
+            warn!("Scheduled iperf3 baseline failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cron_expressions_match_and_find_the_next_run() {
+        // Inside and outside the evening peak, every day
+        let evening = CronExpression::parse("0 20 * * *").unwrap();
+        let night = CronExpression::parse("0 3 * * *").unwrap();
+        assert!(evening.matches(&at(2026, 10, 12, 20, 0)) && !evening.matches(&at(2026, 10, 12, 20, 1)));
+        assert_eq!(evening.next_after(&at(2026, 10, 12, 20, 0)), Some(at(2026, 10, 13, 20, 0)));
+        assert_eq!(night.next_after(&at(2026, 10, 12, 20, 0)), Some(at(2026, 10, 13, 3, 0)));
+
+        // Every 15 minutes of weekday business hours; 2026-10-17 is a Saturday
+        let office = CronExpression::parse("*/15 9-17 * * 1-5").unwrap();
+        assert!(office.matches(&at(2026, 10, 16, 9, 45)) && !office.matches(&at(2026, 10, 17, 9, 45)));
+        assert_eq!(office.next_after(&at(2026, 10, 16, 17, 45)), Some(at(2026, 10, 19, 9, 0)));
+
+        // Sunday as 7, and cron's either-day rule when both day fields are set
+        assert!(CronExpression::parse("30 8 * * 7").unwrap().matches(&at(2026, 10, 18, 8, 30)));
+        let either = CronExpression::parse("0 12 1 * 1").unwrap();
+        assert!(either.matches(&at(2026, 10, 1, 12, 0)) && either.matches(&at(2026, 10, 19, 12, 0)));
+        assert!(!either.matches(&at(2026, 10, 20, 12, 0)));
+        assert_eq!(CronExpression::parse("0 0 29 2 *").unwrap().next_after(&at(2026, 10, 12, 0, 0)), Some(at(2028, 2, 29, 0, 0)));
+        assert_eq!(CronExpression::parse("@daily").unwrap(), CronExpression::parse("0 0 * * *").unwrap());
+
+        for bad in ["0 20 * *", "0 12 * * mon", "60 * * * *", "0 24 * * *", "5-1 * * * *", "*/0 * * * *", "0 0 0 * *"] {
+            assert!(CronExpression::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
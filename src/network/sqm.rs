@@ -0,0 +1,322 @@
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::network::proxy;
+use serde::Serialize;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use tracing::{debug, info};
+
+/// Queue disciplines that actively manage latency under load
+const SQM_QDISCS: &[&str] = &["fq_codel", "cake", "fq_pie"];
+
+const PROBE_HOST: &str = "speed.cloudflare.com:443";
+const LOAD_HOST: &str = "speed.cloudflare.com";
+const LOAD_URL: &str = "https://speed.cloudflare.com/__down?bytes=50000000";
+const PROBE_SAMPLES: usize = 8;
+const LOAD_DURATION: Duration = Duration::from_secs(8);
+/// Head start the load gets to fill the queue before loaded latency is sampled
+const LOAD_RAMP: Duration = Duration::from_secs(2);
+
+/// Bufferbloat grade, from latency added under load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BufferbloatGrade {
+    #[serde(rename = "A+")]
+    APlus,
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl BufferbloatGrade {
+    pub fn from_added_latency(added_ms: f64) -> Self {
+        match added_ms {
+            x if x < 5.0 => BufferbloatGrade::APlus,
+            x if x < 30.0 => BufferbloatGrade::A,
+            x if x < 60.0 => BufferbloatGrade::B,
+            x if x < 200.0 => BufferbloatGrade::C,
+            x if x < 400.0 => BufferbloatGrade::D,
+            _ => BufferbloatGrade::F,
+        }
+    }
+}
+
+/// Home-side queueing report with actionable fixes
+#[derive(Debug, Clone, Serialize)]
+pub struct SqmAdvice {
+    pub interface: Option<String>,
+    /// Root queue discipline reported by `tc`, if it could be read
+    pub qdisc: Option<String>,
+    pub sqm_active: bool,
+    pub idle_latency_ms: Option<f64>,
+    pub loaded_latency_ms: Option<f64>,
+    pub bufferbloat_ms: Option<f64>,
+    pub grade: Option<BufferbloatGrade>,
+    pub recommendations: Vec<String>,
+    /// Command that would enable fq_codel on the default interface
+    pub setup_command: Option<String>,
+}
+
+/// Name of the interface holding the IPv4 default route, from `/proc/net/route` content
+pub fn parse_default_route(route_table: &str) -> Option<String> {
+    route_table.lines().skip(1).find_map(|line| {
+        let mut cols = line.split_whitespace();
+        let iface = cols.next()?;
+        let destination = cols.next()?;
+        (destination == "00000000").then(|| iface.to_string())
+    })
+}
+
+/// Root qdisc kind from `tc qdisc show dev <iface>` output. For multiqueue roots (`mq`)
+/// the children decide, so an mq whose leaves are all fq_codel reports `fq_codel`.
+pub fn parse_root_qdisc(tc_output: &str) -> Option<String> {
+    let kinds: Vec<(&str, bool)> = tc_output
+        .lines()
+        .filter_map(|line| {
+            let mut cols = line.split_whitespace();
+            if cols.next()? != "qdisc" { return None; }
+            let kind = cols.next()?;
+            Some((kind, cols.any(|c| c == "root")))
+        })
+        .collect();
+    let root = kinds.iter().find(|(_, is_root)| *is_root).map(|(k, _)| *k)?;
+    if root == "mq" {
+        let children: Vec<&str> = kinds.iter().filter(|(_, is_root)| !is_root).map(|(k, _)| *k).collect();
+        if let Some(first) = children.first() {
+            if children.iter().all(|k| k == first) {
+                return Some(first.to_string());
+            }
+        }
+    }
+    Some(root.to_string())
+}
+
+pub fn is_sqm_qdisc(kind: &str) -> bool {
+    SQM_QDISCS.contains(&kind)
+}
+
+fn fq_codel_command(interface: &str) -> String {
+    format!("tc qdisc replace dev {} root fq_codel", interface)
+}
+
+fn median(mut samples: Vec<f64>) -> Option<f64> {
+    if samples.is_empty() { return None; }
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    Some(samples[samples.len() / 2])
+}
+
+/// Detects queueing setup and measures bufferbloat on the default interface
+pub struct SqmAdvisor;
+
+impl SqmAdvisor {
+    /// Full check: qdisc detection plus an idle vs loaded latency measurement
+    pub async fn analyze() -> SqmAdvice {
+        let interface = Self::default_interface().await;
+        let qdisc = match &interface {
+            Some(iface) => Self::root_qdisc(iface).await,
+            None => None,
+        };
+        let sqm_active = qdisc.as_deref().map(is_sqm_qdisc).unwrap_or(false);
+
+        let (idle, loaded, _) = match Self::measure_bufferbloat().await {
+            Ok(measured) => measured,
+            Err(e) => {
+                debug!("Bufferbloat measurement failed: {}", e);
+                (None, None, None)
+            }
+        };
+        let bufferbloat_ms = idle.zip(loaded).map(|(i, l)| (l - i).max(0.0));
+        let grade = bufferbloat_ms.map(BufferbloatGrade::from_added_latency);
+
+        let advice = Self::build_advice(interface, qdisc, sqm_active, idle, loaded, bufferbloat_ms, grade);
+        info!("SQM check: qdisc {:?}, bufferbloat {:?} ms, grade {:?}", advice.qdisc, advice.bufferbloat_ms, advice.grade);
+        advice
+    }
+
+    fn build_advice(
+        interface: Option<String>,
+        qdisc: Option<String>,
+        sqm_active: bool,
+        idle_latency_ms: Option<f64>,
+        loaded_latency_ms: Option<f64>,
+        bufferbloat_ms: Option<f64>,
+        grade: Option<BufferbloatGrade>,
+    ) -> SqmAdvice {
+        let mut recommendations = Vec::new();
+        let bloated = matches!(grade, Some(BufferbloatGrade::C | BufferbloatGrade::D | BufferbloatGrade::F));
+
+        if !sqm_active {
+            if let (Some(iface), Some(kind)) = (&interface, &qdisc) {
+                recommendations.push(format!(
+                    "{} uses {}; switching to fq_codel keeps latency low when the link is busy",
+                    iface, kind
+                ));
+            }
+        }
+        if bloated {
+            recommendations.push(
+                "Latency rises sharply under load: the queue sits in your router or modem. Enable SQM (cake or fq_codel) there, shaped to ~90% of your plan speed".to_string()
+            );
+            if sqm_active {
+                recommendations.push(
+                    "This computer already uses a fair queue, so the remaining delay is upstream of it".to_string()
+                );
+            }
+        }
+        if recommendations.is_empty() && grade.is_some() {
+            recommendations.push("Queueing looks healthy; no home-side changes needed".to_string());
+        }
+
+        let setup_command = match (&interface, sqm_active) {
+            (Some(iface), false) if cfg!(target_os = "linux") => Some(fq_codel_command(iface)),
+            _ => None,
+        };
+
+        SqmAdvice {
+            interface,
+            qdisc,
+            sqm_active,
+            idle_latency_ms,
+            loaded_latency_ms,
+            bufferbloat_ms,
+            grade,
+            recommendations,
+            setup_command,
+        }
+    }
+
+    async fn default_interface() -> Option<String> {
+        if !cfg!(target_os = "linux") { return None; }
+        let table = tokio::fs::read_to_string("/proc/net/route").await.ok()?;
+        parse_default_route(&table)
+    }
+
+    async fn root_qdisc(interface: &str) -> Option<String> {
+        if !cfg!(target_os = "linux") { return None; }
+        let output = tokio::process::Command::new("tc")
+            .args(["qdisc", "show", "dev", interface])
+            .output()
+            .await
+            .ok()?;
+        if !output.status.success() { return None; }
+        parse_root_qdisc(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Median TCP connect time while idle, then while a bulk download saturates the link, with the
+    /// download rate reached in Mbps
+    pub async fn measure_bufferbloat() -> Result<(Option<f64>, Option<f64>, Option<f64>)> {
+        Self::measure_under_load(Self::bulk_download(LOAD_DURATION)).await
+    }
+
+    /// Median TCP connect time while idle, then while `load` runs, with what `load` returned.
+    /// Callers bring their own load when the traffic has to stay within a budget.
+    pub async fn measure_under_load<F: Future>(load: F) -> Result<(Option<f64>, Option<f64>, F::Output)> {
+        let addr = tokio::net::lookup_host(PROBE_HOST).await?
+            .next()
+            .ok_or_else(|| SpeedKarmaError::NetworkUnavailable(format!("Cannot resolve {}", PROBE_HOST)))?;
+
+        let idle = median(Self::probe_latency(addr, PROBE_SAMPLES).await);
+        let (output, loaded) = tokio::join!(load, async {
+            tokio::time::sleep(LOAD_RAMP).await;
+            median(Self::probe_latency(addr, PROBE_SAMPLES).await)
+        });
+
+        Ok((idle, loaded, output))
+    }
+
+    /// Downloads from the load URL for `duration`; the rate reached in Mbps, `None` when nothing arrived
+    async fn bulk_download(duration: Duration) -> Option<f64> {
+        let (builder, _) = proxy::measurement(reqwest::Client::builder(), LOAD_HOST).await;
+        let client = builder.timeout(duration + Duration::from_secs(2)).build().ok()?;
+        let started = Instant::now();
+        let mut received: u64 = 0;
+        if let Ok(mut resp) = client.get(LOAD_URL).send().await {
+            while started.elapsed() < duration {
+                match resp.chunk().await {
+                    Ok(Some(chunk)) => received += chunk.len() as u64,
+                    _ => break,
+                }
+            }
+        }
+        let elapsed = started.elapsed().as_secs_f64();
+        (received > 0 && elapsed > 0.0).then(|| received as f64 * 8.0 / 1_000_000.0 / elapsed)
+    }
+
+    async fn probe_latency(addr: SocketAddr, samples: usize) -> Vec<f64> {
+        let mut rtts = Vec::with_capacity(samples);
+        for _ in 0..samples {
+            let start = Instant::now();
+            if let Ok(Ok(_stream)) = timeout(Duration::from_secs(3), proxy::connect_measurement(&addr.ip().to_string(), addr.port())).await {
+                rtts.push(start.elapsed().as_secs_f64() * 1000.0);
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+        rtts
+    }
+
+    /// Replaces the root qdisc with fq_codel through the system's privilege helper (pkexec)
+    pub async fn apply_fq_codel(interface: &str) -> Result<()> {
+        if !cfg!(target_os = "linux") {
+            return Err(SpeedKarmaError::SystemError("fq_codel setup is only supported on Linux".to_string()));
+        }
+        if interface.is_empty() || !interface.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@".contains(c)) {
+            return Err(SpeedKarmaError::ConfigurationError(format!("Invalid interface name: {}", interface)));
+        }
+        let status = tokio::process::Command::new("pkexec")
+            .args(["tc", "qdisc", "replace", "dev", interface, "root", "fq_codel"])
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(SpeedKarmaError::SystemError(format!("tc exited with {}", status)));
+        }
+        info!("Enabled fq_codel on {}", interface);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default_route() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+            docker0\t000011AC\t00000000\t0001\t0\t0\t0\t0000FFFF\n\
+            wlp2s0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\n";
+        assert_eq!(parse_default_route(table), Some("wlp2s0".to_string()));
+        assert_eq!(parse_default_route("Iface\tDestination\n"), None);
+    }
+
+    #[test]
+    fn test_parse_root_qdisc() {
+        assert_eq!(
+            parse_root_qdisc("qdisc fq_codel 0: root refcnt 2 limit 10240p flows 1024 quantum 1514\n"),
+            Some("fq_codel".to_string())
+        );
+        let mq = "qdisc mq 0: root\n\
+            qdisc fq_codel 0: parent :2 limit 10240p\n\
+            qdisc fq_codel 0: parent :1 limit 10240p\n";
+        assert_eq!(parse_root_qdisc(mq), Some("fq_codel".to_string()));
+        assert_eq!(parse_root_qdisc("qdisc pfifo_fast 0: root refcnt 2 bands 3\n"), Some("pfifo_fast".to_string()));
+        assert!(!is_sqm_qdisc("pfifo_fast"));
+        assert!(is_sqm_qdisc("cake"));
+    }
+
+    #[test]
+    fn test_grade_and_advice() {
+        assert_eq!(BufferbloatGrade::from_added_latency(3.0), BufferbloatGrade::APlus);
+        assert_eq!(BufferbloatGrade::from_added_latency(150.0), BufferbloatGrade::C);
+        assert_eq!(BufferbloatGrade::from_added_latency(900.0), BufferbloatGrade::F);
+
+        let advice = SqmAdvisor::build_advice(
+            Some("eth0".to_string()), Some("pfifo_fast".to_string()), false,
+            Some(20.0), Some(320.0), Some(300.0), Some(BufferbloatGrade::D),
+        );
+        assert_eq!(advice.recommendations.len(), 2);
+        if cfg!(target_os = "linux") {
+            assert_eq!(advice.setup_command.as_deref(), Some("tc qdisc replace dev eth0 root fq_codel"));
+        }
+    }
+}
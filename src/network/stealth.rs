@@ -1,7 +1,26 @@
+use crate::core::app_state::SharedAppState;
+use crate::core::config::StealthDnsConfig;
+use crate::core::scheduler::PeriodicScheduler;
+use crate::core::shutdown::{self, CancellationToken};
 use crate::core::error::{Result, SpeedKarmaError};
-use crate::data::models::{SpeedtestServer, StealthLevel};
+use crate::core::webhooks::WebhookNotifier;
+use crate::data::stores::StrategyStore;
+use crate::data::models::{MimicryProfile, OptimizationStrategy, ServerEndpoint, SpeedtestServer, StealthLevel, TrafficSource, TrafficTransport};
+use crate::network::qos::{self, QosOutcome};
+use crate::network::connections::{ConnectionOwner, ConnectionTable};
+use crate::network::cloudflare::{self, CloudflareClient};
+use crate::network::dns::StealthResolver;
+use crate::network::fastcom::FastComClient;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use crate::network::quic::Http3Client;
+use crate::network::usage::DataUsageMeter;
+use crate::network::rtt::RttSampler;
 use crate::network::servers::ServerPool;
+use crate::network::tls::TlsFingerprint;
+use bytes::Bytes;
 use rand::Rng;
+use serde::Serialize;
 use reqwest::{Client, ClientBuilder, header::{HeaderMap, HeaderValue, USER_AGENT, ACCEPT, ACCEPT_LANGUAGE, ACCEPT_ENCODING, CONNECTION, CACHE_CONTROL}};
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
@@ -14,13 +33,35 @@ use tracing::{debug, info, warn, error};
 // DPI bypass and advanced stealth imports
 use std::io::{self, Write};
 use tokio::net::{TcpSocket, TcpStream};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Consecutive failed cycles after which the loop exits so its supervisor can rebuild it
+const MAX_CYCLE_ERROR_STREAK: u32 = 5;
+
+/// A loop silent for longer than this is treated as hung (longest cycle delay is 3 minutes,
+/// a critical-risk pause 5 minutes)
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(600);
+
+const HEARTBEAT_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Server connections kept open for stealth rotation
+const STEALTH_POOL_CONNECTIONS: usize = 3;
+/// Caches fast.com asks for on each run
+const FAST_COM_URL_COUNT: u8 = 5;
+/// The page's first range from each cache
+const FAST_COM_WARMUP_BYTES: u64 = 2048;
+/// fast.com and Cloudflare transfers start at tens of kilobytes, so the stealth packet sizes are scaled up
+const MIMICRY_TRANSFER_SCALE: usize = 16;
+/// Latency pings speed.cloudflare.com sends before it moves data
+const CLOUDFLARE_PINGS: usize = 3;
 
 /// Traffic pattern configuration for mimicry
 #[derive(Debug, Clone)]
 pub struct TrafficPattern {
     pub packet_size_range: (usize, usize),
     pub timing_range: (Duration, Duration),
+    /// Cycle spacing over QUIC; kept under the idle timeout so one connection is reused
+    pub quic_timing_range: (Duration, Duration),
     pub burst_probability: f64,
     pub keep_alive_interval: Duration,
     pub fragmentation_enabled: bool,
@@ -39,10 +80,13 @@ pub struct DPIBypassConfig {
     pub mss_clamping: bool,
     pub timing_obfuscation: bool,
     pub dns_pattern_replication: bool,
+    /// TLS hello presented by mimicry traffic; `None` sends it as plain HTTP
+    pub tls_fingerprint: Option<TlsFingerprint>,
 }
 
 /// Detection risk assessment
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DetectionRisk {
     Low,
     Medium,
@@ -50,6 +94,18 @@ pub enum DetectionRisk {
     Critical,
 }
 
+impl DetectionRisk {
+    /// Identifier in `status_codes`
+    pub fn code(&self) -> &'static str {
+        match self {
+            DetectionRisk::Low => "low",
+            DetectionRisk::Medium => "medium",
+            DetectionRisk::High => "high",
+            DetectionRisk::Critical => "critical",
+        }
+    }
+}
+
 /// Adaptive stealth state
 #[derive(Debug, Clone)]
 pub struct AdaptiveStealthState {
@@ -58,6 +114,8 @@ pub struct AdaptiveStealthState {
     pub last_risk_assessment: Instant,
     pub effectiveness_score: f64,
     pub adaptation_count: u32,
+    /// Stealth cycles that failed in a row
+    pub cycle_error_streak: u32,
 }
 
 /// Server rotation state
@@ -65,7 +123,10 @@ pub struct AdaptiveStealthState {
 struct RotationState {
     current_server_index: usize,
     last_rotation: Instant,
+    /// Before the scheduler's jitter
     rotation_interval: Duration,
+    /// Rotations so far; picks each rotation's jitter
+    rotations: u64,
     servers_in_rotation: Vec<SpeedtestServer>,
 }
 
@@ -89,6 +150,25 @@ pub struct StealthEngine {
     dpi_bypass_config: DPIBypassConfig,
     adaptive_state: Arc<RwLock<AdaptiveStealthState>>,
     is_active: Arc<RwLock<bool>>,
+    shared_state: Option<SharedAppState>,
+    /// Last time the stealth loop reported progress
+    heartbeat: Arc<RwLock<Instant>>,
+    rtt_sampler: Option<RttSampler>,
+    connection_table: Option<ConnectionTable>,
+    limiter: OutboundLimiter,
+    webhooks: Option<Arc<WebhookNotifier>>,
+    usage: Option<DataUsageMeter>,
+    shutdown: Option<CancellationToken>,
+    scheduler: PeriodicScheduler,
+    /// Set by the user; otherwise each cycle follows the active strategy's profile
+    mimicry_profile: Option<MimicryProfile>,
+    strategies: Option<Arc<dyn StrategyStore>>,
+    fast_com: Arc<FastComClient>,
+    cloudflare: Arc<CloudflareClient>,
+    transport: TrafficTransport,
+    /// Opened on first use; its UDP socket needs the runtime
+    quic: Arc<tokio::sync::OnceCell<Http3Client>>,
+    dns: Arc<StealthResolver>,
 }
 
 impl StealthEngine {
@@ -102,6 +182,7 @@ impl StealthEngine {
                 current_server_index: 0,
                 last_rotation: Instant::now(),
                 rotation_interval: Duration::from_secs(300), // 5 minutes default
+                rotations: 0,
                 servers_in_rotation: Vec::new(),
             })),
             active_connections: Arc::new(RwLock::new(HashMap::new())),
@@ -114,8 +195,136 @@ impl StealthEngine {
                 last_risk_assessment: Instant::now(),
                 effectiveness_score: 1.0,
                 adaptation_count: 0,
+                cycle_error_streak: 0,
             })),
             is_active: Arc::new(RwLock::new(false)),
+            shared_state: None,
+            heartbeat: Arc::new(RwLock::new(Instant::now())),
+            rtt_sampler: None,
+            connection_table: None,
+            limiter: OutboundLimiter::default(),
+            webhooks: None,
+            usage: None,
+            shutdown: None,
+            scheduler: PeriodicScheduler::default(),
+            mimicry_profile: None,
+            strategies: None,
+            fast_com: Arc::new(FastComClient::new()),
+            cloudflare: Arc::new(CloudflareClient::new()),
+            transport: TrafficTransport::default(),
+            quic: Arc::new(tokio::sync::OnceCell::new()),
+            dns: Arc::new(StealthResolver::default()),
+        }
+    }
+
+    /// Speed test to imitate whatever the active strategy says; a pinned strategy's profile still wins
+    pub fn with_mimicry_profile(mut self, profile: MimicryProfile) -> Self {
+        self.mimicry_profile = Some(profile);
+        self
+    }
+
+    /// Where each cycle reads the active strategy's mimicry profile
+    pub fn with_strategy_store(mut self, strategies: Arc<dyn StrategyStore>) -> Self {
+        self.strategies = Some(strategies);
+        self
+    }
+
+    /// Transport for speedtest.net traffic when no strategy is pinned; a pinned strategy's wins
+    pub fn with_transport(mut self, transport: TrafficTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Resolver for DNS pattern replication lookups (system by default)
+    pub fn with_dns(mut self, config: StealthDnsConfig) -> Self {
+        self.dns = Arc::new(StealthResolver::new(config));
+        self
+    }
+
+    /// Ends the stealth loop after the current cycle once `token` is cancelled; its supervisor stops too
+    pub fn with_shutdown(mut self, token: CancellationToken) -> Self {
+        self.shutdown = Some(token);
+        self
+    }
+
+    /// Sleeps for `duration`; false when a shutdown cut it short
+    async fn pause(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = sleep(duration) => true,
+            _ = shutdown::cancelled(self.shutdown.as_ref()) => false,
+        }
+    }
+
+    /// Lets the stealth loop honor optimization mode, the live stealth module switch and conflict pauses
+    pub fn with_shared_state(mut self, shared_state: SharedAppState) -> Self {
+        self.shared_state = Some(shared_state);
+        self
+    }
+
+    /// Jitters server rotation from the app-wide scheduler instead of a private one
+    pub fn with_scheduler(mut self, scheduler: PeriodicScheduler) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Reports the handshake time of each stealth connection to the passive monitor
+    pub fn with_rtt_sampler(mut self, sampler: RttSampler) -> Self {
+        self.rtt_sampler = Some(sampler);
+        self
+    }
+
+    /// Mirrors per-server bytes, activity and errors into the shared connection table
+    pub fn with_connection_table(mut self, table: ConnectionTable) -> Self {
+        self.connection_table = Some(table);
+        self
+    }
+
+    /// Posts `detection_risk_critical` when the assessed risk reaches Critical
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookNotifier>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Counts stealth bytes against the monthly data cap
+    pub fn with_usage_meter(mut self, usage: DataUsageMeter) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Counts every stealth request against the app-wide outbound limit
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Plain HTTP is avoided once the ISP was caught rewriting DNS or proxying HTTP
+    async fn prefer_encrypted(&self) -> bool {
+        match &self.shared_state {
+            Some(shared) => shared.read().await.prefer_encrypted,
+            None => false,
+        }
+    }
+
+    /// HTTPS whenever the level presents a TLS fingerprint or the user prefers encrypted traffic
+    async fn use_https(&self) -> bool {
+        self.dpi_bypass_config.tls_fingerprint.is_some() || self.prefer_encrypted().await
+    }
+
+    /// Strategy the user pinned; its level, rotation and pacing replace the engine's own
+    async fn pinned_strategy(&self) -> Option<OptimizationStrategy> {
+        match &self.shared_state {
+            Some(shared) => shared.read().await.active_strategy_override.clone(),
+            None => None,
+        }
+    }
+
+    async fn module_enabled(&self) -> bool {
+        if self.limiter.is_halted() {
+            return false;
+        }
+        match &self.shared_state {
+            Some(shared) => { let s = shared.read().await; s.may_load_link() && s.modules.stealth }
+            None => true,
         }
     }
 
@@ -125,6 +334,7 @@ impl StealthEngine {
             StealthLevel::Low => TrafficPattern {
                 packet_size_range: (1000, 1500),
                 timing_range: (Duration::from_secs(45), Duration::from_secs(75)),
+                quic_timing_range: (Duration::from_secs(15), Duration::from_secs(25)),
                 burst_probability: 0.1,
                 keep_alive_interval: Duration::from_secs(60),
                 fragmentation_enabled: false,
@@ -135,6 +345,7 @@ impl StealthEngine {
             StealthLevel::Medium => TrafficPattern {
                 packet_size_range: (800, 1400),
                 timing_range: (Duration::from_secs(30), Duration::from_secs(90)),
+                quic_timing_range: (Duration::from_secs(10), Duration::from_secs(25)),
                 burst_probability: 0.15,
                 keep_alive_interval: Duration::from_secs(45),
                 fragmentation_enabled: true,
@@ -145,6 +356,7 @@ impl StealthEngine {
             StealthLevel::High => TrafficPattern {
                 packet_size_range: (500, 1200),
                 timing_range: (Duration::from_secs(20), Duration::from_secs(120)),
+                quic_timing_range: (Duration::from_secs(8), Duration::from_secs(25)),
                 burst_probability: 0.2,
                 keep_alive_interval: Duration::from_secs(30),
                 fragmentation_enabled: true,
@@ -155,6 +367,7 @@ impl StealthEngine {
             StealthLevel::Maximum => TrafficPattern {
                 packet_size_range: (300, 1000),
                 timing_range: (Duration::from_secs(15), Duration::from_secs(180)),
+                quic_timing_range: (Duration::from_secs(5), Duration::from_secs(25)),
                 burst_probability: 0.25,
                 keep_alive_interval: Duration::from_secs(20),
                 fragmentation_enabled: true,
@@ -176,6 +389,7 @@ impl StealthEngine {
                 mss_clamping: false,
                 timing_obfuscation: false,
                 dns_pattern_replication: false,
+                tls_fingerprint: TlsFingerprint::for_level(stealth_level),
             },
             StealthLevel::Medium => DPIBypassConfig {
                 packet_fragmentation: true,
@@ -185,6 +399,7 @@ impl StealthEngine {
                 mss_clamping: false,
                 timing_obfuscation: true,
                 dns_pattern_replication: true,
+                tls_fingerprint: TlsFingerprint::for_level(stealth_level),
             },
             StealthLevel::High => DPIBypassConfig {
                 packet_fragmentation: true,
@@ -194,6 +409,7 @@ impl StealthEngine {
                 mss_clamping: true,
                 timing_obfuscation: true,
                 dns_pattern_replication: true,
+                tls_fingerprint: TlsFingerprint::for_level(stealth_level),
             },
             StealthLevel::Maximum => DPIBypassConfig {
                 packet_fragmentation: true,
@@ -203,6 +419,7 @@ impl StealthEngine {
                 mss_clamping: true,
                 timing_obfuscation: true,
                 dns_pattern_replication: true,
+                tls_fingerprint: TlsFingerprint::for_level(stealth_level),
             },
         }
     }
@@ -238,6 +455,9 @@ impl StealthEngine {
         // Close all active connections
         let mut connections = self.active_connections.write().await;
         connections.clear();
+        if let Some(table) = &self.connection_table {
+            table.close_owner(ConnectionOwner::Stealth);
+        }
         
         *is_active = false;
         Ok(())
@@ -256,6 +476,14 @@ impl StealthEngine {
         let suitable_servers = self.select_suitable_servers().await?;
         
         let mut rotation_state = self.rotation_state.write().await;
+        // Keep use counts across restarts of the loop
+        let mut suitable_servers = suitable_servers;
+        for server in &mut suitable_servers {
+            if let Some(previous) = rotation_state.servers_in_rotation.iter().find(|s| s.server_id == server.server_id) {
+                server.use_count = previous.use_count;
+                server.last_used = previous.last_used;
+            }
+        }
         rotation_state.servers_in_rotation = suitable_servers;
         rotation_state.rotation_interval = self.calculate_rotation_interval();
         
@@ -265,27 +493,18 @@ impl StealthEngine {
 
     /// Select servers suitable for stealth operations
     async fn select_suitable_servers(&self) -> Result<Vec<SpeedtestServer>> {
-        // Get regional servers first (Sri Lanka, Singapore, India)
+        // Regional servers first, in order of preference (Sri Lanka, Singapore, India by default)
         let mut regional_servers = Vec::new();
-        
-        regional_servers.extend(
-            self.server_pool.get_regional_servers("Sri Lanka")
-                .into_iter()
-                .cloned()
-        );
-        regional_servers.extend(
-            self.server_pool.get_regional_servers("Singapore")
-                .into_iter()
-                .cloned()
-        );
-        regional_servers.extend(
-            self.server_pool.get_regional_servers("India")
-                .into_iter()
-                .cloned()
-        );
+        for country in self.server_pool.preferred_countries() {
+            regional_servers.extend(
+                self.server_pool.get_regional_servers(&country)
+                    .into_iter()
+                    .cloned()
+            );
+        }
 
         if !regional_servers.is_empty() {
-            return Ok(regional_servers);
+            return Ok(self.server_pool.rank(regional_servers).await);
         }
 
         // Fallback to closest servers
@@ -300,42 +519,89 @@ impl StealthEngine {
             ));
         }
 
-        Ok(closest_servers)
+        Ok(self.server_pool.rank(closest_servers).await)
     }
 
-    /// Calculate rotation interval based on stealth level
+    /// Calculate rotation interval based on stealth level; the scheduler jitters it per rotation
     fn calculate_rotation_interval(&self) -> Duration {
-        let base_interval = match self.stealth_level {
+        match self.stealth_level {
             StealthLevel::Low => Duration::from_secs(900),   // 15 minutes
             StealthLevel::Medium => Duration::from_secs(600), // 10 minutes
             StealthLevel::High => Duration::from_secs(300),   // 5 minutes
             StealthLevel::Maximum => Duration::from_secs(180), // 3 minutes
-        };
+        }
+    }
 
-        // Add randomization to avoid predictable patterns
-        let variation = base_interval.as_secs() / 4;
-        let mut rng = rand::thread_rng();
-        let random_offset = rng.gen_range(0..variation);
-        base_interval + Duration::from_secs(random_offset)
+    /// Time from the last rotation to the next: the pinned strategy's interval or the engine's own,
+    /// jittered by the scheduler so rotation does not line up with the other periodic loops
+    fn rotation_due(&self, rotation_state: &RotationState, pinned_interval: Option<Duration>) -> Duration {
+        self.scheduler.delay_for("server_rotation", rotation_state.rotations, pinned_interval.unwrap_or(rotation_state.rotation_interval))
     }
 
-    /// Main stealth operation loop
-    pub async fn run_stealth_loop(&self) {
+    /// Main stealth operation loop. Returns `Ok` once stopped, or the last error after
+    /// `MAX_CYCLE_ERROR_STREAK` failed cycles in a row; run it under `StealthSupervisor`.
+    pub async fn run_stealth_loop(&self) -> Result<()> {
         info!("Starting stealth operation loop");
         
-        while *self.is_active.read().await {
-            if let Err(e) = self.execute_stealth_cycle().await {
-                error!("Error in stealth cycle: {}", e);
-                sleep(Duration::from_secs(30)).await;
+        while *self.is_active.read().await && !self.shutdown.as_ref().is_some_and(|t| t.is_cancelled()) {
+            self.beat().await;
+            if !self.module_enabled().await {
+                if !self.pause(Duration::from_secs(30)).await { break; }
                 continue;
             }
+            let Some(cycle) = self.limiter.unless_halted(self.execute_stealth_cycle()).await else {
+                // Emergency stop cancelled the cycle; idle until traffic is allowed again
+                continue;
+            };
+            if let Err(e) = cycle {
+                let streak = self.record_cycle_result(false).await;
+                error!("Error in stealth cycle ({} in a row): {}", streak, e);
+                if streak >= MAX_CYCLE_ERROR_STREAK {
+                    return Err(e);
+                }
+                if !self.pause(Duration::from_secs(30)).await { break; }
+                continue;
+            }
+            self.record_cycle_result(true).await;
 
             // Wait for next cycle with randomized timing
             let wait_time = self.calculate_next_cycle_delay().await;
-            sleep(wait_time).await;
+            if !self.pause(wait_time).await { break; }
         }
 
         info!("Stealth operation loop stopped");
+        Ok(())
+    }
+
+    async fn beat(&self) {
+        *self.heartbeat.write().await = Instant::now();
+    }
+
+    /// Time since the stealth loop last reported progress
+    pub async fn heartbeat_age(&self) -> Duration {
+        self.heartbeat.read().await.elapsed()
+    }
+
+    /// Tracks the cycle error streak and lets it raise detection risk; returns the streak
+    async fn record_cycle_result(&self, success: bool) -> u32 {
+        let streak = {
+            let mut adaptive_state = self.adaptive_state.write().await;
+            adaptive_state.cycle_error_streak = if success { 0 } else { adaptive_state.cycle_error_streak + 1 };
+            adaptive_state.cycle_error_streak
+        };
+        if let Err(e) = self.adapt_stealth_strategy().await {
+            debug!("Stealth adaptation failed: {}", e);
+        }
+        streak
+    }
+
+    /// Reconnects the server pool when needed and restarts rotation; run before every supervised loop
+    async fn prepare(&self) -> Result<()> {
+        self.stop().await?;
+        if self.server_pool.get_connected_servers().await.is_empty() {
+            self.server_pool.establish_connection_pool(STEALTH_POOL_CONNECTIONS.min(self.limiter.max_concurrent())).await?;
+        }
+        self.start().await
     }
 
     /// Execute one cycle of stealth operations
@@ -356,14 +622,48 @@ impl StealthEngine {
 
     /// Check if server rotation is needed
     pub async fn should_rotate_servers(&self) -> bool {
+        let pinned_interval = self.pinned_strategy().await.map(|s| Duration::from_secs(s.server_rotation_interval_minutes as u64 * 60));
         let rotation_state = self.rotation_state.read().await;
-        rotation_state.last_rotation.elapsed() >= rotation_state.rotation_interval
+        rotation_state.last_rotation.elapsed() >= self.rotation_due(&rotation_state, pinned_interval)
+    }
+
+    /// Profile for this cycle: the pinned strategy's, the user's choice, else the active strategy's,
+    /// read again every cycle so a newly activated strategy takes effect without a restart
+    async fn current_profile(&self, pinned: Option<&OptimizationStrategy>) -> MimicryProfile {
+        if let Some(strategy) = pinned {
+            return strategy.mimicry_profile;
+        }
+        if let Some(profile) = self.mimicry_profile {
+            return profile;
+        }
+        let Some(strategies) = &self.strategies else { return MimicryProfile::default() };
+        match strategies.get_best_optimization_strategy().await {
+            Ok(Some(strategy)) => strategy.mimicry_profile,
+            Ok(None) => MimicryProfile::default(),
+            Err(e) => {
+                debug!("Could not read the active strategy's mimicry profile: {}", e);
+                MimicryProfile::default()
+            }
+        }
     }
 
     /// Calculate delay until next stealth cycle
     pub async fn calculate_next_cycle_delay(&self) -> Duration {
-        let min_delay = self.traffic_pattern.timing_range.0;
-        let max_delay = self.traffic_pattern.timing_range.1;
+        // A row stored before validation covered timings may hold seconds `Duration` cannot take
+        let timing = |s: &OptimizationStrategy| Some((
+            Duration::try_from_secs_f64(s.packet_timing_min_seconds).ok()?,
+            Duration::try_from_secs_f64(s.packet_timing_max_seconds).ok()?,
+        ));
+        let pinned = self.pinned_strategy().await;
+        let transport = pinned.as_ref().map_or(self.transport, |s| s.transport);
+        let (min_delay, max_delay) = match pinned.as_ref().and_then(timing) {
+            Some(range) => range,
+            None if transport == TrafficTransport::Quic => self.traffic_pattern.quic_timing_range,
+            None => self.traffic_pattern.timing_range,
+        };
+        if max_delay <= min_delay {
+            return min_delay;
+        }
         
         let delay_range = max_delay.as_millis() - min_delay.as_millis();
         let random_delay = rand::thread_rng().gen_range(0..delay_range);
@@ -371,8 +671,30 @@ impl StealthEngine {
         min_delay + Duration::from_millis(random_delay as u64)
     }
 
-    /// Generates traffic patterns that mimic speedtest.net with DPI bypass
+    /// Generates traffic that mimics the strategy's speed test: speedtest.net with DPI bypass, fast.com
+    /// or speed.cloudflare.com
     pub async fn generate_mimicry_traffic(&self) -> Result<()> {
+        let pinned = self.pinned_strategy().await;
+        let profile = self.current_profile(pinned.as_ref()).await;
+        let hosted = match profile {
+            MimicryProfile::Speedtest => None,
+            MimicryProfile::FastCom => Some(self.send_fast_com_mimicry().await),
+            MimicryProfile::Cloudflare => Some(self.send_cloudflare_mimicry().await),
+        };
+        if let Some(result) = hosted {
+            if let Some(shared) = &self.shared_state {
+                shared.write().await.transport_in_use = Some(TrafficTransport::Tcp);
+            }
+            match &result {
+                Ok(()) => self.record_connection_result(true, Some(0.8)).await,
+                Err(e) => {
+                    self.record_connection_result(false, None).await;
+                    warn!("Failed to generate {} mimicry traffic: {}", profile.as_str(), e);
+                }
+            }
+            return result;
+        }
+
         let rotation_state = self.rotation_state.read().await;
         if rotation_state.servers_in_rotation.is_empty() {
             return Ok(());
@@ -387,23 +709,41 @@ impl StealthEngine {
         // Replicate DNS patterns before connection
         self.replicate_dns_patterns(&current_server).await?;
 
-        // Create stealth connection or use HTTP client based on stealth level
-        let result = if self.stealth_level == StealthLevel::Maximum {
-            self.send_raw_stealth_traffic(&current_server).await
+        // HTTP/3 when the transport asks for it, else a stealth connection or HTTP client based on stealth level.
+        // QUIC cannot go through a SOCKS or HTTP proxy, so proxied hosts stay on TCP
+        let transport = match pinned.as_ref().map_or(self.transport, |s| s.transport) {
+            TrafficTransport::Quic if proxy::route(&current_server.host).is_some() => TrafficTransport::Tcp,
+            transport => transport,
+        };
+        let stealth_level = pinned.map(|s| s.stealth_level).unwrap_or_else(|| self.stealth_level.clone());
+        let mut ran = transport;
+        let mut result = if transport == TrafficTransport::Quic {
+            self.send_quic_mimicry(&current_server).await
         } else {
-            // Create authentic speedtest client with obfuscated headers
-            let client = self.create_authentic_speedtest_client().await?;
-            self.send_speedtest_mimicry_requests(&client, &current_server).await
+            self.send_tcp_mimicry(&current_server, &stealth_level).await
         };
+        // UDP is often blocked outright; the cycle still goes out over TCP
+        if let (TrafficTransport::Quic, Err(e)) = (transport, &result) {
+            debug!("HTTP/3 to {} failed, falling back to TCP: {}", current_server.name, e);
+            ran = TrafficTransport::Tcp;
+            result = self.send_tcp_mimicry(&current_server, &stealth_level).await;
+        }
+        if let Some(shared) = &self.shared_state {
+            shared.write().await.transport_in_use = Some(ran);
+        }
 
         // Record the result for adaptive learning
         match &result {
             Ok(_) => {
                 self.record_connection_result(true, Some(0.8)).await;
+                self.record_server_use(&current_server.server_id).await;
                 debug!("Generated mimicry traffic for server: {}", current_server.name);
             }
             Err(e) => {
                 self.record_connection_result(false, None).await;
+                if let Some(table) = &self.connection_table {
+                    table.record_error(ConnectionOwner::Stealth, &current_server, &e.to_string());
+                }
                 warn!("Failed to generate mimicry traffic for {}: {}", current_server.name, e);
             }
         }
@@ -411,6 +751,158 @@ impl StealthEngine {
         result
     }
 
+    /// Raw stealth connection at maximum stealth, else an HTTP client with obfuscated headers
+    async fn send_tcp_mimicry(&self, server: &SpeedtestServer, stealth_level: &StealthLevel) -> Result<()> {
+        if *stealth_level == StealthLevel::Maximum {
+            self.send_raw_stealth_traffic(server).await
+        } else {
+            let client = self.create_authentic_speedtest_client().await?;
+            self.send_speedtest_mimicry_requests(&client, server).await
+        }
+    }
+
+    /// speedtest.net's sequence over HTTP/3: latency probe, upload, then a second probe, on the
+    /// connection left open from the previous cycle when it is still alive
+    async fn send_quic_mimicry(&self, server: &SpeedtestServer) -> Result<()> {
+        let client = self.quic.get_or_try_init(|| async { Http3Client::new() }).await?;
+        let user_agent = self.create_obfuscated_headers().await
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let mut transferred = 0u64;
+        let latency_url = server.endpoint_url(true, ServerEndpoint::Latency, 0, &self.generate_random_string(8));
+        let response = {
+            let _permit = self.limiter.acquire("stealth quic latency").await;
+            client.request(http::Method::GET, &latency_url, &user_agent, None).await?
+        };
+        if let Some(handshake) = response.handshake {
+            self.record_handshake(handshake);
+        }
+        transferred += response.received;
+        sleep(Duration::from_millis(500)).await;
+
+        let payload_size = rand::thread_rng().gen_range(self.traffic_pattern.packet_size_range.0..=self.traffic_pattern.packet_size_range.1);
+        let upload_url = server.endpoint_url(true, ServerEndpoint::Upload, 0, "");
+        let payload = Bytes::from(self.generate_speedtest_payload(payload_size));
+        let response = {
+            let _permit = self.limiter.acquire("stealth quic upload").await;
+            client.request(http::Method::POST, &upload_url, &user_agent, Some(payload)).await?
+        };
+        transferred += payload_size as u64 + response.received;
+        sleep(Duration::from_millis(300)).await;
+
+        let ping_url = server.endpoint_url(true, ServerEndpoint::Latency, 0, &self.generate_random_string(8));
+        let response = {
+            let _permit = self.limiter.acquire("stealth quic keep-alive").await;
+            client.request(http::Method::GET, &ping_url, &user_agent, None).await?
+        };
+        transferred += response.received;
+
+        self.update_connection_stats(server, transferred).await;
+        debug!("Sent HTTP/3 mimicry traffic to {} (last status {})", server.name, response.status);
+        Ok(())
+    }
+
+    /// fast.com's sequence: token and caches from api.fast.com, a small range from every cache the
+    /// way the page warms up, then one larger range like the start of its download phase
+    async fn send_fast_com_mimicry(&self) -> Result<()> {
+        let targets = {
+            let _permit = self.limiter.acquire("stealth fast.com api").await;
+            self.fast_com.targets(FAST_COM_URL_COUNT).await?
+        };
+        let Some(main) = targets.first() else {
+            return Err(SpeedKarmaError::NetworkUnavailable("api.fast.com returned no caches".to_string()));
+        };
+
+        let mut received = 0u64;
+        for target in &targets {
+            let _permit = self.limiter.acquire("stealth fast.com warmup").await;
+            match self.fast_com.fetch_range(target, 0, FAST_COM_WARMUP_BYTES).await {
+                Ok(bytes) => received += bytes,
+                Err(e) => debug!("fast.com warmup range from {} failed: {}", target.name, e),
+            }
+            let pause = Duration::from_millis(rand::thread_rng().gen_range(100..400));
+            sleep(pause).await;
+        }
+
+        let (min, max) = self.traffic_pattern.packet_size_range;
+        let size = (rand::thread_rng().gen_range(min..=max) * MIMICRY_TRANSFER_SCALE) as u64;
+        let download = {
+            let _permit = self.limiter.acquire("stealth fast.com download").await;
+            self.fast_com.fetch_range(main, 0, size - 1).await
+        };
+        received += download.as_ref().copied().unwrap_or(0);
+
+        // The warmup ranges count against the cap even when the download fails
+        if let Some(usage) = &self.usage {
+            usage.record(TrafficSource::Stealth, received).await;
+        }
+        download?;
+        debug!("Generated fast.com mimicry traffic: {} bytes from {} caches", received, targets.len());
+        Ok(())
+    }
+
+    /// speed.cloudflare.com's sequence: `/meta` for the POP, a few empty downloads as latency pings,
+    /// then one `__down` and one `__up` transfer under the same measurement id
+    async fn send_cloudflare_mimicry(&self) -> Result<()> {
+        let measurement_id = cloudflare::new_measurement_id();
+        {
+            let _permit = self.limiter.acquire("stealth cloudflare meta").await;
+            match self.cloudflare.meta().await {
+                Ok(meta) => debug!("speed.cloudflare.com serving from {}", meta.colo.as_deref().unwrap_or("unknown POP")),
+                Err(e) => debug!("speed.cloudflare.com meta failed: {}", e),
+            }
+        }
+        for _ in 0..CLOUDFLARE_PINGS {
+            let _permit = self.limiter.acquire("stealth cloudflare ping").await;
+            self.cloudflare.ping(&measurement_id).await?;
+            let pause = Duration::from_millis(rand::thread_rng().gen_range(50..200));
+            sleep(pause).await;
+        }
+
+        let (min, max) = self.traffic_pattern.packet_size_range;
+        let (down_size, up_size) = {
+            let mut rng = rand::thread_rng();
+            ((rng.gen_range(min..=max) * MIMICRY_TRANSFER_SCALE) as u64, rng.gen_range(min..=max) as u64)
+        };
+        let received = {
+            let _permit = self.limiter.acquire("stealth cloudflare download").await;
+            self.cloudflare.download(&measurement_id, down_size).await?
+        };
+        let upload = {
+            let _permit = self.limiter.acquire("stealth cloudflare upload").await;
+            self.cloudflare.upload(&measurement_id, up_size).await
+        };
+        let sent = upload.as_ref().copied().unwrap_or(0);
+
+        // The download counts against the cap even when the upload fails
+        if let Some(usage) = &self.usage {
+            usage.record(TrafficSource::Stealth, received + sent).await;
+        }
+        upload?;
+        debug!("Generated Cloudflare mimicry traffic: {} bytes down, {} bytes up", received, sent);
+        Ok(())
+    }
+
+    async fn record_server_use(&self, server_id: &str) {
+        let mut rotation_state = self.rotation_state.write().await;
+        if let Some(server) = rotation_state.servers_in_rotation.iter_mut().find(|s| s.server_id == server_id) {
+            server.use_count += 1;
+            server.last_used = Some(chrono::Utc::now());
+        }
+    }
+
+    /// Servers in the rotation, with stealth use counts, and the id of the current one
+    pub async fn rotation_servers(&self) -> (Vec<SpeedtestServer>, Option<String>) {
+        let rotation_state = self.rotation_state.read().await;
+        let current = rotation_state.servers_in_rotation
+            .get(rotation_state.current_server_index)
+            .map(|s| s.server_id.clone());
+        (rotation_state.servers_in_rotation.clone(), current)
+    }
+
     /// Create HTTP client that mimics speedtest.net behavior with DPI bypass
     pub async fn create_authentic_speedtest_client(&self) -> Result<Client> {
         // Use obfuscated headers if enabled
@@ -424,10 +916,13 @@ impl StealthEngine {
         final_headers.insert("Sec-Fetch-User", HeaderValue::from_static("?1"));
         final_headers.insert("Upgrade-Insecure-Requests", HeaderValue::from_static("1"));
 
-        let mut client_builder = ClientBuilder::new()
+        let mut client_builder = proxy::apply(ClientBuilder::new())
             .timeout(Duration::from_secs(30))
             .default_headers(final_headers)
             .tcp_keepalive(Duration::from_secs(60));
+        if let Some(fingerprint) = &self.dpi_bypass_config.tls_fingerprint {
+            client_builder = fingerprint.apply(client_builder);
+        }
 
         // Configure TCP settings for DPI bypass
         if self.dpi_bypass_config.tcp_window_size != 65535 {
@@ -445,18 +940,31 @@ impl StealthEngine {
 
     /// Send raw stealth traffic using direct TCP connection for maximum stealth
     async fn send_raw_stealth_traffic(&self, server: &SpeedtestServer) -> Result<()> {
+        let _permit = self.limiter.acquire("stealth raw").await;
         // Create stealth TCP connection
-        let mut stream = self.create_stealth_connection(server).await?;
+        let stream = self.create_stealth_connection(server).await?;
 
         // Prepare HTTP request data
         let request_data = self.create_raw_http_request(server).await?;
 
-        // Send fragmented request if enabled
-        self.send_fragmented_request(&mut stream, &request_data).await?;
-
-        // Read response (minimal to avoid detection)
+        // Send fragmented request if enabled, inside TLS when the server or level calls for it;
+        // each fragment then travels as its own TLS record. A server pinned to plain http or https
+        // gets that, as in `endpoint_url`
         let mut buffer = [0; 1024];
-        let _ = stream.read(&mut buffer).await; // Ignore response content
+        let fingerprint = self.dpi_bypass_config.tls_fingerprint;
+        let secure = server.tls.unwrap_or(self.use_https().await || server.port == 443);
+        if secure {
+            // A TLS-only server at a level without a fingerprint gets the Speedtest app's hello
+            let fingerprint = fingerprint.unwrap_or(TlsFingerprint::SpeedtestApp);
+            let mut stream = fingerprint.connect(&server.host, stream).await?;
+            self.send_fragmented_request(&mut stream, &request_data).await?;
+            let _ = stream.read(&mut buffer).await; // Ignore response content
+        } else {
+            let mut stream = stream;
+            self.send_fragmented_request(&mut stream, &request_data).await?;
+            // Read response (minimal to avoid detection)
+            let _ = stream.read(&mut buffer).await; // Ignore response content
+        }
 
         debug!("Sent raw stealth traffic to {}", server.name);
         Ok(())
@@ -468,8 +976,8 @@ impl StealthEngine {
         
         // Build HTTP request manually for maximum control
         let mut request = format!(
-            "GET /speedtest/latency.txt?r={} HTTP/1.1\r\n",
-            self.generate_random_string(8)
+            "GET {} HTTP/1.1\r\n",
+            server.paths.render(ServerEndpoint::Latency, 0, &self.generate_random_string(8))
         );
         
         request.push_str(&format!("Host: {}:{}\r\n", server.host, server.port));
@@ -508,11 +1016,11 @@ impl StealthEngine {
 
     /// Send latency test request (mimics speedtest.net behavior)
     async fn send_latency_test(&self, client: &Client, server: &SpeedtestServer) -> Result<()> {
-        let latency_url = format!("http://{}:{}/speedtest/latency.txt", server.host, server.port);
+        let latency_url = server.endpoint_url(self.use_https().await, ServerEndpoint::Latency, 0, &self.generate_random_string(8));
         
+        let _permit = self.limiter.acquire("stealth latency").await;
         let response = client
             .get(&latency_url)
-            .query(&[("r", &self.generate_random_string(8))])
             .send()
             .await;
 
@@ -537,7 +1045,7 @@ impl StealthEngine {
 
     /// Send configuration request (mimics speedtest.net behavior)
     async fn send_config_request(&self, client: &Client, server: &SpeedtestServer) -> Result<()> {
-        let config_url = format!("http://{}:{}/speedtest/upload.php", server.host, server.port);
+        let config_url = server.endpoint_url(self.use_https().await, ServerEndpoint::Upload, 0, "");
         
         // Generate random data payload similar to speedtest.net
         let payload_size = rand::thread_rng().gen_range(
@@ -546,6 +1054,7 @@ impl StealthEngine {
         
         let payload = self.generate_speedtest_payload(payload_size);
         
+        let _permit = self.limiter.acquire("stealth upload").await;
         let response = client
             .post(&config_url)
             .header("Content-Type", "application/x-www-form-urlencoded")
@@ -568,8 +1077,9 @@ impl StealthEngine {
 
     /// Send keep-alive ping
     async fn send_keep_alive_ping(&self, client: &Client, server: &SpeedtestServer) -> Result<()> {
-        let ping_url = format!("http://{}:{}/speedtest/latency.txt", server.host, server.port);
+        let ping_url = server.endpoint_url(self.use_https().await, ServerEndpoint::Latency, 0, &self.generate_random_string(8));
         
+        let _permit = self.limiter.acquire("stealth keep-alive").await;
         let response = client
             .head(&ping_url)
             .send()
@@ -618,6 +1128,12 @@ impl StealthEngine {
 
     /// Update connection statistics
     pub async fn update_connection_stats(&self, server: &SpeedtestServer, bytes_sent: u64) {
+        if let Some(table) = &self.connection_table {
+            table.record_activity(ConnectionOwner::Stealth, server, bytes_sent);
+        }
+        if let Some(usage) = &self.usage {
+            usage.record(TrafficSource::Stealth, bytes_sent).await;
+        }
         let mut connections = self.active_connections.write().await;
         
         if let Some(connection) = connections.get_mut(&server.server_id) {
@@ -650,17 +1166,23 @@ impl StealthEngine {
         let old_index = rotation_state.current_server_index;
         let old_server_id = rotation_state.servers_in_rotation[old_index].server_id.clone();
         let old_server_name = rotation_state.servers_in_rotation[old_index].name.clone();
+
+        // Re-rank by the latest probes so servers that stopped answering drop out
+        let ranked = self.server_pool.rank(rotation_state.servers_in_rotation.clone()).await;
+        let old_position = ranked.iter().position(|s| s.server_id == old_server_id);
+        rotation_state.servers_in_rotation = ranked;
         
         // Move to next server in rotation
-        rotation_state.current_server_index = 
-            (rotation_state.current_server_index + 1) % rotation_state.servers_in_rotation.len();
+        rotation_state.current_server_index = match old_position {
+            Some(position) => (position + 1) % rotation_state.servers_in_rotation.len(),
+            None => 0,
+        };
         rotation_state.last_rotation = Instant::now();
-        
-        // Randomize rotation interval for next rotation
+        rotation_state.rotations += 1;
         rotation_state.rotation_interval = self.calculate_rotation_interval();
         
         let new_server_name = rotation_state.servers_in_rotation[rotation_state.current_server_index].name.clone();
-        let rotation_interval = rotation_state.rotation_interval;
+        let rotation_interval = self.rotation_due(&rotation_state, None);
         
         drop(rotation_state);
         
@@ -670,6 +1192,9 @@ impl StealthEngine {
         // Clean up old connection
         let mut connections = self.active_connections.write().await;
         connections.remove(&old_server_id);
+        if let Some(table) = &self.connection_table {
+            table.close(ConnectionOwner::Stealth, &old_server_id);
+        }
         
         Ok(())
     }
@@ -708,9 +1233,10 @@ impl StealthEngine {
             current_server: rotation_state.servers_in_rotation
                 .get(rotation_state.current_server_index)
                 .map(|s| s.name.clone()),
-            next_rotation_in: rotation_state.rotation_interval
+            next_rotation_in: self.rotation_due(&rotation_state, None)
                 .saturating_sub(rotation_state.last_rotation.elapsed()),
             stealth_level: self.stealth_level.clone(),
+            transport: self.transport,
             dpi_bypass_stats,
         }
     }
@@ -726,6 +1252,22 @@ impl StealthEngine {
             dpi_bypass_config: self.dpi_bypass_config.clone(),
             adaptive_state: Arc::clone(&self.adaptive_state),
             is_active: Arc::clone(&self.is_active),
+            shared_state: self.shared_state.clone(),
+            heartbeat: Arc::clone(&self.heartbeat),
+            rtt_sampler: self.rtt_sampler.clone(),
+            connection_table: self.connection_table.clone(),
+            limiter: self.limiter.clone(),
+            webhooks: self.webhooks.clone(),
+            usage: self.usage.clone(),
+            shutdown: self.shutdown.clone(),
+            scheduler: self.scheduler.clone(),
+            mimicry_profile: self.mimicry_profile,
+            strategies: self.strategies.clone(),
+            fast_com: Arc::clone(&self.fast_com),
+            cloudflare: Arc::clone(&self.cloudflare),
+            transport: self.transport,
+            quic: Arc::clone(&self.quic),
+            dns: Arc::clone(&self.dns),
         }
     }
 
@@ -746,9 +1288,17 @@ impl StealthEngine {
 
     /// Create DPI-bypassing TCP connection with advanced stealth features
     pub async fn create_stealth_connection(&self, server: &SpeedtestServer) -> Result<TcpStream> {
-        let addr = format!("{}:{}", server.host, server.port);
-        let socket_addr: SocketAddr = addr.parse()
-            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Invalid address {}: {}", addr, e)))?;
+        // Through a proxy the socket goes to the proxy, which then opens the tunnel to the server
+        let proxy = proxy::route(&server.host);
+        let addr = match &proxy {
+            Some(proxy) => format!("{}:{}", proxy.host, proxy.port),
+            None => format!("{}:{}", server.host, server.port),
+        };
+        // Hosts are usually names; TLS needs the name for SNI, the socket needs an address
+        let socket_addr: SocketAddr = tokio::net::lookup_host(&addr).await
+            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Invalid address {}: {}", addr, e)))?
+            .next()
+            .ok_or_else(|| SpeedKarmaError::NetworkUnavailable(format!("{} did not resolve", addr)))?;
 
         // Create TCP socket with custom configuration
         let socket = if socket_addr.is_ipv4() {
@@ -764,14 +1314,36 @@ impl StealthEngine {
         let stream = if self.dpi_bypass_config.timing_obfuscation {
             self.connect_with_timing_obfuscation(socket, socket_addr).await?
         } else {
-            socket.connect(socket_addr).await
-                .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Connection failed: {}", e)))?
+            let started = Instant::now();
+            let stream = socket.connect(socket_addr).await
+                .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Connection failed: {}", e)))?;
+            self.record_handshake(started.elapsed());
+            stream
+        };
+        let stream = match proxy {
+            Some(proxy) => proxy.tunnel(stream, &server.host, server.port).await?,
+            None => stream,
         };
 
+        self.apply_qos(&stream);
+
         debug!("Created stealth connection to {}", server.name);
         Ok(stream)
     }
 
+    /// Requests OS-level prioritization for a keep-warm connection (qWave on Windows, DSCP elsewhere)
+    fn apply_qos(&self, stream: &TcpStream) {
+        let dscp = self.dpi_bypass_config.dscp_marking;
+        if dscp == 0 {
+            return;
+        }
+        match qos::prioritize(stream, dscp) {
+            QosOutcome::QwaveFlow(flow_id) => debug!("Added connection to qWave flow {} (DSCP {})", flow_id, dscp),
+            QosOutcome::DscpMarked(value) => debug!("Marked connection with DSCP {}", value),
+            QosOutcome::Skipped(reason) => debug!("QoS not applied: {}", reason),
+        }
+    }
+
     /// Configure socket for DPI bypass
     async fn configure_socket_for_dpi_bypass(&self, socket: &TcpSocket) -> Result<()> {
         // Set TCP window size for mimicking speedtest behavior
@@ -785,17 +1357,16 @@ impl StealthEngine {
         // Enable TCP keepalive for persistent connections
         let _ = socket.set_keepalive(true);
 
-        // Set socket options for stealth
-        if self.dpi_bypass_config.dscp_marking > 0 {
-            // Note: DSCP marking typically requires raw sockets or special privileges
-            // This is a placeholder for the concept - actual implementation would need
-            // platform-specific code or elevated privileges
-            debug!("DSCP marking configured: {}", self.dpi_bypass_config.dscp_marking);
-        }
-
+        // DSCP/QoS is requested once the connection exists (see `apply_qos`)
         Ok(())
     }
 
+    fn record_handshake(&self, rtt: Duration) {
+        if let Some(sampler) = &self.rtt_sampler {
+            sampler.record(rtt);
+        }
+    }
+
     /// Connect with timing obfuscation to avoid pattern detection
     async fn connect_with_timing_obfuscation(&self, socket: TcpSocket, addr: SocketAddr) -> Result<TcpStream> {
         // Add random delay before connection attempt
@@ -803,8 +1374,10 @@ impl StealthEngine {
         sleep(Duration::from_millis(delay_ms)).await;
 
         // Attempt connection
+        let started = Instant::now();
         let stream = socket.connect(addr).await
             .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Obfuscated connection failed: {}", e)))?;
+        self.record_handshake(started.elapsed());
 
         // Add post-connection delay to mimic human behavior
         let post_delay_ms = rand::thread_rng().gen_range(100..500);
@@ -814,7 +1387,7 @@ impl StealthEngine {
     }
 
     /// Send fragmented packets to bypass DPI
-    pub async fn send_fragmented_request(&self, stream: &mut TcpStream, data: &[u8]) -> Result<()> {
+    pub async fn send_fragmented_request<S: AsyncWrite + Unpin>(&self, stream: &mut S, data: &[u8]) -> Result<()> {
         if !self.dpi_bypass_config.packet_fragmentation {
             // Send normally if fragmentation is disabled
             stream.write_all(data).await
@@ -895,28 +1468,27 @@ impl StealthEngine {
         headers
     }
 
-    /// Replicate DNS lookup patterns characteristic of speedtest.net
+    /// Resolves the hostnames a speedtest.net session looks up, in the page's order: the site,
+    /// its static CDN, the config API, then the chosen server. Failed lookups are only logged.
     pub async fn replicate_dns_patterns(&self, server: &SpeedtestServer) -> Result<()> {
         if !self.dpi_bypass_config.dns_pattern_replication {
             return Ok(());
         }
 
-        // Simulate DNS lookups that speedtest.net typically performs
-        let dns_queries = vec![
-            format!("{}", server.host),
-            "www.speedtest.net".to_string(),
-            "c.speedtest.net".to_string(),
-            format!("ping-{}.speedtest.net", rand::thread_rng().gen_range(1..10)),
-        ];
-
-        for query in dns_queries {
-            // Simulate DNS lookup timing
-            let lookup_delay = rand::thread_rng().gen_range(10..50);
-            sleep(Duration::from_millis(lookup_delay)).await;
+        let mut names = vec!["www.speedtest.net", "b.cdnst.net", "c.speedtest.net"];
+        // IP-literal servers need no lookup
+        if server.host.parse::<IpAddr>().is_err() {
+            names.push(server.host.as_str());
+        }
 
-            // In a real implementation, we would perform actual DNS lookups
-            // For now, we just simulate the timing pattern
-            debug!("Simulated DNS lookup for: {}", query);
+        let _permit = self.limiter.acquire("stealth dns").await;
+        for name in names {
+            match self.dns.lookup(name).await {
+                Ok(addresses) => debug!("Resolved {} via {:?}: {} addresses", name, self.dns.kind(), addresses.len()),
+                Err(e) => debug!("DNS lookup for {} failed: {}", name, e),
+            }
+            let gap = rand::thread_rng().gen_range(10..50);
+            sleep(Duration::from_millis(gap)).await;
         }
 
         Ok(())
@@ -943,8 +1515,19 @@ impl StealthEngine {
             DetectionRisk::Low
         };
 
-        // Return the higher risk level
-        match (failure_risk, effectiveness_risk) {
+        // Whole cycles failing in a row suggest servers or paths are being blocked
+        let streak_risk = match adaptive_state.cycle_error_streak {
+            0 => DetectionRisk::Low,
+            1..=2 => DetectionRisk::Medium,
+            _ => DetectionRisk::High,
+        };
+
+        // Return the highest risk level
+        Self::higher_risk(Self::higher_risk(failure_risk, effectiveness_risk), streak_risk)
+    }
+
+    fn higher_risk(a: DetectionRisk, b: DetectionRisk) -> DetectionRisk {
+        match (a, b) {
             (DetectionRisk::Critical, _) | (_, DetectionRisk::Critical) => DetectionRisk::Critical,
             (DetectionRisk::High, _) | (_, DetectionRisk::High) => DetectionRisk::High,
             (DetectionRisk::Medium, _) | (_, DetectionRisk::Medium) => DetectionRisk::Medium,
@@ -957,6 +1540,14 @@ impl StealthEngine {
         let current_risk = self.assess_detection_risk().await;
         let mut adaptive_state = self.adaptive_state.write().await;
 
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.detection_risk(current_risk == DetectionRisk::Critical, &serde_json::json!({
+                "consecutive_failures": adaptive_state.consecutive_failures,
+                "cycle_error_streak": adaptive_state.cycle_error_streak,
+                "effectiveness_score": adaptive_state.effectiveness_score,
+            })).await;
+        }
+
         if current_risk != adaptive_state.current_risk_level {
             info!("Detection risk changed from {:?} to {:?}", adaptive_state.current_risk_level, current_risk);
             
@@ -1020,16 +1611,18 @@ impl StealthEngine {
             consecutive_failures: adaptive_state.consecutive_failures,
             effectiveness_score: adaptive_state.effectiveness_score,
             adaptation_count: adaptive_state.adaptation_count,
+            cycle_error_streak: adaptive_state.cycle_error_streak,
             packet_fragmentation_enabled: self.dpi_bypass_config.packet_fragmentation,
             header_obfuscation_enabled: self.dpi_bypass_config.header_obfuscation,
             dscp_marking: self.dpi_bypass_config.dscp_marking,
             dns_pattern_replication_enabled: self.dpi_bypass_config.dns_pattern_replication,
+            tls_fingerprint: self.dpi_bypass_config.tls_fingerprint,
         }
     }
 }
 
 /// Statistics for stealth operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct StealthStats {
     pub active_connections: usize,
     pub total_packets_sent: u64,
@@ -1037,19 +1630,93 @@ pub struct StealthStats {
     pub current_server: Option<String>,
     pub next_rotation_in: Duration,
     pub stealth_level: StealthLevel,
+    pub transport: TrafficTransport,
     pub dpi_bypass_stats: DPIBypassStats,
 }
 
 /// Statistics for DPI bypass operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DPIBypassStats {
     pub detection_risk: DetectionRisk,
     pub consecutive_failures: u32,
     pub effectiveness_score: f64,
     pub adaptation_count: u32,
+    pub cycle_error_streak: u32,
     pub packet_fragmentation_enabled: bool,
     pub header_obfuscation_enabled: bool,
     pub dscp_marking: u8,
     pub dns_pattern_replication_enabled: bool,
+    pub tls_fingerprint: Option<TlsFingerprint>,
+}
+
+/// Runs the stealth loop as one task of the app `Supervisor`, which restarts it with backoff.
+/// A loop that stops sending heartbeats is dropped and reported as failed.
+pub struct StealthWatchdog {
+    engine: Arc<StealthEngine>,
 }
 
+impl StealthWatchdog {
+    pub fn new(engine: Arc<StealthEngine>) -> Self {
+        Self { engine }
+    }
+
+    pub fn engine(&self) -> &Arc<StealthEngine> { &self.engine }
+
+    /// One run: waits until stealth traffic is allowed, then runs the loop until it stops on
+    /// purpose (`Ok`), gives up, or hangs (`Err`)
+    pub async fn run(&self) -> Result<()> {
+        // Stay idle (and off the network) until stealth traffic is allowed
+        while !self.engine.module_enabled().await {
+            if !self.engine.pause(Duration::from_secs(30)).await { return Ok(()); }
+        }
+        self.engine.prepare().await
+            .map_err(|e| SpeedKarmaError::SystemError(format!("Stealth loop start failed: {}", e)))?;
+
+        // Polled in place rather than spawned, so aborting this task also ends the loop
+        let stealth_loop = self.engine.run_stealth_loop();
+        tokio::pin!(stealth_loop);
+        loop {
+            tokio::select! {
+                result = &mut stealth_loop => {
+                    return result.map_err(|e| SpeedKarmaError::SystemError(
+                        format!("Stealth loop gave up after {} failed cycles: {}", MAX_CYCLE_ERROR_STREAK, e)
+                    ));
+                }
+                _ = sleep(HEARTBEAT_CHECK_INTERVAL) => {
+                    let age = self.engine.heartbeat_age().await;
+                    if age > HEARTBEAT_TIMEOUT {
+                        return Err(SpeedKarmaError::SystemError(format!("Stealth loop hung (no heartbeat for {}s)", age.as_secs())));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::app_state::AppControlState;
+    use crate::core::supervisor::Supervisor;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_abort_stops_the_stealth_worker() {
+        // Optimization is off by default, so the worker idles in its wait loop
+        let shared: SharedAppState = Arc::new(RwLock::new(AppControlState::default()));
+        let engine = StealthEngine::new(Arc::new(ServerPool::new().unwrap()), StealthLevel::Medium).with_shared_state(shared);
+        let watchdog = Arc::new(StealthWatchdog::new(Arc::new(engine)));
+        let supervisor = Supervisor::new();
+        let worker = Arc::clone(&watchdog);
+        supervisor.spawn("stealth", move || {
+            let worker = Arc::clone(&worker);
+            async move { worker.run().await }
+        });
+        sleep(Duration::from_secs(90)).await;
+        assert!(Arc::strong_count(&watchdog) > 1);
+
+        // Aborting drops the supervising task, its factory and the running worker
+        supervisor.abort_all();
+        sleep(Duration::from_secs(1)).await;
+        assert_eq!(Arc::strong_count(&watchdog), 1);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::core::config::TamperingCheckConfig;
+use crate::core::error::Result;
+use crate::data::models::TamperingReport;
+use crate::data::repository::Repository;
+use crate::network::limiter::OutboundLimiter;
+use crate::network::proxy;
+use chrono::Utc;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Names that must not resolve; answers for them come from an NXDOMAIN-rewriting resolver
+const NONEXISTENT_PARENT: &str = "example.com";
+/// DNSSEC-signed names with stable anycast answers, compared against the validated answer
+const SIGNED_NAMES: &[&str] = &["one.one.one.one", "dns.google"];
+
+/// DNS-over-HTTPS JSON answer for one A query
+#[derive(Debug, Clone, PartialEq)]
+pub struct DohAnswer {
+    /// Resolver validated the answer with DNSSEC
+    pub authenticated: bool,
+    pub addresses: Vec<IpAddr>,
+}
+
+/// `application/dns-json` response (Cloudflare and Google share the format)
+pub fn parse_doh_answer(json: &Value) -> Option<DohAnswer> {
+    if json.get("Status")?.as_u64()? != 0 {
+        return None;
+    }
+    let addresses = json
+        .get("Answer")
+        .and_then(Value::as_array)
+        .map(|records| {
+            records
+                .iter()
+                .filter(|r| r.get("type").and_then(Value::as_u64) == Some(1))
+                .filter_map(|r| r.get("data")?.as_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(DohAnswer { authenticated: json.get("AD").and_then(Value::as_bool).unwrap_or(false), addresses })
+}
+
+/// Whether the local resolver's IPv4 answer shares nothing with a DNSSEC-validated one
+pub fn dnssec_mismatch(system: &[IpAddr], validated: &DohAnswer) -> bool {
+    let local: Vec<&IpAddr> = system.iter().filter(|a| a.is_ipv4()).collect();
+    validated.authenticated
+        && !validated.addresses.is_empty()
+        && !local.is_empty()
+        && !local.iter().any(|a| validated.addresses.contains(a))
+}
+
+/// Headers an echo service saw over plain HTTP but not over HTTPS, lower-cased and sorted
+pub fn injected_headers(http_echo: &Value, https_echo: &Value) -> Vec<String> {
+    let names = |echo: &Value| -> HashSet<String> {
+        echo.get("headers")
+            .and_then(Value::as_object)
+            .map(|h| h.keys().map(|k| k.to_ascii_lowercase()).collect())
+            .unwrap_or_default()
+    };
+    let secure = names(https_echo);
+    let mut injected: Vec<String> = names(http_echo).into_iter().filter(|h| !secure.contains(h)).collect();
+    injected.sort();
+    injected
+}
+
+/// Looks for NXDOMAIN hijacking, DNSSEC-mismatched answers and transparent HTTP proxies
+pub struct TamperingChecker {
+    config: TamperingCheckConfig,
+    limiter: OutboundLimiter,
+}
+
+impl TamperingChecker {
+    pub fn new(config: TamperingCheckConfig) -> Self {
+        Self { config, limiter: OutboundLimiter::default() }
+    }
+
+    /// Counts each lookup and echo request against the app-wide outbound limit
+    pub fn with_limiter(mut self, limiter: OutboundLimiter) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Runs every check; checks that cannot reach their reference service report nothing
+    pub async fn check(&self) -> Result<TamperingReport> {
+        let client = proxy::apply(reqwest::Client::builder())
+            .timeout(Duration::from_secs(self.config.timeout_seconds.max(1)))
+            .build()?;
+        let (injected_headers, via) = self.check_http_proxy(&client).await;
+        let report = TamperingReport {
+            checked_at: Utc::now(),
+            nxdomain_hijack: self.check_nxdomain().await,
+            dnssec_mismatch: self.check_dnssec(&client).await,
+            injected_headers,
+            via,
+        };
+        info!(
+            "Tampering check: NXDOMAIN hijack {:?}, DNSSEC mismatch {:?}, injected headers {:?}, via {:?}",
+            report.nxdomain_hijack, report.dnssec_mismatch, report.injected_headers, report.via
+        );
+        Ok(report)
+    }
+
+    async fn check_nxdomain(&self) -> Vec<IpAddr> {
+        let mut hijacked = Vec::new();
+        for _ in 0..2 {
+            let _permit = self.limiter.acquire("NXDOMAIN check").await;
+            let name = format!("sk-{}.{}", uuid::Uuid::new_v4().simple(), NONEXISTENT_PARENT);
+            let Ok(addrs) = tokio::net::lookup_host(format!("{}:80", name)).await else { continue };
+            for ip in addrs.map(|a| a.ip()) {
+                if !hijacked.contains(&ip) {
+                    hijacked.push(ip);
+                }
+            }
+        }
+        hijacked
+    }
+
+    async fn check_dnssec(&self, client: &reqwest::Client) -> Vec<String> {
+        let mut mismatched = Vec::new();
+        for name in SIGNED_NAMES {
+            let _permit = self.limiter.acquire("DNSSEC check").await;
+            let Ok(local) = tokio::net::lookup_host((*name, 443)).await else { continue };
+            let local: Vec<IpAddr> = local.map(|a| a.ip()).collect();
+            let response = client
+                .get(&self.config.doh_url)
+                .query(&[("name", *name), ("type", "A"), ("do", "1")])
+                .header("accept", "application/dns-json")
+                .send()
+                .await;
+            let validated = match response {
+                Ok(resp) => resp.json::<Value>().await.ok().as_ref().and_then(parse_doh_answer),
+                Err(e) => {
+                    debug!("DoH lookup of {} failed: {}", name, e);
+                    None
+                }
+            };
+            if validated.is_some_and(|v| dnssec_mismatch(&local, &v)) {
+                warn!("Resolver answer for {} differs from the DNSSEC-validated one", name);
+                mismatched.push(name.to_string());
+            }
+        }
+        mismatched
+    }
+
+    async fn echo(&self, client: &reqwest::Client, scheme: &str) -> Option<(Value, Option<String>)> {
+        let _permit = self.limiter.acquire("header echo").await;
+        let response = client
+            .get(format!("{}://{}", scheme, self.config.header_echo_url))
+            .send()
+            .await
+            .map_err(|e| debug!("Header echo over {} failed: {}", scheme, e))
+            .ok()?;
+        let via = response.headers().get("via").and_then(|v| v.to_str().ok()).map(str::to_string);
+        Some((response.json().await.ok()?, via))
+    }
+
+    async fn check_http_proxy(&self, client: &reqwest::Client) -> (Vec<String>, Option<String>) {
+        let (Some((plain, via)), Some((secure, _))) = (self.echo(client, "http").await, self.echo(client, "https").await) else {
+            return (Vec::new(), None);
+        };
+        (injected_headers(&plain, &secure), via)
+    }
+
+    /// Runs the checks and stores the result on the ISP profile
+    pub async fn check_and_record(&self, repository: &Repository, profile_id: i64) -> Result<TamperingReport> {
+        let report = self.check().await?;
+        repository.update_isp_profile_tampering(profile_id, &report).await?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tampering_signals() {
+        let doh = serde_json::json!({
+            "Status": 0, "AD": true,
+            "Answer": [{ "name": "one.one.one.one", "type": 1, "data": "1.1.1.1" }, { "name": "one.one.one.one", "type": 1, "data": "1.0.0.1" }]
+        });
+        let validated = parse_doh_answer(&doh).unwrap();
+        assert!(validated.authenticated);
+        assert_eq!(validated.addresses.len(), 2);
+        assert!(!dnssec_mismatch(&["1.0.0.1".parse().unwrap()], &validated));
+        assert!(dnssec_mismatch(&["203.0.113.7".parse().unwrap()], &validated));
+        assert!(!dnssec_mismatch(&[], &validated));
+        assert!(parse_doh_answer(&serde_json::json!({ "Status": 3 })).is_none());
+
+        let https = serde_json::json!({ "headers": { "Host": "httpbin.org", "Accept": "*/*" } });
+        let http = serde_json::json!({ "headers": { "Host": "httpbin.org", "Accept": "*/*", "X-Forwarded-For": "10.0.0.2", "Via": "1.1 proxy" } });
+        assert_eq!(injected_headers(&http, &https), vec!["via".to_string(), "x-forwarded-for".to_string()]);
+        assert!(injected_headers(&https, &https).is_empty());
+    }
+}
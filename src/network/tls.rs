@@ -0,0 +1,162 @@
+//! TLS client hellos for generated traffic. Plain HTTP on port 8080 is trivially classified, so
+//! mimicry and keeper requests go over TLS with the cipher, key exchange group and ALPN order of
+//! the client they imitate. rustls cannot send GREASE values or reorder extensions, so the match
+//! covers the offered algorithms, not the full JA3 hash.
+
+use crate::core::error::{Result, SpeedKarmaError};
+use crate::data::models::StealthLevel;
+use reqwest::ClientBuilder;
+use rustls::{cipher_suite, kx_group, version, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName, SupportedCipherSuite, SupportedKxGroup};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+
+/// Client whose TLS hello the generated traffic presents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsFingerprint {
+    /// Chrome, as used by the speedtest.net web page: AES-128 first, ALPN h2 then http/1.1
+    Chrome,
+    /// Speedtest desktop and CLI apps on OpenSSL defaults: AES-256 first, ALPN http/1.1 only
+    SpeedtestApp,
+}
+
+/// Mozilla's root certificates, bundled so lookups do not depend on the OS store
+pub(crate) fn root_store() -> RootCertStore {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(anchor.subject, anchor.spki, anchor.name_constraints)
+    }));
+    roots
+}
+
+impl TlsFingerprint {
+    /// Low stays on plain HTTP; Medium looks like the Speedtest app, High and Maximum like Chrome
+    pub fn for_level(level: &StealthLevel) -> Option<Self> {
+        match level {
+            StealthLevel::Low => None,
+            StealthLevel::Medium => Some(TlsFingerprint::SpeedtestApp),
+            StealthLevel::High | StealthLevel::Maximum => Some(TlsFingerprint::Chrome),
+        }
+    }
+
+    /// Offered cipher suites, in the client's order
+    pub fn cipher_suites(&self) -> Vec<SupportedCipherSuite> {
+        match self {
+            TlsFingerprint::Chrome => vec![
+                cipher_suite::TLS13_AES_128_GCM_SHA256,
+                cipher_suite::TLS13_AES_256_GCM_SHA384,
+                cipher_suite::TLS13_CHACHA20_POLY1305_SHA256,
+                cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+                cipher_suite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+                cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+                cipher_suite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+                cipher_suite::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+                cipher_suite::TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+            ],
+            TlsFingerprint::SpeedtestApp => vec![
+                cipher_suite::TLS13_AES_256_GCM_SHA384,
+                cipher_suite::TLS13_CHACHA20_POLY1305_SHA256,
+                cipher_suite::TLS13_AES_128_GCM_SHA256,
+                cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+                cipher_suite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+                cipher_suite::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+                cipher_suite::TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+                cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+                cipher_suite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+            ],
+        }
+    }
+
+    /// Offered key exchange groups; both clients prefer X25519, then P-256 and P-384
+    pub fn kx_groups(&self) -> Vec<&'static SupportedKxGroup> {
+        vec![&kx_group::X25519, &kx_group::SECP256R1, &kx_group::SECP384R1]
+    }
+
+    pub fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        match self {
+            TlsFingerprint::Chrome => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            TlsFingerprint::SpeedtestApp => vec![b"http/1.1".to_vec()],
+        }
+    }
+
+    /// rustls config offering this fingerprint; built once per fingerprint
+    pub fn client_config(&self) -> Arc<ClientConfig> {
+        static CHROME: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+        static SPEEDTEST_APP: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+        let cell = match self {
+            TlsFingerprint::Chrome => &CHROME,
+            TlsFingerprint::SpeedtestApp => &SPEEDTEST_APP,
+        };
+        Arc::clone(cell.get_or_init(|| Arc::new(self.build_config(self.alpn_protocols()))))
+    }
+
+    /// Same hello with ALPN cut to http/1.1, for raw streams that write HTTP/1.1 themselves;
+    /// offering h2 there would let the server pick a protocol the stream never speaks
+    pub fn http1_client_config(&self) -> Arc<ClientConfig> {
+        static CHROME: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+        static SPEEDTEST_APP: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+        let cell = match self {
+            TlsFingerprint::Chrome => &CHROME,
+            TlsFingerprint::SpeedtestApp => &SPEEDTEST_APP,
+        };
+        Arc::clone(cell.get_or_init(|| Arc::new(self.build_config(vec![b"http/1.1".to_vec()]))))
+    }
+
+    fn build_config(&self, alpn_protocols: Vec<Vec<u8>>) -> ClientConfig {
+        let mut config = ClientConfig::builder()
+            .with_cipher_suites(&self.cipher_suites())
+            .with_kx_groups(&self.kx_groups())
+            .with_protocol_versions(&[&version::TLS13, &version::TLS12])
+            .expect("every fingerprint offers TLS 1.2 and 1.3 suites")
+            .with_root_certificates(root_store())
+            .with_no_client_auth();
+        config.alpn_protocols = alpn_protocols;
+        config
+    }
+
+    /// reqwest client builder whose HTTPS connections present this fingerprint
+    pub fn apply(&self, builder: ClientBuilder) -> ClientBuilder {
+        builder.use_preconfigured_tls(ClientConfig::clone(&self.client_config()))
+    }
+
+    /// TLS handshake over an already connected stream, with `host` as SNI. Offers http/1.1 only,
+    /// since the caller writes HTTP/1.1 on the stream
+    pub async fn connect<S>(&self, host: &str, stream: S) -> Result<TlsStream<S>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let server_name = ServerName::try_from(host)
+            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("Invalid TLS server name {}: {}", host, e)))?;
+        TlsConnector::from(self.http1_client_config())
+            .connect(server_name, stream)
+            .await
+            .map_err(|e| SpeedKarmaError::NetworkUnavailable(format!("TLS handshake with {} failed: {}", host, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprints_follow_the_imitated_clients() {
+        assert_eq!(TlsFingerprint::for_level(&StealthLevel::Low), None);
+        assert_eq!(TlsFingerprint::for_level(&StealthLevel::Medium), Some(TlsFingerprint::SpeedtestApp));
+        assert_eq!(TlsFingerprint::for_level(&StealthLevel::Maximum), Some(TlsFingerprint::Chrome));
+
+        let chrome = TlsFingerprint::Chrome;
+        assert_eq!(chrome.cipher_suites()[0].suite(), cipher_suite::TLS13_AES_128_GCM_SHA256.suite());
+        assert_eq!(chrome.client_config().alpn_protocols, vec![b"h2".to_vec(), b"http/1.1".to_vec()]);
+        assert_eq!(chrome.http1_client_config().alpn_protocols, vec![b"http/1.1".to_vec()]);
+        let suites = |config: Arc<ClientConfig>| config.cipher_suites.iter().map(|s| s.suite()).collect::<Vec<_>>();
+        assert_eq!(suites(chrome.http1_client_config()), suites(chrome.client_config()));
+
+        let app = TlsFingerprint::SpeedtestApp;
+        assert_eq!(app.cipher_suites()[0].suite(), cipher_suite::TLS13_AES_256_GCM_SHA384.suite());
+        assert_eq!(app.client_config().alpn_protocols, vec![b"http/1.1".to_vec()]);
+        assert!(Arc::ptr_eq(&app.client_config(), &app.client_config()));
+    }
+}
@@ -0,0 +1,298 @@
+use crate::core::command::command;
+use crate::data::models::{RouteHop, RouteSnapshot, SpeedMeasurement};
+use crate::network::asn_db::AsnDatabase;
+use crate::network::resolvers::is_routable;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration as StdDuration;
+use tracing::debug;
+
+/// Anycast addresses traced to; numeric so no DNS lookup is needed
+pub const ANCHORS: &[&str] = &["1.1.1.1", "8.8.8.8", "9.9.9.9"];
+
+/// Hops traced per anchor; the access network is always within the first few
+const MAX_HOPS: u8 = 12;
+/// Leading hops searched for the ISP's network
+const ISP_HOPS: usize = 5;
+/// A single trace slower than this is given up on
+const TRACE_TIMEOUT: StdDuration = StdDuration::from_secs(40);
+/// Confidence when every traced path enters the same known operator
+const ROUTE_CONFIDENCE: f64 = 0.85;
+/// Scale applied when the ISP is only known by its AS description
+const UNKNOWN_OPERATOR_SCALE: f64 = 0.8;
+/// Longest route-change history the panel can ask for, the most history the app keeps
+pub const MAX_ROUTE_HISTORY_DAYS: u32 = 365;
+
+/// ISP the traced paths enter first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteVerdict {
+    pub isp_name: String,
+    /// Country code, empty when unknown
+    pub region: String,
+    pub confidence: f64,
+    pub asn: u32,
+    pub organization: Option<String>,
+}
+
+/// The AS path to an anchor differed from the previous snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathChange {
+    pub anchor: String,
+    pub changed_at: DateTime<Utc>,
+    pub previous_path: String,
+    pub new_path: String,
+}
+
+/// Median download around a path change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathChangeImpact {
+    pub change: PathChange,
+    pub before_mbps: Option<f64>,
+    pub after_mbps: Option<f64>,
+}
+
+impl PathChangeImpact {
+    /// Download after the change relative to before it
+    pub fn ratio(&self) -> Option<f64> {
+        let (before, after) = (self.before_mbps?, self.after_mbps?);
+        (before > 0.0).then(|| after / before)
+    }
+}
+
+fn parse_rtt(token: &str) -> Option<f64> {
+    let value = token.trim_start_matches('<').trim_end_matches("ms");
+    value.parse().ok().filter(|v: &f64| v.is_finite())
+}
+
+/// Hops from `traceroute -n`, `tracepath -n` or `tracert -d` output, ordered by TTL.
+/// Repeated TTLs (tracepath) keep the first answer.
+pub fn parse_traceroute(output: &str) -> Vec<RouteHop> {
+    let mut hops: Vec<RouteHop> = Vec::new();
+    for line in output.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(ttl) = tokens.first().and_then(|t| t.trim_end_matches([':', '?']).parse::<u8>().ok()) else { continue };
+        if line.contains("[LOCALHOST]") { continue; }
+        let address = tokens[1..].iter().find_map(|t| t.trim_matches(|c| c == '(' || c == ')' || c == '[' || c == ']').parse::<IpAddr>().ok());
+        // `1.2 ms`, `1.2ms` and `<1 ms` all appear; `*` means the probe timed out
+        let rtt_ms = tokens.windows(2).find_map(|w| if w[1] == "ms" { parse_rtt(w[0]) } else { None })
+            .or_else(|| tokens.iter().find(|t| t.ends_with("ms") && t.len() > 2).and_then(|t| parse_rtt(t)));
+        let hop = RouteHop { ttl, address, rtt_ms: address.and(rtt_ms), asn: None, asn_name: None };
+        match hops.iter_mut().find(|h| h.ttl == ttl) {
+            Some(existing) if existing.address.is_none() => *existing = hop,
+            Some(_) => {}
+            None => hops.push(hop),
+        }
+    }
+    hops.sort_by_key(|h| h.ttl);
+    hops
+}
+
+/// Fills in hop ASNs from the offline database; private and CGNAT hops stay unannotated
+pub fn annotate(hops: &mut [RouteHop], asn_db: &AsnDatabase) {
+    for hop in hops.iter_mut() {
+        let Some(IpAddr::V4(v4)) = hop.address.filter(is_routable) else { continue };
+        if let Some(record) = asn_db.lookup(v4) {
+            hop.asn = Some(record.asn);
+            hop.asn_name = Some(record.description.clone()).filter(|d| !d.is_empty());
+        }
+    }
+}
+
+/// Snapshot of `hops` with the ISP taken from the first annotated public hop within `ISP_HOPS`
+pub fn snapshot(anchor: &str, hops: Vec<RouteHop>, asn_db: &AsnDatabase) -> RouteSnapshot {
+    let first = hops.iter().take(ISP_HOPS).find(|h| h.asn.is_some());
+    let record = first.and_then(|h| match h.address {
+        Some(IpAddr::V4(v4)) => asn_db.lookup(v4),
+        _ => None,
+    });
+    RouteSnapshot {
+        id: None,
+        taken_at: Utc::now(),
+        anchor: anchor.to_string(),
+        isp_name: record.map(|r| r.isp_name().map(str::to_string).unwrap_or_else(|| r.description.clone())),
+        isp_asn: first.and_then(|h| h.asn),
+        hops,
+    }
+}
+
+/// ISP most traced paths enter first, scaled by how many paths agree. `None` when no path
+/// reached a public hop the ASN database knows within the first few hops.
+pub fn isp_from_routes(snapshots: &[RouteSnapshot], asn_db: &AsnDatabase) -> Option<RouteVerdict> {
+    let entered: Vec<u32> = snapshots.iter().filter_map(|s| s.isp_asn).collect();
+    let asn = *entered.iter().max_by_key(|a| entered.iter().filter(|b| b == a).count())?;
+    let agreeing = entered.iter().filter(|a| **a == asn).count();
+    let lead = snapshots.iter().find(|s| s.isp_asn == Some(asn))?;
+    let record = lead.hops.iter().take(ISP_HOPS).find_map(|h| match (h.asn, h.address) {
+        (Some(a), Some(IpAddr::V4(v4))) if a == asn => asn_db.lookup(v4),
+        _ => None,
+    });
+    let known = record.and_then(|r| r.isp_name()).is_some();
+    Some(RouteVerdict {
+        isp_name: lead.isp_name.clone()?,
+        region: record.map(|r| r.country.clone()).unwrap_or_default(),
+        confidence: ROUTE_CONFIDENCE * agreeing as f64 / snapshots.len() as f64 * if known { 1.0 } else { UNKNOWN_OPERATOR_SCALE },
+        asn,
+        organization: record.map(|r| r.description.clone()).filter(|d| !d.is_empty()),
+    })
+}
+
+/// Consecutive snapshots of the same anchor whose AS paths differ. Incomplete traces are skipped.
+pub fn path_changes(snapshots: &[RouteSnapshot]) -> Vec<PathChange> {
+    let mut sorted: Vec<&RouteSnapshot> = snapshots.iter().filter(|s| !s.path_signature().is_empty()).collect();
+    sorted.sort_by(|a, b| a.anchor.cmp(&b.anchor).then(a.taken_at.cmp(&b.taken_at)));
+    sorted
+        .windows(2)
+        .filter(|w| w[0].anchor == w[1].anchor && w[0].path_signature() != w[1].path_signature())
+        .map(|w| PathChange {
+            anchor: w[1].anchor.clone(),
+            changed_at: w[1].taken_at,
+            previous_path: w[0].path_signature(),
+            new_path: w[1].path_signature(),
+        })
+        .collect()
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() { return None; }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    Some(values[values.len() / 2])
+}
+
+/// Median download in the `window` before and after each change
+pub fn correlate_path_changes(changes: Vec<PathChange>, measurements: &[SpeedMeasurement], window: Duration) -> Vec<PathChangeImpact> {
+    changes
+        .into_iter()
+        .map(|change| {
+            let at = change.changed_at;
+            let downloads = |from: DateTime<Utc>, to: DateTime<Utc>| {
+                measurements.iter().filter(|m| m.timestamp >= from && m.timestamp < to).map(|m| m.download_mbps).collect::<Vec<_>>()
+            };
+            PathChangeImpact {
+                before_mbps: median(downloads(at - window, at)),
+                after_mbps: median(downloads(at, at + window)),
+                change,
+            }
+        })
+        .collect()
+}
+
+async fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = tokio::time::timeout(TRACE_TIMEOUT, command(program).args(args).output()).await.ok()?.ok()?;
+    // traceroute exits non-zero when the last hops do not answer; the hops it got are still useful
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    (!stdout.trim().is_empty()).then_some(stdout)
+}
+
+/// Raw hops to `anchor` using the platform's traceroute tool (UDP probes; ICMP with `tracert`)
+#[cfg(not(target_os = "windows"))]
+pub async fn trace(anchor: &str) -> Vec<RouteHop> {
+    let max_hops = MAX_HOPS.to_string();
+    if let Some(out) = command_output("traceroute", &["-n", "-q", "1", "-w", "2", "-m", &max_hops, anchor]).await {
+        return parse_traceroute(&out);
+    }
+    // Many Linux installs ship tracepath but not traceroute
+    command_output("tracepath", &["-n", "-m", &max_hops, anchor]).await.map(|out| parse_traceroute(&out)).unwrap_or_default()
+}
+
+#[cfg(target_os = "windows")]
+pub async fn trace(anchor: &str) -> Vec<RouteHop> {
+    let max_hops = MAX_HOPS.to_string();
+    command_output("tracert", &["-d", "-h", &max_hops, "-w", "1000", anchor]).await.map(|out| parse_traceroute(&out)).unwrap_or_default()
+}
+
+/// Annotated snapshots of every anchor that could be traced
+pub async fn trace_anchors(anchors: &[String], asn_db: &AsnDatabase) -> Vec<RouteSnapshot> {
+    let mut snapshots = Vec::new();
+    for anchor in anchors {
+        let mut hops = trace(anchor).await;
+        if hops.is_empty() {
+            debug!("Traceroute to {} returned no hops", anchor);
+            continue;
+        }
+        annotate(&mut hops, asn_db);
+        snapshots.push(snapshot(anchor, hops, asn_db));
+    }
+    snapshots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::asn_db::AsnRecord;
+
+    fn ip(a: [u8; 4]) -> u32 { u32::from_be_bytes(a) }
+
+    #[test]
+    fn test_parse_tool_outputs() {
+        let traceroute = "traceroute to 1.1.1.1 (1.1.1.1), 12 hops max, 60 byte packets\n \
+            1  192.168.1.1  1.204 ms\n 2  *\n 3  100.72.0.1  9.871 ms\n 4  203.94.64.1  12.5 ms\n";
+        let hops = parse_traceroute(traceroute);
+        assert_eq!(hops.len(), 4);
+        assert_eq!(hops[0].address, Some("192.168.1.1".parse().unwrap()));
+        assert_eq!(hops[1].address, None);
+        assert_eq!(hops[3].rtt_ms, Some(12.5));
+
+        let tracepath = " 1?: [LOCALHOST]                      pmtu 1500\n \
+            1:  192.168.1.1                                           1.123ms \n \
+            1:  192.168.1.1                                           1.050ms \n \
+            2:  no reply\n \
+            3:  203.94.64.1                                          11.2ms asymm  4\n";
+        let hops = parse_traceroute(tracepath);
+        assert_eq!(hops.iter().map(|h| h.ttl).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(hops[0].rtt_ms, Some(1.123));
+        assert_eq!(hops[2].address, Some("203.94.64.1".parse().unwrap()));
+
+        let tracert = "Tracing route to 1.1.1.1 over a maximum of 12 hops\r\n\r\n  \
+            1    <1 ms    <1 ms    <1 ms  192.168.1.1\r\n  \
+            2     *        *        *     Request timed out.\r\n  \
+            3     8 ms     9 ms     8 ms  203.94.64.1\r\n\r\nTrace complete.\r\n";
+        let hops = parse_traceroute(tracert);
+        assert_eq!(hops.len(), 3);
+        assert_eq!(hops[0].rtt_ms, Some(1.0));
+        assert_eq!((hops[1].address, hops[2].rtt_ms), (None, Some(8.0)));
+    }
+
+    #[test]
+    fn test_isp_and_path_changes_from_routes() {
+        let db = AsnDatabase::from_records(vec![
+            AsnRecord { range_start: ip([203, 94, 64, 0]), range_end: ip([203, 94, 127, 255]), asn: 9506, country: "LK".into(), description: "Hutchison Telecommunications Lanka".into() },
+            AsnRecord { range_start: ip([4, 69, 0, 0]), range_end: ip([4, 69, 255, 255]), asn: 3356, country: "US".into(), description: "Level 3 Parent".into() },
+            AsnRecord { range_start: ip([1, 1, 1, 0]), range_end: ip([1, 1, 1, 255]), asn: 13335, country: "US".into(), description: "Cloudflare".into() },
+        ]);
+        let trace_of = |addresses: &[&str]| {
+            let output: String = addresses.iter().enumerate().map(|(i, a)| format!(" {}  {}  {}.0 ms\n", i + 1, a, i + 1)).collect();
+            let mut hops = parse_traceroute(&output);
+            annotate(&mut hops, &db);
+            hops
+        };
+
+        let direct = snapshot("1.1.1.1", trace_of(&["192.168.1.1", "100.72.0.1", "203.94.64.1", "1.1.1.1"]), &db);
+        let via_transit = RouteSnapshot {
+            taken_at: direct.taken_at + Duration::hours(2),
+            ..snapshot("1.1.1.1", trace_of(&["192.168.1.1", "100.72.0.1", "203.94.64.1", "4.69.1.1", "1.1.1.1"]), &db)
+        };
+        // The CGNAT hop is skipped; the ISP is the first public network
+        assert_eq!((direct.isp_name.as_deref(), direct.isp_asn), (Some("Hutch"), Some(9506)));
+        assert_eq!(direct.path_signature(), "9506 13335");
+
+        let unanswered = snapshot("8.8.8.8", trace_of(&["192.168.1.1"]), &db);
+        let verdict = isp_from_routes(&[direct.clone(), via_transit.clone(), unanswered], &db).unwrap();
+        assert_eq!((verdict.isp_name.as_str(), verdict.asn, verdict.region.as_str()), ("Hutch", 9506, "LK"));
+        assert!((verdict.confidence - ROUTE_CONFIDENCE * 2.0 / 3.0).abs() < 1e-9);
+
+        let changes = path_changes(&[via_transit.clone(), direct.clone()]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].previous_path.as_str(), changes[0].new_path.as_str()), ("9506 13335", "9506 3356 13335"));
+
+        let at = via_transit.taken_at;
+        let measurements: Vec<SpeedMeasurement> = [(-30, 40.0), (-10, 42.0), (10, 12.0), (30, 14.0)]
+            .iter()
+            .map(|(min, mbps)| SpeedMeasurement { timestamp: at + Duration::minutes(*min), ..SpeedMeasurement::new(*mbps, 5.0, 20, false) })
+            .collect();
+        let impact = &correlate_path_changes(changes, &measurements, Duration::hours(1))[0];
+        assert_eq!((impact.before_mbps, impact.after_mbps), (Some(42.0), Some(14.0)));
+        assert!(impact.ratio().unwrap() < 0.5);
+        assert!(isp_from_routes(&[], &db).is_none());
+    }
+}
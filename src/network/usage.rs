@@ -0,0 +1,235 @@
+use crate::core::app_state::SharedAppState;
+use crate::core::config::DataBudgetConfig;
+use crate::core::error::Result;
+use crate::data::models::{TrafficSource, TrafficUsage};
+use crate::data::repository::Repository;
+use chrono::{Datelike, Local, Months, NaiveDate};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often counted bytes are written to `traffic_usage`
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+/// First day of the billing cycle that contains `today`
+pub fn cycle_start(today: NaiveDate, billing_day: u32) -> NaiveDate {
+    let billing_day = billing_day.clamp(1, 28);
+    let this_month = today.with_day(billing_day).unwrap_or(today);
+    if today >= this_month { this_month } else { this_month - Months::new(1) }
+}
+
+/// `get_data_usage` payload
+#[derive(Debug, Clone, Serialize)]
+pub struct DataUsage {
+    pub cycle_start: NaiveDate,
+    /// First day of the next cycle
+    pub cycle_end: NaiveDate,
+    pub cap_mb: Option<f64>,
+    pub used_mb: f64,
+    pub cap_reached: bool,
+    pub by_source: HashMap<TrafficSource, f64>,
+    /// Per day and module, oldest first
+    pub daily: Vec<TrafficUsage>,
+}
+
+#[derive(Debug)]
+struct MeterState {
+    budget: DataBudgetConfig,
+    cycle_start: NaiveDate,
+    /// Stored and pending bytes of the current cycle
+    cycle_bytes: u64,
+    pending: HashMap<(NaiveDate, TrafficSource), u64>,
+}
+
+impl MeterState {
+    fn cap_reached(&self) -> bool {
+        self.budget.monthly_cap_mb.is_some_and(|cap| self.cycle_bytes as f64 >= cap * BYTES_PER_MB)
+    }
+}
+
+/// Counts bytes the traffic generators produce and stops them, through `data_cap_reached` in the
+/// shared state, once the monthly cap is used up
+#[derive(Clone)]
+pub struct DataUsageMeter {
+    repository: Arc<Repository>,
+    shared: SharedAppState,
+    state: Arc<Mutex<MeterState>>,
+}
+
+impl DataUsageMeter {
+    pub fn new(repository: Arc<Repository>, shared: SharedAppState, budget: DataBudgetConfig) -> Self {
+        let state = MeterState {
+            cycle_start: cycle_start(Local::now().date_naive(), budget.billing_day),
+            budget,
+            cycle_bytes: 0,
+            pending: HashMap::new(),
+        };
+        Self { repository, shared, state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Reads what the current cycle has used so far, e.g. at startup
+    pub async fn load(&self) -> Result<()> {
+        let start = {
+            let mut state = self.state.lock().unwrap();
+            state.cycle_start = cycle_start(Local::now().date_naive(), state.budget.billing_day);
+            state.cycle_start
+        };
+        let stored: u64 = self.repository.get_traffic_usage_since(start).await?.iter().map(|u| u.bytes).sum();
+        {
+            let mut state = self.state.lock().unwrap();
+            let pending: u64 = state.pending.iter().filter(|((day, _), _)| *day >= start).map(|(_, bytes)| bytes).sum();
+            state.cycle_bytes = stored + pending;
+        }
+        self.apply_cap().await;
+        Ok(())
+    }
+
+    /// Counts `bytes` generated by `source`; stops generation right away when this used up the cap
+    pub async fn record(&self, source: TrafficSource, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+        let today = Local::now().date_naive();
+        let rolled_over = {
+            let mut state = self.state.lock().unwrap();
+            let start = cycle_start(today, state.budget.billing_day);
+            let rolled_over = start != state.cycle_start;
+            if rolled_over {
+                state.cycle_start = start;
+                state.cycle_bytes = 0;
+            }
+            *state.pending.entry((today, source)).or_insert(0) += bytes;
+            state.cycle_bytes += bytes;
+            rolled_over
+        };
+        if rolled_over {
+            info!("New data budget cycle started");
+        }
+        self.apply_cap().await;
+    }
+
+    /// Writes counted bytes to the database
+    pub async fn flush(&self) -> Result<()> {
+        let pending: Vec<((NaiveDate, TrafficSource), u64)> = self.state.lock().unwrap().pending.drain().collect();
+        for (index, ((day, source), bytes)) in pending.iter().enumerate() {
+            if let Err(e) = self.repository.add_traffic_usage(*day, *source, *bytes).await {
+                // Keep what was not written for the next flush
+                let mut state = self.state.lock().unwrap();
+                for ((day, source), bytes) in &pending[index..] {
+                    *state.pending.entry((*day, *source)).or_insert(0) += bytes;
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a changed budget; a new billing day moves the cycle
+    pub async fn update_budget(&self, budget: DataBudgetConfig) -> Result<()> {
+        self.state.lock().unwrap().budget = budget;
+        self.load().await
+    }
+
+    pub async fn usage(&self) -> Result<DataUsage> {
+        self.flush().await?;
+        self.load().await?;
+        let (start, cap_mb, cap_reached) = {
+            let state = self.state.lock().unwrap();
+            (state.cycle_start, state.budget.monthly_cap_mb, state.cap_reached())
+        };
+        let daily = self.repository.get_traffic_usage_since(start).await?;
+        let mut by_source: HashMap<TrafficSource, f64> = TrafficSource::ALL.into_iter().map(|s| (s, 0.0)).collect();
+        for usage in &daily {
+            *by_source.entry(usage.source).or_insert(0.0) += usage.bytes as f64 / BYTES_PER_MB;
+        }
+        Ok(DataUsage {
+            cycle_start: start,
+            cycle_end: start + Months::new(1),
+            cap_mb,
+            used_mb: daily.iter().map(|u| u.bytes).sum::<u64>() as f64 / BYTES_PER_MB,
+            cap_reached,
+            by_source,
+            daily,
+        })
+    }
+
+    /// Flushes once a minute and lifts the stop when a new cycle starts
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.flush().await {
+                    warn!("Failed to save data usage: {}", e);
+                }
+                let cycle_ended = {
+                    let state = self.state.lock().unwrap();
+                    cycle_start(Local::now().date_naive(), state.budget.billing_day) != state.cycle_start
+                };
+                if cycle_ended {
+                    if let Err(e) = self.load().await {
+                        warn!("Failed to load data usage: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    async fn apply_cap(&self) {
+        let reached = self.state.lock().unwrap().cap_reached();
+        let mut shared = self.shared.write().await;
+        if shared.data_cap_reached != reached {
+            if reached {
+                warn!("Monthly data cap reached; traffic generation stopped until the next cycle");
+            } else {
+                info!("Data cap no longer reached; traffic generation may resume");
+            }
+            shared.data_cap_reached = reached;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::app_state::{AppControlState, OptimizationMode};
+    use crate::data::migrations::MigrationManager;
+    use sqlx::SqlitePool;
+    use tokio::sync::RwLock;
+
+    #[tokio::test]
+    async fn test_cap_stops_generation_and_usage_is_stored() {
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        assert_eq!(cycle_start(day(3, 20), 15), day(3, 15));
+        assert_eq!(cycle_start(day(3, 10), 15), day(2, 15));
+        assert_eq!(cycle_start(day(1, 3), 5), NaiveDate::from_ymd_opt(2023, 12, 5).unwrap());
+
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        MigrationManager::new(":memory:".to_string()).run_migrations(&pool).await.unwrap();
+        let repository = Arc::new(Repository::new(pool));
+        let shared: SharedAppState = Arc::new(RwLock::new(AppControlState { optimization_mode: OptimizationMode::Enabled, ..AppControlState::default() }));
+        let meter = DataUsageMeter::new(Arc::clone(&repository), Arc::clone(&shared), DataBudgetConfig { monthly_cap_mb: Some(2.0), billing_day: 1 });
+        meter.load().await.unwrap();
+
+        meter.record(TrafficSource::Keeper, 1024 * 1024).await;
+        assert!(shared.read().await.may_generate());
+        meter.record(TrafficSource::Stealth, 1024 * 1024).await;
+        assert!(!shared.read().await.may_generate());
+
+        let usage = meter.usage().await.unwrap();
+        assert_eq!(usage.used_mb, 2.0);
+        assert!(usage.cap_reached);
+        assert_eq!(usage.by_source[&TrafficSource::Keeper], 1.0);
+        assert_eq!(usage.by_source[&TrafficSource::Speedtest], 0.0);
+
+        // A raised cap lets generation resume; stored usage survives a restart
+        meter.update_budget(DataBudgetConfig { monthly_cap_mb: Some(10.0), billing_day: 1 }).await.unwrap();
+        assert!(shared.read().await.may_generate());
+        let restarted = DataUsageMeter::new(repository, shared, DataBudgetConfig::default());
+        assert_eq!(restarted.usage().await.unwrap().used_mb, 2.0);
+    }
+}
@@ -0,0 +1,147 @@
+//! Reads the Wi-Fi signal with each platform's tools. Whether slowdowns are down to weak Wi-Fi is
+//! decided in `core::wifi`.
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use crate::core::command::command_output;
+use serde::{Deserialize, Serialize};
+
+/// Signal of the active Wi-Fi connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WifiSignal {
+    pub interface: Option<String>,
+    pub rssi_dbm: Option<i32>,
+    pub link_rate_mbps: Option<f64>,
+}
+
+/// Strongest interface in `/proc/net/wireless`; the level column is in dBm
+pub fn parse_proc_net_wireless(content: &str) -> Option<WifiSignal> {
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(':')?;
+            let level: f64 = rest.split_whitespace().nth(2)?.trim_end_matches('.').parse().ok()?;
+            // Drivers reporting in 8-bit unsigned form give values above zero
+            let dbm = if level > 0.0 { level - 256.0 } else { level };
+            Some(WifiSignal { interface: Some(name.trim().to_string()), rssi_dbm: Some(dbm as i32), link_rate_mbps: None })
+        })
+        .max_by_key(|s| s.rssi_dbm)
+}
+
+/// `tx bitrate` from `iw dev <if> link`
+pub fn parse_iw_link(output: &str) -> Option<f64> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("tx bitrate:"))
+        .and_then(|rest| rest.split_whitespace().next()?.parse().ok())
+}
+
+/// Lines of the `Current Network Information:` block of macOS `system_profiler SPAirPortDataType`;
+/// the first is the network name followed by a colon
+fn current_network_block(output: &str) -> Vec<&str> {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut lines = output.lines().skip_while(|line| line.trim() != "Current Network Information:");
+    let Some(header) = lines.next() else { return Vec::new() };
+    let depth = indent(header);
+    lines.take_while(|line| line.trim().is_empty() || indent(line) > depth).filter(|line| !line.trim().is_empty()).collect()
+}
+
+/// Signal of the joined network from macOS `system_profiler SPAirPortDataType`
+/// (`airport -I` is gone from current macOS)
+pub fn parse_system_profiler(output: &str) -> Option<WifiSignal> {
+    let block = current_network_block(output);
+    let field = |name: &str| {
+        block.iter().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+    };
+    // `Signal / Noise: -52 dBm / -94 dBm`
+    let rssi_dbm = field("Signal / Noise").and_then(|v| v.split_whitespace().next()?.parse().ok());
+    let link_rate_mbps = field("Transmit Rate").and_then(|v| v.parse().ok());
+    if rssi_dbm.is_none() && link_rate_mbps.is_none() { return None; }
+    Some(WifiSignal { interface: None, rssi_dbm, link_rate_mbps })
+}
+
+/// Name of the joined network from macOS `system_profiler SPAirPortDataType`; macOS hides it as
+/// `<redacted>` from apps without location access
+pub fn parse_system_profiler_ssid(output: &str) -> Option<String> {
+    let name = current_network_block(output).first()?.trim().strip_suffix(':')?.to_string();
+    (!name.is_empty() && name != "<redacted>").then_some(name)
+}
+
+/// `netsh wlan show interfaces` on Windows; signal quality is converted to approximate dBm
+pub fn parse_netsh(output: &str) -> Option<WifiSignal> {
+    let field = |name: &str| {
+        output.lines().find_map(|line| {
+            let (key, value) = line.split_once(" : ")?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+    };
+    if field("State").is_some_and(|state| state != "connected") { return None; }
+    let quality: Option<f64> = field("Signal").and_then(|v| v.trim_end_matches('%').parse().ok());
+    let rssi_dbm = quality.map(|q| (q / 2.0 - 100.0).round() as i32);
+    let link_rate_mbps = field("Receive rate (Mbps)").and_then(|v| v.parse().ok());
+    if rssi_dbm.is_none() && link_rate_mbps.is_none() { return None; }
+    Some(WifiSignal { interface: field("Name"), rssi_dbm, link_rate_mbps })
+}
+
+/// Current Wi-Fi signal, or `None` on wired links and where the platform tools are missing
+#[cfg(target_os = "linux")]
+pub async fn read_signal() -> Option<WifiSignal> {
+    let table = tokio::fs::read_to_string("/proc/net/wireless").await.ok()?;
+    let mut signal = parse_proc_net_wireless(&table)?;
+    if let Some(interface) = signal.interface.clone() {
+        signal.link_rate_mbps = command_output("iw", &["dev", &interface, "link"]).await.as_deref().and_then(parse_iw_link);
+    }
+    Some(signal)
+}
+
+#[cfg(target_os = "macos")]
+pub async fn read_signal() -> Option<WifiSignal> {
+    parse_system_profiler(&command_output("system_profiler", &["SPAirPortDataType"]).await?)
+}
+
+#[cfg(target_os = "windows")]
+pub async fn read_signal() -> Option<WifiSignal> {
+    parse_netsh(&command_output("netsh", &["wlan", "show", "interfaces"]).await?)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub async fn read_signal() -> Option<WifiSignal> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform_parsers() {
+        let wireless = "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE\n \
+             face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22\n \
+             wlan0: 0000   54.  -56.  -256        0      0      0      0      0        0\n";
+        let signal = parse_proc_net_wireless(wireless).unwrap();
+        assert_eq!(signal.interface.as_deref(), Some("wlan0"));
+        assert_eq!(signal.rssi_dbm, Some(-56));
+        assert!(parse_proc_net_wireless("Inter-|\n face |\n").is_none());
+
+        let iw = "Connected to aa:bb:cc:dd:ee:ff (on wlan0)\n\tsignal: -56 dBm\n\ttx bitrate: 866.7 MBit/s VHT-MCS 9 80MHz\n";
+        assert_eq!(parse_iw_link(iw), Some(866.7));
+
+        let profiler = "Wi-Fi:\n\n      Interfaces:\n        en0:\n          Status: Connected\n          Current Network Information:\n            \
+             home:\n              PHY Mode: 802.11ac\n              Signal / Noise: -71 dBm / -94 dBm\n              Transmit Rate: 144\n          \
+             Other Local Wi-Fi Networks:\n            neighbour:\n              Signal / Noise: -40 dBm / -94 dBm\n";
+        let mac = parse_system_profiler(profiler).unwrap();
+        assert_eq!((mac.rssi_dbm, mac.link_rate_mbps), (Some(-71), Some(144.0)));
+        assert_eq!(parse_system_profiler_ssid(profiler), Some("home".to_string()));
+        assert!(parse_system_profiler("Wi-Fi:\n        en0:\n          Status: Off\n").is_none());
+
+        let netsh = "    Name                   : Wi-Fi\n    State                  : connected\n    \
+             Receive rate (Mbps)    : 300\n    Transmit rate (Mbps)   : 300\n    Signal                 : 40%\n";
+        let win = parse_netsh(netsh).unwrap();
+        assert_eq!(win.interface.as_deref(), Some("Wi-Fi"));
+        assert_eq!((win.rssi_dbm, win.link_rate_mbps), (Some(-80), Some(300.0)));
+        assert!(parse_netsh("    State                  : disconnected\n    Signal                 : 90%\n").is_none());
+    }
+}
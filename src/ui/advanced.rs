@@ -49,12 +49,13 @@ impl AdvancedInterface {
     /// Export app configuration to JSON string
     pub async fn export_config(&self) -> Result<String> {
         let cfg = AppConfig::load().await?;
-        Ok(serde_json::to_string_pretty(&cfg)? )
+        Ok(serde_json::to_string_pretty(&cfg.redacted())? )
     }
 
     /// Import app configuration from JSON string and persist
     pub async fn import_config(&self, json: &str) -> Result<()> {
-        let cfg: AppConfig = serde_json::from_str(json)?;
+        let mut cfg: AppConfig = serde_json::from_str(json)?;
+        cfg.restore_redacted(&AppConfig::load().await?);
         cfg.validate()?;
         cfg.save().await?;
         Ok(())
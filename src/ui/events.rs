@@ -0,0 +1,21 @@
+use crate::core::events::EventSink;
+use tauri::api::notification::Notification;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+/// Routes engine events to the webviews and notifications to the OS
+impl EventSink for AppHandle {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.emit_all(event, payload);
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        if let Err(e) = Notification::new(&self.config().tauri.bundle.identifier)
+            .title(title)
+            .body(body)
+            .show()
+        {
+            warn!("Failed to show notification: {}", e);
+        }
+    }
+}
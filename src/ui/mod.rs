@@ -3,6 +3,7 @@ pub mod status;
 pub mod advanced;
 pub mod panel;
 pub mod progress;
+pub mod events;
 
 // Re-export commonly used types
 pub use tray::SystemTray;
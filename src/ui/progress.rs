@@ -1,5 +1,7 @@
 use crate::core::app_state::{OptimizationMode, SharedAppState};
 use crate::core::error::Result;
+use crate::core::shutdown::CancellationToken;
+use crate::data::models::MeasurementSource;
 use crate::data::repository::Repository;
 use chrono::{Utc, Duration as ChronoDuration};
 use serde::Serialize;
@@ -32,6 +34,9 @@ pub struct LiveMetrics {
     pub up_mbps: f64,
     pub latency_ms: u32,
     pub improvement: f64,
+    /// Source of the newest measurement behind these figures
+    pub source: Option<MeasurementSource>,
+    pub measured_at: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -45,11 +50,12 @@ pub struct OptimizationProgressPayload {
     pub timestamp: String,
 }
 
-/// Starts a background task that emits `optimization_progress` events to the UI
+/// Starts a background task that emits `optimization_progress` events to the UI until `shutdown` is cancelled
 pub fn start_progress_broadcaster(
     app_handle: AppHandle,
     repository: Arc<Repository>,
     shared_state: SharedAppState,
+    shutdown: CancellationToken,
 ) {
     tauri::async_runtime::spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_secs(2));
@@ -63,7 +69,10 @@ pub fn start_progress_broadcaster(
         if rotation_seconds == 0 { rotation_seconds = default_rotation_s; }
         let mut next_rotation_s: u32 = rotation_seconds;
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
 
             // Check if optimization is enabled
             let enabled = {
@@ -80,7 +89,7 @@ pub fn start_progress_broadcaster(
                         phase: ProgressPhase::Inactive,
                         phase_percent: 0,
                         server: ServerInfo { host: "".into(), region: "".into(), ip: None, stealth_level: None },
-                        metrics: LiveMetrics { down_mbps: 0.0, up_mbps: 0.0, latency_ms: 0, improvement: 1.0 },
+                        metrics: LiveMetrics { down_mbps: 0.0, up_mbps: 0.0, latency_ms: 0, improvement: 1.0, source: None, measured_at: None },
                         next_rotation_s: 0,
                         confidence: 0.0,
                         timestamp: Utc::now().to_rfc3339(),
@@ -108,12 +117,18 @@ pub fn start_progress_broadcaster(
             let mut down_sum = 0.0;
             let mut up_sum = 0.0;
             let mut count = 0.0;
+            let mut up_count = 0.0;
             for m in &measurements {
                 down_sum += m.download_mbps;
-                up_sum += m.upload_mbps;
                 count += 1.0;
+                if m.upload_measured {
+                    up_sum += m.upload_mbps;
+                    up_count += 1.0;
+                }
             }
-            let (down_mbps, up_mbps) = if count > 0.0 { (down_sum / count, up_sum / count) } else { (0.0, 0.0) };
+            let down_mbps = if count > 0.0 { down_sum / count } else { 0.0 };
+            let up_mbps = if up_count > 0.0 { up_sum / up_count } else { 0.0 };
+            let newest = measurements.first();
 
             // Estimate improvement vs a simple baseline (avg of last 30 minutes unoptimized)
             let since_baseline = Utc::now() - ChronoDuration::minutes(30);
@@ -134,7 +149,14 @@ pub fn start_progress_broadcaster(
                 phase,
                 phase_percent: percent.min(100),
                 server: ServerInfo { host: "Auto route".into(), region: "".into(), ip: None, stealth_level: Some("High".into()) },
-                metrics: LiveMetrics { down_mbps, up_mbps, latency_ms: 0, improvement },
+                metrics: LiveMetrics {
+                    down_mbps,
+                    up_mbps,
+                    latency_ms: 0,
+                    improvement,
+                    source: newest.map(|m| m.source),
+                    measured_at: newest.map(|m| m.timestamp.to_rfc3339()),
+                },
                 next_rotation_s,
                 confidence,
                 timestamp: Utc::now().to_rfc3339(),
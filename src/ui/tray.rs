@@ -1,14 +1,17 @@
 use crate::core::error::{Result, SpeedKarmaError};
-use crate::core::intelligence::{SystemStatus, SystemState};
-use crate::core::app_state::{OptimizationMode};
+use crate::core::intelligence::{MeasurementSnapshot, SystemStatus, SystemState};
+use crate::core::app_state::{self, OptimizationMode};
+use crate::core::shutdown::{Shutdown, SHUTDOWN_GRACE};
+use crate::data::models::MeasurementSource;
 use crate::ui::advanced::AdvancedInterface;
 use crate::ui::panel::PanelInterface;
 use tauri::{
     AppHandle, CustomMenuItem, Manager, SystemTray as TauriSystemTray, 
-    SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
+    SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem, SystemTraySubmenu,
     api::notification::Notification,
 };
 use std::sync::Arc;
+use chrono::{DateTime, Utc};
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
@@ -17,14 +20,22 @@ pub struct SystemTray {
     app_handle: Option<AppHandle>,
     current_status: Arc<RwLock<SystemStatus>>,
     menu_items: SystemTrayMenuItems,
+    /// Which measurement source the speed line shows (`None` = automatic)
+    speed_source: Arc<RwLock<Option<MeasurementSource>>>,
 }
 
 /// Menu item identifiers for system tray
 #[derive(Debug)]
 struct SystemTrayMenuItems {
     status_item: String,
+    speed_item: String,
     separator1: String,
     toggle_optimization: String,
+    emergency_stop: String,
+    snooze_30_minutes: String,
+    snooze_2_hours: String,
+    snooze_until_tomorrow: String,
+    speed_source: String,
     advanced: String,
     separator2: String,
     quit: String,
@@ -34,8 +45,14 @@ impl Default for SystemTrayMenuItems {
     fn default() -> Self {
         Self {
             status_item: "status".to_string(),
+            speed_item: "speed".to_string(),
             separator1: "sep1".to_string(),
             toggle_optimization: "toggle_opt".to_string(),
+            emergency_stop: "emergency_stop".to_string(),
+            snooze_30_minutes: "snooze_30m".to_string(),
+            snooze_2_hours: "snooze_2h".to_string(),
+            snooze_until_tomorrow: "snooze_tomorrow".to_string(),
+            speed_source: "speed_source".to_string(),
             advanced: "advanced".to_string(),
             separator2: "sep2".to_string(),
             quit: "quit".to_string(),
@@ -52,8 +69,12 @@ impl SystemTray {
                 message: "Initializing...".to_string(),
                 data_collection_progress: None,
                 effectiveness: None,
+                latest_passive: None,
+                latest_active: None,
+                tasks: Vec::new(),
             })),
             menu_items: SystemTrayMenuItems::default(),
+            speed_source: Arc::new(RwLock::new(None)),
         }
     }
     
@@ -64,16 +85,28 @@ impl SystemTray {
         // Create menu items with Apple-inspired design
         let status_item = CustomMenuItem::new(&menu_items.status_item, "◉ SpeedKarma")
             .disabled(); // Status item is non-clickable
+        let speed_item = CustomMenuItem::new(&menu_items.speed_item, "No speed data yet")
+            .disabled();
         
         let toggle_optimization = CustomMenuItem::new(&menu_items.toggle_optimization, "Enable Optimization");
+        let emergency_stop = CustomMenuItem::new(&menu_items.emergency_stop, "Emergency Stop");
+        let snooze = SystemTraySubmenu::new("Snooze", SystemTrayMenu::new()
+            .add_item(CustomMenuItem::new(&menu_items.snooze_30_minutes, "For 30 Minutes"))
+            .add_item(CustomMenuItem::new(&menu_items.snooze_2_hours, "For 2 Hours"))
+            .add_item(CustomMenuItem::new(&menu_items.snooze_until_tomorrow, "Until Tomorrow")));
+        let speed_source = CustomMenuItem::new(&menu_items.speed_source, "Speed Source: Auto");
         let advanced = CustomMenuItem::new(&menu_items.advanced, "Advanced...");
         let quit = CustomMenuItem::new(&menu_items.quit, "Quit SpeedKarma");
         
         // Build menu with progressive disclosure
         let tray_menu = SystemTrayMenu::new()
             .add_item(status_item)
+            .add_item(speed_item)
             .add_native_item(SystemTrayMenuItem::Separator)
             .add_item(toggle_optimization)
+            .add_submenu(snooze)
+            .add_item(emergency_stop)
+            .add_item(speed_source)
             .add_native_item(SystemTrayMenuItem::Separator)
             .add_item(advanced)
             .add_native_item(SystemTrayMenuItem::Separator)
@@ -105,7 +138,12 @@ impl SystemTray {
         *self.current_status.write().await = status.clone();
         
         // Update tray tooltip
-        let tooltip = self.format_tooltip(&status);
+        let preferred = *self.speed_source.read().await;
+        let tooltip = format!(
+            "{}\n{}",
+            self.format_tooltip(&status),
+            Self::format_speed_line(status.displayed_measurement(preferred))
+        );
         self.update_tray_tooltip(&tooltip).await?;
         
         // Update menu items based on status
@@ -157,6 +195,15 @@ impl SystemTray {
             tray_handle.get_item(&self.menu_items.status_item)
                 .set_title(&status_text)
                 .map_err(|e| SpeedKarmaError::SystemError(format!("Failed to update status item: {}", e)))?;
+
+            // Update speed line and the source selector
+            let preferred = *self.speed_source.read().await;
+            tray_handle.get_item(&self.menu_items.speed_item)
+                .set_title(Self::format_speed_line(status.displayed_measurement(preferred)))
+                .map_err(|e| SpeedKarmaError::SystemError(format!("Failed to update speed item: {}", e)))?;
+            tray_handle.get_item(&self.menu_items.speed_source)
+                .set_title(format!("Speed Source: {}", Self::speed_source_label(preferred)))
+                .map_err(|e| SpeedKarmaError::SystemError(format!("Failed to update speed source item: {}", e)))?;
             
             // Update optimization toggle based on state
             let (toggle_text, toggle_enabled, toggle_selected) = match status.state {
@@ -222,6 +269,34 @@ impl SystemTray {
         }
     }
     
+    /// Formats the current speed figures with their source and time
+    fn format_speed_line(snapshot: Option<&MeasurementSnapshot>) -> String {
+        match snapshot {
+            Some(m) => {
+                let source = match m.source {
+                    MeasurementSource::Passive => "passive estimate",
+                    MeasurementSource::Active => "speed test",
+                };
+                format!(
+                    "↓ {:.1} ↑ {:.1} Mbps · {} at {}",
+                    m.download_mbps,
+                    m.upload_mbps,
+                    source,
+                    m.timestamp.with_timezone(&chrono::Local).format("%H:%M")
+                )
+            }
+            None => "No speed data yet".to_string(),
+        }
+    }
+
+    fn speed_source_label(preferred: Option<MeasurementSource>) -> &'static str {
+        match preferred {
+            None => "Auto",
+            Some(MeasurementSource::Passive) => "Passive",
+            Some(MeasurementSource::Active) => "Speed Test",
+        }
+    }
+    
     /// Formats the tooltip text
     fn format_tooltip(&self, status: &SystemStatus) -> String {
         match status.state {
@@ -324,6 +399,21 @@ impl SystemTray {
                 info!("Optimization toggle clicked");
                 self.handle_optimization_toggle().await?;
             }
+            id if id == self.menu_items.emergency_stop => {
+                self.handle_emergency_stop().await?;
+            }
+            id if id == self.menu_items.snooze_30_minutes => {
+                self.handle_snooze(Utc::now() + chrono::Duration::minutes(30)).await?;
+            }
+            id if id == self.menu_items.snooze_2_hours => {
+                self.handle_snooze(Utc::now() + chrono::Duration::hours(2)).await?;
+            }
+            id if id == self.menu_items.snooze_until_tomorrow => {
+                self.handle_snooze(app_state::start_of_tomorrow(Utc::now())).await?;
+            }
+            id if id == self.menu_items.speed_source => {
+                self.cycle_speed_source().await?;
+            }
             id if id == self.menu_items.advanced => {
                 info!("Advanced settings clicked");
                 self.show_advanced_interface().await?;
@@ -339,9 +429,56 @@ impl SystemTray {
         Ok(())
     }
     
+    /// Cycles the displayed speed source: Auto → Passive → Speed Test → Auto
+    async fn cycle_speed_source(&self) -> Result<()> {
+        {
+            let mut preferred = self.speed_source.write().await;
+            *preferred = match *preferred {
+                None => Some(MeasurementSource::Passive),
+                Some(MeasurementSource::Passive) => Some(MeasurementSource::Active),
+                Some(MeasurementSource::Active) => None,
+            };
+            info!("Speed source display set to {}", Self::speed_source_label(*preferred));
+        }
+        let status = self.current_status.read().await.clone();
+        self.update_status(status).await
+    }
+    
+    /// Halts all generated traffic until the user re-enables it from Advanced settings
+    async fn handle_emergency_stop(&self) -> Result<()> {
+        let Some(app_handle) = &self.app_handle else { return Ok(()) };
+        let (Some(shared), Some(limiter)) = (
+            app_handle.try_state::<crate::core::app_state::SharedAppState>(),
+            app_handle.try_state::<crate::network::limiter::OutboundLimiter>(),
+        ) else {
+            return Ok(());
+        };
+        crate::core::emergency::engage(&shared, &limiter).await?;
+        self.show_notification("SpeedKarma stopped", "All generated traffic is halted. Re-enable it from Advanced settings.").await
+    }
+
+    /// Holds traffic generators off until `until`; optimization stays on and resumes by itself
+    async fn handle_snooze(&self, until: DateTime<Utc>) -> Result<()> {
+        let Some(shared) = self.app_handle.as_ref().and_then(|a| a.try_state::<app_state::SharedAppState>()) else {
+            return Ok(());
+        };
+        shared.write().await.paused_until = Some(until);
+        info!("Optimization snoozed until {}", until);
+        let resume_at = until.with_timezone(&chrono::Local).format("%H:%M");
+        self.show_notification("SpeedKarma snoozed", &format!("Optimization resumes at {}", resume_at)).await
+    }
+
     /// Handles optimization toggle
     async fn handle_optimization_toggle(&self) -> Result<()> {
         let current_status = self.current_status.read().await.clone();
+        if let Some(shared) = self.app_handle.as_ref().and_then(|a| a.try_state::<crate::core::app_state::SharedAppState>()) {
+            if shared.read().await.stopped_by_user {
+                return self.show_notification("SpeedKarma", "Emergency stop is on. Re-enable traffic in Advanced settings first.").await;
+            }
+            if shared.read().await.safe_mode {
+                return self.show_notification("SpeedKarma", "Safe mode is on after repeated crashes. Leave it in Advanced settings first.").await;
+            }
+        }
         
         match current_status.state {
             SystemState::Optimizing => {
@@ -350,7 +487,7 @@ impl SystemTray {
                 if let Some(app_handle) = &self.app_handle {
                     let state = app_handle.state::<crate::core::app_state::SharedAppState>();
                     let mut guard = state.write().await;
-                    guard.optimization_mode = OptimizationMode::Disabled;
+                    guard.set_optimization_mode(OptimizationMode::Disabled);
                 }
             }
             SystemState::Monitoring | SystemState::Inactive => {
@@ -359,7 +496,7 @@ impl SystemTray {
                 if let Some(app_handle) = &self.app_handle {
                     let state = app_handle.state::<crate::core::app_state::SharedAppState>();
                     let mut guard = state.write().await;
-                    guard.optimization_mode = OptimizationMode::Enabled;
+                    guard.set_optimization_mode(OptimizationMode::Enabled);
                 }
             }
             SystemState::Learning => {
@@ -396,6 +533,9 @@ impl SystemTray {
         info!("Application quit requested");
         
         if let Some(app_handle) = &self.app_handle {
+            if let Some(shutdown) = app_handle.try_state::<Shutdown>() {
+                shutdown.run(SHUTDOWN_GRACE).await;
+            }
             app_handle.exit(0);
         }
         
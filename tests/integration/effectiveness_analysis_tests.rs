@@ -17,7 +17,7 @@ async fn setup_comprehensive_test_environment() -> (Arc<Repository>, DefaultInte
     migration_manager.run_migrations(&pool).await.unwrap();
     
     let repository = Arc::new(Repository::new(pool));
-    let intelligence = DefaultIntelligenceCore::new(Arc::clone(&repository));
+    let intelligence = DefaultIntelligenceCore::new(repository.clone());
     let monitor = BackgroundMonitor::new(Arc::clone(&repository));
     
     // Create realistic ISP profile
@@ -80,9 +80,19 @@ async fn setup_comprehensive_test_environment() -> (Arc<Repository>, DefaultInte
                 timestamp,
                 download_mbps: actual_speed.max(5.0), // Minimum 5 Mbps
                 upload_mbps: actual_speed * 0.15,
-                latency_ms: if is_throttled { 80 + (hour as u32 * 2) } else { 30 + (hour as u32) },
+                latency_ms: Some(if is_throttled { 80 + (hour as u32 * 2) } else { 30 + (hour as u32) }),
                 optimization_active: false,
                 confidence: 0.8 + (day as f64 % 10.0) * 0.02,
+                source: MeasurementSource::Passive,
+                wifi_rssi_dbm: None,
+                wifi_link_mbps: None,
+                packet_loss_pct: None,
+                jitter_ms: None,
+                via_vpn: false,
+                via_proxy: false,
+                network_context: None,
+                method: MeasurementMethod::Interface,
+                upload_measured: true,
             };
             repository.save_speed_measurement(&baseline_measurement).await.unwrap();
             
@@ -94,9 +104,19 @@ async fn setup_comprehensive_test_environment() -> (Arc<Repository>, DefaultInte
                     timestamp: timestamp + Duration::minutes(30), // Slightly offset
                     download_mbps: optimized_speed.min(150.0), // Cap at 150 Mbps
                     upload_mbps: optimized_speed * 0.2,
-                    latency_ms: 35 + (hour as u32),
+                    latency_ms: Some(35 + (hour as u32)),
                     optimization_active: true,
                     confidence: 0.9,
+                    source: MeasurementSource::Passive,
+                    wifi_rssi_dbm: None,
+                    wifi_link_mbps: None,
+                    packet_loss_pct: None,
+                    jitter_ms: None,
+                    via_vpn: false,
+                    via_proxy: false,
+                    network_context: None,
+                    method: MeasurementMethod::Interface,
+                    upload_measured: true,
                 };
                 repository.save_speed_measurement(&optimized_measurement).await.unwrap();
             }
@@ -114,6 +134,8 @@ async fn setup_comprehensive_test_environment() -> (Arc<Repository>, DefaultInte
             connection_count: 2,
             traffic_intensity: 0.3,
             stealth_level: StealthLevel::High,
+            mimicry_profile: MimicryProfile::Speedtest,
+            transport: TrafficTransport::Tcp,
             effectiveness_score: Some(0.7),
             created_at: Utc::now(),
         },
@@ -126,6 +148,8 @@ async fn setup_comprehensive_test_environment() -> (Arc<Repository>, DefaultInte
             connection_count: 5,
             traffic_intensity: 0.8,
             stealth_level: StealthLevel::Medium,
+            mimicry_profile: MimicryProfile::Speedtest,
+            transport: TrafficTransport::Tcp,
             effectiveness_score: Some(0.9),
             created_at: Utc::now(),
         },
@@ -138,6 +162,8 @@ async fn setup_comprehensive_test_environment() -> (Arc<Repository>, DefaultInte
             connection_count: 3,
             traffic_intensity: 0.5,
             stealth_level: StealthLevel::Medium,
+            mimicry_profile: MimicryProfile::Speedtest,
+            transport: TrafficTransport::Tcp,
             effectiveness_score: Some(0.85),
             created_at: Utc::now(),
         },
@@ -189,6 +215,8 @@ async fn test_strategy_effectiveness_comparison() {
         connection_count: 2,
         traffic_intensity: 0.3,
         stealth_level: StealthLevel::High,
+        mimicry_profile: MimicryProfile::Speedtest,
+        transport: TrafficTransport::Tcp,
         effectiveness_score: None,
         created_at: Utc::now(),
     };
@@ -202,6 +230,8 @@ async fn test_strategy_effectiveness_comparison() {
         connection_count: 5,
         traffic_intensity: 0.8,
         stealth_level: StealthLevel::Medium,
+        mimicry_profile: MimicryProfile::Speedtest,
+        transport: TrafficTransport::Tcp,
         effectiveness_score: None,
         created_at: Utc::now(),
     };
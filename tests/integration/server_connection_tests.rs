@@ -267,4 +267,36 @@ async fn test_complete_connection_workflow() {
             println!("This is acceptable in test environments with limited network access");
         }
     }
-}
\ No newline at end of file
+}
+/// Test server list cache roundtrip and freshness
+#[tokio::test]
+async fn test_server_list_cache_roundtrip() {
+    use isp_speedkarma::network::servers::ServerListCache;
+
+    let path = std::env::temp_dir().join(format!("speedkarma-servers-{}.json", uuid::Uuid::new_v4()));
+    let cache = ServerListCache {
+        fetched_at: chrono::Utc::now(),
+        etag: Some("\"abc123\"".to_string()),
+        last_modified: None,
+        servers: vec![SpeedtestServer::new(
+            "1".to_string(),
+            "speedtest.example.com".to_string(),
+            8080,
+            "Example".to_string(),
+            "Sri Lanka".to_string(),
+            "Example ISP".to_string(),
+        )],
+    };
+    cache.save(&path).await.expect("Failed to save cache");
+
+    let loaded = ServerListCache::load(&path).await.expect("Cache should load");
+    assert_eq!(loaded.etag.as_deref(), Some("\"abc123\""));
+    assert_eq!(loaded.servers.len(), 1);
+    assert!(loaded.is_fresh(Duration::from_secs(60)));
+
+    let mut stale = loaded.clone();
+    stale.fetched_at = chrono::Utc::now() - chrono::Duration::hours(2);
+    assert!(!stale.is_fresh(Duration::from_secs(3600)));
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -55,4 +55,23 @@ async fn test_isp_profile_operations() {
     let current_profile = repo.get_current_isp_profile().await.unwrap();
     assert!(current_profile.is_some());
     assert_eq!(current_profile.unwrap().name, "Hutch");
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_speedtest_retries_survive_a_restart() {
+    let pool = setup_test_db().await;
+    let repo = Repository::new(pool);
+
+    let due = Utc::now() + chrono::Duration::days(2);
+    let retries = vec![
+        PendingRetry { due: due + chrono::Duration::days(1), attempt: 2, reason: "a call was in progress".to_string() },
+        PendingRetry { due, attempt: 1, reason: "no data transferred".to_string() },
+    ];
+    repo.replace_speedtest_retries(&retries).await.unwrap();
+    let stored = repo.get_speedtest_retries().await.unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!((stored[0].attempt, stored[0].due), (1, due));
+
+    // Running a retry removes it from the stored queue
+    repo.replace_speedtest_retries(&stored[1..]).await.unwrap();
+    assert_eq!(repo.get_speedtest_retries().await.unwrap(), vec![retries[0].clone()]);
+}
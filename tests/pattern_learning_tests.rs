@@ -15,7 +15,7 @@ async fn setup_test_db_with_data() -> (Arc<Repository>, DefaultIntelligenceCore)
     migration_manager.run_migrations(&pool).await.unwrap();
     
     let repository = Arc::new(Repository::new(pool));
-    let intelligence = DefaultIntelligenceCore::new(Arc::clone(&repository));
+    let intelligence = DefaultIntelligenceCore::new(repository.clone());
     
     // Add sample speed measurements for testing
     let base_time = Utc::now() - Duration::days(14);
@@ -34,9 +34,19 @@ async fn setup_test_db_with_data() -> (Arc<Repository>, DefaultIntelligenceCore)
                 timestamp,
                 download_mbps: base_speed + (hour as f64 * 0.5), // Add some variation
                 upload_mbps: base_speed * 0.2,
-                latency_ms: 30 + (hour as u32 * 2),
+                latency_ms: Some(30 + (hour as u32 * 2)),
                 optimization_active: false,
                 confidence: 0.8,
+                source: MeasurementSource::Passive,
+                wifi_rssi_dbm: None,
+                wifi_link_mbps: None,
+                packet_loss_pct: None,
+                jitter_ms: None,
+                via_vpn: false,
+                via_proxy: false,
+                network_context: None,
+                method: MeasurementMethod::Interface,
+                upload_measured: true,
             };
             
             repository.save_speed_measurement(&measurement).await.unwrap();
@@ -53,9 +63,19 @@ async fn setup_test_db_with_data() -> (Arc<Repository>, DefaultIntelligenceCore)
                 timestamp,
                 download_mbps: 75.0 + (hour as f64 * 0.5), // Better speed with optimization
                 upload_mbps: 15.0,
-                latency_ms: 25,
+                latency_ms: Some(25),
                 optimization_active: true,
                 confidence: 0.9,
+                source: MeasurementSource::Passive,
+                wifi_rssi_dbm: None,
+                wifi_link_mbps: None,
+                packet_loss_pct: None,
+                jitter_ms: None,
+                via_vpn: false,
+                via_proxy: false,
+                network_context: None,
+                method: MeasurementMethod::Interface,
+                upload_measured: true,
             };
             
             repository.save_speed_measurement(&measurement).await.unwrap();
@@ -344,4 +364,213 @@ async fn test_recommendation_prioritization() {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_intelligence_with_in_memory_store() {
+    use isp_speedkarma::data::{InMemoryStore, MeasurementStore};
+
+    let store = Arc::new(InMemoryStore::new());
+    for hour in 0..24 {
+        let mut measurement = SpeedMeasurement::new(40.0, 8.0, 25, false);
+        measurement.timestamp = Utc::now() - Duration::hours(hour);
+        store.save_speed_measurement(&measurement).await.unwrap();
+    }
+
+    let intelligence = DefaultIntelligenceCore::new(store.clone());
+    let status = intelligence.get_status().await.unwrap();
+    assert!(matches!(status.state, SystemState::Learning), "One day of data should still be learning");
+}
+
+#[tokio::test]
+async fn test_status_reports_measurement_sources() {
+    use isp_speedkarma::data::{InMemoryStore, MeasurementStore};
+
+    let store = Arc::new(InMemoryStore::new());
+    let mut passive = SpeedMeasurement::new(30.0, 6.0, 0, false);
+    passive.timestamp = Utc::now() - Duration::minutes(5);
+    store.save_speed_measurement(&passive).await.unwrap();
+    let mut active = SpeedMeasurement::new(85.0, 20.0, 15, true).with_source(MeasurementSource::Active);
+    active.timestamp = Utc::now() - Duration::minutes(20);
+    store.save_speed_measurement(&active).await.unwrap();
+
+    let intelligence = DefaultIntelligenceCore::new(store.clone());
+    let status = intelligence.get_status().await.unwrap();
+    assert_eq!(status.latest_passive.as_ref().unwrap().download_mbps, 30.0);
+    assert_eq!(status.latest_active.as_ref().unwrap().download_mbps, 85.0);
+
+    // Auto prefers a recent speed test; explicit choices are honoured
+    assert_eq!(status.displayed_measurement(None).unwrap().source, MeasurementSource::Active);
+    assert_eq!(status.displayed_measurement(Some(MeasurementSource::Passive)).unwrap().source, MeasurementSource::Passive);
+
+    // A stale speed test falls back to the passive estimate
+    let mut stale = status.clone();
+    stale.latest_active.as_mut().unwrap().timestamp = Utc::now() - Duration::hours(3);
+    assert_eq!(stale.displayed_measurement(None).unwrap().source, MeasurementSource::Passive);
+}
+
+#[tokio::test]
+async fn test_satisfaction_feedback_shapes_strategy_reward() {
+    use isp_speedkarma::data::{FeedbackStore, InMemoryStore, MeasurementStore};
+
+    let store = Arc::new(InMemoryStore::new());
+    for i in 0..10 {
+        let mut baseline = SpeedMeasurement::new(40.0, 8.0, 25, false);
+        baseline.timestamp = Utc::now() - Duration::hours(i * 2 + 1);
+        store.save_speed_measurement(&baseline).await.unwrap();
+        let mut optimized = SpeedMeasurement::new(42.0, 8.0, 25, true);
+        optimized.timestamp = Utc::now() - Duration::hours(i * 2);
+        store.save_speed_measurement(&optimized).await.unwrap();
+    }
+    let intelligence = DefaultIntelligenceCore::new(store.clone());
+    let strategy = OptimizationStrategy::default_strategy();
+
+    let before = intelligence.calculate_strategy_effectiveness(&strategy).await.unwrap();
+    assert!(before.satisfaction.is_none());
+
+    for _ in 0..8 {
+        store.save_satisfaction_feedback(&SatisfactionFeedback::new(true, true, Some(strategy.name.clone()))).await.unwrap();
+    }
+    // Answers given while idle or under another strategy do not count
+    store.save_satisfaction_feedback(&SatisfactionFeedback::new(false, false, None)).await.unwrap();
+    store.save_satisfaction_feedback(&SatisfactionFeedback::new(false, true, Some("High Stealth".to_string()))).await.unwrap();
+
+    let after = intelligence.calculate_strategy_effectiveness(&strategy).await.unwrap();
+    assert_eq!(after.satisfaction, Some(0.9));
+    assert!(after.success_rate > before.success_rate);
+    assert!(after.success_rate <= 0.5 * before.success_rate + 0.5 * 0.9 + 1e-9);
+
+    let (rate, approval) = fold_satisfaction(0.8, &[]);
+    assert_eq!((rate, approval), (0.8, None));
+}
+
+#[tokio::test]
+async fn test_strategy_from_learned_data() {
+    let (repository, mut intelligence) = setup_test_db_with_data().await;
+    let isp_profile = ISPProfile::new("Hutch".to_string(), "Sri Lanka".to_string(), "Test Detection".to_string());
+    repository.save_isp_profile(&isp_profile).await.unwrap();
+    intelligence.train_model().await.unwrap();
+
+    let budget = isp_speedkarma::core::config::ThroughputKeeperConfig::default();
+    let proposal = intelligence.synthesize_strategy(&budget).await.unwrap();
+    assert!(!proposal.best_hours.is_empty() && proposal.best_hours.len() <= 4);
+    let throttled: Vec<u8> = intelligence.predict_optimal_times().into_iter().map(|(h, _)| h).collect();
+    assert!(proposal.best_hours.iter().all(|h| throttled.contains(h)));
+    assert!(proposal.strategy.name.starts_with("My data ("));
+    assert!(proposal.strategy.effectiveness_score.is_none());
+    assert!(proposal.strategy.validate().is_ok());
+
+    // A tighter budget never raises intensity
+    let tight = isp_speedkarma::core::config::ThroughputKeeperConfig { hourly_budget_mb: 5.0, ..budget.clone() };
+    let tight_proposal = intelligence.synthesize_strategy(&tight).await.unwrap();
+    assert!(tight_proposal.strategy.traffic_intensity < proposal.strategy.traffic_intensity);
+
+    // Saving without activation leaves the current best in place
+    let mut existing = OptimizationStrategy::default_strategy();
+    existing.effectiveness_score = Some(0.7);
+    repository.save_optimization_strategy(&existing).await.unwrap();
+    let saved = intelligence.save_strategy_proposal(proposal.clone(), false).await.unwrap();
+    assert!(saved.strategy.id.is_some() && !saved.activated);
+    assert_eq!(repository.get_best_optimization_strategy().await.unwrap().unwrap().name, "Default");
+
+    let activated = intelligence.save_strategy_proposal(proposal, true).await.unwrap();
+    assert!(activated.activated);
+    let best = repository.get_best_optimization_strategy().await.unwrap().unwrap();
+    assert_eq!(best.id, activated.strategy.id);
+    // Activation does not invent a score for an unmeasured strategy
+    assert_eq!(best.effectiveness_score, None);
+}
+
+#[test]
+fn test_safe_traffic_intensity_respects_budget() {
+    let mut budget = isp_speedkarma::core::config::ThroughputKeeperConfig::default();
+    assert!((safe_traffic_intensity(0.5, 0.0, &budget, 4) - 0.5).abs() < 1e-9);
+    assert!((safe_traffic_intensity(0.5, 1.0, &budget, 4) - 0.25).abs() < 1e-9);
+
+    // 40 MB a day over 4 hours leaves 10 MB an hour, a third of the full-intensity budget
+    budget.daily_budget_mb = Some(40.0);
+    assert!((safe_traffic_intensity(0.6, 0.0, &budget, 4) - 0.2).abs() < 1e-9);
+}
+
+#[test]
+fn test_transport_effectiveness_explores_quic_then_prefers_the_better_one() {
+    let trial = |transport: Option<TrafficTransport>, during: f64| OptimizationTrial {
+        id: None,
+        started_at: Utc::now() - Duration::hours(2),
+        ended_at: Utc::now() - Duration::hours(1),
+        strategy_name: Some("Default".to_string()),
+        before_mbps: Some(10.0),
+        during_mbps: Some(during),
+        after_mbps: Some(10.0),
+        status: TrialStatus::Completed,
+        note: None,
+        transport,
+    };
+    // Trials from before transports were recorded count as TCP
+    let mut trials = vec![trial(None, 11.0), trial(Some(TrafficTransport::Tcp), 11.0), trial(Some(TrafficTransport::Tcp), 11.0)];
+    trials.extend((0..2).map(|_| trial(Some(TrafficTransport::Quic), 14.0)));
+
+    let effectiveness = transport_effectiveness(&trials);
+    assert_eq!(effectiveness.len(), 2);
+    assert_eq!(effectiveness[0].transport, TrafficTransport::Tcp);
+    assert_eq!(effectiveness[0].trial_count, 3);
+    assert!((effectiveness[1].avg_improvement - 1.4).abs() < 1e-9);
+    // QUIC is still short of trials, so new strategies explore it
+    assert_eq!(preferred_transport(&effectiveness), TrafficTransport::Quic);
+    assert_eq!(preferred_transport(&[]), TrafficTransport::Tcp);
+
+    trials.push(trial(Some(TrafficTransport::Quic), 13.0));
+    assert_eq!(preferred_transport(&transport_effectiveness(&trials)), TrafficTransport::Quic);
+
+    // Once both are tried, the better one wins even when it is TCP
+    trials.extend((0..3).map(|_| trial(Some(TrafficTransport::Quic), 8.0)));
+    assert_eq!(preferred_transport(&transport_effectiveness(&trials)), TrafficTransport::Tcp);
+}
+
+#[tokio::test]
+async fn test_vpn_measurements_stay_out_of_the_isp_model() {
+    let (repository, _) = setup_test_db_with_data().await;
+    let tunnelled = SpeedMeasurement { via_vpn: true, ..SpeedMeasurement::new(3.0, 1.0, 120, false) };
+    repository.save_speed_measurement(&tunnelled).await.unwrap();
+    let proxied = SpeedMeasurement { via_proxy: true, ..SpeedMeasurement::new(4.0, 1.0, 90, false) };
+    repository.save_speed_measurement(&proxied).await.unwrap();
+
+    let stored = repository.get_speed_measurements_since(Utc::now() - Duration::days(60)).await.unwrap();
+    assert_eq!(stored.iter().filter(|m| m.via_vpn).count(), 1);
+    assert_eq!(stored.iter().filter(|m| m.via_proxy).count(), 1);
+    let direct = isp_attributable(&stored);
+    assert_eq!(direct.len(), stored.len() - 2);
+    assert!(direct.iter().all(|m| !m.tunnelled()));
+    assert_eq!(without_vpn(&stored).len(), direct.len());
+}
+
+#[tokio::test]
+async fn test_training_follows_the_current_network_context() {
+    let (repository, _) = setup_test_db_with_data().await;
+    let tagged = |context: &str, mbps: f64, minutes_ago: i64| SpeedMeasurement {
+        timestamp: Utc::now() - Duration::minutes(minutes_ago),
+        network_context: Some(context.to_string()),
+        ..SpeedMeasurement::new(mbps, 5.0, 20, false)
+    };
+    for minutes_ago in 10..13 {
+        repository.save_speed_measurement(&tagged("home", 90.0, minutes_ago)).await.unwrap();
+    }
+    let legacy = repository.get_speed_measurements_since(Utc::now() - Duration::days(60)).await.unwrap().len();
+    assert_eq!(in_current_context(&repository.get_speed_measurements_since(Utc::now() - Duration::days(60)).await.unwrap()).len(), legacy,
+        "nothing tagged yet, so all history counts");
+
+    // History from before tracking belongs to the first network detected
+    assert_eq!(repository.backfill_network_context("home").await.unwrap(), legacy as u64);
+    for minutes_ago in 10..13 {
+        repository.save_speed_measurement(&tagged("home", 90.0, minutes_ago)).await.unwrap();
+    }
+    assert_eq!(repository.backfill_network_context("office").await.unwrap(), 0, "backfill runs once");
+    repository.save_speed_measurement(&tagged("office", 300.0, 1)).await.unwrap();
+    // A sample whose network could not be detected belongs to no context
+    repository.save_speed_measurement(&SpeedMeasurement::new(50.0, 5.0, 20, false)).await.unwrap();
+
+    let stored = repository.get_speed_measurements_since(Utc::now() - Duration::days(60)).await.unwrap();
+    let current = in_current_context(&stored);
+    // The laptop moved to the office: home samples and untagged ones leave the model
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0].network_context.as_deref(), Some("office"));
+}
@@ -0,0 +1,165 @@
+use crate::sign::Signing;
+use crate::{crate_version, run_command, Result};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const CHECKSUMS: &str = "SHA256SUMS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    MacOs,
+    Windows,
+    Linux,
+}
+
+impl Platform {
+    /// Tauri cannot cross-bundle, so the installer is always the host's
+    pub fn host() -> Result<Self> {
+        match std::env::consts::OS {
+            "macos" => Ok(Platform::MacOs),
+            "windows" => Ok(Platform::Windows),
+            "linux" => Ok(Platform::Linux),
+            other => Err(format!("no installer format for {}", other)),
+        }
+    }
+
+    /// `--bundles` value for `cargo tauri build`, also the bundle output directory
+    pub fn bundle(&self) -> &'static str {
+        match self {
+            Platform::MacOs => "dmg",
+            Platform::Windows => "msi",
+            Platform::Linux => "appimage",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Platform::MacOs => "dmg",
+            Platform::Windows => "msi",
+            Platform::Linux => "AppImage",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Platform::MacOs => "macos",
+            Platform::Windows => "windows",
+            Platform::Linux => "linux",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Skip signing and notarization, for local test builds
+    pub unsigned: bool,
+    pub out_dir: Option<PathBuf>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut options = Options::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--unsigned" => options.unsigned = true,
+                "--out" => options.out_dir = Some(args.next().ok_or("--out needs a directory")?.into()),
+                other => return Err(format!("unknown dist option {}", other)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// `ISP-SpeedKarma_0.1.0_linux_x86_64.AppImage`
+pub fn artifact_name(product: &str, version: &str, platform: Platform, arch: &str) -> String {
+    format!("{}_{}_{}_{}.{}", product, version, platform.label(), arch, platform.extension())
+}
+
+pub fn run(root: &Path, options: &Options) -> Result<()> {
+    let platform = Platform::host()?;
+    let version = crate_version(root)?;
+    let conf: Value = serde_json::from_str(&fs::read_to_string(root.join("tauri.conf.json")).map_err(|e| format!("reading tauri.conf.json: {}", e))?)
+        .map_err(|e| format!("tauri.conf.json: {}", e))?;
+    check_metadata(&conf, root, &version)?;
+    let product = conf["package"]["productName"].as_str().unwrap_or("ISP-SpeedKarma");
+    // Checked before the long build so a missing secret fails in seconds
+    let signing = if options.unsigned { None } else { Some(Signing::from_env(platform)?) };
+
+    println!("Building {} {} ({})", product, version, platform.bundle());
+    let mut build = Command::new("cargo");
+    build.current_dir(root).args(["tauri", "build", "--bundles", platform.bundle()]);
+    if let Some(config) = signing.as_ref().and_then(Signing::tauri_config) {
+        build.args(["--config", &config]);
+    }
+    run_command(&mut build)?;
+
+    let bundle_dir = root.join("target").join("release").join("bundle");
+    let built = newest_with_extension(&bundle_dir.join(platform.bundle()), platform.extension())?;
+    let out_dir = options.out_dir.clone().unwrap_or_else(|| root.join("target").join("dist"));
+    fs::create_dir_all(&out_dir).map_err(|e| format!("creating {}: {}", out_dir.display(), e))?;
+    let artifact = out_dir.join(artifact_name(product, &version, platform, std::env::consts::ARCH));
+    fs::copy(&built, &artifact).map_err(|e| format!("copying {}: {}", built.display(), e))?;
+
+    let mut artifacts = vec![artifact.clone()];
+    match &signing {
+        Some(signing) => artifacts.extend(signing.finish(&bundle_dir, product, &artifact)?),
+        None => println!("Unsigned build: not for distribution"),
+    }
+    write_checksums(&out_dir, &artifacts)?;
+    println!("Wrote {}", artifact.display());
+    Ok(())
+}
+
+/// Bundle settings the installers depend on. The version must come from the crate: a stale
+/// `package.version` would stamp the wrong version on the installer.
+fn check_metadata(conf: &Value, root: &Path, version: &str) -> Result<()> {
+    if let Some(pinned) = conf["package"]["version"].as_str() {
+        if pinned != version {
+            return Err(format!("tauri.conf.json pins version {} but the crate is {}; remove package.version", pinned, version));
+        }
+    }
+    // Launch agents, autostart entries and the Windows installer upgrade code are keyed on it
+    if conf["tauri"]["bundle"]["identifier"].as_str().is_none_or(str::is_empty) {
+        return Err("tauri.conf.json needs tauri.bundle.identifier".into());
+    }
+    let tray_icon = conf["tauri"]["systemTray"]["iconPath"].as_str().ok_or("tauri.conf.json has no tray icon")?;
+    let icons = conf["tauri"]["bundle"]["icon"].as_array().into_iter().flatten().filter_map(Value::as_str);
+    for icon in icons.chain([tray_icon]) {
+        if !root.join(icon).is_file() {
+            return Err(format!("icon {} is missing", icon));
+        }
+    }
+    Ok(())
+}
+
+fn newest_with_extension(dir: &Path, extension: &str) -> Result<PathBuf> {
+    fs::read_dir(dir)
+        .map_err(|e| format!("reading {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .max_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .ok_or_else(|| format!("no .{} in {}", extension, dir.display()))
+}
+
+/// `sha256sum`-compatible list of the artifacts this build wrote to `dir`; anything else already
+/// in `--out` is left out
+fn write_checksums(dir: &Path, artifacts: &[PathBuf]) -> Result<()> {
+    let mut names: Vec<String> = artifacts.iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    let mut sums = String::new();
+    for name in names {
+        let bytes = fs::read(dir.join(&name)).map_err(|e| format!("reading {}: {}", name, e))?;
+        let digest = Sha256::digest(&bytes);
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        sums.push_str(&format!("{}  {}\n", hex, name));
+    }
+    fs::write(dir.join(CHECKSUMS), sums).map_err(|e| format!("writing {}: {}", CHECKSUMS, e))
+}
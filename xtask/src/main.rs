@@ -0,0 +1,91 @@
+//! Release tooling. `cargo xtask dist` builds the installer for the host OS (DMG on macOS, MSI on
+//! Windows, AppImage on Linux), signs it, and writes it with a SHA256SUMS file to `target/dist`.
+
+mod dist;
+mod sign;
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+type Result<T> = std::result::Result<T, String>;
+
+const USAGE: &str = "usage: cargo xtask <command>
+
+commands:
+  dist [--unsigned] [--out <dir>]   build, sign and checksum the installer for this OS
+  version                           print the version artifacts are stamped with
+
+signing (skipped with --unsigned):
+  macOS    APPLE_SIGNING_IDENTITY, APPLE_ID, APPLE_PASSWORD, APPLE_TEAM_ID (notarized by the bundler)
+  Windows  WINDOWS_CERTIFICATE_THUMBPRINT (certificate in the user store, signtool on PATH)
+  Linux    SIGNING_GPG_KEY (detached .asc signature)";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let root = project_root();
+    let result = match args.first().map(String::as_str) {
+        Some("dist") => dist::Options::parse(&args[1..]).and_then(|options| dist::run(&root, &options)),
+        Some("version") => crate_version(&root).map(|version| println!("{}", version)),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The app's crate: the directory above this one
+fn project_root() -> PathBuf {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    manifest_dir.parent().unwrap_or(manifest_dir).to_path_buf()
+}
+
+/// Version of the app crate, the single source for bundle and artifact versions
+fn crate_version(root: &Path) -> Result<String> {
+    let manifest = std::fs::read_to_string(root.join("Cargo.toml")).map_err(|e| format!("reading Cargo.toml: {}", e))?;
+    parse_package_version(&manifest).ok_or_else(|| "Cargo.toml has no [package] version".to_string())
+}
+
+/// `version` from the `[package]` table, ignoring other tables' keys
+fn parse_package_version(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(value) = line.strip_prefix("version").and_then(|rest| rest.trim_start().strip_prefix('=')) {
+                return Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Runs `cmd`, failing with the program name when it cannot start or exits unsuccessfully
+fn run_command(cmd: &mut Command) -> Result<()> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let status = cmd.status().map_err(|e| format!("could not run {}: {}", program, e))?;
+    if status.success() { Ok(()) } else { Err(format!("{} failed ({})", program, status)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_comes_from_the_package_table() {
+        let manifest = "[workspace]\nmembers = [\".\", \"xtask\"]\n\n[package]\nname = \"isp-speedkarma\"\nversion = \"0.4.2\"\n\n[dependencies]\ntokio = { version = \"1.0\" }\n";
+        assert_eq!(parse_package_version(manifest).as_deref(), Some("0.4.2"));
+        assert_eq!(parse_package_version("[dependencies]\nversion = \"9\"\n"), None);
+        assert_eq!(crate_version(&project_root()).unwrap(), parse_package_version(&std::fs::read_to_string(project_root().join("Cargo.toml")).unwrap()).unwrap());
+
+        assert_eq!(dist::artifact_name("ISP-SpeedKarma", "0.4.2", dist::Platform::Linux, "x86_64"), "ISP-SpeedKarma_0.4.2_linux_x86_64.AppImage");
+        assert_eq!(dist::artifact_name("ISP-SpeedKarma", "0.4.2", dist::Platform::MacOs, "aarch64"), "ISP-SpeedKarma_0.4.2_macos_aarch64.dmg");
+    }
+}
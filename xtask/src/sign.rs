@@ -0,0 +1,75 @@
+use crate::dist::Platform;
+use crate::{run_command, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Signing credentials for the host's installer, read from the environment so CI secrets never
+/// touch the repository
+#[derive(Debug)]
+pub enum Signing {
+    /// Developer ID signing. The Tauri bundler signs and notarizes the app from the `APPLE_*`
+    /// variables; the DMG around it is signed, notarized and stapled here
+    MacOs { identity: String, apple_id: String, password: String, team_id: String },
+    /// Authenticode through signtool with a certificate from the user's store
+    Windows { thumbprint: String },
+    /// Detached armored GPG signature next to the AppImage
+    Linux { key: String },
+}
+
+impl Signing {
+    pub fn from_env(platform: Platform) -> Result<Self> {
+        match platform {
+            Platform::MacOs => {
+                let mut values = require(&["APPLE_SIGNING_IDENTITY", "APPLE_ID", "APPLE_PASSWORD", "APPLE_TEAM_ID"])?.into_iter();
+                let mut next = || values.next().unwrap_or_default();
+                Ok(Signing::MacOs { identity: next(), apple_id: next(), password: next(), team_id: next() })
+            }
+            Platform::Windows => Ok(Signing::Windows { thumbprint: require(&["WINDOWS_CERTIFICATE_THUMBPRINT"])?.remove(0) }),
+            Platform::Linux => Ok(Signing::Linux { key: require(&["SIGNING_GPG_KEY"])?.remove(0) }),
+        }
+    }
+
+    /// `--config` override merged into tauri.conf.json for the build
+    pub fn tauri_config(&self) -> Option<String> {
+        match self {
+            Signing::Windows { thumbprint } => {
+                Some(serde_json::json!({ "tauri": { "bundle": { "windows": { "certificateThumbprint": thumbprint } } } }).to_string())
+            }
+            Signing::MacOs { .. } | Signing::Linux { .. } => None,
+        }
+    }
+
+    /// Checks or adds the signature on the copied artifact; returns files written next to it
+    pub fn finish(&self, bundle_dir: &Path, product: &str, artifact: &Path) -> Result<Vec<PathBuf>> {
+        match self {
+            Signing::MacOs { identity, apple_id, password, team_id } => {
+                // The bundler staples the ticket to the app; the DMG needs its own
+                run_command(Command::new("xcrun").arg("stapler").arg("validate").arg(bundle_dir.join("macos").join(format!("{}.app", product))))?;
+                run_command(Command::new("codesign").args(["--force", "--timestamp", "--sign", identity]).arg(artifact))?;
+                run_command(Command::new("xcrun").args(["notarytool", "submit"]).arg(artifact)
+                    .args(["--apple-id", apple_id, "--password", password, "--team-id", team_id, "--wait"]))?;
+                run_command(Command::new("xcrun").args(["stapler", "staple"]).arg(artifact))?;
+                run_command(Command::new("xcrun").args(["stapler", "validate"]).arg(artifact))?;
+                Ok(Vec::new())
+            }
+            Signing::Windows { .. } => run_command(Command::new("signtool").args(["verify", "/pa"]).arg(artifact)).map(|_| Vec::new()),
+            Signing::Linux { key } => {
+                run_command(Command::new("gpg").args(["--batch", "--yes", "--armor", "--local-user", key, "--detach-sign"]).arg(artifact))?;
+                let mut signature = artifact.as_os_str().to_owned();
+                signature.push(".asc");
+                Ok(vec![signature.into()])
+            }
+        }
+    }
+}
+
+/// Values of `names`, or one error listing every unset variable
+fn require(names: &[&str]) -> Result<Vec<String>> {
+    let values: Vec<Option<String>> = names.iter().map(|name| std::env::var(name).ok().filter(|v| !v.is_empty())).collect();
+    let missing: Vec<&str> = names.iter().zip(&values).filter(|(_, v)| v.is_none()).map(|(name, _)| *name).collect();
+    if missing.is_empty() {
+        Ok(values.into_iter().flatten().collect())
+    } else {
+        Err(format!("set {} to sign, or pass --unsigned", missing.join(", ")))
+    }
+}